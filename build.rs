@@ -0,0 +1,39 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_CUDA").is_some() {
+        build_cuda_kernels();
+    }
+}
+
+/// Compile the CUDA kernels into PTX loaded by the driver at run time, so that they are
+/// JIT-compiled for whichever GPU the outputs are on
+fn build_cuda_kernels() {
+    const KERNELS: &str = "src/cuda/kernels.cu";
+    println!("cargo:rerun-if-changed={KERNELS}");
+    println!("cargo:rerun-if-env-changed=NVCC");
+    println!("cargo:rerun-if-env-changed=CUDA_PATH");
+
+    let cuda_path = env::var_os("CUDA_PATH").map(PathBuf::from);
+    let nvcc = env::var_os("NVCC")
+        .map(PathBuf::from)
+        .or_else(|| cuda_path.as_ref().map(|path| path.join("bin/nvcc")))
+        .unwrap_or_else(|| PathBuf::from("nvcc"));
+    let ptx = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("kernels.ptx");
+    let status = Command::new(&nvcc)
+        .args(["--ptx", "-O3", "-arch=compute_60", "-o"])
+        .arg(&ptx)
+        .arg(KERNELS)
+        .status()
+        .unwrap_or_else(|e| panic!("the cuda feature needs nvcc, failed to run {nvcc:?}: {e}"));
+    assert!(status.success(), "nvcc failed to compile {KERNELS}");
+
+    // The driver API comes with the driver, its stub library with the toolkit
+    if let Some(cuda_path) = cuda_path {
+        println!("cargo:rustc-link-search=native={}", cuda_path.join("lib64/stubs").display());
+    }
+    println!("cargo:rustc-link-lib=dylib=cuda");
+}
@@ -0,0 +1,176 @@
+/* Generated by cbindgen from src/ffi/mod.rs, do not edit */
+
+#ifndef FURIOSA_NATIVE_POSTPROCESS_H
+#define FURIOSA_NATIVE_POSTPROCESS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+typedef struct FuriosaYolo FuriosaYolo;
+
+// SSD model of [`furiosa_ssd_create`]
+typedef enum {
+  FURIOSA_SSD_MODEL_MOBILENET = 0,
+  FURIOSA_SSD_MODEL_RESNET34 = 1,
+} FuriosaSsdModel;
+
+// Status returned by every function of the C interface
+typedef enum {
+  FURIOSA_STATUS_OK = 0,
+  // A null pointer, an invalid shape or an out of range argument
+  FURIOSA_STATUS_INVALID_ARGUMENT = 1,
+  // The postprocessor could not be set up
+  FURIOSA_STATUS_RUNTIME = 2,
+  // The postprocess panicked, which is a bug
+  FURIOSA_STATUS_PANIC = 3,
+} FuriosaStatus;
+
+// Batched detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+typedef struct FuriosaResults FuriosaResults;
+
+typedef struct FuriosaSsd FuriosaSsd;
+
+// A dense row-major float32 tensor borrowed from the caller
+typedef struct {
+  const float *data;
+  const size_t *shape;
+  size_t ndim;
+} FuriosaTensor;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message of the last error of the calling thread, null if none occurred
+//
+// The message is valid until the next failing call on the same thread.
+const char *furiosa_last_error(void);
+
+// Create a YOLOv5 postprocessor
+//
+// `anchors` holds `num_layers * num_anchors * 2` floats and `strides` one per layer.
+// `num_threads` of 0 shares the global thread pool.
+//
+// # Safety
+//
+// `anchors` and `strides` must point to as many floats as described above and `out` to a
+// writable pointer.
+FuriosaStatus furiosa_yolo_create(const float *anchors,
+                                  size_t num_layers,
+                                  size_t num_anchors,
+                                  const float *strides,
+                                  bool agnostic,
+                                  bool channels_last,
+                                  size_t num_threads,
+                                  FuriosaYolo **out);
+
+// Create a YOLOv5 postprocessor of a JSON or YAML configuration, as saved by
+// [`furiosa_yolo_config`], or of the anchors and strides of an Ultralytics YOLOv5 model
+// definition
+//
+// # Safety
+//
+// `config` must be a NUL-terminated UTF-8 string and `out` must point to a writable pointer.
+FuriosaStatus furiosa_yolo_create_from_config(const char *config, FuriosaYolo **out);
+
+// Write the JSON configuration of a YOLOv5 postprocessor into `buffer`, NUL-terminated
+//
+// `length` receives the length of the configuration without the NUL whether or not it fits in
+// the `capacity` bytes of `buffer`, which fails if it is smaller than the length plus one.
+//
+// # Safety
+//
+// `postprocessor` must be a live postprocessor, `buffer` must point to `capacity` writable
+// bytes and `length` to a writable size.
+FuriosaStatus furiosa_yolo_config(const FuriosaYolo *postprocessor,
+                                  char *buffer,
+                                  size_t capacity,
+                                  size_t *length);
+
+// Destroy a YOLOv5 postprocessor, doing nothing if it is null
+//
+// # Safety
+//
+// `postprocessor` must have been created by [`furiosa_yolo_create`] and not destroyed yet.
+void furiosa_yolo_destroy(FuriosaYolo *postprocessor);
+
+// Decode and suppress the output tensors of a YOLOv5 model, one per detection layer
+//
+// # Safety
+//
+// `postprocessor` must be a live postprocessor, `inputs` must point to `num_inputs` valid
+// tensors and `out` to a writable pointer.
+FuriosaStatus furiosa_yolo_eval(const FuriosaYolo *postprocessor,
+                                const FuriosaTensor *inputs,
+                                size_t num_inputs,
+                                float conf_threshold,
+                                float iou_threshold,
+                                FuriosaResults **out);
+
+// Create an SSD postprocessor
+//
+// # Safety
+//
+// `out` must point to a writable pointer.
+FuriosaStatus furiosa_ssd_create(FuriosaSsdModel model, FuriosaSsd **out);
+
+// Destroy an SSD postprocessor, doing nothing if it is null
+//
+// # Safety
+//
+// `postprocessor` must have been created by [`furiosa_ssd_create`] and not destroyed yet.
+void furiosa_ssd_destroy(FuriosaSsd *postprocessor);
+
+// Postprocess the 6 raw box and 6 raw score tensors of a single image, all of them 3D, the
+// results holding that single image
+//
+// # Safety
+//
+// `postprocessor` must be a live postprocessor, `boxes` and `scores` must point to 6 valid
+// tensors each and `out` to a writable pointer.
+FuriosaStatus furiosa_ssd_eval(const FuriosaSsd *postprocessor,
+                               const FuriosaTensor *boxes,
+                               const FuriosaTensor *scores,
+                               FuriosaResults **out);
+
+// Number of images of the results
+//
+// # Safety
+//
+// `results` must be live results.
+size_t furiosa_results_num_images(const FuriosaResults *results);
+
+// Number of detections of an image of the results, 0 if it is out of range
+//
+// # Safety
+//
+// `results` must be live results.
+size_t furiosa_results_num_detections(const FuriosaResults *results, size_t image);
+
+// Copy the detections of an image into `buffer` as rows (x1, y1, x2, y2, score, class)
+//
+// `capacity` is the number of floats `buffer` holds, which must be at least 6 times the number
+// of detections.
+//
+// # Safety
+//
+// `results` must be live results and `buffer` must point to `capacity` writable floats.
+FuriosaStatus furiosa_results_copy(const FuriosaResults *results,
+                                   size_t image,
+                                   float *buffer,
+                                   size_t capacity);
+
+// Release results, doing nothing if they are null
+//
+// # Safety
+//
+// `results` must have been returned by an eval function and not released yet.
+void furiosa_results_destroy(FuriosaResults *results);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* FURIOSA_NATIVE_POSTPROCESS_H */
@@ -0,0 +1,5 @@
+from .furiosa_native_postprocess import *  # noqa: F401,F403
+
+__doc__ = furiosa_native_postprocess.__doc__  # noqa: F405
+if hasattr(furiosa_native_postprocess, "__all__"):  # noqa: F405
+    __all__ = furiosa_native_postprocess.__all__  # noqa: F405
@@ -0,0 +1,283 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Callable, Dict, Iterator, List, Optional, Sequence, Union
+
+import numpy
+
+__version__: str
+
+class Postprocessor:
+    """Postprocessor of a model created by `create_postprocessor`
+    """
+    def __repr__(self) -> str:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: Optional[float] = ..., iou_threshold: Optional[float] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Output tensors of the model of dtype float32
+            conf_threshold (Optional[float]): Minimum score of the detections, default is the one
+                of the model
+            iou_threshold (Optional[float]): IoU threshold of NMS, default is the one of the model
+
+        Returns:
+            List[numpy.ndarray]: Detections of each image of shape (N, 6) with rows
+                (x1, y1, x2, y2, score, class)
+        """
+        ...
+    def set_rescore(self, callback: Optional[Callable[..., Any]] = ...) -> Any:
+        """Register a callback rescoring the candidates of each image before NMS
+
+        It is called as `callback(boxes, scores, classes, image)` with the boxes of shape (N, 4)
+        with rows (x1, y1, x2, y2), their scores and classes of shape (N,), and the index of the
+        image in the batch, and returns the N new scores, e.g. `scores * prior` or
+        `numpy.where(mask, scores, 0)`. Candidates whose new score does not exceed the confidence
+        threshold, or is NaN, are dropped. The callback holds the GIL, so images are rescored one
+        at a time.
+
+        Args:
+            callback (Optional[Callable[..., Any]]): Rescoring callback, None to remove it
+        """
+        ...
+
+class PyDetectionResult:
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    @property
+    def bottom(self) -> Any:
+        ...
+    @property
+    def category_id(self) -> Any:
+        """Dataset category id of the class, the class itself unless the postprocessor has
+        category ids
+        """
+        ...
+    @property
+    def class_id(self) -> Any:
+        ...
+    @property
+    def label(self) -> Any:
+        """Name of the class, None unless the postprocessor has class names
+        """
+        ...
+    @property
+    def left(self) -> Any:
+        ...
+    @property
+    def right(self) -> Any:
+        ...
+    @property
+    def score(self) -> Any:
+        ...
+    @property
+    def top(self) -> Any:
+        ...
+
+class PyDetectionResults:
+    """Detections of a single image, a sequence of PyDetectionResult
+
+    The fields of every detection are also available at once as arrays: `boxes` of shape (N, 4)
+    with rows (x1, y1, x2, y2), `scores` of shape (N,) and `classes` of shape (N,), along with
+    the `labels` and `category_ids` of the classes in the table of the postprocessor.
+    """
+    def __getitem__(self, key: Any) -> Any:
+        ...
+    def __iter__(self) -> Iterator[Any]:
+        ...
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    @property
+    def boxes(self) -> Any:
+        ...
+    @property
+    def category_ids(self) -> Any:
+        ...
+    @property
+    def classes(self) -> Any:
+        ...
+    @property
+    def labels(self) -> Any:
+        ...
+    @property
+    def scores(self) -> Any:
+        ...
+
+class PyObbResults:
+    """Oriented bounding box detection results of a single image
+    """
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    @property
+    def corners(self) -> Any:
+        """Box corners of shape (N, 4, 2)
+        """
+        ...
+    @property
+    def detections(self) -> Any:
+        """Detections of shape (N, 7), each row being (cx, cy, w, h, angle, score, class) with the
+        angle in radians
+        """
+        ...
+
+class PyPoseResults:
+    """Pose estimation results of a single image
+    """
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    @property
+    def detections(self) -> Any:
+        """Detections of shape (N, 6), each row being (x1, y1, x2, y2, score, class)
+        """
+        ...
+    @property
+    def keypoints(self) -> Any:
+        """Keypoints of shape (N, K, 3), each row being (x, y, visibility)
+        """
+        ...
+
+class PySegmentationResults:
+    """Instance segmentation results of a single image
+    """
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    @property
+    def coefficients(self) -> Any:
+        """Mask coefficients of shape (N, num_masks)
+        """
+        ...
+    @property
+    def detections(self) -> Any:
+        """Detections of shape (N, 6), each row being (x1, y1, x2, y2, score, class)
+        """
+        ...
+    @property
+    def masks(self) -> Any:
+        """Binary masks of shape (N, H, W) at prototype resolution
+        """
+        ...
+
+from . import bert as bert
+from . import box_ops as box_ops
+from . import classification as classification
+from . import coco as coco
+def create_postprocessor(name: str, config: Optional[Dict[str, Any]] = ...) -> Postprocessor:
+    """Create the postprocessor of a model by name
+
+    Args:
+        name (str): "yolov5", "ssd_mobilenet", "ssd_resnet34", or "generic" for heads described
+            by a spec
+        config (Optional[Dict[str, Any]]): Config of the model, as taken by the `from_dict` of
+            yolo.RustPostProcessor, overriding keys of ssd.SsdPostProcessor, or the spec taken
+            by head.HeadDecoder
+
+    Returns:
+        Postprocessor: Postprocessor of the model
+    """
+    ...
+
+from . import crop as crop
+from . import ctc as ctc
+from . import detr as detr
+from . import efficientdet as efficientdet
+from . import ensemble as ensemble
+from . import eval as eval
+from . import fcos as fcos
+def get_num_threads() -> int:
+    """Number of threads the postprocesses run in
+
+    Returns:
+        int: Number of threads set by `set_num_threads`, or the number of cores
+    """
+    ...
+
+from . import head as head
+from . import maskrcnn as maskrcnn
+from . import mlperf as mlperf
+def nms(boxes: numpy.ndarray, scores: numpy.ndarray, classes: Optional[numpy.ndarray], iou_threshold: float, agnostic: Optional[bool] = ..., epsilon: Optional[float] = ..., max_det: Optional[int] = ...) -> numpy.ndarray:
+    """Non-Maximum Suppression of arbitrary boxes
+
+    Args:
+        boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+        scores (numpy.ndarray): Scores of shape (N,)
+        classes (Optional[numpy.ndarray]): Class indices of shape (N,), boxes of different classes
+            never suppressing each other, a single class if None
+        iou_threshold (float): IoU threshold
+        agnostic (Optional[bool]): Whether to suppress across classes, default is False
+        epsilon (Optional[float]): Epsilon for numerical stability
+        max_det (Optional[int]): Maximum number of kept boxes, default is 300
+
+    Returns:
+        numpy.ndarray: Indices of the kept boxes in descending score order
+    """
+    ...
+
+from . import obb as obb
+from . import openimages as openimages
+from . import pose as pose
+from . import preprocess as preprocess
+from . import retinanet as retinanet
+from . import segmentation as segmentation
+def set_log_filter(filter: Optional[str] = ...) -> Any:
+    """Forward the tracing events of the postprocesses to the Python loggers named after their
+    modules, e.g. `furiosa_native_postprocess.yolo`
+
+    The loggers still need a level low enough for the events to show, e.g. with
+    `logging.basicConfig(level=logging.DEBUG)`. Spans log how long they were open when closed.
+
+    Args:
+        filter (Optional[str]): Comma-separated directives `[target=]level` as `RUST_LOG`, e.g.
+            "info,yolo=debug", None to forward nothing
+    """
+    ...
+
+def set_num_threads(num_threads: Optional[int] = ..., cores: Optional[Sequence[int]] = ...) -> Any:
+    """Set the number of threads the postprocesses run in, e.g. to leave cores to the inference
+    runtime
+
+    Postprocessors constructed with `num_threads` keep their own pool.
+
+    Args:
+        num_threads (Optional[int]): Number of threads, None to use one per core again
+        cores (Optional[Sequence[int]]): Cores to pin the threads to in turn, Linux only, default
+            is not to pin them
+    """
+    ...
+
+from . import ssd as ssd
+from . import ssd_mobilenet as ssd_mobilenet
+from . import ssd_resnet34 as ssd_resnet34
+from . import tiling as tiling
+from . import tracking as tracking
+from . import unet3d as unet3d
+from . import viz as viz
+def wbf(detections: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], weights: Optional[Sequence[float]] = ..., iou_threshold: Optional[float] = ..., skip_box_threshold: Optional[float] = ...) -> Union[numpy.ndarray, PyDetectionResults]:
+    """Weighted Box Fusion of the detections of multiple models for the same image
+
+    Args:
+        detections (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+            each model, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+            or lists of PyDetectionResult
+        weights (Optional[Sequence[float]]): Weight of each model, default is 1 for every model
+        iou_threshold (Optional[float]): IoU threshold to match boxes, default is 0.55
+        skip_box_threshold (Optional[float]): Boxes scoring below it are ignored, default is 0
+
+    Returns:
+        Union[numpy.ndarray, PyDetectionResults]: Fused detections in the same format as
+            the inputs, in descending score order
+    """
+    ...
+
+from . import yolo as yolo
+from . import yolov5 as yolov5
+from . import yolov8 as yolov8
+from . import yolox as yolox
@@ -0,0 +1,36 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """BERT SQuAD PostProcessor
+
+    It extracts the n-best answer spans of question answering models from start/end logits.
+
+    Args:
+        n_best_size (Optional[int]): Number of start/end candidates and spans to keep,
+            default is 20
+        max_answer_length (Optional[int]): Maximum number of tokens of a span, default is 30
+    """
+    def __init__(self, n_best_size: Optional[int] = ..., max_answer_length: Optional[int] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, start_logits: numpy.ndarray, end_logits: numpy.ndarray, context_mask: Optional[numpy.ndarray] = ...) -> List[List[Tuple[int, int, float]]]:
+        """Evaluate the postprocess
+
+        Args:
+            start_logits (numpy.ndarray): Start logits of shape (N, S)
+            end_logits (numpy.ndarray): End logits of shape (N, S)
+            context_mask (Optional[numpy.ndarray]): Boolean mask of shape (N, S) marking the tokens
+                an answer may span, e.g. context tokens having their maximum context in the window
+
+        Returns:
+            List[List[Tuple[int, int, float]]]: Batched n-best (start, end, score) spans in
+                descending score order, with inclusive token indices
+        """
+        ...
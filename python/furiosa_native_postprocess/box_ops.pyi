@@ -0,0 +1,84 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+import numpy
+
+def box_area(boxes: numpy.ndarray) -> numpy.ndarray:
+    """Area of boxes, zero for boxes of a negative width or height
+
+    Args:
+        boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: Areas of shape (N,)
+    """
+    ...
+
+def ciou(a: numpy.ndarray, b: numpy.ndarray) -> numpy.ndarray:
+    """Complete-IoU of every pair of boxes
+
+    Args:
+        a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+        b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: CIoU of shape (N, M)
+    """
+    ...
+
+def diou(a: numpy.ndarray, b: numpy.ndarray) -> numpy.ndarray:
+    """Distance-IoU of every pair of boxes
+
+    Args:
+        a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+        b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: DIoU of shape (N, M), in [-1, 1]
+    """
+    ...
+
+def giou(a: numpy.ndarray, b: numpy.ndarray) -> numpy.ndarray:
+    """Generalized IoU of every pair of boxes
+
+    Args:
+        a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+        b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: GIoU of shape (N, M), in [-1, 1]
+    """
+    ...
+
+def iou_matrix(a: numpy.ndarray, b: numpy.ndarray) -> numpy.ndarray:
+    """IoU of every pair of boxes
+
+    Args:
+        a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+        b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: IoU of shape (N, M)
+    """
+    ...
+
+def xywh2xyxy(boxes: numpy.ndarray) -> numpy.ndarray:
+    """Convert boxes from (center x, center y, width, height) to (x1, y1, x2, y2)
+
+    Args:
+        boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (cx, cy, w, h)
+
+    Returns:
+        numpy.ndarray: Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+    """
+    ...
+
+def xyxy2xywh(boxes: numpy.ndarray) -> numpy.ndarray:
+    """Convert boxes from (x1, y1, x2, y2) to (center x, center y, width, height)
+
+    Args:
+        boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+
+    Returns:
+        numpy.ndarray: Boxes of shape (N, 4) with rows (cx, cy, w, h)
+    """
+    ...
@@ -0,0 +1,32 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Optional, Tuple
+
+import numpy
+
+class TopKPostProcessor:
+    """Top-k Classification PostProcessor
+
+    It selects the k highest scoring classes of batched classification logits (e.g., ResNet50).
+
+    Args:
+        softmax (Optional[bool]): Whether to apply softmax to the logits, default is True
+    """
+    def __init__(self, softmax: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, logits: numpy.ndarray, k: int) -> Tuple[numpy.ndarray, numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            logits (numpy.ndarray): Logits of shape (N, C)
+            k (int): Number of classes to keep, must not exceed C
+
+        Returns:
+            Tuple[numpy.ndarray, numpy.ndarray]: Class indices (int64) and scores (float32),
+                both of shape (N, k) in descending score order
+        """
+        ...
@@ -0,0 +1,97 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, List, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+class GroundTruth:
+    """Ground truth of a COCO instances annotation file, such as instances_val2017.json
+
+    Categories are sorted by id and the class of a box is the index of its category, e.g. the 80
+    contiguous COCO classes, `category_ids` mapping them back to the COCO category ids.
+
+    Args:
+        path (str): Path of the annotation file
+    """
+    def __init__(self, path: str) -> None:
+        ...
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def boxes(self, image_id: int) -> numpy.ndarray:
+        """Ground truth boxes of an image
+
+        Args:
+            image_id (int): COCO image id
+
+        Returns:
+            numpy.ndarray: Array of shape (M, 5) with rows (x1, y1, x2, y2, class), empty for an
+                unknown image
+        """
+        ...
+    @property
+    def category_ids(self) -> Any:
+        """COCO category id of each class
+        """
+        ...
+    @property
+    def category_names(self) -> Any:
+        """Name of each class
+        """
+        ...
+    def crowd(self, image_id: int) -> List[bool]:
+        """Whether each ground truth box of an image is a crowd region
+
+        Args:
+            image_id (int): COCO image id
+
+        Returns:
+            List[bool]: Crowd flag of each row of `boxes(image_id)`
+        """
+        ...
+    @property
+    def image_ids(self) -> Any:
+        """Ids of the images, in file order
+        """
+        ...
+
+def match_detections(detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], ground_truth: numpy.ndarray, iou_threshold: Optional[float] = ..., crowd: Optional[Sequence[bool]] = ...) -> Tuple[List[Tuple[int, int]], List[int], List[int]]:
+    """Match the detections of an image to its ground truth as the COCO evaluation does
+
+    Detections are visited in descending score order, each taking the unmatched ground truth box
+    of its class it overlaps most. Detections only matching a crowd region are ignored.
+
+    Args:
+        detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the image,
+            either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+            PyDetectionResult
+        ground_truth (numpy.ndarray): Ground truth of the image, an array of shape (M, 5) with
+            rows (x1, y1, x2, y2, class)
+        iou_threshold (Optional[float]): Minimum IoU of a match, default is 0.5
+        crowd (Optional[Sequence[bool]]): Whether each ground truth box is a crowd region, default
+            is False for every box
+
+    Returns:
+        Tuple[List[Tuple[int, int]], List[int], List[int]]: (detection, ground truth) indices of
+            the true positives, indices of the false positive detections and indices of the
+            unmatched ground truth boxes
+    """
+    ...
+
+def write_results(results: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], image_ids: Sequence[int], path: str, category_ids: Optional[Union[str, Sequence[int]]] = ...) -> Any:
+    """Write batched detections to a COCO detection results JSON file
+
+    Args:
+        results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+            image, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or lists
+            of PyDetectionResult
+        image_ids (Sequence[int]): COCO image id of each image
+        path (str): Path of the JSON file to write
+        category_ids (Optional[Union[str, Sequence[int]]]): COCO category id of each class
+            index, or "coco91" to map the 80 contiguous classes to the 91 COCO category ids,
+            default is the class index itself
+    """
+    ...
@@ -0,0 +1,26 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+def crop_detections(image: numpy.ndarray, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], size: Optional[Tuple[int, int]] = ..., margin: Optional[float] = ...) -> Union[numpy.ndarray, List[numpy.ndarray]]:
+    """Crop the boxes of detections out of an image, e.g. for a classifier or a ReID model
+
+    Args:
+        image (numpy.ndarray): uint8 array of shape (H, W, C)
+        detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections in the
+            coordinates of the image, either an array of shape (N, 6) with rows
+            (x1, y1, x2, y2, score, class) or a list of PyDetectionResult
+        size (Optional[Tuple[int, int]]): (height, width) to resize the crops to with bilinear
+            interpolation, default is to keep them as they are
+        margin (Optional[float]): Enlargement of the boxes on every side as a ratio of their
+            size, default is 0
+
+    Returns:
+        Union[numpy.ndarray, List[numpy.ndarray]]: Batch of shape (N, height, width, C) if `size`
+            is given, the crops of shape (h, w, C) otherwise, boxes being clipped to the image
+    """
+    ...
@@ -0,0 +1,40 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Callable, List, Optional, Sequence, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """CTC PostProcessor
+
+    It decodes CTC outputs of speech recognition models with prefix beam search.
+
+    Args:
+        blank (Optional[int]): Index of the blank label, default is 0
+        beam_width (Optional[int]): Number of prefixes kept per frame, default is 10
+        log_softmax (Optional[bool]): Whether to apply log-softmax to the inputs, False if they are
+            already log probabilities, default is True
+    """
+    def __init__(self, blank: Optional[int] = ..., beam_width: Optional[int] = ..., log_softmax: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, logits: numpy.ndarray, lengths: Optional[Sequence[int]] = ..., lm: Optional[Callable[[List[int]], float]] = ..., lm_weight: Optional[float] = ..., insertion_bonus: Optional[float] = ...) -> List[List[Tuple[List[int], float]]]:
+        """Evaluate the postprocess
+
+        Args:
+            logits (numpy.ndarray): Logits of shape (N, T, V)
+            lengths (Optional[Sequence[int]]): Valid number of frames per sample, T if None
+            lm (Optional[Callable[[List[int]], float]]): Language model returning the log
+                probability of the last label of a prefix given the preceding ones
+            lm_weight (Optional[float]): Weight of the language model score, default is 0.5
+            insertion_bonus (Optional[float]): Score added per emitted label when `lm` is given,
+                default is 0
+
+        Returns:
+            List[List[Tuple[List[int], float]]]: Batched hypotheses (labels, log score) in
+                descending score order
+        """
+        ...
@@ -0,0 +1,50 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Tuple
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResults
+
+class RustPostProcessor:
+    """DETR PostProcessor
+
+    It converts set-prediction query outputs into detections without NMS.
+
+    Args:
+        scoring (str): Either "sigmoid" (RT-DETR) or "softmax" (DETR, last class is "no object")
+        top_k (Optional[int]): Maximum number of detections per image, default is 100
+    """
+    def __init__(self, scoring: str, top_k: Optional[int] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, logits: numpy.ndarray, boxes: numpy.ndarray, conf_threshold: float, image_shape: Optional[Tuple[float, float]] = ...) -> List[PyDetectionResults]:
+        """Evaluate the postprocess
+
+        Args:
+            logits (numpy.ndarray): Class logits of shape (N, num_queries, num_classes)
+            boxes (numpy.ndarray): Normalized cxcywh boxes of shape (N, num_queries, 4)
+            conf_threshold (float): Confidence threshold
+            image_shape (Optional[Tuple[float, float]]): (height, width) to scale boxes to
+
+        Returns:
+            List[PyDetectionResults]: Batched detection results
+        """
+        ...
+    def eval_to_array(self, logits: numpy.ndarray, boxes: numpy.ndarray, conf_threshold: float, image_shape: Optional[Tuple[float, float]] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess, returning the results of each image as a single array
+
+        Args:
+            logits (numpy.ndarray): Class logits of shape (N, num_queries, num_classes)
+            boxes (numpy.ndarray): Normalized cxcywh boxes of shape (N, num_queries, 4)
+            conf_threshold (float): Confidence threshold
+            image_shape (Optional[Tuple[float, float]]): (height, width) to scale boxes to
+
+        Returns:
+            List[numpy.ndarray]: Batched detections of shape (N, 6) with rows
+                (x1, y1, x2, y2, score, class)
+        """
+        ...
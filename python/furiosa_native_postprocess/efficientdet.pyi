@@ -0,0 +1,39 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """EfficientDet PostProcessor
+
+    It generates the BiFPN anchor grid (3 scale octaves x 3 aspect ratios per level),
+    keeps the top 5000 candidates over all levels, decodes them, and runs Gaussian soft-NMS.
+    Class outputs hold raw logits with shape `(N, 9 * num_classes, H, W)` and box outputs
+    hold `(ty, tx, th, tw)` regressions with shape `(N, 9 * 4, H, W)`.
+
+    Args:
+        image_size (Optional[Tuple[int, int]]): (height, width) of network input, default is (512, 512)
+        min_level (Optional[int]): Lowest pyramid level, default is 3
+        max_level (Optional[int]): Highest pyramid level, default is 7
+        anchor_scale (Optional[float]): Base anchor scale relative to the stride, default is 4.0
+    """
+    def __init__(self, image_size: Optional[Tuple[int, int]] = ..., min_level: Optional[int] = ..., max_level: Optional[int] = ..., anchor_scale: Optional[float] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, cls_outputs: Sequence[numpy.ndarray], box_outputs: Sequence[numpy.ndarray], conf_threshold: float, sigma: Optional[float] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            cls_outputs (Sequence[numpy.ndarray]): Class logits of each level
+            box_outputs (Sequence[numpy.ndarray]): Box regressions of each level
+            conf_threshold (float): Confidence threshold, also the soft-NMS score threshold
+            sigma (Optional[float]): Gaussian soft-NMS sigma, default is 0.5
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
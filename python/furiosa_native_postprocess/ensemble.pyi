@@ -0,0 +1,31 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Optional, Sequence, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult, PyDetectionResults
+
+def fuse(results: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], weights: Optional[Sequence[float]] = ..., method: Optional[str] = ..., iou_threshold: Optional[float] = ..., sigma: Optional[float] = ..., class_maps: Optional[Sequence[Optional[Sequence[int]]]] = ..., max_det: Optional[int] = ...) -> Union[numpy.ndarray, PyDetectionResults]:
+    """Fuse the detections of several models for the same image
+
+    Args:
+        results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+            each model, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+            or lists of PyDetectionResult
+        weights (Optional[Sequence[float]]): Weight of each model scaling its scores, default is
+            1 for every model
+        method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS across the
+            models, or "wbf" for Weighted Box Fusion, default is "wbf"
+        iou_threshold (Optional[float]): IoU threshold, default is 0.55
+        sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+        class_maps (Optional[Sequence[Optional[Sequence[int]]]]): Shared class of each class of
+            each model, e.g. to fuse models trained on different datasets, the detections of
+            classes mapped to -1 being dropped, default is the classes as they are
+        max_det (Optional[int]): Maximum number of detections, default is 300
+
+    Returns:
+        Union[numpy.ndarray, PyDetectionResults]: Fused detections in the same format as the
+            inputs, in descending score order
+    """
+    ...
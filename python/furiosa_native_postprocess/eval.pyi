@@ -0,0 +1,119 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+class CocoMetrics:
+    """COCO detection metrics of the "all" area range
+    """
+    def __repr__(self) -> str:
+        ...
+    @property
+    def ap50(self) -> Any:
+        """AP at IoU 0.5
+        """
+        ...
+    @property
+    def ap75(self) -> Any:
+        """AP at IoU 0.75
+        """
+        ...
+    @property
+    def ar(self) -> Any:
+        """Recall given `max_dets` detections per image averaged over IoU thresholds and classes
+        """
+        ...
+    @property
+    def map(self) -> Any:
+        """AP averaged over IoU thresholds 0.5:0.05:0.95 and classes
+        """
+        ...
+    @property
+    def per_class_ap(self) -> Any:
+        """AP averaged over IoU thresholds of each class having ground truth
+        """
+        ...
+
+class ConfusionMatrix:
+    """Confusion matrix accumulated over images, like the validation of Ultralytics
+
+    Counts are indexed by (predicted class, true class), the last row and column standing for
+    the background: missed ground truth boxes are counted in the last row and detections
+    matching no box in the last column.
+
+    Args:
+        num_classes (int): Number of classes
+        conf_threshold (Optional[float]): Detections of a score up to this are left out of the
+            matrix, default is 0.25
+        iou_threshold (Optional[float]): Minimum IoU of a match, default is 0.45
+    """
+    def __init__(self, num_classes: int, conf_threshold: Optional[float] = ..., iou_threshold: Optional[float] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def matrix(self) -> numpy.ndarray:
+        """Confusion matrix
+
+        Returns:
+            numpy.ndarray: Counts of shape (num_classes + 1, num_classes + 1) indexed by
+                (predicted class, true class), background last
+        """
+        ...
+    def precision_recall(self, num_points: Optional[int] = ...) -> Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]:
+        """Precision-recall curves of each class over the confidence threshold
+
+        Args:
+            num_points (Optional[int]): Number of confidence thresholds evenly spaced over [0, 1],
+                default is 1000
+
+        Returns:
+            Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]: Confidence thresholds of shape
+                (num_points,), then precision and recall of shape (num_classes, num_points) keeping
+                the detections scoring at least each threshold
+        """
+        ...
+    def reset(self) -> Any:
+        """Forget every image added so far
+        """
+        ...
+    def tp_fp(self) -> Tuple[numpy.ndarray, numpy.ndarray]:
+        """True and false positives of each class according to the matrix
+
+        Returns:
+            Tuple[numpy.ndarray, numpy.ndarray]: True and false positives, of shape (num_classes,)
+        """
+        ...
+    def update(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], ground_truth: numpy.ndarray, crowd: Optional[Sequence[bool]] = ...) -> Any:
+        """Add the detections and the ground truth of an image
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+                image, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+                a list of PyDetectionResult
+            ground_truth (numpy.ndarray): Ground truth of the image, an array of shape (M, 5) with
+                rows (x1, y1, x2, y2, class)
+            crowd (Optional[Sequence[bool]]): Whether each ground truth box is a crowd region,
+                which are left out, default is False for every box
+        """
+        ...
+
+def coco_map(predictions: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], ground_truths: Sequence[numpy.ndarray], crowds: Optional[Sequence[Sequence[bool]]] = ..., max_dets: Optional[int] = ...) -> CocoMetrics:
+    """Compute COCO detection metrics without pycocotools
+
+    Args:
+        predictions (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+            each image, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+            lists of PyDetectionResult
+        ground_truths (Sequence[numpy.ndarray]): Ground truth of each image, arrays of shape
+            (M, 5) with rows (x1, y1, x2, y2, class)
+        crowds (Optional[Sequence[Sequence[bool]]]): Whether each ground truth box is a crowd
+            region, default is False for every box
+        max_dets (Optional[int]): Maximum number of detections per image, default is 100
+
+    Returns:
+        CocoMetrics: mAP@[.5:.95], AP50, AP75, AR and per-class AP
+    """
+    ...
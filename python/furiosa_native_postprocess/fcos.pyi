@@ -0,0 +1,40 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence
+
+import numpy
+
+class RustPostProcessor:
+    """FCOS PostProcessor
+
+    It decodes anchor-free per-pixel detection heads. Per FPN level, class logits have shape
+    `(N, num_classes, H, W)`, ltrb distance regressions normalized by the stride have shape
+    `(N, 4, H, W)`, and centerness logits have shape `(N, 1, H, W)`.
+
+    Args:
+        strides (numpy.ndarray): Strides (1D Array)
+        pre_nms_top_n (Optional[int]): Maximum number of candidates per level, default is 1000
+        agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+    """
+    def __init__(self, strides: numpy.ndarray, pre_nms_top_n: Optional[int] = ..., agnostic: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, cls_logits: Sequence[numpy.ndarray], bbox_regression: Sequence[numpy.ndarray], centerness: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, epsilon: Optional[float] = ..., agnostic: Optional[bool] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            cls_logits (Sequence[numpy.ndarray]): Class logits per level
+            bbox_regression (Sequence[numpy.ndarray]): ltrb distances per level
+            centerness (Sequence[numpy.ndarray]): Centerness logits per level
+            conf_threshold (float): Confidence threshold on the classification score
+            iou_threshold (float): IoU threshold
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
@@ -0,0 +1,80 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Dict, List, Optional, Sequence
+
+import numpy
+
+class HeadDecoder:
+    """HeadDecoder
+
+    Decode of output heads described by a spec, for models close to YOLOv5 whose heads differ in
+    their layout, activation, anchors, box encoding or channels, followed by NMS.
+
+    Args:
+        spec (Dict[str, Any]): Spec with "num_classes", the optional "conf_threshold",
+            "iou_threshold", "max_det" and "agnostic", and "heads", a list of one dict per output
+            with its "stride", "anchors" as (width, height) pairs in pixels, "layout" ("nchw",
+            "nhwc" or "nahwc"), "activation" ("identity" or "sigmoid"), "encoding" ("yolov5",
+            "darknet", "yolox" or "ltrb") and "channels", a dict of the offsets of the "boxes",
+            "objectness" and "classes" channels within those of an anchor
+    """
+    def __init__(self, spec: Dict[str, Any]) -> None:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: Optional[float] = ..., iou_threshold: Optional[float] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Output tensor of each head of dtype float32
+            conf_threshold (Optional[float]): Minimum score of the detections, default is the one
+                of the spec
+            iou_threshold (Optional[float]): IoU threshold of NMS, default is the one of the spec
+
+        Returns:
+            List[numpy.ndarray]: Detections of each image of shape (N, 6) with rows
+                (x1, y1, x2, y2, score, class) in pixels, a RuntimeWarning being raised if the
+                decode of an image reached 10000 candidates and dropped the remaining ones
+        """
+        ...
+    @staticmethod
+    def from_json(spec: str) -> HeadDecoder:
+        """Decoder of a spec serialized as JSON
+
+        Args:
+            spec (str): JSON object of the spec
+
+        Returns:
+            HeadDecoder: Decoder of the spec
+        """
+        ...
+    @staticmethod
+    def from_yaml(spec: str) -> HeadDecoder:
+        """Decoder of a spec serialized as YAML
+
+        Args:
+            spec (str): Path of a YAML file or the YAML document itself
+
+        Returns:
+            HeadDecoder: Decoder of the spec
+        """
+        ...
+    def to_dict(self) -> Dict[str, Any]:
+        """Spec of the decoder with all its keys
+
+        Returns:
+            Dict[str, Any]: Spec as taken by the constructor
+        """
+        ...
+    def to_json(self) -> str:
+        """Serialize the spec of the decoder as JSON
+
+        Returns:
+            str: JSON object of the spec
+        """
+        ...
+    def to_yaml(self) -> str:
+        """Serialize the spec of the decoder as YAML
+
+        Returns:
+            str: YAML mapping of the spec
+        """
+        ...
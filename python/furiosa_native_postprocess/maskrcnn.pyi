@@ -0,0 +1,50 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """Mask R-CNN PostProcessor
+
+    It decodes the RoI box head (softmax scoring, per-class box regression with weights
+    (10, 10, 5, 5), score thresholding, class-aware NMS) and pastes mask head outputs
+    into full-image binary masks.
+
+    Args:
+        max_detections (Optional[int]): Maximum number of detections per image, default is 100
+    """
+    def __init__(self, max_detections: Optional[int] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, class_logits: Sequence[numpy.ndarray], box_regression: Sequence[numpy.ndarray], proposals: Sequence[numpy.ndarray], image_shapes: Sequence[Tuple[float, float]], conf_threshold: float, iou_threshold: float) -> List[numpy.ndarray]:
+        """Evaluate the box head postprocess
+
+        Args:
+            class_logits (Sequence[numpy.ndarray]): Class logits of shape (R, num_classes) per image
+            box_regression (Sequence[numpy.ndarray]): Box deltas of shape (R, num_classes * 4) per image
+            proposals (Sequence[numpy.ndarray]): Proposal boxes of shape (R, 4) per image in xyxy
+            image_shapes (Sequence[Tuple[float, float]]): (height, width) of each image
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): IoU threshold
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
+    def paste_masks(self, masks: numpy.ndarray, detections: numpy.ndarray, image_shape: Tuple[int, int], threshold: Optional[float] = ...) -> numpy.ndarray:
+        """Paste mask head outputs into full-image binary masks
+
+        Args:
+            masks (numpy.ndarray): Mask probabilities of the detected class, shape (N, M, M)
+            detections (numpy.ndarray): Detection results of one image returned by `eval`
+            image_shape (Tuple[int, int]): (height, width) of the image
+            threshold (Optional[float]): Mask probability threshold, default is 0.5
+
+        Returns:
+            numpy.ndarray: Binary masks of shape (N, height, width)
+        """
+        ...
@@ -0,0 +1,41 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+def pack_response(results: Union[numpy.ndarray, Sequence[PyDetectionResult]], image_id: int, category_ids: Optional[Union[str, Sequence[int]]] = ...) -> Tuple[numpy.ndarray, int]:
+    """Pack the detections of an image into the QuerySampleResponse data of LoadGen
+
+    Args:
+        results (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the image,
+            either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+            PyDetectionResult
+        image_id (int): Index of the image in the query sample library
+        category_ids (Optional[Union[str, Sequence[int]]]): Category id each class is remapped
+            to, or "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids
+            the accuracy script expects, default is the class index itself
+
+    Returns:
+        Tuple[numpy.ndarray, int]: Contiguous float32 array of rows (image_id, ymin, xmin, ymax,
+            xmax, score, class) and its length in bytes, to be passed to QuerySampleResponse as
+            `array.ctypes.data` and the length
+    """
+    ...
+
+def write_accuracy_log(results: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], qsl_indices: Sequence[int], path: str, category_ids: Optional[Union[str, Sequence[int]]] = ...) -> Any:
+    """Write `mlperf_log_accuracy.json` from the detections of the queried samples
+
+    Args:
+        results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+            sample, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or lists
+            of PyDetectionResult
+        qsl_indices (Sequence[int]): Index of each sample in the query sample library
+        path (str): Path of the accuracy log to write
+        category_ids (Optional[Union[str, Sequence[int]]]): Category id each class is remapped
+            to, or "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids
+            the accuracy script expects, default is the class index itself
+    """
+    ...
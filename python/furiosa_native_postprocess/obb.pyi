@@ -0,0 +1,39 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence
+
+import numpy
+
+from furiosa_native_postprocess import PyObbResults
+
+class RustPostProcessor:
+    """Oriented Bounding Box PostProcessor
+
+    It decodes YOLOv8-OBB detection heads whose input tensors have shape
+    `(N, 4 * reg_max + num_classes + 1, H, W)`, the last channel being the rotation angle logit,
+    and runs rotated NMS.
+
+    Args:
+        strides (numpy.ndarray): Strides (1D Array)
+        reg_max (Optional[int]): Number of DFL bins per box side, default is 16
+        agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+    """
+    def __init__(self, strides: numpy.ndarray, reg_max: Optional[int] = ..., agnostic: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, agnostic: Optional[bool] = ...) -> List[PyObbResults]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): Rotated IoU threshold
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+
+        Returns:
+            List[PyObbResults]: Batched oriented detection results
+        """
+        ...
@@ -0,0 +1,60 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, List, Optional, Sequence, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult, PyDetectionResults
+
+class Hierarchy:
+    """Class hierarchy of OpenImages for the evaluation protocol of the challenge
+
+    Args:
+        path (str): Path of the hierarchy file, such as bbox_labels_600_hierarchy.json
+        labels (Sequence[str]): Label name (MID) of each class of the model, e.g. "/m/0bt9lr"
+    """
+    def __init__(self, path: str, labels: Sequence[str]) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def ancestors(self, class_: int) -> List[int]:
+        """Ancestor classes of a class, e.g. to expand the ground truth labels
+
+        Args:
+            class_ (int): Class index
+
+        Returns:
+            List[int]: Ancestor classes, empty for a root class
+        """
+        ...
+    def expand(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]]) -> Union[numpy.ndarray, PyDetectionResults]:
+        """Expand detections to the ancestors of their class
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections, either an
+                array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+                PyDetectionResult
+
+        Returns:
+            Union[numpy.ndarray, PyDetectionResults]: Each detection followed by a copy for every
+                ancestor of its class, in the same format as the input
+        """
+        ...
+    @property
+    def num_classes(self) -> Any:
+        ...
+    def suppress_duplicates(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], iou_threshold: Optional[float] = ...) -> Union[numpy.ndarray, PyDetectionResults]:
+        """Suppress the detections duplicating a higher scoring one of a parent or child class
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections, either an
+                array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+                PyDetectionResult
+            iou_threshold (Optional[float]): IoU above which boxes of related classes are
+                duplicates, default is 0.5
+
+        Returns:
+            Union[numpy.ndarray, PyDetectionResults]: Remaining detections in descending score
+                order, in the same format as the input
+        """
+        ...
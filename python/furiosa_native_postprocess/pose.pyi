@@ -0,0 +1,39 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence
+
+import numpy
+
+from furiosa_native_postprocess import PyPoseResults
+
+class RustPostProcessor:
+    """Pose PostProcessor
+
+    It decodes keypoints together with boxes for YOLOv7-pose (anchor-based, when `anchors` is
+    given) or YOLOv8-pose (anchor-free DFL head) models and runs OKS-aware NMS.
+
+    Args:
+        strides (numpy.ndarray): Strides (1D Array)
+        anchors (Optional[numpy.ndarray]): Anchors (3D Array) of a YOLOv7-pose model
+        num_keypoints (Optional[int]): Number of keypoints, default is 17
+        reg_max (Optional[int]): Number of DFL bins per box side of YOLOv8-pose, default is 16
+    """
+    def __init__(self, strides: numpy.ndarray, anchors: Optional[numpy.ndarray] = ..., num_keypoints: Optional[int] = ..., reg_max: Optional[int] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, oks_threshold: Optional[float] = ...) -> List[PyPoseResults]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): IoU threshold
+            oks_threshold (Optional[float]): OKS threshold, keypoint similarity is ignored if None
+
+        Returns:
+            List[PyPoseResults]: Batched pose results
+        """
+        ...
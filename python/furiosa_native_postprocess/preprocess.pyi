@@ -0,0 +1,44 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple
+
+import numpy
+
+def letterbox(images: Sequence[numpy.ndarray], input_shape: Tuple[int, int], pad_value: Optional[int] = ..., mean: Optional[Sequence[float]] = ..., std: Optional[Sequence[float]] = ..., swap_rb: Optional[bool] = ..., quantize: Optional[Tuple[float, int]] = ...) -> Tuple[numpy.ndarray, List[Tuple[float, float, float]]]:
+    """Letterbox images and normalize them into an NCHW tensor for the network input
+
+    Args:
+        images (Sequence[numpy.ndarray]): uint8 images of shape (H, W, C)
+        input_shape (Tuple[int, int]): (height, width) of the network input
+        pad_value (Optional[int]): Value of the padding, default is 114
+        mean (Optional[Sequence[float]]): Mean of each channel, or of all of them, subtracted from
+            the 0-255 pixel values, default is 0
+        std (Optional[Sequence[float]]): Std of each channel, or of all of them, dividing the
+            pixel values, default is 255
+        swap_rb (Optional[bool]): Whether to reverse the channel order, e.g. from the BGR of
+            OpenCV to RGB, default is False
+        quantize (Optional[Tuple[float, int]]): (scale, zero_point) to quantize the normalized
+            values to uint8, default is to keep them as float32
+
+    Returns:
+        Tuple[numpy.ndarray, List[Tuple[float, float, float]]]: Tensor of shape
+            (N, C, height, width), and the (scale, pad_x, pad_y) of each image to pass as the
+            `letterbox_params` of the postprocessors
+    """
+    ...
+
+def mlperf_preprocess(images: Sequence[numpy.ndarray], model: str, swap_rb: Optional[bool] = ..., quantize: Optional[Tuple[float, int]] = ...) -> numpy.ndarray:
+    """Preprocess images as the reference implementation of an MLPerf Inference benchmark
+
+    Args:
+        images (Sequence[numpy.ndarray]): uint8 RGB images of shape (H, W, 3)
+        model (str): One of "resnet50", "ssd-mobilenet", "ssd-resnet34" or "retinanet"
+        swap_rb (Optional[bool]): Whether the images are in BGR, as read by `cv2.imread`, default
+            is False
+        quantize (Optional[Tuple[float, int]]): (scale, zero_point) to quantize the normalized
+            values to uint8, default is to keep them as float32
+
+    Returns:
+        numpy.ndarray: Tensor of shape (N, 3, height, width) of the model input
+    """
+    ...
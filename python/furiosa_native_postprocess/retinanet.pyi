@@ -0,0 +1,39 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """RetinaNet PostProcessor
+
+    It generates the FPN anchor grid as torchvision does (3 scales x 3 aspect ratios per level),
+    applies box deltas, keeps the per-level top-k candidates, and runs class-aware NMS.
+    Class outputs hold raw logits with shape `(N, 9 * num_classes, H, W)` and box outputs
+    hold deltas with shape `(N, 9 * 4, H, W)`.
+
+    Args:
+        image_size (Optional[Tuple[int, int]]): (height, width) of network input, default is (800, 800)
+        strides (Optional[Sequence[int]]): Strides of each FPN level, default is [8, 16, 32, 64, 128]
+        anchor_sizes (Optional[Sequence[float]]): Anchor size of each level, default is [32, 64, 128, 256, 512]
+    """
+    def __init__(self, image_size: Optional[Tuple[int, int]] = ..., strides: Optional[Sequence[int]] = ..., anchor_sizes: Optional[Sequence[float]] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, cls_outputs: Sequence[numpy.ndarray], box_outputs: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, top_k: Optional[int] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            cls_outputs (Sequence[numpy.ndarray]): Class logits of each level
+            box_outputs (Sequence[numpy.ndarray]): Box deltas of each level
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): IoU threshold
+            top_k (Optional[int]): Number of candidates kept per level before NMS, default is 1000
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
@@ -0,0 +1,33 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Optional, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """Semantic Segmentation PostProcessor
+
+    It computes the per-pixel argmax over channels of NCHW logits (e.g., DeepLabV3), returning
+    class indices which can be used directly as palette indices.
+
+    Args:
+        align_corners (Optional[bool]): `align_corners` of the bilinear resize, default is False
+    """
+    def __init__(self, align_corners: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, logits: numpy.ndarray, output_shape: Optional[Tuple[int, int]] = ...) -> numpy.ndarray:
+        """Evaluate the postprocess
+
+        Args:
+            logits (numpy.ndarray): Logits of shape (N, C, H, W)
+            output_shape (Optional[Tuple[int, int]]): Original (height, width) to resize logits to
+                before argmax, the logits' resolution is kept if None
+
+        Returns:
+            numpy.ndarray: Class indices of shape (N, H, W) as uint8
+        """
+        ...
@@ -0,0 +1,75 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Dict, Optional, Sequence, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResults
+
+class SsdPostProcessor:
+    """Postprocessor of an SSD head, MLPerf SSD-MobileNet by default
+
+    Args:
+        model (Optional[str]): "ssd_mobilenet" or "ssd_resnet34", the configuration to start from
+        config (Optional[Dict[str, Any]]): Keys of the configuration overriding those of the
+            model, as returned by `to_dict`
+    """
+    def __init__(self, model: Optional[str] = ..., config: Optional[Dict[str, Any]] = ...) -> None:
+        ...
+    def eval(self, boxes: Sequence[numpy.ndarray], scores: Sequence[numpy.ndarray], nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> PyDetectionResults:
+        """Evaluate the postprocess
+
+        Args:
+            boxes (Sequence[numpy.ndarray]): Box tensors of dtype float32, float16 or bfloat16
+            scores (Sequence[numpy.ndarray]): Score tensors of dtype float32, float16 or bfloat16
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            PyDetectionResults: Detections of the image
+        """
+        ...
+    def eval_quantized(self, boxes: Sequence[numpy.ndarray], scores: Sequence[numpy.ndarray], box_scales: Sequence[Union[float, Sequence[float]]], box_zero_points: Sequence[Union[int, Sequence[int]]], score_scales: Sequence[Union[float, Sequence[float]]], score_zero_points: Sequence[Union[int, Sequence[int]]], nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> PyDetectionResults:
+        """Evaluate the postprocess of quantized outputs, dequantized during decode
+
+        Args:
+            boxes (Sequence[numpy.ndarray]): Box tensors of dtype uint8 or int8
+            scores (Sequence[numpy.ndarray]): Score tensors of the same dtype as the boxes
+            box_scales (Sequence[Union[float, Sequence[float]]]): Scale of each box tensor, or of
+                each of its channels (first axis)
+            box_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each box tensor,
+                or of each of its channels
+            score_scales (Sequence[Union[float, Sequence[float]]]): Scale of each score tensor, or
+                of each of its channels
+            score_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each score
+                tensor, or of each of its channels
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            PyDetectionResults: Detections of the image
+        """
+        ...
+    def eval_to_array(self, boxes: Sequence[numpy.ndarray], scores: Sequence[numpy.ndarray], nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> numpy.ndarray:
+        """Evaluate the postprocess, returning the results as a single array
+
+        Args:
+            boxes (Sequence[numpy.ndarray]): Box tensors of dtype float32, float16 or bfloat16
+            scores (Sequence[numpy.ndarray]): Score tensors of dtype float32, float16 or bfloat16
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            numpy.ndarray: Detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+        """
+        ...
+    def to_dict(self) -> Dict[str, Any]:
+        """Configuration of the postprocessor
+
+        Returns:
+            Dict[str, Any]: Configuration of which any subset may be passed as `config`
+        """
+        ...
@@ -0,0 +1,69 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Dict, Optional, Sequence, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResults
+
+class RustPostProcessor:
+    """RustPostProcessor
+
+    It takes a DFG whose unlower part is removed.
+    The DFG binary must have magic number in its head.
+
+    Args:
+        priors (Optional[Dict[str, Any]]): Config of the priors, with the "feature_maps", "scales",
+            "aspect_ratios", "convention" ("caffe" or "tensorflow"), "reduce_lowest_layer" and
+            "clip" of the prior generator of the model, default is the one of SSD-MobileNet
+    """
+    def __init__(self, priors: Optional[Dict[str, Any]] = ...) -> None:
+        ...
+    def eval(self, boxes: Any, scores: Any, nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> PyDetectionResults:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            PyDetectionResults: Detections of the image
+        """
+        ...
+    def eval_quantized(self, boxes: Sequence[numpy.ndarray], scores: Sequence[numpy.ndarray], box_scales: Sequence[Union[float, Sequence[float]]], box_zero_points: Sequence[Union[int, Sequence[int]]], score_scales: Sequence[Union[float, Sequence[float]]], score_zero_points: Sequence[Union[int, Sequence[int]]], nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> PyDetectionResults:
+        """Evaluate the postprocess of quantized outputs, dequantized during decode
+
+        Args:
+            boxes (Sequence[numpy.ndarray]): Box tensors of dtype uint8 or int8
+            scores (Sequence[numpy.ndarray]): Score tensors of the same dtype as the boxes
+            box_scales (Sequence[Union[float, Sequence[float]]]): Scale of each box tensor, or of
+                each of its channels (first axis)
+            box_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each box tensor,
+                or of each of its channels
+            score_scales (Sequence[Union[float, Sequence[float]]]): Scale of each score tensor, or
+                of each of its channels
+            score_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each score
+                tensor, or of each of its channels
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            PyDetectionResults: Detections of the image
+        """
+        ...
+    def eval_to_array(self, boxes: Any, scores: Any, nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> numpy.ndarray:
+        """Evaluate the postprocess, returning the results as a single array
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            numpy.ndarray: Detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+        """
+        ...
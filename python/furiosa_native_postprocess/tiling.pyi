@@ -0,0 +1,45 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult, PyDetectionResults
+
+def stitch(tiles: Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]], offsets: Sequence[Tuple[float, float]], iou_threshold: float, nms_method: Optional[str] = ..., sigma: Optional[float] = ..., max_det: Optional[int] = ...) -> Union[numpy.ndarray, PyDetectionResults]:
+    """Stitch the detections of the tiles of an image into global coordinates and suppress the
+    duplicates of objects spanning several tiles
+
+    Args:
+        tiles (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+            tile in its own coordinates, either arrays of shape (N, 6) with rows
+            (x1, y1, x2, y2, score, class) or lists of PyDetectionResult
+        offsets (Sequence[Tuple[float, float]]): (x, y) offset of each tile in the image
+        iou_threshold (float): IoU threshold
+        nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+            for class-wise Weighted Box Fusion, default is "hard"
+        sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+        max_det (Optional[int]): Maximum number of detections, default is 300
+
+    Returns:
+        Union[numpy.ndarray, PyDetectionResults]: Detections of the image in the same format as
+            the inputs, in descending score order
+    """
+    ...
+
+def tile_offsets(image_width: int, image_height: int, tile_width: int, tile_height: int, overlap: Optional[float] = ...) -> List[Tuple[int, int]]:
+    """(x, y) offsets of the tiles covering an image, row by row
+
+    Args:
+        image_width (int): Width of the image
+        image_height (int): Height of the image
+        tile_width (int): Width of a tile
+        tile_height (int): Height of a tile
+        overlap (Optional[float]): Overlap of adjacent tiles as a ratio of their size, default
+            is 0.2
+
+    Returns:
+        List[Tuple[int, int]]: Offset of each tile, the last tiles of a row or column ending at
+            the border of the image
+    """
+    ...
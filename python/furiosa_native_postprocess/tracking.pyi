@@ -0,0 +1,108 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Optional, Sequence, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+class BoxSmoother:
+    """Exponential moving average of the boxes of consecutive frames, reducing the jitter of video
+    overlays
+
+    Args:
+        alpha (Optional[float]): Weight of the box of the current frame, default is 0.5
+        iou_threshold (Optional[float]): Minimum IoU of an untracked box with a smoothed box of
+            the same class to follow it, default is 0.3
+        max_age (Optional[int]): Number of frames a smoothed box is kept without a box following
+            it, default is 1
+    """
+    def __init__(self, alpha: Optional[float] = ..., iou_threshold: Optional[float] = ..., max_age: Optional[int] = ...) -> None:
+        ...
+    def reset(self) -> Any:
+        """Forget every smoothed box, as at the start of another video
+        """
+        ...
+    def update(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]]) -> numpy.ndarray:
+        """Smooth the boxes of a frame
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+                frame, either tracked ones of shape (N, 7) as returned by the trackers, followed
+                by track id, or untracked ones of shape (N, 6) with rows
+                (x1, y1, x2, y2, score, class) or a list of PyDetectionResult, followed by IoU
+
+        Returns:
+            numpy.ndarray: Detections of the same shape with smoothed boxes
+        """
+        ...
+
+class ByteTrack:
+    """ByteTrack multi-object tracker, fed the detections of the frames of a video in order,
+    including the low scoring ones
+
+    Args:
+        track_threshold (Optional[float]): Minimum score of a high scoring detection, matched
+            first, default is 0.5
+        low_threshold (Optional[float]): Minimum score of a low scoring detection, matched to the
+            tracks left unmatched by the high scoring ones, default is 0.1
+        new_track_threshold (Optional[float]): Minimum score of a detection starting a new track,
+            default is `track_threshold + 0.1`
+        match_iou (Optional[float]): Minimum IoU of a high scoring detection with the predicted
+            box of a track to match it, default is 0.2
+        track_buffer (Optional[int]): Number of frames a lost track is kept, default is 30
+    """
+    def __init__(self, track_threshold: Optional[float] = ..., low_threshold: Optional[float] = ..., new_track_threshold: Optional[float] = ..., match_iou: Optional[float] = ..., track_buffer: Optional[int] = ...) -> None:
+        ...
+    def reset(self) -> Any:
+        """Forget every track, as at the start of another video
+        """
+        ...
+    def update(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], low_detections: Optional[Union[numpy.ndarray, Sequence[PyDetectionResult]]] = ...) -> numpy.ndarray:
+        """Advance by a frame
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+                frame, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+                a list of PyDetectionResult, decoded with a confidence threshold as low as
+                `low_threshold`
+            low_detections (Optional[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Low
+                scoring detections returned apart, e.g. by the `low_conf_threshold` of
+                `eval_detailed`
+
+        Returns:
+            numpy.ndarray: Tracked detections of the frame of shape (M, 7) with rows
+                (x1, y1, x2, y2, score, class, track_id), the boxes being estimated by the tracks
+        """
+        ...
+
+class Sort:
+    """SORT multi-object tracker, fed the detections of the frames of a video in order
+
+    Args:
+        max_age (Optional[int]): Number of frames a track survives without a matching
+            detection, default is 1
+        min_hits (Optional[int]): Number of consecutive matches before a track is reported,
+            default is 3
+        iou_threshold (Optional[float]): Minimum IoU of a detection with the predicted box of a
+            track to match it, default is 0.3
+    """
+    def __init__(self, max_age: Optional[int] = ..., min_hits: Optional[int] = ..., iou_threshold: Optional[float] = ...) -> None:
+        ...
+    def reset(self) -> Any:
+        """Forget every track, as at the start of another video
+        """
+        ...
+    def update(self, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]]) -> numpy.ndarray:
+        """Advance by a frame
+
+        Args:
+            detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+                frame, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+                a list of PyDetectionResult
+
+        Returns:
+            numpy.ndarray: Tracked detections of the frame of shape (M, 7) with rows
+                (x1, y1, x2, y2, score, class, track_id), the boxes being estimated by the tracks
+        """
+        ...
@@ -0,0 +1,47 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Tuple
+
+import numpy
+
+class RustPostProcessor:
+    """3D-UNet PostProcessor
+
+    It aggregates overlapping sliding-window patch predictions with Gaussian importance
+    weighting as the MLPerf 3D-UNet (KiTS19) reference does, producing the final segmentation
+    volume.
+
+    Args:
+        patch_size (Optional[Tuple[int, int, int]]): Patch size, default is (128, 128, 128)
+        std_factor (Optional[float]): Gaussian standard deviation relative to the patch size,
+            default is 0.125
+    """
+    def __init__(self, patch_size: Optional[Tuple[int, int, int]] = ..., std_factor: Optional[float] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, patches: numpy.ndarray, origins: numpy.ndarray, volume_shape: Tuple[int, int, int]) -> numpy.ndarray:
+        """Evaluate the postprocess
+
+        Args:
+            patches (numpy.ndarray): Patch predictions of shape (P, C, D, H, W)
+            origins (numpy.ndarray): Non-negative int64 patch origins of shape (P, 3)
+            volume_shape (Tuple[int, int, int]): (depth, height, width) of the padded volume
+
+        Returns:
+            numpy.ndarray: Segmentation volume of shape (D, H, W) as uint8
+        """
+        ...
+    def patch_origins(self, volume_shape: Tuple[int, int, int], overlap: Optional[float] = ...) -> List[Tuple[int, int, int]]:
+        """Compute the origins of the sliding-window patches
+
+        Args:
+            volume_shape (Tuple[int, int, int]): (depth, height, width) of the padded volume
+            overlap (Optional[float]): Overlap ratio between neighboring patches, default is 0.5
+
+        Returns:
+            List[Tuple[int, int, int]]: Patch origins in (depth, height, width) order
+        """
+        ...
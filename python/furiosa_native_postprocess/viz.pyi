@@ -0,0 +1,25 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Optional, Sequence, Tuple, Union
+
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResult
+
+def draw_detections(image: numpy.ndarray, detections: Union[numpy.ndarray, Sequence[PyDetectionResult]], class_names: Optional[Sequence[str]] = ..., thickness: Optional[int] = ..., font_scale: Optional[int] = ..., labels: Optional[bool] = ..., scores: Optional[bool] = ..., colors: Optional[Sequence[Tuple[int, int, int]]] = ...) -> Any:
+    """Draw the boxes of detections into an image in place, with their class name and score
+
+    Args:
+        image (numpy.ndarray): uint8 array of shape (H, W, 3) or (H, W, 4), modified in place
+        detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections in the
+            coordinates of the image, either an array of shape (N, 6) with rows
+            (x1, y1, x2, y2, score, class) or a list of PyDetectionResult
+        class_names (Optional[Sequence[str]]): Name of each class, default is the class index
+        thickness (Optional[int]): Width of the box outlines in pixels, default is 2
+        font_scale (Optional[int]): Integer scale of the 5x7 pixel font, default is 1
+        labels (Optional[bool]): Whether to write the class names, default is True
+        scores (Optional[bool]): Whether to write the scores, default is True
+        colors (Optional[Sequence[Tuple[int, int, int]]]): Color of each class in the channel
+            order of the image, cycling over the classes, default is the palette of Ultralytics
+    """
+    ...
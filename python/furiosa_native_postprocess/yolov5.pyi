@@ -0,0 +1,443 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import Any, Callable, Dict, List, Optional, Sequence, Tuple, Union
+
+import concurrent.futures
+import numpy
+
+from furiosa_native_postprocess import PyDetectionResults, PySegmentationResults
+
+class EvalResult:
+    """Result of `RustPostProcessor.eval_detailed`, the detections of a batch with what each was
+    kept from and the statistics of the postprocess
+    """
+    def __len__(self) -> int:
+        ...
+    def __repr__(self) -> str:
+        ...
+    @property
+    def detections(self) -> Any:
+        """Detections of each image of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+        """
+        ...
+    @property
+    def indices(self) -> Any:
+        """int64 arrays of shape (N, 5) with rows (candidate, layer, anchor, y, x) giving the index
+        of each detection among the candidates returned by `decode_only` and the grid cell it
+        was decoded from, None for "wbf" whose boxes are averages of candidates
+        """
+        ...
+    @property
+    def low_conf(self) -> Any:
+        """Detections of each image scoring between `low_conf_threshold` and `conf_threshold`,
+        empty unless `low_conf_threshold` is given
+        """
+        ...
+    @property
+    def stats(self) -> Any:
+        """Time spent in each stage summed over the images, "decode_ms", "trim_ms", "nms_ms" and
+        "build_ms", the wall time of the call, "total_ms", the numbers of "candidates" decoded
+        and of "detections" kept, and the indices of the images whose decode reached
+        `max_boxes`, "overflowed", which also raise a RuntimeWarning
+        """
+        ...
+    @property
+    def timed_out(self) -> Any:
+        """Whether the NMS of each image ran out of `time_limit_ms`
+        """
+        ...
+
+class RustPostProcessor:
+    """YOLOv5 PostProcessor
+
+    It takes anchors, class_names, strides as input
+
+    Args:
+        anchors (numpy.ndarray): Anchors of shape (num_layers, num_anchors, 2) in units of the
+            stride of each layer, any number of detection layers being supported, e.g. 4 for
+            the P6 models at 1280
+        strides (numpy.ndarray): Stride of each detection layer (1D Array)
+        agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+        num_threads (Optional[int]): Number of threads decoding and suppressing the images of a
+            batch in parallel, default is the number of logical CPUs
+        layout (Optional[str]): Layout of the input tensors, "nahwc" for
+            (batch, anchor, y, x, channels), "nhwc" for channels-last
+            (batch, y, x, anchor * channels) or "lowered" for the device-native
+            (batch, y, x / tile, padded channels, tile) of the compiler, default is "nahwc"
+        clip (Optional[bool]): Whether to clip boxes to the image bounds after NMS, default is
+            False
+        max_det (Optional[int]): Maximum number of detections per image, default is 300
+        max_boxes (Optional[int]): Maximum number of candidates decoded per image, default is
+            10000
+        max_nms (Optional[int]): Maximum number of highest scoring candidates per image entering
+            NMS, default is 30000
+        channels (Optional[int]): Number of channels per anchor of the "lowered" layout, the
+            anchors being padded together up to the channel alignment of the device
+        tile (Optional[int]): Width of the x tiles of the "lowered" layout, default is 1
+        raw_logits (Optional[bool]): Whether the model outputs raw logits, exported without its
+            final sigmoid, which is then applied during decode to the surviving cells only,
+            default is False
+        fast_sigmoid (Optional[bool]): Whether to approximate the sigmoid of `raw_logits`
+            within 2e-4, default is False
+        objectness_only (Optional[bool]): Whether objectness alone is the score of class 0, for
+            single-class models with a single or no class channel, default is False
+        multi_label (Optional[bool]): Whether every class above the threshold yields a candidate,
+            rather than the class of the highest calibrated score alone, default is True
+        merge (Optional[bool]): Whether to merge the boxes suppressed by a kept box into it,
+            averaging the coordinates of those overlapping it weighted by their scores, default
+            is False
+        min_area (Optional[float]): Minimum area of the decoded boxes in pixels of the network
+            input, the smaller ones being dropped before NMS, default is 0
+        min_side (Optional[float]): Minimum width and height of the decoded boxes in pixels of
+            the network input, default is 0
+        max_aspect_ratio (Optional[float]): Maximum ratio of the longer side of the decoded
+            boxes to their shorter side, default is unbounded
+        calibration (Optional[Union[str, Dict[str, Any]]]): Calibration of the scores during
+            decode, before thresholding, as `{"temperature": t}` dividing their logits by `t` or
+            `{"a": [...], "b": [...]}` mapping the logit `x` of class `c` to `a[c] * x + b[c]`,
+            or a JSON document or file of either, default is none
+        class_names (Optional[Union[str, Sequence[str]]]): Name of each class, labeling the
+            detections, or "coco80" for the 80 COCO classes, default is none
+        category_ids (Optional[Union[str, Sequence[int]]]): Dataset category id of each class, or
+            "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids, default is
+            the class index itself
+    """
+    def __init__(self, anchors: numpy.ndarray, strides: numpy.ndarray, agnostic: Optional[bool] = ..., num_threads: Optional[int] = ..., layout: Optional[str] = ..., clip: Optional[bool] = ..., max_det: Optional[int] = ..., max_boxes: Optional[int] = ..., max_nms: Optional[int] = ..., channels: Optional[int] = ..., tile: Optional[int] = ..., raw_logits: Optional[bool] = ..., fast_sigmoid: Optional[bool] = ..., objectness_only: Optional[bool] = ..., multi_label: Optional[bool] = ..., merge: Optional[bool] = ..., min_area: Optional[float] = ..., min_side: Optional[float] = ..., max_aspect_ratio: Optional[float] = ..., calibration: Optional[Union[str, Dict[str, Any]]] = ..., class_names: Optional[Union[str, Sequence[str]]] = ..., category_ids: Optional[Union[str, Sequence[int]]] = ...) -> None:
+        ...
+    def __getstate__(self) -> Any:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __setstate__(self, state: Any) -> Any:
+        ...
+    def __str__(self) -> str:
+        ...
+    def category_ids(self, classes: Sequence[int]) -> numpy.ndarray:
+        """Dataset category ids of classes, e.g. of the class column of the detections of `eval`
+
+        Args:
+            classes (Sequence[int]): Class indices
+
+        Returns:
+            numpy.ndarray: int64 category id of each class, the class itself without category ids
+        """
+        ...
+    def decode_only(self, inputs: Sequence[numpy.ndarray], conf_threshold: Union[float, Sequence[float]], classes: Optional[Sequence[int]] = ...) -> List[Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]]:
+        """Decode the candidate boxes without suppressing them, e.g. to apply a custom NMS
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+                classes
+
+        Returns:
+            List[Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]]: Batched candidates as boxes
+                of shape (N, 4) with rows (x1, y1, x2, y2), scores of shape (N,) and classes of
+                shape (N,)
+        """
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ..., original_shapes: Optional[Sequence[Tuple[float, float]]] = ..., letterbox_params: Optional[Sequence[Tuple[float, float, float]]] = ..., clip: Optional[bool] = ..., classes: Optional[Sequence[int]] = ..., time_limit_ms: Optional[float] = ..., rois: Optional[Sequence[Sequence[Any]]] = ..., normalized: Optional[bool] = ..., box_format: Optional[str] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16,
+                half-precision ones being converted during decode
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+            original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+                original image, to map boxes back to it assuming centered letterbox padding
+            letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+                pad_y) each original image was letterboxed with, takes precedence over
+                `original_shapes`, which then only bound the clipping
+            clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+                input if it is not given, takes precedence constructor's
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+                dropped during decode, default is all classes
+            time_limit_ms (Optional[float]): Time budget of the NMS of each image in milliseconds,
+                after which it stops and keeps the boxes selected so far, default is unlimited
+            rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+                rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+                letterboxed or else the network input, the candidates centered outside of it being
+                dropped during decode, default is the whole image
+            normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+                and height of the original image, or of the network input if it is not given,
+                default is False
+            box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+                y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+                width, height), default is "xyxy"
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
+    def eval_detailed(self, inputs: Sequence[numpy.ndarray], conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ..., original_shapes: Optional[Sequence[Tuple[float, float]]] = ..., letterbox_params: Optional[Sequence[Tuple[float, float, float]]] = ..., clip: Optional[bool] = ..., classes: Optional[Sequence[int]] = ..., time_limit_ms: Optional[float] = ..., low_conf_threshold: Optional[float] = ..., rois: Optional[Sequence[Sequence[Any]]] = ..., normalized: Optional[bool] = ..., box_format: Optional[str] = ...) -> EvalResult:
+        """Evaluate the postprocess as `eval` does, also returning what each detection was kept
+        from, whether NMS ran out of time and the statistics of the stages
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16,
+                half-precision ones being converted during decode
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+            original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+                original image, to map boxes back to it assuming centered letterbox padding
+            letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+                pad_y) each original image was letterboxed with, takes precedence over
+                `original_shapes`, which then only bound the clipping
+            clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+                input if it is not given, takes precedence constructor's
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+                dropped during decode, default is all classes
+            time_limit_ms (Optional[float]): Time budget of the NMS of each image in milliseconds,
+                after which it stops and keeps the boxes selected so far, default is unlimited
+            low_conf_threshold (Optional[float]): Lower confidence threshold of the candidates
+                also kept through NMS but returned apart, e.g. for the second association of
+                ByteTrack, default is none
+            rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+                rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+                letterboxed or else the network input, the candidates centered outside of it being
+                dropped during decode, default is the whole image
+            normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+                and height of the original image, or of the network input if it is not given,
+                default is False
+            box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+                y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+                width, height), default is "xyxy"
+
+        Returns:
+            EvalResult: Batched detection results with their indices, timeouts and statistics
+        """
+        ...
+    def eval_flat(self, inputs: numpy.ndarray, conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ..., classes: Optional[Sequence[int]] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess of a flattened output, as exported to ONNX with the grid decode
+        baked in, whose confidences are scored and filtered as in `eval` before NMS
+
+        Args:
+            inputs (numpy.ndarray): Input tensor of shape (N, num_boxes, 5 + num_classes) whose
+                rows are (cx, cy, w, h, objectness, class confidences...) in pixels
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+                dropped during decode, default is all classes
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
+    def eval_quantized(self, inputs: Sequence[numpy.ndarray], scales: Sequence[Union[float, Sequence[float]]], zero_points: Sequence[Union[int, Sequence[int]]], conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess of quantized outputs
+
+        Dequantization is fused into the decode, so that only the channels of cells whose
+        quantized objectness exceeds the quantized confidence threshold are dequantized.
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype uint8 or int8
+            scales (Sequence[Union[float, Sequence[float]]]): Scale of each input tensor, or of
+                each of its channels (last axis)
+            zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each input tensor, or
+                of each of its channels (last axis)
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results
+        """
+        ...
+    def eval_seg(self, inputs: Sequence[numpy.ndarray], protos: numpy.ndarray, conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ...) -> List[PySegmentationResults]:
+        """Evaluate the postprocess of YOLOv5-seg models
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors, ending with `num_masks` mask coefficients
+            protos (numpy.ndarray): Prototype masks of shape (N, num_masks, H, W)
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+
+        Returns:
+            List[PySegmentationResults]: Batched segmentation results
+        """
+        ...
+    def eval_single(self, inputs: Sequence[numpy.ndarray], conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ..., original_shape: Optional[Tuple[float, float]] = ..., letterbox_params: Optional[Tuple[float, float, float]] = ..., clip: Optional[bool] = ..., classes: Optional[Sequence[int]] = ..., normalized: Optional[bool] = ...) -> PyDetectionResults:
+        """YOLOv5 postprocess of a single image, e.g. for the SingleStream scenario of MLPerf
+
+        The layers are decoded sequentially into buffers the postprocessor reuses across calls,
+        avoiding the batched bookkeeping of `eval`.
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32 with a batch of 1
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+            original_shape (Optional[Tuple[float, float]]): (height, width) of the original
+                image, to map boxes back to it assuming centered letterbox padding
+            letterbox_params (Optional[Tuple[float, float, float]]): (scale, pad_x, pad_y) the
+                original image was letterboxed with, takes precedence over `original_shape`
+            clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+                input if it is not given, takes precedence constructor's
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+                classes
+            normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+                and height of the original image, or of the network input if it is not given,
+                default is False
+
+        Returns:
+            PyDetectionResults: Detections of the image
+        """
+        ...
+    @staticmethod
+    def from_dict(config: Dict[str, Any]) -> RustPostProcessor:
+        """Postprocessor of a configuration as returned by `to_dict`
+
+        Args:
+            config (Dict[str, Any]): Constructor arguments, `anchors` and `strides` being required
+
+        Returns:
+            RustPostProcessor: Postprocessor of the configuration
+        """
+        ...
+    @staticmethod
+    def from_json(config: str) -> RustPostProcessor:
+        """Postprocessor of a configuration serialized by `to_json`
+
+        Args:
+            config (str): JSON object of the configuration
+
+        Returns:
+            RustPostProcessor: Postprocessor of the configuration
+        """
+        ...
+    @staticmethod
+    def from_yaml(config: str, strides: Optional[Sequence[float]] = ...) -> RustPostProcessor:
+        """Postprocessor of a configuration serialized by `to_yaml`, or of the anchors and strides of
+        an Ultralytics YOLOv5 model definition
+
+        The strides of a model definition are inferred by following the downsampling of its
+        backbone and head up to the inputs of its `Detect` layer, and its anchors in pixels are
+        divided by them as the model does when built.
+
+        Args:
+            config (str): Path of a YAML file or the YAML document itself
+            strides (Optional[Sequence[float]]): Strides of the detection layers of a model
+                definition, taking precedence over the inferred ones
+
+        Returns:
+            RustPostProcessor: Postprocessor of the configuration
+        """
+        ...
+    def labels(self, classes: Sequence[int]) -> List[Optional[str]]:
+        """Names of classes, e.g. of the class column of the detections of `eval`
+
+        Args:
+            classes (Sequence[int]): Class indices
+
+        Returns:
+            List[Optional[str]]: Name of each class, None without class names
+        """
+        ...
+    def submit(self, inputs: Sequence[numpy.ndarray], conf_threshold: Union[float, Sequence[float]], iou_threshold: Union[float, Sequence[float]], callback: Optional[Union[Callable[[List[numpy.ndarray]], None], concurrent.futures.Future]] = ..., epsilon: Optional[float] = ..., agnostic: Optional[bool] = ..., nms_method: Optional[str] = ..., sigma: Optional[float] = ..., original_shapes: Optional[Sequence[Tuple[float, float]]] = ..., letterbox_params: Optional[Sequence[Tuple[float, float, float]]] = ..., clip: Optional[bool] = ..., classes: Optional[Sequence[int]] = ..., rois: Optional[Sequence[Sequence[Any]]] = ..., normalized: Optional[bool] = ..., box_format: Optional[str] = ...) -> Optional[concurrent.futures.Future]:
+        """Submit the postprocess of a batch to the background workers and return at once, e.g. to
+        postprocess a batch while the next one runs on the device
+
+        The inputs are copied, so that their buffers can be reused as soon as this returns.
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+            conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+            iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+            callback (Optional[Union[Callable[[List[numpy.ndarray]], None], concurrent.futures.Future]]):
+                Callable called with the results from a worker thread, or a
+                `concurrent.futures.Future` or `asyncio.Future` to set them to, default is a new
+                `concurrent.futures.Future`
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+            nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+                for class-wise Weighted Box Fusion, default is "hard"
+            sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+            original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+                original image, to map boxes back to it assuming centered letterbox padding
+            letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+                pad_y) each original image was letterboxed with, takes precedence over
+                `original_shapes`
+            clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+                input if it is not given, takes precedence constructor's
+            classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+                classes
+            rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+                rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+                letterboxed or else the network input, the candidates centered outside of it being
+                dropped during decode, default is the whole image
+            normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+                and height of the original image, or of the network input if it is not given,
+                default is False
+            box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+                y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+                width, height), default is "xyxy"
+
+        Returns:
+            Optional[concurrent.futures.Future]: The future the batched detection results of
+                `eval` are set to, None if `callback` is a callable, whose exceptions are reported
+                as unraisable
+        """
+        ...
+    def set_rescore(self, callback: Optional[Callable[..., Any]] = ...) -> Any:
+        """Register a callback rescoring the candidates of each image between decode and NMS of
+        every eval method but `eval_seg`, which raises while one is registered, and of `submit`,
+        whose worker takes the GIL to call it
+
+        It is called as `callback(boxes, scores, classes, image)` with the boxes of shape (N, 4)
+        with rows (x1, y1, x2, y2) in the network input, their scores and classes of shape (N,),
+        and the index of the image in the batch, and returns the N new scores. Candidates whose
+        new score does not exceed the confidence threshold of their class, or is NaN, are
+        dropped. Candidates outside of `rois` are dropped before the callback sees them.
+
+        Args:
+            callback (Optional[Callable[..., Any]]): Rescoring callback, None to remove it
+        """
+        ...
+    def to_dict(self) -> Dict[str, Any]:
+        """Configuration of the postprocessor, the arguments of its constructor
+
+        Returns:
+            Dict[str, Any]: Anchors as nested lists, strides and the other options
+        """
+        ...
+    def to_json(self) -> str:
+        """Serialize the configuration of the postprocessor as JSON
+
+        Returns:
+            str: JSON object of the configuration returned by `to_dict`
+        """
+        ...
+    def to_yaml(self) -> str:
+        """Serialize the configuration of the postprocessor as YAML
+
+        Returns:
+            str: YAML mapping of the configuration returned by `to_dict`
+        """
+        ...
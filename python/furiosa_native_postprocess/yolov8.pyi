@@ -0,0 +1,39 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence
+
+import numpy
+
+class RustPostProcessor:
+    """YOLOv8 PostProcessor
+
+    It decodes anchor-free detection heads whose box branch is regressed with
+    Distribution Focal Loss. Each input tensor has shape `(N, 4 * reg_max + num_classes, H, W)`
+    where class channels hold raw logits.
+
+    Args:
+        strides (numpy.ndarray): Strides (1D Array)
+        reg_max (Optional[int]): Number of DFL bins per box side, default is 16
+        agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+    """
+    def __init__(self, strides: numpy.ndarray, reg_max: Optional[int] = ..., agnostic: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, epsilon: Optional[float] = ..., agnostic: Optional[bool] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): IoU threshold
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results, a RuntimeWarning being raised if the
+                decode of an image reached 10000 candidates and dropped the remaining ones
+        """
+        ...
@@ -0,0 +1,38 @@
+# Generated by scripts/generate_stubs.py from the furiosa_native_postprocess extension, do not edit
+
+from typing import List, Optional, Sequence
+
+import numpy
+
+class RustPostProcessor:
+    """YOLOX PostProcessor
+
+    It decodes anchor-free decoupled heads. Each input tensor has shape
+    `(N, 5 + num_classes, H, W)` holding raw box regressions followed by
+    sigmoid-activated objectness and class confidences.
+
+    Args:
+        strides (numpy.ndarray): Strides (1D Array)
+        agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+    """
+    def __init__(self, strides: numpy.ndarray, agnostic: Optional[bool] = ...) -> None:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def __str__(self) -> str:
+        ...
+    def eval(self, inputs: Sequence[numpy.ndarray], conf_threshold: float, iou_threshold: float, epsilon: Optional[float] = ..., agnostic: Optional[bool] = ...) -> List[numpy.ndarray]:
+        """Evaluate the postprocess
+
+        Args:
+            inputs (Sequence[numpy.ndarray]): Input tensors
+            conf_threshold (float): Confidence threshold
+            iou_threshold (float): IoU threshold
+            epsilon (Optional[float]): Epsilon for numerical stability
+            agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+
+        Returns:
+            List[numpy.ndarray]: Batched detection results, a RuntimeWarning being raised if the
+                decode of an image reached 10000 candidates and dropped the remaining ones
+        """
+        ...
@@ -0,0 +1,192 @@
+"""Generate the type stubs of the extension module
+
+The extension is introspected for its submodules, classes and functions. Parameters come from
+the text signatures PyO3 generates and their types from the Google-style docstrings, `Any`
+being used for the undocumented ones.
+
+Usage:
+    python scripts/generate_stubs.py <path of the built extension> <output directory>
+"""
+
+import importlib.machinery
+import importlib.util
+import inspect
+import re
+import sys
+from pathlib import Path
+from types import ModuleType
+from typing import Dict, List, Optional, Set
+
+MODULE = "furiosa_native_postprocess"
+HEADER = f"# Generated by scripts/generate_stubs.py from the {MODULE} extension, do not edit\n"
+TYPING = {"Any", "Callable", "Dict", "Iterator", "List", "Optional", "Sequence", "Tuple", "Union"}
+DUNDER_TYPES = {"__len__": "int", "__repr__": "str", "__str__": "str", "__iter__": "Iterator[Any]"}
+SPECIAL_METHODS = {*DUNDER_TYPES, "__getitem__", "__getstate__", "__setstate__", "__reduce__"}
+ARG = re.compile(r"^\s+(\w+) \((.+?)\):")
+RETURN = re.compile(r"^\s+(.+?):(\s|$)")
+# Modules whose types are referred to qualified in the docstrings
+MODULES = ("concurrent.futures", "numpy")
+IDENTIFIER = re.compile(r"[A-Za-z_][\w.]*")
+
+
+def load(path: str) -> ModuleType:
+    loader = importlib.machinery.ExtensionFileLoader(MODULE, path)
+    spec = importlib.util.spec_from_file_location(MODULE, path, loader=loader)
+    module = importlib.util.module_from_spec(spec)
+    sys.modules[MODULE] = module
+    loader.exec_module(module)
+    return module
+
+
+def section(doc: Optional[str], name: str) -> List[str]:
+    """Lines of a section of a Google-style docstring"""
+    lines = (doc or "").splitlines()
+    try:
+        start = lines.index(f"{name}:") + 1
+    except ValueError:
+        return []
+    end = start
+    while end < len(lines) and (not lines[end] or lines[end][0].isspace()):
+        end += 1
+    return lines[start:end]
+
+
+def arg_types(doc: Optional[str]) -> Dict[str, str]:
+    return {m[1]: m[2] for m in map(ARG.match, section(doc, "Args")) if m}
+
+
+def return_type(doc: Optional[str]) -> Optional[str]:
+    lines = [line for line in section(doc, "Returns") if line.strip()]
+    match = RETURN.match(lines[0]) if lines else None
+    return match[1] if match else None
+
+
+class Stub:
+    def __init__(self, name: str, classes: Set[str], local: Set[str]):
+        self.name = name
+        self.classes = classes
+        self.local = local
+        self.imports: Set[str] = set()
+        self.lines: List[str] = []
+
+    def annotation(self, annotation: Optional[str]) -> str:
+        if annotation is None:
+            self.imports.add("Any")
+            return "Any"
+        for identifier in IDENTIFIER.findall(annotation):
+            if identifier in self.local:
+                continue
+            if identifier in TYPING or identifier in self.classes:
+                self.imports.add(identifier)
+            elif identifier.rpartition(".")[0] in MODULES:
+                self.imports.add(identifier.rpartition(".")[0])
+            elif identifier not in {"int", "float", "str", "bool", "bytes", "None", "object"}:
+                print(f"{self.name}: unknown type {identifier!r}", file=sys.stderr)
+        return annotation
+
+    def docstring(self, doc: Optional[str], indent: str) -> None:
+        if not doc:
+            return
+        doc = doc.replace("\\", "\\\\").replace('"""', '\\"\\"\\"')
+        lines = doc.splitlines()
+        self.lines.append(f'{indent}"""{lines[0]}')
+        self.lines.extend(f"{indent}{line}" if line else "" for line in lines[1:])
+        self.lines.append(f'{indent}"""')
+
+    def function(
+        self, name: str, obj, doc: Optional[str], indent: str = "", bound: bool = True
+    ) -> None:
+        types = arg_types(doc)
+        try:
+            parameters = list(inspect.signature(obj).parameters.values())
+        except (TypeError, ValueError):
+            parameters = None
+        if parameters is None:
+            params = ["*args: Any", "**kwargs: Any"]
+            self.imports.add("Any")
+        else:
+            params = []
+            for p in parameters:
+                if p.name in ("self", "$self"):
+                    params.append("self")
+                    continue
+                prefix = {p.VAR_POSITIONAL: "*", p.VAR_KEYWORD: "**"}.get(p.kind, "")
+                param = f"{prefix}{p.name}: {self.annotation(types.get(p.name))}"
+                params.append(param if p.default is p.empty else f"{param} = ...")
+        if indent and bound and params[:1] != ["self"]:
+            params.insert(0, "self")
+        if name == "__init__":
+            returns = "None"
+        else:
+            returns = self.annotation(DUNDER_TYPES.get(name) or return_type(doc))
+        self.lines.append(f"{indent}def {name}({', '.join(params)}) -> {returns}:")
+        # The docstrings of special methods are the generic ones of their slots
+        if not name.startswith("__"):
+            self.docstring(doc, indent + "    ")
+        self.lines.append(f"{indent}    ...")
+
+    def class_(self, name: str, cls: type) -> None:
+        self.lines.append(f"class {name}:")
+        self.docstring(cls.__doc__, "    ")
+        if cls.__text_signature__:
+            self.function("__init__", cls, cls.__doc__, "    ")
+        for attr, value in sorted(vars(cls).items()):
+            if inspect.isgetsetdescriptor(value):
+                self.lines.append("    @property")
+                self.lines.append(f"    def {attr}(self) -> {self.annotation(None)}:")
+                self.docstring(value.__doc__, "        ")
+                self.lines.append("        ...")
+            elif isinstance(value, (staticmethod, classmethod)):
+                self.lines.append(f"    @{type(value).__name__}")
+                function = value.__func__
+                self.function(attr, function, function.__doc__, "    ", bound=False)
+            elif callable(value) and (not attr.startswith("_") or attr in SPECIAL_METHODS):
+                self.function(attr, value, value.__doc__, "    ")
+        self.lines.append("")
+
+    def render(self) -> str:
+        typing = sorted(self.imports & TYPING)
+        classes = sorted(self.imports & self.classes)
+        header = [HEADER]
+        if typing:
+            header.append(f"from typing import {', '.join(typing)}\n")
+        modules = [module for module in MODULES if module in self.imports]
+        if modules:
+            header.append("".join(f"import {module}\n" for module in modules))
+        if classes:
+            header.append(f"from {MODULE} import {', '.join(classes)}\n")
+        return "\n".join(header) + "\n" + "\n".join(self.lines).rstrip() + "\n"
+
+
+def generate(module: ModuleType, name: str, classes: Set[str]) -> str:
+    local = {attr for attr, value in vars(module).items() if isinstance(value, type)}
+    stub = Stub(name, classes, local)
+    for attr, value in sorted(vars(module).items()):
+        if attr.startswith("_"):
+            continue
+        if isinstance(value, ModuleType):
+            stub.lines.append(f"from . import {attr} as {attr}")
+        elif isinstance(value, type):
+            stub.class_(attr, value)
+        elif callable(value):
+            stub.function(attr, value, value.__doc__)
+            stub.lines.append("")
+    if name == MODULE:
+        stub.lines.insert(0, "__version__: str\n")
+    return stub.render()
+
+
+def main(path: str, output: str) -> None:
+    module = load(path)
+    output_dir = Path(output)
+    output_dir.mkdir(parents=True, exist_ok=True)
+    classes = {name for name, value in vars(module).items() if isinstance(value, type)}
+    (output_dir / "__init__.pyi").write_text(generate(module, MODULE, classes))
+    for name, submodule in sorted(vars(module).items()):
+        if isinstance(submodule, ModuleType):
+            (output_dir / f"{name}.pyi").write_text(generate(submodule, name, classes))
+    (output_dir / "py.typed").touch()
+
+
+if __name__ == "__main__":
+    main(*sys.argv[1:])
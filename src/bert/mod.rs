@@ -0,0 +1,184 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{ArrayView1, ArrayView2};
+use numpy::PyReadonlyArray2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+
+const DEFAULT_N_BEST_SIZE: usize = 20;
+const DEFAULT_MAX_ANSWER_LENGTH: usize = 30;
+
+/// Answer span of a single sample, `start` and `end` being inclusive token indices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub n_best_size: usize,
+    pub max_answer_length: usize,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ n_best_size: {}, max_answer_length: {} }}",
+            self.n_best_size, self.max_answer_length
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(n_best_size: Option<usize>, max_answer_length: Option<usize>) -> Self {
+        Self {
+            n_best_size: n_best_size.unwrap_or(DEFAULT_N_BEST_SIZE),
+            max_answer_length: max_answer_length.unwrap_or(DEFAULT_MAX_ANSWER_LENGTH),
+        }
+    }
+
+    /// Indices of the `n_best_size` largest logits among the valid positions, descending
+    fn best_indices(
+        &self,
+        logits: ArrayView1<'_, f32>,
+        mask: Option<ArrayView1<'_, bool>>,
+    ) -> Vec<usize> {
+        let mut indices: Vec<usize> =
+            (0..logits.len()).filter(|&i| mask.map_or(true, |mask| mask[i])).collect();
+        let by_logit = |&a: &usize, &b: &usize| logits[b].total_cmp(&logits[a]).then(a.cmp(&b));
+        if indices.len() > self.n_best_size {
+            indices.select_nth_unstable_by(self.n_best_size, by_logit);
+            indices.truncate(self.n_best_size);
+        }
+        indices.sort_unstable_by(by_logit);
+        indices
+    }
+
+    /// N-best span extraction of the reference SQuAD postprocess for a single sample
+    ///
+    /// Candidate spans combine the `n_best_size` best start and end positions, are discarded if
+    /// they are reversed, longer than `max_answer_length` or touch a masked out position, and
+    /// are scored by the sum of their start and end logits.
+    fn extract(
+        &self,
+        start_logits: ArrayView1<'_, f32>,
+        end_logits: ArrayView1<'_, f32>,
+        mask: Option<ArrayView1<'_, bool>>,
+    ) -> Vec<Span> {
+        let starts = self.best_indices(start_logits, mask);
+        let ends = self.best_indices(end_logits, mask);
+
+        let mut spans: Vec<Span> = Vec::with_capacity(starts.len() * ends.len());
+        for &start in &starts {
+            for &end in &ends {
+                if end < start || end - start + 1 > self.max_answer_length {
+                    continue;
+                }
+                spans.push(Span { start, end, score: start_logits[start] + end_logits[end] });
+            }
+        }
+        spans.sort_by(|a, b| b.score.total_cmp(&a.score));
+        spans.truncate(self.n_best_size);
+        spans
+    }
+
+    /// BERT postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        start_logits: ArrayView2<'_, f32>,
+        end_logits: ArrayView2<'_, f32>,
+        context_mask: Option<ArrayView2<'_, bool>>,
+    ) -> Vec<Vec<Span>> {
+        let masks = match &context_mask {
+            Some(mask) => mask.outer_iter().map(Some).collect::<Vec<_>>(),
+            None => vec![None; start_logits.shape()[0]],
+        };
+        let samples =
+            izip!(start_logits.outer_iter(), end_logits.outer_iter(), masks).collect::<Vec<_>>();
+        samples
+            .into_par_iter()
+            .map(|(start_logits, end_logits, mask)| self.extract(start_logits, end_logits, mask))
+            .collect()
+    }
+}
+
+/// BERT SQuAD PostProcessor
+///
+/// It extracts the n-best answer spans of question answering models from start/end logits.
+///
+/// Args:
+///     n_best_size (Optional[int]): Number of start/end candidates and spans to keep,
+///         default is 20
+///     max_answer_length (Optional[int]): Maximum number of tokens of a span, default is 30
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(n_best_size: Option<usize>, max_answer_length: Option<usize>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(n_best_size, max_answer_length)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     start_logits (numpy.ndarray): Start logits of shape (N, S)
+    ///     end_logits (numpy.ndarray): End logits of shape (N, S)
+    ///     context_mask (Optional[numpy.ndarray]): Boolean mask of shape (N, S) marking the tokens
+    ///         an answer may span, e.g. context tokens having their maximum context in the window
+    ///
+    /// Returns:
+    ///     List[List[Tuple[int, int, float]]]: Batched n-best (start, end, score) spans in
+    ///         descending score order, with inclusive token indices
+    fn eval(
+        &self,
+        py: Python<'_>,
+        start_logits: PyReadonlyArray2<'_, f32>,
+        end_logits: PyReadonlyArray2<'_, f32>,
+        context_mask: Option<PyReadonlyArray2<'_, bool>>,
+    ) -> PyResult<Vec<Vec<(usize, usize, f32)>>> {
+        if start_logits.shape() != end_logits.shape()
+            || context_mask.as_ref().is_some_and(|mask| mask.shape() != start_logits.shape())
+        {
+            return Err(PyValueError::new_err(format!(
+                "start_logits {:?}, end_logits {:?} and context_mask {:?} must have the same shape",
+                start_logits.shape(),
+                end_logits.shape(),
+                context_mask.as_ref().map(|mask| mask.shape())
+            )));
+        }
+
+        let (start_logits, end_logits) = (start_logits.as_array(), end_logits.as_array());
+        let context_mask = context_mask.as_ref().map(|mask| mask.as_array());
+        let spans = threads::allow_threads(py, || {
+            self.0.postprocess(start_logits, end_logits, context_mask)
+        });
+        Ok(spans
+            .into_iter()
+            .map(|spans| spans.into_iter().map(|s| (s.start, s.end, s.score)).collect())
+            .collect())
+    }
+}
+
+pub(crate) fn bert(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use ndarray::{arr1, Array1, Array2, ArrayView2, Axis};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::BoundingBox;
+use crate::common::threads;
+
+/// Rows of an (N, 4) array
+fn boxes<'a>(name: &str, array: &'a PyReadonlyArray2<'_, f32>) -> PyResult<ArrayView2<'a, f32>> {
+    let array = array.as_array();
+    if array.shape()[1] != 4 {
+        return Err(PyValueError::new_err(format!(
+            "{name} must have shape (N, 4) but got {:?}",
+            array.shape()
+        )));
+    }
+    Ok(array)
+}
+
+fn bounding_boxes(boxes: ArrayView2<'_, f32>) -> Vec<BoundingBox> {
+    boxes.outer_iter().map(|r| BoundingBox { px1: r[0], py1: r[1], px2: r[2], py2: r[3] }).collect()
+}
+
+/// Pairwise `overlap` of the (x1, y1, x2, y2) rows of `a` and `b`
+fn pairwise(
+    py: Python<'_>,
+    a: PyReadonlyArray2<'_, f32>,
+    b: PyReadonlyArray2<'_, f32>,
+    overlap: fn(&BoundingBox, &BoundingBox) -> f32,
+) -> PyResult<Py<PyArray2<f32>>> {
+    let (a, b) = (boxes("a", &a)?, boxes("b", &b)?);
+    let matrix = threads::allow_threads(py, || {
+        let (a, b) = (bounding_boxes(a), bounding_boxes(b));
+        let values = a
+            .par_iter()
+            .flat_map_iter(|a| b.iter().map(move |b| overlap(a, b)))
+            .collect::<Vec<_>>();
+        Array2::from_shape_vec((a.len(), b.len()), values).unwrap()
+    });
+    Ok(matrix.into_pyarray(py).to_owned())
+}
+
+/// IoU of every pair of boxes
+///
+/// Args:
+///     a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///     b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: IoU of shape (N, M)
+#[pyfunction]
+fn iou_matrix(
+    py: Python<'_>,
+    a: PyReadonlyArray2<'_, f32>,
+    b: PyReadonlyArray2<'_, f32>,
+) -> PyResult<Py<PyArray2<f32>>> {
+    pairwise(py, a, b, BoundingBox::iou)
+}
+
+/// Generalized IoU of every pair of boxes
+///
+/// Args:
+///     a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///     b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: GIoU of shape (N, M), in [-1, 1]
+#[pyfunction]
+fn giou(
+    py: Python<'_>,
+    a: PyReadonlyArray2<'_, f32>,
+    b: PyReadonlyArray2<'_, f32>,
+) -> PyResult<Py<PyArray2<f32>>> {
+    pairwise(py, a, b, BoundingBox::giou)
+}
+
+/// Distance-IoU of every pair of boxes
+///
+/// Args:
+///     a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///     b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: DIoU of shape (N, M), in [-1, 1]
+#[pyfunction]
+fn diou(
+    py: Python<'_>,
+    a: PyReadonlyArray2<'_, f32>,
+    b: PyReadonlyArray2<'_, f32>,
+) -> PyResult<Py<PyArray2<f32>>> {
+    pairwise(py, a, b, BoundingBox::diou)
+}
+
+/// Complete-IoU of every pair of boxes
+///
+/// Args:
+///     a (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///     b (numpy.ndarray): Boxes of shape (M, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: CIoU of shape (N, M)
+#[pyfunction]
+fn ciou(
+    py: Python<'_>,
+    a: PyReadonlyArray2<'_, f32>,
+    b: PyReadonlyArray2<'_, f32>,
+) -> PyResult<Py<PyArray2<f32>>> {
+    pairwise(py, a, b, BoundingBox::ciou)
+}
+
+/// Area of boxes, zero for boxes of a negative width or height
+///
+/// Args:
+///     boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: Areas of shape (N,)
+#[pyfunction]
+fn box_area(py: Python<'_>, boxes: PyReadonlyArray2<'_, f32>) -> PyResult<Py<PyArray1<f32>>> {
+    let boxes = self::boxes("boxes", &boxes)?;
+    let areas: Array1<f32> = boxes
+        .outer_iter()
+        .map(|r| f32::max(0.0, r[2] - r[0]) * f32::max(0.0, r[3] - r[1]))
+        .collect();
+    Ok(areas.into_pyarray(py).to_owned())
+}
+
+/// Convert boxes from (center x, center y, width, height) to (x1, y1, x2, y2)
+///
+/// Args:
+///     boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (cx, cy, w, h)
+///
+/// Returns:
+///     numpy.ndarray: Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+#[pyfunction]
+fn xywh2xyxy(py: Python<'_>, boxes: PyReadonlyArray2<'_, f32>) -> PyResult<Py<PyArray2<f32>>> {
+    let mut boxes = self::boxes("boxes", &boxes)?.to_owned();
+    for mut r in boxes.axis_iter_mut(Axis(0)) {
+        let (cx, cy, w, h) = (r[0], r[1], r[2], r[3]);
+        r.assign(&arr1(&[cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0]));
+    }
+    Ok(boxes.into_pyarray(py).to_owned())
+}
+
+/// Convert boxes from (x1, y1, x2, y2) to (center x, center y, width, height)
+///
+/// Args:
+///     boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///
+/// Returns:
+///     numpy.ndarray: Boxes of shape (N, 4) with rows (cx, cy, w, h)
+#[pyfunction]
+fn xyxy2xywh(py: Python<'_>, boxes: PyReadonlyArray2<'_, f32>) -> PyResult<Py<PyArray2<f32>>> {
+    let mut boxes = self::boxes("boxes", &boxes)?.to_owned();
+    for mut r in boxes.axis_iter_mut(Axis(0)) {
+        let (x1, y1, x2, y2) = (r[0], r[1], r[2], r[3]);
+        r.assign(&arr1(&[(x1 + x2) / 2.0, (y1 + y2) / 2.0, x2 - x1, y2 - y1]));
+    }
+    Ok(boxes.into_pyarray(py).to_owned())
+}
+
+pub(crate) fn box_ops(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(iou_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(giou, m)?)?;
+    m.add_function(wrap_pyfunction!(diou, m)?)?;
+    m.add_function(wrap_pyfunction!(ciou, m)?)?;
+    m.add_function(wrap_pyfunction!(box_area, m)?)?;
+    m.add_function(wrap_pyfunction!(xywh2xyxy, m)?)?;
+    m.add_function(wrap_pyfunction!(xyxy2xywh, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use ndarray::{Array2, ArrayView2};
+use numpy::{PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+
+/// Class indices and scores of shape (N, k)
+type PyTopKResults = (Py<PyArray2<i64>>, Py<PyArray2<f32>>);
+
+#[derive(Debug, Clone)]
+pub struct TopKPostprocessor {
+    pub softmax: bool,
+}
+
+impl fmt::Display for TopKPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TopKPostProcessor {{ softmax: {} }}", self.softmax)
+    }
+}
+
+impl TopKPostprocessor {
+    fn new(softmax: Option<bool>) -> Self {
+        Self { softmax: softmax.unwrap_or(true) }
+    }
+
+    /// Top-k classes of each row in descending score order
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(&self, logits: ArrayView2<'_, f32>, k: usize) -> (Array2<i64>, Array2<f32>) {
+        let batch_size = logits.shape()[0];
+        let rows = logits
+            .outer_iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|row| {
+                let mut ranked: Vec<(usize, f32)> = row.iter().copied().enumerate().collect();
+                let by_score = |a: &(usize, f32), b: &(usize, f32)| b.1.total_cmp(&a.1);
+                if k < ranked.len() {
+                    ranked.select_nth_unstable_by(k, by_score);
+                    ranked.truncate(k);
+                }
+                ranked.sort_unstable_by(by_score);
+
+                if self.softmax {
+                    // Softmax is monotonic, so only the selected entries need normalizing
+                    let max = row.fold(f32::NEG_INFINITY, |acc, &v| f32::max(acc, v));
+                    let sum = row.fold(0f32, |acc, &v| acc + f32::exp(v - max));
+                    ranked.iter_mut().for_each(|(_, v)| *v = f32::exp(*v - max) / sum);
+                }
+                ranked
+            })
+            .collect::<Vec<_>>();
+
+        let mut indices = Array2::zeros((batch_size, k));
+        let mut scores = Array2::zeros((batch_size, k));
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, (index, score)) in row.into_iter().enumerate() {
+                indices[[i, j]] = index as i64;
+                scores[[i, j]] = score;
+            }
+        }
+        (indices, scores)
+    }
+}
+
+/// Top-k Classification PostProcessor
+///
+/// It selects the k highest scoring classes of batched classification logits (e.g., ResNet50).
+///
+/// Args:
+///     softmax (Optional[bool]): Whether to apply softmax to the logits, default is True
+#[pyclass]
+pub struct TopKPostProcessor(TopKPostprocessor);
+
+#[pymethods]
+impl TopKPostProcessor {
+    #[new]
+    fn new(softmax: Option<bool>) -> PyResult<Self> {
+        Ok(Self(TopKPostprocessor::new(softmax)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     logits (numpy.ndarray): Logits of shape (N, C)
+    ///     k (int): Number of classes to keep, must not exceed C
+    ///
+    /// Returns:
+    ///     Tuple[numpy.ndarray, numpy.ndarray]: Class indices (int64) and scores (float32),
+    ///         both of shape (N, k) in descending score order
+    fn eval(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray2<'_, f32>,
+        k: usize,
+    ) -> PyResult<PyTopKResults> {
+        let logits = logits.as_array();
+        let num_classes = logits.shape()[1];
+        if k == 0 || k > num_classes {
+            return Err(PyValueError::new_err(format!(
+                "k must be between 1 and the number of classes ({num_classes}) but got {k}"
+            )));
+        }
+
+        let (indices, scores) = threads::allow_threads(py, || self.0.postprocess(logits, k));
+        Ok((
+            PyArray2::from_owned_array(py, indices).to_owned(),
+            PyArray2::from_owned_array(py, scores).to_owned(),
+        ))
+    }
+}
+
+pub(crate) fn classification(m: &PyModule) -> PyResult<()> {
+    m.add_class::<TopKPostProcessor>()?;
+
+    Ok(())
+}
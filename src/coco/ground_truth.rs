@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResults};
+use crate::eval::{coco_iou, GroundTruth as GroundTruthBox};
+
+/// An image of a COCO annotation file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub id: i64,
+    pub file_name: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Ground truth of a COCO instances annotation file, such as `instances_val2017.json`
+///
+/// Categories are sorted by id and the class of a box is the index of its category, i.e. the
+/// 80 contiguous classes of the models rather than the 91 COCO category ids.
+#[derive(Debug, Clone, Default)]
+pub struct GroundTruth {
+    pub images: Vec<Image>,
+    /// COCO category id of each class
+    pub category_ids: Vec<i64>,
+    /// Name of each class
+    pub category_names: Vec<String>,
+    boxes: HashMap<i64, Vec<GroundTruthBox>>,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> io::Result<&'a Value> {
+    value.get(key).ok_or_else(|| invalid(format!("missing \"{key}\" field")))
+}
+
+fn number(value: &Value, key: &str) -> io::Result<f64> {
+    field(value, key)?.as_f64().ok_or_else(|| invalid(format!("\"{key}\" is not a number")))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> io::Result<&'a [Value]> {
+    field(value, key)?
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| invalid(format!("\"{key}\" is not an array")))
+}
+
+impl GroundTruth {
+    /// Load an annotation file, failing with `InvalidData` if it is not a COCO instances file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let root: Value = serde_json::from_str(text)?;
+
+        let mut categories = array(&root, "categories")?
+            .iter()
+            .map(|c| {
+                let name = field(c, "name")?.as_str().unwrap_or_default().to_string();
+                Ok((number(c, "id")? as i64, name))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        categories.sort_by_key(|&(id, _)| id);
+        let classes = categories.iter().enumerate().map(|(class, &(id, _))| (id, class));
+        let classes = classes.collect::<HashMap<_, _>>();
+
+        let images = array(&root, "images")?
+            .iter()
+            .map(|image| {
+                Ok(Image {
+                    id: number(image, "id")? as i64,
+                    file_name: image
+                        .get("file_name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    width: number(image, "width")? as usize,
+                    height: number(image, "height")? as usize,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut boxes = HashMap::<i64, Vec<GroundTruthBox>>::new();
+        for annotation in array(&root, "annotations")? {
+            let category_id = number(annotation, "category_id")? as i64;
+            let class = *classes
+                .get(&category_id)
+                .ok_or_else(|| invalid(format!("unknown category id {category_id}")))?;
+            let bbox = array(annotation, "bbox")?
+                .iter()
+                .map(|v| v.as_f64().map(|v| v as f32))
+                .collect::<Option<Vec<_>>>()
+                .filter(|b| b.len() == 4)
+                .ok_or_else(|| invalid("\"bbox\" is not [x, y, width, height]"))?;
+            let crowd = annotation.get("iscrowd").and_then(Value::as_f64).unwrap_or(0.0) != 0.0;
+            boxes.entry(number(annotation, "image_id")? as i64).or_default().push(GroundTruthBox {
+                bbox: BoundingBox {
+                    px1: bbox[0],
+                    py1: bbox[1],
+                    px2: bbox[0] + bbox[2],
+                    py2: bbox[1] + bbox[3],
+                },
+                class,
+                crowd,
+            });
+        }
+
+        let (category_ids, category_names) = categories.into_iter().unzip();
+        Ok(Self { images, category_ids, category_names, boxes })
+    }
+
+    /// Ground truth boxes of an image, empty for an unknown image
+    pub fn boxes(&self, image_id: i64) -> &[GroundTruthBox] {
+        self.boxes.get(&image_id).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// TP/FP/FN assignment of the detections of an image at one IoU threshold
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageMatches {
+    /// (detection, ground truth) index pairs
+    pub true_positives: Vec<(usize, usize)>,
+    /// Detections matching no ground truth
+    pub false_positives: Vec<usize>,
+    /// Regular ground truth matched by no detection
+    pub false_negatives: Vec<usize>,
+}
+
+/// Match the detections of an image to its ground truth, as the COCO evaluation does
+///
+/// Detections are visited in descending score order, each taking the unmatched ground truth
+/// box of its class it overlaps most with an IoU of at least `iou_threshold`. A detection only
+/// matching a crowd region is ignored, counted neither as a true nor as a false positive.
+pub fn match_detections(
+    detections: &DetectionResults,
+    gts: &[GroundTruthBox],
+    iou_threshold: f32,
+) -> ImageMatches {
+    let mut order = (0..detections.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| detections[b].score.total_cmp(&detections[a].score));
+
+    let mut matches = ImageMatches::default();
+    let mut gt_matched = vec![false; gts.len()];
+    for d in order {
+        let detection = &detections[d];
+        let mut best: Option<(usize, f64)> = None;
+        let mut crowd = false;
+        for (g, gt) in gts.iter().enumerate() {
+            if gt.class != detection.class as usize || gt_matched[g] {
+                continue;
+            }
+            let iou = coco_iou(&detection.bbox, gt);
+            if iou < f64::from(iou_threshold) {
+                continue;
+            }
+            if gt.crowd {
+                crowd = true;
+            } else if best.map_or(true, |(_, best_iou)| iou > best_iou) {
+                best = Some((g, iou));
+            }
+        }
+        match best {
+            Some((g, _)) => {
+                gt_matched[g] = true;
+                matches.true_positives.push((d, g));
+            }
+            None if crowd => {}
+            None => matches.false_positives.push(d),
+        }
+    }
+    matches.false_negatives = gts
+        .iter()
+        .enumerate()
+        .filter(|&(g, gt)| !gt.crowd && !gt_matched[g])
+        .map(|(g, _)| g)
+        .collect();
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ssd_postprocess::DetectionResult;
+
+    const ANNOTATIONS: &str = r#"{
+        "info": {"description": "test \"set\" é😀"},
+        "images": [{"id": 7, "file_name": "000007.jpg", "width": 640, "height": 480}],
+        "annotations": [
+            {"id": 1, "image_id": 7, "category_id": 18, "bbox": [10, 10, 100, 100], "iscrowd": 0},
+            {"id": 2, "image_id": 7, "category_id": 1, "bbox": [300.5, 0, 1e2, 50], "iscrowd": 0},
+            {"id": 3, "image_id": 7, "category_id": 1, "bbox": [0, 300, 200, 150], "iscrowd": 1}
+        ],
+        "categories": [{"id": 18, "name": "dog"}, {"id": 1, "name": "person"}]
+    }"#;
+
+    #[test]
+    fn unittest_ground_truth() {
+        let gt = GroundTruth::from_json(ANNOTATIONS).unwrap();
+        assert_eq!(gt.category_ids, [1, 18]);
+        assert_eq!(gt.category_names, ["person", "dog"]);
+        assert_eq!(gt.images[0].file_name, "000007.jpg");
+        let boxes = gt.boxes(7);
+        assert_eq!(
+            boxes.iter().map(|b| (b.class, b.crowd)).collect::<Vec<_>>(),
+            [(1, false), (0, false), (0, true)]
+        );
+        assert_eq!(boxes[1].bbox.px2, 400.5);
+        assert!(gt.boxes(8).is_empty());
+
+        assert!(GroundTruth::from_json("{\"images\": [}").is_err());
+        assert!(GroundTruth::from_json("{\"images\": []}").is_err());
+        assert!(GroundTruth::from_json(&"[".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn unittest_match_detections() {
+        let gt = GroundTruth::from_json(ANNOTATIONS).unwrap();
+        let detection = |px1: f32, py1: f32, px2: f32, py2: f32, score: f32, class: f32| {
+            DetectionResult { index: 0.0, bbox: BoundingBox { px1, py1, px2, py2 }, score, class }
+        };
+        let detections = vec![
+            // The dog, then a duplicate of lower score
+            detection(12.0, 10.0, 110.0, 110.0, 0.6, 1.0),
+            detection(10.0, 10.0, 110.0, 110.0, 0.9, 1.0),
+            // A person in the crowd region, and one nowhere
+            detection(0.0, 300.0, 100.0, 400.0, 0.8, 0.0),
+            detection(500.0, 400.0, 600.0, 470.0, 0.7, 0.0),
+        ];
+        let matches = match_detections(&detections.into(), gt.boxes(7), 0.5);
+        assert_eq!(
+            matches,
+            ImageMatches {
+                true_positives: vec![(1, 0)],
+                false_positives: vec![3, 0],
+                false_negatives: vec![1],
+            }
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::common::extract_detections;
+use crate::common::labels::CategoryIds;
+use crate::common::ssd_postprocess::DetectionResults;
+use crate::eval::extract_ground_truth;
+
+mod ground_truth;
+
+pub use ground_truth::{match_detections, GroundTruth, Image, ImageMatches};
+
+use crate::common::threads;
+
+pub const DEFAULT_MATCH_IOU_THRESHOLD: f32 = 0.5;
+
+/// Write batched detections as a COCO detection results JSON array
+///
+/// Each detection becomes an object `{"image_id", "category_id", "bbox", "score"}` whose bbox
+/// is `[x, y, width, height]`. The class of a detection is looked up in `category_ids` when
+/// given, otherwise it is used as the category id as is.
+pub fn write_results<W: Write>(
+    writer: &mut W,
+    results: &[DetectionResults],
+    image_ids: &[i64],
+    category_ids: Option<&[i64]>,
+) -> io::Result<()> {
+    let mut first = true;
+    writer.write_all(b"[")?;
+    for (detections, &image_id) in results.iter().zip(image_ids) {
+        for r in detections.iter() {
+            let class = r.class as usize;
+            let category_id = match category_ids {
+                Some(table) => *table.get(class).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("class {class} has no entry in the category table"),
+                    )
+                })?,
+                None => class as i64,
+            };
+            if !first {
+                writer.write_all(b",")?;
+            }
+            first = false;
+            write!(
+                writer,
+                "\n{{\"image_id\": {image_id}, \"category_id\": {category_id}, \
+                 \"bbox\": [{}, {}, {}, {}], \"score\": {}}}",
+                json_number(r.bbox.px1),
+                json_number(r.bbox.py1),
+                json_number(r.bbox.px2 - r.bbox.px1),
+                json_number(r.bbox.py2 - r.bbox.py1),
+                json_number(r.score),
+            )?;
+        }
+    }
+    writer.write_all(b"\n]\n")?;
+    writer.flush()
+}
+
+/// JSON has no representation of infinities and NaN
+fn json_number(v: f32) -> String {
+    if v.is_finite() {
+        format!("{v}")
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Write batched detections to a COCO detection results JSON file
+///
+/// Args:
+///     results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+///         image, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or lists
+///         of PyDetectionResult
+///     image_ids (Sequence[int]): COCO image id of each image
+///     path (str): Path of the JSON file to write
+///     category_ids (Optional[Union[str, Sequence[int]]]): COCO category id of each class
+///         index, or "coco91" to map the 80 contiguous classes to the 91 COCO category ids,
+///         default is the class index itself
+#[pyfunction]
+#[pyo3(name = "write_results")]
+fn write_results_py(
+    py: Python<'_>,
+    results: Vec<&PyAny>,
+    image_ids: Vec<i64>,
+    path: &str,
+    category_ids: Option<CategoryIds>,
+) -> PyResult<()> {
+    if results.len() != image_ids.len() {
+        return Err(PyValueError::new_err(format!(
+            "expected {} image ids but got {}",
+            results.len(),
+            image_ids.len()
+        )));
+    }
+    let results =
+        results.into_iter().map(extract_detections).collect::<PyResult<Vec<DetectionResults>>>()?;
+
+    threads::allow_threads(py, || {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let category_ids = category_ids.as_ref().map(|CategoryIds(ids)| ids.as_slice());
+        write_results(&mut writer, &results, &image_ids, category_ids)
+    })
+    .map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidInput => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    })
+}
+
+/// Malformed annotation files are a ValueError, other failures to read them an OSError
+fn load_error(e: io::Error) -> PyErr {
+    match e.kind() {
+        io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    }
+}
+
+/// Ground truth of a COCO instances annotation file, such as instances_val2017.json
+///
+/// Categories are sorted by id and the class of a box is the index of its category, e.g. the 80
+/// contiguous COCO classes, `category_ids` mapping them back to the COCO category ids.
+///
+/// Args:
+///     path (str): Path of the annotation file
+#[pyclass(name = "GroundTruth", module = "furiosa_native_postprocess.coco")]
+pub struct PyGroundTruth(GroundTruth);
+
+#[pymethods]
+impl PyGroundTruth {
+    #[new]
+    fn new(py: Python<'_>, path: &str) -> PyResult<Self> {
+        threads::allow_threads(py, || GroundTruth::load(path)).map(Self).map_err(load_error)
+    }
+
+    /// Ids of the images, in file order
+    #[getter]
+    fn image_ids(&self) -> Vec<i64> {
+        self.0.images.iter().map(|image| image.id).collect()
+    }
+
+    /// COCO category id of each class
+    #[getter]
+    fn category_ids(&self) -> Vec<i64> {
+        self.0.category_ids.clone()
+    }
+
+    /// Name of each class
+    #[getter]
+    fn category_names(&self) -> Vec<String> {
+        self.0.category_names.clone()
+    }
+
+    /// Ground truth boxes of an image
+    ///
+    /// Args:
+    ///     image_id (int): COCO image id
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Array of shape (M, 5) with rows (x1, y1, x2, y2, class), empty for an
+    ///         unknown image
+    fn boxes(&self, py: Python<'_>, image_id: i64) -> Py<PyArray2<f32>> {
+        let boxes = self.0.boxes(image_id);
+        Array2::from_shape_fn((boxes.len(), 5), |(i, j)| {
+            let gt = &boxes[i];
+            [gt.bbox.px1, gt.bbox.py1, gt.bbox.px2, gt.bbox.py2, gt.class as f32][j]
+        })
+        .into_pyarray(py)
+        .to_owned()
+    }
+
+    /// Whether each ground truth box of an image is a crowd region
+    ///
+    /// Args:
+    ///     image_id (int): COCO image id
+    ///
+    /// Returns:
+    ///     List[bool]: Crowd flag of each row of `boxes(image_id)`
+    fn crowd(&self, image_id: i64) -> Vec<bool> {
+        self.0.boxes(image_id).iter().map(|gt| gt.crowd).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.images.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GroundTruth(num_images: {}, num_classes: {})",
+            self.0.images.len(),
+            self.0.category_ids.len()
+        )
+    }
+}
+
+/// Match the detections of an image to its ground truth as the COCO evaluation does
+///
+/// Detections are visited in descending score order, each taking the unmatched ground truth box
+/// of its class it overlaps most. Detections only matching a crowd region are ignored.
+///
+/// Args:
+///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the image,
+///         either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+///         PyDetectionResult
+///     ground_truth (numpy.ndarray): Ground truth of the image, an array of shape (M, 5) with
+///         rows (x1, y1, x2, y2, class)
+///     iou_threshold (Optional[float]): Minimum IoU of a match, default is 0.5
+///     crowd (Optional[Sequence[bool]]): Whether each ground truth box is a crowd region, default
+///         is False for every box
+///
+/// Returns:
+///     Tuple[List[Tuple[int, int]], List[int], List[int]]: (detection, ground truth) indices of
+///         the true positives, indices of the false positive detections and indices of the
+///         unmatched ground truth boxes
+#[pyfunction]
+#[pyo3(name = "match_detections")]
+#[allow(clippy::type_complexity)]
+fn match_detections_py(
+    py: Python<'_>,
+    detections: &PyAny,
+    ground_truth: PyReadonlyArray2<'_, f32>,
+    iou_threshold: Option<f32>,
+    crowd: Option<Vec<bool>>,
+) -> PyResult<(Vec<(usize, usize)>, Vec<usize>, Vec<usize>)> {
+    let detections = extract_detections(detections)?;
+    let gts = extract_ground_truth(ground_truth.as_array(), crowd.as_deref())?;
+    let matches = threads::allow_threads(py, || {
+        match_detections(&detections, &gts, iou_threshold.unwrap_or(DEFAULT_MATCH_IOU_THRESHOLD))
+    });
+    Ok((matches.true_positives, matches.false_positives, matches.false_negatives))
+}
+
+pub(crate) fn coco(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyGroundTruth>()?;
+    m.add_function(wrap_pyfunction!(write_results_py, m)?)?;
+    m.add_function(wrap_pyfunction!(match_detections_py, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::common::{Error, Result};
+
+/// Size and shape bounds of the decoded boxes, the others being dropped before NMS
+///
+/// Quantized models in particular yield slivers a few pixels wide, which only slow down NMS and
+/// confuse downstream tracking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxFilter {
+    /// Minimum width times height, in pixels of the network input
+    pub min_area: f32,
+    /// Minimum of the width and height, in pixels of the network input
+    pub min_side: f32,
+    /// Maximum of width / height and height / width
+    pub max_aspect_ratio: f32,
+}
+
+impl Default for BoxFilter {
+    fn default() -> Self {
+        Self { min_area: 0.0, min_side: 0.0, max_aspect_ratio: f32::INFINITY }
+    }
+}
+
+impl BoxFilter {
+    pub fn new(
+        min_area: Option<f32>,
+        min_side: Option<f32>,
+        max_aspect_ratio: Option<f32>,
+    ) -> Result<Self> {
+        let default = Self::default();
+        let filter = Self {
+            min_area: min_area.unwrap_or(default.min_area),
+            min_side: min_side.unwrap_or(default.min_side),
+            max_aspect_ratio: max_aspect_ratio.unwrap_or(default.max_aspect_ratio),
+        };
+        for (name, bound) in [("min_area", filter.min_area), ("min_side", filter.min_side)] {
+            if !(bound.is_finite() && bound >= 0.0) {
+                return Err(Error::invalid(format!(
+                    "{name} must be finite and non-negative but got {bound}"
+                )));
+            }
+        }
+        if filter.max_aspect_ratio.is_nan() || filter.max_aspect_ratio < 1.0 {
+            return Err(Error::invalid(format!(
+                "max_aspect_ratio must be at least 1 but got {}",
+                filter.max_aspect_ratio
+            )));
+        }
+        Ok(filter)
+    }
+
+    /// Whether every box is kept, so that the filter can be skipped
+    pub fn is_noop(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether a box of width `w` and height `h` is kept
+    #[inline]
+    pub fn keeps(&self, w: f32, h: f32) -> bool {
+        let (short, long) = (w.min(h), w.max(h));
+        // An unbounded ratio keeps degenerate boxes, whose ratio is undefined
+        w * h >= self.min_area
+            && short >= self.min_side
+            && (self.max_aspect_ratio == f32::INFINITY || long <= self.max_aspect_ratio * short)
+    }
+
+    /// min_area, min_side and max_aspect_ratio, None where they do not filter
+    pub fn describe(&self) -> (Option<f32>, Option<f32>, Option<f32>) {
+        let default = Self::default();
+        let bound = |value: f32, default: f32| (value != default).then_some(value);
+        (
+            bound(self.min_area, default.min_area),
+            bound(self.min_side, default.min_side),
+            bound(self.max_aspect_ratio, default.max_aspect_ratio),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_box_filter() {
+        let filter = BoxFilter::default();
+        assert!(filter.is_noop());
+        assert!(filter.keeps(0.0, 100.0));
+
+        let filter = BoxFilter::new(Some(16.0), Some(2.0), Some(4.0)).unwrap();
+        assert!(filter.keeps(4.0, 4.0));
+        assert!(filter.keeps(2.0, 8.0));
+        assert!(!filter.keeps(3.0, 5.0), "area below 16");
+        assert!(!filter.keeps(1.5, 20.0), "side below 2");
+        assert!(!filter.keeps(20.0, 4.5), "aspect ratio above 4");
+        assert_eq!(filter.describe(), (Some(16.0), Some(2.0), Some(4.0)));
+
+        assert!(BoxFilter::new(Some(-1.0), None, None).is_err());
+        assert!(BoxFilter::new(None, Some(f32::NAN), None).is_err());
+        assert!(BoxFilter::new(None, None, Some(0.5)).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use ndarray::{arr1, s, Array2, Axis};
+
+use crate::common::{Error, Result};
+
+/// Layout of the boxes of the detections returned to the caller
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BoxFormat {
+    /// (x1, y1, x2, y2), the corners the postprocess works with
+    #[default]
+    Xyxy,
+    /// (x1, y1, width, height), as in COCO annotations
+    Xywh,
+    /// (center x, center y, width, height)
+    Cxcywh,
+}
+
+impl BoxFormat {
+    /// Parse the `box_format` argument of `eval`, which is one of "xyxy", "xywh", or "cxcywh"
+    pub fn parse(format: Option<&str>) -> Result<Self> {
+        match format.unwrap_or("xyxy") {
+            "xyxy" => Ok(Self::Xyxy),
+            "xywh" => Ok(Self::Xywh),
+            "cxcywh" => Ok(Self::Cxcywh),
+            format => Err(Error::invalid(format!(
+                "box_format must be one of 'xyxy', 'xywh', or 'cxcywh' but got '{format}'"
+            ))),
+        }
+    }
+
+    /// Convert the boxes of detections whose rows start with (x1, y1, x2, y2) in place
+    pub fn convert(&self, detections: &mut Array2<f32>) {
+        if *self == Self::Xyxy {
+            return;
+        }
+        for mut row in detections.slice_mut(s![.., ..4]).axis_iter_mut(Axis(0)) {
+            let (x1, y1, x2, y2) = (row[0], row[1], row[2], row[3]);
+            let (w, h) = (x2 - x1, y2 - y1);
+            let [x, y] = match self {
+                Self::Xywh => [x1, y1],
+                _ => [x1 + w / 2.0, y1 + h / 2.0],
+            };
+            row.assign(&arr1(&[x, y, w, h]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn unittest_box_format() {
+        let detections = arr2(&[[10.0, 20.0, 30.0, 60.0, 0.9, 2.0]]);
+        let convert = |format: &str| {
+            let mut detections = detections.clone();
+            BoxFormat::parse(Some(format)).unwrap().convert(&mut detections);
+            detections
+        };
+        assert_eq!(convert("xyxy"), detections);
+        assert_eq!(convert("xywh"), arr2(&[[10.0, 20.0, 20.0, 40.0, 0.9, 2.0]]));
+        assert_eq!(convert("cxcywh"), arr2(&[[20.0, 40.0, 20.0, 40.0, 0.9, 2.0]]));
+        assert_eq!(BoxFormat::parse(None).unwrap(), BoxFormat::Xyxy);
+        assert!(BoxFormat::parse(Some("ltwh")).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::common::{logit, sigmoid, ConfThreshold, Error, Result};
+
+/// Calibration of the confidence scores, a monotonic map applied in logit space
+///
+/// Being monotonic, a threshold on the calibrated scores is a threshold on the raw ones, which
+/// is how cells are still filtered before their scores are calibrated.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "Fields", into = "Fields")]
+pub enum Calibration {
+    #[default]
+    Identity,
+    /// Logits divided by the temperature
+    Temperature(f32),
+    /// Platt scaling, `a * x + b` of the logit `x` with the `a` and `b` of the class
+    Platt { a: Vec<f32>, b: Vec<f32> },
+}
+
+/// Keys of a calibration object, validated into a [`Calibration`]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    a: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    b: Option<Vec<f32>>,
+}
+
+impl TryFrom<Fields> for Calibration {
+    type Error = Error;
+
+    fn try_from(fields: Fields) -> Result<Self> {
+        match fields {
+            Fields { temperature: None, a: None, b: None } => Ok(Self::Identity),
+            Fields { temperature: Some(temperature), a: None, b: None } => {
+                Self::temperature(temperature)
+            }
+            Fields { temperature: None, a: Some(a), b: Some(b) } => Self::platt(a, b),
+            _ => Err(Error::invalid(
+                "calibration must have either a number temperature or both a and b",
+            )),
+        }
+    }
+}
+
+impl From<Calibration> for Fields {
+    fn from(calibration: Calibration) -> Self {
+        match calibration {
+            Calibration::Identity => Self { temperature: None, a: None, b: None },
+            Calibration::Temperature(temperature) => {
+                Self { temperature: Some(temperature), a: None, b: None }
+            }
+            Calibration::Platt { a, b } => Self { temperature: None, a: Some(a), b: Some(b) },
+        }
+    }
+}
+
+impl Calibration {
+    pub fn temperature(temperature: f32) -> Result<Self> {
+        if !(temperature.is_finite() && temperature > 0.0) {
+            return Err(Error::invalid(format!(
+                "temperature must be positive but got {temperature}"
+            )));
+        }
+        Ok(Self::Temperature(temperature))
+    }
+
+    pub fn platt(a: Vec<f32>, b: Vec<f32>) -> Result<Self> {
+        if a.len() != b.len() {
+            return Err(Error::invalid(format!(
+                "expected as many Platt offsets as scales but got {} and {}",
+                b.len(),
+                a.len()
+            )));
+        }
+        if let Some(a) = a.iter().find(|a| !(a.is_finite() && **a > 0.0)) {
+            return Err(Error::invalid(format!("Platt scales must be positive but got {a}")));
+        }
+        if let Some(b) = b.iter().find(|b| !b.is_finite()) {
+            return Err(Error::invalid(format!("Platt offsets must be finite but got {b}")));
+        }
+        Ok(Self::Platt { a, b })
+    }
+
+    /// Calibration of a JSON object, either `{"temperature": t}` or `{"a": [...], "b": [...]}`
+    /// with the scale and offset of each class
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| Error::invalid(format!("invalid calibration: {e}")))
+    }
+
+    /// JSON object read back by [`Calibration::from_json`]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a calibration is always serializable")
+    }
+
+    /// Check the calibration covers the `num_classes` classes of the model
+    pub fn validate(&self, num_classes: usize) -> Result<()> {
+        match self {
+            Self::Platt { a, .. } if a.len() != num_classes => Err(Error::invalid(format!(
+                "expected Platt scaling of {num_classes} classes but got {}",
+                a.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Calibrated score of `class`
+    #[inline]
+    pub fn apply(&self, class: usize, score: f32) -> f32 {
+        match self {
+            Self::Identity => score,
+            Self::Temperature(temperature) => sigmoid(logit(score) / temperature),
+            Self::Platt { a, b } => sigmoid(a[class] * logit(score) + b[class]),
+        }
+    }
+
+    /// Raw score of `class` whose calibrated score is `threshold`, thresholds outside of (0, 1)
+    /// being kept as they select all or none of the scores either way
+    pub fn invert(&self, class: usize, threshold: f32) -> f32 {
+        if !(threshold > 0.0 && threshold < 1.0) {
+            return threshold;
+        }
+        match self {
+            Self::Identity => threshold,
+            Self::Temperature(temperature) => sigmoid(logit(threshold) * temperature),
+            Self::Platt { a, b } => sigmoid((logit(threshold) - b[class]) / a[class]),
+        }
+    }
+
+    /// Lowest raw score any class must exceed to be calibrated above its threshold
+    pub fn min_threshold(&self, conf_threshold: &ConfThreshold) -> f32 {
+        match self {
+            Self::Identity | Self::Temperature(_) => self.invert(0, conf_threshold.min()),
+            Self::Platt { a, .. } => (0..a.len())
+                .map(|class| self.invert(class, conf_threshold.of(class)))
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_calibration() {
+        let temperature = Calibration::temperature(2.0).unwrap();
+        assert!((temperature.apply(0, sigmoid(4.0)) - sigmoid(2.0)).abs() < 1e-6);
+        assert!((temperature.invert(0, sigmoid(2.0)) - sigmoid(4.0)).abs() < 1e-6);
+        assert_eq!(temperature.apply(0, 0.0), 0.0);
+        assert_eq!(temperature.apply(0, 1.0), 1.0);
+
+        let platt = Calibration::platt(vec![1.0, 0.5], vec![1.0, 0.0]).unwrap();
+        assert!((platt.apply(0, sigmoid(1.0)) - sigmoid(2.0)).abs() < 1e-6);
+        assert!((platt.apply(1, sigmoid(1.0)) - sigmoid(0.5)).abs() < 1e-6);
+        for class in 0..2 {
+            let raw = platt.invert(class, 0.7);
+            assert!((platt.apply(class, raw) - 0.7).abs() < 1e-6);
+        }
+        let thresholds = ConfThreshold::PerClass(vec![0.7, f32::INFINITY]);
+        assert_eq!(platt.min_threshold(&thresholds), platt.invert(0, 0.7));
+        assert!(platt.validate(2).is_ok());
+        assert!(platt.validate(3).is_err());
+
+        for calibration in [Calibration::Identity, temperature, platt] {
+            assert_eq!(Calibration::from_json(&calibration.to_json()).unwrap(), calibration);
+        }
+        assert!(Calibration::temperature(0.0).is_err());
+        assert!(Calibration::platt(vec![-1.0], vec![0.0]).is_err());
+        assert!(Calibration::from_json("{\"temperature\": 2, \"a\": [1], \"b\": [0]}").is_err());
+        assert!(Calibration::from_json("{\"scale\": 2}").is_err());
+    }
+}
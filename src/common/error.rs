@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// Errors of the Rust API, raised as Python exceptions by the bindings
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// An argument or an input tensor is invalid, raised as `ValueError`
+    InvalidArgument(String),
+    /// The postprocessor could not be set up, raised as `RuntimeError`
+    Runtime(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        Self::InvalidArgument(message.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgument(message) | Self::Runtime(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(feature = "python")]
+impl From<Error> for pyo3::PyErr {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidArgument(message) => pyo3::exceptions::PyValueError::new_err(message),
+            Error::Runtime(message) => pyo3::exceptions::PyRuntimeError::new_err(message),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use crate::common::ssd_postprocess::DetectionResults;
+use crate::common::{Error, Result};
+
+/// Names of the 80 contiguous COCO classes the detection models are trained on
+pub const COCO80_NAMES: [&str; 80] = [
+    "person",
+    "bicycle",
+    "car",
+    "motorcycle",
+    "airplane",
+    "bus",
+    "train",
+    "truck",
+    "boat",
+    "traffic light",
+    "fire hydrant",
+    "stop sign",
+    "parking meter",
+    "bench",
+    "bird",
+    "cat",
+    "dog",
+    "horse",
+    "sheep",
+    "cow",
+    "elephant",
+    "bear",
+    "zebra",
+    "giraffe",
+    "backpack",
+    "umbrella",
+    "handbag",
+    "tie",
+    "suitcase",
+    "frisbee",
+    "skis",
+    "snowboard",
+    "sports ball",
+    "kite",
+    "baseball bat",
+    "baseball glove",
+    "skateboard",
+    "surfboard",
+    "tennis racket",
+    "bottle",
+    "wine glass",
+    "cup",
+    "fork",
+    "knife",
+    "spoon",
+    "bowl",
+    "banana",
+    "apple",
+    "sandwich",
+    "orange",
+    "broccoli",
+    "carrot",
+    "hot dog",
+    "pizza",
+    "donut",
+    "cake",
+    "chair",
+    "couch",
+    "potted plant",
+    "bed",
+    "dining table",
+    "toilet",
+    "tv",
+    "laptop",
+    "mouse",
+    "remote",
+    "keyboard",
+    "cell phone",
+    "microwave",
+    "oven",
+    "toaster",
+    "sink",
+    "refrigerator",
+    "book",
+    "clock",
+    "vase",
+    "scissors",
+    "teddy bear",
+    "hair drier",
+    "toothbrush",
+];
+
+/// COCO category id of each of the 80 contiguous classes, the ids of the 91 categories of the
+/// original paper skipping those without instance annotations
+pub const COCO80_TO_COCO91: [i64; 80] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 27, 28,
+    31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55,
+    56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 67, 70, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 84,
+    85, 86, 87, 88, 89, 90,
+];
+
+/// Names and dataset category ids of the classes of a model, either being optional
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ClassTable {
+    pub names: Option<Vec<String>>,
+    pub category_ids: Option<Vec<i64>>,
+}
+
+impl ClassTable {
+    pub fn new(names: Option<Vec<String>>, category_ids: Option<Vec<i64>>) -> Result<Self> {
+        if let (Some(names), Some(category_ids)) = (&names, &category_ids) {
+            if names.len() != category_ids.len() {
+                return Err(Error::invalid(format!(
+                    "expected as many category ids as class names but got {} and {}",
+                    category_ids.len(),
+                    names.len()
+                )));
+            }
+        }
+        Ok(Self { names, category_ids })
+    }
+
+    /// Class names of the built-in table `name`, only "coco80" for now
+    pub fn builtin_names(name: &str) -> Result<Vec<String>> {
+        match name {
+            "coco80" => Ok(COCO80_NAMES.iter().map(|name| name.to_string()).collect()),
+            name => Err(Error::invalid(format!("class_names must be 'coco80' but got '{name}'"))),
+        }
+    }
+
+    /// Check the tables cover the `num_classes` classes of the model
+    pub fn validate(&self, num_classes: usize) -> Result<()> {
+        for (what, len) in [
+            ("class names", self.names.as_ref().map(Vec::len)),
+            ("category ids", self.category_ids.as_ref().map(Vec::len)),
+        ] {
+            if let Some(len) = len.filter(|&len| len != num_classes) {
+                return Err(Error::invalid(format!("expected {num_classes} {what} but got {len}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Name of `class`, None without names
+    pub fn label(&self, class: usize) -> Option<&str> {
+        self.names.as_ref()?.get(class).map(String::as_str)
+    }
+
+    /// Category id of `class`, the class itself without category ids
+    pub fn category_id(&self, class: usize) -> i64 {
+        match &self.category_ids {
+            Some(category_ids) => category_ids.get(class).copied().unwrap_or(class as i64),
+            None => class as i64,
+        }
+    }
+}
+
+/// Table given either as a list or as the name of a built-in one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Listed<T> {
+    Builtin(String),
+    List(Vec<T>),
+}
+
+/// Name of each class index, serialized as a list and read from a list or the name of a
+/// built-in table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Listed<String>")]
+pub struct ClassNames(pub Vec<String>);
+
+impl TryFrom<Listed<String>> for ClassNames {
+    type Error = Error;
+
+    fn try_from(names: Listed<String>) -> Result<Self> {
+        match names {
+            Listed::Builtin(name) => ClassTable::builtin_names(&name).map(Self),
+            Listed::List(names) => Ok(Self(names)),
+        }
+    }
+}
+
+/// Dataset category id of each class index, which the classes of the detections are remapped to
+/// when exported, serialized as a list and read from a list or the name of a built-in remap
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Listed<i64>")]
+pub struct CategoryIds(pub Vec<i64>);
+
+impl TryFrom<Listed<i64>> for CategoryIds {
+    type Error = Error;
+
+    fn try_from(ids: Listed<i64>) -> Result<Self> {
+        match ids {
+            Listed::Builtin(name) => Self::builtin(&name),
+            Listed::List(ids) => Ok(Self(ids)),
+        }
+    }
+}
+
+impl CategoryIds {
+    /// Category ids of the built-in remap `name`, only "coco91" for now
+    pub fn builtin(name: &str) -> Result<Self> {
+        match name {
+            "coco91" => Ok(Self(COCO80_TO_COCO91.to_vec())),
+            name => Err(Error::invalid(format!("category_ids must be 'coco91' but got '{name}'"))),
+        }
+    }
+
+    /// Category id of `class`, failing if the table has no entry for it
+    pub fn of(&self, class: usize) -> Result<i64> {
+        self.0.get(class).copied().ok_or_else(|| {
+            Error::invalid(format!("class {class} has no entry in the category table"))
+        })
+    }
+
+    /// Replace the class of each detection by its category id
+    pub fn remap(&self, results: &mut DetectionResults) -> Result<()> {
+        for r in results.iter_mut() {
+            r.class = self.of(r.class as usize)? as f32;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ssd_postprocess::{BoundingBox, DetectionResult};
+
+    #[test]
+    fn unittest_class_table() {
+        let names = ClassTable::builtin_names("coco80").unwrap();
+        let CategoryIds(category_ids) = CategoryIds::builtin("coco91").unwrap();
+        let table = ClassTable::new(Some(names), Some(category_ids)).unwrap();
+        assert_eq!(table.label(0), Some("person"));
+        assert_eq!(table.label(79), Some("toothbrush"));
+        assert_eq!(table.label(80), None);
+        assert_eq!(table.category_id(11), 13);
+        assert_eq!(table.category_id(79), 90);
+        assert!(table.validate(80).is_ok());
+        assert!(table.validate(1).is_err());
+
+        let unnamed = ClassTable::default();
+        assert_eq!((unnamed.label(3), unnamed.category_id(3)), (None, 3));
+        assert!(unnamed.validate(1).is_ok());
+        assert!(ClassTable::new(Some(vec!["a".to_string()]), Some(vec![1, 2])).is_err());
+        assert!(ClassTable::builtin_names("voc").is_err());
+    }
+
+    #[test]
+    fn unittest_category_ids() {
+        let detection = |class: f32| DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: 0.0, py1: 0.0, px2: 1.0, py2: 1.0 },
+            score: 0.5,
+            class,
+        };
+        let coco91 = CategoryIds::builtin("coco91").unwrap();
+        let mut results: DetectionResults = vec![detection(0.0), detection(12.0)].into();
+        coco91.remap(&mut results).unwrap();
+        assert_eq!(results.iter().map(|r| r.class).collect::<Vec<_>>(), [1.0, 14.0]);
+
+        let mut results: DetectionResults = vec![detection(80.0)].into();
+        assert!(coco91.remap(&mut results).is_err());
+        assert!(CategoryIds::builtin("coco80").is_err());
+    }
+}
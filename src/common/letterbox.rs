@@ -0,0 +1,178 @@
+use ndarray::{s, Array2};
+
+use crate::common::{Error, Result};
+
+/// Letterbox transform of an image of `original_shape` (height, width) into the network input,
+/// scaling it by `scale` while keeping its aspect ratio and then padding it by `pad_x` on the
+/// left and `pad_y` on the top
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub scale: f32,
+    pub pad_x: f32,
+    pub pad_y: f32,
+    pub original_shape: (f32, f32),
+}
+
+impl Letterbox {
+    /// The network input itself, as if no letterbox were applied
+    pub fn identity(input_shape: (f32, f32)) -> Self {
+        Self { scale: 1.0, pad_x: 0.0, pad_y: 0.0, original_shape: input_shape }
+    }
+
+    /// Letterbox fitting an image of `original_shape` into `input_shape`, both being (height,
+    /// width), padded equally on both sides as Ultralytics does
+    pub fn fit(input_shape: (f32, f32), original_shape: (f32, f32)) -> Self {
+        let (input_h, input_w) = input_shape;
+        let (original_h, original_w) = original_shape;
+        let scale = f32::min(input_h / original_h, input_w / original_w);
+        Self {
+            scale,
+            pad_x: (input_w - original_w * scale) / 2.0,
+            pad_y: (input_h - original_h * scale) / 2.0,
+            original_shape,
+        }
+    }
+
+    /// Letterbox of the given parameters, the original image being assumed to be padded equally
+    /// on both sides unless its shape is given
+    pub fn with_params(
+        input_shape: (f32, f32),
+        (scale, pad_x, pad_y): (f32, f32, f32),
+        original_shape: Option<(f32, f32)>,
+    ) -> Self {
+        let original_shape = original_shape.unwrap_or((
+            (input_shape.0 - 2.0 * pad_y) / scale,
+            (input_shape.1 - 2.0 * pad_x) / scale,
+        ));
+        Self { scale, pad_x, pad_y, original_shape }
+    }
+
+    /// Letterboxes of a batch, either given as (scale, pad_x, pad_y) or fitted from the original
+    /// image shapes, the identity if neither is given
+    pub fn batch(
+        batch_size: usize,
+        input_shape: (f32, f32),
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+    ) -> Result<Vec<Self>> {
+        let expect_batch = |len: usize| {
+            if len != batch_size {
+                return Err(Error::invalid(format!(
+                    "expected letterbox parameters of {batch_size} images but got {len}"
+                )));
+            }
+            Ok(())
+        };
+        let letterboxes: Vec<Self> = match (letterbox_params, original_shapes) {
+            (Some(params), shapes) => {
+                expect_batch(params.len())?;
+                if let Some(shapes) = &shapes {
+                    expect_batch(shapes.len())?;
+                }
+                params
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, p)| Self::with_params(input_shape, p, shapes.as_ref().map(|s| s[i])))
+                    .collect()
+            }
+            (None, Some(shapes)) => {
+                expect_batch(shapes.len())?;
+                shapes.into_iter().map(|shape| Self::fit(input_shape, shape)).collect()
+            }
+            (None, None) => return Ok(vec![Self::identity(input_shape); batch_size]),
+        };
+        letterboxes.iter().try_for_each(Self::validate)?;
+        Ok(letterboxes)
+    }
+
+    /// Check the letterbox maps boxes back into an original image of a positive shape, which
+    /// the shape derived from the parameters alone may not be
+    fn validate(&self) -> Result<()> {
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        if !positive(self.scale) {
+            return Err(Error::invalid(format!(
+                "letterbox scales must be positive but got {}",
+                self.scale
+            )));
+        }
+        if ![self.pad_x, self.pad_y].iter().all(|pad| pad.is_finite() && *pad >= 0.0) {
+            return Err(Error::invalid(format!(
+                "letterbox pads must be non-negative but got ({}, {})",
+                self.pad_x, self.pad_y
+            )));
+        }
+        let (height, width) = self.original_shape;
+        if !(positive(height) && positive(width)) {
+            return Err(Error::invalid(format!(
+                "original image shapes must be positive but got ({height}, {width})"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Map the boxes of detections whose rows start with (x1, y1, x2, y2) from the network input
+    /// back to the original image
+    pub fn invert(&self, detections: &mut Array2<f32>) {
+        detections.slice_mut(s![.., 0..4;2]).mapv_inplace(|x| (x - self.pad_x) / self.scale);
+        detections.slice_mut(s![.., 1..4;2]).mapv_inplace(|y| (y - self.pad_y) / self.scale);
+    }
+
+    /// Clamp the boxes of detections whose rows start with (x1, y1, x2, y2) into the original
+    /// image
+    pub fn clip(&self, detections: &mut Array2<f32>) {
+        let (height, width) = self.original_shape;
+        // Unlike clamp, max and min do not panic on a NaN or negative bound
+        detections.slice_mut(s![.., 0..4;2]).mapv_inplace(|x| x.max(0.0).min(width));
+        detections.slice_mut(s![.., 1..4;2]).mapv_inplace(|y| y.max(0.0).min(height));
+    }
+
+    /// Scale the boxes of detections whose rows start with (x1, y1, x2, y2) in the original
+    /// image down to fractions of its width and height
+    pub fn normalize(&self, detections: &mut Array2<f32>) {
+        let (height, width) = self.original_shape;
+        detections.slice_mut(s![.., 0..4;2]).mapv_inplace(|x| x / width);
+        detections.slice_mut(s![.., 1..4;2]).mapv_inplace(|y| y / height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn unittest_normalize() {
+        // 640x480 letterboxed into 640x640, padded by 80 on the top
+        let letterbox = Letterbox::fit((640.0, 640.0), (480.0, 640.0));
+        let mut detections = arr2(&[[64.0, 128.0, 320.0, 560.0, 0.9, 1.0]]);
+        letterbox.invert(&mut detections);
+        letterbox.normalize(&mut detections);
+        assert_eq!(detections, arr2(&[[0.1, 0.1, 0.5, 1.0, 0.9, 1.0]]));
+
+        let mut detections = arr2(&[[64.0, 128.0, 320.0, 560.0, 0.9, 1.0]]);
+        Letterbox::identity((640.0, 320.0)).normalize(&mut detections);
+        assert_eq!(detections, arr2(&[[0.2, 0.2, 1.0, 0.875, 0.9, 1.0]]));
+    }
+
+    #[test]
+    fn unittest_batch() {
+        let input_shape = (640.0, 640.0);
+        let batch = |shapes: Option<Vec<_>>, params: Option<Vec<_>>| {
+            Letterbox::batch(1, input_shape, shapes, params)
+        };
+        assert!(batch(Some(vec![(480.0, 640.0)]), None).is_ok());
+        assert!(batch(None, Some(vec![(0.5, 0.0, 80.0)])).is_ok());
+        for shape in [(0.0, 640.0), (-480.0, 640.0), (f32::NAN, 640.0), (480.0, f32::INFINITY)] {
+            assert!(batch(Some(vec![shape]), None).is_err(), "{shape:?}");
+        }
+        for params in [(0.0, 0.0, 0.0), (f32::NAN, 0.0, 0.0), (1.0, -1.0, 0.0), (1.0, 0.0, 400.0)] {
+            assert!(batch(None, Some(vec![params])).is_err(), "{params:?}");
+        }
+        assert!(batch(Some(vec![(f32::NAN, 640.0)]), Some(vec![(1.0, 0.0, 0.0)])).is_err());
+
+        let mut detections = arr2(&[[-10.0, 20.0, 700.0, 500.0, 0.9, 1.0]]);
+        Letterbox::fit(input_shape, (480.0, 640.0)).clip(&mut detections);
+        assert_eq!(detections, arr2(&[[0.0, 20.0, 640.0, 480.0, 0.9, 1.0]]));
+    }
+}
@@ -0,0 +1,332 @@
+//! Bridge of the `tracing` spans and events of the crate to the Python `logging` module
+//!
+//! Nothing is recorded unless a filter is set, from the `FURIOSA_POSTPROCESS_LOG` environment
+//! variable at import or with `set_log_filter`. A filter is a comma-separated list of directives
+//! `[target=]level` in the syntax of `RUST_LOG`, e.g. `info,yolo=debug`, targets being module
+//! paths with or without the crate prefix. Events go to the logger named after their module,
+//! e.g. `furiosa_native_postprocess.yolo`, and closed spans log how long they were open.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::Instant;
+
+use pyo3::prelude::*;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use super::{Error, Result};
+
+/// Environment variable of the filter set at import
+pub const LOG_ENV: &str = "FURIOSA_POSTPROCESS_LOG";
+
+const CRATE: &str = "furiosa_native_postprocess";
+
+/// Levels of targets, the most specific directive matching a target applying
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Filter {
+    /// (target, level), sorted by descending target length so that the first match is the most
+    /// specific one, the default level having an empty target
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Result<Self> {
+        let mut directives = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(|directive| {
+                let (target, level) = match directive.rsplit_once('=') {
+                    Some((target, level)) => (target.trim().replace('.', "::"), level.trim()),
+                    None => (String::new(), directive),
+                };
+                let level = level.parse::<LevelFilter>().map_err(|_| {
+                    Error::invalid(format!(
+                        "invalid log level {level:?} in {directive:?}, expected one of off, \
+                         error, warn, info, debug or trace"
+                    ))
+                })?;
+                Ok((target, level))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(Self { directives })
+    }
+
+    fn level(&self, target: &str) -> LevelFilter {
+        let relative = target.strip_prefix(CRATE).and_then(|t| t.strip_prefix("::"));
+        let matches = |prefix: &str, target: &str| {
+            target
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+        self.directives
+            .iter()
+            .find(|(prefix, _)| {
+                prefix.is_empty()
+                    || matches(prefix, target)
+                    || relative.is_some_and(|relative| matches(prefix, relative))
+            })
+            .map_or(LevelFilter::OFF, |&(_, level)| level)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= &self.level(metadata.target())
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.directives.iter().map(|&(_, level)| level).max().unwrap_or(LevelFilter::OFF)
+    }
+}
+
+static FILTER: RwLock<Option<Filter>> = RwLock::new(None);
+
+/// Fields of a span or an event formatted as ` key=value`, the message first and bare
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{value:?}"));
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.insert_str(0, value);
+        } else {
+            let _ = write!(self.0, " {}={value}", field.name());
+        }
+    }
+}
+
+struct Span {
+    metadata: &'static Metadata<'static>,
+    fields: String,
+    opened: Instant,
+    references: usize,
+}
+
+/// Subscriber forwarding to Python loggers
+#[derive(Default)]
+struct PythonLogger {
+    spans: Mutex<HashMap<u64, Span>>,
+    next_id: AtomicU64,
+}
+
+thread_local! {
+    /// Spans entered on the thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+impl PythonLogger {
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Span>> {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Names and fields of the spans entered on the thread, as `outer{a=1}:inner`
+    fn context(&self) -> String {
+        let spans = self.spans();
+        ENTERED.with(|entered| {
+            entered
+                .borrow()
+                .iter()
+                .filter_map(|id| spans.get(id))
+                .map(|span| match span.fields.trim_start() {
+                    "" => span.metadata.name().to_string(),
+                    fields => format!("{}{{{fields}}}", span.metadata.name()),
+                })
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+    }
+
+    fn log(metadata: &Metadata<'_>, message: String) {
+        // Events of threads outliving the interpreter are dropped
+        if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+            return;
+        }
+        let level = match *metadata.level() {
+            Level::TRACE => 5,
+            Level::DEBUG => 10,
+            Level::INFO => 20,
+            Level::WARN => 30,
+            Level::ERROR => 40,
+        };
+        let name = metadata.target().replace("::", ".");
+        Python::with_gil(|py| {
+            let logged = py
+                .import("logging")
+                .and_then(|logging| logging.call_method1("getLogger", (name,)))
+                .and_then(|logger| logger.call_method1("log", (level, message)));
+            if let Err(e) = logged {
+                e.print(py);
+            }
+        });
+    }
+}
+
+impl Subscriber for PythonLogger {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.enabled(metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        FILTER
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .is_some_and(|filter| filter.enabled(metadata))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        let filter = FILTER.read().unwrap_or_else(PoisonError::into_inner);
+        Some(filter.as_ref().map_or(LevelFilter::OFF, Filter::max_level))
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let span = Span {
+            metadata: attributes.metadata(),
+            fields: fields.0,
+            opened: Instant::now(),
+            references: 1,
+        };
+        self.spans().insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans().get_mut(&span.into_u64()) {
+            let mut fields = Fields(std::mem::take(&mut span.fields));
+            values.record(&mut fields);
+            span.fields = fields.0;
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let message = match self.context() {
+            context if context.is_empty() => fields.0,
+            context => format!("{context}: {}", fields.0),
+        };
+        Self::log(event.metadata(), message);
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        let id = span.into_u64();
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|&entered| entered == id) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some(span) = self.spans().get_mut(&id.into_u64()) {
+            span.references += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let mut spans = self.spans();
+        let Some(span) = spans.get_mut(&id.into_u64()) else { return false };
+        span.references -= 1;
+        if span.references > 0 {
+            return false;
+        }
+        let span = spans.remove(&id.into_u64()).expect("the span was just found");
+        drop(spans);
+        let message = format!(
+            "{}{} closed after {:?}",
+            span.metadata.name(),
+            span.fields,
+            span.opened.elapsed()
+        );
+        Self::log(span.metadata, message);
+        true
+    }
+}
+
+/// Set the filter of the events forwarded to Python, installing the bridge the first time, or
+/// forward nothing if `spec` is None
+///
+/// The bridge is not installed if the process already has a global subscriber.
+pub fn set_log_filter(spec: Option<&str>) -> Result<()> {
+    let filter = spec.map(Filter::parse).transpose()?;
+    *FILTER.write().unwrap_or_else(PoisonError::into_inner) = filter;
+    static INSTALLED: std::sync::Once = std::sync::Once::new();
+    INSTALLED.call_once(|| {
+        let _ = tracing::subscriber::set_global_default(PythonLogger::default());
+    });
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+/// Set the filter of `FURIOSA_POSTPROCESS_LOG`, if any
+pub(crate) fn init_from_env() -> Result<()> {
+    match std::env::var(LOG_ENV) {
+        Ok(spec) => set_log_filter(Some(&spec)),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Forward the tracing events of the postprocesses to the Python loggers named after their
+/// modules, e.g. `furiosa_native_postprocess.yolo`
+///
+/// The loggers still need a level low enough for the events to show, e.g. with
+/// `logging.basicConfig(level=logging.DEBUG)`. Spans log how long they were open when closed.
+///
+/// Args:
+///     filter (Optional[str]): Comma-separated directives `[target=]level` as `RUST_LOG`, e.g.
+///         "info,yolo=debug", None to forward nothing
+#[pyfunction]
+#[pyo3(name = "set_log_filter")]
+pub(crate) fn set_log_filter_py(filter: Option<&str>) -> PyResult<()> {
+    Ok(set_log_filter(filter)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_log_filter() {
+        let filter = Filter::parse("warn, yolo=debug,yolo.simd=off").unwrap();
+        assert_eq!(filter.level("furiosa_native_postprocess::yolo"), LevelFilter::DEBUG);
+        assert_eq!(filter.level("furiosa_native_postprocess::yolo::simd"), LevelFilter::OFF);
+        assert_eq!(filter.level("furiosa_native_postprocess::yolox"), LevelFilter::WARN);
+        assert_eq!(filter.level("chrome_layer"), LevelFilter::WARN);
+        assert_eq!(filter.max_level(), LevelFilter::DEBUG);
+
+        let filter = Filter::parse("furiosa_native_postprocess::common=trace").unwrap();
+        assert_eq!(filter.level("furiosa_native_postprocess::common::nms"), LevelFilter::TRACE);
+        assert_eq!(filter.level("furiosa_native_postprocess::yolo"), LevelFilter::OFF);
+
+        assert!(Filter::parse("yolo=loud").is_err());
+    }
+}
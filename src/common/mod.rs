@@ -1,99 +1,493 @@
+pub mod box_filter;
+pub mod box_format;
+pub mod calibration;
+pub mod error;
+pub mod labels;
+pub mod letterbox;
+#[cfg(feature = "python")]
+pub mod logging;
+#[cfg(feature = "python")]
+pub mod nms;
+#[cfg(feature = "python")]
+mod python;
+pub(crate) mod resize;
+pub mod roi;
 pub mod ssd_postprocess;
+pub mod threads;
+pub mod top_k;
+pub mod wbf;
+#[cfg(feature = "python")]
+pub(crate) mod worker;
 
-use numpy::{PyArray3, PyReadonlyArray3};
-use pyo3::types::PyList;
-use pyo3::{self, pyclass, pymethods, PyResult};
-use ssd_postprocess::{DetectionResult, DetectionResults};
+use std::cmp::Ordering;
+use std::time::Instant;
 
-#[pyclass]
-#[derive(Clone, Debug)]
-pub struct BoundingBox {
-    #[pyo3(get)]
-    left: f32,
-    #[pyo3(get)]
-    top: f32,
-    #[pyo3(get)]
-    right: f32,
-    #[pyo3(get)]
-    bottom: f32,
+pub use error::{Error, Result};
+#[cfg(feature = "python")]
+pub use python::*;
+
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::RustPostprocessor;
+
+/// Half-precision floating point formats, read from numpy arrays as their bit patterns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HalfFormat {
+    /// IEEE 754 binary16, i.e. `numpy.float16`
+    F16,
+    /// bfloat16 following the ml_dtypes convention
+    Bf16,
+}
+
+impl HalfFormat {
+    #[inline]
+    pub fn to_f32(self, bits: u16) -> f32 {
+        match self {
+            Self::F16 => f16_to_f32(bits),
+            Self::Bf16 => f32::from_bits((bits as u32) << 16),
+        }
+    }
+}
+
+#[inline]
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exponent {
+        // Zero and subnormals, mantissa * 2^-24
+        0 => {
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            if sign == 0 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        }
+        // Infinities and NaNs
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        // Rebias the exponent from 15 to 127
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Total order of scores from the highest to the lowest with NaNs last, to be tie-broken by the
+/// index of the candidates so that sorts are reproducible
+#[inline]
+pub fn descending(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.total_cmp(&a),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+/// Inverse of the sigmoid function, used to compare raw logits against a probability threshold
+#[inline]
+pub(crate) fn logit(p: f32) -> f32 {
+    (p / (1.0 - p)).ln()
+}
+
+#[inline]
+pub(crate) fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Sigmoid with exp approximated by a cubic of the fractional power of two, within 2e-4 of the
+/// exact sigmoid
+#[inline]
+pub(crate) fn fast_sigmoid(x: f32) -> f32 {
+    let t = (-x * std::f32::consts::LOG2_E).clamp(-126.0, 126.0);
+    let i = t.floor();
+    let f = t - i;
+    let fraction = 1.0 + f * (0.695_83 + f * (0.226_06 + f * 0.078_024));
+    let exp = f32::from_bits(((i as i32 + 127) << 23) as u32) * fraction;
+    1.0 / (1.0 + exp)
+}
+
+/// Affine quantization parameters of a tensor, either per-tensor or per-channel along its channel
+/// axis, which is the last one of YOLO outputs and the first one of SSD outputs, such that
+/// `value = (q - zero_point) * scale`
+#[derive(Debug, Clone)]
+pub struct Quantization {
+    pub scales: Vec<f32>,
+    pub zero_points: Vec<i32>,
+}
+
+impl Quantization {
+    pub fn new(scales: Vec<f32>, zero_points: Vec<i32>) -> Result<Self> {
+        if scales.is_empty() || scales.len() != zero_points.len() {
+            return Err(Error::invalid(format!(
+                "expected as many scales as zero points but got {} and {}",
+                scales.len(),
+                zero_points.len()
+            )));
+        }
+        if scales.iter().any(|&s| s.is_nan() || s <= 0.0) {
+            return Err(Error::invalid(format!("scales must be positive: {scales:?}")));
+        }
+        Ok(Self { scales, zero_points })
+    }
+
+    #[inline]
+    pub fn num_channels(&self) -> usize {
+        self.scales.len()
+    }
+
+    #[inline]
+    fn param_index(&self, channel: usize) -> usize {
+        if self.scales.len() == 1 {
+            0
+        } else {
+            channel
+        }
+    }
+
+    #[inline]
+    pub fn dequantize<T: Into<i32>>(&self, channel: usize, q: T) -> f32 {
+        let i = self.param_index(channel);
+        (q.into() - self.zero_points[i]) as f32 * self.scales[i]
+    }
+
+    /// Quantized threshold of `channel` such that `q > threshold_q` if and only if the
+    /// dequantized `q` exceeds `threshold`
+    #[inline]
+    pub fn quantize_threshold(&self, channel: usize, threshold: f32) -> i32 {
+        let i = self.param_index(channel);
+        (threshold / self.scales[i]).floor() as i32 + self.zero_points[i]
+    }
 }
 
-#[pymethods]
-impl BoundingBox {
-    fn __repr__(&self) -> String {
-        format!(
-            "BoundingBox(left: {}, top: {}, right: {}, bottom: {})",
-            self.left, self.top, self.right, self.bottom
-        )
+/// Confidence threshold shared by every class or given per class
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfThreshold {
+    Scalar(f32),
+    PerClass(Vec<f32>),
+}
+
+impl ConfThreshold {
+    /// Threshold of the given class
+    #[inline]
+    pub fn of(&self, class: usize) -> f32 {
+        match self {
+            Self::Scalar(threshold) => *threshold,
+            Self::PerClass(thresholds) => thresholds[class],
+        }
     }
 
-    fn __str__(&self) -> String {
-        format!(
-            "(left: {}, top: {}, right: {}, bottom: {})",
-            self.left, self.top, self.right, self.bottom
-        )
+    /// Lowest threshold of all classes, which a candidate must exceed to survive at all
+    pub fn min(&self) -> f32 {
+        match self {
+            Self::Scalar(threshold) => *threshold,
+            Self::PerClass(thresholds) => thresholds.iter().copied().fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    /// Threshold of the `num_classes` classes which excludes the classes not in `classes`
+    pub fn keep_classes(&self, classes: &[usize], num_classes: usize) -> Result<Self> {
+        if let Some(&class) = classes.iter().find(|&&c| c >= num_classes) {
+            return Err(Error::invalid(format!(
+                "class {class} is out of range for {num_classes} classes"
+            )));
+        }
+        let mut thresholds = vec![f32::INFINITY; num_classes];
+        for &class in classes {
+            thresholds[class] = self.of(class);
+        }
+        Ok(Self::PerClass(thresholds))
+    }
+
+    /// Threshold lowered to `low` for the classes of a higher one, the excluded classes staying
+    /// excluded
+    pub fn lowered(&self, low: f32) -> Self {
+        match self {
+            Self::Scalar(threshold) => Self::Scalar(threshold.min(low)),
+            Self::PerClass(thresholds) => Self::PerClass(
+                thresholds.iter().map(|&t| if t.is_finite() { t.min(low) } else { t }).collect(),
+            ),
+        }
+    }
+
+    pub fn validate(&self, num_classes: usize) -> Result<()> {
+        match self {
+            Self::PerClass(thresholds) if thresholds.len() != num_classes => {
+                Err(Error::invalid(format!(
+                    "expected {num_classes} per-class confidence thresholds but got {}",
+                    thresholds.len()
+                )))
+            }
+            _ => Ok(()),
+        }
     }
 }
 
-#[pyclass]
-#[derive(Debug)]
-pub struct PyDetectionResult {
-    #[pyo3(get)]
-    pub left: f32,
-    #[pyo3(get)]
-    pub right: f32,
-    #[pyo3(get)]
-    pub top: f32,
-    #[pyo3(get)]
-    pub bottom: f32,
-    #[pyo3(get)]
-    pub score: f32,
-    #[pyo3(get)]
-    pub class_id: i32,
+/// IoU threshold of NMS shared by every class or given per class
+///
+/// Boxes are suppressed by the threshold of the class of the box kept over them, which is also
+/// their own class unless NMS is class-agnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IouThreshold {
+    Scalar(f32),
+    PerClass(Vec<f32>),
 }
 
-#[pymethods]
-impl PyDetectionResult {
-    fn __repr__(&self) -> String {
-        format!("{self:?}")
+impl IouThreshold {
+    /// Threshold of the given class
+    #[inline]
+    pub fn of(&self, class: usize) -> f32 {
+        match self {
+            Self::Scalar(threshold) => *threshold,
+            Self::PerClass(thresholds) => thresholds[class],
+        }
     }
 
-    fn __str__(&self) -> String {
-        format!("{self:?}")
+    /// Lowest threshold of all classes
+    pub fn min(&self) -> f32 {
+        match self {
+            Self::Scalar(threshold) => *threshold,
+            Self::PerClass(thresholds) => thresholds.iter().copied().fold(f32::INFINITY, f32::min),
+        }
     }
+
+    pub fn validate(&self, num_classes: usize) -> Result<()> {
+        match self {
+            Self::PerClass(thresholds) if thresholds.len() != num_classes => {
+                Err(Error::invalid(format!(
+                    "expected {num_classes} per-class IoU thresholds but got {}",
+                    thresholds.len()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Suppression method of Non-Maximum Suppression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NmsMethod {
+    /// Discard boxes overlapping a kept box by more than the IoU threshold
+    Hard,
+    /// Soft-NMS decaying scores by `1 - iou` when overlapping by more than the IoU threshold
+    Linear,
+    /// Soft-NMS decaying scores by `exp(-iou^2 / sigma)`
+    Gaussian { sigma: f32 },
+    /// Discard boxes whose DIoU with a kept box exceeds the IoU threshold, which keeps adjacent
+    /// objects whose centers are apart
+    Diou,
+    /// Weighted Box Fusion, merging overlapping boxes instead of discarding them
+    Wbf,
 }
 
-impl PyDetectionResult {
-    pub fn new(r: DetectionResult) -> Self {
-        PyDetectionResult {
-            left: r.bbox.px1,
-            right: r.bbox.px2,
-            top: r.bbox.py1,
-            bottom: r.bbox.py2,
-            score: r.score,
-            class_id: r.class as i32,
+impl NmsMethod {
+    pub const DEFAULT_SIGMA: f32 = 0.5;
+
+    /// Parse the `nms_method` argument of `eval`, which is one of "hard", "linear", "gaussian",
+    /// "diou", or "wbf"
+    pub fn parse(method: Option<&str>, sigma: Option<f32>) -> Result<Self> {
+        match method.unwrap_or("hard") {
+            "hard" => Ok(Self::Hard),
+            "linear" => Ok(Self::Linear),
+            "gaussian" => {
+                let sigma = sigma.unwrap_or(Self::DEFAULT_SIGMA);
+                if !(sigma.is_finite() && sigma > 0.0) {
+                    return Err(Error::invalid(format!("sigma must be positive but got {sigma}")));
+                }
+                Ok(Self::Gaussian { sigma })
+            }
+            "diou" => Ok(Self::Diou),
+            "wbf" => Ok(Self::Wbf),
+            method => Err(Error::invalid(format!(
+                "nms_method must be one of 'hard', 'linear', 'gaussian', 'diou', or 'wbf' but \
+                 got '{method}'"
+            ))),
+        }
+    }
+
+    /// Overlap between two boxes the method suppresses by
+    #[inline]
+    pub fn overlap(
+        &self,
+        a: &ssd_postprocess::BoundingBox,
+        b: &ssd_postprocess::BoundingBox,
+    ) -> f32 {
+        match self {
+            Self::Diou => a.diou(b),
+            _ => a.iou(b),
+        }
+    }
+
+    /// Factor applied to the score of a box overlapping a kept box by `overlap`
+    #[inline]
+    pub fn decay(&self, overlap: f32, iou_threshold: f32) -> f32 {
+        match *self {
+            Self::Hard | Self::Diou if overlap > iou_threshold => 0.0,
+            Self::Linear if overlap > iou_threshold => 1.0 - overlap,
+            Self::Gaussian { sigma } => f32::exp(-overlap * overlap / sigma),
+            _ => 1.0,
         }
     }
 }
 
-pub type PyDetectionResults = Vec<PyDetectionResult>;
+/// Greedy NMS over `(score, box index)` candidates supporting every `NmsMethod`
+///
+/// Repeatedly keeps the highest scoring candidate and decays the scores of the remaining ones
+/// according to `method`, dropping those falling below `score_threshold`. Returns the kept
+/// candidates with their decayed scores in selection order.
+pub(crate) fn soft_suppress(
+    candidates: Vec<(f32, usize)>,
+    boxes: &[ssd_postprocess::BoundingBox],
+    method: NmsMethod,
+    iou_threshold: f32,
+    score_threshold: f32,
+    max_det: usize,
+) -> Vec<(f32, usize)> {
+    soft_suppress_until(
+        candidates,
+        boxes,
+        method,
+        |_| iou_threshold,
+        score_threshold,
+        max_det,
+        None,
+    )
+    .0
+}
+
+/// `soft_suppress` which stops at `deadline`, also returning whether it stopped early, the
+/// IoU threshold being the one of the box kept at each step
+pub(crate) fn soft_suppress_until(
+    mut candidates: Vec<(f32, usize)>,
+    boxes: &[ssd_postprocess::BoundingBox],
+    method: NmsMethod,
+    iou_threshold: impl Fn(usize) -> f32,
+    score_threshold: f32,
+    max_det: usize,
+    deadline: Option<Instant>,
+) -> (Vec<(f32, usize)>, bool) {
+    candidates.retain(|&(score, _)| score >= score_threshold);
+
+    let mut results = Vec::new();
+    while !candidates.is_empty() && results.len() < max_det {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return (results, true);
+        }
+        let (position, _) = candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| descending(a.0, b.0).then(a.1.cmp(&b.1)))
+            .unwrap();
+        let (score, cur_idx) = candidates.swap_remove(position);
+        results.push((score, cur_idx));
 
-impl From<DetectionResults> for PyDetectionResults {
-    fn from(value: DetectionResults) -> Self {
-        value.0.into_iter().map(PyDetectionResult::new).collect()
+        let cur_box = &boxes[cur_idx];
+        let iou_threshold = iou_threshold(cur_idx);
+        candidates.retain_mut(|(score, i)| {
+            *score *= method.decay(method.overlap(cur_box, &boxes[*i]), iou_threshold);
+            *score >= score_threshold
+        });
     }
+
+    (results, false)
 }
 
-pub(crate) fn downcast_to_f32(inputs: &PyList) -> PyResult<Vec<PyReadonlyArray3<'_, f32>>> {
-    let mut ret = Vec::with_capacity(inputs.len());
+/// Class-aware suppression of arbitrary detections with any `NmsMethod`, Weighted Box Fusion
+/// fusing them instead, keeping the `max_det` highest scoring ones in descending score order
+pub fn suppress_detections(
+    detections: &ssd_postprocess::DetectionResults,
+    method: NmsMethod,
+    iou_threshold: f32,
+    score_threshold: f32,
+    max_det: usize,
+) -> ssd_postprocess::DetectionResults {
+    if method == NmsMethod::Wbf {
+        return wbf::fuse_single(detections.clone(), &IouThreshold::Scalar(iou_threshold), max_det);
+    }
+    let column = |f: fn(&ssd_postprocess::DetectionResult) -> f32| {
+        detections.iter().map(f).collect::<ndarray::Array1<f32>>()
+    };
+    let mut boxes = DetectionBoxes::new(
+        column(|r| r.bbox.px1),
+        column(|r| r.bbox.py1),
+        column(|r| r.bbox.px2),
+        column(|r| r.bbox.py2),
+        column(|r| r.score),
+        column(|r| r.class),
+    );
+    let (kept, _) = RustPostprocessor::suppress(
+        &mut boxes,
+        method,
+        &IouThreshold::Scalar(iou_threshold),
+        score_threshold,
+        None,
+        max_det,
+        false,
+        None,
+    );
+    kept.into_iter()
+        .map(|i| ssd_postprocess::DetectionResult { score: boxes.scores[i], ..detections[i] })
+        .collect::<Vec<_>>()
+        .into()
+}
 
-    for tensor in inputs.into_iter() {
-        let tensor = tensor.downcast::<PyArray3<f32>>()?.readonly();
+/// Check the input tensors of the detection layers share the batch size of the first one, their
+/// first axis
+#[cfg(feature = "python")]
+pub(crate) fn validate_batch<S, D>(inputs: &[ndarray::ArrayBase<S, D>]) -> Result<()>
+where
+    S: ndarray::Data,
+    D: ndarray::Dimension,
+{
+    let Some(first) = inputs.first() else { return Ok(()) };
+    let batch_size = first.shape()[0];
+    for (i, input) in inputs.iter().enumerate().skip(1) {
+        if input.shape()[0] != batch_size {
+            return Err(Error::invalid(format!(
+                "input {i} has a batch of {} but input 0 has {batch_size}",
+                input.shape()[0]
+            )));
+        }
+    }
+    Ok(())
+}
 
-        ret.push(tensor);
+/// Check the class and box outputs of an anchor-based head with `num_anchors` anchors per cell,
+/// one of each per level of the given (height, width) grid, all of a batch of the same size
+#[cfg(feature = "python")]
+pub(crate) fn validate_anchor_heads(
+    grids: &[(usize, usize)],
+    num_anchors: usize,
+    cls_outputs: &[ndarray::ArrayView4<'_, f32>],
+    box_outputs: &[ndarray::ArrayView4<'_, f32>],
+) -> Result<()> {
+    let num_levels = grids.len();
+    if cls_outputs.len() != num_levels || box_outputs.len() != num_levels {
+        return Err(Error::invalid(format!(
+            "expected {num_levels} class and box outputs but got {} and {}",
+            cls_outputs.len(),
+            box_outputs.len()
+        )));
     }
+    let Some(first) = cls_outputs.first() else { return Ok(()) };
 
-    Ok(ret)
+    let batch_size = first.shape()[0];
+    for (i, (&(height, width), cls, reg)) in
+        itertools::izip!(grids, cls_outputs, box_outputs).enumerate()
+    {
+        let &[n, c, h, w] = cls.shape() else { unreachable!() };
+        if n != batch_size || c % num_anchors != 0 || (h, w) != (height, width) {
+            return Err(Error::invalid(format!(
+                "class output {i} must have shape ({batch_size}, {num_anchors} * num_classes, \
+                 {height}, {width}) but got {:?}",
+                cls.shape()
+            )));
+        }
+        if reg.shape() != [batch_size, num_anchors * 4, height, width] {
+            return Err(Error::invalid(format!(
+                "box output {i} must have shape ({batch_size}, {}, {height}, {width}) but got {:?}",
+                num_anchors * 4,
+                reg.shape()
+            )));
+        }
+    }
+    Ok(())
 }
 
 // u8slice
@@ -121,8 +515,78 @@ pub unsafe fn uninitialized_vec<T>(size: usize) -> Vec<T> {
 
 #[cfg(test)]
 mod tests {
+    use super::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
     use super::*;
 
+    #[test]
+    fn unittest_half_to_f32() {
+        assert_eq!(HalfFormat::F16.to_f32(0x3c00), 1.0);
+        assert_eq!(HalfFormat::F16.to_f32(0xc000), -2.0);
+        assert_eq!(HalfFormat::F16.to_f32(0x7bff), 65504.0);
+        assert_eq!(HalfFormat::F16.to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(HalfFormat::F16.to_f32(0x7c00), f32::INFINITY);
+        assert!(HalfFormat::F16.to_f32(0x7e00).is_nan());
+        assert_eq!(HalfFormat::Bf16.to_f32(0x3f80), 1.0);
+        assert_eq!(HalfFormat::Bf16.to_f32(0xc040), -3.0);
+    }
+
+    #[test]
+    fn unittest_detection_results_into_array() {
+        let results: DetectionResults = vec![DetectionResult {
+            index: 3.0,
+            bbox: ssd_postprocess::BoundingBox { px1: 1.0, py1: 2.0, px2: 3.0, py2: 4.0 },
+            score: 0.5,
+            class: 7.0,
+        }]
+        .into();
+        let array = results.into_array();
+        assert_eq!(array, ndarray::arr2(&[[1.0, 2.0, 3.0, 4.0, 0.5, 7.0]]));
+    }
+
+    #[test]
+    fn unittest_fast_sigmoid() {
+        for i in -2000..=2000 {
+            let x = i as f32 / 100.0;
+            assert!((fast_sigmoid(x) - sigmoid(x)).abs() < 2e-4, "{x}");
+        }
+        assert_eq!(fast_sigmoid(1000.0), 1.0);
+        assert!(fast_sigmoid(-1000.0) < 1e-37);
+        assert!(fast_sigmoid(f32::NAN).is_nan());
+    }
+
+    #[test]
+    fn unittest_descending_scores() {
+        let scores = [0.5, f32::NAN, 0.9, 0.5, f32::NEG_INFINITY, 0.1];
+        let mut indices: Vec<usize> = (0..scores.len()).collect();
+        indices.sort_unstable_by(|&i, &j| descending(scores[i], scores[j]).then(i.cmp(&j)));
+        assert_eq!(indices, [2, 0, 3, 5, 4, 1]);
+    }
+
+    #[test]
+    fn unittest_iou_variants() {
+        let a = BoundingBox { px1: 0.0, py1: 0.0, px2: 2.0, py2: 2.0 };
+        let b = BoundingBox { px1: 1.0, py1: 0.0, px2: 3.0, py2: 2.0 };
+        let c = BoundingBox { px1: 4.0, py1: 0.0, px2: 6.0, py2: 2.0 };
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-4;
+        assert!(close(a.iou(&b), 1.0 / 3.0));
+        assert!(close(a.giou(&b), 1.0 / 3.0));
+        // Disjoint boxes are told apart by how far they are
+        assert!(close(a.giou(&c), -1.0 / 3.0));
+        assert!(close(a.diou(&c), -16.0 / 40.0));
+        // Boxes of the same aspect ratio have no CIoU penalty on top of DIoU
+        assert!(close(a.ciou(&b), a.diou(&b)));
+        assert!(close(a.iou(&a), 1.0) && close(a.giou(&a), 1.0) && close(a.ciou(&a), 1.0));
+    }
+
+    #[test]
+    fn unittest_gaussian_sigma() {
+        let gaussian = |sigma: f32| NmsMethod::parse(Some("gaussian"), Some(sigma));
+        assert_eq!(gaussian(0.3).unwrap(), NmsMethod::Gaussian { sigma: 0.3 });
+        for sigma in [0.0, -0.5, f32::NAN, f32::INFINITY] {
+            assert!(gaussian(sigma).is_err(), "{sigma}");
+        }
+    }
+
     #[test]
     fn unittest_slice_layout() {
         let v = vec![9u8; 1000];
@@ -131,4 +595,37 @@ mod tests {
         assert_eq!(ptr, slice.as_ptr());
         assert_eq!(len, slice.len());
     }
+
+    #[test]
+    #[cfg(feature = "python")]
+    fn unittest_validate_batch() {
+        let layers =
+            [ndarray::Array4::<f32>::zeros((2, 6, 4, 4)), ndarray::Array4::zeros((2, 6, 2, 2))];
+        assert!(validate_batch(&layers).is_ok());
+        assert!(validate_batch::<ndarray::OwnedRepr<f32>, ndarray::Ix4>(&[]).is_ok());
+        let layers =
+            [ndarray::Array4::<f32>::zeros((2, 6, 4, 4)), ndarray::Array4::zeros((1, 6, 2, 2))];
+        assert!(validate_batch(&layers).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "python")]
+    fn unittest_validate_anchor_heads() {
+        let cls =
+            [ndarray::Array4::<f32>::zeros((2, 18, 4, 4)), ndarray::Array4::zeros((2, 18, 2, 2))];
+        let reg =
+            [ndarray::Array4::<f32>::zeros((2, 36, 4, 4)), ndarray::Array4::zeros((2, 36, 2, 2))];
+        fn views(arrays: &[ndarray::Array4<f32>]) -> Vec<ndarray::ArrayView4<'_, f32>> {
+            arrays.iter().map(|a| a.view()).collect()
+        }
+        let grids = [(4, 4), (2, 2)];
+        assert!(validate_anchor_heads(&grids, 9, &views(&cls), &views(&reg)).is_ok());
+        assert!(validate_anchor_heads(&grids, 9, &views(&cls[..1]), &views(&reg[..1])).is_err());
+        assert!(validate_anchor_heads(&grids, 9, &views(&cls), &views(&reg[..1])).is_err());
+        assert!(validate_anchor_heads(&[(4, 4), (1, 1)], 9, &views(&cls), &views(&reg)).is_err());
+        assert!(validate_anchor_heads(&grids, 4, &views(&cls), &views(&reg)).is_err());
+        let batch =
+            [ndarray::Array4::<f32>::zeros((2, 18, 4, 4)), ndarray::Array4::zeros((1, 18, 2, 2))];
+        assert!(validate_anchor_heads(&grids, 9, &views(&batch), &views(&reg)).is_err());
+    }
 }
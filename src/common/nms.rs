@@ -0,0 +1,91 @@
+use ndarray::{Array1, ArrayView1, ArrayView2};
+use numpy::{PyArray1, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+use super::threads;
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor, DEFAULT_MAX_DET};
+
+/// Class indices given as either float32 or integer arrays
+fn extract_classes(classes: &PyAny) -> PyResult<Array1<f32>> {
+    if let Ok(classes) = classes.extract::<PyReadonlyArray1<'_, f32>>() {
+        return Ok(classes.as_array().to_owned());
+    }
+    if let Ok(classes) = classes.extract::<PyReadonlyArray1<'_, i64>>() {
+        return Ok(classes.as_array().mapv(|c| c as f32));
+    }
+    if let Ok(classes) = classes.extract::<PyReadonlyArray1<'_, i32>>() {
+        return Ok(classes.as_array().mapv(|c| c as f32));
+    }
+    Err(PyTypeError::new_err("classes must be a float32, int32 or int64 array"))
+}
+
+fn detection_boxes(
+    boxes: ArrayView2<'_, f32>,
+    scores: ArrayView1<'_, f32>,
+    classes: Array1<f32>,
+) -> DetectionBoxes {
+    let column = |i: usize| boxes.column(i).to_owned();
+    DetectionBoxes::new(column(0), column(1), column(2), column(3), scores.to_owned(), classes)
+}
+
+/// Non-Maximum Suppression of arbitrary boxes
+///
+/// Args:
+///     boxes (numpy.ndarray): Boxes of shape (N, 4) with rows (x1, y1, x2, y2)
+///     scores (numpy.ndarray): Scores of shape (N,)
+///     classes (Optional[numpy.ndarray]): Class indices of shape (N,), boxes of different classes
+///         never suppressing each other, a single class if None
+///     iou_threshold (float): IoU threshold
+///     agnostic (Optional[bool]): Whether to suppress across classes, default is False
+///     epsilon (Optional[float]): Epsilon for numerical stability
+///     max_det (Optional[int]): Maximum number of kept boxes, default is 300
+///
+/// Returns:
+///     numpy.ndarray: Indices of the kept boxes in descending score order
+#[pyfunction]
+#[pyo3(signature = (boxes, scores, classes, iou_threshold, agnostic=None, epsilon=None, max_det=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn nms(
+    py: Python<'_>,
+    boxes: PyReadonlyArray2<'_, f32>,
+    scores: PyReadonlyArray1<'_, f32>,
+    classes: Option<&PyAny>,
+    iou_threshold: f32,
+    agnostic: Option<bool>,
+    epsilon: Option<f32>,
+    max_det: Option<usize>,
+) -> PyResult<Py<PyArray1<i64>>> {
+    let (boxes, scores) = (boxes.as_array(), scores.as_array());
+    let num_boxes = boxes.shape()[0];
+    if boxes.shape()[1] != 4 {
+        return Err(PyValueError::new_err(format!(
+            "boxes must have shape (N, 4) but got {:?}",
+            boxes.shape()
+        )));
+    }
+    let classes = match classes {
+        Some(classes) => extract_classes(classes)?,
+        None => Array1::zeros(num_boxes),
+    };
+    if scores.len() != num_boxes || classes.len() != num_boxes {
+        return Err(PyValueError::new_err(format!(
+            "expected {num_boxes} scores and classes but got {} and {}",
+            scores.len(),
+            classes.len()
+        )));
+    }
+
+    let indices = threads::allow_threads(py, || {
+        let dbox = detection_boxes(boxes, scores, classes);
+        RustPostprocessor::nms(
+            &dbox,
+            iou_threshold,
+            epsilon,
+            max_det.unwrap_or(DEFAULT_MAX_DET),
+            agnostic.unwrap_or(false),
+        )
+    });
+    Ok(PyArray1::from_iter(py, indices.into_iter().map(|i| i as i64)).to_owned())
+}
@@ -0,0 +1,542 @@
+use std::os::raw::c_long;
+use std::sync::Arc;
+
+use ndarray::{Array2, Array3, Dimension, Ix3};
+use numpy::{PyArray1, PyArray2, PyArray3, PyReadonlyArray, PyReadonlyArray2};
+use pyo3::exceptions::{PyIndexError, PyRuntimeWarning, PyTypeError, PyValueError};
+use pyo3::types::{PyBool, PyDict, PyList, PySlice, PyString};
+use pyo3::{
+    pyclass, pymethods, FromPyObject, IntoPy, Py, PyAny, PyErr, PyObject, PyRef, PyResult, Python,
+    ToPyObject,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::labels::{CategoryIds, ClassTable};
+use super::roi::Roi;
+use super::ssd_postprocess::{
+    self, DetectionResult, DetectionResults, PriorConfig, PriorConvention,
+};
+use super::{wbf, ConfThreshold, HalfFormat, IouThreshold, Quantization};
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct BoundingBox {
+    #[pyo3(get)]
+    left: f32,
+    #[pyo3(get)]
+    top: f32,
+    #[pyo3(get)]
+    right: f32,
+    #[pyo3(get)]
+    bottom: f32,
+}
+
+#[pymethods]
+impl BoundingBox {
+    fn __repr__(&self) -> String {
+        format!(
+            "BoundingBox(left: {}, top: {}, right: {}, bottom: {})",
+            self.left, self.top, self.right, self.bottom
+        )
+    }
+
+    fn __str__(&self) -> String {
+        format!(
+            "(left: {}, top: {}, right: {}, bottom: {})",
+            self.left, self.top, self.right, self.bottom
+        )
+    }
+}
+
+#[pyclass]
+#[derive(Debug)]
+pub struct PyDetectionResult {
+    #[pyo3(get)]
+    pub left: f32,
+    #[pyo3(get)]
+    pub right: f32,
+    #[pyo3(get)]
+    pub top: f32,
+    #[pyo3(get)]
+    pub bottom: f32,
+    #[pyo3(get)]
+    pub score: f32,
+    #[pyo3(get)]
+    pub class_id: i32,
+    /// Name of the class, None unless the postprocessor has class names
+    #[pyo3(get)]
+    pub label: Option<String>,
+    /// Dataset category id of the class, the class itself unless the postprocessor has
+    /// category ids
+    #[pyo3(get)]
+    pub category_id: i64,
+}
+
+#[pymethods]
+impl PyDetectionResult {
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn __str__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl PyDetectionResult {
+    pub fn new(r: DetectionResult) -> Self {
+        Self::labeled(r, &ClassTable::default())
+    }
+
+    /// Detection labeled with the name and category id of its class in `table`
+    pub fn labeled(r: DetectionResult, table: &ClassTable) -> Self {
+        let class = r.class as usize;
+        PyDetectionResult {
+            left: r.bbox.px1,
+            right: r.bbox.px2,
+            top: r.bbox.py1,
+            bottom: r.bbox.py2,
+            score: r.score,
+            class_id: r.class as i32,
+            label: table.label(class).map(str::to_string),
+            category_id: table.category_id(class),
+        }
+    }
+}
+
+/// Detections of a single image, a sequence of PyDetectionResult
+///
+/// The fields of every detection are also available at once as arrays: `boxes` of shape (N, 4)
+/// with rows (x1, y1, x2, y2), `scores` of shape (N,) and `classes` of shape (N,), along with
+/// the `labels` and `category_ids` of the classes in the table of the postprocessor.
+#[pyclass(sequence)]
+#[derive(Debug, Clone, Default)]
+pub struct PyDetectionResults(pub DetectionResults, pub Arc<ClassTable>);
+
+impl From<DetectionResults> for PyDetectionResults {
+    fn from(value: DetectionResults) -> Self {
+        Self(value, Arc::default())
+    }
+}
+
+#[pymethods]
+impl PyDetectionResults {
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn __getitem__(&self, py: Python<'_>, index: &PyAny) -> PyResult<PyObject> {
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let indices = slice.indices(self.0.len() as c_long)?;
+            let results = (0..indices.slicelength)
+                .map(|i| self.0[(indices.start + i * indices.step) as usize])
+                .collect::<Vec<_>>();
+            return Ok(Self(results.into(), Arc::clone(&self.1)).into_py(py));
+        }
+        let i: isize = index.extract()?;
+        let len = self.0.len() as isize;
+        let position = if i < 0 { i + len } else { i };
+        if !(0..len).contains(&position) {
+            return Err(PyIndexError::new_err(format!(
+                "index {i} is out of range for {len} detections"
+            )));
+        }
+        Ok(PyDetectionResult::labeled(self.0[position as usize], &self.1).into_py(py))
+    }
+
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let results = self.0.iter().map(|&r| PyDetectionResult::labeled(r, &self.1).into_py(py));
+        Ok(PyList::new(py, results).call_method0("__iter__")?.into())
+    }
+
+    #[getter]
+    fn boxes(&self, py: Python<'_>) -> Py<PyArray2<f32>> {
+        let boxes = Array2::from_shape_fn((self.0.len(), 4), |(i, j)| {
+            let bbox = &self.0[i].bbox;
+            [bbox.px1, bbox.py1, bbox.px2, bbox.py2][j]
+        });
+        PyArray2::from_owned_array(py, boxes).to_owned()
+    }
+
+    #[getter]
+    fn scores(&self, py: Python<'_>) -> Py<PyArray1<f32>> {
+        PyArray1::from_iter(py, self.0.iter().map(|r| r.score)).to_owned()
+    }
+
+    #[getter]
+    fn classes(&self, py: Python<'_>) -> Py<PyArray1<i32>> {
+        PyArray1::from_iter(py, self.0.iter().map(|r| r.class as i32)).to_owned()
+    }
+
+    #[getter]
+    fn labels(&self) -> Vec<Option<String>> {
+        self.0.iter().map(|r| self.1.label(r.class as usize).map(str::to_string)).collect()
+    }
+
+    #[getter]
+    fn category_ids(&self, py: Python<'_>) -> Py<PyArray1<i64>> {
+        PyArray1::from_iter(py, self.0.iter().map(|r| self.1.category_id(r.class as usize)))
+            .to_owned()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyDetectionResults(num_detections: {})", self.0.len())
+    }
+}
+
+/// Instance segmentation results of a single image
+#[pyclass]
+pub struct PySegmentationResults {
+    /// Detections of shape (N, 6), each row being (x1, y1, x2, y2, score, class)
+    #[pyo3(get)]
+    pub detections: Py<PyArray2<f32>>,
+    /// Mask coefficients of shape (N, num_masks)
+    #[pyo3(get)]
+    pub coefficients: Py<PyArray2<f32>>,
+    /// Binary masks of shape (N, H, W) at prototype resolution
+    #[pyo3(get)]
+    pub masks: Py<PyArray3<u8>>,
+}
+
+#[pymethods]
+impl PySegmentationResults {
+    fn __len__(&self, py: Python<'_>) -> usize {
+        self.detections.as_ref(py).shape()[0]
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PySegmentationResults(num_detections: {}, mask_shape: {:?})",
+            self.__len__(py),
+            &self.masks.as_ref(py).shape()[1..]
+        )
+    }
+}
+
+/// Pose estimation results of a single image
+#[pyclass]
+pub struct PyPoseResults {
+    /// Detections of shape (N, 6), each row being (x1, y1, x2, y2, score, class)
+    #[pyo3(get)]
+    pub detections: Py<PyArray2<f32>>,
+    /// Keypoints of shape (N, K, 3), each row being (x, y, visibility)
+    #[pyo3(get)]
+    pub keypoints: Py<PyArray3<f32>>,
+}
+
+#[pymethods]
+impl PyPoseResults {
+    fn __len__(&self, py: Python<'_>) -> usize {
+        self.detections.as_ref(py).shape()[0]
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PyPoseResults(num_detections: {}, num_keypoints: {})",
+            self.__len__(py),
+            self.keypoints.as_ref(py).shape()[1]
+        )
+    }
+}
+
+/// Oriented bounding box detection results of a single image
+#[pyclass]
+pub struct PyObbResults {
+    /// Detections of shape (N, 7), each row being (cx, cy, w, h, angle, score, class) with the
+    /// angle in radians
+    #[pyo3(get)]
+    pub detections: Py<PyArray2<f32>>,
+    /// Box corners of shape (N, 4, 2)
+    #[pyo3(get)]
+    pub corners: Py<PyArray3<f32>>,
+}
+
+#[pymethods]
+impl PyObbResults {
+    fn __len__(&self, py: Python<'_>) -> usize {
+        self.detections.as_ref(py).shape()[0]
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!("PyObbResults(num_detections: {})", self.__len__(py))
+    }
+}
+
+impl HalfFormat {
+    /// Format of the given numpy array, None if it is not a half-precision one
+    pub(crate) fn of(array: &PyAny) -> PyResult<Option<Self>> {
+        let name: String = array.getattr("dtype")?.getattr("name")?.extract()?;
+        Ok(match name.as_str() {
+            "float16" => Some(Self::F16),
+            "bfloat16" => Some(Self::Bf16),
+            _ => None,
+        })
+    }
+
+    /// Zero-copy view of a half-precision numpy array as uint16
+    pub(crate) fn view_bits<D: Dimension>(array: &PyAny) -> PyResult<PyReadonlyArray<'_, u16, D>> {
+        array.call_method1("view", ("uint16",))?.extract()
+    }
+}
+
+/// Convert the 3D tensors of `inputs` into f32 arrays mapped by `f`, reading float16 and
+/// bfloat16 tensors as well as float32 ones
+pub(crate) fn map_to_f32(inputs: &PyList, f: impl Fn(f32) -> f32) -> PyResult<Vec<Array3<f32>>> {
+    inputs
+        .iter()
+        .map(|tensor| match HalfFormat::of(tensor)? {
+            None => Ok(tensor.downcast::<PyArray3<f32>>()?.readonly().as_array().mapv(&f)),
+            Some(format) => {
+                let bits = HalfFormat::view_bits::<Ix3>(tensor)?;
+                let converted = bits.as_array().mapv(|b| f(format.to_f32(b)));
+                Ok(converted)
+            }
+        })
+        .collect()
+}
+
+/// Warn with a RuntimeWarning that the decode of `images` reached `limit` candidates, so that
+/// further ones were dropped and their detections may be missing
+pub(crate) fn warn_overflow(py: Python<'_>, images: &[usize], limit: usize) -> PyResult<()> {
+    if images.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "the decode of images {images:?} of the batch reached {limit} candidates and dropped \
+         the remaining ones; raise the confidence threshold or max_boxes"
+    );
+    PyErr::warn(py, py.get_type::<PyRuntimeWarning>(), &message, 1)
+}
+
+/// Extract the detections of one image, given either as an array of shape (N, 6) with rows
+/// (x1, y1, x2, y2, score, class), as PyDetectionResults or as a list of PyDetectionResult
+pub(crate) fn extract_detections(detections: &PyAny) -> PyResult<DetectionResults> {
+    if let Ok(results) = detections.extract::<PyRef<'_, PyDetectionResults>>() {
+        return Ok(results.0.clone());
+    }
+    if let Ok(array) = detections.extract::<PyReadonlyArray2<'_, f32>>() {
+        let array = array.as_array();
+        if array.shape()[1] != 6 {
+            return Err(PyValueError::new_err(format!(
+                "detections must have shape (N, 6) but got {:?}",
+                array.shape()
+            )));
+        }
+        return Ok(wbf::from_rows(array));
+    }
+
+    let results = detections.extract::<Vec<PyRef<'_, PyDetectionResult>>>()?;
+    Ok(results
+        .iter()
+        .map(|r| DetectionResult {
+            index: 0.0,
+            bbox: ssd_postprocess::BoundingBox {
+                px1: r.left,
+                py1: r.top,
+                px2: r.right,
+                py2: r.bottom,
+            },
+            score: r.score,
+            class: r.class_id as f32,
+        })
+        .collect::<Vec<_>>()
+        .into())
+}
+
+/// Extract every object as `T`, e.g. input tensors of a given dtype
+pub(crate) fn extract_all<'py, T: FromPyObject<'py>>(objects: &[&'py PyAny]) -> PyResult<Vec<T>> {
+    objects.iter().map(|o| o.extract()).collect()
+}
+
+/// Quantization parameters of each tensor, given as scalars or per channel
+pub(crate) fn extract_quantization(
+    scales: &[&PyAny],
+    zero_points: &[&PyAny],
+) -> PyResult<Vec<Quantization>> {
+    if scales.len() != zero_points.len() {
+        return Err(PyValueError::new_err(format!(
+            "expected as many scales as zero points but got {} and {}",
+            scales.len(),
+            zero_points.len()
+        )));
+    }
+    scales.iter().zip(zero_points).map(|(s, z)| Quantization::extract(s, z)).collect()
+}
+
+impl Quantization {
+    /// Extract per-tensor parameters given as scalars or per-channel ones given as sequences
+    pub(crate) fn extract(scale: &PyAny, zero_point: &PyAny) -> PyResult<Self> {
+        let scales = scale.extract::<f32>().map(|s| vec![s]).or_else(|_| scale.extract())?;
+        let zero_points =
+            zero_point.extract::<i32>().map(|z| vec![z]).or_else(|_| zero_point.extract())?;
+        Ok(Self::new(scales, zero_points)?)
+    }
+}
+
+impl<'source> FromPyObject<'source> for ConfThreshold {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<f32>() {
+            Ok(threshold) => Ok(Self::Scalar(threshold)),
+            Err(_) => Ok(Self::PerClass(ob.extract()?)),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for IouThreshold {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<f32>() {
+            Ok(threshold) => Ok(Self::Scalar(threshold)),
+            Err(_) => Ok(Self::PerClass(ob.extract()?)),
+        }
+    }
+}
+
+/// Category ids given as a list or as the name of a built-in remap
+impl<'source> FromPyObject<'source> for CategoryIds {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>() {
+            Ok(name) => Ok(Self::builtin(name)?),
+            Err(_) => Ok(Self(ob.extract()?)),
+        }
+    }
+}
+
+/// ROI given as a rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices
+impl<'source> FromPyObject<'source> for Roi {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(vertices) = ob.extract::<Vec<[f32; 2]>>() {
+            return Ok(Roi::polygon(vertices)?);
+        }
+        match ob.extract::<[f32; 4]>() {
+            Ok([x1, y1, x2, y2]) => Ok(Roi::rect(x1, y1, x2, y2)?),
+            Err(_) => Err(PyValueError::new_err(
+                "ROI must be a rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices",
+            )),
+        }
+    }
+}
+
+/// Prior config given as a dict of "feature_maps", "scales", "aspect_ratios", "convention"
+/// ("caffe" or "tensorflow"), and the optional "reduce_lowest_layer" and "clip" flags
+impl<'source> FromPyObject<'source> for PriorConfig {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let config: &PyDict = ob.downcast()?;
+        let get = |key: &str| -> PyResult<&PyAny> {
+            config
+                .get_item(key)?
+                .ok_or_else(|| PyValueError::new_err(format!("prior config must contain {key:?}")))
+        };
+        let flag = |key: &str| -> PyResult<bool> {
+            config.get_item(key)?.map_or(Ok(false), |value| value.extract())
+        };
+        let convention = match get("convention")?.extract::<&str>()? {
+            "caffe" => PriorConvention::Caffe,
+            "tensorflow" => {
+                PriorConvention::TensorFlow { reduce_lowest_layer: flag("reduce_lowest_layer")? }
+            }
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "prior convention must be \"caffe\" or \"tensorflow\" but got {other:?}"
+                )))
+            }
+        };
+        Ok(Self {
+            feature_maps: get("feature_maps")?.extract()?,
+            scales: get("scales")?.extract()?,
+            aspect_ratios: get("aspect_ratios")?.extract()?,
+            convention,
+            clip: flag("clip")?,
+        })
+    }
+}
+
+impl PriorConfig {
+    /// Dict of the config as extracted from Python
+    pub(crate) fn to_py_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let config = PyDict::new(py);
+        config.set_item("feature_maps", &self.feature_maps)?;
+        config.set_item("scales", &self.scales)?;
+        config.set_item("aspect_ratios", &self.aspect_ratios)?;
+        match self.convention {
+            PriorConvention::Caffe => config.set_item("convention", "caffe")?,
+            PriorConvention::TensorFlow { reduce_lowest_layer } => {
+                config.set_item("convention", "tensorflow")?;
+                config.set_item("reduce_lowest_layer", reduce_lowest_layer)?;
+            }
+        }
+        config.set_item("clip", self.clip)?;
+        Ok(config)
+    }
+}
+
+/// JSON value of a Python object made of dicts, sequences, strings, numbers, booleans and None,
+/// numpy arrays and scalars included
+fn to_json_value(ob: &PyAny) -> PyResult<Value> {
+    if ob.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(flag) = ob.downcast::<PyBool>() {
+        return Ok(Value::Bool(flag.is_true()));
+    }
+    if let Ok(text) = ob.downcast::<PyString>() {
+        return Ok(Value::String(text.to_str()?.to_string()));
+    }
+    if let Ok(dict) = ob.downcast::<PyDict>() {
+        return dict
+            .iter()
+            .map(|(key, value)| Ok((key.extract::<String>()?, to_json_value(value)?)))
+            .collect::<PyResult<_>>()
+            .map(Value::Object);
+    }
+    if let Ok(integer) = ob.extract::<i64>() {
+        return Ok(Value::from(integer));
+    }
+    if let Ok(number) = ob.extract::<f64>() {
+        return Ok(Value::from(number));
+    }
+    match ob.iter() {
+        Ok(items) => {
+            items.map(|item| to_json_value(item?)).collect::<PyResult<_>>().map(Value::Array)
+        }
+        Err(_) => {
+            Err(PyTypeError::new_err(format!("{} is not serializable", ob.get_type().name()?)))
+        }
+    }
+}
+
+/// Python object of a JSON value, objects being dicts and arrays lists
+fn from_json_value(py: Python<'_>, value: &Value) -> PyObject {
+    match value {
+        Value::Null => py.None(),
+        Value::Bool(flag) => flag.to_object(py),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => integer.to_object(py),
+            None => number.as_f64().to_object(py),
+        },
+        Value::String(text) => text.to_object(py),
+        Value::Array(items) => {
+            PyList::new(py, items.iter().map(|item| from_json_value(py, item))).to_object(py)
+        }
+        Value::Object(members) => {
+            let dict = PyDict::new(py);
+            for (key, value) in members {
+                dict.set_item(key, from_json_value(py, value)).unwrap();
+            }
+            dict.to_object(py)
+        }
+    }
+}
+
+/// Deserialize a Python object as [`to_json_value`] reads it, a ValueError being raised if it
+/// does not describe a `T`
+pub(crate) fn from_py<T: DeserializeOwned>(ob: &PyAny) -> PyResult<T> {
+    serde_json::from_value(to_json_value(ob)?).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Serialize `value` as Python dicts, lists and scalars
+pub(crate) fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let value = serde_json::to_value(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(from_json_value(py, &value))
+}
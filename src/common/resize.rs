@@ -0,0 +1,44 @@
+use ndarray::{ArrayView3, ArrayViewMut3};
+
+/// A region of an image in pixels, as the half-open [x1, x2) x [y1, y2)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Region {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+/// Bilinear resize of a region of an HWC image into `out`, sampling pixel centers as OpenCV's
+/// `INTER_LINEAR` does and replicating the border pixels of the region
+///
+/// `out` must have as many channels as `image`, and the region must hold at least a pixel of
+/// the image.
+pub(crate) fn resize_bilinear(
+    image: ArrayView3<'_, u8>,
+    region: Region,
+    mut out: ArrayViewMut3<'_, u8>,
+) {
+    let (height, width, channels) = image.dim();
+    let (out_h, out_w, _) = out.dim();
+    // Source indices and weight of the second source pixel along an axis
+    let sample = |o: usize, start: f32, end: f32, len: usize, size: usize| {
+        let last = (end.ceil() - 1.0).min((size - 1) as f32);
+        let s = start + (o as f32 + 0.5) * (end - start) / len as f32 - 0.5;
+        let s = s.clamp(start.max(0.0), last);
+        let i = s as usize;
+        (i, (i + 1).min(last as usize), s - i as f32)
+    };
+    let xs = (0..out_w).map(|x| sample(x, region.x1, region.x2, out_w, width)).collect::<Vec<_>>();
+    for y in 0..out_h {
+        let (y0, y1, wy) = sample(y, region.y1, region.y2, out_h, height);
+        for (x, &(x0, x1, wx)) in xs.iter().enumerate() {
+            for c in 0..channels {
+                let top = image[(y0, x0, c)] as f32 * (1.0 - wx) + image[(y0, x1, c)] as f32 * wx;
+                let bottom =
+                    image[(y1, x0, c)] as f32 * (1.0 - wx) + image[(y1, x1, c)] as f32 * wx;
+                out[(y, x, c)] = (top * (1.0 - wy) + bottom * wy).round() as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::common::letterbox::Letterbox;
+use crate::common::{Error, Result};
+
+/// Region of interest of an image, outside of which the centers of the candidates are dropped
+#[derive(Debug, Clone, PartialEq)]
+pub enum Roi {
+    /// (x1, y1, x2, y2), boundaries included
+    Rect([f32; 4]),
+    /// Vertices in order, a point being inside by the even-odd rule
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl Roi {
+    pub fn rect(x1: f32, y1: f32, x2: f32, y2: f32) -> Result<Self> {
+        if !(x1 <= x2 && y1 <= y2) {
+            return Err(Error::invalid(format!(
+                "ROI must have x1 <= x2 and y1 <= y2 but got ({x1}, {y1}, {x2}, {y2})"
+            )));
+        }
+        Ok(Self::Rect([x1, y1, x2, y2]))
+    }
+
+    pub fn polygon(vertices: Vec<[f32; 2]>) -> Result<Self> {
+        if vertices.len() < 3 {
+            return Err(Error::invalid(format!(
+                "ROI polygon must have at least 3 vertices but got {}",
+                vertices.len()
+            )));
+        }
+        if vertices.iter().flatten().any(|v| !v.is_finite()) {
+            return Err(Error::invalid("ROI polygon vertices must be finite"));
+        }
+        Ok(Self::Polygon(vertices))
+    }
+
+    /// Whether (x, y) is inside the region
+    #[inline]
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Self::Rect([x1, y1, x2, y2]) => *x1 <= x && x <= *x2 && *y1 <= y && y <= *y2,
+            Self::Polygon(vertices) => {
+                // Crossings of a ray going right from the point
+                let mut inside = false;
+                let mut previous = vertices[vertices.len() - 1];
+                for &current in vertices {
+                    let ([xi, yi], [xj, yj]) = (current, previous);
+                    if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    previous = current;
+                }
+                inside
+            }
+        }
+    }
+
+    /// Region in the network input of a region of the original image letterboxed by `letterbox`
+    pub fn letterboxed(&self, letterbox: &Letterbox) -> Self {
+        let Letterbox { scale, pad_x, pad_y, .. } = *letterbox;
+        let map = |[x, y]: [f32; 2]| [x * scale + pad_x, y * scale + pad_y];
+        match self {
+            Self::Rect([x1, y1, x2, y2]) => {
+                let ([x1, y1], [x2, y2]) = (map([*x1, *y1]), map([*x2, *y2]));
+                Self::Rect([x1, y1, x2, y2])
+            }
+            Self::Polygon(vertices) => Self::Polygon(vertices.iter().copied().map(map).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_roi_contains() {
+        let rect = Roi::rect(10.0, 20.0, 30.0, 40.0).unwrap();
+        assert!(rect.contains(10.0, 40.0));
+        assert!(!rect.contains(9.0, 30.0));
+        assert!(Roi::rect(30.0, 20.0, 10.0, 40.0).is_err());
+
+        // L shape missing the top right quadrant of the square (0, 0)-(2, 2)
+        let polygon = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [0.0, 2.0]];
+        let polygon = Roi::polygon(polygon.to_vec()).unwrap();
+        assert!(polygon.contains(0.5, 0.5));
+        assert!(polygon.contains(1.5, 1.5));
+        assert!(!polygon.contains(1.5, 0.5));
+        assert!(!polygon.contains(-0.5, 1.5));
+        assert!(Roi::polygon(vec![[0.0, 0.0], [1.0, 1.0]]).is_err());
+
+        let letterbox =
+            Letterbox { scale: 0.5, pad_x: 0.0, pad_y: 8.0, original_shape: (4.0, 4.0) };
+        assert_eq!(rect.letterboxed(&letterbox), Roi::Rect([5.0, 18.0, 15.0, 28.0]));
+    }
+}
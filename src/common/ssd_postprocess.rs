@@ -1,6 +1,10 @@
 use std::ops::{Deref, DerefMut};
 use std::{mem, slice};
 
+use ndarray::{s, Array2, Array3, ArrayView3};
+
+use crate::common::{Error, Quantization, Result};
+
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
 pub struct BoundingBox {
@@ -74,6 +78,41 @@ impl BoundingBox {
         let overlap = cw * ch;
         overlap / (self.area() + other.area() - overlap + 0.00001f32)
     }
+
+    /// Distance-IoU, i.e. IoU penalized by the squared distance between the box centers
+    /// normalized by the squared diagonal of the smallest enclosing box
+    #[inline]
+    pub fn diou(&self, other: &Self) -> f32 {
+        let cw = f32::max(self.px2, other.px2) - f32::min(self.px1, other.px1);
+        let ch = f32::max(self.py2, other.py2) - f32::min(self.py1, other.py1);
+        let distance = (self.pcx() - other.pcx()).powi(2) + (self.pcy() - other.pcy()).powi(2);
+        self.iou(other) - distance / (cw * cw + ch * ch + 0.00001f32)
+    }
+
+    /// Generalized IoU, i.e. IoU penalized by the area of the smallest enclosing box not
+    /// covered by the union of the boxes
+    #[inline]
+    pub fn giou(&self, other: &Self) -> f32 {
+        let cw = f32::max(self.px2, other.px2) - f32::min(self.px1, other.px1);
+        let ch = f32::max(self.py2, other.py2) - f32::min(self.py1, other.py1);
+        let iw = f32::max(0.0, f32::min(self.px2, other.px2) - f32::max(self.px1, other.px1));
+        let ih = f32::max(0.0, f32::min(self.py2, other.py2) - f32::max(self.py1, other.py1));
+        let union = self.area() + other.area() - iw * ih + 0.00001f32;
+        let enclosing = cw * ch + 0.00001f32;
+        self.iou(other) - (enclosing - union) / enclosing
+    }
+
+    /// Complete-IoU, i.e. DIoU further penalized by the difference of the aspect ratios
+    #[inline]
+    pub fn ciou(&self, other: &Self) -> f32 {
+        let iou = self.iou(other);
+        let v = 4.0 / (std::f32::consts::PI * std::f32::consts::PI)
+            * (f32::atan(other.pw() / (other.ph() + 0.00001f32))
+                - f32::atan(self.pw() / (self.ph() + 0.00001f32)))
+            .powi(2);
+        let alpha = v / (v - iou + 1.00001f32);
+        self.diou(other) - alpha * v
+    }
 }
 
 #[repr(C)]
@@ -151,6 +190,152 @@ impl CenteredBox {
     }
 }
 
+/// How the priors of a feature map location follow from its scale and aspect ratios
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorConvention {
+    /// Caffe SSD and its PyTorch ports, MLPerf SSD-ResNet34 among them: a square prior of the
+    /// scale of the feature map, a square one of the geometric mean of its scale and the next,
+    /// then a prior of each aspect ratio followed by one of its inverse
+    Caffe,
+    /// `SsdAnchorGenerator` of the TensorFlow Object Detection API, as in MLPerf SSD-MobileNet: a
+    /// prior of each aspect ratio at the scale of the feature map, then a square one of the
+    /// geometric mean of its scale and the next. With `reduce_lowest_layer`, the first feature
+    /// map only has a square prior of scale 0.1 and priors of aspect ratios 2 and 1/2.
+    TensorFlow { reduce_lowest_layer: bool },
+}
+
+/// Declarative description of the prior boxes of an SSD head, from which they are generated in
+/// the layout of its box tensors: by feature map, then prior, then row, then column
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorConfig {
+    /// Sides of the square feature maps
+    pub feature_maps: Vec<usize>,
+    /// Scale of the priors of each feature map relative to the image, followed by the scale
+    /// past the last feature map
+    pub scales: Vec<f32>,
+    /// Aspect ratios, width over height, of the priors of each feature map
+    pub aspect_ratios: Vec<Vec<f32>>,
+    pub convention: PriorConvention,
+    /// Clamp the centers and sizes of the priors into [0, 1]
+    pub clip: bool,
+}
+
+impl PriorConfig {
+    /// Priors of MLPerf SSD-MobileNet for 300x300 images
+    pub fn ssd_mobilenet() -> Self {
+        const ASPECT_RATIOS: [f32; 5] = [1.0, 2.0, 0.5, 3.0, 1.0 / 3.0];
+        Self {
+            feature_maps: vec![19, 10, 5, 3, 2, 1],
+            scales: vec![0.2, 0.35, 0.5, 0.65, 0.8, 0.95, 1.0],
+            aspect_ratios: vec![ASPECT_RATIOS.to_vec(); 6],
+            convention: PriorConvention::TensorFlow { reduce_lowest_layer: true },
+            clip: false,
+        }
+    }
+
+    /// Priors of MLPerf SSD-ResNet34 for 1200x1200 images
+    pub fn ssd_resnet34() -> Self {
+        Self {
+            feature_maps: vec![50, 25, 13, 7, 3, 3],
+            scales: [84.0, 180.0, 396.0, 612.0, 828.0, 1044.0, 1260.0]
+                .into_iter()
+                .map(|size| size / 1200.0)
+                .collect(),
+            aspect_ratios: vec![
+                vec![2.0],
+                vec![2.0, 3.0],
+                vec![2.0, 3.0],
+                vec![2.0, 3.0],
+                vec![2.0],
+                vec![2.0],
+            ],
+            convention: PriorConvention::Caffe,
+            clip: true,
+        }
+    }
+
+    /// Number of priors at each location of each feature map
+    pub fn priors_per_location(&self) -> Vec<usize> {
+        (0..self.feature_maps.len())
+            .map(|map| {
+                let num_ratios = self.aspect_ratios.get(map).map_or(0, Vec::len);
+                match self.convention {
+                    PriorConvention::Caffe => 2 + 2 * num_ratios,
+                    PriorConvention::TensorFlow { reduce_lowest_layer: true } if map == 0 => 3,
+                    PriorConvention::TensorFlow { .. } => num_ratios + 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Widths and heights of the priors of a location of feature map `map`
+    fn shapes(&self, map: usize) -> Vec<(f64, f64)> {
+        let scale = self.scales[map] as f64;
+        let interpolated = (scale * self.scales[map + 1] as f64).sqrt();
+        let ratios = self.aspect_ratios[map].iter().map(|&ratio| (ratio as f64).sqrt());
+        match self.convention {
+            PriorConvention::Caffe => [(scale, scale), (interpolated, interpolated)]
+                .into_iter()
+                .chain(ratios.flat_map(|r| [(scale * r, scale / r), (scale / r, scale * r)]))
+                .collect(),
+            PriorConvention::TensorFlow { reduce_lowest_layer: true } if map == 0 => {
+                let r = 2f64.sqrt();
+                vec![(0.1, 0.1), (scale * r, scale / r), (scale / r, scale * r)]
+            }
+            PriorConvention::TensorFlow { .. } => ratios
+                .map(|r| (scale * r, scale / r))
+                .chain([(interpolated, interpolated)])
+                .collect(),
+        }
+    }
+
+    /// Generate the priors, failing if the scales or aspect ratios do not match the feature maps
+    pub fn generate(&self) -> Result<Vec<CenteredBox>> {
+        let num_maps = self.feature_maps.len();
+        if self.scales.len() != num_maps + 1 {
+            return Err(Error::invalid(format!(
+                "expected {} scales for {num_maps} feature maps but got {}",
+                num_maps + 1,
+                self.scales.len()
+            )));
+        }
+        if self.aspect_ratios.len() != num_maps {
+            return Err(Error::invalid(format!(
+                "expected aspect ratios of {num_maps} feature maps but got {}",
+                self.aspect_ratios.len()
+            )));
+        }
+        if let Some(ratio) =
+            self.aspect_ratios.iter().flatten().find(|&&ratio| !ratio.is_finite() || ratio <= 0.0)
+        {
+            return Err(Error::invalid(format!(
+                "aspect ratios must be positive and finite but got {ratio}"
+            )));
+        }
+
+        let clip = |v: f64| if self.clip { v.clamp(0.0, 1.0) } else { v };
+        let mut priors = Vec::new();
+        for (map, &side) in self.feature_maps.iter().enumerate() {
+            for (w, h) in self.shapes(map) {
+                for y in 0..side {
+                    for x in 0..side {
+                        priors.push(CenteredBox {
+                            pcy: clip((y as f64 + 0.5) / side as f64) as f32,
+                            pcx: clip((x as f64 + 0.5) / side as f64) as f32,
+                            ph: clip(h) as f32,
+                            pw: clip(w) as f32,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(priors)
+    }
+}
+
+/// Number of f32 fields of a `DetectionResult`
+pub const FIELDS: usize = mem::size_of::<DetectionResult>() / mem::size_of::<f32>();
+
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
 pub struct DetectionResult {
@@ -205,4 +390,205 @@ impl DetectionResults {
             )
         }
     }
+
+    /// Convert into the fields of the results, rows of (index, y1, x1, y2, x2, score, class),
+    /// reusing their allocation
+    pub fn into_fields(self) -> Vec<f32> {
+        let (ptr, len, capacity) = self.0.into_raw_parts();
+        // SAFETY: `DetectionResult` is `repr(C)` and made of f32 fields only, so the allocation
+        // has the size and alignment of `capacity * FIELDS` f32
+        unsafe { Vec::from_raw_parts(ptr as *mut f32, len * FIELDS, capacity * FIELDS) }
+    }
+
+    /// Convert into an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class), reusing
+    /// the allocation of the results instead of copying them
+    pub fn into_array(self) -> Array2<f32> {
+        let len = self.0.len();
+        let mut fields = self.into_fields();
+        // Bounding boxes are stored as (y1, x1, y2, x2)
+        for row in fields.chunks_exact_mut(FIELDS) {
+            row.swap(1, 2);
+            row.swap(3, 4);
+        }
+        Array2::from_shape_vec((len, FIELDS), fields)
+            .expect("shape matches the number of fields")
+            .slice_move(s![.., 1..])
+    }
+}
+
+/// Class score tensors of shape (channel, y, x) of the feature maps of an SSD head
+pub trait ScoreMaps: Sync {
+    /// Score of `channel` at (y, x) of feature map `map`, None if it is below `threshold`
+    fn score_at_least(
+        &self,
+        map: usize,
+        channel: usize,
+        y: usize,
+        x: usize,
+        threshold: f32,
+    ) -> Option<f32>;
+}
+
+impl ScoreMaps for [Array3<f32>] {
+    #[inline]
+    fn score_at_least(
+        &self,
+        map: usize,
+        channel: usize,
+        y: usize,
+        x: usize,
+        threshold: f32,
+    ) -> Option<f32> {
+        let score = self[map][[channel, y, x]];
+        (score >= threshold).then_some(score)
+    }
+}
+
+/// Quantized score tensors, of which only the values that may reach the threshold are
+/// dequantized and activated
+pub struct QuantizedScoreMaps<'a, 'v, T> {
+    maps: &'a [ArrayView3<'v, T>],
+    quantization: &'a [Quantization],
+    activation: fn(f32) -> f32,
+    /// Quantized value of each channel of each map below which the activated score is below
+    /// the threshold
+    thresholds: Vec<Vec<i32>>,
+}
+
+impl<'a, 'v, T: Copy + Into<i32>> QuantizedScoreMaps<'a, 'v, T> {
+    /// Scores given by `activation` of the dequantized values, those below `min_value` before
+    /// the activation being skipped without dequantizing them
+    pub fn new(
+        maps: &'a [ArrayView3<'v, T>],
+        quantization: &'a [Quantization],
+        activation: fn(f32) -> f32,
+        min_value: f32,
+    ) -> Self {
+        let thresholds = maps
+            .iter()
+            .zip(quantization)
+            .map(|(map, q)| {
+                (0..map.shape()[0]).map(|c| q.quantize_threshold(c, min_value)).collect()
+            })
+            .collect();
+        Self { maps, quantization, activation, thresholds }
+    }
+}
+
+impl<T: Copy + Sync + Into<i32>> ScoreMaps for QuantizedScoreMaps<'_, '_, T> {
+    #[inline]
+    fn score_at_least(
+        &self,
+        map: usize,
+        channel: usize,
+        y: usize,
+        x: usize,
+        threshold: f32,
+    ) -> Option<f32> {
+        let q = self.maps[map][[channel, y, x]];
+        if q.into() < self.thresholds[map][channel] {
+            return None;
+        }
+        let score = (self.activation)(self.quantization[map].dequantize(channel, q));
+        (score >= threshold).then_some(score)
+    }
+}
+
+/// Dequantize a tensor of shape (channel, y, x), mapping the values by `f` in the same pass
+pub fn dequantize_map<T: Copy + Into<i32>>(
+    tensor: ArrayView3<'_, T>,
+    quantization: &Quantization,
+    f: impl Fn(f32) -> f32,
+) -> Array3<f32> {
+    Array3::from_shape_fn(tensor.raw_dim(), |(c, y, x)| {
+        f(quantization.dequantize(c, tensor[(c, y, x)]))
+    })
+}
+
+/// Check that each of the `name` tensors has its quantization parameters, either per tensor or
+/// one per channel
+pub fn validate_quantized<T>(
+    name: &str,
+    tensors: &[ArrayView3<'_, T>],
+    quantization: &[Quantization],
+) -> Result<()> {
+    if tensors.len() != quantization.len() {
+        return Err(Error::invalid(format!(
+            "expected quantization parameters of {} {name} tensors but got {}",
+            tensors.len(),
+            quantization.len()
+        )));
+    }
+    for (i, (tensor, q)) in tensors.iter().zip(quantization).enumerate() {
+        let channels = tensor.shape()[0];
+        if q.num_channels() != 1 && q.num_channels() != channels {
+            return Err(Error::invalid(format!(
+                "{name} tensor {i} has {channels} channels but {} quantization parameters",
+                q.num_channels()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Priors the postprocessors used to load, of which the centered ones are stored transposed
+    fn precomputed(bytes: &[u8], centered: bool) -> Vec<CenteredBox> {
+        bytes
+            .chunks_exact(16)
+            .map(|b| {
+                let v = |i: usize| f32::from_le_bytes(b[i * 4..i * 4 + 4].try_into().unwrap());
+                if centered {
+                    CenteredBox { pcy: v(0), pcx: v(1), ph: v(2), pw: v(3) }.into_transposed()
+                } else {
+                    BoundingBox { py1: v(0), px1: v(1), py2: v(2), px2: v(3) }.into()
+                }
+            })
+            .collect()
+    }
+
+    fn assert_close(generated: &[CenteredBox], expected: &[CenteredBox]) {
+        assert_eq!(generated.len(), expected.len());
+        for (i, (a, b)) in generated.iter().zip(expected).enumerate() {
+            let diff = a.to_vec().iter().zip(b.to_vec()).map(|(a, b)| (a - b).abs()).sum::<f32>();
+            assert!(diff < 1e-5, "prior {i}: {a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn unittest_ssd_resnet34_priors() {
+        let config = PriorConfig::ssd_resnet34();
+        assert_eq!(config.priors_per_location(), [4, 6, 6, 6, 4, 4]);
+        let expected =
+            precomputed(include_bytes!("../../models/ssd_large_precomputed_priors"), true);
+        assert_close(&config.generate().unwrap(), &expected);
+    }
+
+    #[test]
+    fn unittest_ssd_mobilenet_priors() {
+        let config = PriorConfig::ssd_mobilenet();
+        let per_location = config.priors_per_location();
+        assert_eq!(per_location, [3, 6, 6, 6, 6, 6]);
+        // Precomputed by location, then prior
+        let expected =
+            precomputed(include_bytes!("../../models/ssd_small_precomputed_priors"), false);
+        let mut reordered = Vec::with_capacity(expected.len());
+        let mut base = 0;
+        for (&side, &num_priors) in config.feature_maps.iter().zip(&per_location) {
+            for prior in 0..num_priors {
+                for location in 0..side * side {
+                    reordered.push(expected[base + location * num_priors + prior]);
+                }
+            }
+            base += side * side * num_priors;
+        }
+        assert_close(&config.generate().unwrap(), &reordered);
+
+        let mut invalid = config;
+        invalid.scales.pop();
+        assert!(invalid.generate().is_err());
+    }
 }
@@ -0,0 +1,160 @@
+//! Thread pool the postprocesses of the crate run their parallel loops in
+//!
+//! By default they use the global rayon pool, one thread per core. Latency-critical deployments
+//! can bound it and pin its threads to cores left free by the inference runtime.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use super::{Error, Result};
+
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+/// Pin the calling thread to `core`
+#[cfg(target_os = "linux")]
+fn pin_to_core(core: usize) -> std::io::Result<()> {
+    // SAFETY: the set is zero-initialized plain data, only written within its CPU_SETSIZE bits
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Build a pool of `num_threads` threads, the i-th one pinned to `cores[i % cores.len()]` if
+/// given
+pub fn build_pool(num_threads: usize, cores: Option<&[usize]>) -> Result<ThreadPool> {
+    if num_threads == 0 {
+        return Err(Error::invalid("num_threads must be positive"));
+    }
+    let mut builder = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(|i| format!("postprocess-{i}"));
+    if let Some(cores) = cores {
+        builder = builder.start_handler(pinning(cores)?);
+    }
+    builder.build().map_err(|e| Error::Runtime(e.to_string()))
+}
+
+#[cfg(target_os = "linux")]
+fn pinning(cores: &[usize]) -> Result<impl Fn(usize) + Send + Sync + 'static> {
+    if cores.is_empty() {
+        return Err(Error::invalid("cores must not be empty"));
+    }
+    if let Some(core) = cores.iter().find(|&&core| core >= libc::CPU_SETSIZE as usize) {
+        return Err(Error::invalid(format!(
+            "cores must be below {} but got {core}",
+            libc::CPU_SETSIZE
+        )));
+    }
+    let cores = cores.to_vec();
+    Ok(move |i: usize| {
+        let core = cores[i % cores.len()];
+        if let Err(e) = pin_to_core(core) {
+            tracing::warn!("failed to pin postprocess thread {i} to core {core}: {e}");
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pinning(_cores: &[usize]) -> Result<impl Fn(usize) + Send + Sync + 'static> {
+    Err::<fn(usize), _>(Error::invalid("pinning threads to cores is only supported on Linux"))
+}
+
+/// Run the postprocesses of the crate in a pool of `num_threads` threads, pinned to `cores` in
+/// turn if given, or in the global rayon pool again if `num_threads` is None
+///
+/// Postprocessors with a dedicated pool of their own keep using it.
+pub fn set_num_threads(num_threads: Option<usize>, cores: Option<&[usize]>) -> Result<()> {
+    let pool = match num_threads {
+        Some(num_threads) => Some(Arc::new(build_pool(num_threads, cores)?)),
+        None if cores.is_some() => {
+            return Err(Error::invalid("cores require num_threads"));
+        }
+        None => None,
+    };
+    *POOL.write().unwrap_or_else(PoisonError::into_inner) = pool;
+    Ok(())
+}
+
+/// Number of threads the postprocesses of the crate run in
+pub fn num_threads() -> usize {
+    match &*POOL.read().unwrap_or_else(PoisonError::into_inner) {
+        Some(pool) => pool.current_num_threads(),
+        None => rayon::current_num_threads(),
+    }
+}
+
+/// Run `op` in the pool set by `set_num_threads`, if any, so that its rayon parallelism is
+/// bounded by it
+pub fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    // Cloned out of the lock, so that the pool can be replaced while `op` runs
+    let pool = POOL.read().unwrap_or_else(PoisonError::into_inner).clone();
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Release the GIL and run `op` in the thread pool of the crate
+#[cfg(feature = "python")]
+pub(crate) fn allow_threads<R: Send>(py: Python<'_>, op: impl FnOnce() -> R + Send) -> R {
+    py.allow_threads(|| install(op))
+}
+
+/// Set the number of threads the postprocesses run in, e.g. to leave cores to the inference
+/// runtime
+///
+/// Postprocessors constructed with `num_threads` keep their own pool.
+///
+/// Args:
+///     num_threads (Optional[int]): Number of threads, None to use one per core again
+///     cores (Optional[Sequence[int]]): Cores to pin the threads to in turn, Linux only, default
+///         is not to pin them
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "set_num_threads")]
+pub(crate) fn set_num_threads_py(
+    num_threads: Option<usize>,
+    cores: Option<Vec<usize>>,
+) -> PyResult<()> {
+    Ok(set_num_threads(num_threads, cores.as_deref())?)
+}
+
+/// Number of threads the postprocesses run in
+///
+/// Returns:
+///     int: Number of threads set by `set_num_threads`, or the number of cores
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "get_num_threads")]
+pub(crate) fn get_num_threads_py() -> usize {
+    num_threads()
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn unittest_set_num_threads() {
+        set_num_threads(Some(2), None).unwrap();
+        assert_eq!(num_threads(), 2);
+        assert_eq!(install(rayon::current_num_threads), 2);
+        assert_eq!(install(|| (0..100).into_par_iter().sum::<i32>()), 4950);
+        assert!(set_num_threads(Some(0), None).is_err());
+        assert!(set_num_threads(None, Some(&[0])).is_err());
+        assert_eq!(num_threads(), 2);
+
+        set_num_threads(None, None).unwrap();
+        assert_eq!(num_threads(), rayon::current_num_threads());
+    }
+}
@@ -0,0 +1,157 @@
+//! Bounded selection of the highest scoring candidates of a decode as they are produced
+//!
+//! Sorting every candidate to keep the few thousand entering NMS costs time and memory for
+//! dense outputs. [`TopK`] instead hands out the slot each candidate goes into among at most
+//! `k` kept ones, replacing the lowest scoring one once they are all taken, so that the storage
+//! of the candidates never grows past `k`.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::descending;
+
+/// Kept candidate, ordered so that the worst one is the greatest
+#[derive(Debug, Clone, Copy)]
+struct Kept {
+    score: f32,
+    seq: usize,
+    slot: usize,
+}
+
+impl Ord for Kept {
+    fn cmp(&self, other: &Self) -> Ordering {
+        descending(self.score, other.score).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Kept {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Kept {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Kept {}
+
+/// Selection of the `k` highest scoring of a stream of candidates stored by the caller in
+/// slots, the earliest of tied candidates winning as with a sort by descending score and then
+/// ascending position
+#[derive(Debug, Clone)]
+pub struct TopK {
+    k: usize,
+    /// Position in the stream of the candidate of each slot
+    seqs: Vec<usize>,
+    /// Kept candidates, the worst on top, only built once all the slots are taken
+    heap: BinaryHeap<Kept>,
+}
+
+impl Default for TopK {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self { k, seqs: Vec::new(), heap: BinaryHeap::new() }
+    }
+
+    /// Forget the candidates kept so far and keep at most `k` from now on
+    pub fn reset(&mut self, k: usize) {
+        self.k = k;
+        self.seqs.clear();
+        self.heap.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.seqs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seqs.is_empty()
+    }
+
+    /// Position in the stream of the candidate in `slot`
+    pub fn seq(&self, slot: usize) -> usize {
+        self.seqs[slot]
+    }
+
+    /// Slot of the candidate at position `seq` of the stream scoring `score`, given the scores
+    /// of the kept ones by slot: a new slot at the end while fewer than `k` are kept, then the
+    /// slot of the worst kept one if it beats it, or None if it is not among the `k` highest
+    /// `seq` must increase from one call to the next.
+    pub fn offer(&mut self, seq: usize, score: f32, scores: &[f32]) -> Option<usize> {
+        if self.seqs.len() < self.k {
+            self.seqs.push(seq);
+            return Some(self.seqs.len() - 1);
+        }
+        if self.k == 0 {
+            return None;
+        }
+        if self.heap.is_empty() {
+            self.heap = self
+                .seqs
+                .iter()
+                .enumerate()
+                .map(|(slot, &seq)| Kept { score: scores[slot], seq, slot })
+                .collect();
+        }
+        let mut worst = self.heap.peek_mut().expect("k is positive");
+        // A later candidate only wins with a strictly higher score
+        if descending(score, worst.score) != Ordering::Less {
+            return None;
+        }
+        let slot = worst.slot;
+        *worst = Kept { score, seq, slot };
+        self.seqs[slot] = seq;
+        Some(slot)
+    }
+
+    /// Put the slots back in the order of the stream, returning the previous slot of each new
+    /// one for the caller to move its candidates likewise, or None if they are already in order
+    pub fn sort_by_seq(&mut self) -> Option<Vec<usize>> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        self.heap.clear();
+        let mut order = (0..self.seqs.len()).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|&slot| self.seqs[slot]);
+        self.seqs = order.iter().map(|&slot| self.seqs[slot]).collect();
+        Some(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_top_k() {
+        let stream = [0.5, 0.9, 0.1, 0.7, 0.9, 0.3, 0.8, f32::NAN, 0.8];
+        let mut top = TopK::new(3);
+        let mut scores = Vec::new();
+        for (seq, &score) in stream.iter().enumerate() {
+            match top.offer(seq, score, &scores) {
+                Some(slot) if slot == scores.len() => scores.push(score),
+                Some(slot) => scores[slot] = score,
+                None => {}
+            }
+            assert!(scores.len() <= 3);
+        }
+        let order = top.sort_by_seq().unwrap();
+        let scores = order.iter().map(|&slot| scores[slot]).collect::<Vec<_>>();
+        // The later of the tied 0.8 loses to the earlier one, and NaN to every score
+        assert_eq!(scores, [0.9, 0.9, 0.8]);
+        assert_eq!((0..3).map(|slot| top.seq(slot)).collect::<Vec<_>>(), [1, 4, 6]);
+
+        let mut top = TopK::new(2);
+        assert_eq!(top.offer(0, 0.5, &[]), Some(0));
+        assert_eq!(top.sort_by_seq(), None);
+        assert_eq!(TopK::new(0).offer(0, 1.0, &[]), None);
+    }
+}
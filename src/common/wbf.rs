@@ -0,0 +1,186 @@
+use ndarray::{Array2, ArrayView2};
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArray2};
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
+use crate::common::IouThreshold;
+#[cfg(feature = "python")]
+use crate::common::{extract_detections, PyDetectionResults};
+
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.55;
+
+/// A cluster of matched boxes and the box fused from them
+struct Cluster {
+    fused: DetectionResult,
+    members: Vec<DetectionResult>,
+}
+
+impl Cluster {
+    fn new(member: DetectionResult) -> Self {
+        Self { fused: member, members: vec![member] }
+    }
+
+    /// Average the member coordinates weighted by their scores, and their scores
+    fn add(&mut self, member: DetectionResult) {
+        self.members.push(member);
+        let total: f32 = self.members.iter().map(|m| m.score).sum();
+        let weighted = |coord: fn(&BoundingBox) -> f32| {
+            self.members.iter().map(|m| m.score * coord(&m.bbox)).sum::<f32>() / total
+        };
+        self.fused.bbox = BoundingBox {
+            px1: weighted(|b| b.px1),
+            py1: weighted(|b| b.py1),
+            px2: weighted(|b| b.px2),
+            py2: weighted(|b| b.py2),
+        };
+        self.fused.score = total / self.members.len() as f32;
+    }
+}
+
+/// Convert rows of (x1, y1, x2, y2, score, class) into detection results
+pub(crate) fn from_rows(rows: ArrayView2<'_, f32>) -> DetectionResults {
+    rows.outer_iter()
+        .map(|row| DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: row[0], py1: row[1], px2: row[2], py2: row[3] },
+            score: row[4],
+            class: row[5],
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Convert detection results into rows of (x1, y1, x2, y2, score, class)
+pub(crate) fn to_rows(results: &DetectionResults) -> Array2<f32> {
+    Array2::from_shape_fn((results.len(), 6), |(i, j)| {
+        let r = &results[i];
+        [r.bbox.px1, r.bbox.py1, r.bbox.px2, r.bbox.py2, r.score, r.class][j]
+    })
+}
+
+/// Weighted Box Fusion by Solovyev et al.
+///
+/// Instead of discarding overlapping boxes, boxes of the same class whose IoU with a fused box
+/// exceeds `iou_threshold` are merged into it by confidence-weighted averaging. `detections`
+/// holds the results of each model for the same image, whose scores are scaled by `weights`.
+/// Fused scores are lowered when fewer models than available contributed to a box.
+pub fn weighted_box_fusion(
+    detections: &[DetectionResults],
+    weights: &[f32],
+    iou_threshold: f32,
+    skip_box_threshold: f32,
+) -> DetectionResults {
+    fuse(detections, weights, &IouThreshold::Scalar(iou_threshold), skip_box_threshold)
+}
+
+/// Weighted Box Fusion matching the boxes of each class by the IoU threshold of the class
+fn fuse(
+    detections: &[DetectionResults],
+    weights: &[f32],
+    iou_threshold: &IouThreshold,
+    skip_box_threshold: f32,
+) -> DetectionResults {
+    let mut candidates: Vec<DetectionResult> = detections
+        .iter()
+        .zip(weights)
+        .flat_map(|(results, &weight)| {
+            results
+                .iter()
+                .filter(|r| r.score >= skip_box_threshold)
+                .map(move |&r| DetectionResult { score: r.score * weight, ..r })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for candidate in candidates {
+        let best = clusters
+            .iter_mut()
+            .filter(|c| c.fused.class == candidate.class)
+            .map(|c| (c.fused.bbox.iou(&candidate.bbox), c))
+            .filter(|(iou, _)| *iou > iou_threshold.of(candidate.class as usize))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        match best {
+            Some((_, cluster)) => cluster.add(candidate),
+            None => clusters.push(Cluster::new(candidate)),
+        }
+    }
+
+    let weight_sum: f32 = weights.iter().sum();
+    let mut results: Vec<DetectionResult> = clusters
+        .into_iter()
+        .map(|c| {
+            let contributors = usize::min(weights.len(), c.members.len()) as f32;
+            DetectionResult { score: c.fused.score * contributors / weight_sum, ..c.fused }
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.into()
+}
+
+/// Weighted Box Fusion of the detections of a single model, as the final stage of postprocess
+/// in place of NMS, keeping the `max_det` highest scoring fused boxes
+pub(crate) fn fuse_single(
+    detections: DetectionResults,
+    iou_threshold: &IouThreshold,
+    max_det: usize,
+) -> DetectionResults {
+    let mut fused = fuse(&[detections], &[1.0], iou_threshold, 0.0);
+    fused.truncate(max_det);
+    fused
+}
+
+/// Weighted Box Fusion of the detections of multiple models for the same image
+///
+/// Args:
+///     detections (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+///         each model, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+///         or lists of PyDetectionResult
+///     weights (Optional[Sequence[float]]): Weight of each model, default is 1 for every model
+///     iou_threshold (Optional[float]): IoU threshold to match boxes, default is 0.55
+///     skip_box_threshold (Optional[float]): Boxes scoring below it are ignored, default is 0
+///
+/// Returns:
+///     Union[numpy.ndarray, PyDetectionResults]: Fused detections in the same format as
+///         the inputs, in descending score order
+#[cfg(feature = "python")]
+#[pyfunction]
+pub fn wbf(
+    py: Python<'_>,
+    detections: Vec<&PyAny>,
+    weights: Option<Vec<f32>>,
+    iou_threshold: Option<f32>,
+    skip_box_threshold: Option<f32>,
+) -> PyResult<PyObject> {
+    let weights = weights.unwrap_or_else(|| vec![1.0; detections.len()]);
+    if weights.len() != detections.len() {
+        return Err(PyValueError::new_err(format!(
+            "expected {} weights but got {}",
+            detections.len(),
+            weights.len()
+        )));
+    }
+
+    let as_arrays =
+        detections.first().is_some_and(|d| d.extract::<PyReadonlyArray2<'_, f32>>().is_ok());
+    let results = detections
+        .iter()
+        .map(|&d| extract_detections(d))
+        .collect::<PyResult<Vec<DetectionResults>>>()?;
+
+    let fused = weighted_box_fusion(
+        &results,
+        &weights,
+        iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD),
+        skip_box_threshold.unwrap_or(0.0),
+    );
+    Ok(if as_arrays {
+        PyArray2::from_owned_array(py, to_rows(&fused)).to_object(py)
+    } else {
+        PyDetectionResults::from(fused).into_py(py)
+    })
+}
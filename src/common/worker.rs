@@ -0,0 +1,168 @@
+//! Background workers running postprocesses submitted from Python, delivering their results to
+//! a callback or a future once done
+//!
+//! Workers are plain threads rather than a rayon pool, so that the parallel postprocess they run
+//! still spreads over the global rayon pool, and so that waiting for the GIL to deliver a result
+//! never holds up a rayon thread the GIL holder may be waiting for.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+
+use pyo3::exceptions::{PyRuntimeError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+
+use super::threads;
+
+/// Number of postprocesses running at once, enough to overlap one with the next inference
+/// while the previous result is delivered
+pub const DEFAULT_NUM_WORKERS: usize = 2;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct WorkerPool {
+    sender: Mutex<Sender<Job>>,
+}
+
+impl WorkerPool {
+    fn new(num_workers: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..num_workers {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("postprocess-worker-{i}"))
+                .spawn(move || Self::work(&receiver))
+                .expect("failed to spawn a postprocess worker");
+        }
+        Self { sender: Mutex::new(sender) }
+    }
+
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn spawn(&self, job: Job) {
+        let sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+        sender.send(job).expect("postprocess workers outlive the pool");
+    }
+}
+
+fn pool() -> &'static WorkerPool {
+    static POOL: OnceLock<WorkerPool> = OnceLock::new();
+    POOL.get_or_init(|| WorkerPool::new(DEFAULT_NUM_WORKERS))
+}
+
+/// Where the result of a submission goes
+pub(crate) enum Completion {
+    /// A callable called with the result
+    Callback(PyObject),
+    /// A `concurrent.futures.Future`
+    Future(PyObject),
+    /// An `asyncio.Future`, resolved on the thread of its event loop
+    AsyncioFuture { future: PyObject, event_loop: PyObject },
+}
+
+impl Completion {
+    /// Completion of the `callback` argument of `submit`, a new `concurrent.futures.Future` if it
+    /// is None, along with what `submit` returns, the future or None
+    pub(crate) fn new(py: Python<'_>, callback: Option<&PyAny>) -> PyResult<(Self, PyObject)> {
+        let Some(callback) = callback.filter(|callback| !callback.is_none()) else {
+            let future = py.import("concurrent.futures")?.getattr("Future")?.call0()?;
+            return Ok((Self::Future(future.into()), future.into()));
+        };
+        if callback.hasattr("get_loop")? && callback.hasattr("set_result")? {
+            let event_loop = callback.call_method0("get_loop")?.into();
+            Ok((Self::AsyncioFuture { future: callback.into(), event_loop }, callback.into()))
+        } else if callback.hasattr("set_result")? {
+            Ok((Self::Future(callback.into()), callback.into()))
+        } else if callback.is_callable() {
+            Ok((Self::Callback(callback.into()), py.None()))
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "callback must be a callable or a future but got {}",
+                callback.get_type().name()?
+            )))
+        }
+    }
+
+    fn resolve(self, py: Python<'_>, result: PyResult<PyObject>) {
+        let delivered = match &self {
+            Self::Callback(callback) => match result {
+                Ok(result) => callback.call1(py, (result,)).map(drop),
+                Err(e) => Err(e),
+            },
+            Self::Future(future) => (|| {
+                // A future cancelled while pending takes no result
+                if !future.call_method0(py, "set_running_or_notify_cancel")?.is_true(py)? {
+                    return Ok(());
+                }
+                match result {
+                    Ok(result) => future.call_method1(py, "set_result", (result,)),
+                    Err(e) => future.call_method1(py, "set_exception", (e.into_value(py),)),
+                }
+                .map(drop)
+            })(),
+            Self::AsyncioFuture { future, event_loop } => (|| {
+                let (method, value) = match result {
+                    Ok(result) => ("set_result", result),
+                    Err(e) => ("set_exception", e.into_value(py).into_py(py)),
+                };
+                let args = (asyncio_resolve(py)?, future, method, value);
+                event_loop.call_method1(py, "call_soon_threadsafe", args).map(drop)
+            })(),
+        };
+        if let Err(e) = delivered {
+            let target = match &self {
+                Self::Callback(target) | Self::Future(target) => target,
+                Self::AsyncioFuture { future, .. } => future,
+            };
+            e.write_unraisable(py, Some(target.as_ref(py)));
+        }
+    }
+}
+
+/// Python function setting the result or exception of an asyncio future on its event loop,
+/// unless it was cancelled meanwhile
+fn asyncio_resolve(py: Python<'_>) -> PyResult<&PyAny> {
+    static RESOLVE: GILOnceCell<PyObject> = GILOnceCell::new();
+    let resolve = RESOLVE.get_or_try_init(py, || {
+        let code = "def resolve(future, method, value):\n    if not future.cancelled():\n        getattr(future, method)(value)\n";
+        let module = PyModule::from_code(py, code, "worker.py", "worker")?;
+        Ok::<_, PyErr>(module.getattr("resolve")?.into())
+    })?;
+    Ok(resolve.as_ref(py))
+}
+
+/// Run `compute` on a worker without the GIL, then `convert` its output to Python and deliver
+/// it to `completion`
+///
+/// A panic of `compute` is delivered as a RuntimeError rather than taking the worker down.
+pub(crate) fn submit<T: Send + 'static>(
+    completion: Completion,
+    compute: impl FnOnce() -> PyResult<T> + Send + 'static,
+    convert: impl FnOnce(Python<'_>, T) -> PyResult<PyObject> + Send + 'static,
+) {
+    pool().spawn(Box::new(move || {
+        let output = panic::catch_unwind(AssertUnwindSafe(|| threads::install(compute)));
+        let output = output.unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(PyRuntimeError::new_err(format!("postprocess panicked: {message}")))
+        });
+        Python::with_gil(|py| {
+            completion.resolve(py, output.and_then(|output| convert(py, output)))
+        });
+    }));
+}
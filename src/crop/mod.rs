@@ -0,0 +1,178 @@
+use ndarray::{s, Array3, Array4, ArrayView3, ArrayViewMut3};
+#[cfg(feature = "python")]
+use numpy::{IntoPyArray, PyReadonlyArray3};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+#[cfg(feature = "python")]
+use crate::common::extract_detections;
+use crate::common::resize::{resize_bilinear, Region};
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResults};
+#[cfg(feature = "python")]
+use crate::common::threads;
+use crate::common::{Error, Result};
+
+/// Box of a detection enlarged by `margin` of its size on every side and clipped to an image of
+/// `width` by `height`, as integer pixel bounds
+fn crop_region(bbox: &BoundingBox, margin: f32, width: usize, height: usize) -> Region {
+    let (mx, my) = ((bbox.px2 - bbox.px1) * margin, (bbox.py2 - bbox.py1) * margin);
+    let clip = |v: f32, size: usize| v.clamp(0.0, size as f32);
+    Region {
+        x1: clip((bbox.px1 - mx).floor(), width),
+        y1: clip((bbox.py1 - my).floor(), height),
+        x2: clip((bbox.px2 + mx).ceil(), width),
+        y2: clip((bbox.py2 + my).ceil(), height),
+    }
+}
+
+fn check_image(image: &ArrayView3<'_, u8>, margin: f32) -> Result<()> {
+    if image.is_empty() {
+        return Err(Error::invalid(format!("image must not be empty but got {:?}", image.shape())));
+    }
+    if margin.is_nan() || margin < 0.0 {
+        return Err(Error::invalid(format!("margin must be non-negative but got {margin}")));
+    }
+    Ok(())
+}
+
+/// Crop the box of each detection out of an HWC image, enlarged by `margin` of its size on every
+/// side and clipped to the image, a box outside of the image giving an empty crop
+pub fn crop_detections(
+    image: ArrayView3<'_, u8>,
+    detections: &DetectionResults,
+    margin: f32,
+) -> Result<Vec<Array3<u8>>> {
+    check_image(&image, margin)?;
+    let (height, width, _) = image.dim();
+    Ok(detections
+        .iter()
+        .map(|r| {
+            let region = crop_region(&r.bbox, margin, width, height);
+            let (x1, x2) = (region.x1 as usize, (region.x2 as usize).max(region.x1 as usize));
+            let (y1, y2) = (region.y1 as usize, (region.y2 as usize).max(region.y1 as usize));
+            image.slice(s![y1..y2, x1..x2, ..]).to_owned()
+        })
+        .collect())
+}
+
+/// Crop the box of each detection out of an HWC image and resize it to `size` (height, width),
+/// giving a batch of patches of shape (N, height, width, C) for a downstream model
+///
+/// Boxes are enlarged by `margin` of their size on every side and clipped to the image, the
+/// patch of a box outside of the image being zeros.
+pub fn crop_resized(
+    image: ArrayView3<'_, u8>,
+    detections: &DetectionResults,
+    size: (usize, usize),
+    margin: f32,
+) -> Result<Array4<u8>> {
+    check_image(&image, margin)?;
+    let (height, width, channels) = image.dim();
+    let mut patches = Array4::zeros((detections.len(), size.0, size.1, channels));
+    let patch_len = size.0 * size.1 * channels;
+    if patch_len == 0 {
+        return Ok(patches);
+    }
+    patches
+        .as_slice_mut()
+        .expect("a new array is contiguous")
+        .par_chunks_mut(patch_len)
+        .zip(detections.par_iter())
+        .for_each(|(patch, r)| {
+            let region = crop_region(&r.bbox, margin, width, height);
+            if region.x2 > region.x1 && region.y2 > region.y1 {
+                let patch = ArrayViewMut3::from_shape((size.0, size.1, channels), patch)
+                    .expect("chunks have the shape of a patch");
+                resize_bilinear(image, region, patch);
+            }
+        });
+    Ok(patches)
+}
+
+/// Crop the boxes of detections out of an image, e.g. for a classifier or a ReID model
+///
+/// Args:
+///     image (numpy.ndarray): uint8 array of shape (H, W, C)
+///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections in the
+///         coordinates of the image, either an array of shape (N, 6) with rows
+///         (x1, y1, x2, y2, score, class) or a list of PyDetectionResult
+///     size (Optional[Tuple[int, int]]): (height, width) to resize the crops to with bilinear
+///         interpolation, default is to keep them as they are
+///     margin (Optional[float]): Enlargement of the boxes on every side as a ratio of their
+///         size, default is 0
+///
+/// Returns:
+///     Union[numpy.ndarray, List[numpy.ndarray]]: Batch of shape (N, height, width, C) if `size`
+///         is given, the crops of shape (h, w, C) otherwise, boxes being clipped to the image
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "crop_detections")]
+fn crop_detections_py(
+    py: Python<'_>,
+    image: PyReadonlyArray3<'_, u8>,
+    detections: &PyAny,
+    size: Option<(usize, usize)>,
+    margin: Option<f32>,
+) -> PyResult<PyObject> {
+    let detections = extract_detections(detections)?;
+    let image = image.as_array();
+    let margin = margin.unwrap_or(0.0);
+    Ok(match size {
+        Some(size) => {
+            let patches =
+                threads::allow_threads(py, || crop_resized(image, &detections, size, margin))?;
+            patches.into_pyarray(py).to_object(py)
+        }
+        None => {
+            let crops = threads::allow_threads(py, || crop_detections(image, &detections, margin))?;
+            crops.into_iter().map(|crop| crop.into_pyarray(py)).collect::<Vec<_>>().to_object(py)
+        }
+    })
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn crop(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(crop_detections_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ssd_postprocess::DetectionResult;
+
+    #[test]
+    fn unittest_crop_detections() {
+        // Pixel value x + 10 y on both channels
+        let image = Array3::from_shape_fn((8, 10, 2), |(y, x, _)| (x + 10 * y) as u8);
+        let detection = |px1: f32, py1: f32, px2: f32, py2: f32| DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1, py1, px2, py2 },
+            score: 0.9,
+            class: 0.0,
+        };
+        let detections: DetectionResults = vec![
+            detection(2.0, 1.0, 6.0, 3.0),
+            detection(8.5, 6.0, 20.0, 20.0),
+            detection(-5.0, -5.0, -1.0, -1.0),
+        ]
+        .into();
+
+        let crops = crop_detections(image.view(), &detections, 0.0).unwrap();
+        assert_eq!(crops[0].dim(), (2, 4, 2));
+        assert_eq!(crops[0][(0, 0, 0)], 12);
+        assert_eq!(crops[1].dim(), (2, 2, 2));
+        assert_eq!(crops[2].len(), 0);
+        assert_eq!(crop_detections(image.view(), &detections, 0.5).unwrap()[0].dim(), (4, 8, 2));
+
+        let patches = crop_resized(image.view(), &detections, (4, 8), 0.0).unwrap();
+        assert_eq!(patches.dim(), (3, 4, 8, 2));
+        // Upsampled twice, the corners replicate the border pixels of the crop
+        assert_eq!(patches[(0, 0, 0, 1)], 12);
+        assert_eq!(patches[(0, 3, 7, 0)], 25);
+        assert_eq!(patches[(0, 1, 3, 0)], 16);
+        assert!(patches.slice(s![2, .., .., ..]).iter().all(|&v| v == 0));
+    }
+}
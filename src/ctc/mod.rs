@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use ndarray::{Array2, ArrayView2, ArrayView3, Axis};
+use numpy::PyReadonlyArray3;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+
+const DEFAULT_BEAM_WIDTH: usize = 10;
+const DEFAULT_LM_WEIGHT: f32 = 0.5;
+
+/// A decoded label sequence and its log score
+pub type Hypothesis = (Vec<usize>, f32);
+
+#[inline]
+fn log_sum_exp(a: f32, b: f32) -> f32 {
+    if a == f32::NEG_INFINITY {
+        return b;
+    }
+    if b == f32::NEG_INFINITY {
+        return a;
+    }
+    let max = f32::max(a, b);
+    max + f32::ln(f32::exp(a - max) + f32::exp(b - max))
+}
+
+/// Log probabilities of a prefix ending in blank and in non-blank
+#[derive(Debug, Clone, Copy)]
+struct PrefixScore {
+    blank: f32,
+    non_blank: f32,
+}
+
+impl Default for PrefixScore {
+    fn default() -> Self {
+        Self { blank: f32::NEG_INFINITY, non_blank: f32::NEG_INFINITY }
+    }
+}
+
+impl PrefixScore {
+    fn total(&self) -> f32 {
+        log_sum_exp(self.blank, self.non_blank)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub blank: usize,
+    pub beam_width: usize,
+    pub log_softmax: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ blank: {}, beam_width: {}, log_softmax: {} }}",
+            self.blank, self.beam_width, self.log_softmax
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(blank: Option<usize>, beam_width: Option<usize>, log_softmax: Option<bool>) -> Self {
+        Self {
+            blank: blank.unwrap_or(0),
+            beam_width: beam_width.unwrap_or(DEFAULT_BEAM_WIDTH),
+            log_softmax: log_softmax.unwrap_or(true),
+        }
+    }
+
+    fn log_probs(&self, logits: ArrayView2<'_, f32>) -> Array2<f32> {
+        let mut log_probs = logits.to_owned();
+        if self.log_softmax {
+            for mut row in log_probs.axis_iter_mut(Axis(0)) {
+                let max = row.fold(f32::NEG_INFINITY, |acc, &v| f32::max(acc, v));
+                let lse = max + row.fold(0f32, |acc, &v| acc + f32::exp(v - max)).ln();
+                row -= lse;
+            }
+        }
+        log_probs
+    }
+
+    /// CTC prefix beam search over a `(T, V)` logit matrix
+    ///
+    /// `lm_score` is called with every newly extended prefix and returns the log score added to
+    /// the extension, which allows shallow fusion with an external language model.
+    fn decode<F>(&self, logits: ArrayView2<'_, f32>, mut lm_score: F) -> PyResult<Vec<Hypothesis>>
+    where
+        F: FnMut(&[usize]) -> PyResult<f32>,
+    {
+        let log_probs = self.log_probs(logits);
+        let mut beams: Vec<(Vec<usize>, PrefixScore)> =
+            vec![(Vec::new(), PrefixScore { blank: 0.0, non_blank: f32::NEG_INFINITY })];
+        let mut lm_cache: HashMap<Vec<usize>, f32> = HashMap::new();
+
+        for frame in log_probs.outer_iter() {
+            let mut next: HashMap<Vec<usize>, PrefixScore> = HashMap::new();
+            for (prefix, score) in &beams {
+                for (token, &p) in frame.iter().enumerate() {
+                    if token == self.blank {
+                        let entry = next.entry(prefix.clone()).or_default();
+                        entry.blank = log_sum_exp(entry.blank, score.total() + p);
+                        continue;
+                    }
+
+                    let mut extended = prefix.clone();
+                    extended.push(token);
+                    let lm = match lm_cache.get(&extended) {
+                        Some(&lm) => lm,
+                        None => {
+                            let lm = lm_score(&extended)?;
+                            lm_cache.insert(extended.clone(), lm);
+                            lm
+                        }
+                    };
+
+                    if prefix.last() == Some(&token) {
+                        // Repeated tokens collapse unless separated by a blank
+                        let entry = next.entry(extended).or_default();
+                        entry.non_blank = log_sum_exp(entry.non_blank, score.blank + p + lm);
+                        let entry = next.entry(prefix.clone()).or_default();
+                        entry.non_blank = log_sum_exp(entry.non_blank, score.non_blank + p);
+                    } else {
+                        let entry = next.entry(extended).or_default();
+                        entry.non_blank = log_sum_exp(entry.non_blank, score.total() + p + lm);
+                    }
+                }
+            }
+
+            beams = next.into_iter().collect();
+            beams.sort_unstable_by(|a, b| {
+                b.1.total().total_cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0))
+            });
+            beams.truncate(self.beam_width);
+        }
+
+        Ok(beams.into_iter().map(|(prefix, score)| (prefix, score.total())).collect())
+    }
+
+    /// CTC postprocess function without language model, decoding the batch in parallel
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        logits: ArrayView3<'_, f32>,
+        lengths: &[usize],
+    ) -> PyResult<Vec<Vec<Hypothesis>>> {
+        let samples = logits.outer_iter().zip(lengths).collect::<Vec<_>>();
+        samples
+            .into_par_iter()
+            .map(|(logits, &length)| {
+                self.decode(logits.slice_axis(Axis(0), (0..length).into()), |_| Ok(0.0))
+            })
+            .collect()
+    }
+}
+
+/// CTC PostProcessor
+///
+/// It decodes CTC outputs of speech recognition models with prefix beam search.
+///
+/// Args:
+///     blank (Optional[int]): Index of the blank label, default is 0
+///     beam_width (Optional[int]): Number of prefixes kept per frame, default is 10
+///     log_softmax (Optional[bool]): Whether to apply log-softmax to the inputs, False if they are
+///         already log probabilities, default is True
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        blank: Option<usize>,
+        beam_width: Option<usize>,
+        log_softmax: Option<bool>,
+    ) -> PyResult<Self> {
+        if beam_width == Some(0) {
+            return Err(PyValueError::new_err("beam_width must be positive"));
+        }
+        Ok(Self(RustPostprocessor::new(blank, beam_width, log_softmax)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     logits (numpy.ndarray): Logits of shape (N, T, V)
+    ///     lengths (Optional[Sequence[int]]): Valid number of frames per sample, T if None
+    ///     lm (Optional[Callable[[List[int]], float]]): Language model returning the log
+    ///         probability of the last label of a prefix given the preceding ones
+    ///     lm_weight (Optional[float]): Weight of the language model score, default is 0.5
+    ///     insertion_bonus (Optional[float]): Score added per emitted label when `lm` is given,
+    ///         default is 0
+    ///
+    /// Returns:
+    ///     List[List[Tuple[List[int], float]]]: Batched hypotheses (labels, log score) in
+    ///         descending score order
+    fn eval(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray3<'_, f32>,
+        lengths: Option<Vec<usize>>,
+        lm: Option<PyObject>,
+        lm_weight: Option<f32>,
+        insertion_bonus: Option<f32>,
+    ) -> PyResult<Vec<Vec<Hypothesis>>> {
+        let logits = logits.as_array();
+        let &[batch_size, num_frames, vocab_size] = logits.shape() else { unreachable!() };
+        if self.0.blank >= vocab_size {
+            return Err(PyValueError::new_err(format!(
+                "blank index {} is out of range for a vocabulary of {vocab_size}",
+                self.0.blank
+            )));
+        }
+        let lengths = lengths.unwrap_or_else(|| vec![num_frames; batch_size]);
+        if lengths.len() != batch_size || lengths.iter().any(|&l| l > num_frames) {
+            return Err(PyValueError::new_err(format!(
+                "lengths must have {batch_size} entries of at most {num_frames} but got {lengths:?}"
+            )));
+        }
+
+        match lm {
+            // Calling back into Python requires the GIL, so samples are decoded sequentially
+            Some(lm) => {
+                let (lm_weight, insertion_bonus) =
+                    (lm_weight.unwrap_or(DEFAULT_LM_WEIGHT), insertion_bonus.unwrap_or(0.0));
+                logits
+                    .outer_iter()
+                    .zip(&lengths)
+                    .map(|(logits, &length)| {
+                        self.0.decode(logits.slice_axis(Axis(0), (0..length).into()), |prefix| {
+                            let score: f32 = lm.call1(py, (prefix.to_vec(),))?.extract(py)?;
+                            Ok(lm_weight * score + insertion_bonus)
+                        })
+                    })
+                    .collect()
+            }
+            None => threads::allow_threads(py, || self.0.postprocess(logits, &lengths)),
+        }
+    }
+}
+
+pub(crate) fn ctc(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
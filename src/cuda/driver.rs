@@ -0,0 +1,253 @@
+//! Bindings of the few CUDA driver API calls the backend makes, with RAII wrappers
+//!
+//! The driver API rather than the runtime one, so that the kernels run in the context the
+//! arrays were allocated in by PyTorch or CuPy, whichever runtime they link.
+
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+use std::{mem, ptr};
+
+use crate::common::{Error, Result};
+
+type CuResult = c_int;
+pub type DevicePtr = u64;
+type Context = *mut c_void;
+type Module = *mut c_void;
+type Function = *mut c_void;
+pub type Stream = *mut c_void;
+
+const CUDA_SUCCESS: CuResult = 0;
+const CU_POINTER_ATTRIBUTE_CONTEXT: c_int = 1;
+
+#[link(name = "cuda")]
+extern "C" {
+    fn cuInit(flags: c_uint) -> CuResult;
+    fn cuGetErrorString(error: CuResult, string: *mut *const c_char) -> CuResult;
+    fn cuPointerGetAttribute(data: *mut c_void, attribute: c_int, ptr: DevicePtr) -> CuResult;
+    #[link_name = "cuCtxPushCurrent_v2"]
+    fn cuCtxPushCurrent(ctx: Context) -> CuResult;
+    #[link_name = "cuCtxPopCurrent_v2"]
+    fn cuCtxPopCurrent(ctx: *mut Context) -> CuResult;
+    fn cuModuleLoadData(module: *mut Module, image: *const c_void) -> CuResult;
+    fn cuModuleGetFunction(
+        function: *mut Function,
+        module: Module,
+        name: *const c_char,
+    ) -> CuResult;
+    #[allow(clippy::too_many_arguments)]
+    fn cuLaunchKernel(
+        function: Function,
+        grid_x: c_uint,
+        grid_y: c_uint,
+        grid_z: c_uint,
+        block_x: c_uint,
+        block_y: c_uint,
+        block_z: c_uint,
+        shared_mem_bytes: c_uint,
+        stream: Stream,
+        params: *mut *mut c_void,
+        extra: *mut *mut c_void,
+    ) -> CuResult;
+    #[link_name = "cuMemAlloc_v2"]
+    fn cuMemAlloc(ptr: *mut DevicePtr, bytes: usize) -> CuResult;
+    #[link_name = "cuMemFree_v2"]
+    fn cuMemFree(ptr: DevicePtr) -> CuResult;
+    #[link_name = "cuMemcpyHtoDAsync_v2"]
+    fn cuMemcpyHtoDAsync(
+        dst: DevicePtr,
+        src: *const c_void,
+        bytes: usize,
+        stream: Stream,
+    ) -> CuResult;
+    #[link_name = "cuMemcpyDtoHAsync_v2"]
+    fn cuMemcpyDtoHAsync(
+        dst: *mut c_void,
+        src: DevicePtr,
+        bytes: usize,
+        stream: Stream,
+    ) -> CuResult;
+    fn cuMemsetD32Async(dst: DevicePtr, value: c_uint, count: usize, stream: Stream) -> CuResult;
+    fn cuStreamSynchronize(stream: Stream) -> CuResult;
+}
+
+fn check(result: CuResult) -> Result<()> {
+    if result == CUDA_SUCCESS {
+        return Ok(());
+    }
+    let mut message = ptr::null();
+    // SAFETY: the driver sets a static string, or leaves it null for an unknown error
+    unsafe { cuGetErrorString(result, &mut message) };
+    let message = if message.is_null() {
+        format!("error {result}")
+    } else {
+        // SAFETY: a non-null message is a static nul-terminated string
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    };
+    Err(Error::Runtime(format!("CUDA driver: {message}")))
+}
+
+/// The context `ptr` was allocated in, current on the thread until dropped
+pub struct ContextGuard(Context);
+
+impl ContextGuard {
+    pub fn of(ptr: DevicePtr) -> Result<Self> {
+        let mut context: Context = ptr::null_mut();
+        // SAFETY: initializing twice is a no-op, and the attribute is written as a context
+        unsafe {
+            check(cuInit(0))?;
+            check(cuPointerGetAttribute(
+                &mut context as *mut Context as *mut c_void,
+                CU_POINTER_ATTRIBUTE_CONTEXT,
+                ptr,
+            ))?;
+            check(cuCtxPushCurrent(context))?;
+        }
+        Ok(Self(context))
+    }
+
+    /// Kernels of the backend in this context, loaded the first time
+    pub fn kernels(&self) -> Result<Kernels> {
+        static MODULES: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+        const PTX: &str = concat!(include_str!(concat!(env!("OUT_DIR"), "/kernels.ptx")), "\0");
+
+        let mut modules = MODULES.lock().unwrap_or_else(PoisonError::into_inner);
+        let module = match modules.iter().find(|&&(context, _)| context == self.0 as usize) {
+            Some(&(_, module)) => module as Module,
+            None => {
+                let mut module: Module = ptr::null_mut();
+                // SAFETY: the PTX is nul-terminated and the context is current
+                check(unsafe { cuModuleLoadData(&mut module, PTX.as_ptr().cast()) })?;
+                modules.push((self.0 as usize, module as usize));
+                module
+            }
+        };
+        let function = |name: &[u8]| {
+            let name = CStr::from_bytes_with_nul(name).expect("kernel names are nul-terminated");
+            let mut function: Function = ptr::null_mut();
+            // SAFETY: the module is loaded in the current context
+            check(unsafe { cuModuleGetFunction(&mut function, module, name.as_ptr()) })?;
+            Ok(Kernel(function))
+        };
+        Ok(Kernels {
+            decode_flat: function(b"decode_flat\0")?,
+            nms_mask: function(b"nms_mask\0")?,
+            nms_reduce: function(b"nms_reduce\0")?,
+        })
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let mut context: Context = ptr::null_mut();
+        // SAFETY: the context pushed by `of` is the current one
+        unsafe { cuCtxPopCurrent(&mut context) };
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Kernel(Function);
+
+pub struct Kernels {
+    pub decode_flat: Kernel,
+    pub nms_mask: Kernel,
+    pub nms_reduce: Kernel,
+}
+
+impl Kernel {
+    /// Launch the kernel on `stream` with `params` pointing to each argument in order
+    ///
+    /// # Safety
+    ///
+    /// The arguments must match the parameters of the kernel and the buffers they point to
+    /// must be large enough for the launch.
+    pub unsafe fn launch(
+        self,
+        grid: (u32, u32),
+        block: u32,
+        shared_mem_bytes: u32,
+        stream: Stream,
+        params: &mut [*mut c_void],
+    ) -> Result<()> {
+        check(cuLaunchKernel(
+            self.0,
+            grid.0,
+            grid.1,
+            1,
+            block,
+            1,
+            1,
+            shared_mem_bytes,
+            stream,
+            params.as_mut_ptr(),
+            ptr::null_mut(),
+        ))
+    }
+}
+
+/// Pointer to an argument of a kernel
+pub fn arg<T>(value: &T) -> *mut c_void {
+    value as *const T as *mut c_void
+}
+
+/// Device allocation of `len` elements of `T` in the current context
+pub struct DeviceBuffer<T> {
+    ptr: DevicePtr,
+    len: usize,
+    _element: std::marker::PhantomData<T>,
+}
+
+impl<T: Copy + Default> DeviceBuffer<T> {
+    pub fn new(len: usize) -> Result<Self> {
+        let mut ptr = 0;
+        // SAFETY: a context is current
+        check(unsafe { cuMemAlloc(&mut ptr, (len * mem::size_of::<T>()).max(1)) })?;
+        Ok(Self { ptr, len, _element: std::marker::PhantomData })
+    }
+
+    pub fn from_slice(values: &[T], stream: Stream) -> Result<Self> {
+        let buffer = Self::new(values.len())?;
+        // SAFETY: the buffer holds as many elements as the slice, which outlives the copy as the
+        // caller synchronizes the stream before returning
+        check(unsafe {
+            cuMemcpyHtoDAsync(buffer.ptr, values.as_ptr().cast(), mem::size_of_val(values), stream)
+        })?;
+        Ok(buffer)
+    }
+
+    pub fn ptr(&self) -> DevicePtr {
+        self.ptr
+    }
+
+    /// Zero the buffer, for elements of 4 bytes
+    pub fn zero(&self, stream: Stream) -> Result<()> {
+        debug_assert_eq!(mem::size_of::<T>(), 4);
+        // SAFETY: the buffer holds `len` elements of 4 bytes
+        check(unsafe { cuMemsetD32Async(self.ptr, 0, self.len, stream) })
+    }
+
+    /// Copy the elements of `range` to the host, waiting for `stream`
+    pub fn read(&self, range: Range<usize>, stream: Stream) -> Result<Vec<T>> {
+        assert!(range.end <= self.len, "{range:?} is out of the {} elements", self.len);
+        let mut values = vec![T::default(); range.len()];
+        // SAFETY: both sides hold `range.len()` elements, and the stream is synchronized before
+        // the host values are read
+        unsafe {
+            check(cuMemcpyDtoHAsync(
+                values.as_mut_ptr().cast(),
+                self.ptr + (range.start * mem::size_of::<T>()) as DevicePtr,
+                mem::size_of_val(&values[..]),
+                stream,
+            ))?;
+            check(cuStreamSynchronize(stream))?;
+        }
+        Ok(values)
+    }
+}
+
+impl<T> Drop for DeviceBuffer<T> {
+    fn drop(&mut self) {
+        // SAFETY: the pointer was allocated by cuMemAlloc and is freed once
+        unsafe { cuMemFree(self.ptr) };
+    }
+}
@@ -0,0 +1,155 @@
+// Decode and NMS kernels of the CUDA backend, compiled into PTX by build.rs
+
+// Floats per candidate: x1, y1, x2, y2, score, class, and the position of the candidate among
+// the (row, class) pairs of the image as the bits of an int, for a reproducible order
+#define CANDIDATE_LEN 7
+#define MASK_BITS 64
+
+// Keep the rows of flattened outputs of shape (batch, num_rows, 5 + num_classes) whose class
+// confidence times objectness exceeds the threshold of the class, as LTRB candidates appended
+// to the `max_boxes` candidates of the image. `counts` keeps counting past `max_boxes`.
+extern "C" __global__ void decode_flat(
+    const float* __restrict__ input,
+    int num_rows,
+    int row_len,
+    long long batch_stride,
+    long long row_stride,
+    long long channel_stride,
+    const float* __restrict__ thresholds,
+    float min_threshold,
+    unsigned int max_boxes,
+    float* __restrict__ candidates,
+    unsigned int* __restrict__ counts)
+{
+    int row = blockIdx.x * blockDim.x + threadIdx.x;
+    int batch = blockIdx.y;
+    if (row >= num_rows) {
+        return;
+    }
+    const float* x = input + batch * batch_stride + row * row_stride;
+    float objectness = x[4 * channel_stride];
+    // Class confidences never exceed 1
+    if (!(objectness > min_threshold)) {
+        return;
+    }
+    float cx = x[0];
+    float cy = x[channel_stride];
+    float w = x[2 * channel_stride];
+    float h = x[3 * channel_stride];
+    int num_classes = row_len - 5;
+    for (int c = 0; c < num_classes; ++c) {
+        float score = x[(5 + c) * channel_stride] * objectness;
+        if (!(score > thresholds[c])) {
+            continue;
+        }
+        unsigned int i = atomicAdd(&counts[batch], 1u);
+        if (i >= max_boxes) {
+            return;
+        }
+        float* out = candidates + ((long long)batch * max_boxes + i) * CANDIDATE_LEN;
+        out[0] = cx - w * 0.5f;
+        out[1] = cy - h * 0.5f;
+        out[2] = cx + w * 0.5f;
+        out[3] = cy + h * 0.5f;
+        out[4] = score;
+        out[5] = (float)c;
+        out[6] = __int_as_float(row * num_classes + c);
+    }
+}
+
+// IoU test of the CPU NMS, a NaN IoU suppressing the box
+__device__ __forceinline__ bool suppresses(const float* a, const float* b, float iou_threshold,
+                                           float epsilon)
+{
+    float width = fmaxf(0.0f, fminf(a[2], b[2]) - fmaxf(a[0], b[0]));
+    float height = fmaxf(0.0f, fminf(a[3], b[3]) - fmaxf(a[1], b[1]));
+    float intersection = width * height;
+    float area_a = fmaxf(0.0f, a[2] - a[0]) * fmaxf(0.0f, a[3] - a[1]);
+    float area_b = fmaxf(0.0f, b[2] - b[0]) * fmaxf(0.0f, b[3] - b[1]);
+    return !(intersection / (area_a + area_b - intersection + epsilon) <= iou_threshold);
+}
+
+// Bitmask of shape (n, ceil(n / 64)) of the later boxes each of the `n` boxes sorted by
+// descending score suppresses, with boxes of shape (n, 4) offset by class unless agnostic.
+// Launched with blocks of 64 threads on a grid of ceil(n / 64) x ceil(n / 64).
+extern "C" __global__ void nms_mask(
+    const float* __restrict__ boxes,
+    int n,
+    float iou_threshold,
+    float epsilon,
+    unsigned long long* __restrict__ mask)
+{
+    int row_block = blockIdx.y;
+    int col_block = blockIdx.x;
+    int col_blocks = (n + MASK_BITS - 1) / MASK_BITS;
+    int row_size = min(n - row_block * MASK_BITS, MASK_BITS);
+    int col_size = min(n - col_block * MASK_BITS, MASK_BITS);
+    int cur = row_block * MASK_BITS + threadIdx.x;
+
+    // Earlier boxes are never suppressed by later ones
+    if (row_block > col_block) {
+        if (threadIdx.x < row_size) {
+            mask[(long long)cur * col_blocks + col_block] = 0;
+        }
+        return;
+    }
+
+    __shared__ float block[MASK_BITS * 4];
+    if (threadIdx.x < col_size) {
+        for (int k = 0; k < 4; ++k) {
+            block[threadIdx.x * 4 + k] = boxes[(col_block * MASK_BITS + threadIdx.x) * 4 + k];
+        }
+    }
+    __syncthreads();
+
+    if (threadIdx.x < row_size) {
+        const float* box = boxes + cur * 4;
+        unsigned long long bits = 0;
+        int start = row_block == col_block ? threadIdx.x + 1 : 0;
+        for (int i = start; i < col_size; ++i) {
+            if (suppresses(box, block + i * 4, iou_threshold, epsilon)) {
+                bits |= 1ULL << i;
+            }
+        }
+        mask[(long long)cur * col_blocks + col_block] = bits;
+    }
+}
+
+// Greedy pass over the bitmask of `nms_mask`, writing the indices of the first `max_det` boxes
+// kept into `keep` and their number into `num_kept`. Launched as a single block with
+// ceil(n / 64) * 8 bytes of dynamic shared memory.
+extern "C" __global__ void nms_reduce(
+    const unsigned long long* __restrict__ mask,
+    int n,
+    int max_det,
+    int* __restrict__ keep,
+    int* __restrict__ num_kept)
+{
+    extern __shared__ unsigned long long removed[];
+    int col_blocks = (n + MASK_BITS - 1) / MASK_BITS;
+    for (int j = threadIdx.x; j < col_blocks; j += blockDim.x) {
+        removed[j] = 0;
+    }
+    __syncthreads();
+
+    int kept = 0;
+    for (int i = 0; i < n && kept < max_det; ++i) {
+        // The bit of box i is only ever set before the barrier of an earlier iteration, so that
+        // every thread takes the same branch
+        if (removed[i / MASK_BITS] >> (i % MASK_BITS) & 1) {
+            continue;
+        }
+        if (threadIdx.x == 0) {
+            keep[kept] = i;
+        }
+        ++kept;
+        const unsigned long long* row = mask + (long long)i * col_blocks;
+        for (int j = i / MASK_BITS + threadIdx.x; j < col_blocks; j += blockDim.x) {
+            removed[j] |= row[j];
+        }
+        __syncthreads();
+    }
+    if (threadIdx.x == 0) {
+        *num_kept = kept;
+    }
+}
@@ -0,0 +1,380 @@
+//! CUDA backend of the postprocess of flattened YOLOv5 outputs, run where GPU-served models
+//! leave their outputs
+//!
+//! The confidence filter and the IoU matrix of NMS run on the device; only the candidates above
+//! the threshold go through the host to be sorted, and only the kept indices come back.
+
+mod driver;
+
+use ndarray::Array2;
+use numpy::PyArray2;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use self::driver::{arg, ContextGuard, DeviceBuffer, DevicePtr, Kernels, Stream};
+use crate::common::{descending, threads, warn_overflow, ConfThreshold, Error, Result};
+use crate::yolo::{DEFAULT_MAX_BOXES, DEFAULT_MAX_DET, DEFAULT_MAX_NMS, MAX_WH};
+
+/// Floats per candidate written by the decode kernel
+const CANDIDATE_LEN: usize = 7;
+/// Boxes per word of the NMS bitmask, and threads per block of the mask kernel
+const MASK_BITS: usize = 64;
+const BLOCK_SIZE: u32 = 256;
+
+/// float32 array on a CUDA device, described by its `__cuda_array_interface__`
+#[derive(Debug, Clone)]
+pub struct DeviceArray {
+    pub ptr: DevicePtr,
+    pub shape: Vec<usize>,
+    /// Strides in elements
+    pub strides: Vec<usize>,
+    /// Stream the producer ordered its work on, to order the postprocess after it
+    pub stream: Option<usize>,
+}
+
+impl<'source> FromPyObject<'source> for DeviceArray {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let interface = ob.getattr("__cuda_array_interface__").map_err(|_| {
+            PyTypeError::new_err(format!(
+                "expected an array exposing __cuda_array_interface__ but got {}",
+                ob.get_type().name().unwrap_or("an object")
+            ))
+        })?;
+        let interface: &PyDict = interface.downcast()?;
+        let get = |key: &str| {
+            interface.get_item(key)?.ok_or_else(|| {
+                PyValueError::new_err(format!("__cuda_array_interface__ has no {key}"))
+            })
+        };
+
+        let typestr: &str = get("typestr")?.extract()?;
+        if typestr != "<f4" {
+            return Err(PyTypeError::new_err(format!(
+                "expected a float32 array but got typestr {typestr:?}"
+            )));
+        }
+        let shape: Vec<usize> = get("shape")?.extract()?;
+        let (ptr, _readonly): (DevicePtr, bool) = get("data")?.extract()?;
+        let strides = match get("strides")?.extract::<Option<Vec<isize>>>()? {
+            Some(strides) => strides
+                .into_iter()
+                .map(|stride| match stride {
+                    stride if stride >= 0 && stride % 4 == 0 => Ok(stride as usize / 4),
+                    stride => Err(PyValueError::new_err(format!(
+                        "strides must be non-negative multiples of 4 bytes but got {stride}"
+                    ))),
+                })
+                .collect::<PyResult<_>>()?,
+            // C-contiguous
+            None => {
+                let mut strides = vec![1; shape.len()];
+                for i in (0..shape.len().saturating_sub(1)).rev() {
+                    strides[i] = strides[i + 1] * shape[i + 1];
+                }
+                strides
+            }
+        };
+        // Only version 3 of the interface has a stream
+        let stream = match interface.get_item("stream")? {
+            Some(stream) => stream.extract()?,
+            None => None,
+        };
+        Ok(Self { ptr, shape, strides, stream })
+    }
+}
+
+/// Candidate decoded on the device
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    ltrb: [f32; 4],
+    score: f32,
+    class: f32,
+    /// Position among the (row, class) pairs of the image, which orders tied scores
+    seq: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub agnostic: bool,
+    pub max_det: usize,
+    pub max_boxes: usize,
+    pub max_nms: usize,
+}
+
+impl RustPostprocessor {
+    pub fn new(
+        agnostic: Option<bool>,
+        max_det: Option<usize>,
+        max_boxes: Option<usize>,
+        max_nms: Option<usize>,
+    ) -> Result<Self> {
+        for (name, limit) in [("max_det", max_det), ("max_boxes", max_boxes), ("max_nms", max_nms)]
+        {
+            if limit == Some(0) {
+                return Err(Error::invalid(format!("{name} must be positive")));
+            }
+        }
+        Ok(Self {
+            agnostic: agnostic.unwrap_or(false),
+            max_det: max_det.unwrap_or(DEFAULT_MAX_DET),
+            max_boxes: max_boxes.unwrap_or(DEFAULT_MAX_BOXES),
+            max_nms: max_nms.unwrap_or(DEFAULT_MAX_NMS),
+        })
+    }
+
+    /// Postprocess of flattened outputs of shape (batch, num_boxes, 5 + num_classes) on the
+    /// device, ordered on the CUDA stream of handle `stream`, 0 being the legacy default one,
+    /// returning the detections of each image and whether its
+    /// decode reached `max_boxes` candidates
+    ///
+    /// Which candidates are kept beyond `max_boxes` is unspecified, as they are appended
+    /// concurrently.
+    pub fn postprocess(
+        &self,
+        input: &DeviceArray,
+        conf_threshold: &ConfThreshold,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        stream: usize,
+    ) -> Result<Vec<(Array2<f32>, bool)>> {
+        let stream = stream as Stream;
+        let &[batch, num_rows, row_len] = &input.shape[..] else {
+            return Err(Error::invalid(format!(
+                "input must have shape (N, num_boxes, 5 + num_classes) but got {:?}",
+                input.shape
+            )));
+        };
+        if row_len <= 5 {
+            return Err(Error::invalid(format!(
+                "input must have more than 5 channels but got shape {:?}",
+                input.shape
+            )));
+        }
+        conf_threshold.validate(row_len - 5)?;
+        if batch == 0 || num_rows == 0 {
+            return Ok(vec![(Array2::zeros((0, 6)), false); batch]);
+        }
+        let epsilon = epsilon.unwrap_or(1e-5);
+        let agnostic = agnostic.unwrap_or(self.agnostic);
+
+        let context = ContextGuard::of(input.ptr)?;
+        let kernels = context.kernels()?;
+        let thresholds = (0..row_len - 5).map(|c| conf_threshold.of(c)).collect::<Vec<_>>();
+        let thresholds = DeviceBuffer::from_slice(&thresholds, stream)?;
+        let candidates = DeviceBuffer::<f32>::new(batch * self.max_boxes * CANDIDATE_LEN)?;
+        let counts = DeviceBuffer::<u32>::new(batch)?;
+        counts.zero(stream)?;
+
+        let [batch_stride, row_stride, channel_stride] =
+            [0, 1, 2].map(|axis| input.strides[axis] as i64);
+        let (num_rows_arg, row_len_arg) = (num_rows as i32, row_len as i32);
+        let max_boxes = self.max_boxes as u32;
+        let (thresholds_ptr, candidates_ptr, counts_ptr) =
+            (thresholds.ptr(), candidates.ptr(), counts.ptr());
+        // SAFETY: the arguments follow the parameters of decode_flat, with room for
+        // `max_boxes` candidates and a count per image
+        unsafe {
+            kernels.decode_flat.launch(
+                ((num_rows as u32 + BLOCK_SIZE - 1) / BLOCK_SIZE, batch as u32),
+                BLOCK_SIZE,
+                0,
+                stream,
+                &mut [
+                    arg(&input.ptr),
+                    arg(&num_rows_arg),
+                    arg(&row_len_arg),
+                    arg(&batch_stride),
+                    arg(&row_stride),
+                    arg(&channel_stride),
+                    arg(&thresholds_ptr),
+                    arg(&conf_threshold.min()),
+                    arg(&max_boxes),
+                    arg(&candidates_ptr),
+                    arg(&counts_ptr),
+                ],
+            )?;
+        }
+        let counts = counts.read(0..batch, stream)?;
+
+        let mut results = Vec::with_capacity(batch);
+        for (batch_index, &count) in counts.iter().enumerate() {
+            let overflowed = count as usize >= self.max_boxes;
+            let start = batch_index * self.max_boxes * CANDIDATE_LEN;
+            let len = (count as usize).min(self.max_boxes) * CANDIDATE_LEN;
+            let mut decoded = candidates
+                .read(start..start + len, stream)?
+                .chunks_exact(CANDIDATE_LEN)
+                .map(|c| Candidate {
+                    ltrb: [c[0], c[1], c[2], c[3]],
+                    score: c[4],
+                    class: c[5],
+                    seq: c[6].to_bits(),
+                })
+                .collect::<Vec<_>>();
+            // The order of the CPU NMS, which kernels appending concurrently do not keep
+            decoded.sort_unstable_by(|a, b| descending(a.score, b.score).then(a.seq.cmp(&b.seq)));
+            decoded.truncate(self.max_nms);
+
+            let kept =
+                self.suppress(&kernels, &decoded, iou_threshold, epsilon, agnostic, stream)?;
+            let detections = Array2::from_shape_fn((kept.len(), 6), |(i, j)| {
+                let candidate = &decoded[kept[i]];
+                match j {
+                    0..=3 => candidate.ltrb[j],
+                    4 => candidate.score,
+                    _ => candidate.class,
+                }
+            });
+            results.push((detections, overflowed));
+        }
+        Ok(results)
+    }
+
+    /// Hard NMS of candidates sorted by descending score, returning the indices of the kept ones
+    fn suppress(
+        &self,
+        kernels: &Kernels,
+        candidates: &[Candidate],
+        iou_threshold: f32,
+        epsilon: f32,
+        agnostic: bool,
+        stream: Stream,
+    ) -> Result<Vec<usize>> {
+        let n = candidates.len();
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let boxes = candidates
+            .iter()
+            .flat_map(|c| {
+                let offset = if agnostic { 0.0 } else { c.class * MAX_WH };
+                c.ltrb.map(|v| v + offset)
+            })
+            .collect::<Vec<_>>();
+        let boxes = DeviceBuffer::from_slice(&boxes, stream)?;
+        let col_blocks = (n + MASK_BITS - 1) / MASK_BITS;
+        let mask = DeviceBuffer::<u64>::new(n * col_blocks)?;
+        let keep = DeviceBuffer::<i32>::new(self.max_det.min(n))?;
+        let num_kept = DeviceBuffer::<i32>::new(1)?;
+
+        let (n_arg, max_det) = (n as i32, self.max_det.min(n) as i32);
+        let (boxes_ptr, mask_ptr, keep_ptr, num_kept_ptr) =
+            (boxes.ptr(), mask.ptr(), keep.ptr(), num_kept.ptr());
+        // SAFETY: the arguments follow the parameters of nms_mask and nms_reduce, with a mask
+        // of `col_blocks` words per box and room for `max_det` kept indices
+        unsafe {
+            kernels.nms_mask.launch(
+                (col_blocks as u32, col_blocks as u32),
+                MASK_BITS as u32,
+                0,
+                stream,
+                &mut [
+                    arg(&boxes_ptr),
+                    arg(&n_arg),
+                    arg(&iou_threshold),
+                    arg(&epsilon),
+                    arg(&mask_ptr),
+                ],
+            )?;
+            kernels.nms_reduce.launch(
+                (1, 1),
+                BLOCK_SIZE,
+                (col_blocks * std::mem::size_of::<u64>()) as u32,
+                stream,
+                &mut [
+                    arg(&mask_ptr),
+                    arg(&n_arg),
+                    arg(&max_det),
+                    arg(&keep_ptr),
+                    arg(&num_kept_ptr),
+                ],
+            )?;
+        }
+        let num_kept = num_kept.read(0..1, stream)?[0] as usize;
+        Ok(keep.read(0..num_kept, stream)?.into_iter().map(|i| i as usize).collect())
+    }
+}
+
+/// CUDA PostProcessor of flattened YOLOv5 outputs
+///
+/// It postprocesses outputs of shape (N, num_boxes, 5 + num_classes) on the GPU they were
+/// inferred on, as `yolo.RustPostProcessor.eval_flat` does on the host with hard NMS. Inputs
+/// are any float32 arrays exposing `__cuda_array_interface__`, such as PyTorch CUDA tensors or
+/// CuPy arrays.
+///
+/// Args:
+///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+///     max_det (Optional[int]): Maximum number of detections per image, default is 300
+///     max_boxes (Optional[int]): Maximum number of candidates decoded per image, default is
+///         10000
+///     max_nms (Optional[int]): Maximum number of highest scoring candidates per image entering
+///         NMS, default is 30000
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        agnostic: Option<bool>,
+        max_det: Option<usize>,
+        max_boxes: Option<usize>,
+        max_nms: Option<usize>,
+    ) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(agnostic, max_det, max_boxes, max_nms)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    /// Evaluate the postprocess on the device of the input
+    ///
+    /// Args:
+    ///     inputs (Any): float32 CUDA array of shape (N, num_boxes, 5 + num_classes) whose rows
+    ///         are (cx, cy, w, h, objectness, class confidences...) in pixels
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (float): IoU threshold
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     stream (Optional[int]): CUDA stream to run on, default is the stream of the
+    ///         `__cuda_array_interface__` of the input, or the legacy default stream
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results on the host, a RuntimeWarning being
+    ///         raised if the decode of an image reached `max_boxes` candidates
+    #[allow(clippy::too_many_arguments)]
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: DeviceArray,
+        conf_threshold: ConfThreshold,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        stream: Option<usize>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let stream = stream.or(inputs.stream).unwrap_or(0);
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&inputs, &conf_threshold, iou_threshold, epsilon, agnostic, stream)
+        })?;
+        let overflowed = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(_, overflowed))| overflowed.then_some(i))
+            .collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, self.0.max_boxes)?;
+        Ok(results
+            .into_iter()
+            .map(|(detections, _)| PyArray2::from_owned_array(py, detections).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn cuda(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
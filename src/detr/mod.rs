@@ -0,0 +1,243 @@
+use std::fmt;
+
+use ndarray::{ArrayView2, ArrayView3, Axis};
+use numpy::{PyArray2, PyReadonlyArray3};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::{CenteredBox, DetectionResult, DetectionResults};
+use crate::common::{descending, sigmoid, threads, PyDetectionResults};
+
+/// How query logits are turned into class probabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scoring {
+    /// Independent sigmoid per class, top-k over every (query, class) pair (RT-DETR)
+    Sigmoid,
+    /// Softmax over classes with a trailing "no object" class, best class per query (DETR)
+    Softmax,
+}
+
+impl TryFrom<&str> for Scoring {
+    type Error = PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "sigmoid" => Ok(Self::Sigmoid),
+            "softmax" => Ok(Self::Softmax),
+            _ => Err(PyValueError::new_err(format!(
+                "scoring must be either 'sigmoid' or 'softmax' but got '{value}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub scoring: Scoring,
+    pub top_k: usize,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RustPostProcessor {{ scoring: {:?}, top_k: {} }}", self.scoring, self.top_k)
+    }
+}
+
+impl RustPostprocessor {
+    fn new(scoring: Scoring, top_k: Option<usize>) -> Self {
+        const DEFAULT_TOP_K: usize = 100;
+        Self { scoring, top_k: top_k.unwrap_or(DEFAULT_TOP_K) }
+    }
+
+    /// Collect `(score, query_index, class_index)` for one image, best first
+    fn select(&self, logits: ArrayView2<'_, f32>, conf_threshold: f32) -> Vec<(f32, usize, usize)> {
+        let mut candidates = Vec::new();
+        match self.scoring {
+            Scoring::Sigmoid => {
+                for (query, row) in logits.outer_iter().enumerate() {
+                    for (class, &logit) in row.iter().enumerate() {
+                        let score = sigmoid(logit);
+                        if score > conf_threshold {
+                            candidates.push((score, query, class));
+                        }
+                    }
+                }
+            }
+            Scoring::Softmax => {
+                for (query, row) in logits.outer_iter().enumerate() {
+                    let max = row.fold(f32::NEG_INFINITY, |acc, &v| f32::max(acc, v));
+                    let sum: f32 = row.iter().map(|&v| f32::exp(v - max)).sum();
+
+                    // The last class is "no object" and never becomes a detection
+                    let num_classes = row.len() - 1;
+                    let Some((class, &logit)) = row
+                        .iter()
+                        .take(num_classes)
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| descending(**a, **b))
+                    else {
+                        continue;
+                    };
+                    let score = f32::exp(logit - max) / sum;
+                    if score > conf_threshold {
+                        candidates.push((score, query, class));
+                    }
+                }
+            }
+        }
+
+        candidates.sort_unstable_by(|a, b| descending(a.0, b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+        candidates.truncate(self.top_k);
+        candidates
+    }
+
+    /// DETR postprocess function
+    /// Boxes are given as normalized `(cx, cy, w, h)` and scaled by `image_shape` if any
+    fn postprocess(
+        &self,
+        logits: ArrayView3<'_, f32>,
+        boxes: ArrayView3<'_, f32>,
+        conf_threshold: f32,
+        image_shape: Option<(f32, f32)>,
+    ) -> Vec<DetectionResults> {
+        let (height, width) = image_shape.unwrap_or((1.0, 1.0));
+
+        (0..logits.shape()[0])
+            .into_par_iter()
+            .map(|batch_index| {
+                let boxes = boxes.index_axis(Axis(0), batch_index);
+                let results: Vec<DetectionResult> = self
+                    .select(logits.index_axis(Axis(0), batch_index), conf_threshold)
+                    .into_iter()
+                    .map(|(score, query, class)| {
+                        let cbox = CenteredBox {
+                            pcx: boxes[[query, 0]] * width,
+                            pcy: boxes[[query, 1]] * height,
+                            pw: boxes[[query, 2]] * width,
+                            ph: boxes[[query, 3]] * height,
+                        };
+                        DetectionResult {
+                            index: batch_index as f32,
+                            bbox: cbox.into(),
+                            score,
+                            class: class as f32,
+                        }
+                    })
+                    .collect();
+                results.into()
+            })
+            .collect()
+    }
+}
+
+/// DETR PostProcessor
+///
+/// It converts set-prediction query outputs into detections without NMS.
+///
+/// Args:
+///     scoring (str): Either "sigmoid" (RT-DETR) or "softmax" (DETR, last class is "no object")
+///     top_k (Optional[int]): Maximum number of detections per image, default is 100
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(scoring: &str, top_k: Option<usize>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(scoring.try_into()?, top_k)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     logits (numpy.ndarray): Class logits of shape (N, num_queries, num_classes)
+    ///     boxes (numpy.ndarray): Normalized cxcywh boxes of shape (N, num_queries, 4)
+    ///     conf_threshold (float): Confidence threshold
+    ///     image_shape (Optional[Tuple[float, float]]): (height, width) to scale boxes to
+    ///
+    /// Returns:
+    ///     List[PyDetectionResults]: Batched detection results
+    fn eval(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray3<'_, f32>,
+        boxes: PyReadonlyArray3<'_, f32>,
+        conf_threshold: f32,
+        image_shape: Option<(f32, f32)>,
+    ) -> PyResult<Vec<PyDetectionResults>> {
+        let results = self.run(py, logits, boxes, conf_threshold, image_shape)?;
+        Ok(results.into_iter().map(PyDetectionResults::from).collect())
+    }
+
+    /// Evaluate the postprocess, returning the results of each image as a single array
+    ///
+    /// Args:
+    ///     logits (numpy.ndarray): Class logits of shape (N, num_queries, num_classes)
+    ///     boxes (numpy.ndarray): Normalized cxcywh boxes of shape (N, num_queries, 4)
+    ///     conf_threshold (float): Confidence threshold
+    ///     image_shape (Optional[Tuple[float, float]]): (height, width) to scale boxes to
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detections of shape (N, 6) with rows
+    ///         (x1, y1, x2, y2, score, class)
+    fn eval_to_array(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray3<'_, f32>,
+        boxes: PyReadonlyArray3<'_, f32>,
+        conf_threshold: f32,
+        image_shape: Option<(f32, f32)>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let results = self.run(py, logits, boxes, conf_threshold, image_shape)?;
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results.into_array()).to_owned())
+            .collect())
+    }
+}
+
+impl RustPostProcessor {
+    fn run(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray3<'_, f32>,
+        boxes: PyReadonlyArray3<'_, f32>,
+        conf_threshold: f32,
+        image_shape: Option<(f32, f32)>,
+    ) -> PyResult<Vec<DetectionResults>> {
+        let (logits, boxes) = (logits.as_array(), boxes.as_array());
+        if logits.shape()[..2] != boxes.shape()[..2] || boxes.shape()[2] != 4 {
+            return Err(PyValueError::new_err(format!(
+                "expected boxes of shape ({}, {}, 4) but got {:?}",
+                logits.shape()[0],
+                logits.shape()[1],
+                boxes.shape()
+            )));
+        }
+        if self.0.scoring == Scoring::Softmax && logits.shape()[2] < 2 {
+            return Err(PyValueError::new_err(
+                "softmax scoring requires at least one class besides \"no object\"",
+            ));
+        }
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(logits, boxes, conf_threshold, image_shape)
+        });
+        Ok(results)
+    }
+}
+
+pub(crate) fn detr(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
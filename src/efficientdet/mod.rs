@@ -0,0 +1,313 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{Array2, ArrayView4, Axis};
+use numpy::{PyArray2, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::CenteredBox;
+use crate::common::{descending, logit, threads, validate_anchor_heads, NmsMethod};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::RustPostprocessor as AnchorPostprocessor;
+
+const DEFAULT_IMAGE_SIZE: (usize, usize) = (512, 512);
+const DEFAULT_MIN_LEVEL: u32 = 3;
+const DEFAULT_MAX_LEVEL: u32 = 7;
+const DEFAULT_ANCHOR_SCALE: f32 = 4.0;
+const NUM_SCALES: usize = 3;
+// (x, y) multipliers, as in the reference `aspect_ratios` config
+const ASPECT_RATIOS: [(f32, f32); 3] = [(1.0, 1.0), (1.4, 0.7), (0.7, 1.4)];
+const NUM_ANCHORS: usize = NUM_SCALES * ASPECT_RATIOS.len();
+
+// Candidates kept over all levels before NMS and detections kept after it
+const MAX_DETECTION_POINTS: usize = 5000;
+const MAX_DETECTIONS: usize = 100;
+
+/// Anchors of a single BiFPN level laid out as `(y, x, anchor)`, matching the head output order
+#[derive(Debug, Clone)]
+struct LevelAnchors {
+    height: usize,
+    width: usize,
+    anchors: Vec<CenteredBox>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    image_size: (usize, usize),
+    min_level: u32,
+    anchor_scale: f32,
+    levels: Vec<LevelAnchors>,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ image_size: {:?}, levels: {}..={}, anchor_scale: {}, num_anchors: {} }}",
+            self.image_size,
+            self.min_level,
+            self.min_level as usize + self.levels.len() - 1,
+            self.anchor_scale,
+            NUM_ANCHORS
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(
+        image_size: Option<(usize, usize)>,
+        min_level: Option<u32>,
+        max_level: Option<u32>,
+        anchor_scale: Option<f32>,
+    ) -> PyResult<Self> {
+        let image_size = image_size.unwrap_or(DEFAULT_IMAGE_SIZE);
+        let min_level = min_level.unwrap_or(DEFAULT_MIN_LEVEL);
+        let max_level = max_level.unwrap_or(DEFAULT_MAX_LEVEL);
+        let anchor_scale = anchor_scale.unwrap_or(DEFAULT_ANCHOR_SCALE);
+        if min_level > max_level || max_level >= usize::BITS {
+            return Err(PyValueError::new_err(format!(
+                "expected min_level <= max_level < {} but got {min_level} and {max_level}",
+                usize::BITS
+            )));
+        }
+
+        let levels = (min_level..=max_level)
+            .map(|level| Self::generate_anchors(image_size, 1 << level, anchor_scale))
+            .collect();
+
+        Ok(Self { image_size, min_level, anchor_scale, levels })
+    }
+
+    /// Generate the anchor grid of one level, scale octave major and aspect ratio minor
+    fn generate_anchors(
+        image_size: (usize, usize),
+        stride: usize,
+        anchor_scale: f32,
+    ) -> LevelAnchors {
+        let height = (image_size.0 + stride - 1) / stride;
+        let width = (image_size.1 + stride - 1) / stride;
+        let stride = stride as f32;
+
+        let mut anchors = Vec::with_capacity(height * width * NUM_ANCHORS);
+        for y in 0..height {
+            for x in 0..width {
+                let pcy = y as f32 * stride + stride / 2.0;
+                let pcx = x as f32 * stride + stride / 2.0;
+                for octave in 0..NUM_SCALES {
+                    let base = anchor_scale * stride * 2f32.powf(octave as f32 / NUM_SCALES as f32);
+                    for (ratio_x, ratio_y) in ASPECT_RATIOS {
+                        anchors.push(CenteredBox {
+                            pcy,
+                            pcx,
+                            ph: base * ratio_y,
+                            pw: base * ratio_x,
+                        });
+                    }
+                }
+            }
+        }
+
+        LevelAnchors { height, width, anchors }
+    }
+
+    /// Decode one image: sigmoid threshold, global top-k over all levels, then box regression
+    fn box_decode(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+    ) -> DetectionBoxes {
+        let logit_threshold = logit(conf_threshold);
+
+        // (logit, class, level, anchor_index)
+        let mut candidates: Vec<(f32, usize, usize, usize)> = Vec::new();
+        for (level_index, (level, cls)) in izip!(&self.levels, cls_outputs).enumerate() {
+            let cls = cls.index_axis(Axis(0), batch_index);
+            let num_classes = cls.shape()[0] / NUM_ANCHORS;
+            for y in 0..level.height {
+                for x in 0..level.width {
+                    for a in 0..NUM_ANCHORS {
+                        for k in 0..num_classes {
+                            let v = unsafe { *cls.uget((a * num_classes + k, y, x)) };
+                            if v > logit_threshold {
+                                let anchor_index = (y * level.width + x) * NUM_ANCHORS + a;
+                                candidates.push((v, k, level_index, anchor_index));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if candidates.len() > MAX_DETECTION_POINTS {
+            candidates.select_nth_unstable_by(MAX_DETECTION_POINTS, |a, b| {
+                descending(a.0, b.0).then((a.1, a.2, a.3).cmp(&(b.1, b.2, b.3)))
+            });
+            candidates.truncate(MAX_DETECTION_POINTS);
+        }
+
+        let len = candidates.len();
+        let mut x1 = Vec::with_capacity(len);
+        let mut y1 = Vec::with_capacity(len);
+        let mut x2 = Vec::with_capacity(len);
+        let mut y2 = Vec::with_capacity(len);
+        let mut scores = Vec::with_capacity(len);
+        let mut classes = Vec::with_capacity(len);
+        for (v, k, level_index, anchor_index) in candidates {
+            let level = &self.levels[level_index];
+            let reg = box_outputs[level_index].index_axis(Axis(0), batch_index);
+            let a = anchor_index % NUM_ANCHORS;
+            let cell = anchor_index / NUM_ANCHORS;
+            let (y, x) = (cell / level.width, cell % level.width);
+
+            // Box regressions are ordered (ty, tx, th, tw)
+            let (ty, tx, th, tw) = unsafe {
+                (
+                    *reg.uget((a * 4, y, x)),
+                    *reg.uget((a * 4 + 1, y, x)),
+                    *reg.uget((a * 4 + 2, y, x)),
+                    *reg.uget((a * 4 + 3, y, x)),
+                )
+            };
+            let anchor = &level.anchors[anchor_index];
+            let decoded = CenteredBox {
+                pcy: ty * anchor.ph + anchor.pcy,
+                pcx: tx * anchor.pw + anchor.pcx,
+                ph: f32::exp(th) * anchor.ph,
+                pw: f32::exp(tw) * anchor.pw,
+            };
+
+            x1.push(decoded.px1());
+            y1.push(decoded.py1());
+            x2.push(decoded.px2());
+            y2.push(decoded.py2());
+            scores.push(1.0 / (1.0 + f32::exp(-v)));
+            classes.push(k as f32);
+        }
+
+        DetectionBoxes::new(
+            x1.into(),
+            y1.into(),
+            x2.into(),
+            y2.into(),
+            scores.into(),
+            classes.into(),
+        )
+    }
+
+    /// EfficientDet postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        nms_method: NmsMethod,
+    ) -> Vec<Array2<f32>> {
+        (0..cls_outputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| {
+                let mut dbox =
+                    self.box_decode(cls_outputs, box_outputs, batch_index, conf_threshold);
+                let indices = AnchorPostprocessor::soft_nms(
+                    &mut dbox,
+                    nms_method,
+                    0.0,
+                    conf_threshold,
+                    MAX_DETECTIONS,
+                    false,
+                );
+                dbox.select_and_convert(&indices)
+            })
+            .collect()
+    }
+
+    fn validate(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+    ) -> PyResult<()> {
+        let grids = self.levels.iter().map(|l| (l.height, l.width)).collect::<Vec<_>>();
+        Ok(validate_anchor_heads(&grids, NUM_ANCHORS, cls_outputs, box_outputs)?)
+    }
+}
+
+/// EfficientDet PostProcessor
+///
+/// It generates the BiFPN anchor grid (3 scale octaves x 3 aspect ratios per level),
+/// keeps the top 5000 candidates over all levels, decodes them, and runs Gaussian soft-NMS.
+/// Class outputs hold raw logits with shape `(N, 9 * num_classes, H, W)` and box outputs
+/// hold `(ty, tx, th, tw)` regressions with shape `(N, 9 * 4, H, W)`.
+///
+/// Args:
+///     image_size (Optional[Tuple[int, int]]): (height, width) of network input, default is (512, 512)
+///     min_level (Optional[int]): Lowest pyramid level, default is 3
+///     max_level (Optional[int]): Highest pyramid level, default is 7
+///     anchor_scale (Optional[float]): Base anchor scale relative to the stride, default is 4.0
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        image_size: Option<(usize, usize)>,
+        min_level: Option<u32>,
+        max_level: Option<u32>,
+        anchor_scale: Option<f32>,
+    ) -> PyResult<Self> {
+        if min_level.unwrap_or(DEFAULT_MIN_LEVEL) > max_level.unwrap_or(DEFAULT_MAX_LEVEL) {
+            return Err(PyValueError::new_err("min_level must not exceed max_level"));
+        }
+        Ok(Self(RustPostprocessor::new(image_size, min_level, max_level, anchor_scale)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     cls_outputs (Sequence[numpy.ndarray]): Class logits of each level
+    ///     box_outputs (Sequence[numpy.ndarray]): Box regressions of each level
+    ///     conf_threshold (float): Confidence threshold, also the soft-NMS score threshold
+    ///     sigma (Optional[float]): Gaussian soft-NMS sigma, default is 0.5
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    fn eval(
+        &self,
+        py: Python<'_>,
+        cls_outputs: Vec<PyReadonlyArray4<'_, f32>>,
+        box_outputs: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        sigma: Option<f32>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let nms_method = NmsMethod::parse(Some("gaussian"), sigma)?;
+        let cls_outputs = cls_outputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let box_outputs = box_outputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&cls_outputs, &box_outputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&cls_outputs, &box_outputs, conf_threshold, nms_method)
+        });
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn efficientdet(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
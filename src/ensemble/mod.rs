@@ -0,0 +1,195 @@
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArray2};
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::common::ssd_postprocess::{DetectionResult, DetectionResults};
+#[cfg(feature = "python")]
+use crate::common::threads;
+#[cfg(feature = "python")]
+use crate::common::{extract_detections, PyDetectionResults};
+use crate::common::{suppress_detections, wbf, Error, NmsMethod, Result};
+#[cfg(feature = "python")]
+use crate::yolo::DEFAULT_MAX_DET;
+
+/// Detections of a model with the classes mapped by `class_map`, the detections of classes
+/// mapped to a negative index or out of the table being dropped
+fn remap(results: &DetectionResults, class_map: &[i64]) -> DetectionResults {
+    results
+        .iter()
+        .filter_map(|r| {
+            let class = *class_map.get(r.class as usize)?;
+            (class >= 0).then_some(DetectionResult { class: class as f32, ..*r })
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Fuse the detections of several models for the same image
+///
+/// The scores of each model are scaled by its weight. Weighted Box Fusion averages the
+/// overlapping boxes of the models, while the other methods suppress all of them together,
+/// class by class. `class_maps` maps the classes of each model given one into shared ones.
+pub fn fuse(
+    results: &[DetectionResults],
+    weights: &[f32],
+    method: NmsMethod,
+    iou_threshold: f32,
+    class_maps: Option<&[Option<Vec<i64>>]>,
+    max_det: usize,
+) -> Result<DetectionResults> {
+    if weights.len() != results.len() {
+        return Err(Error::invalid(format!(
+            "expected {} weights but got {}",
+            results.len(),
+            weights.len()
+        )));
+    }
+    let remapped;
+    let results = match class_maps {
+        Some(class_maps) if class_maps.len() != results.len() => {
+            return Err(Error::invalid(format!(
+                "expected {} class maps but got {}",
+                results.len(),
+                class_maps.len()
+            )));
+        }
+        Some(class_maps) => {
+            remapped = results
+                .iter()
+                .zip(class_maps)
+                .map(|(results, class_map)| match class_map {
+                    Some(class_map) => remap(results, class_map),
+                    None => results.clone(),
+                })
+                .collect::<Vec<_>>();
+            &remapped[..]
+        }
+        None => results,
+    };
+
+    if method == NmsMethod::Wbf {
+        let mut fused = wbf::weighted_box_fusion(results, weights, iou_threshold, 0.0);
+        fused.truncate(max_det);
+        return Ok(fused);
+    }
+    let weighted = results
+        .iter()
+        .zip(weights)
+        .flat_map(|(results, &weight)| {
+            results.iter().map(move |&r| DetectionResult { score: r.score * weight, ..r })
+        })
+        .collect::<Vec<_>>()
+        .into();
+    Ok(suppress_detections(&weighted, method, iou_threshold, 0.0, max_det))
+}
+
+/// Fuse the detections of several models for the same image
+///
+/// Args:
+///     results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+///         each model, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+///         or lists of PyDetectionResult
+///     weights (Optional[Sequence[float]]): Weight of each model scaling its scores, default is
+///         1 for every model
+///     method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS across the
+///         models, or "wbf" for Weighted Box Fusion, default is "wbf"
+///     iou_threshold (Optional[float]): IoU threshold, default is 0.55
+///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+///     class_maps (Optional[Sequence[Optional[Sequence[int]]]]): Shared class of each class of
+///         each model, e.g. to fuse models trained on different datasets, the detections of
+///         classes mapped to -1 being dropped, default is the classes as they are
+///     max_det (Optional[int]): Maximum number of detections, default is 300
+///
+/// Returns:
+///     Union[numpy.ndarray, PyDetectionResults]: Fused detections in the same format as the
+///         inputs, in descending score order
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "fuse")]
+#[allow(clippy::too_many_arguments)]
+fn fuse_py(
+    py: Python<'_>,
+    results: Vec<&PyAny>,
+    weights: Option<Vec<f32>>,
+    method: Option<&str>,
+    iou_threshold: Option<f32>,
+    sigma: Option<f32>,
+    class_maps: Option<Vec<Option<Vec<i64>>>>,
+    max_det: Option<usize>,
+) -> PyResult<PyObject> {
+    let method = NmsMethod::parse(Some(method.unwrap_or("wbf")), sigma)?;
+    if max_det == Some(0) {
+        return Err(PyValueError::new_err("max_det must be positive"));
+    }
+    let weights = weights.unwrap_or_else(|| vec![1.0; results.len()]);
+    let as_arrays =
+        results.first().is_some_and(|d| d.extract::<PyReadonlyArray2<'_, f32>>().is_ok());
+    let results =
+        results.into_iter().map(extract_detections).collect::<PyResult<Vec<DetectionResults>>>()?;
+
+    let fused = threads::allow_threads(py, || {
+        fuse(
+            &results,
+            &weights,
+            method,
+            iou_threshold.unwrap_or(wbf::DEFAULT_IOU_THRESHOLD),
+            class_maps.as_deref(),
+            max_det.unwrap_or(DEFAULT_MAX_DET),
+        )
+    })?;
+    Ok(if as_arrays {
+        PyArray2::from_owned_array(py, wbf::to_rows(&fused)).to_object(py)
+    } else {
+        PyDetectionResults::from(fused).into_py(py)
+    })
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn ensemble(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(fuse_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ssd_postprocess::BoundingBox;
+
+    fn detection(x1: f32, score: f32, class: f32) -> DetectionResult {
+        DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: x1, py1: 0.0, px2: x1 + 10.0, py2: 10.0 },
+            score,
+            class,
+        }
+    }
+
+    #[test]
+    fn unittest_fuse() {
+        let a: DetectionResults = vec![detection(0.0, 0.9, 0.0), detection(50.0, 0.6, 1.0)].into();
+        // The other model knows the class 0 as its class 2, and has a class unknown to the first
+        let b: DetectionResults = vec![detection(1.0, 0.8, 2.0), detection(80.0, 0.9, 0.0)].into();
+        let class_maps = [None, Some(vec![-1, 1, 0])];
+
+        let fused = fuse(
+            &[a.clone(), b.clone()],
+            &[1.0, 0.5],
+            NmsMethod::Hard,
+            0.5,
+            Some(&class_maps),
+            300,
+        )
+        .unwrap();
+        let scores = fused.iter().map(|r| (r.score, r.class)).collect::<Vec<_>>();
+        assert_eq!(scores, [(0.9, 0.0), (0.6, 1.0)]);
+
+        let fused =
+            fuse(&[a, b], &[1.0, 1.0], NmsMethod::Wbf, 0.5, Some(&class_maps), 300).unwrap();
+        assert_eq!(fused.len(), 2);
+        assert!(fused[0].bbox.px1 > 0.0 && fused[0].bbox.px1 < 1.0, "{:?}", fused[0]);
+    }
+}
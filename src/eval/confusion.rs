@@ -0,0 +1,317 @@
+use ndarray::{Array1, Array2};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray2};
+use pyo3::prelude::*;
+
+use super::{extract_ground_truth, GroundTruth};
+use crate::coco::match_detections;
+use crate::common::ssd_postprocess::DetectionResults;
+use crate::common::{extract_detections, threads, Error, Result};
+
+pub const DEFAULT_CONF_THRESHOLD: f32 = 0.25;
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.45;
+pub const DEFAULT_NUM_POINTS: usize = 1000;
+
+/// Confusion matrix of a detector accumulated over images, as computed by the validation of
+/// Ultralytics
+///
+/// Counts are indexed by (predicted class, true class), the extra last row and column standing
+/// for the background: a missed ground truth box counts as predicted background and a
+/// detection matching no box as true background. Besides, every detection is recorded with
+/// whether it matches a box of its class to compute precision and recall at any confidence.
+#[derive(Debug, Clone)]
+pub struct ConfusionMatrix {
+    pub num_classes: usize,
+    /// Detections of a lower score are left out of the matrix
+    pub conf_threshold: f32,
+    /// Minimum IoU of a detection with a ground truth box to match it
+    pub iou_threshold: f32,
+    matrix: Array2<u64>,
+    /// (score, class, true positive) of every detection
+    records: Vec<(f32, usize, bool)>,
+    num_gts: Vec<usize>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(num_classes: usize, conf_threshold: f32, iou_threshold: f32) -> Self {
+        Self {
+            num_classes,
+            conf_threshold,
+            iou_threshold,
+            matrix: Array2::zeros((num_classes + 1, num_classes + 1)),
+            records: Vec::new(),
+            num_gts: vec![0; num_classes],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.matrix.fill(0);
+        self.records.clear();
+        self.num_gts.fill(0);
+    }
+
+    /// Counts indexed by (predicted class, true class), background last
+    pub fn matrix(&self) -> &Array2<u64> {
+        &self.matrix
+    }
+
+    /// Add the detections and the ground truth of an image, crowd regions being left out
+    pub fn update(&mut self, detections: &DetectionResults, gts: &[GroundTruth]) -> Result<()> {
+        let num_classes = self.num_classes;
+        if let Some(class) = detections
+            .iter()
+            .map(|r| r.class as usize)
+            .chain(gts.iter().map(|gt| gt.class))
+            .find(|&class| class >= num_classes)
+        {
+            return Err(Error::invalid(format!(
+                "class {class} is out of the {num_classes} classes of the confusion matrix"
+            )));
+        }
+        let gts = gts.iter().filter(|gt| !gt.crowd).copied().collect::<Vec<_>>();
+
+        let matches = match_detections(detections, &gts, self.iou_threshold);
+        let mut true_positive = vec![false; detections.len()];
+        for &(d, _) in &matches.true_positives {
+            true_positive[d] = true;
+        }
+        self.records.extend(
+            detections.iter().zip(true_positive).map(|(r, tp)| (r.score, r.class as usize, tp)),
+        );
+        for gt in &gts {
+            self.num_gts[gt.class] += 1;
+        }
+
+        let confident =
+            detections.iter().filter(|r| r.score > self.conf_threshold).collect::<Vec<_>>();
+        // Class-agnostic matching: the best box of each detection, then the best detection of
+        // each box
+        let mut pairs = Vec::new();
+        for (d, r) in confident.iter().enumerate() {
+            let best = gts
+                .iter()
+                .enumerate()
+                .map(|(g, gt)| (g, r.bbox.iou(&gt.bbox)))
+                .filter(|&(_, iou)| iou > self.iou_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((g, iou)) = best {
+                pairs.push((iou, g, d));
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut gt_detection = vec![None; gts.len()];
+        let mut matched = vec![false; confident.len()];
+        for (_, g, d) in pairs {
+            if gt_detection[g].is_none() {
+                gt_detection[g] = Some(d);
+                matched[d] = true;
+            }
+        }
+
+        for (gt, d) in gts.iter().zip(gt_detection) {
+            let predicted = d.map_or(num_classes, |d| confident[d].class as usize);
+            self.matrix[(predicted, gt.class)] += 1;
+        }
+        for (r, _) in confident.iter().zip(matched).filter(|(_, matched)| !matched) {
+            self.matrix[(r.class as usize, num_classes)] += 1;
+        }
+        Ok(())
+    }
+
+    /// True and false positives of each class according to the matrix
+    pub fn tp_fp(&self) -> (Array1<u64>, Array1<u64>) {
+        let n = self.num_classes;
+        let tp = Array1::from_shape_fn(n, |c| self.matrix[(c, c)]);
+        let fp = Array1::from_shape_fn(n, |c| self.matrix.row(c).sum() - tp[c]);
+        (tp, fp)
+    }
+
+    /// Precision and recall of each class, of shape (classes, `num_points`), keeping the
+    /// detections scoring at least each of `num_points` confidences evenly spaced over [0, 1]
+    ///
+    /// Precision is 1 where a class has no detection, and recall 0 where it has no ground truth.
+    pub fn precision_recall(&self, num_points: usize) -> (Array2<f32>, Array2<f32>) {
+        let mut records = self.records.clone();
+        records.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let confidences = confidences(num_points);
+
+        let mut precision = Array2::ones((self.num_classes, num_points));
+        let mut recall = Array2::zeros((self.num_classes, num_points));
+        for class in 0..self.num_classes {
+            let (mut tp, mut fp) = (0usize, 0usize);
+            let mut class_records = records.iter().filter(|r| r.1 == class).peekable();
+            // Confidences from the highest, taking in the detections scoring at least each
+            for (i, &confidence) in confidences.iter().enumerate().rev() {
+                while let Some(&(_, _, true_positive)) =
+                    class_records.next_if(|r| r.0 >= confidence)
+                {
+                    if true_positive {
+                        tp += 1;
+                    } else {
+                        fp += 1;
+                    }
+                }
+                if tp + fp > 0 {
+                    precision[(class, i)] = tp as f32 / (tp + fp) as f32;
+                }
+                if self.num_gts[class] > 0 {
+                    recall[(class, i)] = tp as f32 / self.num_gts[class] as f32;
+                }
+            }
+        }
+        (precision, recall)
+    }
+}
+
+fn confidences(num_points: usize) -> Array1<f32> {
+    Array1::linspace(0.0, 1.0, num_points)
+}
+
+/// Confusion matrix accumulated over images, like the validation of Ultralytics
+///
+/// Counts are indexed by (predicted class, true class), the last row and column standing for
+/// the background: missed ground truth boxes are counted in the last row and detections
+/// matching no box in the last column.
+///
+/// Args:
+///     num_classes (int): Number of classes
+///     conf_threshold (Optional[float]): Detections of a score up to this are left out of the
+///         matrix, default is 0.25
+///     iou_threshold (Optional[float]): Minimum IoU of a match, default is 0.45
+#[pyclass(name = "ConfusionMatrix", module = "furiosa_native_postprocess.eval")]
+pub struct PyConfusionMatrix(ConfusionMatrix);
+
+#[pymethods]
+impl PyConfusionMatrix {
+    #[new]
+    fn new(num_classes: usize, conf_threshold: Option<f32>, iou_threshold: Option<f32>) -> Self {
+        Self(ConfusionMatrix::new(
+            num_classes,
+            conf_threshold.unwrap_or(DEFAULT_CONF_THRESHOLD),
+            iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD),
+        ))
+    }
+
+    /// Add the detections and the ground truth of an image
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+    ///         image, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+    ///         a list of PyDetectionResult
+    ///     ground_truth (numpy.ndarray): Ground truth of the image, an array of shape (M, 5) with
+    ///         rows (x1, y1, x2, y2, class)
+    ///     crowd (Optional[Sequence[bool]]): Whether each ground truth box is a crowd region,
+    ///         which are left out, default is False for every box
+    fn update(
+        &mut self,
+        py: Python<'_>,
+        detections: &PyAny,
+        ground_truth: PyReadonlyArray2<'_, f32>,
+        crowd: Option<Vec<bool>>,
+    ) -> PyResult<()> {
+        let detections = extract_detections(detections)?;
+        let gts = extract_ground_truth(ground_truth.as_array(), crowd.as_deref())?;
+        Ok(threads::allow_threads(py, || self.0.update(&detections, &gts))?)
+    }
+
+    /// Forget every image added so far
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// Confusion matrix
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Counts of shape (num_classes + 1, num_classes + 1) indexed by
+    ///         (predicted class, true class), background last
+    fn matrix(&self, py: Python<'_>) -> Py<PyArray2<u64>> {
+        self.0.matrix().clone().into_pyarray(py).to_owned()
+    }
+
+    /// True and false positives of each class according to the matrix
+    ///
+    /// Returns:
+    ///     Tuple[numpy.ndarray, numpy.ndarray]: True and false positives, of shape (num_classes,)
+    fn tp_fp(&self, py: Python<'_>) -> (Py<PyArray1<u64>>, Py<PyArray1<u64>>) {
+        let (tp, fp) = self.0.tp_fp();
+        (tp.into_pyarray(py).to_owned(), fp.into_pyarray(py).to_owned())
+    }
+
+    /// Precision-recall curves of each class over the confidence threshold
+    ///
+    /// Args:
+    ///     num_points (Optional[int]): Number of confidence thresholds evenly spaced over [0, 1],
+    ///         default is 1000
+    ///
+    /// Returns:
+    ///     Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]: Confidence thresholds of shape
+    ///         (num_points,), then precision and recall of shape (num_classes, num_points) keeping
+    ///         the detections scoring at least each threshold
+    #[allow(clippy::type_complexity)]
+    fn precision_recall(
+        &self,
+        py: Python<'_>,
+        num_points: Option<usize>,
+    ) -> (Py<PyArray1<f32>>, Py<PyArray2<f32>>, Py<PyArray2<f32>>) {
+        let num_points = num_points.unwrap_or(DEFAULT_NUM_POINTS);
+        let (precision, recall) =
+            threads::allow_threads(py, || self.0.precision_recall(num_points));
+        (
+            confidences(num_points).into_pyarray(py).to_owned(),
+            precision.into_pyarray(py).to_owned(),
+            recall.into_pyarray(py).to_owned(),
+        )
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ConfusionMatrix(num_classes: {}, conf_threshold: {}, iou_threshold: {})",
+            self.0.num_classes, self.0.conf_threshold, self.0.iou_threshold
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use super::*;
+    use crate::common::ssd_postprocess::{BoundingBox, DetectionResult};
+
+    #[test]
+    fn unittest_confusion_matrix() {
+        let bbox = |x1: f32| BoundingBox { px1: x1, py1: 0.0, px2: x1 + 10.0, py2: 10.0 };
+        let gts = [
+            GroundTruth { bbox: bbox(0.0), class: 0, crowd: false },
+            GroundTruth { bbox: bbox(20.0), class: 1, crowd: false },
+            GroundTruth { bbox: bbox(40.0), class: 1, crowd: false },
+        ];
+        let detection = |x1: f32, score: f32, class: f32| DetectionResult {
+            index: 0.0,
+            bbox: bbox(x1),
+            score,
+            class,
+        };
+        let detections = vec![
+            // Right, confused, background, and below the confidence threshold
+            detection(0.0, 0.95, 0.0),
+            detection(20.0, 0.8, 0.0),
+            detection(70.0, 0.7, 1.0),
+            detection(40.0, 0.1, 1.0),
+        ];
+        let mut matrix = ConfusionMatrix::new(2, 0.25, 0.45);
+        matrix.update(&detections.into(), &gts).unwrap();
+        assert_eq!(matrix.matrix(), arr2(&[[1, 1, 0], [0, 0, 1], [0, 1, 0]]));
+        let (tp, fp) = matrix.tp_fp();
+        assert_eq!((tp.to_vec(), fp.to_vec()), (vec![1, 0], vec![1, 1]));
+
+        let (precision, recall) = matrix.precision_recall(11);
+        // Every detection of class 1 at 0, only the background one from 0.2 to 0.7
+        assert_eq!(precision[(1, 0)], 0.5);
+        assert_eq!(recall[(1, 0)], 0.5);
+        assert_eq!((precision[(1, 5)], recall[(1, 5)]), (0.0, 0.0));
+        assert_eq!((precision[(1, 10)], recall[(1, 10)]), (1.0, 0.0));
+        assert_eq!((precision[(0, 9)], recall[(0, 9)]), (1.0, 1.0));
+
+        assert!(matrix.update(&vec![detection(0.0, 0.9, 2.0)].into(), &[]).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+
+use ndarray::ArrayView2;
+use numpy::PyReadonlyArray2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::extract_detections;
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
+
+mod confusion;
+
+pub use confusion::{ConfusionMatrix, PyConfusionMatrix};
+
+use crate::common::threads;
+
+pub const DEFAULT_MAX_DETS: usize = 100;
+const NUM_IOU_THRESHOLDS: usize = 10;
+const NUM_RECALL_THRESHOLDS: usize = 101;
+
+/// `N` evenly spaced values from `start` to `stop` computed as `numpy.linspace` does, so that
+/// the thresholds are bit-identical to those of pycocotools
+fn linspace<const N: usize>(start: f64, stop: f64) -> [f64; N] {
+    let step = (stop - start) / (N - 1) as f64;
+    std::array::from_fn(|i| if i == N - 1 { stop } else { i as f64 * step + start })
+}
+
+/// IoU thresholds 0.5:0.05:0.95
+fn iou_thresholds() -> [f64; NUM_IOU_THRESHOLDS] {
+    linspace(0.5, 0.95)
+}
+
+/// Recall thresholds 0:0.01:1 of the interpolated precision
+fn recall_thresholds() -> [f64; NUM_RECALL_THRESHOLDS] {
+    linspace(0.0, 1.0)
+}
+
+/// Class index of a class stored as float, None if negative or not integral
+pub(crate) fn class_index(class: f32) -> Option<usize> {
+    (class >= 0.0 && class.fract() == 0.0).then_some(class as usize)
+}
+
+/// A ground truth box of one image
+#[derive(Debug, Clone, Copy)]
+pub struct GroundTruth {
+    pub bbox: BoundingBox,
+    pub class: usize,
+    /// Crowd regions may match any number of detections, which are then ignored
+    pub crowd: bool,
+}
+
+/// COCO detection metrics of the "all" area range
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct CocoMetrics {
+    /// AP averaged over IoU thresholds 0.5:0.05:0.95 and classes
+    #[pyo3(get)]
+    pub map: f64,
+    /// AP at IoU 0.5
+    #[pyo3(get)]
+    pub ap50: f64,
+    /// AP at IoU 0.75
+    #[pyo3(get)]
+    pub ap75: f64,
+    /// Recall given `max_dets` detections per image averaged over IoU thresholds and classes
+    #[pyo3(get)]
+    pub ar: f64,
+    /// AP averaged over IoU thresholds of each class having ground truth
+    #[pyo3(get)]
+    pub per_class_ap: BTreeMap<usize, f64>,
+}
+
+#[pymethods]
+impl CocoMetrics {
+    fn __repr__(&self) -> String {
+        format!(
+            "CocoMetrics(map: {}, ap50: {}, ap75: {}, ar: {}, num_classes: {})",
+            self.map,
+            self.ap50,
+            self.ap75,
+            self.ar,
+            self.per_class_ap.len()
+        )
+    }
+}
+
+/// A detection matched against the ground truth of its image
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    score: f32,
+    /// Whether it is a true positive at each IoU threshold
+    matched: [bool; NUM_IOU_THRESHOLDS],
+    /// Whether it matched a crowd region at each IoU threshold
+    ignored: [bool; NUM_IOU_THRESHOLDS],
+}
+
+/// IoU as defined by COCO, where the overlap with a crowd region is relative to the detection,
+/// computed in double precision as pycocotools does
+#[inline]
+pub(crate) fn coco_iou(detection: &BoundingBox, gt: &GroundTruth) -> f64 {
+    let corners = |b: &BoundingBox| [b.px1, b.py1, b.px2, b.py2].map(f64::from);
+    let [dx1, dy1, dx2, dy2] = corners(detection);
+    let [gx1, gy1, gx2, gy2] = corners(&gt.bbox);
+    let cw = f64::max(0.0, f64::min(dx2, gx2) - f64::max(dx1, gx1));
+    let ch = f64::max(0.0, f64::min(dy2, gy2) - f64::max(dy1, gy1));
+    let overlap = cw * ch;
+    let (detection_area, gt_area) = ((dx2 - dx1) * (dy2 - dy1), (gx2 - gx1) * (gy2 - gy1));
+    let union = if gt.crowd { detection_area } else { detection_area + gt_area - overlap };
+    if union > 0.0 {
+        overlap / union
+    } else {
+        0.0
+    }
+}
+
+/// Greedily match the detections of one class in one image, in descending score order, to the
+/// ground truth of the same class
+fn match_image(detections: &[DetectionResult], gts: &[GroundTruth]) -> Vec<Match> {
+    // Non-crowd ground truth first, so that crowd regions are only matched as a last resort
+    let mut gts = gts.to_vec();
+    gts.sort_by_key(|gt| gt.crowd);
+    let thresholds = iou_thresholds();
+
+    let mut gt_matched = vec![[false; NUM_IOU_THRESHOLDS]; gts.len()];
+    detections
+        .iter()
+        .map(|detection| {
+            let ious = gts.iter().map(|gt| coco_iou(&detection.bbox, gt)).collect::<Vec<_>>();
+            let mut m = Match {
+                score: detection.score,
+                matched: [false; NUM_IOU_THRESHOLDS],
+                ignored: [false; NUM_IOU_THRESHOLDS],
+            };
+            for (t, &threshold) in thresholds.iter().enumerate() {
+                let mut best: Option<usize> = None;
+                let mut best_iou = f64::min(threshold, 1.0 - 1e-10);
+                for (g, gt) in gts.iter().enumerate() {
+                    if gt_matched[g][t] && !gt.crowd {
+                        continue;
+                    }
+                    // Stop at crowd regions once a regular ground truth box matched
+                    if best.is_some_and(|b| !gts[b].crowd) && gt.crowd {
+                        break;
+                    }
+                    if ious[g] < best_iou {
+                        continue;
+                    }
+                    best_iou = ious[g];
+                    best = Some(g);
+                }
+                if let Some(g) = best {
+                    gt_matched[g][t] = true;
+                    m.matched[t] = true;
+                    m.ignored[t] = gts[g].crowd;
+                }
+            }
+            m
+        })
+        .collect()
+}
+
+/// Interpolated precision at 101 recall points and the final recall of one class and one IoU
+/// threshold, None if the class has no regular ground truth
+fn precision_recall(matches: &[Match], t: usize, num_gts: usize) -> Option<(f64, f64)> {
+    if num_gts == 0 {
+        return None;
+    }
+
+    let (mut tp, mut fp) = (0f64, 0f64);
+    let mut recalls = Vec::with_capacity(matches.len());
+    let mut precisions = Vec::with_capacity(matches.len());
+    for m in matches.iter().filter(|m| !m.ignored[t]) {
+        if m.matched[t] {
+            tp += 1.0;
+        } else {
+            fp += 1.0;
+        }
+        recalls.push(tp / num_gts as f64);
+        // f64::EPSILON is numpy.spacing(1)
+        precisions.push(tp / (tp + fp + f64::EPSILON));
+    }
+    // Make precision monotonically decreasing
+    for i in (1..precisions.len()).rev() {
+        precisions[i - 1] = f64::max(precisions[i - 1], precisions[i]);
+    }
+
+    let ap = recall_thresholds()
+        .into_iter()
+        .map(|threshold| {
+            let index = recalls.partition_point(|&r| r < threshold);
+            precisions.get(index).copied().unwrap_or(0.0)
+        })
+        .sum::<f64>()
+        / NUM_RECALL_THRESHOLDS as f64;
+    Some((ap, recalls.last().copied().unwrap_or(0.0)))
+}
+
+/// COCO mAP evaluation following pycocotools for the "all" area range
+///
+/// `predictions` and `ground_truths` hold the detections and the ground truth of each image.
+/// Only the `max_dets` highest scoring detections of each class in each image are evaluated.
+pub fn coco_map(
+    predictions: &[DetectionResults],
+    ground_truths: &[Vec<GroundTruth>],
+    max_dets: usize,
+) -> CocoMetrics {
+    let mut classes = ground_truths.iter().flatten().map(|gt| gt.class).collect::<Vec<_>>();
+    classes.sort_unstable();
+    classes.dedup();
+
+    let sorted = predictions
+        .iter()
+        .map(|detections| {
+            let mut detections = detections.to_vec();
+            detections.sort_by(|a, b| b.score.total_cmp(&a.score));
+            detections
+        })
+        .collect::<Vec<_>>();
+
+    // (AP, recall) of each class at each IoU threshold
+    let per_class = classes
+        .par_iter()
+        .map(|&class| {
+            let mut matches = Vec::new();
+            let mut num_gts = 0;
+            for (detections, gts) in sorted.iter().zip(ground_truths) {
+                let detections = detections
+                    .iter()
+                    .filter(|d| d.class == class as f32)
+                    .take(max_dets)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let gts = gts.iter().filter(|gt| gt.class == class).copied().collect::<Vec<_>>();
+                num_gts += gts.iter().filter(|gt| !gt.crowd).count();
+                matches.extend(match_image(&detections, &gts));
+            }
+            // Stable so that ties keep the image order, as pycocotools does
+            matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+            (0..NUM_IOU_THRESHOLDS)
+                .map(|t| precision_recall(&matches, t, num_gts))
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Vec<_>>();
+
+    let evaluated = classes.iter().zip(&per_class).filter_map(|(&c, r)| Some((c, r.as_ref()?)));
+    let mean = |values: Vec<f64>| {
+        if values.is_empty() {
+            -1.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    };
+    let at = |t: usize| mean(evaluated.clone().map(|(_, r)| r[t].0).collect());
+    CocoMetrics {
+        map: mean(evaluated.clone().flat_map(|(_, r)| r.iter().map(|&(ap, _)| ap)).collect()),
+        ap50: at(0),
+        ap75: at(5),
+        ar: mean(evaluated.clone().flat_map(|(_, r)| r.iter().map(|&(_, ar)| ar)).collect()),
+        per_class_ap: evaluated
+            .map(|(c, r)| (c, r.iter().map(|&(ap, _)| ap).sum::<f64>() / r.len() as f64))
+            .collect(),
+    }
+}
+
+pub(crate) fn extract_ground_truth(
+    boxes: ArrayView2<'_, f32>,
+    crowd: Option<&[bool]>,
+) -> PyResult<Vec<GroundTruth>> {
+    if boxes.shape()[1] != 5 {
+        return Err(PyValueError::new_err(format!(
+            "ground truth must have shape (M, 5) but got {:?}",
+            boxes.shape()
+        )));
+    }
+    if crowd.is_some_and(|c| c.len() != boxes.shape()[0]) {
+        return Err(PyValueError::new_err(format!(
+            "expected {} crowd flags but got {}",
+            boxes.shape()[0],
+            crowd.map_or(0, |c| c.len())
+        )));
+    }
+    boxes
+        .outer_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let class = class_index(row[4]).ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "ground truth {i} has class {} which is not a class index",
+                    row[4]
+                ))
+            })?;
+            Ok(GroundTruth {
+                bbox: BoundingBox { px1: row[0], py1: row[1], px2: row[2], py2: row[3] },
+                class,
+                crowd: crowd.is_some_and(|c| c[i]),
+            })
+        })
+        .collect()
+}
+
+/// Compute COCO detection metrics without pycocotools
+///
+/// Args:
+///     predictions (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of
+///         each image, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+///         lists of PyDetectionResult
+///     ground_truths (Sequence[numpy.ndarray]): Ground truth of each image, arrays of shape
+///         (M, 5) with rows (x1, y1, x2, y2, class)
+///     crowds (Optional[Sequence[Sequence[bool]]]): Whether each ground truth box is a crowd
+///         region, default is False for every box
+///     max_dets (Optional[int]): Maximum number of detections per image, default is 100
+///
+/// Returns:
+///     CocoMetrics: mAP@[.5:.95], AP50, AP75, AR and per-class AP
+#[pyfunction]
+#[pyo3(name = "coco_map")]
+fn coco_map_py(
+    py: Python<'_>,
+    predictions: Vec<&PyAny>,
+    ground_truths: Vec<PyReadonlyArray2<'_, f32>>,
+    crowds: Option<Vec<Vec<bool>>>,
+    max_dets: Option<usize>,
+) -> PyResult<CocoMetrics> {
+    if predictions.len() != ground_truths.len() {
+        return Err(PyValueError::new_err(format!(
+            "expected ground truth of {} images but got {}",
+            predictions.len(),
+            ground_truths.len()
+        )));
+    }
+    if crowds.as_ref().is_some_and(|c| c.len() != ground_truths.len()) {
+        return Err(PyValueError::new_err(format!(
+            "expected crowd flags of {} images",
+            ground_truths.len()
+        )));
+    }
+
+    let predictions = predictions
+        .into_iter()
+        .map(extract_detections)
+        .collect::<PyResult<Vec<DetectionResults>>>()?;
+    for (i, detections) in predictions.iter().enumerate() {
+        if let Some(d) = detections.iter().find(|d| class_index(d.class).is_none()) {
+            return Err(PyValueError::new_err(format!(
+                "a detection of image {i} has class {} which is not a class index",
+                d.class
+            )));
+        }
+    }
+    let ground_truths = ground_truths
+        .iter()
+        .enumerate()
+        .map(|(i, gt)| {
+            extract_ground_truth(gt.as_array(), crowds.as_ref().map(|c| c[i].as_slice()))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    Ok(threads::allow_threads(py, || {
+        coco_map(&predictions, &ground_truths, max_dets.unwrap_or(DEFAULT_MAX_DETS))
+    }))
+}
+
+pub(crate) fn eval(m: &PyModule) -> PyResult<()> {
+    m.add_class::<CocoMetrics>()?;
+    m.add_class::<PyConfusionMatrix>()?;
+    m.add_function(wrap_pyfunction!(coco_map_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox([px1, py1, px2, py2]: [f32; 4]) -> BoundingBox {
+        BoundingBox { px1, py1, px2, py2 }
+    }
+
+    fn detection(corners: [f32; 4], score: f32) -> DetectionResult {
+        DetectionResult { index: 0.0, bbox: bbox(corners), score, class: 0.0 }
+    }
+
+    fn gt(corners: [f32; 4]) -> GroundTruth {
+        GroundTruth { bbox: bbox(corners), class: 0, crowd: false }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn unittest_thresholds() {
+        // Values of numpy.linspace(.5, .95, 10) and numpy.linspace(.0, 1.0, 101)
+        assert_eq!(iou_thresholds()[1], 0.55);
+        assert_eq!(iou_thresholds()[8], 0.8999999999999999);
+        assert_eq!(iou_thresholds()[9], 0.95);
+        assert_eq!(recall_thresholds()[35], 0.35000000000000003);
+        assert_eq!(recall_thresholds()[50], 0.5);
+        assert_eq!(recall_thresholds()[100], 1.0);
+
+        assert_eq!(class_index(3.0), Some(3));
+        assert_eq!(class_index(-1.0), None);
+        assert_eq!(class_index(0.5), None);
+        assert_eq!(class_index(f32::NAN), None);
+    }
+
+    #[test]
+    fn unittest_coco_map() {
+        // A true positive, a box overlapping the other ground truth by exactly 0.55, and a false
+        // positive: every detection matches at 0.5 and 0.55, giving AP 1, while the others only
+        // reach the precision of 1 up to the recall of 0.5, the first 51 recall thresholds
+        let predictions = vec![DetectionResults::from(vec![
+            detection([0.0, 0.0, 10.0, 10.0], 0.9),
+            detection([20.0, 20.0, 30.0, 25.5], 0.8),
+            detection([50.0, 50.0, 60.0, 60.0], 0.7),
+        ])];
+        let ground_truths = vec![vec![gt([0.0, 0.0, 10.0, 10.0]), gt([20.0, 20.0, 30.0, 30.0])]];
+        let metrics = coco_map(&predictions, &ground_truths, DEFAULT_MAX_DETS);
+        assert_close(metrics.map, (2.0 + 8.0 * 51.0 / 101.0) / 10.0);
+        assert_close(metrics.ap50, 1.0);
+        assert_close(metrics.ap75, 51.0 / 101.0);
+        assert_close(metrics.ar, 0.6);
+        assert_eq!(metrics.per_class_ap.keys().copied().collect::<Vec<_>>(), [0]);
+
+        // An IoU of exactly 0.95 matches at the last threshold as in double precision
+        let predictions = vec![DetectionResults::from(vec![detection([0.0, 0.0, 10.0, 9.5], 0.9)])];
+        let ground_truths = vec![vec![gt([0.0, 0.0, 10.0, 10.0])]];
+        let metrics = coco_map(&predictions, &ground_truths, DEFAULT_MAX_DETS);
+        assert_close(metrics.map, 1.0);
+        assert_close(metrics.ar, 1.0);
+
+        // 7 of 20 true positives, a false positive and the 13 others: the recall threshold
+        // 0.35000000000000003 of pycocotools exceeds the recall 7 / 20 reached before the false
+        // positive, so only the first 35 thresholds get the precision of 1, the 66 others 20 / 21
+        let cell = |i: usize| [20.0 * i as f32, 0.0, 20.0 * i as f32 + 10.0, 10.0];
+        let mut detections =
+            (0..7).map(|i| detection(cell(i), 1.0 - 0.01 * i as f32)).collect::<Vec<_>>();
+        detections.push(detection([500.0, 500.0, 510.0, 510.0], 0.92));
+        detections.extend((7..20).map(|i| detection(cell(i), 0.9 - 0.01 * i as f32)));
+        let ground_truths = vec![(0..20).map(|i| gt(cell(i))).collect::<Vec<_>>()];
+        let metrics = coco_map(&[detections.into()], &ground_truths, DEFAULT_MAX_DETS);
+        assert_close(metrics.map, (35.0 + 66.0 * 20.0 / 21.0) / 101.0);
+        assert_close(metrics.ap50, (35.0 + 66.0 * 20.0 / 21.0) / 101.0);
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{Array2, ArrayView4};
+use numpy::{PyArray2, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::{descending, logit, sigmoid, threads, validate_batch};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor as AnchorPostprocessor, DEFAULT_MAX_DET};
+
+const DEFAULT_PRE_NMS_TOP_N: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub strides: Vec<f32>,
+    pub pre_nms_top_n: usize,
+    pub agnostic: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ num_detection_layers: {}, strides: {:?}, pre_nms_top_n: {}, agnostic: {} }}",
+            self.strides.len(),
+            self.strides,
+            self.pre_nms_top_n,
+            self.agnostic
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(
+        strides: Vec<f32>,
+        pre_nms_top_n: Option<usize>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Self> {
+        if strides.is_empty() {
+            return Err(PyValueError::new_err("strides must not be empty"));
+        }
+        Ok(Self {
+            strides,
+            pre_nms_top_n: pre_nms_top_n.unwrap_or(DEFAULT_PRE_NMS_TOP_N),
+            agnostic: agnostic.unwrap_or(false),
+        })
+    }
+
+    /// Decode per-pixel ltrb distances of every FPN level into candidate boxes
+    ///
+    /// Candidates are selected by the classification score alone as in the reference
+    /// implementation, then rescored with the centerness as `sqrt(cls * centerness)`.
+    fn box_decode(
+        &self,
+        cls_logits: &[ArrayView4<'_, f32>],
+        bbox_regression: &[ArrayView4<'_, f32>],
+        centerness: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+    ) -> Vec<DetectionBoxes> {
+        let batch_size = cls_logits[0].shape()[0];
+        let logit_threshold = logit(conf_threshold);
+        let mut detection_boxes: Vec<DetectionBoxes> = vec![DetectionBoxes::empty(); batch_size];
+
+        for (&stride, cls_logits, bbox_regression, centerness) in
+            izip!(&self.strides, cls_logits, bbox_regression, centerness)
+        {
+            let &[_, num_classes, height, width] = cls_logits.shape() else { unreachable!() };
+
+            for (batch_index, (cls_logits, bbox_regression, centerness)) in izip!(
+                cls_logits.outer_iter(),
+                bbox_regression.outer_iter(),
+                centerness.outer_iter()
+            )
+            .enumerate()
+            {
+                // (class logit, class, y, x) of the candidates above threshold
+                let mut candidates: Vec<(f32, usize, usize, usize)> = Vec::new();
+                for c in 0..num_classes {
+                    for y in 0..height {
+                        for x in 0..width {
+                            let class_logit = unsafe { *cls_logits.uget((c, y, x)) };
+                            if class_logit > logit_threshold {
+                                candidates.push((class_logit, c, y, x));
+                            }
+                        }
+                    }
+                }
+                if candidates.len() > self.pre_nms_top_n {
+                    candidates.select_nth_unstable_by(self.pre_nms_top_n, |a, b| {
+                        descending(a.0, b.0).then((a.1, a.2, a.3).cmp(&(b.1, b.2, b.3)))
+                    });
+                    candidates.truncate(self.pre_nms_top_n);
+                }
+
+                let mut x1: Vec<f32> = Vec::with_capacity(candidates.len());
+                let mut y1: Vec<f32> = Vec::with_capacity(candidates.len());
+                let mut x2: Vec<f32> = Vec::with_capacity(candidates.len());
+                let mut y2: Vec<f32> = Vec::with_capacity(candidates.len());
+                let mut scores: Vec<f32> = Vec::with_capacity(candidates.len());
+                let mut classes: Vec<f32> = Vec::with_capacity(candidates.len());
+                for (class_logit, c, y, x) in candidates {
+                    // Locations are the centers of the receptive fields on the input image
+                    let cx = x as f32 * stride + (stride / 2.0).floor();
+                    let cy = y as f32 * stride + (stride / 2.0).floor();
+                    let [l, t, r, b] = [0, 1, 2, 3]
+                        .map(|side| unsafe { *bbox_regression.uget((side, y, x)) } * stride);
+                    let ctr = sigmoid(unsafe { *centerness.uget((0, y, x)) });
+
+                    x1.push(cx - l);
+                    y1.push(cy - t);
+                    x2.push(cx + r);
+                    y2.push(cy + b);
+                    scores.push(f32::sqrt(sigmoid(class_logit) * ctr));
+                    classes.push(c as f32);
+                }
+                detection_boxes[batch_index].append(
+                    x1.into(),
+                    y1.into(),
+                    x2.into(),
+                    y2.into(),
+                    scores.into(),
+                    classes.into(),
+                );
+            }
+        }
+
+        detection_boxes
+    }
+
+    /// FCOS postprocess function
+    /// The vector in function input/output is for batched input/output
+    #[allow(clippy::too_many_arguments)]
+    fn postprocess(
+        &self,
+        cls_logits: &[ArrayView4<'_, f32>],
+        bbox_regression: &[ArrayView4<'_, f32>],
+        centerness: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> Vec<Array2<f32>> {
+        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
+
+        self.box_decode(cls_logits, bbox_regression, centerness, conf_threshold)
+            .into_par_iter()
+            .map(|mut dbox| {
+                let indices = AnchorPostprocessor::nms(
+                    &dbox,
+                    iou_threshold,
+                    epsilon,
+                    DEFAULT_MAX_DET,
+                    agnostic,
+                );
+                dbox.select_and_convert(&indices)
+            })
+            .collect()
+    }
+
+    fn validate(
+        &self,
+        cls_logits: &[ArrayView4<'_, f32>],
+        bbox_regression: &[ArrayView4<'_, f32>],
+        centerness: &[ArrayView4<'_, f32>],
+    ) -> PyResult<()> {
+        let num_levels = self.strides.len();
+        if cls_logits.len() != num_levels
+            || bbox_regression.len() != num_levels
+            || centerness.len() != num_levels
+        {
+            return Err(PyValueError::new_err(format!(
+                "expected {num_levels} tensors per head but got {}, {}, and {}",
+                cls_logits.len(),
+                bbox_regression.len(),
+                centerness.len()
+            )));
+        }
+        // The heads of a level are checked against its cls_logits below
+        validate_batch(cls_logits)?;
+
+        for (level, (cls, reg, ctr)) in izip!(cls_logits, bbox_regression, centerness).enumerate() {
+            let (cls, reg, ctr) = (cls.shape(), reg.shape(), ctr.shape());
+            let spatial = [cls[0], cls[2], cls[3]];
+            if [reg[0], reg[2], reg[3]] != spatial
+                || [ctr[0], ctr[2], ctr[3]] != spatial
+                || reg[1] != 4
+                || ctr[1] != 1
+            {
+                return Err(PyValueError::new_err(format!(
+                    "level {level} has mismatching shapes: cls_logits {cls:?}, \
+                     bbox_regression {reg:?}, centerness {ctr:?}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// FCOS PostProcessor
+///
+/// It decodes anchor-free per-pixel detection heads. Per FPN level, class logits have shape
+/// `(N, num_classes, H, W)`, ltrb distance regressions normalized by the stride have shape
+/// `(N, 4, H, W)`, and centerness logits have shape `(N, 1, H, W)`.
+///
+/// Args:
+///     strides (numpy.ndarray): Strides (1D Array)
+///     pre_nms_top_n (Optional[int]): Maximum number of candidates per level, default is 1000
+///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        strides: Vec<f32>,
+        pre_nms_top_n: Option<usize>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(strides, pre_nms_top_n, agnostic)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     cls_logits (Sequence[numpy.ndarray]): Class logits per level
+    ///     bbox_regression (Sequence[numpy.ndarray]): ltrb distances per level
+    ///     centerness (Sequence[numpy.ndarray]): Centerness logits per level
+    ///     conf_threshold (float): Confidence threshold on the classification score
+    ///     iou_threshold (float): IoU threshold
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    #[allow(clippy::too_many_arguments)]
+    fn eval(
+        &self,
+        py: Python<'_>,
+        cls_logits: Vec<PyReadonlyArray4<'_, f32>>,
+        bbox_regression: Vec<PyReadonlyArray4<'_, f32>>,
+        centerness: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let cls_logits = cls_logits.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let bbox_regression = bbox_regression.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let centerness = centerness.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&cls_logits, &bbox_regression, &centerness)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(
+                &cls_logits,
+                &bbox_regression,
+                &centerness,
+                conf_threshold,
+                iou_threshold,
+                epsilon,
+                agnostic,
+            )
+        });
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn fcos(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,553 @@
+//! C interface of the YOLOv5 and SSD postprocessors, for inference servers linking the library
+//! directly
+//!
+//! Every function returns a [`Status`], the message of the last error of the calling thread being
+//! available from [`furiosa_last_error`]. Results are owned by the library until released with
+//! [`furiosa_results_destroy`], and are read by copying them into caller buffers.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use ndarray::{Array2, Array3, ArrayView3, ArrayViewD, IxDyn};
+
+use crate::common::{ConfThreshold, Error, IouThreshold, NmsMethod};
+use crate::yolo::{Config, Layout, RustPostprocessor as Yolo};
+use crate::{ssd_large, ssd_small};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Status returned by every function of the C interface
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    /// A null pointer, an invalid shape or an out of range argument
+    InvalidArgument = 1,
+    /// The postprocessor could not be set up
+    Runtime = 2,
+    /// The postprocess panicked, which is a bug
+    Panic = 3,
+}
+
+/// A dense row-major float32 tensor borrowed from the caller
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Tensor {
+    pub data: *const f32,
+    pub shape: *const usize,
+    pub ndim: usize,
+}
+
+impl Tensor {
+    /// # Safety
+    ///
+    /// `data` must point to as many floats as the product of the `ndim` dimensions of `shape`.
+    unsafe fn view<'a>(&self) -> Result<ArrayViewD<'a, f32>, Error> {
+        if self.data.is_null() || self.shape.is_null() {
+            return Err(Error::invalid("tensor data and shape must not be null"));
+        }
+        let shape = slice::from_raw_parts(self.shape, self.ndim);
+        Ok(ArrayViewD::from_shape_ptr(IxDyn(shape), self.data))
+    }
+
+    unsafe fn view3<'a>(&self) -> Result<ArrayView3<'a, f32>, Error> {
+        self.view()?.into_dimensionality().map_err(|_| {
+            Error::invalid(format!("expected a 3D tensor but got {} dimensions", self.ndim))
+        })
+    }
+}
+
+/// Batched detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+pub struct Results(Vec<Array2<f32>>);
+
+/// SSD model of [`furiosa_ssd_create`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsdModel {
+    Mobilenet = 0,
+    Resnet34 = 1,
+}
+
+pub enum Ssd {
+    Mobilenet(ssd_small::RustPostprocessor),
+    Resnet34(ssd_large::RustPostprocessor),
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, recording its error or panic as the last error
+fn guard(f: impl FnOnce() -> Result<(), Error>) -> Status {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => Status::Ok,
+        Ok(Err(e)) => {
+            let status = match e {
+                Error::InvalidArgument(_) => Status::InvalidArgument,
+                Error::Runtime(_) => Status::Runtime,
+            };
+            set_last_error(e.to_string());
+            status
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "postprocess panicked".to_string());
+            set_last_error(message);
+            Status::Panic
+        }
+    }
+}
+
+unsafe fn non_null<'a, T>(ptr: *const T, name: &str) -> Result<&'a T, Error> {
+    ptr.as_ref().ok_or_else(|| Error::invalid(format!("{name} must not be null")))
+}
+
+unsafe fn tensors<'a>(tensors: *const Tensor, len: usize) -> Result<&'a [Tensor], Error> {
+    if tensors.is_null() {
+        return Err(Error::invalid("tensors must not be null"));
+    }
+    Ok(slice::from_raw_parts(tensors, len))
+}
+
+/// Message of the last error of the calling thread, null if none occurred
+///
+/// The message is valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn furiosa_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Create a YOLOv5 postprocessor
+///
+/// `anchors` holds `num_layers * num_anchors * 2` floats and `strides` one per layer.
+/// `num_threads` of 0 shares the global thread pool.
+///
+/// # Safety
+///
+/// `anchors` and `strides` must point to as many floats as described above and `out` to a
+/// writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_yolo_create(
+    anchors: *const f32,
+    num_layers: usize,
+    num_anchors: usize,
+    strides: *const f32,
+    agnostic: bool,
+    channels_last: bool,
+    num_threads: usize,
+    out: *mut *mut Yolo,
+) -> Status {
+    guard(|| {
+        if anchors.is_null() || strides.is_null() || out.is_null() {
+            return Err(Error::invalid("anchors, strides and out must not be null"));
+        }
+        let anchors = slice::from_raw_parts(anchors, num_layers * num_anchors * 2);
+        let anchors = Array3::from_shape_vec((num_layers, num_anchors, 2), anchors.to_vec())
+            .map_err(|e| Error::invalid(e.to_string()))?;
+        let strides = slice::from_raw_parts(strides, num_layers).to_vec();
+        let layout = if channels_last { Layout::Nhwc } else { Layout::Nahwc };
+        let postprocessor = Yolo::new(
+            anchors,
+            strides,
+            Some(agnostic),
+            (num_threads > 0).then_some(num_threads),
+            layout,
+            None,
+            None,
+            None,
+            None,
+        )?;
+        *out = Box::into_raw(Box::new(postprocessor));
+        Ok(())
+    })
+}
+
+/// Create a YOLOv5 postprocessor of a JSON or YAML configuration, as saved by
+/// [`furiosa_yolo_config`], or of the anchors and strides of an Ultralytics YOLOv5 model
+/// definition
+///
+/// # Safety
+///
+/// `config` must be a NUL-terminated UTF-8 string and `out` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_yolo_create_from_config(
+    config: *const c_char,
+    out: *mut *mut Yolo,
+) -> Status {
+    guard(|| {
+        if config.is_null() || out.is_null() {
+            return Err(Error::invalid("config and out must not be null"));
+        }
+        let config = CStr::from_ptr(config)
+            .to_str()
+            .map_err(|e| Error::invalid(format!("config must be UTF-8: {e}")))?;
+        // JSON documents are YAML ones
+        let postprocessor = Config::from_yaml(config, None)?.build()?;
+        *out = Box::into_raw(Box::new(postprocessor));
+        Ok(())
+    })
+}
+
+/// Write the JSON configuration of a YOLOv5 postprocessor into `buffer`, NUL-terminated
+///
+/// `length` receives the length of the configuration without the NUL whether or not it fits in
+/// the `capacity` bytes of `buffer`, which fails if it is smaller than the length plus one.
+///
+/// # Safety
+///
+/// `postprocessor` must be a live postprocessor, `buffer` must point to `capacity` writable
+/// bytes and `length` to a writable size.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_yolo_config(
+    postprocessor: *const Yolo,
+    buffer: *mut c_char,
+    capacity: usize,
+    length: *mut usize,
+) -> Status {
+    guard(|| {
+        let postprocessor = non_null(postprocessor, "postprocessor")?;
+        if length.is_null() {
+            return Err(Error::invalid("length must not be null"));
+        }
+        let config = Config::from(postprocessor).to_json();
+        *length = config.len();
+        if config.len() >= capacity {
+            return Err(Error::invalid(format!(
+                "buffer of {capacity} bytes is too small for a configuration of {} bytes and its NUL",
+                config.len()
+            )));
+        }
+        if buffer.is_null() {
+            return Err(Error::invalid("buffer must not be null"));
+        }
+        ptr::copy_nonoverlapping(config.as_ptr().cast::<c_char>(), buffer, config.len());
+        *buffer.add(config.len()) = 0;
+        Ok(())
+    })
+}
+
+/// Destroy a YOLOv5 postprocessor, doing nothing if it is null
+///
+/// # Safety
+///
+/// `postprocessor` must have been created by [`furiosa_yolo_create`] and not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_yolo_destroy(postprocessor: *mut Yolo) {
+    if !postprocessor.is_null() {
+        drop(Box::from_raw(postprocessor));
+    }
+}
+
+/// Decode and suppress the output tensors of a YOLOv5 model, one per detection layer
+///
+/// # Safety
+///
+/// `postprocessor` must be a live postprocessor, `inputs` must point to `num_inputs` valid
+/// tensors and `out` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_yolo_eval(
+    postprocessor: *const Yolo,
+    inputs: *const Tensor,
+    num_inputs: usize,
+    conf_threshold: f32,
+    iou_threshold: f32,
+    out: *mut *mut Results,
+) -> Status {
+    guard(|| {
+        let postprocessor = non_null(postprocessor, "postprocessor")?;
+        if out.is_null() {
+            return Err(Error::invalid("out must not be null"));
+        }
+        let views =
+            tensors(inputs, num_inputs)?.iter().map(|t| t.view()).collect::<Result<Vec<_>, _>>()?;
+        let inputs = postprocessor.layers(views)?;
+        let conf_threshold = postprocessor.conf_threshold(
+            &inputs,
+            &ConfThreshold::Scalar(conf_threshold),
+            None,
+            0,
+        )?;
+        let results = postprocessor.install(|| {
+            postprocessor.postprocess(
+                &inputs,
+                &conf_threshold,
+                &IouThreshold::Scalar(iou_threshold),
+                None,
+                None,
+                NmsMethod::Hard,
+                None,
+                None,
+            )
+        });
+        let results = results.into_iter().map(|r| r.detections).collect();
+        *out = Box::into_raw(Box::new(Results(results)));
+        Ok(())
+    })
+}
+
+/// Create an SSD postprocessor
+///
+/// # Safety
+///
+/// `out` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_ssd_create(model: SsdModel, out: *mut *mut Ssd) -> Status {
+    guard(|| {
+        if out.is_null() {
+            return Err(Error::invalid("out must not be null"));
+        }
+        let postprocessor = match model {
+            SsdModel::Mobilenet => Ssd::Mobilenet(ssd_small::RustPostprocessor::new()),
+            SsdModel::Resnet34 => Ssd::Resnet34(ssd_large::RustPostprocessor::new()),
+        };
+        *out = Box::into_raw(Box::new(postprocessor));
+        Ok(())
+    })
+}
+
+/// Destroy an SSD postprocessor, doing nothing if it is null
+///
+/// # Safety
+///
+/// `postprocessor` must have been created by [`furiosa_ssd_create`] and not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_ssd_destroy(postprocessor: *mut Ssd) {
+    if !postprocessor.is_null() {
+        drop(Box::from_raw(postprocessor));
+    }
+}
+
+/// Postprocess the 6 raw box and 6 raw score tensors of a single image, all of them 3D, the
+/// results holding that single image
+///
+/// # Safety
+///
+/// `postprocessor` must be a live postprocessor, `boxes` and `scores` must point to 6 valid
+/// tensors each and `out` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_ssd_eval(
+    postprocessor: *const Ssd,
+    boxes: *const Tensor,
+    scores: *const Tensor,
+    out: *mut *mut Results,
+) -> Status {
+    const NUM_FEATURE_MAPS: usize = 6;
+    guard(|| {
+        let postprocessor = non_null(postprocessor, "postprocessor")?;
+        if out.is_null() {
+            return Err(Error::invalid("out must not be null"));
+        }
+        let boxes = tensors(boxes, NUM_FEATURE_MAPS)?
+            .iter()
+            .map(|t| t.view3())
+            .collect::<Result<Vec<_>, _>>()?;
+        let scores = tensors(scores, NUM_FEATURE_MAPS)?
+            .iter()
+            .map(|t| t.view3())
+            .collect::<Result<Vec<_>, _>>()?;
+        let results = match postprocessor {
+            Ssd::Mobilenet(p) => p.eval(&boxes, &scores, NmsMethod::Hard)?,
+            Ssd::Resnet34(p) => p.eval(&boxes, &scores, NmsMethod::Hard)?,
+        };
+        *out = Box::into_raw(Box::new(Results(vec![results.into_array()])));
+        Ok(())
+    })
+}
+
+/// Number of images of the results
+///
+/// # Safety
+///
+/// `results` must be live results.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_results_num_images(results: *const Results) -> usize {
+    results.as_ref().map_or(0, |r| r.0.len())
+}
+
+/// Number of detections of an image of the results, 0 if it is out of range
+///
+/// # Safety
+///
+/// `results` must be live results.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_results_num_detections(
+    results: *const Results,
+    image: usize,
+) -> usize {
+    results.as_ref().and_then(|r| r.0.get(image)).map_or(0, |d| d.nrows())
+}
+
+/// Copy the detections of an image into `buffer` as rows (x1, y1, x2, y2, score, class)
+///
+/// `capacity` is the number of floats `buffer` holds, which must be at least 6 times the number
+/// of detections.
+///
+/// # Safety
+///
+/// `results` must be live results and `buffer` must point to `capacity` writable floats.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_results_copy(
+    results: *const Results,
+    image: usize,
+    buffer: *mut f32,
+    capacity: usize,
+) -> Status {
+    guard(|| {
+        let results = non_null(results, "results")?;
+        let detections = results.0.get(image).ok_or_else(|| {
+            Error::invalid(format!("image {image} is out of range for {}", results.0.len()))
+        })?;
+        if detections.len() > capacity {
+            return Err(Error::invalid(format!(
+                "buffer of {capacity} floats is too small for {} detections",
+                detections.nrows()
+            )));
+        }
+        if buffer.is_null() && !detections.is_empty() {
+            return Err(Error::invalid("buffer must not be null"));
+        }
+        for (i, &v) in detections.iter().enumerate() {
+            *buffer.add(i) = v;
+        }
+        Ok(())
+    })
+}
+
+/// Release results, doing nothing if they are null
+///
+/// # Safety
+///
+/// `results` must have been returned by an eval function and not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn furiosa_results_destroy(results: *mut Results) {
+    if !results.is_null() {
+        drop(Box::from_raw(results));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, s, Array5};
+
+    use super::*;
+
+    /// Create a postprocessor of a single layer of stride 8 and a single anchor
+    unsafe fn create() -> *mut Yolo {
+        let mut postprocessor = ptr::null_mut();
+        let status = furiosa_yolo_create(
+            [1.0, 1.0].as_ptr(),
+            1,
+            1,
+            [8.0].as_ptr(),
+            false,
+            false,
+            1,
+            &mut postprocessor,
+        );
+        assert_eq!(status, Status::Ok);
+        postprocessor
+    }
+
+    fn last_error() -> String {
+        let message = furiosa_last_error();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn unittest_yolo() {
+        unsafe {
+            let postprocessor = create();
+            // A single box of one class at the first cell of a 2x2 grid
+            let mut input = Array5::<f32>::zeros((1, 1, 2, 2, 6));
+            input.slice_mut(s![0, 0, 0, 0, ..]).assign(&arr1(&[0.5, 0.5, 0.5, 0.5, 0.9, 0.9]));
+            let shape = input.shape().to_vec();
+            let tensor = Tensor { data: input.as_ptr(), shape: shape.as_ptr(), ndim: shape.len() };
+
+            let mut results = ptr::null_mut();
+            let status = furiosa_yolo_eval(postprocessor, &tensor, 1, 0.25, 0.45, &mut results);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(furiosa_results_num_images(results), 1);
+            assert_eq!(furiosa_results_num_detections(results, 0), 1);
+            assert_eq!(furiosa_results_num_detections(results, 1), 0);
+
+            let mut buffer = [0.0f32; 6];
+            let status = furiosa_results_copy(results, 0, buffer.as_mut_ptr(), 5);
+            assert_eq!(status, Status::InvalidArgument);
+            assert!(last_error().contains("too small"));
+            let status = furiosa_results_copy(results, 0, buffer.as_mut_ptr(), 6);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(buffer, [0.0, 0.0, 8.0, 8.0, 0.9 * 0.9, 0.0]);
+            let status = furiosa_results_copy(results, 1, buffer.as_mut_ptr(), 6);
+            assert_eq!(status, Status::InvalidArgument);
+            assert!(last_error().contains("out of range"));
+            let status = furiosa_results_copy(results, 0, ptr::null_mut(), 6);
+            assert_eq!(status, Status::InvalidArgument);
+            furiosa_results_destroy(results);
+
+            // A second layer the postprocessor does not have
+            let tensors = [tensor, tensor];
+            let status =
+                furiosa_yolo_eval(postprocessor, tensors.as_ptr(), 2, 0.25, 0.45, &mut results);
+            assert_eq!(status, Status::InvalidArgument);
+            let status = furiosa_yolo_eval(postprocessor, ptr::null(), 1, 0.25, 0.45, &mut results);
+            assert_eq!(status, Status::InvalidArgument);
+            let status = furiosa_yolo_eval(ptr::null(), &tensor, 1, 0.25, 0.45, &mut results);
+            assert_eq!(status, Status::InvalidArgument);
+            assert_eq!(last_error(), "postprocessor must not be null");
+            let status = furiosa_yolo_eval(postprocessor, &tensor, 1, 0.25, 0.45, ptr::null_mut());
+            assert_eq!(status, Status::InvalidArgument);
+            let status = furiosa_results_copy(ptr::null(), 0, buffer.as_mut_ptr(), 6);
+            assert_eq!(status, Status::InvalidArgument);
+            assert_eq!(furiosa_results_num_images(ptr::null()), 0);
+
+            furiosa_yolo_destroy(postprocessor);
+            furiosa_yolo_destroy(ptr::null_mut());
+            furiosa_results_destroy(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn unittest_yolo_config() {
+        unsafe {
+            let postprocessor = create();
+            let mut length = 0;
+            let status = furiosa_yolo_config(postprocessor, ptr::null_mut(), 0, &mut length);
+            assert_eq!(status, Status::InvalidArgument);
+            assert!(length > 0);
+            let mut buffer = vec![0 as c_char; length + 1];
+            let status =
+                furiosa_yolo_config(postprocessor, buffer.as_mut_ptr(), length + 1, &mut length);
+            assert_eq!(status, Status::Ok);
+            furiosa_yolo_destroy(postprocessor);
+
+            let mut rebuilt = ptr::null_mut();
+            let status = furiosa_yolo_create_from_config(buffer.as_ptr(), &mut rebuilt);
+            assert_eq!(status, Status::Ok);
+            assert_eq!((*rebuilt).strides, [8.0]);
+            furiosa_yolo_destroy(rebuilt);
+
+            let status = furiosa_yolo_create_from_config(b"{\0".as_ptr().cast(), &mut rebuilt);
+            assert_eq!(status, Status::InvalidArgument);
+            let status = furiosa_yolo_create(
+                ptr::null(),
+                1,
+                1,
+                [8.0].as_ptr(),
+                false,
+                false,
+                1,
+                &mut rebuilt,
+            );
+            assert_eq!(status, Status::InvalidArgument);
+        }
+    }
+}
@@ -0,0 +1,678 @@
+//! Detection heads decoded from a declarative spec rather than model specific code
+//!
+//! Models whose heads are close to YOLOv5 but differ in their tensor layout, activation,
+//! anchors, box encoding or channel order are described by a [`DecoderSpec`], usually read from
+//! JSON, and decoded by a [`HeadDecoder`] assembled from it at runtime.
+
+#[cfg(feature = "python")]
+use itertools::Itertools;
+use ndarray::{ArrayView5, ArrayViewD, CowArray, Ix5, IxDyn};
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::common::{from_py, threads, to_py, warn_overflow};
+use crate::common::{Error, Result};
+use crate::postprocessor::{Postprocessor, Thresholds};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{self, RustPostprocessor};
+
+/// Maximum number of candidates decoded per image
+const MAX_BOXES: usize = 10_000;
+
+/// Layout of the output tensor of a head, whose channels are grouped by anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// (batch, anchor * channel, y, x)
+    #[default]
+    Nchw,
+    /// (batch, y, x, anchor * channel)
+    Nhwc,
+    /// (batch, anchor, y, x, channel)
+    Nahwc,
+}
+
+/// Activation of the channels the box encoding expects activated, and of the scores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    /// The model applies the sigmoid itself
+    #[default]
+    Identity,
+    /// The model outputs logits
+    Sigmoid,
+}
+
+impl Activation {
+    #[inline]
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Self::Identity => x,
+            Self::Sigmoid => 1.0 / (1.0 + f32::exp(-x)),
+        }
+    }
+}
+
+/// Encoding of the 4 box channels of a cell at (x, y) of a head of stride s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoxEncoding {
+    /// YOLOv5, of activated channels: center (2 t - 0.5 + cell) s and size (2 t)^2 anchor
+    #[default]
+    Yolov5,
+    /// YOLOv2/v3, of activated center channels: center (t + cell) s and size exp(t) anchor
+    Darknet,
+    /// YOLOX, anchor free: center (t + cell) s and size exp(t) s
+    Yolox,
+    /// Anchor free distances to the left, top, right and bottom edges from the center of the
+    /// cell (cell + 0.5) s, in units of the stride
+    Ltrb,
+}
+
+impl BoxEncoding {
+    fn anchor_free(self) -> bool {
+        matches!(self, Self::Yolox | Self::Ltrb)
+    }
+}
+
+/// Offsets of the channel groups within the channels of an anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Channels {
+    /// First of the 4 box channels
+    #[serde(default)]
+    pub boxes: usize,
+    /// Objectness multiplying the class scores, if any
+    pub objectness: Option<usize>,
+    /// First of the class channels
+    pub classes: usize,
+}
+
+impl Default for Channels {
+    /// (x, y, w, h, objectness, classes...) of YOLOv5
+    fn default() -> Self {
+        Self { boxes: 0, objectness: Some(4), classes: 5 }
+    }
+}
+
+/// Spec of an output head
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeadSpec {
+    pub stride: f32,
+    /// (width, height) of the anchors in pixels, empty for anchor free encodings
+    #[serde(default)]
+    pub anchors: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub activation: Activation,
+    #[serde(default)]
+    pub encoding: BoxEncoding,
+    #[serde(default)]
+    pub channels: Channels,
+}
+
+impl HeadSpec {
+    fn num_anchors(&self) -> usize {
+        self.anchors.len().max(1)
+    }
+}
+
+/// Spec of the decode of the output heads of a model, in the order of its outputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DecoderSpec {
+    pub num_classes: usize,
+    pub heads: Vec<HeadSpec>,
+    #[serde(default = "default_conf_threshold")]
+    pub conf_threshold: f32,
+    #[serde(default = "default_iou_threshold")]
+    pub iou_threshold: f32,
+    #[serde(default = "default_max_det")]
+    pub max_det: usize,
+    /// Whether NMS ignores the classes
+    #[serde(default)]
+    pub agnostic: bool,
+}
+
+fn default_conf_threshold() -> f32 {
+    yolo::DEFAULT_CONF_THRESHOLD
+}
+
+fn default_iou_threshold() -> f32 {
+    yolo::DEFAULT_IOU_THRESHOLD
+}
+
+fn default_max_det() -> usize {
+    yolo::DEFAULT_MAX_DET
+}
+
+impl DecoderSpec {
+    /// Spec of a JSON object with the keys of [`DecoderSpec`], each head being an object with
+    /// the keys of [`HeadSpec`]
+    /// `layout` is "nchw", "nhwc" or "nahwc", `activation` "identity" or "sigmoid", and
+    /// `encoding` "yolov5", "darknet", "yolox" or "ltrb". Thresholds, `max_det` and `agnostic`
+    /// default to those of YOLOv5, and the channels of a head to (x, y, w, h, objectness,
+    /// classes...).
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| Error::invalid(format!("invalid spec: {e}")))
+    }
+
+    /// JSON object of the spec, as read by [`DecoderSpec::from_json`]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a spec is always serializable")
+    }
+
+    /// Spec of a YAML mapping with the keys of [`DecoderSpec::from_json`]
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        serde_yaml::from_str(text).map_err(|e| Error::invalid(format!("invalid spec: {e}")))
+    }
+
+    /// YAML mapping of the spec, as read by [`DecoderSpec::from_yaml`]
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).expect("a spec is always serializable")
+    }
+}
+
+/// Decode of the output heads of a model described by a [`DecoderSpec`], keeping the best class
+/// of each anchor of each cell
+#[derive(Debug, Clone)]
+pub struct HeadDecoder {
+    spec: DecoderSpec,
+}
+
+impl HeadDecoder {
+    pub fn new(spec: DecoderSpec) -> Result<Self> {
+        if spec.num_classes == 0 {
+            return Err(Error::invalid("num_classes must be positive"));
+        }
+        if spec.heads.is_empty() {
+            return Err(Error::invalid("spec must have at least one head"));
+        }
+        for (i, head) in spec.heads.iter().enumerate() {
+            if !head.stride.is_finite() || head.stride <= 0.0 {
+                return Err(Error::invalid(format!(
+                    "heads[{i}].stride must be positive and finite but got {}",
+                    head.stride
+                )));
+            }
+            match (head.encoding.anchor_free(), head.anchors.is_empty()) {
+                (true, false) => {
+                    return Err(Error::invalid(format!(
+                        "heads[{i}] is anchor free but has anchors"
+                    )))
+                }
+                (false, true) => {
+                    return Err(Error::invalid(format!("heads[{i}] must have anchors")))
+                }
+                _ => {}
+            }
+        }
+        Ok(Self { spec })
+    }
+
+    pub fn spec(&self) -> &DecoderSpec {
+        &self.spec
+    }
+
+    /// Number of channels of each anchor the channel groups of a head need
+    fn min_channels(&self, head: &HeadSpec) -> usize {
+        let Channels { boxes, objectness, classes } = head.channels;
+        (boxes + 4).max(classes + self.spec.num_classes).max(objectness.map_or(0, |o| o + 1))
+    }
+
+    /// Input of a head viewed as (batch, anchor, y, x, channel), copied first unless it is
+    /// contiguous for the layouts whose anchors and channels share an axis
+    fn view<'a>(
+        &self,
+        index: usize,
+        head: &HeadSpec,
+        input: &'a CowArray<'_, f32, IxDyn>,
+    ) -> Result<ArrayView5<'a, f32>> {
+        let shape = input.shape();
+        let num_anchors = head.num_anchors();
+        let grouped = |channels: usize| {
+            (channels % num_anchors == 0 && channels / num_anchors >= self.min_channels(head))
+                .then_some(channels / num_anchors)
+        };
+        let view = match (head.layout, shape) {
+            (Layout::Nchw, &[b, c, h, w]) => grouped(c).map(|c| {
+                let view = input.view().into_shape((b, num_anchors, c, h, w)).unwrap();
+                view.permuted_axes([0, 1, 3, 4, 2])
+            }),
+            (Layout::Nhwc, &[b, h, w, c]) => grouped(c).map(|c| {
+                let view = input.view().into_shape((b, h, w, num_anchors, c)).unwrap();
+                view.permuted_axes([0, 3, 1, 2, 4])
+            }),
+            (Layout::Nahwc, &[_, a, _, _, c]) if a == num_anchors => {
+                grouped(c * a).map(|_| input.view().into_dimensionality::<Ix5>().unwrap())
+            }
+            _ => None,
+        };
+        view.ok_or_else(|| {
+            Error::invalid(format!(
+                "input {index} of shape {shape:?} does not match the {:?} layout of {num_anchors} \
+                 anchors of at least {} channels",
+                head.layout,
+                self.min_channels(head)
+            ))
+        })
+    }
+
+    /// Candidates of each image of a batch whose score exceeds `conf_threshold`, along with
+    /// whether the decode of each image dropped candidates beyond `MAX_BOXES`
+    pub fn decode_batch(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+    ) -> Result<Vec<(DetectionBoxes, bool)>> {
+        if inputs.len() != self.spec.heads.len() {
+            return Err(Error::invalid(format!(
+                "expected {} inputs, one per head, but got {}",
+                self.spec.heads.len(),
+                inputs.len()
+            )));
+        }
+        let inputs = inputs.iter().map(|input| input.as_standard_layout()).collect::<Vec<_>>();
+        let heads = self
+            .spec
+            .heads
+            .iter()
+            .zip(&inputs)
+            .enumerate()
+            .map(|(i, (head, input))| self.view(i, head, input))
+            .collect::<Result<Vec<_>>>()?;
+        let batch_size = heads[0].shape()[0];
+        if heads.iter().any(|head| head.shape()[0] != batch_size) {
+            return Err(Error::invalid("inputs must all have the same batch size"));
+        }
+        Ok((0..batch_size)
+            .into_par_iter()
+            .map(|image| self.decode_image(&heads, image, conf_threshold))
+            .collect())
+    }
+
+    /// Candidates of image `image` of the heads whose score exceeds `conf_threshold`, in pixels,
+    /// stopping at `MAX_BOXES` candidates, along with whether further ones were dropped
+    fn decode_image(
+        &self,
+        heads: &[ArrayView5<'_, f32>],
+        image: usize,
+        conf_threshold: f32,
+    ) -> (DetectionBoxes, bool) {
+        let mut fields: [Vec<f32>; 6] = Default::default();
+        let mut overflowed = false;
+        'image: for (head, input) in self.spec.heads.iter().zip(heads) {
+            let Channels { boxes, objectness, classes } = head.channels;
+            let act = |x: f32| head.activation.apply(x);
+            let &[_, num_anchors, height, width, _] = input.shape() else { unreachable!() };
+            for anchor in 0..num_anchors {
+                for y in 0..height {
+                    for x in 0..width {
+                        let cell = input.slice(ndarray::s![image, anchor, y, x, ..]);
+                        let objectness = match objectness {
+                            Some(o) => act(cell[o]),
+                            None => 1.0,
+                        };
+                        if objectness <= conf_threshold {
+                            continue;
+                        }
+                        let (class, score) =
+                            (0..self.spec.num_classes).map(|k| (k, act(cell[classes + k]))).fold(
+                                (0, f32::NEG_INFINITY),
+                                |best, c| if c.1 > best.1 { c } else { best },
+                            );
+                        let score = objectness * score;
+                        if score <= conf_threshold {
+                            continue;
+                        }
+                        if fields[0].len() >= MAX_BOXES {
+                            overflowed = true;
+                            break 'image;
+                        }
+                        let t = [cell[boxes], cell[boxes + 1], cell[boxes + 2], cell[boxes + 3]];
+                        let (gx, gy, s) = (x as f32, y as f32, head.stride);
+                        let [x1, y1, x2, y2] = match head.encoding {
+                            BoxEncoding::Yolov5 => {
+                                let [tx, ty, tw, th] = t.map(act);
+                                let [aw, ah] = head.anchors[anchor];
+                                centered(
+                                    (tx * 2.0 - 0.5 + gx) * s,
+                                    (ty * 2.0 - 0.5 + gy) * s,
+                                    (tw * 2.0).powi(2) * aw,
+                                    (th * 2.0).powi(2) * ah,
+                                )
+                            }
+                            BoxEncoding::Darknet => {
+                                let [aw, ah] = head.anchors[anchor];
+                                centered(
+                                    (act(t[0]) + gx) * s,
+                                    (act(t[1]) + gy) * s,
+                                    f32::exp(t[2]) * aw,
+                                    f32::exp(t[3]) * ah,
+                                )
+                            }
+                            BoxEncoding::Yolox => centered(
+                                (t[0] + gx) * s,
+                                (t[1] + gy) * s,
+                                f32::exp(t[2]) * s,
+                                f32::exp(t[3]) * s,
+                            ),
+                            BoxEncoding::Ltrb => {
+                                let (cx, cy) = ((gx + 0.5) * s, (gy + 0.5) * s);
+                                [cx - t[0] * s, cy - t[1] * s, cx + t[2] * s, cy + t[3] * s]
+                            }
+                        };
+                        for (field, value) in
+                            fields.iter_mut().zip([x1, y1, x2, y2, score, class as f32])
+                        {
+                            field.push(value);
+                        }
+                    }
+                }
+            }
+        }
+        let [x1, y1, x2, y2, scores, classes] = fields.map(Into::into);
+        (DetectionBoxes::new(x1, y1, x2, y2, scores, classes), overflowed)
+    }
+}
+
+#[inline]
+fn centered(cx: f32, cy: f32, w: f32, h: f32) -> [f32; 4] {
+    [cx - w * 0.5, cy - h * 0.5, cx + w * 0.5, cy + h * 0.5]
+}
+
+impl Postprocessor for HeadDecoder {
+    fn default_thresholds(&self) -> Thresholds {
+        Thresholds { conf: self.spec.conf_threshold, iou: self.spec.iou_threshold }
+    }
+
+    fn decode(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+    ) -> Result<Vec<DetectionBoxes>> {
+        let decoded = self.decode_batch(inputs, conf_threshold)?;
+        Ok(decoded.into_iter().map(|(candidates, _)| candidates).collect())
+    }
+
+    fn suppress(&self, candidates: &mut DetectionBoxes, thresholds: Thresholds) -> Vec<usize> {
+        RustPostprocessor::nms(
+            candidates,
+            thresholds.iou,
+            None,
+            self.spec.max_det,
+            self.spec.agnostic,
+        )
+    }
+}
+
+/// Spec of a dict with the keys of [`DecoderSpec::from_json`]
+#[cfg(feature = "python")]
+pub(crate) fn spec_from_dict(spec: &PyDict) -> PyResult<DecoderSpec> {
+    from_py(spec)
+}
+
+/// HeadDecoder
+///
+/// Decode of output heads described by a spec, for models close to YOLOv5 whose heads differ in
+/// their layout, activation, anchors, box encoding or channels, followed by NMS.
+///
+/// Args:
+///     spec (Dict[str, Any]): Spec with "num_classes", the optional "conf_threshold",
+///         "iou_threshold", "max_det" and "agnostic", and "heads", a list of one dict per output
+///         with its "stride", "anchors" as (width, height) pairs in pixels, "layout" ("nchw",
+///         "nhwc" or "nahwc"), "activation" ("identity" or "sigmoid"), "encoding" ("yolov5",
+///         "darknet", "yolox" or "ltrb") and "channels", a dict of the offsets of the "boxes",
+///         "objectness" and "classes" channels within those of an anchor
+#[cfg(feature = "python")]
+#[pyclass(name = "HeadDecoder", module = "furiosa_native_postprocess.head")]
+pub struct PyHeadDecoder(HeadDecoder);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyHeadDecoder {
+    #[new]
+    fn new(spec: &PyDict) -> PyResult<Self> {
+        Ok(Self(HeadDecoder::new(spec_from_dict(spec)?)?))
+    }
+
+    /// Decoder of a spec serialized as JSON
+    ///
+    /// Args:
+    ///     spec (str): JSON object of the spec
+    ///
+    /// Returns:
+    ///     HeadDecoder: Decoder of the spec
+    #[staticmethod]
+    fn from_json(spec: &str) -> PyResult<Self> {
+        Ok(Self(HeadDecoder::new(DecoderSpec::from_json(spec)?)?))
+    }
+
+    /// Decoder of a spec serialized as YAML
+    ///
+    /// Args:
+    ///     spec (str): Path of a YAML file or the YAML document itself
+    ///
+    /// Returns:
+    ///     HeadDecoder: Decoder of the spec
+    #[staticmethod]
+    fn from_yaml(spec: &str) -> PyResult<Self> {
+        let spec = match std::path::Path::new(spec).is_file() {
+            true => std::fs::read_to_string(spec)?,
+            false => spec.to_string(),
+        };
+        Ok(Self(HeadDecoder::new(DecoderSpec::from_yaml(&spec)?)?))
+    }
+
+    /// Spec of the decoder with all its keys
+    ///
+    /// Returns:
+    ///     Dict[str, Any]: Spec as taken by the constructor
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        Ok(to_py(py, &self.0.spec)?.into_ref(py).downcast()?)
+    }
+
+    /// Serialize the spec of the decoder as JSON
+    ///
+    /// Returns:
+    ///     str: JSON object of the spec
+    fn to_json(&self) -> String {
+        self.0.spec.to_json()
+    }
+
+    /// Serialize the spec of the decoder as YAML
+    ///
+    /// Returns:
+    ///     str: YAML mapping of the spec
+    fn to_yaml(&self) -> String {
+        self.0.spec.to_yaml()
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Output tensor of each head of dtype float32
+    ///     conf_threshold (Optional[float]): Minimum score of the detections, default is the one
+    ///         of the spec
+    ///     iou_threshold (Optional[float]): IoU threshold of NMS, default is the one of the spec
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Detections of each image of shape (N, 6) with rows
+    ///         (x1, y1, x2, y2, score, class) in pixels, a RuntimeWarning being raised if the
+    ///         decode of an image reached 10000 candidates and dropped the remaining ones
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArrayDyn<'_, f32>>,
+        conf_threshold: Option<f32>,
+        iou_threshold: Option<f32>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let defaults = self.0.default_thresholds();
+        let thresholds = Thresholds {
+            conf: conf_threshold.unwrap_or(defaults.conf),
+            iou: iou_threshold.unwrap_or(defaults.iou),
+        };
+        let views = inputs.iter().map(|input| input.as_array()).collect::<Vec<_>>();
+        let (candidates, overflowed): (Vec<_>, Vec<_>) =
+            threads::allow_threads(py, || self.0.decode_batch(&views, thresholds.conf))?
+                .into_iter()
+                .unzip();
+        let overflowed = overflowed.iter().positions(|&overflowed| overflowed).collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, MAX_BOXES)?;
+        let detections =
+            threads::allow_threads(py, || self.0.suppress_batch(candidates, thresholds));
+        Ok(detections
+            .into_iter()
+            .map(|detections| PyArray2::from_owned_array(py, detections).to_owned())
+            .collect())
+    }
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn head(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyHeadDecoder>()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr3, s, Array, Array5, Axis, ShapeBuilder};
+
+    use super::*;
+    use crate::yolo::{ClassMode, Layout as YoloLayout};
+
+    fn uniform<Sh: ShapeBuilder>(shape: Sh, seed: u32) -> Array<f32, Sh::Dim> {
+        let mut state = seed;
+        Array::from_shape_simple_fn(shape, || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        })
+    }
+
+    const YOLOV5: &str = r#"{
+        "num_classes": 2,
+        "heads": [{"stride": 8, "anchors": [[10, 13], [16, 30], [33, 23]], "layout": "nahwc"}]
+    }"#;
+
+    #[test]
+    fn unittest_head_matches_yolov5() {
+        let spec = DecoderSpec::from_json(YOLOV5).unwrap();
+        assert_eq!(DecoderSpec::from_json(&spec.to_json()).unwrap(), spec);
+        assert_eq!(DecoderSpec::from_yaml(&spec.to_yaml()).unwrap(), spec);
+        let decoder = HeadDecoder::new(spec).unwrap();
+        let anchors = arr3(&[[[10.0, 13.0], [16.0, 30.0], [33.0, 23.0]]]) / 8.0;
+        let mut yolo = RustPostprocessor::new(
+            anchors,
+            vec![8.0],
+            None,
+            Some(1),
+            YoloLayout::Nahwc,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        yolo.class_mode = ClassMode::Best;
+
+        let output = uniform((2, 3, 16, 16, 7), 3);
+        let inputs = [output.view().into_dyn()];
+        let thresholds = decoder.default_thresholds();
+        let expected = Postprocessor::postprocess(&yolo, &inputs, thresholds).unwrap();
+        let results = decoder.postprocess(&inputs, thresholds).unwrap();
+        for (result, expected) in results.iter().zip(&expected) {
+            assert!(!result.is_empty());
+            assert_eq!(result.shape(), expected.shape());
+            for (a, b) in result.iter().zip(expected) {
+                assert!((a - b).abs() < 1e-3, "{a} != {b}");
+            }
+        }
+
+        // The same head as (batch, anchor * channel, y, x) and (batch, y, x, anchor * channel)
+        for (layout, permuted) in [("nchw", [0, 1, 4, 2, 3]), ("nhwc", [0, 2, 3, 1, 4])] {
+            let spec = YOLOV5.replace("nahwc", layout);
+            let decoder = HeadDecoder::new(DecoderSpec::from_json(&spec).unwrap()).unwrap();
+            let input = output.view().permuted_axes(permuted).as_standard_layout().into_owned();
+            let shape = input.shape().to_vec();
+            let grouped = match layout {
+                "nchw" => input.into_shape((shape[0], 21, shape[3], shape[4])).unwrap(),
+                _ => input.into_shape((shape[0], shape[1], shape[2], 21)).unwrap(),
+            };
+            let decoded = decoder.postprocess(&[grouped.view().into_dyn()], thresholds).unwrap();
+            assert_eq!(decoded, results);
+        }
+    }
+
+    #[test]
+    fn unittest_head_anchor_free() {
+        let spec = r#"{
+            "num_classes": 3,
+            "heads": [{
+                "stride": 16, "encoding": "ltrb", "activation": "sigmoid",
+                "channels": {"boxes": 3, "objectness": null, "classes": 0}
+            }]
+        }"#;
+        let decoder = HeadDecoder::new(DecoderSpec::from_json(spec).unwrap()).unwrap();
+        // Class 1 at cell (x, y) = (2, 1), 1 stride from the left and top of its center, 2 from
+        // the right and bottom
+        let mut output = Array5::from_elem((1, 1, 7, 4, 4), -10f32);
+        output.slice_mut(s![0, 0, 3.., 1, 2]).assign(&ndarray::arr1(&[1.0, 1.0, 2.0, 2.0]));
+        output[(0, 0, 1, 1, 2)] = 10.0;
+        let output = output.index_axis_move(Axis(1), 0);
+        let results =
+            decoder.postprocess(&[output.view().into_dyn()], Thresholds { conf: 0.5, iou: 0.5 });
+        let [result] = &results.unwrap()[..] else { panic!("expected a single image") };
+        assert_eq!(result.shape(), [1, 6]);
+        assert_eq!(result.slice(s![0, ..4]), ndarray::arr1(&[24.0, 8.0, 72.0, 56.0]));
+        assert_eq!(result[(0, 5)], 1.0);
+
+        let anchors = spec.replace("\"stride\": 16", "\"stride\": 16, \"anchors\": [[1, 1]]");
+        assert!(HeadDecoder::new(DecoderSpec::from_json(&anchors).unwrap()).is_err());
+        assert!(DecoderSpec::from_json(&spec.replace("ltrb", "ltbr")).is_err());
+        assert!(DecoderSpec::from_json(&spec.replace("stride", "strides")).is_err());
+        let channels = decoder.postprocess(
+            &[output.slice(s![.., ..6, .., ..]).into_dyn()],
+            Thresholds { conf: 0.5, iou: 0.5 },
+        );
+        assert!(channels.is_err());
+    }
+
+    #[test]
+    fn unittest_head_overflow() {
+        let spec = r#"{
+            "num_classes": 1,
+            "heads": [{
+                "stride": 1, "encoding": "ltrb",
+                "channels": {"boxes": 1, "objectness": null, "classes": 0}
+            }]
+        }"#;
+        let decoder = HeadDecoder::new(DecoderSpec::from_json(spec).unwrap()).unwrap();
+        // Every cell scores 0.5 for class 0
+        let decode = |height: usize, conf_threshold: f32| {
+            let mut output = Array::from_elem((1, 5, height, 100), 1f32);
+            output.slice_mut(s![.., 0, .., ..]).fill(0.5);
+            let [decoded] =
+                &decoder.decode_batch(&[output.into_dyn().view()], conf_threshold).unwrap()[..]
+            else {
+                panic!("expected a single image")
+            };
+            (decoded.0.len(), decoded.1)
+        };
+        // Scores equal to the threshold are dropped
+        assert_eq!(decode(10, 0.5), (0, false));
+        assert_eq!(decode(10, 0.25), (1000, false));
+        // Only candidates actually dropped overflow
+        assert_eq!(decode(100, 0.25), (MAX_BOXES, false));
+        assert_eq!(decode(101, 0.25), (MAX_BOXES, true));
+    }
+}
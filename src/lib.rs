@@ -1,14 +1,109 @@
 #![deny(unused_extern_crates)]
 #![feature(vec_into_raw_parts)]
+// AVX-512 kernels of the NMS
+#![feature(stdsimd, avx512_target_feature)]
 #![allow(clippy::borrow_deref_ref)]
 
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
 
+#[cfg(feature = "python")]
+pub mod bert;
+#[cfg(feature = "python")]
+pub mod box_ops;
+#[cfg(feature = "python")]
+pub mod classification;
+#[cfg(feature = "python")]
+pub mod coco;
 pub mod common;
+pub mod crop;
+#[cfg(feature = "python")]
+pub mod ctc;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+#[cfg(feature = "python")]
+pub mod detr;
+#[cfg(feature = "python")]
+pub mod efficientdet;
+pub mod ensemble;
+#[cfg(feature = "python")]
+pub mod eval;
+#[cfg(feature = "python")]
+pub mod fcos;
+pub mod ffi;
+pub mod head;
+#[cfg(feature = "python")]
+pub mod maskrcnn;
+pub mod mlperf;
+#[cfg(feature = "python")]
+pub mod obb;
+pub mod openimages;
+#[cfg(feature = "python")]
+pub mod pose;
+pub mod postprocessor;
+pub mod preprocess;
+#[cfg(feature = "python")]
+pub mod retinanet;
+#[cfg(feature = "python")]
+pub mod segmentation;
+pub mod ssd;
 pub mod ssd_large;
 pub mod ssd_small;
+pub mod tiling;
+pub mod tracking;
+#[cfg(feature = "python")]
+pub mod unet3d;
+pub mod viz;
 pub mod yolo;
+#[cfg(feature = "python")]
+pub mod yolov8;
+#[cfg(feature = "python")]
+pub mod yolox;
 
+// Postprocessors are shared rather than copied per thread: Python threads call the same instance
+// concurrently with the GIL released, so their state must be immutable or behind locks. Adding a
+// `Cell` or an unsynchronized buffer to one of them fails to compile here.
+const _: () = {
+    const fn shareable<T: Send + Sync>() {}
+
+    #[cfg(feature = "python")]
+    shareable::<bert::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<classification::TopKPostprocessor>();
+    #[cfg(feature = "cuda")]
+    shareable::<cuda::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<ctc::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<detr::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<efficientdet::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<fcos::RustPostprocessor>();
+    shareable::<head::HeadDecoder>();
+    #[cfg(feature = "python")]
+    shareable::<maskrcnn::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<obb::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<pose::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<retinanet::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<segmentation::RustPostprocessor>();
+    shareable::<ssd::SsdPostprocessor>();
+    shareable::<ssd_large::RustPostprocessor>();
+    shareable::<ssd_small::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<unet3d::RustPostprocessor>();
+    shareable::<yolo::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<yolov8::RustPostprocessor>();
+    #[cfg(feature = "python")]
+    shareable::<yolox::RustPostprocessor>();
+};
+
+#[cfg(feature = "python")]
 fn add_submodule(
     m: &PyModule,
     init_submodule: fn(&PyModule) -> PyResult<()>,
@@ -28,15 +123,58 @@ fn add_submodule(
     Ok(())
 }
 
+#[cfg(feature = "python")]
 #[pymodule]
 fn furiosa_native_postprocess(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
 
     m.add("__version__", VERSION)?;
+    common::logging::init_from_env()?;
+    m.add_function(wrap_pyfunction!(common::wbf::wbf, m)?)?;
+    m.add_function(wrap_pyfunction!(common::nms::nms, m)?)?;
+    m.add_function(wrap_pyfunction!(common::threads::set_num_threads_py, m)?)?;
+    m.add_function(wrap_pyfunction!(common::threads::get_num_threads_py, m)?)?;
+    m.add_function(wrap_pyfunction!(common::logging::set_log_filter_py, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocessor::create_postprocessor_py, m)?)?;
+    m.add_class::<common::PyDetectionResult>()?;
+    m.add_class::<common::PyDetectionResults>()?;
+    m.add_class::<common::PySegmentationResults>()?;
+    m.add_class::<common::PyPoseResults>()?;
+    m.add_class::<common::PyObbResults>()?;
+    m.add_class::<postprocessor::PyPostprocessor>()?;
 
+    add_submodule(m, ssd::ssd, "ssd")?;
     add_submodule(m, ssd_large::ssd_resnet34, "ssd_resnet34")?;
     add_submodule(m, ssd_small::ssd_mobilenet, "ssd_mobilenet")?;
+    add_submodule(m, detr::detr, "detr")?;
+    add_submodule(m, retinanet::retinanet, "retinanet")?;
     add_submodule(m, yolo::yolo, "yolo")?;
+    add_submodule(m, yolov8::yolov8, "yolov8")?;
+    add_submodule(m, yolox::yolox, "yolox")?;
+    add_submodule(m, head::head, "head")?;
+    add_submodule(m, efficientdet::efficientdet, "efficientdet")?;
+    add_submodule(m, maskrcnn::maskrcnn, "maskrcnn")?;
+    add_submodule(m, pose::pose, "pose")?;
+    add_submodule(m, fcos::fcos, "fcos")?;
+    add_submodule(m, segmentation::segmentation, "segmentation")?;
+    add_submodule(m, unet3d::unet3d, "unet3d")?;
+    add_submodule(m, classification::classification, "classification")?;
+    add_submodule(m, bert::bert, "bert")?;
+    add_submodule(m, ctc::ctc, "ctc")?;
+    add_submodule(m, obb::obb, "obb")?;
+    add_submodule(m, coco::coco, "coco")?;
+    add_submodule(m, eval::eval, "eval")?;
+    add_submodule(m, mlperf::mlperf, "mlperf")?;
+    add_submodule(m, box_ops::box_ops, "box_ops")?;
+    add_submodule(m, tracking::tracking, "tracking")?;
+    add_submodule(m, tiling::tiling, "tiling")?;
+    add_submodule(m, ensemble::ensemble, "ensemble")?;
+    add_submodule(m, openimages::openimages, "openimages")?;
+    add_submodule(m, viz::viz, "viz")?;
+    add_submodule(m, crop::crop, "crop")?;
+    add_submodule(m, preprocess::preprocess, "preprocess")?;
+    #[cfg(feature = "cuda")]
+    add_submodule(m, cuda::cuda, "cuda")?;
 
     // backward compatibility
     add_submodule(m, yolo::yolo, "yolov5")?;
@@ -0,0 +1,350 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{s, Array2, Array3, ArrayView2, ArrayView3};
+use numpy::{PyArray2, PyArray3, PyReadonlyArray2, PyReadonlyArray3};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor as AnchorPostprocessor, DEFAULT_MAX_DET};
+
+// Same as torchvision's `BoxCoder` of `RoIHeads`
+const BOX_CODER_WEIGHTS: [f32; 4] = [10.0, 10.0, 5.0, 5.0];
+const BBOX_XFORM_CLIP: f32 = 4.135_166_5; // ln(1000 / 16)
+const MIN_BOX_SIZE: f32 = 1e-2;
+// One pixel of zero padding around each mask before pasting
+const MASK_PADDING: usize = 1;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub max_detections: usize,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RustPostProcessor {{ max_detections: {} }}", self.max_detections)
+    }
+}
+
+impl RustPostprocessor {
+    fn new(max_detections: Option<usize>) -> Self {
+        const DEFAULT_MAX_DETECTIONS: usize = 100;
+        Self { max_detections: max_detections.unwrap_or(DEFAULT_MAX_DETECTIONS) }
+    }
+
+    /// Decode box head outputs of one image into per-class candidates, skipping background
+    fn box_decode(
+        &self,
+        class_logits: ArrayView2<'_, f32>,
+        box_regression: ArrayView2<'_, f32>,
+        proposals: ArrayView2<'_, f32>,
+        image_shape: (f32, f32),
+        conf_threshold: f32,
+    ) -> DetectionBoxes {
+        let (height, width) = image_shape;
+        let [wx, wy, ww, wh] = BOX_CODER_WEIGHTS;
+
+        let mut x1 = Vec::new();
+        let mut y1 = Vec::new();
+        let mut x2 = Vec::new();
+        let mut y2 = Vec::new();
+        let mut scores = Vec::new();
+        let mut classes = Vec::new();
+
+        for (logits, deltas, proposal) in
+            izip!(class_logits.outer_iter(), box_regression.outer_iter(), proposals.outer_iter())
+        {
+            let max = logits.fold(f32::NEG_INFINITY, |acc, &v| f32::max(acc, v));
+            let sum: f32 = logits.iter().map(|&v| f32::exp(v - max)).sum();
+
+            let pw = proposal[2] - proposal[0];
+            let ph = proposal[3] - proposal[1];
+            let pcx = proposal[0] + 0.5 * pw;
+            let pcy = proposal[1] + 0.5 * ph;
+
+            // Class 0 is background
+            for (class, &logit) in logits.iter().enumerate().skip(1) {
+                let score = f32::exp(logit - max) / sum;
+                if score <= conf_threshold {
+                    continue;
+                }
+
+                let dx = deltas[class * 4] / wx;
+                let dy = deltas[class * 4 + 1] / wy;
+                let dw = f32::min(deltas[class * 4 + 2] / ww, BBOX_XFORM_CLIP);
+                let dh = f32::min(deltas[class * 4 + 3] / wh, BBOX_XFORM_CLIP);
+
+                let cx = dx * pw + pcx;
+                let cy = dy * ph + pcy;
+                let w = f32::exp(dw) * pw;
+                let h = f32::exp(dh) * ph;
+
+                let bx1 = (cx - 0.5 * w).clamp(0.0, width);
+                let by1 = (cy - 0.5 * h).clamp(0.0, height);
+                let bx2 = (cx + 0.5 * w).clamp(0.0, width);
+                let by2 = (cy + 0.5 * h).clamp(0.0, height);
+                if bx2 - bx1 < MIN_BOX_SIZE || by2 - by1 < MIN_BOX_SIZE {
+                    continue;
+                }
+
+                x1.push(bx1);
+                y1.push(by1);
+                x2.push(bx2);
+                y2.push(by2);
+                scores.push(score);
+                classes.push(class as f32);
+            }
+        }
+
+        DetectionBoxes::new(
+            x1.into(),
+            y1.into(),
+            x2.into(),
+            y2.into(),
+            scores.into(),
+            classes.into(),
+        )
+    }
+
+    /// Mask R-CNN box postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        class_logits: &[ArrayView2<'_, f32>],
+        box_regression: &[ArrayView2<'_, f32>],
+        proposals: &[ArrayView2<'_, f32>],
+        image_shapes: &[(f32, f32)],
+        conf_threshold: f32,
+        iou_threshold: f32,
+    ) -> Vec<Array2<f32>> {
+        izip!(class_logits, box_regression, proposals, image_shapes)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(class_logits, box_regression, proposals, &image_shape)| {
+                let mut dbox = self.box_decode(
+                    class_logits.view(),
+                    box_regression.view(),
+                    proposals.view(),
+                    image_shape,
+                    conf_threshold,
+                );
+                let mut indices =
+                    AnchorPostprocessor::nms(&dbox, iou_threshold, None, DEFAULT_MAX_DET, false);
+                indices.truncate(self.max_detections);
+                dbox.select_and_convert(&indices)
+            })
+            .collect()
+    }
+
+    /// Bilinear sample of `mask` with PyTorch's `align_corners=False` convention
+    #[inline]
+    fn bilinear(mask: &Array2<f32>, y: f32, x: f32) -> f32 {
+        let (h, w) = mask.dim();
+        let y = y.max(0.0);
+        let x = x.max(0.0);
+        let y0 = (y as usize).min(h - 1);
+        let x0 = (x as usize).min(w - 1);
+        let y1 = (y0 + 1).min(h - 1);
+        let x1 = (x0 + 1).min(w - 1);
+        let (ly, lx) = (y - y0 as f32, x - x0 as f32);
+        let (ly, lx) = (ly.min(1.0), lx.min(1.0));
+
+        (1.0 - ly) * ((1.0 - lx) * mask[[y0, x0]] + lx * mask[[y0, x1]])
+            + ly * ((1.0 - lx) * mask[[y1, x0]] + lx * mask[[y1, x1]])
+    }
+
+    /// Project per-detection mask probabilities into full-image binary masks, like torchvision's
+    /// `paste_masks_in_image`
+    fn paste_masks(
+        &self,
+        masks: ArrayView3<'_, f32>,
+        detections: ArrayView2<'_, f32>,
+        image_shape: (usize, usize),
+        threshold: f32,
+    ) -> Array3<u8> {
+        let (im_h, im_w) = image_shape;
+        let (n, m_h, m_w) = masks.dim();
+        let mut pasted = Array3::<u8>::zeros((n, im_h, im_w));
+
+        let im_masks = pasted.outer_iter_mut().collect::<Vec<_>>();
+        im_masks.into_par_iter().enumerate().for_each(|(i, mut im_mask)| {
+            // Pad the mask and expand its box accordingly
+            let mut padded = Array2::<f32>::zeros((m_h + 2 * MASK_PADDING, m_w + 2 * MASK_PADDING));
+            padded
+                .slice_mut(s![MASK_PADDING..m_h + MASK_PADDING, MASK_PADDING..m_w + MASK_PADDING])
+                .assign(&masks.slice(s![i, .., ..]));
+            let scale_x = (m_w + 2 * MASK_PADDING) as f32 / m_w as f32;
+            let scale_y = (m_h + 2 * MASK_PADDING) as f32 / m_h as f32;
+
+            let det = detections.row(i);
+            let (cx, cy) = ((det[0] + det[2]) * 0.5, (det[1] + det[3]) * 0.5);
+            let (hw, hh) = ((det[2] - det[0]) * 0.5 * scale_x, (det[3] - det[1]) * 0.5 * scale_y);
+            let (bx1, by1) = ((cx - hw) as i64, (cy - hh) as i64);
+            let (bx2, by2) = ((cx + hw) as i64, (cy + hh) as i64);
+
+            let w = i64::max(bx2 - bx1 + 1, 1);
+            let h = i64::max(by2 - by1 + 1, 1);
+            let (sx, sy) = (padded.dim().1 as f32 / w as f32, padded.dim().0 as f32 / h as f32);
+
+            let x0 = bx1.clamp(0, im_w as i64);
+            let x1 = (bx2 + 1).clamp(0, im_w as i64);
+            let y0 = by1.clamp(0, im_h as i64);
+            let y1 = (by2 + 1).clamp(0, im_h as i64);
+
+            for y in y0..y1 {
+                let src_y = ((y - by1) as f32 + 0.5) * sy - 0.5;
+                for x in x0..x1 {
+                    let src_x = ((x - bx1) as f32 + 0.5) * sx - 0.5;
+                    if Self::bilinear(&padded, src_y, src_x) > threshold {
+                        im_mask[[y as usize, x as usize]] = 1;
+                    }
+                }
+            }
+        });
+
+        pasted
+    }
+}
+
+/// Mask R-CNN PostProcessor
+///
+/// It decodes the RoI box head (softmax scoring, per-class box regression with weights
+/// (10, 10, 5, 5), score thresholding, class-aware NMS) and pastes mask head outputs
+/// into full-image binary masks.
+///
+/// Args:
+///     max_detections (Optional[int]): Maximum number of detections per image, default is 100
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(max_detections: Option<usize>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(max_detections)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the box head postprocess
+    ///
+    /// Args:
+    ///     class_logits (Sequence[numpy.ndarray]): Class logits of shape (R, num_classes) per image
+    ///     box_regression (Sequence[numpy.ndarray]): Box deltas of shape (R, num_classes * 4) per image
+    ///     proposals (Sequence[numpy.ndarray]): Proposal boxes of shape (R, 4) per image in xyxy
+    ///     image_shapes (Sequence[Tuple[float, float]]): (height, width) of each image
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): IoU threshold
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    #[allow(clippy::too_many_arguments)]
+    fn eval(
+        &self,
+        py: Python<'_>,
+        class_logits: Vec<PyReadonlyArray2<'_, f32>>,
+        box_regression: Vec<PyReadonlyArray2<'_, f32>>,
+        proposals: Vec<PyReadonlyArray2<'_, f32>>,
+        image_shapes: Vec<(f32, f32)>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let batch_size = class_logits.len();
+        if box_regression.len() != batch_size
+            || proposals.len() != batch_size
+            || image_shapes.len() != batch_size
+        {
+            return Err(PyValueError::new_err(format!(
+                "expected {batch_size} images but got {} box regressions, {} proposals and {} image shapes",
+                box_regression.len(),
+                proposals.len(),
+                image_shapes.len()
+            )));
+        }
+
+        let class_logits = class_logits.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let box_regression = box_regression.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let proposals = proposals.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        for (i, (logits, deltas, proposals)) in
+            izip!(&class_logits, &box_regression, &proposals).enumerate()
+        {
+            let (r, c) = logits.dim();
+            if deltas.dim() != (r, c * 4) || proposals.dim() != (r, 4) {
+                return Err(PyValueError::new_err(format!(
+                    "image {i}: expected box regression of shape ({r}, {}) and proposals of shape ({r}, 4) but got {:?} and {:?}",
+                    c * 4,
+                    deltas.shape(),
+                    proposals.shape()
+                )));
+            }
+        }
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(
+                &class_logits,
+                &box_regression,
+                &proposals,
+                &image_shapes,
+                conf_threshold,
+                iou_threshold,
+            )
+        });
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+
+    /// Paste mask head outputs into full-image binary masks
+    ///
+    /// Args:
+    ///     masks (numpy.ndarray): Mask probabilities of the detected class, shape (N, M, M)
+    ///     detections (numpy.ndarray): Detection results of one image returned by `eval`
+    ///     image_shape (Tuple[int, int]): (height, width) of the image
+    ///     threshold (Optional[float]): Mask probability threshold, default is 0.5
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Binary masks of shape (N, height, width)
+    fn paste_masks(
+        &self,
+        py: Python<'_>,
+        masks: PyReadonlyArray3<'_, f32>,
+        detections: PyReadonlyArray2<'_, f32>,
+        image_shape: (usize, usize),
+        threshold: Option<f32>,
+    ) -> PyResult<Py<PyArray3<u8>>> {
+        const DEFAULT_THRESHOLD: f32 = 0.5;
+
+        let (masks, detections) = (masks.as_array(), detections.as_array());
+        if masks.shape()[0] != detections.shape()[0] || detections.shape()[1] < 4 {
+            return Err(PyValueError::new_err(format!(
+                "expected one mask per detection but got {} masks and detections of shape {:?}",
+                masks.shape()[0],
+                detections.shape()
+            )));
+        }
+
+        let pasted = self.0.paste_masks(
+            masks,
+            detections,
+            image_shape,
+            threshold.unwrap_or(DEFAULT_THRESHOLD),
+        );
+        Ok(PyArray3::from_owned_array(py, pasted).to_owned())
+    }
+}
+
+pub(crate) fn maskrcnn(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
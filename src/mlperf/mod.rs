@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+use std::mem;
+#[cfg(feature = "python")]
+use std::{fs::File, io::BufWriter};
+
+#[cfg(feature = "python")]
+use numpy::PyArray1;
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+#[cfg(feature = "python")]
+use crate::common::extract_detections;
+#[cfg(feature = "python")]
+use crate::common::labels::CategoryIds;
+use crate::common::ssd_postprocess::DetectionResults;
+#[cfg(feature = "python")]
+use crate::common::threads;
+
+/// Pack the detections of an image into the QuerySampleResponse data of LoadGen, rows of
+/// (image_id, ymin, xmin, ymax, xmax, score, class), returning the buffer and its length in bytes
+pub fn pack_response(mut results: DetectionResults, image_id: f32) -> (Vec<f32>, usize) {
+    for r in results.iter_mut() {
+        r.index = image_id;
+    }
+    let data = results.into_fields();
+    let byte_len = data.len() * mem::size_of::<f32>();
+    (data, byte_len)
+}
+
+/// Write the entries of `mlperf_log_accuracy.json` as LoadGen logs them in accuracy mode
+///
+/// The detections of each image are packed by [`pack_response`] with the image id being its
+/// index in the query sample library, then hex encoded.
+pub fn write_accuracy_log<W: Write>(
+    writer: &mut W,
+    results: Vec<DetectionResults>,
+    qsl_indices: &[usize],
+) -> io::Result<()> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    if results.len() != qsl_indices.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("expected {} qsl indices but got {}", results.len(), qsl_indices.len()),
+        ));
+    }
+
+    let mut hex = Vec::new();
+    writer.write_all(b"[")?;
+    for (seq_id, (detections, &qsl_idx)) in results.into_iter().zip(qsl_indices).enumerate() {
+        let (data, _) = pack_response(detections, qsl_idx as f32);
+        hex.clear();
+        for byte in data.iter().flat_map(|v| v.to_le_bytes()) {
+            hex.push(HEX[(byte >> 4) as usize]);
+            hex.push(HEX[(byte & 0xf) as usize]);
+        }
+        if seq_id > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "\n{{ \"seq_id\" : {seq_id}, \"qsl_idx\" : {qsl_idx}, \"data\" : \"")?;
+        writer.write_all(&hex)?;
+        writer.write_all(b"\" }")?;
+    }
+    writer.write_all(b"\n]\n")?;
+    writer.flush()
+}
+
+/// Pack the detections of an image into the QuerySampleResponse data of LoadGen
+///
+/// Args:
+///     results (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the image,
+///         either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+///         PyDetectionResult
+///     image_id (int): Index of the image in the query sample library
+///     category_ids (Optional[Union[str, Sequence[int]]]): Category id each class is remapped
+///         to, or "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids
+///         the accuracy script expects, default is the class index itself
+///
+/// Returns:
+///     Tuple[numpy.ndarray, int]: Contiguous float32 array of rows (image_id, ymin, xmin, ymax,
+///         xmax, score, class) and its length in bytes, to be passed to QuerySampleResponse as
+///         `array.ctypes.data` and the length
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "pack_response")]
+fn pack_response_py(
+    py: Python<'_>,
+    results: &PyAny,
+    image_id: i64,
+    category_ids: Option<CategoryIds>,
+) -> PyResult<(Py<PyArray1<f32>>, usize)> {
+    let mut results = extract_detections(results)?;
+    if let Some(category_ids) = &category_ids {
+        category_ids.remap(&mut results)?;
+    }
+    let (data, byte_len) = pack_response(results, image_id as f32);
+    Ok((PyArray1::from_vec(py, data).to_owned(), byte_len))
+}
+
+/// Write `mlperf_log_accuracy.json` from the detections of the queried samples
+///
+/// Args:
+///     results (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+///         sample, either arrays of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or lists
+///         of PyDetectionResult
+///     qsl_indices (Sequence[int]): Index of each sample in the query sample library
+///     path (str): Path of the accuracy log to write
+///     category_ids (Optional[Union[str, Sequence[int]]]): Category id each class is remapped
+///         to, or "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids
+///         the accuracy script expects, default is the class index itself
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "write_accuracy_log")]
+fn write_accuracy_log_py(
+    py: Python<'_>,
+    results: Vec<&PyAny>,
+    qsl_indices: Vec<usize>,
+    path: &str,
+    category_ids: Option<CategoryIds>,
+) -> PyResult<()> {
+    let mut results =
+        results.into_iter().map(extract_detections).collect::<PyResult<Vec<DetectionResults>>>()?;
+    if let Some(category_ids) = &category_ids {
+        for results in &mut results {
+            category_ids.remap(results)?;
+        }
+    }
+
+    threads::allow_threads(py, || {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_accuracy_log(&mut writer, results, &qsl_indices)
+    })
+    .map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidInput => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    })
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn mlperf(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(pack_response_py, m)?)?;
+    m.add_function(wrap_pyfunction!(write_accuracy_log_py, m)?)?;
+
+    Ok(())
+}
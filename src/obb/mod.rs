@@ -0,0 +1,284 @@
+pub mod rotated;
+use std::f32::consts::PI;
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{Array2, Array3, ArrayView3, ArrayView4};
+use numpy::{PyArray2, PyArray3, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use rotated::RotatedBox;
+
+use crate::common::{descending, logit, sigmoid, threads, validate_batch, PyObbResults};
+
+const MAX_BOXES: usize = 10_000;
+const MAX_NMS: usize = 300;
+const DEFAULT_REG_MAX: usize = 16;
+
+/// A decoded candidate of one image
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    rbox: RotatedBox,
+    score: f32,
+    class: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub strides: Vec<f32>,
+    pub reg_max: usize,
+    pub agnostic: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ num_detection_layers: {}, reg_max: {}, strides: {:?}, agnostic: {} }}",
+            self.strides.len(),
+            self.reg_max,
+            self.strides,
+            self.agnostic
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(strides: Vec<f32>, reg_max: Option<usize>, agnostic: Option<bool>) -> PyResult<Self> {
+        let reg_max = reg_max.unwrap_or(DEFAULT_REG_MAX);
+        if strides.is_empty() {
+            return Err(PyValueError::new_err("strides must not be empty"));
+        }
+        if reg_max == 0 {
+            return Err(PyValueError::new_err("reg_max must be positive"));
+        }
+        Ok(Self { strides, reg_max, agnostic: agnostic.unwrap_or(false) })
+    }
+
+    /// Distribution Focal Loss decoding: the expectation of the softmax over `reg_max` bins
+    #[inline]
+    fn dfl(&self, feature: &ArrayView3<'_, f32>, side: usize, y: usize, x: usize) -> f32 {
+        let base = side * self.reg_max;
+        let mut max = f32::NEG_INFINITY;
+        for i in 0..self.reg_max {
+            max = f32::max(max, unsafe { *feature.uget((base + i, y, x)) });
+        }
+
+        let (mut sum, mut weighted) = (0f32, 0f32);
+        for i in 0..self.reg_max {
+            let e = f32::exp(unsafe { *feature.uget((base + i, y, x)) } - max);
+            sum += e;
+            weighted += e * i as f32;
+        }
+        weighted / sum
+    }
+
+    /// YOLOv8-OBB decode of one image, each layer having `4 * reg_max + nc + 1` channels with
+    /// the rotation angle last
+    fn box_decode(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+    ) -> Vec<Candidate> {
+        let box_channels = 4 * self.reg_max;
+        let logit_threshold = logit(conf_threshold);
+
+        let mut candidates = Vec::new();
+        for (&stride, input) in izip!(&self.strides, inputs) {
+            let feature = input.index_axis(ndarray::Axis(0), batch_index);
+            let &[channels, height, width] = feature.shape() else { unreachable!() };
+            let angle_channel = channels - 1;
+
+            for y in 0..height {
+                for x in 0..width {
+                    let mut decoded = None;
+                    for c in box_channels..angle_channel {
+                        let class_logit = unsafe { *feature.uget((c, y, x)) };
+                        if class_logit <= logit_threshold {
+                            continue;
+                        }
+                        if candidates.len() >= MAX_BOXES {
+                            return candidates;
+                        }
+
+                        // dist2rbox: rotate the center offset implied by the ltrb distances
+                        let &mut rbox = decoded.get_or_insert_with(|| {
+                            let [l, t, r, b] =
+                                [0, 1, 2, 3].map(|side| self.dfl(&feature, side, y, x));
+                            let angle = (sigmoid(unsafe { *feature.uget((angle_channel, y, x)) })
+                                - 0.25)
+                                * PI;
+                            let (sin, cos) = angle.sin_cos();
+                            let (xf, yf) = ((r - l) * 0.5, (b - t) * 0.5);
+                            RotatedBox {
+                                cx: (xf * cos - yf * sin + x as f32 + 0.5) * stride,
+                                cy: (xf * sin + yf * cos + y as f32 + 0.5) * stride,
+                                w: (l + r) * stride,
+                                h: (t + b) * stride,
+                                angle,
+                            }
+                        });
+                        candidates.push(Candidate {
+                            rbox,
+                            score: sigmoid(class_logit),
+                            class: c - box_channels,
+                        });
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Greedy rotated Non-Maximum Suppression based on the exact rotated IoU
+    fn rotated_nms(candidates: &[Candidate], iou_threshold: f32, agnostic: bool) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..candidates.len()).collect();
+        indices.sort_unstable_by(|&i, &j| {
+            descending(candidates[i].score, candidates[j].score).then(i.cmp(&j))
+        });
+
+        let mut results: Vec<usize> = Vec::new();
+        for i in indices {
+            if results.len() >= MAX_NMS {
+                break;
+            }
+            let candidate = &candidates[i];
+            let suppressed = results.iter().any(|&j| {
+                let kept = &candidates[j];
+                (agnostic || kept.class == candidate.class)
+                    && kept.rbox.iou(&candidate.rbox) > iou_threshold
+            });
+            if !suppressed {
+                results.push(i);
+            }
+        }
+        results
+    }
+
+    /// YOLOv8-OBB postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        agnostic: Option<bool>,
+    ) -> Vec<(Array2<f32>, Array3<f32>)> {
+        let agnostic = agnostic.unwrap_or(self.agnostic);
+
+        (0..inputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| {
+                let candidates = self.box_decode(inputs, batch_index, conf_threshold);
+                let indices = Self::rotated_nms(&candidates, iou_threshold, agnostic);
+
+                let mut detections = Array2::zeros((indices.len(), 7));
+                let mut corners = Array3::zeros((indices.len(), 4, 2));
+                for (i, &j) in indices.iter().enumerate() {
+                    let Candidate { rbox, score, class } = candidates[j];
+                    let row = [rbox.cx, rbox.cy, rbox.w, rbox.h, rbox.angle, score, class as f32];
+                    for (k, v) in row.into_iter().enumerate() {
+                        detections[[i, k]] = v;
+                    }
+                    for (k, [px, py]) in rbox.corners().into_iter().enumerate() {
+                        corners[[i, k, 0]] = px;
+                        corners[[i, k, 1]] = py;
+                    }
+                }
+                (detections, corners)
+            })
+            .collect()
+    }
+
+    fn validate(&self, inputs: &[ArrayView4<'_, f32>]) -> PyResult<()> {
+        if inputs.len() != self.strides.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected {} input tensors but got {}",
+                self.strides.len(),
+                inputs.len()
+            )));
+        }
+        validate_batch(inputs)?;
+        let min_channels = 4 * self.reg_max + 2;
+        for (i, input) in inputs.iter().enumerate() {
+            if input.shape()[1] < min_channels {
+                return Err(PyValueError::new_err(format!(
+                    "input {i} must have at least {min_channels} channels (4 * reg_max + nc + 1) \
+                     but got shape {:?}",
+                    input.shape()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Oriented Bounding Box PostProcessor
+///
+/// It decodes YOLOv8-OBB detection heads whose input tensors have shape
+/// `(N, 4 * reg_max + num_classes + 1, H, W)`, the last channel being the rotation angle logit,
+/// and runs rotated NMS.
+///
+/// Args:
+///     strides (numpy.ndarray): Strides (1D Array)
+///     reg_max (Optional[int]): Number of DFL bins per box side, default is 16
+///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(strides: Vec<f32>, reg_max: Option<usize>, agnostic: Option<bool>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(strides, reg_max, agnostic)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): Rotated IoU threshold
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///
+    /// Returns:
+    ///     List[PyObbResults]: Batched oriented detection results
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        agnostic: Option<bool>,
+    ) -> PyResult<Vec<PyObbResults>> {
+        let inputs = inputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&inputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&inputs, conf_threshold, iou_threshold, agnostic)
+        });
+        Ok(results
+            .into_iter()
+            .map(|(detections, corners)| PyObbResults {
+                detections: PyArray2::from_owned_array(py, detections).to_owned(),
+                corners: PyArray3::from_owned_array(py, corners).to_owned(),
+            })
+            .collect())
+    }
+}
+
+pub(crate) fn obb(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+/// Oriented bounding box, rotated by `angle` radians around its center
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RotatedBox {
+    pub cx: f32,
+    pub cy: f32,
+    pub w: f32,
+    pub h: f32,
+    pub angle: f32,
+}
+
+impl RotatedBox {
+    #[inline]
+    pub fn area(&self) -> f32 {
+        self.w * self.h
+    }
+
+    /// Corners in counter-clockwise order in a y-up frame, i.e. clockwise on the image
+    pub fn corners(&self) -> [[f32; 2]; 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let (dx, dy) = (self.w * 0.5, self.h * 0.5);
+        [(-dx, -dy), (dx, -dy), (dx, dy), (-dx, dy)]
+            .map(|(x, y)| [self.cx + x * cos - y * sin, self.cy + x * sin + y * cos])
+    }
+
+    /// Exact IoU of two oriented boxes, clipping one box polygon by the other
+    pub fn iou(&self, other: &Self) -> f32 {
+        let dist2 = (self.cx - other.cx).powi(2) + (self.cy - other.cy).powi(2);
+        let reach =
+            (self.w.powi(2) + self.h.powi(2)).sqrt() + (other.w.powi(2) + other.h.powi(2)).sqrt();
+        // Circumscribed circles do not overlap
+        if dist2 * 4.0 > reach * reach {
+            return 0.0;
+        }
+
+        let overlap = polygon_area(&clip(&self.corners(), &other.corners()));
+        overlap / (self.area() + other.area() - overlap + 0.00001f32)
+    }
+}
+
+#[inline]
+fn cross(o: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+/// Sutherland-Hodgman clipping of `subject` by the convex polygon `clipper`, both having the
+/// same orientation
+fn clip(subject: &[[f32; 2]], clipper: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut output = subject.to_vec();
+    for (i, &a) in clipper.iter().enumerate() {
+        let b = clipper[(i + 1) % clipper.len()];
+        let input = std::mem::take(&mut output);
+        for (j, &p) in input.iter().enumerate() {
+            let q = input[(j + 1) % input.len()];
+            let (p_inside, q_inside) = (cross(a, b, p) >= 0.0, cross(a, b, q) >= 0.0);
+            if p_inside {
+                output.push(p);
+            }
+            if p_inside != q_inside {
+                // Intersection of segment pq with the clipping edge ab
+                let (cp, cq) = (cross(a, b, p), cross(a, b, q));
+                let t = cp / (cp - cq);
+                output.push([p[0] + t * (q[0] - p[0]), p[1] + t * (q[1] - p[1])]);
+            }
+        }
+        if output.is_empty() {
+            break;
+        }
+    }
+    output
+}
+
+/// Shoelace formula
+fn polygon_area(polygon: &[[f32; 2]]) -> f32 {
+    let n = polygon.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let twice: f32 = (0..n)
+        .map(|i| {
+            let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+            p[0] * q[1] - q[0] * p[1]
+        })
+        .sum();
+    twice.abs() * 0.5
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArray2};
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde_json::Value;
+
+use crate::common::ssd_postprocess::{DetectionResult, DetectionResults};
+#[cfg(feature = "python")]
+use crate::common::threads;
+#[cfg(feature = "python")]
+use crate::common::{extract_detections, wbf, PyDetectionResults};
+
+pub const DEFAULT_DUPLICATE_IOU_THRESHOLD: f32 = 0.5;
+
+/// Class hierarchy of OpenImages, as given by `bbox_labels_600_hierarchy.json`
+///
+/// The official evaluation protocol counts a box of a class as a box of each of its ancestors
+/// too, e.g. a "Dog" is also an "Animal", so that both ground truth and detections are expanded
+/// to the parent labels before matching.
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    /// Ancestor classes of each class, a class with several parents having all of them
+    ancestors: Vec<Vec<usize>>,
+}
+
+impl Hierarchy {
+    /// Load a hierarchy file, `labels` being the label name (MID) of each class of the model
+    pub fn load(path: impl AsRef<Path>, labels: &[impl AsRef<str>]) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?, labels)
+    }
+
+    /// Parse a hierarchy, labels of the hierarchy outside of `labels` such as the "Entity" root
+    /// only linking their descendants to their ancestors
+    pub fn from_json(text: &str, labels: &[impl AsRef<str>]) -> io::Result<Self> {
+        let index = labels.iter().enumerate().map(|(class, label)| (label.as_ref(), class));
+        let index = index.collect::<HashMap<_, _>>();
+        let mut ancestors = vec![Vec::new(); labels.len()];
+        walk(&serde_json::from_str(text)?, &index, &mut Vec::new(), &mut ancestors)?;
+        Ok(Self { ancestors })
+    }
+
+    pub fn num_classes(&self) -> usize {
+        self.ancestors.len()
+    }
+
+    /// Ancestor classes of a class, empty for a root or an unknown class
+    pub fn ancestors(&self, class: usize) -> &[usize] {
+        self.ancestors.get(class).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether one of two distinct classes is an ancestor of the other
+    pub fn related(&self, a: usize, b: usize) -> bool {
+        self.ancestors(a).contains(&b) || self.ancestors(b).contains(&a)
+    }
+
+    /// Detections followed by a copy of each of them for every ancestor of its class
+    pub fn expand(&self, detections: &DetectionResults) -> DetectionResults {
+        detections
+            .iter()
+            .flat_map(|&r| {
+                let ancestors = self.ancestors(r.class as usize).iter();
+                std::iter::once(r).chain(
+                    ancestors.map(move |&class| DetectionResult { class: class as f32, ..r }),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Suppress the detections overlapping a higher scoring one of an ancestor or a descendant
+    /// class by an IoU above `iou_threshold`, in descending score order
+    ///
+    /// Unlike the class-wise NMS, a model trained on the hierarchy emits a box for a child label
+    /// along with one for its parent label, which this reduces to the most confident of them.
+    pub fn suppress_duplicates(
+        &self,
+        detections: &DetectionResults,
+        iou_threshold: f32,
+    ) -> DetectionResults {
+        let mut sorted = detections.to_vec();
+        sorted.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut kept: Vec<DetectionResult> = Vec::with_capacity(sorted.len());
+        for r in sorted {
+            let duplicate = kept.iter().any(|k| {
+                self.related(k.class as usize, r.class as usize)
+                    && k.bbox.iou(&r.bbox) > iou_threshold
+            });
+            if !duplicate {
+                kept.push(r);
+            }
+        }
+        kept.into()
+    }
+}
+
+fn walk(
+    node: &Value,
+    index: &HashMap<&str, usize>,
+    path: &mut Vec<usize>,
+    ancestors: &mut [Vec<usize>],
+) -> io::Result<()> {
+    let label = node.get("LabelName").and_then(Value::as_str).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "hierarchy node without a \"LabelName\"")
+    })?;
+    let class = index.get(label).copied();
+    if let Some(class) = class {
+        for &ancestor in path.iter() {
+            if ancestor != class && !ancestors[class].contains(&ancestor) {
+                ancestors[class].push(ancestor);
+            }
+        }
+        path.push(class);
+    }
+    // Only subcategories are expanded, parts ("Wheel" of a "Car") are separate objects
+    if let Some(children) = node.get("Subcategory").and_then(Value::as_array) {
+        for child in children {
+            walk(child, index, path, ancestors)?;
+        }
+    }
+    if class.is_some() {
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Class hierarchy of OpenImages for the evaluation protocol of the challenge
+///
+/// Args:
+///     path (str): Path of the hierarchy file, such as bbox_labels_600_hierarchy.json
+///     labels (Sequence[str]): Label name (MID) of each class of the model, e.g. "/m/0bt9lr"
+#[cfg(feature = "python")]
+#[pyclass(name = "Hierarchy", module = "furiosa_native_postprocess.openimages")]
+pub struct PyHierarchy(Hierarchy);
+
+#[cfg(feature = "python")]
+fn detections_like(py: Python<'_>, as_arrays: bool, detections: DetectionResults) -> PyObject {
+    if as_arrays {
+        PyArray2::from_owned_array(py, wbf::to_rows(&detections)).to_object(py)
+    } else {
+        PyDetectionResults::from(detections).into_py(py)
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyHierarchy {
+    #[new]
+    fn new(py: Python<'_>, path: &str, labels: Vec<String>) -> PyResult<Self> {
+        threads::allow_threads(py, || Hierarchy::load(path, &labels)).map(Self).map_err(|e| match e
+            .kind()
+        {
+            io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+            _ => e.into(),
+        })
+    }
+
+    #[getter]
+    fn num_classes(&self) -> usize {
+        self.0.num_classes()
+    }
+
+    /// Ancestor classes of a class, e.g. to expand the ground truth labels
+    ///
+    /// Args:
+    ///     class_ (int): Class index
+    ///
+    /// Returns:
+    ///     List[int]: Ancestor classes, empty for a root class
+    fn ancestors(&self, class_: usize) -> Vec<usize> {
+        self.0.ancestors(class_).to_vec()
+    }
+
+    /// Expand detections to the ancestors of their class
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections, either an
+    ///         array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+    ///         PyDetectionResult
+    ///
+    /// Returns:
+    ///     Union[numpy.ndarray, PyDetectionResults]: Each detection followed by a copy for every
+    ///         ancestor of its class, in the same format as the input
+    fn expand(&self, py: Python<'_>, detections: &PyAny) -> PyResult<PyObject> {
+        let as_arrays = detections.extract::<PyReadonlyArray2<'_, f32>>().is_ok();
+        let detections = extract_detections(detections)?;
+        Ok(detections_like(py, as_arrays, self.0.expand(&detections)))
+    }
+
+    /// Suppress the detections duplicating a higher scoring one of a parent or child class
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections, either an
+    ///         array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or a list of
+    ///         PyDetectionResult
+    ///     iou_threshold (Optional[float]): IoU above which boxes of related classes are
+    ///         duplicates, default is 0.5
+    ///
+    /// Returns:
+    ///     Union[numpy.ndarray, PyDetectionResults]: Remaining detections in descending score
+    ///         order, in the same format as the input
+    fn suppress_duplicates(
+        &self,
+        py: Python<'_>,
+        detections: &PyAny,
+        iou_threshold: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let as_arrays = detections.extract::<PyReadonlyArray2<'_, f32>>().is_ok();
+        let detections = extract_detections(detections)?;
+        let kept = threads::allow_threads(py, || {
+            self.0.suppress_duplicates(
+                &detections,
+                iou_threshold.unwrap_or(DEFAULT_DUPLICATE_IOU_THRESHOLD),
+            )
+        });
+        Ok(detections_like(py, as_arrays, kept))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Hierarchy(num_classes: {})", self.0.num_classes())
+    }
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn openimages(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyHierarchy>()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ssd_postprocess::BoundingBox;
+
+    const HIERARCHY: &str = r#"{
+        "LabelName": "/m/0bl9f",
+        "Subcategory": [
+            {"LabelName": "/m/0jbk", "Subcategory": [
+                {"LabelName": "/m/0bt9lr"},
+                {"LabelName": "/m/01yrx"}
+            ]},
+            {"LabelName": "/m/0k4j", "Part": [{"LabelName": "/m/083wq"}]},
+            {"LabelName": "/m/01lrl", "Subcategory": [{"LabelName": "/m/0bt9lr"}]}
+        ]
+    }"#;
+
+    #[test]
+    fn unittest_hierarchy() {
+        // Animal, Dog, Cat, Car, Wheel and Carnivore
+        let labels = ["/m/0jbk", "/m/0bt9lr", "/m/01yrx", "/m/0k4j", "/m/083wq", "/m/01lrl"];
+        let hierarchy = Hierarchy::from_json(HIERARCHY, &labels).unwrap();
+        assert_eq!(hierarchy.ancestors(1), [0, 5]);
+        assert_eq!(hierarchy.ancestors(2), [0]);
+        assert!(hierarchy.ancestors(3).is_empty() && hierarchy.ancestors(4).is_empty());
+        assert!(hierarchy.related(0, 1) && !hierarchy.related(1, 2));
+
+        let detection = |x1: f32, score: f32, class: f32| DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: x1, py1: 0.0, px2: x1 + 10.0, py2: 10.0 },
+            score,
+            class,
+        };
+        let expanded = hierarchy.expand(&vec![detection(0.0, 0.9, 1.0)].into());
+        assert_eq!(expanded.iter().map(|r| r.class).collect::<Vec<_>>(), [1.0, 0.0, 5.0]);
+
+        let detections = vec![
+            detection(0.0, 0.8, 0.0),
+            detection(0.0, 0.9, 1.0),
+            detection(1.0, 0.7, 2.0),
+            detection(50.0, 0.6, 0.0),
+        ];
+        let kept = hierarchy.suppress_duplicates(&detections.into(), 0.5);
+        assert_eq!(kept.iter().map(|r| r.score).collect::<Vec<_>>(), [0.9, 0.7, 0.6]);
+    }
+}
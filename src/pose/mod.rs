@@ -0,0 +1,437 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{Array2, Array3, ArrayView1, ArrayView2, ArrayViewD, Axis, Ix3};
+use numpy::{PyArray2, PyArray3, PyReadonlyArray3, PyReadonlyArrayDyn};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::BoundingBox;
+use crate::common::{descending, logit, sigmoid, threads, validate_batch, PyPoseResults};
+
+const MAX_BOXES: usize = 10_000;
+const MAX_NMS: usize = 300;
+const DEFAULT_NUM_KEYPOINTS: usize = 17;
+const DEFAULT_REG_MAX: usize = 16;
+// Per-keypoint standard deviations of the COCO keypoint evaluation
+const COCO_KEYPOINT_SIGMAS: [f32; 17] = [
+    0.026, 0.025, 0.025, 0.035, 0.035, 0.079, 0.079, 0.072, 0.072, 0.062, 0.062, 0.107, 0.107,
+    0.087, 0.087, 0.089, 0.089,
+];
+
+/// Head layout of the pose model
+#[derive(Debug, Clone)]
+pub enum Head {
+    /// Anchor-based head (YOLOv7-pose), input shape `(N, A, H, W, 6 + 3 * num_keypoints)`
+    Anchor { anchors: Array3<f32> },
+    /// Anchor-free DFL head (YOLOv8-pose), input shape `(N, 4 * reg_max + nc + 3 * num_keypoints, H, W)`
+    AnchorFree { reg_max: usize },
+}
+
+/// Decoded candidates of one image, keypoints being `(x, y, visibility)` triplets per candidate
+#[derive(Default)]
+struct PoseCandidates {
+    boxes: Vec<[f32; 6]>,
+    keypoints: Vec<f32>,
+}
+
+impl PoseCandidates {
+    fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    fn push(
+        &mut self,
+        [x1, y1, x2, y2]: [f32; 4],
+        score: f32,
+        class: usize,
+        keypoints: impl Iterator<Item = f32>,
+    ) {
+        self.boxes.push([x1, y1, x2, y2, score, class as f32]);
+        self.keypoints.extend(keypoints);
+    }
+
+    fn bbox(&self, i: usize) -> BoundingBox {
+        let [px1, py1, px2, py2, ..] = self.boxes[i];
+        BoundingBox { px1, py1, px2, py2 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub head: Head,
+    pub strides: Vec<f32>,
+    pub num_keypoints: usize,
+    pub sigmas: Vec<f32>,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let head = match &self.head {
+            Head::Anchor { anchors } => format!("anchor-based, num_anchor: {}", anchors.shape()[1]),
+            Head::AnchorFree { reg_max } => format!("anchor-free, reg_max: {reg_max}"),
+        };
+        write!(
+            f,
+            "RustPostProcessor {{ head: {head}, strides: {:?}, num_keypoints: {} }}",
+            self.strides, self.num_keypoints
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(head: Head, strides: Vec<f32>, num_keypoints: Option<usize>) -> Self {
+        let num_keypoints = num_keypoints.unwrap_or(DEFAULT_NUM_KEYPOINTS);
+        let sigmas = if num_keypoints == COCO_KEYPOINT_SIGMAS.len() {
+            COCO_KEYPOINT_SIGMAS.to_vec()
+        } else {
+            vec![1.0 / num_keypoints as f32; num_keypoints]
+        };
+        Self { head, strides, num_keypoints, sigmas }
+    }
+
+    /// YOLOv7-pose decode of one layer of one image
+    fn decode_anchor(
+        &self,
+        input: ArrayViewD<'_, f32>,
+        anchors: ArrayView2<'_, f32>,
+        stride: f32,
+        conf_threshold: f32,
+        candidates: &mut PoseCandidates,
+    ) {
+        let nk = self.num_keypoints;
+        for (anchor, inner_anchor) in izip!(anchors.outer_iter(), input.outer_iter()) {
+            let (ax, ay) = (anchor[0] * stride, anchor[1] * stride);
+            for (y, inner_y) in inner_anchor.outer_iter().enumerate() {
+                for (x, cell) in inner_y.outer_iter().enumerate() {
+                    if candidates.len() >= MAX_BOXES {
+                        return;
+                    }
+                    // Box, objectness, and class confidences are already sigmoid-activated
+                    let score = cell[4] * cell[5];
+                    if score <= conf_threshold {
+                        continue;
+                    }
+
+                    let cx = (cell[0] * 2.0 - 0.5 + x as f32) * stride;
+                    let cy = (cell[1] * 2.0 - 0.5 + y as f32) * stride;
+                    let w = 4.0 * cell[2] * cell[2] * ax;
+                    let h = 4.0 * cell[3] * cell[3] * ay;
+
+                    let keypoints = (0..nk).flat_map(|k| {
+                        let base = 6 + 3 * k;
+                        [
+                            (cell[base] * 2.0 - 0.5 + x as f32) * stride,
+                            (cell[base + 1] * 2.0 - 0.5 + y as f32) * stride,
+                            sigmoid(cell[base + 2]),
+                        ]
+                    });
+                    candidates.push(
+                        [cx - w * 0.5, cy - h * 0.5, cx + w * 0.5, cy + h * 0.5],
+                        score,
+                        0,
+                        keypoints,
+                    );
+                }
+            }
+        }
+    }
+
+    /// YOLOv8-pose decode of one layer of one image
+    fn decode_anchor_free(
+        &self,
+        input: ArrayViewD<'_, f32>,
+        reg_max: usize,
+        stride: f32,
+        conf_threshold: f32,
+        candidates: &mut PoseCandidates,
+    ) {
+        let nk = self.num_keypoints;
+        let input = input.into_dimensionality::<Ix3>().unwrap();
+        let &[channels, height, width] = input.shape() else { unreachable!() };
+        let box_channels = 4 * reg_max;
+        let kpt_offset = channels - 3 * nk;
+        let logit_threshold = logit(conf_threshold);
+
+        let dfl = |side: usize, y: usize, x: usize| {
+            let bins = (0..reg_max).map(|i| input[[side * reg_max + i, y, x]]);
+            let max = bins.clone().fold(f32::NEG_INFINITY, f32::max);
+            let (sum, weighted) = bins.enumerate().fold((0.0, 0.0), |(sum, weighted), (i, v)| {
+                let e = f32::exp(v - max);
+                (sum + e, weighted + e * i as f32)
+            });
+            weighted / sum
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                for c in box_channels..kpt_offset {
+                    if candidates.len() >= MAX_BOXES {
+                        return;
+                    }
+                    let class_logit = input[[c, y, x]];
+                    if class_logit <= logit_threshold {
+                        continue;
+                    }
+
+                    let (ax, ay) = (x as f32 + 0.5, y as f32 + 0.5);
+                    let bbox = [
+                        (ax - dfl(0, y, x)) * stride,
+                        (ay - dfl(1, y, x)) * stride,
+                        (ax + dfl(2, y, x)) * stride,
+                        (ay + dfl(3, y, x)) * stride,
+                    ];
+                    let keypoints = (0..nk).flat_map(|k| {
+                        let base = kpt_offset + 3 * k;
+                        [
+                            (input[[base, y, x]] * 2.0 + x as f32) * stride,
+                            (input[[base + 1, y, x]] * 2.0 + y as f32) * stride,
+                            sigmoid(input[[base + 2, y, x]]),
+                        ]
+                    });
+                    candidates.push(bbox, sigmoid(class_logit), c - box_channels, keypoints);
+                }
+            }
+        }
+    }
+
+    fn box_decode(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+    ) -> PoseCandidates {
+        let mut candidates = PoseCandidates::default();
+        for (layer, (&stride, input)) in izip!(&self.strides, inputs).enumerate() {
+            let input = input.index_axis(Axis(0), batch_index);
+            match &self.head {
+                Head::Anchor { anchors } => self.decode_anchor(
+                    input,
+                    anchors.index_axis(Axis(0), layer),
+                    stride,
+                    conf_threshold,
+                    &mut candidates,
+                ),
+                Head::AnchorFree { reg_max } => self.decode_anchor_free(
+                    input,
+                    *reg_max,
+                    stride,
+                    conf_threshold,
+                    &mut candidates,
+                ),
+            }
+        }
+        candidates
+    }
+
+    /// Object Keypoint Similarity between two keypoint sets, using `area` as the object scale
+    fn oks(&self, a: &[f32], b: &[f32], area: f32) -> f32 {
+        let (mut similarity, mut visible) = (0f32, 0usize);
+        for (k, (ka, kb)) in izip!(a.chunks_exact(3), b.chunks_exact(3)).enumerate() {
+            if ka[2] < 0.5 || kb[2] < 0.5 {
+                continue;
+            }
+            let d2 = (ka[0] - kb[0]).powi(2) + (ka[1] - kb[1]).powi(2);
+            let kappa = 2.0 * self.sigmas[k];
+            similarity += f32::exp(-d2 / (2.0 * area * kappa * kappa + f32::EPSILON));
+            visible += 1;
+        }
+        if visible == 0 {
+            0.0
+        } else {
+            similarity / visible as f32
+        }
+    }
+
+    /// Greedy NMS where a candidate is suppressed by a kept pose of the same class if either
+    /// their box IoU exceeds `iou_threshold` or their OKS exceeds `oks_threshold`
+    fn nms(
+        &self,
+        candidates: &PoseCandidates,
+        iou_threshold: f32,
+        oks_threshold: Option<f32>,
+    ) -> Vec<usize> {
+        let boxes = &candidates.boxes;
+        let stride = 3 * self.num_keypoints;
+        let keypoints = |i: usize| &candidates.keypoints[i * stride..(i + 1) * stride];
+
+        let mut indices: Vec<usize> = (0..boxes.len()).collect();
+        indices.sort_unstable_by(|&i, &j| descending(boxes[i][4], boxes[j][4]).then(i.cmp(&j)));
+
+        let mut results: Vec<usize> = Vec::new();
+        for i in indices {
+            if results.len() >= MAX_NMS {
+                break;
+            }
+            let candidate = candidates.bbox(i);
+            let suppressed = results.iter().any(|&j| {
+                if boxes[i][5] != boxes[j][5] {
+                    return false;
+                }
+                let kept = candidates.bbox(j);
+                candidate.iou(&kept) > iou_threshold
+                    || oks_threshold.is_some_and(|threshold| {
+                        self.oks(keypoints(i), keypoints(j), kept.area()) > threshold
+                    })
+            });
+            if !suppressed {
+                results.push(i);
+            }
+        }
+        results
+    }
+
+    /// Pose postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        oks_threshold: Option<f32>,
+    ) -> Vec<(Array2<f32>, Array3<f32>)> {
+        (0..inputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| {
+                let candidates = self.box_decode(inputs, batch_index, conf_threshold);
+                let indices = self.nms(&candidates, iou_threshold, oks_threshold);
+
+                let stride = 3 * self.num_keypoints;
+                let mut detections = Array2::zeros((indices.len(), 6));
+                let mut keypoints = Array3::zeros((indices.len(), self.num_keypoints, 3));
+                for (i, &j) in indices.iter().enumerate() {
+                    detections.row_mut(i).assign(&ArrayView1::from(&candidates.boxes[j]));
+                    let kpts = &candidates.keypoints[j * stride..(j + 1) * stride];
+                    keypoints
+                        .index_axis_mut(Axis(0), i)
+                        .assign(&ArrayView2::from_shape((self.num_keypoints, 3), kpts).unwrap());
+                }
+                (detections, keypoints)
+            })
+            .collect()
+    }
+
+    fn validate(&self, inputs: &[ArrayViewD<'_, f32>]) -> PyResult<()> {
+        if inputs.len() != self.strides.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected {} input tensors but got {}",
+                self.strides.len(),
+                inputs.len()
+            )));
+        }
+
+        let keypoint_channels = 3 * self.num_keypoints;
+        for (i, input) in inputs.iter().enumerate() {
+            let (ndim, valid) = match self.head {
+                Head::Anchor { .. } => (5, input.shape().last() == Some(&(6 + keypoint_channels))),
+                Head::AnchorFree { reg_max } => {
+                    (4, input.shape().get(1).is_some_and(|&c| c > 4 * reg_max + keypoint_channels))
+                }
+            };
+            if input.ndim() != ndim || !valid {
+                return Err(PyValueError::new_err(format!(
+                    "input {i} has an unexpected shape {:?} for {} keypoints",
+                    input.shape(),
+                    self.num_keypoints
+                )));
+            }
+        }
+        validate_batch(inputs)?;
+
+        Ok(())
+    }
+}
+
+/// Pose PostProcessor
+///
+/// It decodes keypoints together with boxes for YOLOv7-pose (anchor-based, when `anchors` is
+/// given) or YOLOv8-pose (anchor-free DFL head) models and runs OKS-aware NMS.
+///
+/// Args:
+///     strides (numpy.ndarray): Strides (1D Array)
+///     anchors (Optional[numpy.ndarray]): Anchors (3D Array) of a YOLOv7-pose model
+///     num_keypoints (Optional[int]): Number of keypoints, default is 17
+///     reg_max (Optional[int]): Number of DFL bins per box side of YOLOv8-pose, default is 16
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        strides: Vec<f32>,
+        anchors: Option<PyReadonlyArray3<'_, f32>>,
+        num_keypoints: Option<usize>,
+        reg_max: Option<usize>,
+    ) -> PyResult<Self> {
+        if strides.is_empty() {
+            return Err(PyValueError::new_err("strides must not be empty"));
+        }
+        let head = match anchors {
+            Some(anchors) => {
+                let anchors = anchors.to_owned_array();
+                if anchors.shape()[0] != strides.len() || anchors.shape()[2] != 2 {
+                    return Err(PyValueError::new_err(format!(
+                        "anchors must have shape ({}, num_anchor, 2) but got {:?}",
+                        strides.len(),
+                        anchors.shape()
+                    )));
+                }
+                Head::Anchor { anchors }
+            }
+            None => match reg_max.unwrap_or(DEFAULT_REG_MAX) {
+                0 => return Err(PyValueError::new_err("reg_max must be positive")),
+                reg_max => Head::AnchorFree { reg_max },
+            },
+        };
+        Ok(Self(RustPostprocessor::new(head, strides, num_keypoints)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): IoU threshold
+    ///     oks_threshold (Optional[float]): OKS threshold, keypoint similarity is ignored if None
+    ///
+    /// Returns:
+    ///     List[PyPoseResults]: Batched pose results
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArrayDyn<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        oks_threshold: Option<f32>,
+    ) -> PyResult<Vec<PyPoseResults>> {
+        let inputs = inputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&inputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&inputs, conf_threshold, iou_threshold, oks_threshold)
+        });
+        Ok(results
+            .into_iter()
+            .map(|(detections, keypoints)| PyPoseResults {
+                detections: PyArray2::from_owned_array(py, detections).to_owned(),
+                keypoints: PyArray3::from_owned_array(py, keypoints).to_owned(),
+            })
+            .collect())
+    }
+}
+
+pub(crate) fn pose(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
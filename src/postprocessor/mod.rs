@@ -0,0 +1,383 @@
+//! Common interface of the detection postprocessors, run as decode, suppress, and format
+//!
+//! Models implement [`Postprocessor`] so that they are driven the same way, and are created by
+//! name from Python with `create_postprocessor`.
+
+#[cfg(feature = "python")]
+use std::sync::{Arc, PoisonError, RwLock};
+
+#[cfg(feature = "python")]
+use ndarray::Axis;
+use ndarray::{Array2, ArrayViewD};
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArrayDyn, ToPyArray};
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+
+#[cfg(feature = "python")]
+use crate::common::threads;
+#[cfg(feature = "python")]
+use crate::common::ConfThreshold;
+use crate::common::Result;
+#[cfg(feature = "python")]
+use crate::head::HeadDecoder;
+#[cfg(feature = "python")]
+use crate::ssd::{SsdConfig, SsdPostprocessor};
+use crate::yolo::utils::DetectionBoxes;
+
+/// Score and IoU thresholds of a postprocess
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Minimum score of the candidates
+    pub conf: f32,
+    pub iou: f32,
+}
+
+/// Postprocess of the raw outputs of a detection model into detections of each image
+pub trait Postprocessor: Send + Sync {
+    /// Thresholds used when the caller gives none
+    fn default_thresholds(&self) -> Thresholds;
+
+    /// Candidates of each image of a batch whose score reaches `conf_threshold`
+    fn decode(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+    ) -> Result<Vec<DetectionBoxes>>;
+
+    /// Indices of the candidates of an image kept by suppressing overlapping ones, in the order
+    /// of the detections
+    /// The candidates may be trimmed or updated in place, the indices referring to them after.
+    fn suppress(&self, candidates: &mut DetectionBoxes, thresholds: Thresholds) -> Vec<usize>;
+
+    /// Detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class) of the kept candidates
+    fn format(&self, candidates: &mut DetectionBoxes, kept: &[usize]) -> Array2<f32> {
+        candidates.select_and_convert(kept)
+    }
+
+    /// Detections of the decoded candidates of each image, suppressed in parallel
+    fn suppress_batch(
+        &self,
+        candidates: Vec<DetectionBoxes>,
+        thresholds: Thresholds,
+    ) -> Vec<Array2<f32>> {
+        candidates
+            .into_par_iter()
+            .map(|mut candidates| {
+                let kept = self.suppress(&mut candidates, thresholds);
+                self.format(&mut candidates, &kept)
+            })
+            .collect()
+    }
+
+    /// Detections of each image of a batch
+    fn postprocess(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        thresholds: Thresholds,
+    ) -> Result<Vec<Array2<f32>>> {
+        let candidates = self.decode(inputs, thresholds.conf)?;
+        Ok(self.suppress_batch(candidates, thresholds))
+    }
+}
+
+#[cfg(feature = "python")]
+type Constructor = fn(Option<&PyDict>) -> PyResult<Arc<dyn Postprocessor>>;
+
+/// Models created by `create_postprocessor`, with the constructor of each from its config
+#[cfg(feature = "python")]
+const REGISTRY: [(&str, Constructor); 4] = [
+    ("yolov5", |config| {
+        let config = config
+            .ok_or_else(|| PyValueError::new_err("yolov5 requires a config of its anchors"))?;
+        Ok(crate::yolo::RustPostProcessor::from_dict(config)?.0)
+    }),
+    ("ssd_mobilenet", |config| ssd(SsdConfig::ssd_mobilenet(), config)),
+    ("ssd_resnet34", |config| ssd(SsdConfig::ssd_resnet34(), config)),
+    ("generic", |config| {
+        let config =
+            config.ok_or_else(|| PyValueError::new_err("generic requires a spec of its heads"))?;
+        Ok(Arc::new(HeadDecoder::new(crate::head::spec_from_dict(config)?)?))
+    }),
+];
+
+#[cfg(feature = "python")]
+fn ssd(mut base: SsdConfig, config: Option<&PyDict>) -> PyResult<Arc<dyn Postprocessor>> {
+    if let Some(config) = config {
+        base.update(config)?;
+    }
+    Ok(Arc::new(SsdPostprocessor::new(base)?))
+}
+
+/// Callback of `set_rescore`, replaceable while other threads evaluate
+#[cfg(feature = "python")]
+#[derive(Default)]
+pub(crate) struct Rescore(RwLock<Option<PyObject>>);
+
+#[cfg(feature = "python")]
+impl Rescore {
+    pub(crate) fn set(&self, callback: Option<PyObject>) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = callback;
+    }
+
+    /// The registered callback, cloned so that the lock is not held while it runs
+    pub(crate) fn get(&self, py: Python<'_>) -> Option<PyObject> {
+        let callback = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        callback.as_ref().map(|callback| callback.clone_ref(py))
+    }
+}
+
+/// Postprocessor of a model created by `create_postprocessor`
+#[cfg(feature = "python")]
+#[pyclass(name = "Postprocessor", module = "furiosa_native_postprocess")]
+pub struct PyPostprocessor {
+    name: &'static str,
+    inner: Arc<dyn Postprocessor>,
+    rescore: Rescore,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyPostprocessor {
+    fn __repr__(&self) -> String {
+        format!("Postprocessor({:?})", self.name)
+    }
+
+    /// Register a callback rescoring the candidates of each image before NMS
+    ///
+    /// It is called as `callback(boxes, scores, classes, image)` with the boxes of shape (N, 4)
+    /// with rows (x1, y1, x2, y2), their scores and classes of shape (N,), and the index of the
+    /// image in the batch, and returns the N new scores, e.g. `scores * prior` or
+    /// `numpy.where(mask, scores, 0)`. Candidates whose new score does not exceed the confidence
+    /// threshold, or is NaN, are dropped. The callback holds the GIL, so images are rescored one
+    /// at a time.
+    ///
+    /// Args:
+    ///     callback (Optional[Callable[..., Any]]): Rescoring callback, None to remove it
+    fn set_rescore(&self, callback: Option<PyObject>) {
+        self.rescore.set(callback);
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Output tensors of the model of dtype float32
+    ///     conf_threshold (Optional[float]): Minimum score of the detections, default is the one
+    ///         of the model
+    ///     iou_threshold (Optional[float]): IoU threshold of NMS, default is the one of the model
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Detections of each image of shape (N, 6) with rows
+    ///         (x1, y1, x2, y2, score, class)
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArrayDyn<'_, f32>>,
+        conf_threshold: Option<f32>,
+        iou_threshold: Option<f32>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let rescore = self.rescore.get(py);
+        let rescore = rescore.as_ref().map(|rescore| rescore.as_ref(py));
+        eval_py(self.inner.as_ref(), py, inputs, conf_threshold, iou_threshold, rescore)
+    }
+}
+
+/// Detections of each image of the float32 `inputs`, the thresholds defaulting to those of the
+/// postprocessor
+/// The candidates are rescored between decode and NMS if a `rescore` callback is given.
+#[cfg(feature = "python")]
+pub(crate) fn eval_py(
+    postprocessor: &dyn Postprocessor,
+    py: Python<'_>,
+    inputs: Vec<PyReadonlyArrayDyn<'_, f32>>,
+    conf_threshold: Option<f32>,
+    iou_threshold: Option<f32>,
+    rescore: Option<&PyAny>,
+) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+    let defaults = postprocessor.default_thresholds();
+    let thresholds = Thresholds {
+        conf: conf_threshold.unwrap_or(defaults.conf),
+        iou: iou_threshold.unwrap_or(defaults.iou),
+    };
+    let views = inputs.iter().map(|input| input.as_array()).collect::<Vec<_>>();
+    let detections = match rescore {
+        None => threads::allow_threads(py, || postprocessor.postprocess(&views, thresholds))?,
+        Some(rescore) => {
+            let mut candidates =
+                threads::allow_threads(py, || postprocessor.decode(&views, thresholds.conf))?;
+            for (image, candidates) in candidates.iter_mut().enumerate() {
+                let conf_threshold = ConfThreshold::Scalar(thresholds.conf);
+                rescore_py(rescore, candidates, image, &conf_threshold)?;
+            }
+            threads::allow_threads(py, || postprocessor.suppress_batch(candidates, thresholds))
+        }
+    };
+    Ok(detections
+        .into_iter()
+        .map(|detections| PyArray2::from_owned_array(py, detections).to_owned())
+        .collect())
+}
+
+/// Replace the scores of the candidates of an image with those returned by `rescore`, dropping
+/// the candidates whose new score does not exceed the `conf_threshold` of their class
+/// Returns the indices of the kept candidates among the given ones.
+#[cfg(feature = "python")]
+pub(crate) fn rescore_py(
+    rescore: &PyAny,
+    candidates: &mut DetectionBoxes,
+    image: usize,
+    conf_threshold: &ConfThreshold,
+) -> PyResult<Vec<usize>> {
+    let py = rescore.py();
+    let DetectionBoxes { x1, y1, x2, y2, scores, classes } = &*candidates;
+    let boxes = ndarray::stack![Axis(1), x1.view(), y1.view(), x2.view(), y2.view()];
+    let args = (
+        PyArray2::from_owned_array(py, boxes),
+        scores.to_pyarray(py),
+        classes.to_pyarray(py),
+        image,
+    );
+    let rescored: Vec<f32> = rescore.call1(args)?.extract()?;
+    if rescored.len() != candidates.len() {
+        return Err(PyValueError::new_err(format!(
+            "rescore must return {} scores for image {image} but returned {}",
+            candidates.len(),
+            rescored.len()
+        )));
+    }
+    candidates.scores = rescored.into();
+    let kept = (0..candidates.len())
+        .filter(|&i| candidates.scores[i] > conf_threshold.of(candidates.classes[i] as usize))
+        .collect::<Vec<_>>();
+    if kept.len() < candidates.len() {
+        candidates.select(&kept);
+    }
+    Ok(kept)
+}
+
+/// Create the postprocessor of a model by name
+///
+/// Args:
+///     name (str): "yolov5", "ssd_mobilenet", "ssd_resnet34", or "generic" for heads described
+///         by a spec
+///     config (Optional[Dict[str, Any]]): Config of the model, as taken by the `from_dict` of
+///         yolo.RustPostProcessor, overriding keys of ssd.SsdPostProcessor, or the spec taken
+///         by head.HeadDecoder
+///
+/// Returns:
+///     Postprocessor: Postprocessor of the model
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "create_postprocessor")]
+pub fn create_postprocessor_py(name: &str, config: Option<&PyDict>) -> PyResult<PyPostprocessor> {
+    let Some(&(name, constructor)) = REGISTRY.iter().find(|(model, _)| *model == name) else {
+        let models = REGISTRY.iter().map(|(model, _)| format!("{model:?}")).collect::<Vec<_>>();
+        return Err(PyValueError::new_err(format!(
+            "unknown model {name:?}, expected one of {}",
+            models.join(", ")
+        )));
+    };
+    Ok(PyPostprocessor { name, inner: constructor(config)?, rescore: Rescore::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use ndarray::{arr3, s, stack, Array, Array3, Axis, ShapeBuilder};
+
+    use super::*;
+    use crate::common::{ConfThreshold, IouThreshold, NmsMethod};
+    use crate::ssd::{SsdConfig, SsdPostprocessor};
+    use crate::yolo::{Layout, RustPostprocessor};
+
+    /// Array of uniform values in [low, high) from a fixed seed
+    fn uniform<Sh: ShapeBuilder>(shape: Sh, low: f32, high: f32, seed: u32) -> Array<f32, Sh::Dim> {
+        let mut state = seed;
+        Array::from_shape_simple_fn(shape, || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            low + (high - low) * ((state >> 8) as f32 / (1 << 24) as f32)
+        })
+    }
+
+    #[test]
+    fn unittest_ssd_matches_eval() {
+        for config in [SsdConfig::ssd_mobilenet(), SsdConfig::ssd_resnet34()] {
+            let postprocessor = SsdPostprocessor::new(config.clone()).unwrap();
+            let priors = config.priors.priors_per_location();
+            let (mut boxes, mut scores) = (Vec::new(), Vec::new());
+            for (map, (&side, &n)) in config.priors.feature_maps.iter().zip(&priors).enumerate() {
+                let seed = map as u32;
+                boxes.push(uniform((4 * n, side, side), -1.0, 1.0, seed));
+                let shape = (config.num_classes * n, side, side);
+                scores.push(uniform(shape, -6.0, 3.0, seed + 100));
+            }
+            let box_views = boxes.iter().map(Array3::view).collect::<Vec<_>>();
+            let score_views = scores.iter().map(Array3::view).collect::<Vec<_>>();
+            let expected =
+                postprocessor.eval(&box_views, &score_views, NmsMethod::Hard).unwrap().into_array();
+            assert!(!expected.is_empty());
+
+            let inputs = box_views.iter().chain(&score_views).map(|v| v.into_dyn()).collect_vec();
+            let [single] = &postprocessor
+                .postprocess(&inputs, postprocessor.default_thresholds())
+                .unwrap()[..]
+            else {
+                panic!("expected a single image");
+            };
+            assert_eq!(single, expected);
+
+            // A batch of the image twice
+            let batched =
+                inputs.iter().map(|v| stack![Axis(0), v.view(), v.view()]).collect::<Vec<_>>();
+            let views = batched.iter().map(|b| b.view()).collect::<Vec<_>>();
+            let results = postprocessor.postprocess(&views, postprocessor.default_thresholds());
+            assert_eq!(results.unwrap(), [expected.clone(), expected]);
+
+            assert!(postprocessor.decode(&inputs[1..], 0.5).is_err());
+        }
+    }
+
+    #[test]
+    fn unittest_yolo_matches_postprocess() {
+        let anchors = arr3(&[[[1.25, 1.625], [2.0, 3.75], [4.125, 2.875]]]);
+        let yolo = RustPostprocessor::new(
+            anchors,
+            vec![8.0],
+            None,
+            Some(1),
+            Layout::Nahwc,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut output = uniform((2, 3, 20, 20, 7), 0.0, 1.0, 7);
+        output.slice_mut(s![.., .., .., .., 0..2]).mapv_inplace(|v| v * 0.2 + 0.4);
+        let inputs = [output.view().into_dyn()];
+        let thresholds = yolo.default_thresholds();
+
+        let layers = yolo.layers(inputs.iter().map(|i| i.view())).unwrap();
+        let expected = yolo.postprocess(
+            &layers,
+            &ConfThreshold::Scalar(thresholds.conf),
+            &IouThreshold::Scalar(thresholds.iou),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+            None,
+        );
+        let results = Postprocessor::postprocess(&yolo, &inputs, thresholds).unwrap();
+        assert_eq!(results.len(), 2);
+        for (result, expected) in results.iter().zip(&expected) {
+            assert!(!result.is_empty());
+            assert_eq!(result, expected.detections);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::str::FromStr;
+
+use ndarray::{s, Array3, Array4, ArrayView3, Axis};
+use rayon::prelude::*;
+
+use super::opencv::{resize, Interpolation};
+use crate::common::{Error, Result};
+
+/// Models of the reference preprocessing of the MLPerf Inference vision benchmarks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MlperfModel {
+    /// ResNet50-v1.5 on ImageNet, `pre_process_vgg`
+    ResNet50,
+    /// SSD-MobileNet-v1 on COCO, `pre_process_coco_mobilenet`, keeping the 0-255 pixel values
+    SsdMobileNet,
+    /// SSD-ResNet34 on COCO, `pre_process_coco_resnet34`
+    SsdResNet34,
+    /// RetinaNet on OpenImages, `pre_process_openimages_retinanet`
+    RetinaNet,
+}
+
+impl FromStr for MlperfModel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "resnet50" => Ok(Self::ResNet50),
+            "ssd-mobilenet" => Ok(Self::SsdMobileNet),
+            "ssd-resnet34" => Ok(Self::SsdResNet34),
+            "retinanet" => Ok(Self::RetinaNet),
+            _ => Err(Error::invalid(format!(
+                "unknown MLPerf model {s:?}, expected one of \"resnet50\", \"ssd-mobilenet\", \
+                 \"ssd-resnet34\" or \"retinanet\""
+            ))),
+        }
+    }
+}
+
+impl MlperfModel {
+    /// (height, width) of the network input
+    pub fn input_shape(&self) -> (usize, usize) {
+        match self {
+            Self::ResNet50 => (224, 224),
+            Self::SsdMobileNet => (300, 300),
+            Self::SsdResNet34 => (1200, 1200),
+            Self::RetinaNet => (800, 800),
+        }
+    }
+
+    /// Resized HWC image, before the normalization
+    fn resize(&self, image: ArrayView3<'_, u8>) -> Array3<u8> {
+        let (out_h, out_w) = self.input_shape();
+        match self {
+            Self::ResNet50 => {
+                // `resize_with_aspectratio` with a scale of 87.5%, then `center_crop`
+                let (height, width, _) = image.dim();
+                let (new_h, new_w) = (
+                    (100.0 * out_h as f64 / 87.5) as usize,
+                    (100.0 * out_w as f64 / 87.5) as usize,
+                );
+                let (h, w) = if height > width {
+                    ((new_h * height) as f64 / width as f64, new_w as f64)
+                } else {
+                    (new_h as f64, (new_w * width) as f64 / height as f64)
+                };
+                let resized = resize(image, (h as usize, w as usize), Interpolation::Area);
+                let (h, w, _) = resized.dim();
+                let left = ((w as f64 - out_w as f64) / 2.0) as usize;
+                let top = ((h as f64 - out_h as f64) / 2.0) as usize;
+                resized.slice(s![top..top + out_h, left..left + out_w, ..]).to_owned()
+            }
+            _ => resize(image, (out_h, out_w), Interpolation::Linear),
+        }
+    }
+
+    /// Normalized value of a pixel of channel `c`, computed in the precision of the reference
+    fn normalize(&self, p: u8, c: usize) -> f32 {
+        match self {
+            Self::ResNet50 => p as f32 - [123.68f32, 116.78, 103.94][c],
+            Self::SsdMobileNet => p as f32,
+            // float64 as NumPy promotes the division by a Python float
+            Self::SsdResNet34 => {
+                let mean = [0.485f32, 0.456, 0.406][c] as f64;
+                let std = [0.229f32, 0.224, 0.225][c] as f64;
+                ((p as f64 / 255.0 - mean) / std) as f32
+            }
+            Self::RetinaNet => (p as f64 / 255.0) as f32,
+        }
+    }
+}
+
+/// Preprocess RGB HWC images into the NCHW input of an MLPerf model as its reference
+/// implementation does with OpenCV and NumPy
+///
+/// `swap_rb` reverses the channel order first, for BGR images as read by `cv2.imread`.
+pub fn mlperf_preprocess(
+    images: &[ArrayView3<'_, u8>],
+    model: MlperfModel,
+    swap_rb: bool,
+) -> Result<Array4<f32>> {
+    if let Some(image) = images.iter().find(|image| image.shape()[2] != 3 || image.is_empty()) {
+        return Err(Error::invalid(format!(
+            "images must have shape (H, W, 3) but got {:?}",
+            image.shape()
+        )));
+    }
+    let (out_h, out_w) = model.input_shape();
+    let mut tensor = Array4::zeros((images.len(), 3, out_h, out_w));
+    tensor
+        .axis_iter_mut(Axis(0))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .zip(images.par_iter())
+        .for_each(|(mut chw, image)| {
+            let resized = model.resize(*image);
+            for c in 0..3 {
+                let source = if swap_rb { 2 - c } else { c };
+                chw.index_axis_mut(Axis(0), c).zip_mut_with(
+                    &resized.index_axis(Axis(2), source),
+                    |v, &p| {
+                        *v = model.normalize(p, c);
+                    },
+                );
+            }
+        });
+    Ok(tensor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_mlperf_preprocess() {
+        // A wide image resized to 256 rows and center cropped
+        let image = Array3::from_shape_fn((100, 200, 3), |(_, x, c)| (x + c) as u8);
+        let tensor = mlperf_preprocess(&[image.view()], MlperfModel::ResNet50, false).unwrap();
+        assert_eq!(tensor.dim(), (1, 3, 224, 224));
+        // Columns of 512 resized to 224 from the 144th, 2.56 times in each source pixel
+        assert_eq!(tensor[(0, 0, 0, 0)], 56.0 - 123.68);
+        assert_eq!(tensor[(0, 2, 10, 0)], 58.0 - 103.94);
+
+        let image = Array3::from_elem((10, 10, 3), 255);
+        let tensor = mlperf_preprocess(&[image.view()], MlperfModel::SsdResNet34, true).unwrap();
+        assert_eq!(tensor.dim(), (1, 3, 1200, 1200));
+        assert_eq!(tensor[(0, 1, 600, 600)], ((1.0 - 0.456f32 as f64) / 0.224f32 as f64) as f32);
+
+        assert!("resnet".parse::<MlperfModel>().is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use ndarray::{s, Array3, Array4, ArrayView3, ArrayView4, Axis};
+#[cfg(feature = "python")]
+use numpy::{IntoPyArray, PyReadonlyArray3};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::letterbox::Letterbox;
+use crate::common::resize::{resize_bilinear, Region};
+use crate::common::{Error, Result};
+
+mod mlperf;
+mod opencv;
+
+pub use mlperf::{mlperf_preprocess, MlperfModel};
+pub use opencv::{resize, Interpolation};
+
+#[cfg(feature = "python")]
+use crate::common::threads;
+
+/// Gray of the padding of Ultralytics
+pub const DEFAULT_PAD_VALUE: u8 = 114;
+
+/// Per-channel normalization `(value - mean) / std` of the 0-255 pixel values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+impl Default for Normalization {
+    /// Scaling to [0, 1] as the YOLO models expect
+    fn default() -> Self {
+        Self { mean: vec![0.0], std: vec![255.0] }
+    }
+}
+
+impl Normalization {
+    /// Mean and std of each of `channels`, a single value applying to every channel
+    fn per_channel(&self, channels: usize) -> Result<(Vec<f32>, Vec<f32>)> {
+        let expand = |values: &[f32], name: &str| match values.len() {
+            1 => Ok(vec![values[0]; channels]),
+            n if n == channels => Ok(values.to_vec()),
+            n => Err(Error::invalid(format!("expected 1 or {channels} {name} values but got {n}"))),
+        };
+        let (mean, std) = (expand(&self.mean, "mean")?, expand(&self.std, "std")?);
+        if std.iter().any(|&s| s == 0.0) {
+            return Err(Error::invalid("std must not be zero"));
+        }
+        Ok((mean, std))
+    }
+}
+
+/// Resize an HWC image into `input_shape` (height, width) keeping its aspect ratio, centered
+/// and padded with `pad_value` as the letterbox of Ultralytics
+///
+/// Returns the HWC input and the letterbox mapping the detections back to the image.
+pub fn letterbox_image(
+    image: ArrayView3<'_, u8>,
+    input_shape: (usize, usize),
+    pad_value: u8,
+) -> Result<(Array3<u8>, Letterbox)> {
+    let (height, width, channels) = image.dim();
+    if image.is_empty() {
+        return Err(Error::invalid(format!("image must not be empty but got {:?}", image.shape())));
+    }
+    let (input_h, input_w) = input_shape;
+    if input_h == 0 || input_w == 0 {
+        return Err(Error::invalid(format!(
+            "input shape must be positive but got {input_shape:?}"
+        )));
+    }
+    let scale = f32::min(input_h as f32 / height as f32, input_w as f32 / width as f32);
+    let (resized_h, resized_w) = (
+        ((height as f32 * scale).round() as usize).clamp(1, input_h),
+        ((width as f32 * scale).round() as usize).clamp(1, input_w),
+    );
+    // Rounded as Ultralytics does, the extra pixel of an odd padding going to the bottom right
+    let top = ((input_h - resized_h) as f32 / 2.0 - 0.1).round().max(0.0) as usize;
+    let left = ((input_w - resized_w) as f32 / 2.0 - 0.1).round().max(0.0) as usize;
+
+    let mut input = Array3::from_elem((input_h, input_w, channels), pad_value);
+    let region = Region { x1: 0.0, y1: 0.0, x2: width as f32, y2: height as f32 };
+    let inner = input.slice_mut(s![top..top + resized_h, left..left + resized_w, ..]);
+    resize_bilinear(image, region, inner);
+    let letterbox = Letterbox {
+        scale,
+        pad_x: left as f32,
+        pad_y: top as f32,
+        original_shape: (height as f32, width as f32),
+    };
+    Ok((input, letterbox))
+}
+
+/// Letterbox a batch of HWC images and normalize them into an NCHW tensor
+///
+/// `swap_rb` reverses the channel order, e.g. to feed images of OpenCV in BGR to models trained
+/// on RGB. Returns the tensor and the letterbox of each image to map its detections back.
+pub fn letterbox(
+    images: &[ArrayView3<'_, u8>],
+    input_shape: (usize, usize),
+    pad_value: u8,
+    normalization: &Normalization,
+    swap_rb: bool,
+) -> Result<(Array4<f32>, Vec<Letterbox>)> {
+    let channels = images.first().map_or(3, |image| image.shape()[2]);
+    if let Some(image) = images.iter().find(|image| image.shape()[2] != channels) {
+        return Err(Error::invalid(format!(
+            "images must have {channels} channels but got {:?}",
+            image.shape()
+        )));
+    }
+    let (mean, std) = normalization.per_channel(channels)?;
+
+    let (input_h, input_w) = input_shape;
+    let mut tensor = Array4::zeros((images.len(), channels, input_h, input_w));
+    let letterboxes = tensor
+        .axis_iter_mut(Axis(0))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .zip(images.par_iter())
+        .map(|(mut chw, image)| {
+            let (input, letterbox) = letterbox_image(image.view(), input_shape, pad_value)?;
+            for c in 0..channels {
+                let source = if swap_rb { channels - 1 - c } else { c };
+                let (mean, std) = (mean[source], std[source]);
+                chw.index_axis_mut(Axis(0), c).zip_mut_with(
+                    &input.index_axis(Axis(2), source),
+                    |v, &p| {
+                        *v = (p as f32 - mean) / std;
+                    },
+                );
+            }
+            Ok(letterbox)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((tensor, letterboxes))
+}
+
+/// Quantize a tensor to u8 as `round(value / scale) + zero_point`, saturating
+pub fn quantize(tensor: ArrayView4<'_, f32>, scale: f32, zero_point: i32) -> Result<Array4<u8>> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(Error::invalid(format!("scale must be positive but got {scale}")));
+    }
+    Ok(tensor.mapv(|v| ((v / scale).round() as i32 + zero_point).clamp(0, u8::MAX as i32) as u8))
+}
+
+/// Letterbox images and normalize them into an NCHW tensor for the network input
+///
+/// Args:
+///     images (Sequence[numpy.ndarray]): uint8 images of shape (H, W, C)
+///     input_shape (Tuple[int, int]): (height, width) of the network input
+///     pad_value (Optional[int]): Value of the padding, default is 114
+///     mean (Optional[Sequence[float]]): Mean of each channel, or of all of them, subtracted from
+///         the 0-255 pixel values, default is 0
+///     std (Optional[Sequence[float]]): Std of each channel, or of all of them, dividing the
+///         pixel values, default is 255
+///     swap_rb (Optional[bool]): Whether to reverse the channel order, e.g. from the BGR of
+///         OpenCV to RGB, default is False
+///     quantize (Optional[Tuple[float, int]]): (scale, zero_point) to quantize the normalized
+///         values to uint8, default is to keep them as float32
+///
+/// Returns:
+///     Tuple[numpy.ndarray, List[Tuple[float, float, float]]]: Tensor of shape
+///         (N, C, height, width), and the (scale, pad_x, pad_y) of each image to pass as the
+///         `letterbox_params` of the postprocessors
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "letterbox")]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn letterbox_py(
+    py: Python<'_>,
+    images: Vec<PyReadonlyArray3<'_, u8>>,
+    input_shape: (usize, usize),
+    pad_value: Option<u8>,
+    mean: Option<Vec<f32>>,
+    std: Option<Vec<f32>>,
+    swap_rb: Option<bool>,
+    quantize: Option<(f32, i32)>,
+) -> PyResult<(PyObject, Vec<(f32, f32, f32)>)> {
+    let default = Normalization::default();
+    let normalization =
+        Normalization { mean: mean.unwrap_or(default.mean), std: std.unwrap_or(default.std) };
+    let images = images.iter().map(|image| image.as_array()).collect::<Vec<_>>();
+
+    let (tensor, letterboxes) = threads::allow_threads(py, || {
+        letterbox(
+            &images,
+            input_shape,
+            pad_value.unwrap_or(DEFAULT_PAD_VALUE),
+            &normalization,
+            swap_rb.unwrap_or(false),
+        )
+    })?;
+    let params = letterboxes.iter().map(|l| (l.scale, l.pad_x, l.pad_y)).collect();
+    let tensor = match quantize {
+        Some((scale, zero_point)) => {
+            let quantized =
+                threads::allow_threads(py, || self::quantize(tensor.view(), scale, zero_point))?;
+            quantized.into_pyarray(py).to_object(py)
+        }
+        None => tensor.into_pyarray(py).to_object(py),
+    };
+    Ok((tensor, params))
+}
+
+/// Preprocess images as the reference implementation of an MLPerf Inference benchmark
+///
+/// Args:
+///     images (Sequence[numpy.ndarray]): uint8 RGB images of shape (H, W, 3)
+///     model (str): One of "resnet50", "ssd-mobilenet", "ssd-resnet34" or "retinanet"
+///     swap_rb (Optional[bool]): Whether the images are in BGR, as read by `cv2.imread`, default
+///         is False
+///     quantize (Optional[Tuple[float, int]]): (scale, zero_point) to quantize the normalized
+///         values to uint8, default is to keep them as float32
+///
+/// Returns:
+///     numpy.ndarray: Tensor of shape (N, 3, height, width) of the model input
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "mlperf_preprocess")]
+fn mlperf_preprocess_py(
+    py: Python<'_>,
+    images: Vec<PyReadonlyArray3<'_, u8>>,
+    model: &str,
+    swap_rb: Option<bool>,
+    quantize: Option<(f32, i32)>,
+) -> PyResult<PyObject> {
+    let model = model.parse::<MlperfModel>()?;
+    let images = images.iter().map(|image| image.as_array()).collect::<Vec<_>>();
+
+    let tensor =
+        threads::allow_threads(py, || mlperf_preprocess(&images, model, swap_rb.unwrap_or(false)))?;
+    Ok(match quantize {
+        Some((scale, zero_point)) => {
+            let quantized =
+                threads::allow_threads(py, || self::quantize(tensor.view(), scale, zero_point))?;
+            quantized.into_pyarray(py).to_object(py)
+        }
+        None => tensor.into_pyarray(py).to_object(py),
+    })
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn preprocess(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(letterbox_py, m)?)?;
+    m.add_function(wrap_pyfunction!(mlperf_preprocess_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_preprocess() {
+        // A 2x4 image of a constant color into a 8x8 input, padded by 2 rows on each side
+        let image = Array3::from_shape_fn((2, 4, 3), |(_, _, c)| [10, 20, 30][c]);
+        let (input, l) = letterbox_image(image.view(), (8, 8), DEFAULT_PAD_VALUE).unwrap();
+        assert_eq!((l.scale, l.pad_x, l.pad_y), (2.0, 0.0, 2.0));
+        assert_eq!(input.slice(s![1, 0, ..]).to_vec(), [114; 3]);
+        assert_eq!(input.slice(s![2, 0, ..]).to_vec(), [10, 20, 30]);
+        assert_eq!(input.slice(s![5, 7, ..]).to_vec(), [10, 20, 30]);
+        assert_eq!(input.slice(s![6, 7, ..]).to_vec(), [114; 3]);
+
+        let normalization = Normalization { mean: vec![10.0], std: vec![10.0, 20.0, 40.0] };
+        let (tensor, _) =
+            letterbox(&[image.view(), image.view()], (8, 8), 0, &normalization, true).unwrap();
+        assert_eq!(tensor.dim(), (2, 3, 8, 8));
+        // Channels reversed, the first one being the last of the image
+        assert_eq!(
+            [tensor[(1, 0, 3, 3)], tensor[(1, 1, 3, 3)], tensor[(1, 2, 3, 3)]],
+            [0.5, 0.5, 0.0]
+        );
+        assert_eq!(tensor[(0, 0, 0, 0)], -0.25);
+
+        let quantized = quantize(tensor.view(), 0.25, 2).unwrap();
+        assert_eq!([quantized[(0, 0, 3, 3)], quantized[(0, 0, 0, 0)]], [4, 1]);
+
+        let gray = Array3::<u8>::zeros((2, 2, 1));
+        assert!(letterbox(&[image.view(), gray.view()], (8, 8), 0, &normalization, false).is_err());
+    }
+}
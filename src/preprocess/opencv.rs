@@ -0,0 +1,245 @@
+//! Resize kernels of uint8 HWC images reproducing the arithmetic of OpenCV's `cv2.resize`
+//!
+//! The reference preprocessing of MLPerf resizes images with OpenCV, whose `INTER_LINEAR` works
+//! in 11-bit fixed point and whose `INTER_AREA` accumulates in single precision. Both are
+//! mirrored here, down to the rounding of the SSE kernels of the builds of `opencv-python`.
+
+use ndarray::{Array3, ArrayView3};
+
+/// Fixed-point scale of the linear interpolation coefficients
+const COEF_SCALE: f32 = 2048.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// `cv2.INTER_LINEAR`
+    Linear,
+    /// `cv2.INTER_AREA`
+    Area,
+}
+
+/// `cvRound`, rounding half to even
+fn round_half_even(v: f32) -> f32 {
+    let rounded = v.round();
+    if (v - v.trunc()).abs() == 0.5 {
+        2.0 * (v / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+fn saturate_u8(v: f32) -> u8 {
+    round_half_even(v).clamp(0.0, u8::MAX as f32) as u8
+}
+
+/// Resize an HWC image to `size` (height, width) as `cv2.resize` does
+pub fn resize(
+    image: ArrayView3<'_, u8>,
+    size: (usize, usize),
+    interpolation: Interpolation,
+) -> Array3<u8> {
+    let (height, width, _) = image.dim();
+    let (out_h, out_w) = size;
+    let scale_x = 1.0 / (out_w as f64 / width as f64);
+    let scale_y = 1.0 / (out_h as f64 / height as f64);
+    let (iscale_x, iscale_y) = (scale_x.round(), scale_y.round());
+    let is_area_fast =
+        (scale_x - iscale_x).abs() < f64::EPSILON && (scale_y - iscale_y).abs() < f64::EPSILON;
+
+    // Halving is the same with both and the area kernel is exact
+    let interpolation = match interpolation {
+        Interpolation::Linear if is_area_fast && iscale_x == 2.0 && iscale_y == 2.0 => {
+            Interpolation::Area
+        }
+        interpolation => interpolation,
+    };
+    match interpolation {
+        Interpolation::Area if scale_x >= 1.0 && scale_y >= 1.0 => {
+            if is_area_fast {
+                resize_area_fast(image, size, iscale_x as usize, iscale_y as usize)
+            } else {
+                resize_area(image, size, scale_x, scale_y)
+            }
+        }
+        // Upscaling with INTER_AREA is a linear interpolation of other coefficients
+        _ => resize_linear(image, size, scale_x, scale_y, interpolation == Interpolation::Area),
+    }
+}
+
+/// Source index and fixed-point coefficients of the two source pixels of each destination pixel
+/// along an axis
+fn linear_tab(
+    src_size: usize,
+    dst_size: usize,
+    scale: f64,
+    area_mode: bool,
+) -> Vec<(usize, usize, i32, i32)> {
+    (0..dst_size)
+        .map(|d| {
+            let (mut s, mut f) = if area_mode {
+                let s = (d as f64 * scale).floor() as i64;
+                let f = ((d + 1) as f64 - (s + 1) as f64 / scale) as f32;
+                (s, if f <= 0.0 { 0.0 } else { f - f.floor() })
+            } else {
+                let f = ((d as f64 + 0.5) * scale - 0.5) as f32;
+                let s = f.floor() as i64;
+                (s, f - s as f32)
+            };
+            if s < 0 {
+                (s, f) = (0, 0.0);
+            }
+            if s >= src_size as i64 - 1 {
+                (s, f) = (src_size as i64 - 1, 0.0);
+            }
+            let s = s as usize;
+            let a0 = round_half_even((1.0 - f) * COEF_SCALE) as i32;
+            let a1 = round_half_even(f * COEF_SCALE) as i32;
+            (s, (s + 1).min(src_size - 1), a0, a1)
+        })
+        .collect()
+}
+
+fn resize_linear(
+    image: ArrayView3<'_, u8>,
+    (out_h, out_w): (usize, usize),
+    scale_x: f64,
+    scale_y: f64,
+    area_mode: bool,
+) -> Array3<u8> {
+    let (height, width, channels) = image.dim();
+    let xtab = linear_tab(width, out_w, scale_x, area_mode);
+    let ytab = linear_tab(height, out_h, scale_y, area_mode);
+    let row_len = out_w * channels;
+    let horizontal = |sy: usize| {
+        let mut row = Vec::with_capacity(row_len);
+        for &(x0, x1, a0, a1) in &xtab {
+            for c in 0..channels {
+                row.push(image[(sy, x0, c)] as i32 * a0 + image[(sy, x1, c)] as i32 * a1);
+            }
+        }
+        row
+    };
+    // The SSE kernel covers the row by 16 and then 8 values, leaving the rest to the scalar
+    // code, which rounds differently
+    let vector_end = row_len / 16 * 16 + if row_len % 16 > 8 { 8 } else { 0 };
+
+    let mut out = Array3::zeros((out_h, out_w, channels));
+    let out_rows = out.as_slice_mut().expect("a new array is contiguous").chunks_mut(row_len);
+    for (dst, &(y0, y1, b0, b1)) in out_rows.zip(&ytab) {
+        let (row0, row1) = (horizontal(y0), horizontal(y1));
+        for (x, d) in dst.iter_mut().enumerate() {
+            let (s0, s1) = (row0[x], row1[x]);
+            let v = if x < vector_end {
+                (((b0 * (s0 >> 4)) >> 16) + ((b1 * (s1 >> 4)) >> 16) + 2) >> 2
+            } else {
+                (s0 * b0 + s1 * b1 + (1 << 21)) >> 22
+            };
+            *d = v.clamp(0, u8::MAX as i32) as u8;
+        }
+    }
+    out
+}
+
+/// Averages of the blocks of an integer downscale
+fn resize_area_fast(
+    image: ArrayView3<'_, u8>,
+    (out_h, out_w): (usize, usize),
+    scale_x: usize,
+    scale_y: usize,
+) -> Array3<u8> {
+    let inv_area = 1.0 / (scale_x * scale_y) as f32;
+    Array3::from_shape_fn((out_h, out_w, image.shape()[2]), |(y, x, c)| {
+        let mut sum = 0;
+        for sy in y * scale_y..(y + 1) * scale_y {
+            for sx in x * scale_x..(x + 1) * scale_x {
+                sum += image[(sy, sx, c)] as i32;
+            }
+        }
+        if scale_x == 2 && scale_y == 2 {
+            ((sum + 2) >> 2) as u8
+        } else {
+            saturate_u8(sum as f32 * inv_area)
+        }
+    })
+}
+
+/// (destination, source, weight) of the source pixels overlapping each destination pixel along
+/// an axis, as `computeResizeAreaTab`
+fn area_tab(src_size: usize, dst_size: usize, scale: f64) -> Vec<(usize, usize, f32)> {
+    let mut tab = Vec::new();
+    for d in 0..dst_size {
+        let fs1 = d as f64 * scale;
+        let fs2 = fs1 + scale;
+        let cell = scale.min(src_size as f64 - fs1);
+        let s2 = (fs2.floor() as usize).min(src_size - 1);
+        let s1 = (fs1.ceil() as usize).min(s2);
+        if s1 as f64 - fs1 > 1e-3 {
+            tab.push((d, s1 - 1, ((s1 as f64 - fs1) / cell) as f32));
+        }
+        for s in s1..s2 {
+            tab.push((d, s, (1.0 / cell) as f32));
+        }
+        if fs2 - s2 as f64 > 1e-3 {
+            tab.push((d, s2, ((fs2 - s2 as f64).min(1.0).min(cell) / cell) as f32));
+        }
+    }
+    tab
+}
+
+/// Weighted averages of the source pixels overlapping each destination pixel, accumulated in
+/// the order of OpenCV
+fn resize_area(
+    image: ArrayView3<'_, u8>,
+    (out_h, out_w): (usize, usize),
+    scale_x: f64,
+    scale_y: f64,
+) -> Array3<u8> {
+    let (height, width, channels) = image.dim();
+    let xtab = area_tab(width, out_w, scale_x);
+    let ytab = area_tab(height, out_h, scale_y);
+    let row_len = out_w * channels;
+
+    let mut out = Array3::zeros((out_h, out_w, channels));
+    let mut sum = vec![0f32; row_len];
+    let mut buf = vec![0f32; row_len];
+    for (j, &(dy, sy, beta)) in ytab.iter().enumerate() {
+        buf.fill(0.0);
+        for &(dx, sx, alpha) in &xtab {
+            for c in 0..channels {
+                buf[dx * channels + c] += image[(sy, sx, c)] as f32 * alpha;
+            }
+        }
+        let first = j == 0 || ytab[j - 1].0 != dy;
+        for (s, &b) in sum.iter_mut().zip(&buf) {
+            *s = if first { beta * b } else { *s + beta * b };
+        }
+        if ytab.get(j + 1).map_or(true, |next| next.0 != dy) {
+            for (i, &s) in sum.iter().enumerate() {
+                out[(dy, i / channels, i % channels)] = saturate_u8(s);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn unittest_opencv_resize() {
+        let ramp = Array::from_shape_vec((1, 2, 1), vec![0, 100]).unwrap();
+        let upscaled = resize(ramp.view(), (1, 4), Interpolation::Linear);
+        assert_eq!(upscaled.into_raw_vec(), [0, 25, 75, 100]);
+
+        let row = Array::from_shape_vec((1, 3, 1), vec![30, 60, 90]).unwrap();
+        assert_eq!(resize(row.view(), (1, 2), Interpolation::Area).into_raw_vec(), [40, 80]);
+
+        // Halving rounds half up, unlike the other integer downscales
+        let block = Array::from_shape_vec((2, 2, 1), vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(resize(block.view(), (1, 1), Interpolation::Linear).into_raw_vec(), [3]);
+        let block = Array::from_shape_vec((1, 4, 1), vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(resize(block.view(), (1, 1), Interpolation::Area).into_raw_vec(), [2]);
+    }
+}
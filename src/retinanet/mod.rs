@@ -0,0 +1,336 @@
+use std::fmt;
+
+use itertools::izip;
+use ndarray::{Array2, ArrayView4, Axis};
+use numpy::{PyArray2, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::BoundingBox;
+use crate::common::{descending, logit, threads, validate_anchor_heads};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor as AnchorPostprocessor, DEFAULT_MAX_DET};
+
+const DEFAULT_IMAGE_SIZE: (usize, usize) = (800, 800);
+const DEFAULT_STRIDES: [usize; 5] = [8, 16, 32, 64, 128];
+const DEFAULT_ANCHOR_SIZES: [f32; 5] = [32., 64., 128., 256., 512.];
+const ASPECT_RATIOS: [f32; 3] = [0.5, 1.0, 2.0];
+const NUM_SCALES: usize = 3;
+const NUM_ANCHORS: usize = ASPECT_RATIOS.len() * NUM_SCALES;
+// Same as torchvision's `BoxCoder.bbox_xform_clip`
+const BBOX_XFORM_CLIP: f32 = 4.135_166_5; // ln(1000 / 16)
+
+/// Anchors of a single FPN level laid out as `(y, x, anchor)`, matching the head output order
+#[derive(Debug, Clone)]
+struct LevelAnchors {
+    height: usize,
+    width: usize,
+    anchors: Vec<BoundingBox>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    image_size: (usize, usize),
+    strides: Vec<usize>,
+    levels: Vec<LevelAnchors>,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grids = self.levels.iter().map(|l| (l.height, l.width)).collect::<Vec<_>>();
+        write!(
+            f,
+            "RustPostProcessor {{ image_size: {:?}, strides: {:?}, grids: {:?}, num_anchors: {} }}",
+            self.image_size, self.strides, grids, NUM_ANCHORS
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(
+        image_size: Option<(usize, usize)>,
+        strides: Option<Vec<usize>>,
+        anchor_sizes: Option<Vec<f32>>,
+    ) -> PyResult<Self> {
+        let image_size = image_size.unwrap_or(DEFAULT_IMAGE_SIZE);
+        let strides = strides.unwrap_or_else(|| DEFAULT_STRIDES.to_vec());
+        let anchor_sizes = anchor_sizes.unwrap_or_else(|| DEFAULT_ANCHOR_SIZES.to_vec());
+        if strides.len() != anchor_sizes.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected an anchor size per stride but got {} strides and {} anchor sizes",
+                strides.len(),
+                anchor_sizes.len()
+            )));
+        }
+        if strides.is_empty() || strides.contains(&0) {
+            return Err(PyValueError::new_err(format!(
+                "strides must be a non-empty list of positive strides but got {strides:?}"
+            )));
+        }
+
+        let levels = izip!(&strides, &anchor_sizes)
+            .map(|(&stride, &size)| Self::generate_anchors(image_size, stride, size))
+            .collect();
+
+        Ok(Self { image_size, strides, levels })
+    }
+
+    /// Generate the anchor grid of one level the same way torchvision's `AnchorGenerator` does
+    fn generate_anchors(image_size: (usize, usize), stride: usize, size: f32) -> LevelAnchors {
+        let height = (image_size.0 + stride - 1) / stride;
+        let width = (image_size.1 + stride - 1) / stride;
+
+        // Aspect ratio major, scale minor
+        let mut base_anchors = Vec::with_capacity(NUM_ANCHORS);
+        for ratio in ASPECT_RATIOS {
+            let h_ratio = ratio.sqrt();
+            let w_ratio = 1.0 / h_ratio;
+            for octave in 0..NUM_SCALES {
+                let scale = size * 2f32.powf(octave as f32 / NUM_SCALES as f32);
+                let (ws, hs) = (w_ratio * scale, h_ratio * scale);
+                base_anchors.push([
+                    (-ws / 2.0).round(),
+                    (-hs / 2.0).round(),
+                    (ws / 2.0).round(),
+                    (hs / 2.0).round(),
+                ]);
+            }
+        }
+
+        let mut anchors = Vec::with_capacity(height * width * NUM_ANCHORS);
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = ((x * stride) as f32, (y * stride) as f32);
+                for &[x1, y1, x2, y2] in &base_anchors {
+                    anchors.push(BoundingBox {
+                        px1: sx + x1,
+                        py1: sy + y1,
+                        px2: sx + x2,
+                        py2: sy + y2,
+                    });
+                }
+            }
+        }
+
+        LevelAnchors { height, width, anchors }
+    }
+
+    /// Apply box deltas to an anchor with unit weights, clipping the result into the image
+    #[inline]
+    fn apply_deltas(&self, anchor: &BoundingBox, deltas: [f32; 4]) -> [f32; 4] {
+        let [dx, dy, dw, dh] = deltas;
+        let dw = f32::min(dw, BBOX_XFORM_CLIP);
+        let dh = f32::min(dh, BBOX_XFORM_CLIP);
+
+        let cx = dx * anchor.pw() + anchor.pcx();
+        let cy = dy * anchor.ph() + anchor.pcy();
+        let w = f32::exp(dw) * anchor.pw();
+        let h = f32::exp(dh) * anchor.ph();
+
+        let (height, width) = (self.image_size.0 as f32, self.image_size.1 as f32);
+        [
+            (cx - 0.5 * w).clamp(0.0, width),
+            (cy - 0.5 * h).clamp(0.0, height),
+            (cx + 0.5 * w).clamp(0.0, width),
+            (cy + 0.5 * h).clamp(0.0, height),
+        ]
+    }
+
+    /// Decode one image: per-level threshold and top-k, then box regression
+    fn box_decode(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+        top_k: usize,
+    ) -> DetectionBoxes {
+        let logit_threshold = logit(conf_threshold);
+        let mut dbox = DetectionBoxes::empty();
+
+        for (level, cls, reg) in izip!(&self.levels, cls_outputs, box_outputs) {
+            let cls = cls.index_axis(Axis(0), batch_index);
+            let reg = reg.index_axis(Axis(0), batch_index);
+            let num_classes = cls.shape()[0] / NUM_ANCHORS;
+
+            // (logit, class, anchor_index)
+            let mut candidates: Vec<(f32, usize, usize)> = Vec::new();
+            for y in 0..level.height {
+                for x in 0..level.width {
+                    for a in 0..NUM_ANCHORS {
+                        for k in 0..num_classes {
+                            let v = unsafe { *cls.uget((a * num_classes + k, y, x)) };
+                            if v > logit_threshold {
+                                candidates.push((v, k, (y * level.width + x) * NUM_ANCHORS + a));
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Sigmoid is monotonic, so selecting the top-k on logits is equivalent
+            if candidates.len() > top_k {
+                candidates.select_nth_unstable_by(top_k, |a, b| {
+                    descending(a.0, b.0).then((a.1, a.2).cmp(&(b.1, b.2)))
+                });
+                candidates.truncate(top_k);
+            }
+
+            let len = candidates.len();
+            let (mut x1, mut y1, mut x2, mut y2) = (
+                Vec::with_capacity(len),
+                Vec::with_capacity(len),
+                Vec::with_capacity(len),
+                Vec::with_capacity(len),
+            );
+            let mut scores = Vec::with_capacity(len);
+            let mut classes = Vec::with_capacity(len);
+            for (v, k, anchor_index) in candidates {
+                let a = anchor_index % NUM_ANCHORS;
+                let cell = anchor_index / NUM_ANCHORS;
+                let (y, x) = (cell / level.width, cell % level.width);
+                let deltas = unsafe {
+                    [
+                        *reg.uget((a * 4, y, x)),
+                        *reg.uget((a * 4 + 1, y, x)),
+                        *reg.uget((a * 4 + 2, y, x)),
+                        *reg.uget((a * 4 + 3, y, x)),
+                    ]
+                };
+                let [bx1, by1, bx2, by2] = self.apply_deltas(&level.anchors[anchor_index], deltas);
+
+                x1.push(bx1);
+                y1.push(by1);
+                x2.push(bx2);
+                y2.push(by2);
+                scores.push(1.0 / (1.0 + f32::exp(-v)));
+                classes.push(k as f32);
+            }
+            dbox.append(x1.into(), y1.into(), x2.into(), y2.into(), scores.into(), classes.into());
+        }
+
+        dbox
+    }
+
+    /// RetinaNet postprocess function
+    /// The vector in function input/output is for batched input/output
+    fn postprocess(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        top_k: usize,
+    ) -> Vec<Array2<f32>> {
+        (0..cls_outputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| {
+                let mut dbox =
+                    self.box_decode(cls_outputs, box_outputs, batch_index, conf_threshold, top_k);
+                let indices =
+                    AnchorPostprocessor::nms(&dbox, iou_threshold, None, DEFAULT_MAX_DET, false);
+                dbox.select_and_convert(&indices)
+            })
+            .collect()
+    }
+
+    fn validate(
+        &self,
+        cls_outputs: &[ArrayView4<'_, f32>],
+        box_outputs: &[ArrayView4<'_, f32>],
+    ) -> PyResult<()> {
+        let grids = self.levels.iter().map(|l| (l.height, l.width)).collect::<Vec<_>>();
+        Ok(validate_anchor_heads(&grids, NUM_ANCHORS, cls_outputs, box_outputs)?)
+    }
+}
+
+/// RetinaNet PostProcessor
+///
+/// It generates the FPN anchor grid as torchvision does (3 scales x 3 aspect ratios per level),
+/// applies box deltas, keeps the per-level top-k candidates, and runs class-aware NMS.
+/// Class outputs hold raw logits with shape `(N, 9 * num_classes, H, W)` and box outputs
+/// hold deltas with shape `(N, 9 * 4, H, W)`.
+///
+/// Args:
+///     image_size (Optional[Tuple[int, int]]): (height, width) of network input, default is (800, 800)
+///     strides (Optional[Sequence[int]]): Strides of each FPN level, default is [8, 16, 32, 64, 128]
+///     anchor_sizes (Optional[Sequence[float]]): Anchor size of each level, default is [32, 64, 128, 256, 512]
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(
+        image_size: Option<(usize, usize)>,
+        strides: Option<Vec<usize>>,
+        anchor_sizes: Option<Vec<f32>>,
+    ) -> PyResult<Self> {
+        if let (Some(strides), Some(anchor_sizes)) = (&strides, &anchor_sizes) {
+            if strides.len() != anchor_sizes.len() {
+                return Err(PyValueError::new_err(format!(
+                    "expected one anchor size per stride but got {} strides and {} anchor sizes",
+                    strides.len(),
+                    anchor_sizes.len()
+                )));
+            }
+        }
+        Ok(Self(RustPostprocessor::new(image_size, strides, anchor_sizes)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     cls_outputs (Sequence[numpy.ndarray]): Class logits of each level
+    ///     box_outputs (Sequence[numpy.ndarray]): Box deltas of each level
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): IoU threshold
+    ///     top_k (Optional[int]): Number of candidates kept per level before NMS, default is 1000
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    fn eval(
+        &self,
+        py: Python<'_>,
+        cls_outputs: Vec<PyReadonlyArray4<'_, f32>>,
+        box_outputs: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        top_k: Option<usize>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        const DEFAULT_TOP_K: usize = 1000;
+
+        let cls_outputs = cls_outputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        let box_outputs = box_outputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&cls_outputs, &box_outputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(
+                &cls_outputs,
+                &box_outputs,
+                conf_threshold,
+                iou_threshold,
+                top_k.unwrap_or(DEFAULT_TOP_K),
+            )
+        });
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn retinanet(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+use ndarray::{Array2, Array3, ArrayView3, ArrayView4};
+use numpy::{PyArray3, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub align_corners: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RustPostProcessor {{ align_corners: {} }}", self.align_corners)
+    }
+}
+
+/// Source index pair and interpolation weight of one output coordinate
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    lo: usize,
+    hi: usize,
+    weight: f32,
+}
+
+impl RustPostprocessor {
+    fn new(align_corners: Option<bool>) -> Self {
+        Self { align_corners: align_corners.unwrap_or(false) }
+    }
+
+    /// Bilinear sampling positions along one axis, following `torch.nn.functional.interpolate`
+    fn samples(&self, input_size: usize, output_size: usize) -> Vec<Sample> {
+        let scale = if self.align_corners {
+            if output_size > 1 {
+                (input_size - 1) as f32 / (output_size - 1) as f32
+            } else {
+                0.0
+            }
+        } else {
+            input_size as f32 / output_size as f32
+        };
+
+        (0..output_size)
+            .map(|i| {
+                let src = if self.align_corners {
+                    i as f32 * scale
+                } else {
+                    ((i as f32 + 0.5) * scale - 0.5).max(0.0)
+                };
+                let lo = (src as usize).min(input_size - 1);
+                let hi = (lo + 1).min(input_size - 1);
+                Sample { lo, hi, weight: src - lo as f32 }
+            })
+            .collect()
+    }
+
+    /// Per-pixel argmax over channels of a single `(C, H, W)` logit map, optionally after
+    /// bilinearly resizing the logits to `output_shape`
+    fn argmax(
+        &self,
+        logits: ArrayView3<'_, f32>,
+        output_shape: Option<(usize, usize)>,
+    ) -> Array2<u8> {
+        let &[num_classes, height, width] = logits.shape() else { unreachable!() };
+        let (out_height, out_width) = output_shape.unwrap_or((height, width));
+
+        let mut labels = vec![0u8; out_height * out_width];
+        if (out_height, out_width) == (height, width) {
+            labels.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+                for (x, label) in row.iter_mut().enumerate() {
+                    let mut best = (0, f32::NEG_INFINITY);
+                    for c in 0..num_classes {
+                        let v = unsafe { *logits.uget((c, y, x)) };
+                        if v > best.1 {
+                            best = (c, v);
+                        }
+                    }
+                    *label = best.0 as u8;
+                }
+            });
+        } else {
+            let ys = self.samples(height, out_height);
+            let xs = self.samples(width, out_width);
+            labels.par_chunks_mut(out_width).zip(ys).for_each(|(row, sy)| {
+                for (label, &sx) in row.iter_mut().zip(&xs) {
+                    let mut best = (0, f32::NEG_INFINITY);
+                    for c in 0..num_classes {
+                        let at = |y: usize, x: usize| unsafe { *logits.uget((c, y, x)) };
+                        let top =
+                            at(sy.lo, sx.lo) * (1.0 - sx.weight) + at(sy.lo, sx.hi) * sx.weight;
+                        let bottom =
+                            at(sy.hi, sx.lo) * (1.0 - sx.weight) + at(sy.hi, sx.hi) * sx.weight;
+                        let v = top * (1.0 - sy.weight) + bottom * sy.weight;
+                        if v > best.1 {
+                            best = (c, v);
+                        }
+                    }
+                    *label = best.0 as u8;
+                }
+            });
+        }
+
+        Array2::from_shape_vec((out_height, out_width), labels).unwrap()
+    }
+
+    /// Segmentation postprocess function
+    fn postprocess(
+        &self,
+        logits: ArrayView4<'_, f32>,
+        output_shape: Option<(usize, usize)>,
+    ) -> Array3<u8> {
+        let &[batch_size, _, height, width] = logits.shape() else { unreachable!() };
+        let (out_height, out_width) = output_shape.unwrap_or((height, width));
+
+        let mut labels = Array3::zeros((batch_size, out_height, out_width));
+        for (logits, mut label) in logits.outer_iter().zip(labels.outer_iter_mut()) {
+            label.assign(&self.argmax(logits, output_shape));
+        }
+        labels
+    }
+}
+
+/// Semantic Segmentation PostProcessor
+///
+/// It computes the per-pixel argmax over channels of NCHW logits (e.g., DeepLabV3), returning
+/// class indices which can be used directly as palette indices.
+///
+/// Args:
+///     align_corners (Optional[bool]): `align_corners` of the bilinear resize, default is False
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(align_corners: Option<bool>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(align_corners)))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     logits (numpy.ndarray): Logits of shape (N, C, H, W)
+    ///     output_shape (Optional[Tuple[int, int]]): Original (height, width) to resize logits to
+    ///         before argmax, the logits' resolution is kept if None
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Class indices of shape (N, H, W) as uint8
+    fn eval(
+        &self,
+        py: Python<'_>,
+        logits: PyReadonlyArray4<'_, f32>,
+        output_shape: Option<(usize, usize)>,
+    ) -> PyResult<Py<PyArray3<u8>>> {
+        let logits = logits.as_array();
+        let &[_, num_classes, height, width] = logits.shape() else { unreachable!() };
+        if num_classes == 0 || num_classes > u8::MAX as usize + 1 {
+            return Err(PyValueError::new_err(format!(
+                "number of classes must be between 1 and 256 but got {num_classes}"
+            )));
+        }
+        if height == 0 || width == 0 || output_shape.is_some_and(|(h, w)| h == 0 || w == 0) {
+            return Err(PyValueError::new_err(format!(
+                "empty spatial dimensions: logits {:?}, output_shape {output_shape:?}",
+                logits.shape()
+            )));
+        }
+
+        let labels = threads::allow_threads(py, || self.0.postprocess(logits, output_shape));
+        Ok(PyArray3::from_owned_array(py, labels).to_owned())
+    }
+}
+
+pub(crate) fn segmentation(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,942 @@
+//! Postprocess of SSD heads, of which MLPerf SSD-MobileNet and SSD-ResNet34 are configurations
+
+use itertools::Itertools;
+use ndarray::{Array1, Array3, ArrayView3, ArrayViewD, Axis, Ix4};
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArray3};
+#[cfg(feature = "python")]
+use pyo3::exceptions::{PyTypeError, PyValueError};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::{PyDict, PyList};
+use rayon::prelude::*;
+
+use crate::common::ssd_postprocess::{
+    dequantize_map, validate_quantized, BoundingBox, CenteredBox, DetectionResult,
+    DetectionResults, PriorConfig, QuantizedScoreMaps, ScoreMaps,
+};
+#[cfg(feature = "python")]
+use crate::common::threads;
+use crate::common::{
+    descending, logit, sigmoid, soft_suppress, uninitialized_vec, wbf, Error, IouThreshold,
+    NmsMethod, Quantization, Result,
+};
+#[cfg(feature = "python")]
+use crate::common::{extract_all, extract_quantization, map_to_f32, PyDetectionResults};
+use crate::postprocessor::{Postprocessor, Thresholds};
+use crate::yolo::utils::DetectionBoxes;
+
+/// Activation of the class logits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreActivation {
+    /// Sigmoid of each logit, of which those that cannot reach the score threshold are skipped
+    Sigmoid,
+    /// Softmax over the classes, the background included
+    Softmax,
+}
+
+/// Order of the channels of the box and score tensors of a feature map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadLayout {
+    /// By prior, then (cy, cx, h, w) or class, as in TensorFlow exports
+    PriorMajor,
+    /// By (cx, cy, w, h) or class, then prior, as in PyTorch exports
+    PriorMinor,
+}
+
+/// Configuration of an SSD postprocessor
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsdConfig {
+    /// Number of classes, of which class 0 is the background
+    pub num_classes: usize,
+    pub priors: PriorConfig,
+    /// Variances of the center offsets and of the log sizes the boxes are encoded with
+    pub variances: [f32; 2],
+    pub activation: ScoreActivation,
+    pub layout: HeadLayout,
+    /// Minimum score of the detections
+    pub score_threshold: f32,
+    pub nms_threshold: f32,
+    /// Number of highest scoring candidates of each class kept before NMS, all if None
+    pub max_candidates: Option<usize>,
+    /// Number of highest scoring detections of the image kept after NMS, all if None
+    pub max_detections: Option<usize>,
+}
+
+impl SsdConfig {
+    /// MLPerf SSD-MobileNet on 300x300 images
+    pub fn ssd_mobilenet() -> Self {
+        Self {
+            num_classes: 91,
+            priors: PriorConfig::ssd_mobilenet(),
+            variances: [0.1, 0.2],
+            activation: ScoreActivation::Sigmoid,
+            layout: HeadLayout::PriorMajor,
+            score_threshold: 0.3,
+            nms_threshold: 0.6,
+            max_candidates: None,
+            max_detections: None,
+        }
+    }
+
+    /// MLPerf SSD-ResNet34 on 1200x1200 images
+    pub fn ssd_resnet34() -> Self {
+        Self {
+            num_classes: 81,
+            priors: PriorConfig::ssd_resnet34(),
+            variances: [0.1, 0.2],
+            activation: ScoreActivation::Softmax,
+            layout: HeadLayout::PriorMinor,
+            score_threshold: 0.05,
+            nms_threshold: 0.5,
+            max_candidates: Some(200),
+            max_detections: Some(200),
+        }
+    }
+}
+
+/// Class scores of an image
+enum ClassScores<'a, S: ?Sized> {
+    /// Read from the activated score tensors
+    Maps(&'a S),
+    /// Normalized over the classes, by class then box
+    Dense(Vec<f32>),
+}
+
+/// Tensors of shape (channel, y, x) of each feature map
+type FeatureMaps = Vec<Array3<f32>>;
+
+#[derive(Debug, Clone)]
+pub struct SsdPostprocessor {
+    config: SsdConfig,
+    /// Index of the first box of each feature map, followed by the number of boxes
+    base_index: Vec<usize>,
+    priors_per_location: Vec<usize>,
+    box_priors: Vec<CenteredBox>,
+}
+
+impl SsdPostprocessor {
+    pub fn new(config: SsdConfig) -> Result<Self> {
+        if config.num_classes < 2 {
+            return Err(Error::invalid(format!(
+                "num_classes must count the background and at least one class but got {}",
+                config.num_classes
+            )));
+        }
+        if !config.variances.iter().all(|&v| v.is_finite() && v > 0.0) {
+            return Err(Error::invalid(format!(
+                "variances must be positive but got {:?}",
+                config.variances
+            )));
+        }
+        let box_priors = config.priors.generate()?;
+        let priors_per_location = config.priors.priors_per_location();
+        let base_index = std::iter::once(0)
+            .chain(config.priors.feature_maps.iter().zip(&priors_per_location).scan(
+                0,
+                |base, (&side, &num_priors)| {
+                    *base += side * side * num_priors;
+                    Some(*base)
+                },
+            ))
+            .collect();
+        Ok(Self { config, base_index, priors_per_location, box_priors })
+    }
+
+    pub fn config(&self) -> &SsdConfig {
+        &self.config
+    }
+
+    fn num_boxes(&self) -> usize {
+        self.box_priors.len()
+    }
+
+    /// Channels of the (cy, cx, h, w) encoding of prior `prior` of feature map `map`
+    #[inline]
+    fn box_channels(&self, map: usize, prior: usize) -> [usize; 4] {
+        match self.config.layout {
+            HeadLayout::PriorMajor => [prior * 4, prior * 4 + 1, prior * 4 + 2, prior * 4 + 3],
+            HeadLayout::PriorMinor => {
+                let n = self.priors_per_location[map];
+                [prior + n, prior, prior + 3 * n, prior + 2 * n]
+            }
+        }
+    }
+
+    /// Channel of the score of `class` for prior `prior` of feature map `map`
+    #[inline]
+    fn score_channel(&self, map: usize, prior: usize, class: usize) -> usize {
+        match self.config.layout {
+            HeadLayout::PriorMajor => prior * self.config.num_classes + class,
+            HeadLayout::PriorMinor => class * self.priors_per_location[map] + prior,
+        }
+    }
+
+    /// Check that there are box and score tensors of shape (channel, y, x) for each feature map
+    fn validate_inputs(&self, boxes: &[&[usize]], scores: &[&[usize]]) -> Result<()> {
+        let num_maps = self.priors_per_location.len();
+        for (name, shapes, channels) in
+            [("box", boxes, 4), ("score", scores, self.config.num_classes)]
+        {
+            if shapes.len() != num_maps {
+                return Err(Error::invalid(format!(
+                    "expected {num_maps} input {name} tensors but got {}",
+                    shapes.len()
+                )));
+            }
+            for (map, &shape) in shapes.iter().enumerate() {
+                let side = self.config.priors.feature_maps[map];
+                let expected = [channels * self.priors_per_location[map], side, side];
+                if shape != expected {
+                    return Err(Error::invalid(format!(
+                        "expected {name} tensor {map} of shape {expected:?} but got {shape:?}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Candidates of `class_index` whose score reaches `score_threshold` as (score, box index),
+    /// only the `max_candidates` highest scoring ones in descending order if limited
+    fn candidates<S: ScoreMaps + ?Sized>(
+        &self,
+        scores: &ClassScores<'_, S>,
+        class_index: usize,
+        score_threshold: f32,
+    ) -> Vec<(f32, usize)> {
+        let mut filtered = Vec::with_capacity(self.num_boxes());
+        for (map, &side) in self.config.priors.feature_maps.iter().enumerate() {
+            for prior in 0..self.priors_per_location[map] {
+                let channel = self.score_channel(map, prior, class_index);
+                let base = self.base_index[map] + prior * side * side;
+                for y in 0..side {
+                    for x in 0..side {
+                        let box_index = base + y * side + x;
+                        let score = match scores {
+                            ClassScores::Maps(maps) => {
+                                maps.score_at_least(map, channel, y, x, score_threshold)
+                            }
+                            ClassScores::Dense(dense) => {
+                                let score = dense[class_index * self.num_boxes() + box_index];
+                                (score >= score_threshold).then_some(score)
+                            }
+                        };
+                        if let Some(score) = score {
+                            filtered.push((score, box_index));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(max_candidates) = self.config.max_candidates {
+            filtered.sort_unstable_by(|a, b| descending(a.0, b.0).then(a.1.cmp(&b.1)));
+            filtered.truncate(max_candidates);
+        }
+        filtered
+    }
+
+    fn filter_result<S: ScoreMaps + ?Sized>(
+        &self,
+        query_index: f32,
+        scores: &ClassScores<'_, S>,
+        boxes: &[BoundingBox],
+        class_index: usize,
+        nms_method: NmsMethod,
+    ) -> Vec<DetectionResult> {
+        let SsdConfig { score_threshold, nms_threshold, .. } = self.config;
+        let mut filtered = self.candidates(scores, class_index, score_threshold);
+        let max_candidates = self.config.max_candidates.unwrap_or(self.num_boxes());
+        let detection = |(score, box_index): (f32, usize)| DetectionResult {
+            index: query_index,
+            bbox: boxes[box_index],
+            score,
+            class: class_index as f32,
+        };
+
+        if nms_method == NmsMethod::Wbf {
+            let candidates = filtered.into_iter().map(detection).collect::<Vec<_>>();
+            return wbf::fuse_single(
+                candidates.into(),
+                &IouThreshold::Scalar(nms_threshold),
+                max_candidates,
+            )
+            .0;
+        }
+        if nms_method != NmsMethod::Hard {
+            let kept = soft_suppress(
+                filtered,
+                boxes,
+                nms_method,
+                nms_threshold,
+                score_threshold,
+                max_candidates,
+            );
+            return kept.into_iter().map(detection).collect();
+        }
+
+        filtered.sort_unstable_by(|a, b| descending(a.0, b.0).then(a.1.cmp(&b.1)));
+        let kept = greedy_nms(filtered.iter().map(|c| boxes[c.1]), nms_threshold);
+        kept.into_iter().map(|i| detection(filtered[i])).collect()
+    }
+
+    fn filter_results<S: ScoreMaps + ?Sized>(
+        &self,
+        query_index: f32,
+        scores: &ClassScores<'_, S>,
+        boxes: &[BoundingBox],
+        nms_method: NmsMethod,
+    ) -> DetectionResults {
+        let mut results = (1..self.config.num_classes)
+            .into_par_iter()
+            .map(|class| self.filter_result(query_index, scores, boxes, class, nms_method))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect_vec();
+
+        if let Some(max_detections) = self.config.max_detections {
+            // Stable, so that ties keep the order of the classes
+            results.sort_by(|a, b| descending(a.score, b.score));
+            results.truncate(max_detections);
+        }
+        results.into()
+    }
+
+    /// Exponentials of the logits normalized over the classes, by class then box
+    fn softmax(&self, exp_scores: &[Array3<f32>]) -> Vec<f32> {
+        let num_boxes = self.num_boxes();
+        let mut scores = unsafe { uninitialized_vec(self.config.num_classes * num_boxes) };
+        scores.par_chunks_mut(num_boxes).enumerate().for_each(|(class, scores)| {
+            for (map, &side) in self.config.priors.feature_maps.iter().enumerate() {
+                for prior in 0..self.priors_per_location[map] {
+                    let channel = self.score_channel(map, prior, class);
+                    let base = self.base_index[map] + prior * side * side;
+                    for y in 0..side {
+                        for x in 0..side {
+                            scores[base + y * side + x] = exp_scores[map][(channel, y, x)];
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut sums = vec![0f32; num_boxes];
+        for class_scores in scores.chunks_exact(num_boxes) {
+            for (sum, score) in sums.iter_mut().zip(class_scores) {
+                *sum += score;
+            }
+        }
+        scores.par_chunks_mut(num_boxes).for_each(|class_scores| {
+            for (score, sum) in class_scores.iter_mut().zip(&sums) {
+                *score /= sum;
+            }
+        });
+        scores
+    }
+
+    /// Decode boxes whose center offsets are already scaled by their variance
+    fn decode_box(&self, boxes: &[Array3<f32>]) -> Vec<BoundingBox> {
+        let [variance_xy, variance_wh] = self.config.variances;
+        let mut ret = unsafe { uninitialized_vec(self.num_boxes()) };
+
+        for (map, b) in boxes.iter().enumerate() {
+            let side = self.config.priors.feature_maps[map];
+            for prior in 0..self.priors_per_location[map] {
+                let [cy, cx, h, w] = self.box_channels(map, prior);
+                let base = self.base_index[map] + prior * side * side;
+                for y in 0..side {
+                    for x in 0..side {
+                        let bx = CenteredBox {
+                            pcy: b[(cy, y, x)],
+                            pcx: b[(cx, y, x)],
+                            ph: f32::exp(b[(h, y, x)] * variance_wh / variance_xy),
+                            pw: f32::exp(b[(w, y, x)] * variance_wh / variance_xy),
+                        };
+                        let box_index = base + y * side + x;
+                        ret[box_index] = self.box_priors[box_index].adjust(bx).into();
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    #[tracing::instrument(
+        target = "chrome_layer",
+        fields(name = "PostProcess", cat = "Mlperf"),
+        skip(self, scores, boxes)
+    )]
+    fn postprocess<S: ScoreMaps + ?Sized>(
+        &self,
+        index: f32,
+        scores: &ClassScores<'_, S>,
+        boxes: &[Array3<f32>],
+        nms_method: NmsMethod,
+    ) -> DetectionResults {
+        let boxes = self.decode_box(boxes);
+        self.filter_results(index, scores, &boxes, nms_method)
+    }
+
+    /// Postprocess of activated scores, exponentials of the logits for a softmax
+    fn postprocess_activated(
+        &self,
+        scores: &[Array3<f32>],
+        boxes: &[Array3<f32>],
+        nms_method: NmsMethod,
+    ) -> DetectionResults {
+        match self.config.activation {
+            ScoreActivation::Sigmoid => {
+                self.postprocess(0f32, &ClassScores::Maps(scores), boxes, nms_method)
+            }
+            ScoreActivation::Softmax => {
+                let scores = ClassScores::<[Array3<f32>]>::Dense(self.softmax(scores));
+                self.postprocess(0f32, &scores, boxes, nms_method)
+            }
+        }
+    }
+
+    /// Activation of each logit before any normalization over the classes
+    fn activate(&self) -> fn(f32) -> f32 {
+        match self.config.activation {
+            ScoreActivation::Sigmoid => sigmoid,
+            ScoreActivation::Softmax => f32::exp,
+        }
+    }
+
+    /// Postprocess of the raw box and score tensors of the feature maps of an image
+    pub fn eval(
+        &self,
+        boxes: &[ArrayView3<'_, f32>],
+        scores: &[ArrayView3<'_, f32>],
+        nms_method: NmsMethod,
+    ) -> Result<DetectionResults> {
+        let (scaled_boxes, activated) = self.prepare(boxes, scores)?;
+        Ok(self.postprocess_activated(&activated, &scaled_boxes, nms_method))
+    }
+
+    /// Boxes scaled by the variance of their center offsets and activated scores of an image
+    fn prepare(
+        &self,
+        boxes: &[ArrayView3<'_, f32>],
+        scores: &[ArrayView3<'_, f32>],
+    ) -> Result<(FeatureMaps, FeatureMaps)> {
+        let box_shapes = boxes.iter().map(|b| b.shape()).collect::<Vec<_>>();
+        let score_shapes = scores.iter().map(|s| s.shape()).collect::<Vec<_>>();
+        self.validate_inputs(&box_shapes, &score_shapes)?;
+        let variance_xy = self.config.variances[0];
+        let scaled_boxes = boxes.iter().map(|b| b.mapv(|t| t * variance_xy)).collect::<Vec<_>>();
+        let activated = scores.iter().map(|s| s.mapv(self.activate())).collect::<Vec<_>>();
+        Ok((scaled_boxes, activated))
+    }
+
+    /// Candidates of an image whose score reaches `score_threshold` before NMS, grouped by
+    /// class in ascending order
+    fn decode_candidates(
+        &self,
+        boxes: &[ArrayView3<'_, f32>],
+        scores: &[ArrayView3<'_, f32>],
+        score_threshold: f32,
+    ) -> Result<DetectionBoxes> {
+        let (scaled_boxes, activated) = self.prepare(boxes, scores)?;
+        let boxes = self.decode_box(&scaled_boxes);
+        let scores = match self.config.activation {
+            ScoreActivation::Sigmoid => ClassScores::Maps(&activated[..]),
+            ScoreActivation::Softmax => ClassScores::Dense(self.softmax(&activated)),
+        };
+        let candidates = (1..self.config.num_classes)
+            .into_par_iter()
+            .map(|class| (class, self.candidates(&scores, class, score_threshold)))
+            .collect::<Vec<_>>();
+
+        let len = candidates.iter().map(|(_, c)| c.len()).sum();
+        let mut fields = [(); 6].map(|_| Vec::with_capacity(len));
+        for (class, (score, box_index)) in
+            candidates.into_iter().flat_map(|(class, c)| c.into_iter().map(move |c| (class, c)))
+        {
+            let BoundingBox { py1, px1, py2, px2 } = boxes[box_index];
+            for (field, value) in fields.iter_mut().zip([px1, py1, px2, py2, score, class as f32]) {
+                field.push(value);
+            }
+        }
+        let [x1, y1, x2, y2, scores, classes] = fields.map(Array1::from);
+        Ok(DetectionBoxes::new(x1, y1, x2, y2, scores, classes))
+    }
+
+    /// Postprocess of the quantized raw box and score tensors of the feature maps of an image
+    /// With a sigmoid, scores are only dequantized where their quantized logits may reach the
+    /// score threshold. A softmax normalizes them over all classes, so every score is
+    /// dequantized, within the same pass as their exponential.
+    pub fn eval_quantized<T: Copy + Sync + Into<i32>>(
+        &self,
+        boxes: &[ArrayView3<'_, T>],
+        box_quantization: &[Quantization],
+        scores: &[ArrayView3<'_, T>],
+        score_quantization: &[Quantization],
+        nms_method: NmsMethod,
+    ) -> Result<DetectionResults> {
+        let box_shapes = boxes.iter().map(|b| b.shape()).collect::<Vec<_>>();
+        let score_shapes = scores.iter().map(|s| s.shape()).collect::<Vec<_>>();
+        self.validate_inputs(&box_shapes, &score_shapes)?;
+        validate_quantized("box", boxes, box_quantization)?;
+        validate_quantized("score", scores, score_quantization)?;
+        let variance_xy = self.config.variances[0];
+        let scaled_boxes = boxes
+            .iter()
+            .zip(box_quantization)
+            .map(|(b, q)| dequantize_map(b.view(), q, |t| t * variance_xy))
+            .collect::<Vec<_>>();
+        match self.config.activation {
+            ScoreActivation::Sigmoid => {
+                let min_logit = logit(self.config.score_threshold);
+                let scores =
+                    QuantizedScoreMaps::new(scores, score_quantization, sigmoid, min_logit);
+                Ok(self.postprocess(0f32, &ClassScores::Maps(&scores), &scaled_boxes, nms_method))
+            }
+            ScoreActivation::Softmax => {
+                let exp_scores = scores
+                    .iter()
+                    .zip(score_quantization)
+                    .map(|(s, q)| dequantize_map(s.view(), q, f32::exp))
+                    .collect::<Vec<_>>();
+                Ok(self.postprocess_activated(&exp_scores, &scaled_boxes, nms_method))
+            }
+        }
+    }
+}
+
+impl Postprocessor for SsdPostprocessor {
+    fn default_thresholds(&self) -> Thresholds {
+        Thresholds { conf: self.config.score_threshold, iou: self.config.nms_threshold }
+    }
+
+    /// Inputs are the box tensors of the feature maps followed by their score tensors, of shape
+    /// (channel, y, x) for a single image or (batch, channel, y, x)
+    fn decode(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+    ) -> Result<Vec<DetectionBoxes>> {
+        let num_maps = self.config.priors.feature_maps.len();
+        if inputs.len() != 2 * num_maps {
+            return Err(Error::invalid(format!(
+                "expected {num_maps} box tensors followed by {num_maps} score tensors but got {} \
+                 tensors",
+                inputs.len()
+            )));
+        }
+        let batched = inputs
+            .iter()
+            .map(|input| match input.ndim() {
+                3 => Ok(input.view().insert_axis(Axis(0)).into_dimensionality::<Ix4>().unwrap()),
+                4 => Ok(input.view().into_dimensionality::<Ix4>().unwrap()),
+                _ => Err(Error::invalid(format!(
+                    "inputs must be 3D (channel, y, x) or 4D (batch, channel, y, x) but got shape \
+                     {:?}",
+                    input.shape()
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let batch_size = batched[0].shape()[0];
+        if batched.iter().any(|input| input.shape()[0] != batch_size) {
+            return Err(Error::invalid("inputs must all have the same batch size"));
+        }
+
+        (0..batch_size)
+            .into_par_iter()
+            .map(|image| {
+                let views = batched.iter().map(|b| b.index_axis(Axis(0), image)).collect_vec();
+                let (boxes, scores) = views.split_at(num_maps);
+                self.decode_candidates(boxes, scores, conf_threshold)
+            })
+            .collect()
+    }
+
+    /// Greedy hard NMS within each class, keeping the `max_detections` highest scoring
+    /// detections if limited
+    fn suppress(&self, candidates: &mut DetectionBoxes, thresholds: Thresholds) -> Vec<usize> {
+        let bbox = |i: usize| BoundingBox {
+            py1: candidates.y1[i],
+            px1: candidates.x1[i],
+            py2: candidates.y2[i],
+            px2: candidates.x2[i],
+        };
+        let mut kept = Vec::new();
+        let mut start = 0;
+        while start < candidates.len() {
+            let class = candidates.classes[start];
+            let end = (start..candidates.len())
+                .find(|&i| candidates.classes[i] != class)
+                .unwrap_or(candidates.len());
+            let mut order = (start..end).collect_vec();
+            order.sort_by(|&a, &b| descending(candidates.scores[a], candidates.scores[b]));
+            let class_kept = greedy_nms(order.iter().map(|&i| bbox(i)), thresholds.iou);
+            kept.extend(class_kept.into_iter().map(|i| order[i]));
+            start = end;
+        }
+
+        if let Some(max_detections) = self.config.max_detections {
+            kept.sort_by(|&a, &b| descending(candidates.scores[a], candidates.scores[b]));
+            kept.truncate(max_detections);
+        }
+        kept
+    }
+}
+
+/// Indices of the boxes kept by greedy hard NMS of boxes sorted by descending score, each
+/// suppressing the later ones it overlaps by more than `iou_threshold`
+fn greedy_nms(boxes: impl IntoIterator<Item = BoundingBox>, iou_threshold: f32) -> Vec<usize> {
+    let mut kept: Vec<(usize, BoundingBox)> = Vec::new();
+    for (i, bbox) in boxes.into_iter().enumerate() {
+        if kept.iter().all(|(_, k)| bbox.iou(k) <= iou_threshold) {
+            kept.push((i, bbox));
+        }
+    }
+    kept.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(feature = "python")]
+impl SsdPostprocessor {
+    /// `eval` of float32, float16 or bfloat16 arrays with the GIL released
+    pub(crate) fn eval_py(
+        &self,
+        py: Python<'_>,
+        boxes: &PyList,
+        scores: &PyList,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<DetectionResults> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let variance_xy = self.config.variances[0];
+        let scaled_boxes = map_to_f32(boxes, |t| t * variance_xy)?;
+        let activated = map_to_f32(scores, self.activate())?;
+        let box_shapes = scaled_boxes.iter().map(|b| b.shape()).collect::<Vec<_>>();
+        let score_shapes = activated.iter().map(|s| s.shape()).collect::<Vec<_>>();
+        self.validate_inputs(&box_shapes, &score_shapes)?;
+
+        Ok(threads::allow_threads(py, || {
+            self.postprocess_activated(&activated, &scaled_boxes, nms_method)
+        }))
+    }
+
+    /// `eval_quantized` of uint8 or int8 arrays with the GIL released
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn eval_quantized_py(
+        &self,
+        py: Python<'_>,
+        boxes: Vec<&PyAny>,
+        scores: Vec<&PyAny>,
+        box_scales: Vec<&PyAny>,
+        box_zero_points: Vec<&PyAny>,
+        score_scales: Vec<&PyAny>,
+        score_zero_points: Vec<&PyAny>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<DetectionResults> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let box_quantization = extract_quantization(&box_scales, &box_zero_points)?;
+        let score_quantization = extract_quantization(&score_scales, &score_zero_points)?;
+
+        let quantization = (box_quantization.as_slice(), score_quantization.as_slice());
+        if let (Ok(b), Ok(s)) = (extract_all(&boxes), extract_all(&scores)) {
+            self.run_quantized::<u8>(py, &b, &s, quantization, nms_method)
+        } else if let (Ok(b), Ok(s)) = (extract_all(&boxes), extract_all(&scores)) {
+            self.run_quantized::<i8>(py, &b, &s, quantization, nms_method)
+        } else {
+            Err(PyTypeError::new_err("boxes and scores must be all uint8 or all int8 arrays"))
+        }
+    }
+
+    fn run_quantized<T: numpy::Element + Copy + Sync + Into<i32>>(
+        &self,
+        py: Python<'_>,
+        boxes: &[PyReadonlyArray3<'_, T>],
+        scores: &[PyReadonlyArray3<'_, T>],
+        (box_quantization, score_quantization): (&[Quantization], &[Quantization]),
+        nms_method: NmsMethod,
+    ) -> PyResult<DetectionResults> {
+        let boxes = boxes.iter().map(|b| b.as_array()).collect::<Vec<_>>();
+        let scores = scores.iter().map(|s| s.as_array()).collect::<Vec<_>>();
+        Ok(threads::allow_threads(py, || {
+            self.eval_quantized(&boxes, box_quantization, &scores, score_quantization, nms_method)
+        })?)
+    }
+}
+
+/// Postprocessor of an SSD head, MLPerf SSD-MobileNet by default
+///
+/// Args:
+///     model (Optional[str]): "ssd_mobilenet" or "ssd_resnet34", the configuration to start from
+///     config (Optional[Dict[str, Any]]): Keys of the configuration overriding those of the
+///         model, as returned by `to_dict`
+#[cfg(feature = "python")]
+#[pyclass(name = "SsdPostProcessor", module = "furiosa_native_postprocess.ssd")]
+pub struct PySsdPostProcessor(SsdPostprocessor);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PySsdPostProcessor {
+    #[new]
+    fn new(model: Option<&str>, config: Option<&PyDict>) -> PyResult<Self> {
+        let mut base = match model.unwrap_or("ssd_mobilenet") {
+            "ssd_mobilenet" => SsdConfig::ssd_mobilenet(),
+            "ssd_resnet34" => SsdConfig::ssd_resnet34(),
+            model => {
+                return Err(PyValueError::new_err(format!(
+                    "model must be \"ssd_mobilenet\" or \"ssd_resnet34\" but got {model:?}"
+                )))
+            }
+        };
+        if let Some(config) = config {
+            base.update(config)?;
+        }
+        Ok(Self(SsdPostprocessor::new(base)?))
+    }
+
+    /// Configuration of the postprocessor
+    ///
+    /// Returns:
+    ///     Dict[str, Any]: Configuration of which any subset may be passed as `config`
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.0.config.to_py_dict(py)
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     boxes (Sequence[numpy.ndarray]): Box tensors of dtype float32, float16 or bfloat16
+    ///     scores (Sequence[numpy.ndarray]): Score tensors of dtype float32, float16 or bfloat16
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///
+    /// Returns:
+    ///     PyDetectionResults: Detections of the image
+    fn eval(
+        &self,
+        py: Python<'_>,
+        boxes: &PyList,
+        scores: &PyList,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<PyDetectionResults> {
+        Ok(self.0.eval_py(py, boxes, scores, nms_method, sigma)?.into())
+    }
+
+    /// Evaluate the postprocess, returning the results as a single array
+    ///
+    /// Args:
+    ///     boxes (Sequence[numpy.ndarray]): Box tensors of dtype float32, float16 or bfloat16
+    ///     scores (Sequence[numpy.ndarray]): Score tensors of dtype float32, float16 or bfloat16
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+    fn eval_to_array(
+        &self,
+        py: Python<'_>,
+        boxes: &PyList,
+        scores: &PyList,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<Py<PyArray2<f32>>> {
+        let results = self.0.eval_py(py, boxes, scores, nms_method, sigma)?;
+        Ok(PyArray2::from_owned_array(py, results.into_array()).to_owned())
+    }
+
+    /// Evaluate the postprocess of quantized outputs, dequantized during decode
+    ///
+    /// Args:
+    ///     boxes (Sequence[numpy.ndarray]): Box tensors of dtype uint8 or int8
+    ///     scores (Sequence[numpy.ndarray]): Score tensors of the same dtype as the boxes
+    ///     box_scales (Sequence[Union[float, Sequence[float]]]): Scale of each box tensor, or of
+    ///         each of its channels (first axis)
+    ///     box_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each box tensor,
+    ///         or of each of its channels
+    ///     score_scales (Sequence[Union[float, Sequence[float]]]): Scale of each score tensor, or
+    ///         of each of its channels
+    ///     score_zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each score
+    ///         tensor, or of each of its channels
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///
+    /// Returns:
+    ///     PyDetectionResults: Detections of the image
+    #[allow(clippy::too_many_arguments)]
+    fn eval_quantized(
+        &self,
+        py: Python<'_>,
+        boxes: Vec<&PyAny>,
+        scores: Vec<&PyAny>,
+        box_scales: Vec<&PyAny>,
+        box_zero_points: Vec<&PyAny>,
+        score_scales: Vec<&PyAny>,
+        score_zero_points: Vec<&PyAny>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<PyDetectionResults> {
+        let results = self.0.eval_quantized_py(
+            py,
+            boxes,
+            scores,
+            box_scales,
+            box_zero_points,
+            score_scales,
+            score_zero_points,
+            nms_method,
+            sigma,
+        )?;
+        Ok(results.into())
+    }
+}
+
+#[cfg(feature = "python")]
+impl SsdConfig {
+    const KEYS: [&'static str; 9] = [
+        "num_classes",
+        "priors",
+        "variances",
+        "activation",
+        "layout",
+        "score_threshold",
+        "nms_threshold",
+        "max_candidates",
+        "max_detections",
+    ];
+
+    /// Override the keys of `config` present in the dict
+    pub(crate) fn update(&mut self, config: &PyDict) -> PyResult<()> {
+        for (key, value) in config.iter() {
+            let key = key.extract::<&str>()?;
+            if !Self::KEYS.contains(&key) {
+                return Err(PyValueError::new_err(format!("unknown config key {key:?}")));
+            }
+            match key {
+                "num_classes" => self.num_classes = value.extract()?,
+                "priors" => self.priors = value.extract()?,
+                "variances" => self.variances = value.extract()?,
+                "activation" => {
+                    self.activation = match value.extract()? {
+                        "sigmoid" => ScoreActivation::Sigmoid,
+                        "softmax" => ScoreActivation::Softmax,
+                        other => {
+                            return Err(PyValueError::new_err(format!(
+                                "activation must be \"sigmoid\" or \"softmax\" but got {other:?}"
+                            )))
+                        }
+                    }
+                }
+                "layout" => {
+                    self.layout = match value.extract()? {
+                        "prior_major" => HeadLayout::PriorMajor,
+                        "prior_minor" => HeadLayout::PriorMinor,
+                        other => {
+                            return Err(PyValueError::new_err(format!(
+                                "layout must be \"prior_major\" or \"prior_minor\" but got \
+                                 {other:?}"
+                            )))
+                        }
+                    }
+                }
+                "score_threshold" => self.score_threshold = value.extract()?,
+                "nms_threshold" => self.nms_threshold = value.extract()?,
+                "max_candidates" => self.max_candidates = value.extract()?,
+                "max_detections" => self.max_detections = value.extract()?,
+                _ => unreachable!("keys are checked above"),
+            }
+        }
+        Ok(())
+    }
+
+    fn to_py_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let config = PyDict::new(py);
+        config.set_item("num_classes", self.num_classes)?;
+        config.set_item("priors", self.priors.to_py_dict(py)?)?;
+        config.set_item("variances", self.variances.to_vec())?;
+        let activation = match self.activation {
+            ScoreActivation::Sigmoid => "sigmoid",
+            ScoreActivation::Softmax => "softmax",
+        };
+        config.set_item("activation", activation)?;
+        let layout = match self.layout {
+            HeadLayout::PriorMajor => "prior_major",
+            HeadLayout::PriorMinor => "prior_minor",
+        };
+        config.set_item("layout", layout)?;
+        config.set_item("score_threshold", self.score_threshold)?;
+        config.set_item("nms_threshold", self.nms_threshold)?;
+        config.set_item("max_candidates", self.max_candidates)?;
+        config.set_item("max_detections", self.max_detections)?;
+        Ok(config)
+    }
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn ssd(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PySsdPostProcessor>()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array3;
+
+    use super::*;
+    use crate::common::ssd_postprocess::PriorConvention;
+
+    #[test]
+    fn unittest_ssd_layouts() {
+        // 2 priors at each location of a 2x2 feature map, and a background and 2 classes
+        let priors = PriorConfig {
+            feature_maps: vec![2],
+            scales: vec![0.5, 0.5],
+            aspect_ratios: vec![vec![]],
+            convention: PriorConvention::Caffe,
+            clip: false,
+        };
+        for layout in [HeadLayout::PriorMajor, HeadLayout::PriorMinor] {
+            for activation in [ScoreActivation::Sigmoid, ScoreActivation::Softmax] {
+                let postprocessor = SsdPostprocessor::new(SsdConfig {
+                    num_classes: 3,
+                    priors: priors.clone(),
+                    variances: [0.1, 0.2],
+                    activation,
+                    layout,
+                    score_threshold: 0.5,
+                    nms_threshold: 0.5,
+                    max_candidates: None,
+                    max_detections: None,
+                })
+                .unwrap();
+                // Only prior 1 of location (y, x) = (1, 0) is confident, of class 2, and is
+                // shifted right by 0.1 of its width
+                let mut boxes = Array3::zeros((8, 2, 2));
+                let mut scores = Array3::from_elem((6, 2, 2), -10f32);
+                let (dx, score) = match layout {
+                    HeadLayout::PriorMajor => (5, 3 + 2),
+                    HeadLayout::PriorMinor => (1, 2 * 2 + 1),
+                };
+                boxes[(dx, 1, 0)] = 1.0;
+                scores[(score, 1, 0)] = 10.0;
+
+                let results =
+                    postprocessor.eval(&[boxes.view()], &[scores.view()], NmsMethod::Hard).unwrap();
+                assert_eq!(results.len(), 1, "{layout:?} {activation:?}");
+                let result = results[0];
+                assert_eq!(result.class, 2.0);
+                assert!(result.score > 0.99);
+                assert_eq!(result.bbox.py1, 0.75 - 0.25);
+                assert!((result.bbox.px1 - (0.25 - 0.25 + 0.05)).abs() < 1e-6);
+
+                let error = postprocessor.eval(&[boxes.view()], &[boxes.view()], NmsMethod::Hard);
+                assert!(error.is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(feature = "python")]
+use numpy::{PyArray2, PyReadonlyArray2};
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
+#[cfg(feature = "python")]
+use crate::common::threads;
+#[cfg(feature = "python")]
+use crate::common::{extract_detections, wbf, PyDetectionResults};
+use crate::common::{suppress_detections, Error, NmsMethod, Result};
+#[cfg(feature = "python")]
+use crate::yolo::DEFAULT_MAX_DET;
+
+pub const DEFAULT_OVERLAP: f32 = 0.2;
+
+/// (x, y) offsets of the tiles of `tile_width` by `tile_height` covering an image, adjacent
+/// tiles overlapping by `overlap` of their size
+///
+/// Tiles are laid out row by row, the last ones of a row or column being shifted back to end
+/// at the border of the image rather than crossing it.
+pub fn tile_offsets(
+    image_width: usize,
+    image_height: usize,
+    tile_width: usize,
+    tile_height: usize,
+    overlap: f32,
+) -> Result<Vec<(usize, usize)>> {
+    if tile_width == 0 || tile_height == 0 {
+        return Err(Error::invalid("tiles must have a positive width and height"));
+    }
+    if !(0.0..1.0).contains(&overlap) {
+        return Err(Error::invalid(format!("overlap must be in [0, 1) but got {overlap}")));
+    }
+    let starts = |size: usize, tile: usize| {
+        let step = ((tile as f32 * (1.0 - overlap)) as usize).max(1);
+        let last = size.saturating_sub(tile);
+        let mut starts = (0..last).step_by(step).collect::<Vec<_>>();
+        starts.push(last);
+        starts
+    };
+    let (xs, ys) = (starts(image_width, tile_width), starts(image_height, tile_height));
+    Ok(ys.iter().flat_map(|&y| xs.iter().map(move |&x| (x, y))).collect())
+}
+
+/// Stitch the detections of the tiles of an image, translating them by the (x, y) offset of
+/// their tile, and suppress the duplicates of objects spanning several tiles class by class
+pub fn stitch(
+    tiles: &[DetectionResults],
+    offsets: &[(f32, f32)],
+    method: NmsMethod,
+    iou_threshold: f32,
+    max_det: usize,
+) -> Result<DetectionResults> {
+    if tiles.len() != offsets.len() {
+        return Err(Error::invalid(format!(
+            "expected an offset for each of the {} tiles but got {}",
+            tiles.len(),
+            offsets.len()
+        )));
+    }
+    let detections = tiles
+        .iter()
+        .zip(offsets)
+        .flat_map(|(tile, &(x, y))| {
+            tile.iter().map(move |r| DetectionResult {
+                bbox: BoundingBox {
+                    px1: r.bbox.px1 + x,
+                    py1: r.bbox.py1 + y,
+                    px2: r.bbox.px2 + x,
+                    py2: r.bbox.py2 + y,
+                },
+                ..*r
+            })
+        })
+        .collect::<Vec<_>>()
+        .into();
+    Ok(suppress_detections(&detections, method, iou_threshold, 0.0, max_det))
+}
+
+/// (x, y) offsets of the tiles covering an image, row by row
+///
+/// Args:
+///     image_width (int): Width of the image
+///     image_height (int): Height of the image
+///     tile_width (int): Width of a tile
+///     tile_height (int): Height of a tile
+///     overlap (Optional[float]): Overlap of adjacent tiles as a ratio of their size, default
+///         is 0.2
+///
+/// Returns:
+///     List[Tuple[int, int]]: Offset of each tile, the last tiles of a row or column ending at
+///         the border of the image
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "tile_offsets")]
+fn tile_offsets_py(
+    image_width: usize,
+    image_height: usize,
+    tile_width: usize,
+    tile_height: usize,
+    overlap: Option<f32>,
+) -> PyResult<Vec<(usize, usize)>> {
+    Ok(tile_offsets(
+        image_width,
+        image_height,
+        tile_width,
+        tile_height,
+        overlap.unwrap_or(DEFAULT_OVERLAP),
+    )?)
+}
+
+/// Stitch the detections of the tiles of an image into global coordinates and suppress the
+/// duplicates of objects spanning several tiles
+///
+/// Args:
+///     tiles (Sequence[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Detections of each
+///         tile in its own coordinates, either arrays of shape (N, 6) with rows
+///         (x1, y1, x2, y2, score, class) or lists of PyDetectionResult
+///     offsets (Sequence[Tuple[float, float]]): (x, y) offset of each tile in the image
+///     iou_threshold (float): IoU threshold
+///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+///         for class-wise Weighted Box Fusion, default is "hard"
+///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+///     max_det (Optional[int]): Maximum number of detections, default is 300
+///
+/// Returns:
+///     Union[numpy.ndarray, PyDetectionResults]: Detections of the image in the same format as
+///         the inputs, in descending score order
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "stitch")]
+fn stitch_py(
+    py: Python<'_>,
+    tiles: Vec<&PyAny>,
+    offsets: Vec<(f32, f32)>,
+    iou_threshold: f32,
+    nms_method: Option<&str>,
+    sigma: Option<f32>,
+    max_det: Option<usize>,
+) -> PyResult<PyObject> {
+    let method = NmsMethod::parse(nms_method, sigma)?;
+    if max_det == Some(0) {
+        return Err(PyValueError::new_err("max_det must be positive"));
+    }
+    let as_arrays = tiles.first().is_some_and(|d| d.extract::<PyReadonlyArray2<'_, f32>>().is_ok());
+    let tiles =
+        tiles.into_iter().map(extract_detections).collect::<PyResult<Vec<DetectionResults>>>()?;
+
+    let stitched = threads::allow_threads(py, || {
+        stitch(&tiles, &offsets, method, iou_threshold, max_det.unwrap_or(DEFAULT_MAX_DET))
+    })?;
+    Ok(if as_arrays {
+        PyArray2::from_owned_array(py, wbf::to_rows(&stitched)).to_object(py)
+    } else {
+        PyDetectionResults::from(stitched).into_py(py)
+    })
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn tiling(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(tile_offsets_py, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unittest_tile_offsets() {
+        let offsets = tile_offsets(1000, 600, 512, 512, 0.2).unwrap();
+        assert_eq!(offsets, [(0, 0), (409, 0), (488, 0), (0, 88), (409, 88), (488, 88)]);
+        // An image smaller than a tile is a single tile
+        assert_eq!(tile_offsets(300, 200, 512, 512, 0.2).unwrap(), [(0, 0)]);
+    }
+
+    #[test]
+    fn unittest_stitch() {
+        let detection = |x1: f32, x2: f32, score: f32, class: f32| DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: x1, py1: 10.0, px2: x2, py2: 50.0 },
+            score,
+            class,
+        };
+        // An object across the border of two tiles, and another class at the same place
+        let left = vec![detection(400.0, 500.0, 0.9, 0.0), detection(400.0, 500.0, 0.8, 1.0)];
+        let right = vec![detection(-8.0, 92.0, 0.7, 0.0)];
+        let stitched = stitch(
+            &[left.into(), right.into()],
+            &[(0.0, 0.0), (409.0, 0.0)],
+            NmsMethod::Hard,
+            0.5,
+            300,
+        )
+        .unwrap();
+        assert_eq!(stitched.len(), 2);
+        assert_eq!([stitched[0].class, stitched[1].class], [0.0, 1.0]);
+
+        assert!(stitch(&[DetectionResults::default()], &[], NmsMethod::Hard, 0.5, 1).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use ndarray::{Array2, ArrayView2};
+
+/// Minimum cost assignment of the rows of `cost` to its columns by the Hungarian algorithm
+///
+/// Every row is assigned a distinct column if there are at least as many columns as rows, and
+/// the other way around otherwise. Returns the (row, column) pairs in ascending row order.
+pub(crate) fn linear_assignment(cost: ArrayView2<'_, f64>) -> Vec<(usize, usize)> {
+    let (rows, cols) = cost.dim();
+    if rows > cols {
+        let mut pairs: Vec<_> =
+            linear_assignment(cost.t()).into_iter().map(|(c, r)| (r, c)).collect();
+        pairs.sort_unstable();
+        return pairs;
+    }
+
+    // Shortest augmenting paths with potentials, rows and columns being 1-indexed so that the
+    // column 0 is a virtual one holding the row being inserted
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut matched = vec![0; cols + 1];
+    let mut way = vec![0; cols + 1];
+    for row in 1..=rows {
+        matched[0] = row;
+        let mut col0 = 0;
+        let mut min = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+        loop {
+            used[col0] = true;
+            let row0 = matched[col0];
+            let (mut delta, mut col1) = (f64::INFINITY, 0);
+            for col in (1..=cols).filter(|&col| !used[col]) {
+                let reduced = cost[[row0 - 1, col - 1]] - u[row0] - v[col];
+                if reduced < min[col] {
+                    min[col] = reduced;
+                    way[col] = col0;
+                }
+                if min[col] < delta {
+                    delta = min[col];
+                    col1 = col;
+                }
+            }
+            for col in 0..=cols {
+                if used[col] {
+                    u[matched[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min[col] -= delta;
+                }
+            }
+            col0 = col1;
+            if matched[col0] == 0 {
+                break;
+            }
+        }
+        while col0 != 0 {
+            let col1 = way[col0];
+            matched[col0] = matched[col1];
+            col0 = col1;
+        }
+    }
+
+    let mut pairs: Vec<_> = (1..=cols)
+        .filter(|&col| matched[col] != 0)
+        .map(|col| (matched[col] - 1, col - 1))
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Matches of detections to tracks overlapping by at least `iou_threshold`, maximizing the
+/// total IoU, along with the unmatched detections and tracks
+pub(crate) fn associate(
+    iou: &Array2<f32>,
+    iou_threshold: f32,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    let (num_detections, num_tracks) = iou.dim();
+    let matches = if num_detections == 0 || num_tracks == 0 {
+        Vec::new()
+    } else {
+        let cost = iou.mapv(|iou| -(iou.max(0.0) as f64));
+        linear_assignment(cost.view())
+            .into_iter()
+            .filter(|&(d, t)| iou[(d, t)] >= iou_threshold)
+            .collect()
+    };
+    let mut detection_matched = vec![false; num_detections];
+    let mut track_matched = vec![false; num_tracks];
+    for &(d, t) in &matches {
+        detection_matched[d] = true;
+        track_matched[t] = true;
+    }
+    let unmatched = |matched: Vec<bool>| {
+        matched.into_iter().enumerate().filter(|(_, m)| !m).map(|(i, _)| i).collect()
+    };
+    (matches, unmatched(detection_matched), unmatched(track_matched))
+}
@@ -0,0 +1,187 @@
+use ndarray::Array2;
+
+use super::assignment::associate;
+use super::kalman::KalmanBoxFilter;
+use super::TrackedDetection;
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
+use crate::common::{Error, Result};
+
+pub const DEFAULT_TRACK_THRESHOLD: f32 = 0.5;
+pub const DEFAULT_LOW_THRESHOLD: f32 = 0.1;
+pub const DEFAULT_MATCH_IOU: f32 = 0.2;
+pub const DEFAULT_TRACK_BUFFER: usize = 30;
+
+/// Minimum IoU of the second association, of the low scoring detections
+const LOW_MATCH_IOU: f32 = 0.5;
+/// Minimum IoU of a detection with a track started in the previous frame to confirm it
+const UNCONFIRMED_MATCH_IOU: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Matched in the latest frame
+    Tracked,
+    /// Unmatched since `last_frame`, kept for `track_buffer` frames to be matched again
+    Lost,
+}
+
+#[derive(Debug, Clone)]
+struct Track {
+    id: u64,
+    filter: KalmanBoxFilter,
+    detection: DetectionResult,
+    state: State,
+    /// Whether the track was matched again after the frame it started in
+    confirmed: bool,
+    last_frame: usize,
+}
+
+/// ByteTrack by Zhang et al., associating every detection box instead of the high scoring ones
+/// only
+///
+/// High scoring detections are first matched to the tracked and lost tracks. The low scoring
+/// ones, mostly occluded objects, are then matched to the tracks still unmatched, recovering
+/// them instead of starting new tracks. New tracks are confirmed by a match in the next frame.
+#[derive(Debug, Clone)]
+pub struct ByteTrack {
+    /// Minimum score of a high scoring detection
+    pub track_threshold: f32,
+    /// Minimum score of a low scoring detection, lower ones being ignored
+    pub low_threshold: f32,
+    /// Minimum score of a detection starting a new track
+    pub new_track_threshold: f32,
+    /// Minimum IoU of a high scoring detection with the predicted box of a track to match it
+    pub match_iou: f32,
+    /// Number of frames a lost track is kept
+    pub track_buffer: usize,
+    tracks: Vec<Track>,
+    frame: usize,
+    next_id: u64,
+}
+
+impl ByteTrack {
+    pub fn new(
+        track_threshold: Option<f32>,
+        low_threshold: Option<f32>,
+        new_track_threshold: Option<f32>,
+        match_iou: Option<f32>,
+        track_buffer: Option<usize>,
+    ) -> Result<Self> {
+        let track_threshold = track_threshold.unwrap_or(DEFAULT_TRACK_THRESHOLD);
+        let low_threshold = low_threshold.unwrap_or(DEFAULT_LOW_THRESHOLD);
+        if low_threshold > track_threshold {
+            return Err(Error::invalid(format!(
+                "low_threshold {low_threshold} must not exceed track_threshold {track_threshold}"
+            )));
+        }
+        let match_iou = match_iou.unwrap_or(DEFAULT_MATCH_IOU);
+        if !(0.0..=1.0).contains(&match_iou) {
+            return Err(Error::invalid(format!("match_iou must be in [0, 1] but got {match_iou}")));
+        }
+        Ok(Self {
+            track_threshold,
+            low_threshold,
+            new_track_threshold: new_track_threshold.unwrap_or(track_threshold + 0.1),
+            match_iou,
+            track_buffer: track_buffer.unwrap_or(DEFAULT_TRACK_BUFFER),
+            tracks: Vec::new(),
+            frame: 0,
+            next_id: 1,
+        })
+    }
+
+    /// Forget every track, as at the start of another video, restarting the ids from 1
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+        self.frame = 0;
+        self.next_id = 1;
+    }
+
+    /// Advance by a frame of `detections`, of both high and low scores, returning the
+    /// confirmed tracks matched in this frame
+    pub fn update(&mut self, detections: &DetectionResults) -> Vec<TrackedDetection> {
+        self.frame += 1;
+        let predicted: Vec<BoundingBox> =
+            self.tracks.iter_mut().map(|track| track.filter.predict()).collect();
+
+        let (high, low): (Vec<usize>, Vec<usize>) = (0..detections.len())
+            .filter(|&d| detections[d].score > self.low_threshold)
+            .partition(|&d| detections[d].score >= self.track_threshold);
+        let confirmed = (0..self.tracks.len()).filter(|&t| self.tracks[t].confirmed).collect();
+        let unconfirmed = (0..self.tracks.len()).filter(|&t| !self.tracks[t].confirmed).collect();
+
+        // High scoring detections with the tracked and lost tracks
+        let (high, confirmed) =
+            self.associate(detections, &predicted, high, confirmed, self.match_iou);
+        // Low scoring detections with the tracks matched in the previous frame
+        let (tracked, lost): (Vec<usize>, Vec<usize>) =
+            confirmed.into_iter().partition(|&t| self.tracks[t].state == State::Tracked);
+        let (_, unmatched) = self.associate(detections, &predicted, low, tracked, LOW_MATCH_IOU);
+        for t in unmatched.into_iter().chain(lost) {
+            self.tracks[t].state = State::Lost;
+        }
+        // Remaining high scoring detections with the tracks started in the previous frame
+        let (high, unconfirmed) =
+            self.associate(detections, &predicted, high, unconfirmed, UNCONFIRMED_MATCH_IOU);
+
+        let frame = self.frame;
+        let track_buffer = self.track_buffer;
+        let mut index = 0;
+        self.tracks.retain(|track| {
+            let removed = unconfirmed.contains(&index)
+                || (track.state == State::Lost && frame - track.last_frame > track_buffer);
+            index += 1;
+            !removed
+        });
+        for d in high.into_iter().filter(|&d| detections[d].score >= self.new_track_threshold) {
+            self.tracks.push(Track {
+                id: self.next_id,
+                filter: KalmanBoxFilter::new(&detections[d].bbox),
+                detection: detections[d],
+                state: State::Tracked,
+                // Tracks of the first frame have no earlier frame to be confirmed by
+                confirmed: frame == 1,
+                last_frame: frame,
+            });
+            self.next_id += 1;
+        }
+
+        self.tracks
+            .iter()
+            .filter(|track| track.confirmed && track.last_frame == frame)
+            .map(|track| TrackedDetection {
+                track_id: track.id,
+                detection: DetectionResult { bbox: track.filter.bbox(), ..track.detection },
+            })
+            .collect()
+    }
+
+    /// Match the `detections` to the `tracks` of the given indices, updating the matched tracks
+    /// and returning the unmatched detections and tracks
+    fn associate(
+        &mut self,
+        detections: &DetectionResults,
+        predicted: &[BoundingBox],
+        detection_indices: Vec<usize>,
+        track_indices: Vec<usize>,
+        min_iou: f32,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let iou =
+            Array2::from_shape_fn((detection_indices.len(), track_indices.len()), |(d, t)| {
+                detections[detection_indices[d]].bbox.iou(&predicted[track_indices[t]])
+            });
+        let (matches, unmatched_detections, unmatched_tracks) = associate(&iou, min_iou);
+        for (d, t) in matches {
+            let detection = detections[detection_indices[d]];
+            let track = &mut self.tracks[track_indices[t]];
+            track.filter.update(&detection.bbox);
+            track.detection = detection;
+            track.state = State::Tracked;
+            track.confirmed = true;
+            track.last_frame = self.frame;
+        }
+        (
+            unmatched_detections.into_iter().map(|d| detection_indices[d]).collect(),
+            unmatched_tracks.into_iter().map(|t| track_indices[t]).collect(),
+        )
+    }
+}
@@ -0,0 +1,149 @@
+use crate::common::ssd_postprocess::BoundingBox;
+
+/// Dimension of the state, (cx, cy, area, aspect ratio) and the velocities of the first three
+const STATE: usize = 7;
+/// Dimension of the measurement, (cx, cy, area, aspect ratio)
+const MEASUREMENT: usize = 4;
+
+/// Constant velocity Kalman filter of a box, as in SORT by Bewley et al.
+///
+/// The aspect ratio is assumed constant, so it has no velocity. The observation matrix selects
+/// the first four components of the state, which the update exploits instead of multiplying by
+/// it.
+#[derive(Debug, Clone)]
+pub(crate) struct KalmanBoxFilter {
+    x: [f64; STATE],
+    p: [[f64; STATE]; STATE],
+}
+
+impl KalmanBoxFilter {
+    /// Measurement noise of each component
+    const R: [f64; MEASUREMENT] = [1.0, 1.0, 10.0, 10.0];
+    /// Process noise of each component
+    const Q: [f64; STATE] = [1.0, 1.0, 1.0, 1.0, 0.01, 0.01, 0.0001];
+
+    pub fn new(bbox: &BoundingBox) -> Self {
+        let mut x = [0.0; STATE];
+        x[..MEASUREMENT].copy_from_slice(&measurement(bbox));
+        let mut p = [[0.0; STATE]; STATE];
+        for (i, row) in p.iter_mut().enumerate() {
+            // The velocities are unobserved, hence highly uncertain
+            row[i] = if i < MEASUREMENT { 10.0 } else { 10000.0 };
+        }
+        Self { x, p }
+    }
+
+    /// Advance the state by a frame, returning the predicted box
+    pub fn predict(&mut self) -> BoundingBox {
+        // The area would become negative
+        if self.x[6] + self.x[2] <= 0.0 {
+            self.x[6] = 0.0;
+        }
+        for i in 0..3 {
+            self.x[i] += self.x[i + 4];
+        }
+        // P = F P F^T + Q, where F adds the velocity i + 4 to the component i < 3
+        let f = |p: &[[f64; STATE]; STATE], i: usize, j: usize| {
+            if i < 3 {
+                p[i][j] + p[i + 4][j]
+            } else {
+                p[i][j]
+            }
+        };
+        let mut fp = [[0.0; STATE]; STATE];
+        for (i, row) in fp.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = f(&self.p, i, j);
+            }
+        }
+        for (i, (row, fp)) in self.p.iter_mut().zip(&fp).enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = if j < 3 { fp[j] + fp[j + 4] } else { fp[j] };
+            }
+            row[i] += Self::Q[i];
+        }
+        self.bbox()
+    }
+
+    /// Correct the state with an observed box
+    pub fn update(&mut self, bbox: &BoundingBox) {
+        let z = measurement(bbox);
+        // S = H P H^T + R
+        let mut s = [[0.0; MEASUREMENT]; MEASUREMENT];
+        for (i, row) in s.iter_mut().enumerate() {
+            row.copy_from_slice(&self.p[i][..MEASUREMENT]);
+            row[i] += Self::R[i];
+        }
+        let Some(s_inv) = invert(s) else { return };
+        // K = P H^T S^-1
+        let mut k = [[0.0; MEASUREMENT]; STATE];
+        for (i, row) in k.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..MEASUREMENT).map(|l| self.p[i][l] * s_inv[l][j]).sum();
+            }
+        }
+        let y: [f64; MEASUREMENT] = std::array::from_fn(|i| z[i] - self.x[i]);
+        for (x, row) in self.x.iter_mut().zip(&k) {
+            *x += row.iter().zip(&y).map(|(k, y)| k * y).sum::<f64>();
+        }
+        // P = (I - K H) P
+        let p = self.p;
+        for (row, k) in self.p.iter_mut().zip(&k) {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v -= k.iter().zip(&p).map(|(k, p)| k * p[j]).sum::<f64>();
+            }
+        }
+    }
+
+    /// Box of the current state
+    pub fn bbox(&self) -> BoundingBox {
+        let [cx, cy, area, ratio, ..] = self.x;
+        let w = (area * ratio).max(0.0).sqrt();
+        let h = if w > 0.0 { area / w } else { 0.0 };
+        BoundingBox {
+            px1: (cx - w / 2.0) as f32,
+            py1: (cy - h / 2.0) as f32,
+            px2: (cx + w / 2.0) as f32,
+            py2: (cy + h / 2.0) as f32,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.x.iter().all(|v| v.is_finite())
+    }
+}
+
+/// (cx, cy, area, aspect ratio) of a box
+fn measurement(bbox: &BoundingBox) -> [f64; MEASUREMENT] {
+    let (w, h) = (bbox.pw() as f64, bbox.ph() as f64);
+    [bbox.pcx() as f64, bbox.pcy() as f64, w * h, if h > 0.0 { w / h } else { 0.0 }]
+}
+
+/// Inverse by Gauss-Jordan elimination with partial pivoting, None if singular
+fn invert<const N: usize>(mut a: [[f64; N]; N]) -> Option<[[f64; N]; N]> {
+    let mut inv = [[0.0; N]; N];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let d = a[col][col];
+        for j in 0..N {
+            a[col][j] /= d;
+            inv[col][j] /= d;
+        }
+        for i in (0..N).filter(|&i| i != col) {
+            let factor = a[i][col];
+            for j in 0..N {
+                a[i][j] -= factor * a[col][j];
+                inv[i][j] -= factor * inv[col][j];
+            }
+        }
+    }
+    Some(inv)
+}
@@ -0,0 +1,349 @@
+mod assignment;
+mod byte_track;
+mod kalman;
+mod smoothing;
+mod sort;
+
+#[cfg(feature = "python")]
+use ndarray::Array2;
+#[cfg(feature = "python")]
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+pub use self::byte_track::ByteTrack;
+pub use self::smoothing::BoxSmoother;
+pub use self::sort::Sort;
+#[cfg(feature = "python")]
+use crate::common::extract_detections;
+#[cfg(feature = "python")]
+use crate::common::ssd_postprocess::BoundingBox;
+use crate::common::ssd_postprocess::DetectionResult;
+#[cfg(feature = "python")]
+use crate::common::threads;
+
+/// Detection assigned to a track, with the box estimated by the track
+#[derive(Debug, Clone, Copy)]
+pub struct TrackedDetection {
+    /// Id of the track, unique within a tracker and starting from 1
+    pub track_id: u64,
+    pub detection: DetectionResult,
+}
+
+/// Rows of (x1, y1, x2, y2, score, class, track_id) of tracked detections
+#[cfg(feature = "python")]
+fn tracked_rows(tracked: &[TrackedDetection]) -> Array2<f32> {
+    Array2::from_shape_fn((tracked.len(), 7), |(i, j)| {
+        let TrackedDetection { track_id, detection: r } = tracked[i];
+        [r.bbox.px1, r.bbox.py1, r.bbox.px2, r.bbox.py2, r.score, r.class, track_id as f32][j]
+    })
+}
+
+/// SORT multi-object tracker, fed the detections of the frames of a video in order
+///
+/// Args:
+///     max_age (Optional[int]): Number of frames a track survives without a matching
+///         detection, default is 1
+///     min_hits (Optional[int]): Number of consecutive matches before a track is reported,
+///         default is 3
+///     iou_threshold (Optional[float]): Minimum IoU of a detection with the predicted box of a
+///         track to match it, default is 0.3
+#[cfg(feature = "python")]
+#[pyclass(name = "Sort", module = "furiosa_native_postprocess.tracking")]
+pub struct PySort(Sort);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PySort {
+    #[new]
+    fn new(
+        max_age: Option<usize>,
+        min_hits: Option<usize>,
+        iou_threshold: Option<f32>,
+    ) -> PyResult<Self> {
+        Ok(Self(Sort::new(max_age, min_hits, iou_threshold)?))
+    }
+
+    /// Advance by a frame
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+    ///         frame, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+    ///         a list of PyDetectionResult
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Tracked detections of the frame of shape (M, 7) with rows
+    ///         (x1, y1, x2, y2, score, class, track_id), the boxes being estimated by the tracks
+    fn update(&mut self, py: Python<'_>, detections: &PyAny) -> PyResult<Py<PyArray2<f32>>> {
+        let detections = extract_detections(detections)?;
+        let tracked = threads::allow_threads(py, || self.0.update(&detections));
+        Ok(tracked_rows(&tracked).into_pyarray(py).to_owned())
+    }
+
+    /// Forget every track, as at the start of another video
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// ByteTrack multi-object tracker, fed the detections of the frames of a video in order,
+/// including the low scoring ones
+///
+/// Args:
+///     track_threshold (Optional[float]): Minimum score of a high scoring detection, matched
+///         first, default is 0.5
+///     low_threshold (Optional[float]): Minimum score of a low scoring detection, matched to the
+///         tracks left unmatched by the high scoring ones, default is 0.1
+///     new_track_threshold (Optional[float]): Minimum score of a detection starting a new track,
+///         default is `track_threshold + 0.1`
+///     match_iou (Optional[float]): Minimum IoU of a high scoring detection with the predicted
+///         box of a track to match it, default is 0.2
+///     track_buffer (Optional[int]): Number of frames a lost track is kept, default is 30
+#[cfg(feature = "python")]
+#[pyclass(name = "ByteTrack", module = "furiosa_native_postprocess.tracking")]
+pub struct PyByteTrack(ByteTrack);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyByteTrack {
+    #[new]
+    fn new(
+        track_threshold: Option<f32>,
+        low_threshold: Option<f32>,
+        new_track_threshold: Option<f32>,
+        match_iou: Option<f32>,
+        track_buffer: Option<usize>,
+    ) -> PyResult<Self> {
+        Ok(Self(ByteTrack::new(
+            track_threshold,
+            low_threshold,
+            new_track_threshold,
+            match_iou,
+            track_buffer,
+        )?))
+    }
+
+    /// Advance by a frame
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+    ///         frame, either an array of shape (N, 6) with rows (x1, y1, x2, y2, score, class) or
+    ///         a list of PyDetectionResult, decoded with a confidence threshold as low as
+    ///         `low_threshold`
+    ///     low_detections (Optional[Union[numpy.ndarray, Sequence[PyDetectionResult]]]): Low
+    ///         scoring detections returned apart, e.g. by the `low_conf_threshold` of
+    ///         `eval_detailed`
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Tracked detections of the frame of shape (M, 7) with rows
+    ///         (x1, y1, x2, y2, score, class, track_id), the boxes being estimated by the tracks
+    fn update(
+        &mut self,
+        py: Python<'_>,
+        detections: &PyAny,
+        low_detections: Option<&PyAny>,
+    ) -> PyResult<Py<PyArray2<f32>>> {
+        let mut detections = extract_detections(detections)?;
+        if let Some(low_detections) = low_detections {
+            detections.extend(extract_detections(low_detections)?.iter());
+        }
+        let tracked = threads::allow_threads(py, || self.0.update(&detections));
+        Ok(tracked_rows(&tracked).into_pyarray(py).to_owned())
+    }
+
+    /// Forget every track, as at the start of another video
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Exponential moving average of the boxes of consecutive frames, reducing the jitter of video
+/// overlays
+///
+/// Args:
+///     alpha (Optional[float]): Weight of the box of the current frame, default is 0.5
+///     iou_threshold (Optional[float]): Minimum IoU of an untracked box with a smoothed box of
+///         the same class to follow it, default is 0.3
+///     max_age (Optional[int]): Number of frames a smoothed box is kept without a box following
+///         it, default is 1
+#[cfg(feature = "python")]
+#[pyclass(name = "BoxSmoother", module = "furiosa_native_postprocess.tracking")]
+pub struct PyBoxSmoother(BoxSmoother);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyBoxSmoother {
+    #[new]
+    fn new(
+        alpha: Option<f32>,
+        iou_threshold: Option<f32>,
+        max_age: Option<usize>,
+    ) -> PyResult<Self> {
+        Ok(Self(BoxSmoother::new(alpha, iou_threshold, max_age)?))
+    }
+
+    /// Smooth the boxes of a frame
+    ///
+    /// Args:
+    ///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections of the
+    ///         frame, either tracked ones of shape (N, 7) as returned by the trackers, followed
+    ///         by track id, or untracked ones of shape (N, 6) with rows
+    ///         (x1, y1, x2, y2, score, class) or a list of PyDetectionResult, followed by IoU
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Detections of the same shape with smoothed boxes
+    fn update(&mut self, py: Python<'_>, detections: &PyAny) -> PyResult<Py<PyArray2<f32>>> {
+        if let Ok(array) = detections.extract::<PyReadonlyArray2<'_, f32>>() {
+            let array = array.as_array();
+            if array.shape()[1] == 7 {
+                let tracked = array
+                    .outer_iter()
+                    .map(|row| TrackedDetection {
+                        track_id: row[6] as u64,
+                        detection: DetectionResult {
+                            index: 0.0,
+                            bbox: BoundingBox {
+                                px1: row[0],
+                                py1: row[1],
+                                px2: row[2],
+                                py2: row[3],
+                            },
+                            score: row[4],
+                            class: row[5],
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                let smoothed = threads::allow_threads(py, || self.0.smooth_tracked(&tracked));
+                return Ok(tracked_rows(&smoothed).into_pyarray(py).to_owned());
+            }
+        }
+        let detections = extract_detections(detections)?;
+        let smoothed = threads::allow_threads(py, || self.0.smooth(&detections));
+        Ok(smoothed.into_array().into_pyarray(py).to_owned())
+    }
+
+    /// Forget every smoothed box, as at the start of another video
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn tracking(m: &PyModule) -> PyResult<()> {
+    m.add_class::<PySort>()?;
+    m.add_class::<PyByteTrack>()?;
+    m.add_class::<PyBoxSmoother>()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use super::assignment::linear_assignment;
+    use super::*;
+    use crate::common::ssd_postprocess::{BoundingBox, DetectionResults};
+
+    #[test]
+    fn unittest_linear_assignment() {
+        let cost = arr2(&[[4.0, 1.0, 3.0], [2.0, 0.0, 5.0], [3.0, 2.0, 2.0]]);
+        assert_eq!(linear_assignment(cost.view()), [(0, 1), (1, 0), (2, 2)]);
+        // More rows than columns leaves the costliest rows out
+        let cost = arr2(&[[1.0, 9.0], [9.0, 9.0], [9.0, 1.0]]);
+        assert_eq!(linear_assignment(cost.view()), [(0, 0), (2, 1)]);
+    }
+
+    fn frame(boxes: &[[f32; 4]]) -> DetectionResults {
+        boxes
+            .iter()
+            .map(|&[px1, py1, px2, py2]| DetectionResult {
+                index: 0.0,
+                bbox: BoundingBox { px1, py1, px2, py2 },
+                score: 0.9,
+                class: 0.0,
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    #[test]
+    fn unittest_sort() {
+        let mut sort = Sort::new(None, None, None).unwrap();
+        // Two objects moving apart, listed in either order
+        let mut ids = Vec::new();
+        for t in 0..10 {
+            let t = t as f32;
+            let a = [10.0 + 5.0 * t, 10.0, 50.0 + 5.0 * t, 50.0];
+            let b = [200.0 - 5.0 * t, 100.0, 240.0 - 5.0 * t, 140.0];
+            let detections = if t as usize % 2 == 0 { frame(&[a, b]) } else { frame(&[b, a]) };
+            let tracked = sort.update(&detections);
+            assert_eq!(tracked.len(), 2);
+            let mut frame_ids: Vec<_> = tracked
+                .iter()
+                .map(|tracked| (tracked.detection.bbox.px1 > 120.0, tracked.track_id))
+                .collect();
+            frame_ids.sort_unstable();
+            ids.push(frame_ids);
+            let a_box = &tracked.iter().find(|r| r.detection.bbox.px1 < 120.0).unwrap().detection;
+            assert!((a_box.bbox.px1 - a[0]).abs() < 2.0, "{t}: {:?} != {a:?}", a_box.bbox);
+        }
+        assert!(ids.iter().all(|frame_ids| frame_ids == &ids[0]), "{ids:?}");
+
+        // A lost object is dropped after max_age frames and comes back with a new id
+        sort.update(&frame(&[]));
+        sort.update(&frame(&[]));
+        let tracked = sort.update(&frame(&[[10.0, 10.0, 50.0, 50.0]]));
+        assert!(tracked.is_empty(), "reported before min_hits: {tracked:?}");
+        sort.reset();
+        let tracked = sort.update(&frame(&[[10.0, 10.0, 50.0, 50.0]]));
+        assert_eq!(tracked[0].track_id, 1);
+    }
+
+    #[test]
+    fn unittest_byte_track() {
+        let mut byte_track = ByteTrack::new(None, None, None, None, None).unwrap();
+        let mut ids = Vec::new();
+        for t in 0..12 {
+            let x = 10.0 + 5.0 * t as f32;
+            let mut detections = frame(&[[x, 10.0, x + 40.0, 50.0]]);
+            // Occluded for a few frames, the object is detected with a low score only
+            if (4..8).contains(&t) {
+                detections[0].score = 0.3;
+            }
+            // Low scoring noise away from the object never starts a track
+            detections
+                .push(DetectionResult { score: 0.3, ..frame(&[[300.0, 300.0, 340.0, 340.0]])[0] });
+            let tracked = byte_track.update(&detections);
+            assert_eq!(tracked.len(), 1, "frame {t}: {tracked:?}");
+            ids.push(tracked[0].track_id);
+        }
+        assert!(ids.iter().all(|&id| id == 1), "{ids:?}");
+
+        // A new track is only reported once confirmed by the next frame
+        let far = frame(&[[500.0, 500.0, 540.0, 540.0]]);
+        assert!(byte_track.update(&far).is_empty());
+        assert_eq!(byte_track.update(&far)[0].track_id, 2);
+    }
+
+    #[test]
+    fn unittest_box_smoother() {
+        let mut smoother = BoxSmoother::new(Some(0.5), None, None).unwrap();
+        let first = smoother.smooth(&frame(&[[0.0, 0.0, 40.0, 40.0], [100.0, 0.0, 140.0, 40.0]]));
+        assert_eq!(first[0].bbox.px1, 0.0);
+        // Boxes jittering by 4 pixels move by half of it, whatever their order
+        let second = smoother.smooth(&frame(&[[104.0, 0.0, 144.0, 40.0], [4.0, 0.0, 44.0, 40.0]]));
+        assert_eq!([second[0].bbox.px1, second[1].bbox.px1], [102.0, 2.0]);
+        // Boxes of another class are not followed
+        let mut other = frame(&[[4.0, 0.0, 44.0, 40.0]]);
+        other[0].class = 1.0;
+        assert_eq!(smoother.smooth(&other)[0].bbox.px1, 4.0);
+
+        let tracked = |x: f32| {
+            [TrackedDetection { track_id: 7, detection: frame(&[[x, 0.0, x + 40.0, 40.0]])[0] }]
+        };
+        smoother.reset();
+        smoother.smooth_tracked(&tracked(0.0));
+        assert_eq!(smoother.smooth_tracked(&tracked(100.0))[0].detection.bbox.px1, 50.0);
+    }
+}
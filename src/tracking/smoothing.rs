@@ -0,0 +1,139 @@
+use ndarray::Array2;
+
+use super::assignment::associate;
+use super::TrackedDetection;
+use crate::common::ssd_postprocess::{BoundingBox, DetectionResult, DetectionResults};
+use crate::common::{Error, Result};
+
+pub const DEFAULT_ALPHA: f32 = 0.5;
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.3;
+pub const DEFAULT_MAX_AGE: usize = 1;
+
+#[derive(Debug, Clone)]
+struct Smoothed {
+    /// Track id of the box when keyed by track
+    track_id: Option<u64>,
+    bbox: BoundingBox,
+    class: f32,
+    last_frame: usize,
+}
+
+/// Exponential moving average of the boxes of consecutive frames, reducing the jitter of video
+/// overlays
+///
+/// Boxes are followed across frames either by the track id of tracked detections, or by
+/// matching the boxes of a frame to the smoothed ones of the same class by IoU.
+#[derive(Debug, Clone)]
+pub struct BoxSmoother {
+    /// Weight of the box of the current frame, 1 disabling the smoothing
+    pub alpha: f32,
+    /// Minimum IoU of a box with a smoothed one of the previous frames to follow it
+    pub iou_threshold: f32,
+    /// Number of frames a smoothed box is kept without a box following it
+    pub max_age: usize,
+    smoothed: Vec<Smoothed>,
+    frame: usize,
+}
+
+impl BoxSmoother {
+    pub fn new(
+        alpha: Option<f32>,
+        iou_threshold: Option<f32>,
+        max_age: Option<usize>,
+    ) -> Result<Self> {
+        let alpha = alpha.unwrap_or(DEFAULT_ALPHA);
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(Error::invalid(format!("alpha must be in (0, 1] but got {alpha}")));
+        }
+        Ok(Self {
+            alpha,
+            iou_threshold: iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD),
+            max_age: max_age.unwrap_or(DEFAULT_MAX_AGE),
+            smoothed: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// Forget every smoothed box, as at the start of another video
+    pub fn reset(&mut self) {
+        self.smoothed.clear();
+        self.frame = 0;
+    }
+
+    /// Smooth the boxes of a frame of untracked detections, following them by IoU
+    pub fn smooth(&mut self, detections: &DetectionResults) -> DetectionResults {
+        self.frame += 1;
+        let iou = Array2::from_shape_fn((detections.len(), self.smoothed.len()), |(d, s)| {
+            let smoothed = &self.smoothed[s];
+            if smoothed.track_id.is_some() || smoothed.class != detections[d].class {
+                return 0.0;
+            }
+            detections[d].bbox.iou(&smoothed.bbox)
+        });
+        let (matches, _, _) = associate(&iou, self.iou_threshold);
+        let mut followed = vec![None; detections.len()];
+        for (d, s) in matches {
+            followed[d] = Some(s);
+        }
+        let results = detections
+            .iter()
+            .zip(followed)
+            .map(|(detection, s)| DetectionResult {
+                bbox: self.follow(s, detection, None),
+                ..*detection
+            })
+            .collect::<Vec<_>>();
+        self.expire();
+        results.into()
+    }
+
+    /// Smooth the boxes of a frame of tracked detections, following them by track id
+    pub fn smooth_tracked(&mut self, tracked: &[TrackedDetection]) -> Vec<TrackedDetection> {
+        self.frame += 1;
+        let results = tracked
+            .iter()
+            .map(|t| {
+                let s = self.smoothed.iter().position(|s| s.track_id == Some(t.track_id));
+                let bbox = self.follow(s, &t.detection, Some(t.track_id));
+                TrackedDetection { detection: DetectionResult { bbox, ..t.detection }, ..*t }
+            })
+            .collect();
+        self.expire();
+        results
+    }
+
+    /// Average `detection` into the smoothed box `s` if any, starting a smoothed box otherwise
+    fn follow(
+        &mut self,
+        s: Option<usize>,
+        detection: &DetectionResult,
+        track_id: Option<u64>,
+    ) -> BoundingBox {
+        let Some(s) = s else {
+            self.smoothed.push(Smoothed {
+                track_id,
+                bbox: detection.bbox,
+                class: detection.class,
+                last_frame: self.frame,
+            });
+            return detection.bbox;
+        };
+        let (alpha, b) = (self.alpha, &detection.bbox);
+        let smoothed = &mut self.smoothed[s];
+        let ema = |previous: f32, current: f32| alpha * current + (1.0 - alpha) * previous;
+        smoothed.bbox = BoundingBox {
+            px1: ema(smoothed.bbox.px1, b.px1),
+            py1: ema(smoothed.bbox.py1, b.py1),
+            px2: ema(smoothed.bbox.px2, b.px2),
+            py2: ema(smoothed.bbox.py2, b.py2),
+        };
+        smoothed.class = detection.class;
+        smoothed.last_frame = self.frame;
+        smoothed.bbox
+    }
+
+    fn expire(&mut self) {
+        let (frame, max_age) = (self.frame, self.max_age);
+        self.smoothed.retain(|s| frame - s.last_frame <= max_age);
+    }
+}
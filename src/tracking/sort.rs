@@ -0,0 +1,130 @@
+use ndarray::Array2;
+
+use super::assignment::associate;
+use super::kalman::KalmanBoxFilter;
+use super::TrackedDetection;
+use crate::common::ssd_postprocess::{DetectionResult, DetectionResults};
+use crate::common::{Error, Result};
+
+pub const DEFAULT_MAX_AGE: usize = 1;
+pub const DEFAULT_MIN_HITS: usize = 3;
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.3;
+
+#[derive(Debug, Clone)]
+struct Track {
+    id: u64,
+    filter: KalmanBoxFilter,
+    /// Latest detection matched to the track, whose score and class the track reports
+    detection: DetectionResult,
+    frames_since_update: usize,
+    hit_streak: usize,
+}
+
+/// SORT, Simple Online and Realtime Tracking by Bewley et al.
+///
+/// Each track follows a box with a constant velocity Kalman filter, and detections are
+/// assigned to the predicted boxes of the tracks maximizing the total IoU.
+#[derive(Debug, Clone)]
+pub struct Sort {
+    /// Number of frames a track survives without a matching detection
+    pub max_age: usize,
+    /// Number of consecutive matches before a track is reported
+    pub min_hits: usize,
+    /// Minimum IoU of a detection with the predicted box of a track to match it
+    pub iou_threshold: f32,
+    tracks: Vec<Track>,
+    frame_count: usize,
+    next_id: u64,
+}
+
+impl Sort {
+    pub fn new(
+        max_age: Option<usize>,
+        min_hits: Option<usize>,
+        iou_threshold: Option<f32>,
+    ) -> Result<Self> {
+        let iou_threshold = iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD);
+        if !(0.0..=1.0).contains(&iou_threshold) {
+            return Err(Error::invalid(format!(
+                "iou_threshold must be in [0, 1] but got {iou_threshold}"
+            )));
+        }
+        Ok(Self {
+            max_age: max_age.unwrap_or(DEFAULT_MAX_AGE),
+            min_hits: min_hits.unwrap_or(DEFAULT_MIN_HITS),
+            iou_threshold,
+            tracks: Vec::new(),
+            frame_count: 0,
+            next_id: 1,
+        })
+    }
+
+    /// Forget every track, as at the start of another video, restarting the ids from 1
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+        self.frame_count = 0;
+        self.next_id = 1;
+    }
+
+    /// Advance by a frame of `detections`, returning the tracks matched in this frame
+    ///
+    /// Tracks are reported once matched `min_hits` frames in a row after the one they started
+    /// in, or right away during the first `min_hits` frames. Their boxes are the filtered estimates of the detections.
+    pub fn update(&mut self, detections: &DetectionResults) -> Vec<TrackedDetection> {
+        self.frame_count += 1;
+
+        let mut predicted = Vec::with_capacity(self.tracks.len());
+        self.tracks.retain_mut(|track| {
+            if track.frames_since_update > 0 {
+                track.hit_streak = 0;
+            }
+            track.frames_since_update += 1;
+            let bbox = track.filter.predict();
+            // A diverged filter is dropped along with its track
+            let finite = track.filter.is_finite();
+            if finite {
+                predicted.push(bbox);
+            }
+            finite
+        });
+
+        let iou = Array2::from_shape_fn((detections.len(), predicted.len()), |(d, t)| {
+            detections[d].bbox.iou(&predicted[t])
+        });
+        let (matches, unmatched, _) = associate(&iou, self.iou_threshold);
+        for (d, t) in matches {
+            let track = &mut self.tracks[t];
+            track.filter.update(&detections[d].bbox);
+            track.detection = detections[d];
+            track.frames_since_update = 0;
+            track.hit_streak += 1;
+        }
+        for d in unmatched {
+            self.tracks.push(Track {
+                id: self.next_id,
+                filter: KalmanBoxFilter::new(&detections[d].bbox),
+                detection: detections[d],
+                frames_since_update: 0,
+                hit_streak: 0,
+            });
+            self.next_id += 1;
+        }
+
+        let (min_hits, frame_count) = (self.min_hits, self.frame_count);
+        let tracked = self
+            .tracks
+            .iter()
+            .filter(|track| {
+                track.frames_since_update == 0
+                    && (track.hit_streak >= min_hits || frame_count <= min_hits)
+            })
+            .map(|track| TrackedDetection {
+                track_id: track.id,
+                detection: DetectionResult { bbox: track.filter.bbox(), ..track.detection },
+            })
+            .collect();
+        let max_age = self.max_age;
+        self.tracks.retain(|track| track.frames_since_update <= max_age);
+        tracked
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use ndarray::{s, Array3, Array4, ArrayView1, ArrayView2, ArrayView5, Axis, Zip};
+use numpy::{PyArray3, PyReadonlyArray2, PyReadonlyArray5};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::threads;
+
+const DEFAULT_PATCH_SIZE: usize = 128;
+const DEFAULT_STD_FACTOR: f32 = 0.125;
+const DEFAULT_OVERLAP: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub patch_size: [usize; 3],
+    /// Importance weights of a patch, peaking at its center
+    pub gaussian: Array3<f32>,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RustPostProcessor {{ patch_size: {:?} }}", self.patch_size)
+    }
+}
+
+impl RustPostprocessor {
+    fn new(patch_size: [usize; 3], std_factor: f32) -> Self {
+        Self { patch_size, gaussian: Self::gaussian_kernel(patch_size, std_factor) }
+    }
+
+    /// Gaussian importance map of the MLPerf KiTS19 reference, i.e. the cube root of the outer
+    /// product of 1D Gaussian windows (`scipy.signal.gaussian`) normalized to a peak of 1
+    fn gaussian_kernel(patch_size: [usize; 3], std_factor: f32) -> Array3<f32> {
+        let window = |n: usize| {
+            let std = std_factor * n as f32;
+            let center = (n as f32 - 1.0) / 2.0;
+            (0..n).map(|i| f32::exp(-0.5 * ((i as f32 - center) / std).powi(2))).collect::<Vec<_>>()
+        };
+        let [wd, wh, ww] = patch_size.map(window);
+
+        let mut kernel =
+            Array3::from_shape_fn(patch_size, |(d, h, w)| f32::cbrt(wd[d] * wh[h] * ww[w]));
+        let max = kernel.fold(f32::MIN_POSITIVE, |acc, &v| f32::max(acc, v));
+        kernel /= max;
+        kernel
+    }
+
+    /// Origins of the patches sliding over `volume_shape` with the given overlap
+    fn patch_origins(&self, volume_shape: [usize; 3], overlap: f32) -> Vec<[usize; 3]> {
+        let steps = |axis: usize| {
+            let (size, patch) = (volume_shape[axis], self.patch_size[axis]);
+            let stride = ((patch as f32 * (1.0 - overlap)) as usize).max(1);
+            (0..=size.saturating_sub(patch)).step_by(stride).collect::<Vec<_>>()
+        };
+        let (ds, hs, ws) = (steps(0), steps(1), steps(2));
+
+        let mut origins = Vec::with_capacity(ds.len() * hs.len() * ws.len());
+        for &d in &ds {
+            for &h in &hs {
+                for &w in &ws {
+                    origins.push([d, h, w]);
+                }
+            }
+        }
+        origins
+    }
+
+    /// Accumulate Gaussian weighted patch predictions and take the argmax over classes
+    fn postprocess(
+        &self,
+        patches: ArrayView5<'_, f32>,
+        origins: ArrayView2<'_, usize>,
+        volume_shape: [usize; 3],
+    ) -> Array3<u8> {
+        let num_classes = patches.shape()[1];
+        let [pd, ph, pw] = self.patch_size;
+        let region = |origin: ArrayView1<'_, usize>| {
+            s![origin[0]..origin[0] + pd, origin[1]..origin[1] + ph, origin[2]..origin[2] + pw]
+        };
+
+        let mut norm_map = Array3::<f32>::zeros(volume_shape);
+        for origin in origins.outer_iter() {
+            let mut norm = norm_map.slice_mut(region(origin));
+            norm += &self.gaussian;
+        }
+
+        // Each class accumulates into its own volume, so classes are processed in parallel
+        let mut result =
+            Array4::<f32>::zeros((num_classes, volume_shape[0], volume_shape[1], volume_shape[2]));
+        let class_volumes = result.outer_iter_mut().collect::<Vec<_>>();
+        class_volumes.into_par_iter().enumerate().for_each(|(class, mut volume)| {
+            for (patch, origin) in patches.outer_iter().zip(origins.outer_iter()) {
+                Zip::from(volume.slice_mut(region(origin)))
+                    .and(patch.index_axis(Axis(0), class))
+                    .and(&self.gaussian)
+                    .for_each(|acc, &p, &g| *acc += p * g);
+            }
+        });
+
+        let mut labels = Array3::<u8>::zeros(volume_shape);
+        let label_slices = labels.outer_iter_mut().collect::<Vec<_>>();
+        label_slices.into_par_iter().enumerate().for_each(|(d, mut label)| {
+            let norm = norm_map.index_axis(Axis(0), d);
+            for ((h, w), label) in label.indexed_iter_mut() {
+                // Normalization does not change the argmax but keeps the semantics of the
+                // reference, where voxels uncovered by any patch fall back to class 0
+                let n = norm[(h, w)];
+                if n <= 0.0 {
+                    continue;
+                }
+                let mut best = (0, f32::NEG_INFINITY);
+                for c in 0..num_classes {
+                    let v = result[[c, d, h, w]] / n;
+                    if v > best.1 {
+                        best = (c, v);
+                    }
+                }
+                *label = best.0 as u8;
+            }
+        });
+
+        labels
+    }
+
+    fn validate(
+        &self,
+        patches: &ArrayView5<'_, f32>,
+        origins: &ArrayView2<'_, usize>,
+        volume_shape: [usize; 3],
+    ) -> PyResult<()> {
+        let shape = patches.shape();
+        if shape[2..] != self.patch_size {
+            return Err(PyValueError::new_err(format!(
+                "patches must have shape (P, C, {}, {}, {}) but got {shape:?}",
+                self.patch_size[0], self.patch_size[1], self.patch_size[2]
+            )));
+        }
+        if shape[1] == 0 || shape[1] > u8::MAX as usize + 1 {
+            return Err(PyValueError::new_err(format!(
+                "number of classes must be between 1 and 256 but got {}",
+                shape[1]
+            )));
+        }
+        if origins.shape() != [shape[0], 3] {
+            return Err(PyValueError::new_err(format!(
+                "origins must have shape ({}, 3) but got {:?}",
+                shape[0],
+                origins.shape()
+            )));
+        }
+        for origin in origins.outer_iter() {
+            if (0..3).any(|axis| origin[axis] + self.patch_size[axis] > volume_shape[axis]) {
+                return Err(PyValueError::new_err(format!(
+                    "patch at {origin} does not fit in a volume of shape {volume_shape:?}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 3D-UNet PostProcessor
+///
+/// It aggregates overlapping sliding-window patch predictions with Gaussian importance
+/// weighting as the MLPerf 3D-UNet (KiTS19) reference does, producing the final segmentation
+/// volume.
+///
+/// Args:
+///     patch_size (Optional[Tuple[int, int, int]]): Patch size, default is (128, 128, 128)
+///     std_factor (Optional[float]): Gaussian standard deviation relative to the patch size,
+///         default is 0.125
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(patch_size: Option<(usize, usize, usize)>, std_factor: Option<f32>) -> PyResult<Self> {
+        let patch_size = patch_size.map_or([DEFAULT_PATCH_SIZE; 3], Into::into);
+        if patch_size.contains(&0) {
+            return Err(PyValueError::new_err(format!(
+                "patch_size must be positive but got {patch_size:?}"
+            )));
+        }
+        Ok(Self(RustPostprocessor::new(patch_size, std_factor.unwrap_or(DEFAULT_STD_FACTOR))))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Compute the origins of the sliding-window patches
+    ///
+    /// Args:
+    ///     volume_shape (Tuple[int, int, int]): (depth, height, width) of the padded volume
+    ///     overlap (Optional[float]): Overlap ratio between neighboring patches, default is 0.5
+    ///
+    /// Returns:
+    ///     List[Tuple[int, int, int]]: Patch origins in (depth, height, width) order
+    fn patch_origins(
+        &self,
+        volume_shape: (usize, usize, usize),
+        overlap: Option<f32>,
+    ) -> PyResult<Vec<(usize, usize, usize)>> {
+        let overlap = overlap.unwrap_or(DEFAULT_OVERLAP);
+        if !(0.0..1.0).contains(&overlap) {
+            return Err(PyValueError::new_err(format!(
+                "overlap must be in [0, 1) but got {overlap}"
+            )));
+        }
+        Ok(self.0.patch_origins(volume_shape.into(), overlap).into_iter().map(Into::into).collect())
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     patches (numpy.ndarray): Patch predictions of shape (P, C, D, H, W)
+    ///     origins (numpy.ndarray): Non-negative int64 patch origins of shape (P, 3)
+    ///     volume_shape (Tuple[int, int, int]): (depth, height, width) of the padded volume
+    ///
+    /// Returns:
+    ///     numpy.ndarray: Segmentation volume of shape (D, H, W) as uint8
+    fn eval(
+        &self,
+        py: Python<'_>,
+        patches: PyReadonlyArray5<'_, f32>,
+        origins: PyReadonlyArray2<'_, i64>,
+        volume_shape: (usize, usize, usize),
+    ) -> PyResult<Py<PyArray3<u8>>> {
+        let (patches, origins) = (patches.as_array(), origins.as_array());
+        if let Some(origin) = origins.outer_iter().find(|origin| origin.iter().any(|&v| v < 0)) {
+            return Err(PyValueError::new_err(format!(
+                "patch origins must be non-negative but got {origin}"
+            )));
+        }
+        let origins = origins.mapv(|v| v as usize);
+        let origins = origins.view();
+        let volume_shape = volume_shape.into();
+        self.0.validate(&patches, &origins, volume_shape)?;
+
+        let labels =
+            threads::allow_threads(py, || self.0.postprocess(patches, origins, volume_shape));
+        Ok(PyArray3::from_owned_array(py, labels).to_owned())
+    }
+}
+
+pub(crate) fn unet3d(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use ndarray::ArrayViewMut3;
+#[cfg(feature = "python")]
+use numpy::PyReadwriteArray3;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+#[cfg(feature = "python")]
+use crate::common::extract_detections;
+use crate::common::ssd_postprocess::DetectionResults;
+use crate::common::{Error, Result};
+
+mod font;
+
+use font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+#[cfg(feature = "python")]
+use crate::common::threads;
+
+pub const DEFAULT_THICKNESS: usize = 2;
+pub const DEFAULT_FONT_SCALE: usize = 1;
+
+/// Colors given to the classes in turn, the palette of Ultralytics
+pub const PALETTE: [[u8; 3]; 20] = [
+    [0xff, 0x38, 0x38],
+    [0xff, 0x9d, 0x97],
+    [0xff, 0x70, 0x1f],
+    [0xff, 0xb2, 0x1d],
+    [0xcf, 0xd2, 0x31],
+    [0x48, 0xf9, 0x0a],
+    [0x92, 0xcc, 0x17],
+    [0x3d, 0xdb, 0x86],
+    [0x1a, 0x93, 0x34],
+    [0x00, 0xd4, 0xbb],
+    [0x2c, 0x99, 0xa8],
+    [0x00, 0xc2, 0xff],
+    [0x34, 0x45, 0x93],
+    [0x64, 0x73, 0xff],
+    [0x00, 0x18, 0xec],
+    [0x84, 0x38, 0xff],
+    [0x52, 0x00, 0x85],
+    [0xcb, 0x38, 0xff],
+    [0xff, 0x95, 0xc8],
+    [0xff, 0x37, 0xc7],
+];
+
+#[derive(Debug, Clone)]
+pub struct DrawOptions {
+    /// Width of the box outlines in pixels
+    pub thickness: usize,
+    /// Integer scale of the 5x7 pixel font of the labels
+    pub font_scale: usize,
+    /// Whether to write the class name of each box
+    pub labels: bool,
+    /// Whether to write the score of each box
+    pub scores: bool,
+    /// Color of each class, [`PALETTE`] cycling over the classes if None
+    pub colors: Option<Vec<[u8; 3]>>,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        Self {
+            thickness: DEFAULT_THICKNESS,
+            font_scale: DEFAULT_FONT_SCALE,
+            labels: true,
+            scores: true,
+            colors: None,
+        }
+    }
+}
+
+/// An HWC image of 3 or 4 channels, the 4th being an alpha channel made opaque where drawn
+struct Canvas<'a> {
+    image: ArrayViewMut3<'a, u8>,
+}
+
+impl Canvas<'_> {
+    fn width(&self) -> i64 {
+        self.image.shape()[1] as i64
+    }
+
+    fn height(&self) -> i64 {
+        self.image.shape()[0] as i64
+    }
+
+    /// Fill the pixels of [x0, x1) x [y0, y1) within the image
+    fn fill(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: [u8; 3]) {
+        let (x0, x1) = (x0.clamp(0, self.width()) as usize, x1.clamp(0, self.width()) as usize);
+        let (y0, y1) = (y0.clamp(0, self.height()) as usize, y1.clamp(0, self.height()) as usize);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let mut pixel = self.image.slice_mut(ndarray::s![y, x, ..]);
+                for (channel, &value) in pixel.iter_mut().zip(&color) {
+                    *channel = value;
+                }
+                if let Some(alpha) = pixel.get_mut(3) {
+                    *alpha = u8::MAX;
+                }
+            }
+        }
+    }
+
+    /// Write `text` with its top-left corner at (x, y)
+    fn text(&mut self, x: i64, y: i64, text: &str, scale: usize, color: [u8; 3]) {
+        let scale = scale as i64;
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i as i64 * (GLYPH_WIDTH as i64 + 1) * scale;
+            for (column, bits) in glyph(c).iter().enumerate() {
+                for row in (0..GLYPH_HEIGHT).filter(|row| bits >> row & 1 == 1) {
+                    let (px, py) = (left + column as i64 * scale, y + row as i64 * scale);
+                    self.fill(px, py, px + scale, py + scale, color);
+                }
+            }
+        }
+    }
+}
+
+/// Draw the boxes of detections into an HWC image of 3 or 4 channels, with their class name and
+/// score written on a filled label above them, or inside them at the top of the image
+///
+/// Colors are given in the channel order of the image, e.g. as BGR for images of OpenCV.
+pub fn draw_detections(
+    image: ArrayViewMut3<'_, u8>,
+    detections: &DetectionResults,
+    class_names: Option<&[impl AsRef<str>]>,
+    options: &DrawOptions,
+) -> Result<()> {
+    if !matches!(image.shape()[2], 3 | 4) {
+        return Err(Error::invalid(format!(
+            "image must have shape (H, W, 3) or (H, W, 4) but got {:?}",
+            image.shape()
+        )));
+    }
+    if options.colors.as_ref().is_some_and(Vec::is_empty) {
+        return Err(Error::invalid("colors must not be empty"));
+    }
+    let colors = options.colors.as_deref().unwrap_or(&PALETTE);
+    let (thickness, scale) = (options.thickness as i64, options.font_scale.max(1) as i64);
+    let mut canvas = Canvas { image };
+
+    for r in detections.iter() {
+        let class = r.class as usize;
+        let color = colors[class % colors.len()];
+        let (x1, y1) = (r.bbox.px1.round() as i64, r.bbox.py1.round() as i64);
+        let (x2, y2) = (r.bbox.px2.round() as i64, r.bbox.py2.round() as i64);
+        canvas.fill(x1, y1, x2 + 1, y1 + thickness, color);
+        canvas.fill(x1, y2 + 1 - thickness, x2 + 1, y2 + 1, color);
+        canvas.fill(x1, y1, x1 + thickness, y2 + 1, color);
+        canvas.fill(x2 + 1 - thickness, y1, x2 + 1, y2 + 1, color);
+
+        let name = options.labels.then(|| match class_names.and_then(|names| names.get(class)) {
+            Some(name) => name.as_ref().to_string(),
+            None => class.to_string(),
+        });
+        let score = options.scores.then(|| format!("{:.2}", r.score));
+        let label = name.into_iter().chain(score).collect::<Vec<_>>().join(" ");
+        if label.is_empty() {
+            continue;
+        }
+        let width = (label.chars().count() as i64 * (GLYPH_WIDTH as i64 + 1) + 1) * scale;
+        let height = (GLYPH_HEIGHT as i64 + 2) * scale;
+        let top = if y1 >= height { y1 - height } else { y1.max(0) };
+        // Dark text on light colors
+        let luma = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+        let text_color = if luma > 150.0 { [0; 3] } else { [u8::MAX; 3] };
+        canvas.fill(x1, top, x1 + width, top + height, color);
+        canvas.text(x1 + scale, top + scale, &label, scale as usize, text_color);
+    }
+    Ok(())
+}
+
+/// Draw the boxes of detections into an image in place, with their class name and score
+///
+/// Args:
+///     image (numpy.ndarray): uint8 array of shape (H, W, 3) or (H, W, 4), modified in place
+///     detections (Union[numpy.ndarray, Sequence[PyDetectionResult]]): Detections in the
+///         coordinates of the image, either an array of shape (N, 6) with rows
+///         (x1, y1, x2, y2, score, class) or a list of PyDetectionResult
+///     class_names (Optional[Sequence[str]]): Name of each class, default is the class index
+///     thickness (Optional[int]): Width of the box outlines in pixels, default is 2
+///     font_scale (Optional[int]): Integer scale of the 5x7 pixel font, default is 1
+///     labels (Optional[bool]): Whether to write the class names, default is True
+///     scores (Optional[bool]): Whether to write the scores, default is True
+///     colors (Optional[Sequence[Tuple[int, int, int]]]): Color of each class in the channel
+///         order of the image, cycling over the classes, default is the palette of Ultralytics
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "draw_detections")]
+#[allow(clippy::too_many_arguments)]
+fn draw_detections_py(
+    py: Python<'_>,
+    mut image: PyReadwriteArray3<'_, u8>,
+    detections: &PyAny,
+    class_names: Option<Vec<String>>,
+    thickness: Option<usize>,
+    font_scale: Option<usize>,
+    labels: Option<bool>,
+    scores: Option<bool>,
+    colors: Option<Vec<[u8; 3]>>,
+) -> PyResult<()> {
+    let detections = extract_detections(detections)?;
+    let options = DrawOptions {
+        thickness: thickness.unwrap_or(DEFAULT_THICKNESS),
+        font_scale: font_scale.unwrap_or(DEFAULT_FONT_SCALE),
+        labels: labels.unwrap_or(true),
+        scores: scores.unwrap_or(true),
+        colors,
+    };
+    let image = image.as_array_mut();
+    Ok(threads::allow_threads(py, || {
+        draw_detections(image, &detections, class_names.as_deref(), &options)
+    })?)
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn viz(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(draw_detections_py, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array3;
+
+    use super::*;
+    use crate::common::ssd_postprocess::{BoundingBox, DetectionResult};
+
+    #[test]
+    fn unittest_draw_detections() {
+        let mut image = Array3::<u8>::zeros((40, 60, 3));
+        let detections: DetectionResults = vec![DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: 10.0, py1: 20.0, px2: 30.0, py2: 35.0 },
+            score: 0.5,
+            class: 1.0,
+        }]
+        .into();
+        let options = DrawOptions { scores: false, ..Default::default() };
+        draw_detections(image.view_mut(), &detections, Some(&["a", "dog"]), &options).unwrap();
+
+        let color = PALETTE[1];
+        // Outline, untouched inside
+        assert_eq!(image.slice(ndarray::s![20, 10, ..]).to_vec(), color);
+        assert_eq!(image.slice(ndarray::s![35, 30, ..]).to_vec(), color);
+        assert_eq!(image.slice(ndarray::s![34, 29, ..]).to_vec(), color);
+        assert_eq!(image.slice(ndarray::s![27, 20, ..]).to_vec(), [0; 3]);
+        // Label of 9 rows above the box, the top row of "d" dark on the light color
+        assert_eq!(image.slice(ndarray::s![11, 10, ..]).to_vec(), color);
+        assert_eq!(image.slice(ndarray::s![12, 15, ..]).to_vec(), [0; 3]);
+        assert_eq!(image.slice(ndarray::s![10, 10, ..]).to_vec(), [0; 3]);
+
+        // Boxes across the border are clipped
+        let outside: DetectionResults = vec![DetectionResult {
+            index: 0.0,
+            bbox: BoundingBox { px1: -10.0, py1: -5.0, px2: 100.0, py2: 50.0 },
+            score: 0.5,
+            class: 0.0,
+        }]
+        .into();
+        draw_detections(image.view_mut(), &outside, None::<&[&str]>, &Default::default()).unwrap();
+
+        assert!(draw_detections(
+            Array3::zeros((4, 4, 1)).view_mut(),
+            &detections,
+            None::<&[&str]>,
+            &Default::default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
+
+use super::{ultralytics, Activation, ClassMode, Layout, RustPostprocessor};
+use crate::common::box_filter::BoxFilter;
+use crate::common::calibration::Calibration;
+use crate::common::labels::{CategoryIds, ClassNames, ClassTable};
+use crate::common::{Error, Result};
+
+/// Configuration of a [`RustPostprocessor`], the arguments of its constructor, saved and loaded
+/// as JSON or YAML
+///
+/// Options left out are None and take their default.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// (width, height) pairs of each detection layer, in units of its stride
+    pub anchors: Vec<Vec<[f32; 2]>>,
+    pub strides: Vec<f32>,
+    pub agnostic: Option<bool>,
+    pub merge: Option<bool>,
+    pub num_threads: Option<usize>,
+    pub layout: Option<String>,
+    pub channels: Option<usize>,
+    pub tile: Option<usize>,
+    pub clip: Option<bool>,
+    pub max_det: Option<usize>,
+    pub max_boxes: Option<usize>,
+    pub max_nms: Option<usize>,
+    pub raw_logits: Option<bool>,
+    pub fast_sigmoid: Option<bool>,
+    pub objectness_only: Option<bool>,
+    pub multi_label: Option<bool>,
+    pub min_area: Option<f32>,
+    pub min_side: Option<f32>,
+    pub max_aspect_ratio: Option<f32>,
+    pub calibration: Option<Calibration>,
+    pub class_names: Option<ClassNames>,
+    pub category_ids: Option<CategoryIds>,
+}
+
+impl Config {
+    /// Configuration of a JSON object
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| Error::invalid(format!("invalid config: {e}")))
+    }
+
+    /// JSON object of the configuration, read back by [`Config::from_json`]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a config is always serializable")
+    }
+
+    /// Configuration of a YAML mapping, or of the anchors and strides of an Ultralytics YOLOv5
+    /// model definition
+    ///
+    /// The strides of a model definition are inferred by following the downsampling of its
+    /// backbone and head up to the inputs of its `Detect` layer, unless given, and its anchors
+    /// in pixels are divided by them as the model does when built.
+    pub fn from_yaml(text: &str, strides: Option<Vec<f32>>) -> Result<Self> {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(text).map_err(|e| Error::invalid(format!("invalid YAML: {e}")))?;
+        if !ultralytics::is_model(&value) {
+            if strides.is_some() {
+                return Err(Error::invalid("strides only apply to model definitions"));
+            }
+            return serde_yaml::from_value(value)
+                .map_err(|e| Error::invalid(format!("invalid config: {e}")));
+        }
+        let (mut anchors, inferred) = ultralytics::anchors_and_strides(&value)?;
+        let strides = strides.unwrap_or(inferred);
+        if strides.len() != anchors.shape()[0] {
+            return Err(Error::invalid(format!(
+                "expected {} strides but got {}",
+                anchors.shape()[0],
+                strides.len()
+            )));
+        }
+        // Anchors of model definitions are in pixels, and in units of the stride once built
+        for (mut layer, stride) in anchors.outer_iter_mut().zip(&strides) {
+            layer /= *stride;
+        }
+        Ok(Self { anchors: anchor_pairs(&anchors), strides, ..Self::default() })
+    }
+
+    /// YAML mapping of the configuration, read back by [`Config::from_yaml`]
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).expect("a config is always serializable")
+    }
+
+    /// Postprocessor of the configuration
+    pub fn build(&self) -> Result<RustPostprocessor> {
+        let num_anchors = self.anchors.first().map_or(0, Vec::len);
+        if self.anchors.iter().any(|layer| layer.len() != num_anchors) {
+            return Err(Error::invalid(
+                "anchors must be a list of layers with the same number of (width, height) pairs",
+            ));
+        }
+        let anchors = Array3::from_shape_vec(
+            (self.anchors.len(), num_anchors, 2),
+            self.anchors.iter().flatten().flatten().copied().collect(),
+        )
+        .unwrap();
+        let mut postprocessor = RustPostprocessor::new(
+            anchors,
+            self.strides.clone(),
+            self.agnostic,
+            self.num_threads,
+            Layout::parse(self.layout.as_deref(), self.channels, self.tile)?,
+            self.clip,
+            self.max_det,
+            self.max_boxes,
+            self.max_nms,
+        )?;
+        postprocessor.activation = Activation::parse(self.raw_logits, self.fast_sigmoid)?;
+        postprocessor.class_mode = ClassMode::parse(self.objectness_only, self.multi_label);
+        postprocessor.merge = self.merge.unwrap_or(false);
+        postprocessor.box_filter =
+            BoxFilter::new(self.min_area, self.min_side, self.max_aspect_ratio)?;
+        postprocessor.calibration = self.calibration.clone().unwrap_or_default();
+        postprocessor.class_table = Arc::new(ClassTable::new(
+            self.class_names.clone().map(|ClassNames(names)| names),
+            self.category_ids.clone().map(|CategoryIds(ids)| ids),
+        )?);
+        Ok(postprocessor)
+    }
+}
+
+impl From<&RustPostprocessor> for Config {
+    fn from(p: &RustPostprocessor) -> Self {
+        let (layout, channels, tile) = p.layout.describe();
+        let (raw_logits, fast_sigmoid) = p.activation.describe();
+        let (objectness_only, multi_label) = p.class_mode.describe();
+        let (min_area, min_side, max_aspect_ratio) = p.box_filter.describe();
+        let calibration = match &p.calibration {
+            Calibration::Identity => None,
+            calibration => Some(calibration.clone()),
+        };
+        Self {
+            anchors: anchor_pairs(&p.anchors),
+            strides: p.strides.clone(),
+            agnostic: Some(p.agnostic),
+            merge: Some(p.merge),
+            num_threads: p.pool.as_ref().map(|pool| pool.current_num_threads()),
+            layout: Some(layout.to_string()),
+            channels,
+            tile,
+            clip: Some(p.clip),
+            max_det: Some(p.max_det),
+            max_boxes: Some(p.max_boxes),
+            max_nms: Some(p.max_nms),
+            raw_logits: Some(raw_logits),
+            fast_sigmoid: Some(fast_sigmoid),
+            objectness_only: Some(objectness_only),
+            multi_label: Some(multi_label),
+            min_area,
+            min_side,
+            max_aspect_ratio,
+            calibration,
+            class_names: p.class_table.names.clone().map(ClassNames),
+            category_ids: p.class_table.category_ids.clone().map(CategoryIds),
+        }
+    }
+}
+
+/// Anchors as (width, height) pairs of each layer
+fn anchor_pairs(anchors: &Array3<f32>) -> Vec<Vec<[f32; 2]>> {
+    anchors
+        .outer_iter()
+        .map(|layer| layer.outer_iter().map(|anchor| [anchor[0], anchor[1]]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "
+nc: 80
+anchors:
+  - [10,13, 16,30, 33,23]
+  - [30,61, 62,45, 59,119]
+backbone:
+  [[-1, 1, Conv, [64, 6, 2, 2]],
+   [-1, 1, Conv, [128, 3, 2]],
+   [-1, 1, Conv, [256, 3, 2]],
+  ]
+head:
+  [[-1, 1, Conv, [512, 3, 2]],
+   [[2, 3], 1, Detect, [nc, anchors]],
+  ]
+";
+
+    #[test]
+    fn unittest_config() {
+        let config = Config::from_yaml(MODEL, None).unwrap();
+        assert_eq!(config.strides, [8.0, 16.0]);
+        assert_eq!(config.anchors[1][2], [59.0 / 16.0, 119.0 / 16.0]);
+        assert!(Config::from_yaml(MODEL, Some(vec![8.0])).is_err());
+
+        let mut postprocessor = config.build().unwrap();
+        postprocessor.class_mode = ClassMode::Best;
+        postprocessor.calibration = Calibration::Temperature(2.0);
+        let config = Config::from(&postprocessor);
+        assert_eq!(config.multi_label, Some(false));
+        assert_eq!(Config::from_json(&config.to_json()).unwrap(), config);
+        assert_eq!(Config::from_yaml(&config.to_yaml(), None).unwrap(), config);
+        let rebuilt = config.build().unwrap();
+        assert_eq!(rebuilt.calibration, postprocessor.calibration);
+        assert_eq!(rebuilt.class_mode, ClassMode::Best);
+
+        let config = Config::from_json(
+            r#"{"anchors": [[[1, 2]]], "strides": [8], "class_names": "coco80",
+                "category_ids": "coco91"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.build().unwrap().class_table.category_id(79), 90);
+        assert!(Config::from_json(r#"{"anchors": [[[1, 2]]], "strides": [8], "x": 1}"#).is_err());
+        assert!(Config::from_json(r#"{"anchors": [[[1, 2]]]}"#).is_err());
+        assert!(Config::from_json(r#"{"anchors": [[[1, 2]], []], "strides": [8, 16]}"#)
+            .unwrap()
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use super::simd::SortedBoxes;
+
+/// Number of boxes from which NMS only compares each kept box with the boxes of the grid cells
+/// it covers, rather than with every following box
+pub(crate) const MIN_GRID_BOXES: usize = 4096;
+
+/// Number of cells above which a box is not hashed into the cells it covers but scanned by every
+/// query, so that a single huge box cannot fill the memory
+const MAX_CELLS_PER_BOX: i64 = 256;
+
+/// Number of boxes hashed between two checks of the deadline
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+/// Spatial hash of sorted boxes into square cells about the size of an average box, so that the
+/// boxes a box may overlap are found among the few cells it covers
+#[derive(Debug)]
+pub(crate) struct SpatialGrid {
+    cell_size: f32,
+    /// Indices of the boxes covering each cell, ascending
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    /// Indices of the boxes covering more than `MAX_CELLS_PER_BOX` cells, ascending
+    oversized: Vec<usize>,
+}
+
+impl SpatialGrid {
+    /// Grid of `boxes`, or None if a coordinate is not finite, since the IoU involving such a
+    /// box may suppress boxes it does not overlap, or if `deadline` passes while hashing them
+    pub fn new(boxes: &SortedBoxes, deadline: Option<Instant>) -> Option<Self> {
+        let coords = [&boxes.x1, &boxes.y1, &boxes.x2, &boxes.y2];
+        if !coords.iter().all(|coords| coords.iter().all(|v| v.is_finite())) {
+            return None;
+        }
+        let total_side: f64 = (0..boxes.len())
+            .map(|i| f32::max(boxes.x2[i] - boxes.x1[i], boxes.y2[i] - boxes.y1[i]).max(0.0) as f64)
+            .sum();
+        let mean_side = (total_side / boxes.len().max(1) as f64) as f32;
+        let cell_size = if mean_side > 0.0 { mean_side } else { 1.0 };
+
+        let mut grid = Self { cell_size, cells: HashMap::new(), oversized: Vec::new() };
+        for i in 0..boxes.len() {
+            if i % DEADLINE_CHECK_INTERVAL == 0
+                && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return None;
+            }
+            if grid.num_covered(boxes, i) > MAX_CELLS_PER_BOX {
+                grid.oversized.push(i);
+                continue;
+            }
+            for cell in grid.covered(boxes, i) {
+                grid.cells.entry(cell).or_default().push(i);
+            }
+        }
+        Some(grid)
+    }
+
+    /// Range of the cells covered by box `i` as (x1, y1, x2, y2), inclusive
+    fn range(&self, boxes: &SortedBoxes, i: usize) -> (i64, i64, i64, i64) {
+        // The conversion saturates, so that coordinates far beyond any grid stay ordered
+        let cell = |v: f32| (v / self.cell_size).floor() as i64;
+        (cell(boxes.x1[i]), cell(boxes.y1[i]), cell(boxes.x2[i]), cell(boxes.y2[i]))
+    }
+
+    /// Number of cells covered by box `i`, saturating
+    fn num_covered(&self, boxes: &SortedBoxes, i: usize) -> i64 {
+        let (x1, y1, x2, y2) = self.range(boxes, i);
+        let side = |lo: i64, hi: i64| hi.saturating_sub(lo).saturating_add(1).max(0);
+        side(x1, x2).saturating_mul(side(y1, y2))
+    }
+
+    /// Cells covered by box `i`, none if it is inverted and so overlaps no box
+    fn covered(&self, boxes: &SortedBoxes, i: usize) -> impl Iterator<Item = (i64, i64)> {
+        let (x1, y1, x2, y2) = self.range(boxes, i);
+        (y1..=y2).flat_map(move |y| (x1..=x2).map(move |x| (x, y)))
+    }
+
+    /// Mark the boxes after `cur` whose IoU with it exceeds `iou_threshold` in the `suppressed`
+    /// bitmask, as [`SortedBoxes::suppress_after`] does as long as `iou_threshold` is not
+    /// negative and `epsilon` is positive, so that boxes sharing no cell never suppress
+    /// each other
+    pub fn suppress_after(
+        &self,
+        boxes: &SortedBoxes,
+        cur: usize,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        // An oversized box may overlap any box
+        if self.oversized.binary_search(&cur).is_ok() {
+            boxes.suppress_after(cur, iou_threshold, epsilon, suppressed);
+            return;
+        }
+        let cells = self.covered(boxes, cur).filter_map(|cell| self.cells.get(&cell));
+        for indices in cells.chain([&self.oversized]) {
+            for &i in &indices[indices.partition_point(|&i| i <= cur)..] {
+                let (word, bit) = (i / 64, 1 << (i % 64));
+                if suppressed[word] & bit == 0 && boxes.suppresses(cur, i, iou_threshold, epsilon) {
+                    suppressed[word] |= bit;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Greedy NMS of `len` sorted boxes marking the boxes each kept one suppresses
+    fn nms(len: usize, suppress: &dyn Fn(usize, &mut [u64])) -> Vec<usize> {
+        let mut suppressed = vec![0u64; (len + 63) / 64];
+        let mut kept = Vec::new();
+        for cur in 0..len {
+            if suppressed[cur / 64] >> (cur % 64) & 1 == 0 {
+                kept.push(cur);
+                suppress(cur, &mut suppressed);
+            }
+        }
+        kept
+    }
+
+    fn linear_nms(boxes: &SortedBoxes) -> Vec<usize> {
+        nms(boxes.len(), &|cur, suppressed| boxes.suppress_after(cur, 0.3, 1e-5, suppressed))
+    }
+
+    fn gridded_nms(boxes: &SortedBoxes, grid: &SpatialGrid) -> Vec<usize> {
+        nms(boxes.len(), &|cur, suppressed| grid.suppress_after(boxes, cur, 0.3, 1e-5, suppressed))
+    }
+
+    #[test]
+    fn unittest_grid_matches_linear_scan() {
+        let mut state = 0x9e37_79b9_u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        // Crowded small boxes with a few large ones, inverted ones, and a second class far away
+        let mut boxes = SortedBoxes::default();
+        for i in 0..2000 {
+            let (x, y) = (next() * 400.0 + (i % 2) as f32 * 7680.0, next() * 400.0);
+            let side = if i % 97 == 0 { 200.0 } else { 5.0 + next() * 20.0 };
+            let w = if i % 61 == 0 { -side } else { side };
+            let h = side * (0.5 + next());
+            boxes.x1.push(x);
+            boxes.y1.push(y);
+            boxes.x2.push(x + w);
+            boxes.y2.push(y + h);
+            boxes.areas.push(f32::max(0.0, w) * h);
+        }
+        let grid = SpatialGrid::new(&boxes, None).unwrap();
+        let linear = linear_nms(&boxes);
+        assert!(linear.len() < boxes.len());
+        assert_eq!(gridded_nms(&boxes, &grid), linear);
+
+        boxes.x1[0] = f32::NAN;
+        assert!(SpatialGrid::new(&boxes, None).is_none());
+    }
+
+    #[test]
+    fn unittest_grid_huge_box() {
+        // Small boxes on a 64x64 lattice and huge ones first and in the middle of the order,
+        // each of which would otherwise cover millions of cells
+        let mut boxes = SortedBoxes::default();
+        for i in 0..4097 {
+            let (x, y, side) = match i {
+                0 | 2048 => (-1e9, -1e9, 2e9),
+                _ => ((i % 64) as f32 * 7.0, (i / 64) as f32 * 7.0, 10.0),
+            };
+            boxes.x1.push(x);
+            boxes.y1.push(y);
+            boxes.x2.push(x + side);
+            boxes.y2.push(y + side);
+            boxes.areas.push(side * side);
+        }
+        let grid = SpatialGrid::new(&boxes, None).unwrap();
+        assert_eq!(grid.oversized, [0, 2048]);
+        let linear = linear_nms(&boxes);
+        assert!(linear.len() > 2);
+        assert_eq!(gridded_nms(&boxes, &grid), linear);
+
+        // Coordinates near the limits of float32 saturate rather than overflow
+        boxes.x1[0] = -1e30;
+        boxes.x2[0] = 1e30;
+        assert!(SpatialGrid::new(&boxes, None).is_some());
+        assert!(SpatialGrid::new(&boxes, Some(Instant::now())).is_none());
+    }
+}
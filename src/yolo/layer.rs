@@ -0,0 +1,83 @@
+use ndarray::{s, ArrayView1, ArrayView5, ArrayView6, Axis, IntoDimension, ShapeBuilder};
+
+use crate::common::{Error, Result};
+
+/// Output tensor of a detection layer, viewed as (batch, anchor, y, x / tile, tile, channels)
+///
+/// The x axis is split in tiles of the width lowered by the compiler, a single tile spanning the
+/// whole row for untiled layouts, so that device buffers are indexed in place.
+#[derive(Debug, Clone)]
+pub struct Layer<'a, T>(ArrayView6<'a, T>);
+
+impl<'a, T> Layer<'a, T> {
+    /// Layer of a (batch, anchor, y, x, channels) tensor
+    pub fn nahwc(view: ArrayView5<'a, T>) -> Self {
+        Self(view.insert_axis(Axis(4)))
+    }
+
+    /// Layer of a (batch, y, x / tile, padded channels, tile) tensor, whose first
+    /// `num_anchors * channels` channels hold the anchors one after the other and the rest is
+    /// padding
+    pub fn lowered(view: ArrayView5<'a, T>, num_anchors: usize, channels: usize) -> Result<Self> {
+        let &[batch, height, tiles, padded, tile] = view.shape() else { unreachable!() };
+        if padded < num_anchors * channels {
+            return Err(Error::invalid(format!(
+                "expected at least {num_anchors} * {channels} channels but got {padded}"
+            )));
+        }
+        let &[batch_s, y_s, tiles_s, channel_s, tile_s] = view.strides() else { unreachable!() };
+        let strides = [batch_s, channel_s * channels as isize, y_s, tiles_s, tile_s, channel_s];
+        if strides.iter().any(|&s| s < 0) {
+            return Err(Error::invalid("tensors with negative strides are not supported"));
+        }
+        let shape = [batch, num_anchors, height, tiles, tile, channels]
+            .into_dimension()
+            .strides(strides.map(|s| s as usize).into_dimension());
+        // SAFETY: the anchor and channel axes split the first `num_anchors * channels` channels
+        // of `view`, so every element of the new view is an element of `view`, borrowed for 'a
+        Ok(Self(unsafe { ArrayView6::from_shape_ptr(shape, view.as_ptr()) }))
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.0.shape()[0]
+    }
+
+    pub fn num_anchors(&self) -> usize {
+        self.0.shape()[1]
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.shape()[2]
+    }
+
+    pub fn width(&self) -> usize {
+        self.0.shape()[3] * self.0.shape()[4]
+    }
+
+    pub fn channels(&self) -> usize {
+        self.0.shape()[5]
+    }
+
+    /// Channels of the cell at (anchor, y, x) of an image
+    pub(crate) fn cell(&self, batch: usize, a: usize, y: usize, x: usize) -> ArrayView1<'_, T> {
+        let tile = self.0.shape()[4];
+        self.0.slice(s![batch, a, y, x / tile, x % tile, ..])
+    }
+
+    /// Push the (anchor, y, x) cells of an image whose channel `c` satisfies `f` onto `cells`,
+    /// in grid order
+    pub(crate) fn select_cells(
+        &self,
+        batch: usize,
+        c: usize,
+        f: impl Fn(&T) -> bool,
+        cells: &mut Vec<(usize, usize, usize)>,
+    ) {
+        let tile = self.0.shape()[4];
+        for ((a, y, t, i), v) in self.0.slice(s![batch, .., .., .., .., c]).indexed_iter() {
+            if f(v) {
+                cells.push((a, y, t * tile + i));
+            }
+        }
+    }
+}
@@ -1,18 +1,699 @@
+mod config;
+mod grid;
+mod layer;
+mod simd;
+mod ultralytics;
 pub mod utils;
-use std::fmt;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use std::{fmt, mem};
+#[cfg(feature = "python")]
+use std::{fs, path::Path};
 
 use itertools::{izip, Itertools};
-use ndarray::{Array1, Array2, Array3};
-use numpy::{PyArray2, PyReadonlyArray3, PyReadonlyArray5};
+use ndarray::{
+    s, Array1, Array2, Array3, ArrayView2, ArrayView3, ArrayView4, ArrayViewD, Axis, Ix4, Ix5,
+};
+#[cfg(feature = "python")]
+use ndarray::{ArrayD, IxDyn};
+#[cfg(feature = "python")]
+use numpy::{PyArray1, PyArray2, PyArray3, PyReadonlyArray3, PyReadonlyArray4, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
+use pyo3::exceptions::{PyTypeError, PyValueError};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::PyDict;
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use utils::{centered_box_to_ltrb_bulk, DetectionBoxes};
 
+pub use self::config::Config;
+use self::grid::SpatialGrid;
+pub use self::layer::Layer;
+use self::simd::SortedBoxes;
+use crate::common::box_filter::BoxFilter;
+#[cfg(feature = "python")]
+use crate::common::box_format::BoxFormat;
+use crate::common::calibration::Calibration;
+#[cfg(feature = "python")]
+use crate::common::labels::CategoryIds;
+use crate::common::labels::ClassTable;
+use crate::common::letterbox::Letterbox;
+use crate::common::roi::Roi;
+use crate::common::ssd_postprocess::BoundingBox;
+use crate::common::top_k::TopK;
+#[cfg(feature = "python")]
+use crate::common::worker::{self, Completion};
+use crate::common::{
+    descending, fast_sigmoid, logit, sigmoid, soft_suppress_until, threads, wbf, ConfThreshold,
+    Error, HalfFormat, IouThreshold, NmsMethod, Quantization, Result,
+};
+#[cfg(feature = "python")]
+use crate::common::{from_py, to_py, warn_overflow, PyDetectionResults, PySegmentationResults};
+#[cfg(feature = "python")]
+use crate::postprocessor::{rescore_py, Rescore};
+use crate::postprocessor::{Postprocessor, Thresholds};
+
+/// Default maximum number of candidates decoded per image
+pub const DEFAULT_MAX_BOXES: usize = 10_000;
+/// Default maximum number of detections kept per image by NMS
+pub const DEFAULT_MAX_DET: usize = 300;
+/// Default maximum number of highest scoring candidates per image entering NMS
+pub const DEFAULT_MAX_NMS: usize = 30_000;
+/// Default confidence threshold of the detections, the one of YOLOv5 `detect.py`
+pub const DEFAULT_CONF_THRESHOLD: f32 = 0.25;
+/// Default IoU threshold of NMS, the one of YOLOv5 `detect.py`
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.45;
+pub(crate) const MAX_WH: f32 = 7680.;
+/// Maximum number of input resolutions whose decode plans are cached, the oldest being evicted
+const MAX_PLANS: usize = 16;
+/// Number of grid cells decoded by a single task
+const CELL_CHUNK_SIZE: usize = 256;
+
+/// Boxes, scores, and classes of the decoded candidates of an image
+#[cfg(feature = "python")]
+type DecodedCandidates = (Py<PyArray2<f32>>, Py<PyArray1<f32>>, Py<PyArray1<f32>>);
+
+/// Centered candidate boxes decoded from a part of the grid, of which only the highest scoring
+/// ones are kept once there are more than the cap given to [`Candidates::reset`]
+#[derive(Debug, Default)]
+struct Candidates {
+    pcy: Vec<f32>,
+    pcx: Vec<f32>,
+    ph: Vec<f32>,
+    pw: Vec<f32>,
+    scores: Vec<f32>,
+    classes: Vec<f32>,
+    mask_coefs: Vec<f32>,
+    /// (anchor, y, x) of the cell each candidate was decoded from
+    cells: Vec<(usize, usize, usize)>,
+    /// Number of candidates decoded, kept or not
+    decoded: usize,
+    /// Selection of the kept candidates, by their position among the decoded ones
+    top: TopK,
+}
+
+/// Offsets of the cell centers of a detection layer, so that decoding a center coordinate is a
+/// single multiply-add
+/// (feat[..., 0:2] * 2. - 0.5 + self.grid[i]) * self.stride[i] is feat * scale + offset.
+#[derive(Debug)]
+struct GridOffsets {
+    /// Scale of the xy outputs, twice the stride
+    scale: f32,
+    x: Vec<f32>,
+    y: Vec<f32>,
+}
+
+impl GridOffsets {
+    fn new(stride: f32, height: usize, width: usize) -> Self {
+        let offsets = |len: usize| (0..len).map(|i| (i as f32 - 0.5) * stride).collect();
+        Self { scale: 2.0 * stride, x: offsets(width), y: offsets(height) }
+    }
+}
+
+/// Grid offsets of each layer for the grid sizes of an input resolution
+#[derive(Debug)]
+struct Plan {
+    /// (height, width) of the grid of each layer
+    shapes: Vec<(usize, usize)>,
+    grids: Vec<GridOffsets>,
+}
+
+/// Plans of the input resolutions seen so far, so that serving dynamic shapes builds the plan of
+/// a resolution once while a static shape only looks up its single plan
+#[derive(Debug, Default)]
+struct PlanCache(Mutex<Vec<Arc<Plan>>>);
+
+impl PlanCache {
+    /// Plan of the grids of `layers`, built and cached if they are new
+    fn get<T>(&self, strides: &[f32], layers: &[Layer<'_, T>]) -> Arc<Plan> {
+        let shapes = || layers.iter().map(|layer| (layer.height(), layer.width()));
+        let mut plans = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(plan) = plans.iter().find(|plan| plan.shapes.iter().copied().eq(shapes())) {
+            return plan.clone();
+        }
+        if plans.len() >= MAX_PLANS {
+            plans.remove(0);
+        }
+        let shapes = shapes().collect::<Vec<_>>();
+        let grids = izip!(strides, &shapes)
+            .map(|(&stride, &(height, width))| GridOffsets::new(stride, height, width))
+            .collect();
+        let plan = Arc::new(Plan { shapes, grids });
+        plans.push(plan.clone());
+        plan
+    }
+}
+
+impl Clone for PlanCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()))
+    }
+}
+
+impl Candidates {
+    /// Append the candidates of the given `(anchor, y, x)` cells of image `batch` of one
+    /// detection layer, with `anchors` in pixels, until there are `limit` candidates
+    /// `value` converts the element of a channel into f32, dequantizing it if needed.
+    #[allow(clippy::too_many_arguments)]
+    fn decode<T: Copy>(
+        &mut self,
+        layer: &Layer<'_, T>,
+        batch: usize,
+        cells: &[(usize, usize, usize)],
+        anchors: ArrayView2<'_, f32>,
+        grid: &GridOffsets,
+        conf_threshold: &ConfThreshold,
+        class_mode: ClassMode,
+        num_masks: usize,
+        limit: usize,
+        roi: Option<&Roi>,
+        filter: &BoxFilter,
+        calibration: &Calibration,
+        value: impl Fn(usize, T) -> f32,
+    ) {
+        let candidates = self;
+        'outer: for &(a, y, x) in cells {
+            let (ax, ay) = (anchors[(a, 0)], anchors[(a, 1)]);
+            let inner_x = layer.cell(batch, a, y, x);
+            // Strided channels are gathered, which only happens for the surviving cells
+            let inner_x = match inner_x.as_slice() {
+                Some(inner_x) => Cow::Borrowed(inner_x),
+                None => Cow::Owned(inner_x.to_vec()),
+            };
+            // Destruct output array
+            let [bx, by, bw, bh, object_confidence] = [0, 1, 2, 3, 4].map(|c| value(c, inner_x[c]));
+            let num_channels = inner_x.len();
+            let class_channels = 5..num_channels - num_masks;
+
+            let classes = score_classes(
+                class_mode,
+                object_confidence,
+                class_channels.clone().map(|c| value(c, inner_x[c])),
+                conf_threshold,
+                calibration,
+            );
+            if classes.is_empty() {
+                continue;
+            }
+
+            // Decode box
+            // (feat[..., 2:4] * 2) ** 2 * self.anchor_grid[i]  # wh
+            let cy = by * grid.scale + grid.y[y];
+            let cx = bx * grid.scale + grid.x[x];
+            if roi.is_some_and(|roi| !roi.contains(cx, cy)) {
+                continue;
+            }
+            let h = 4.0 * bh * bh * ay;
+            let w = 4.0 * bw * bw * ax;
+            if !filter.keeps(w, h) {
+                continue;
+            }
+
+            for (class_idx, score) in classes {
+                // No chunk can contribute more than the limit of the whole image
+                if candidates.decoded >= limit {
+                    break 'outer;
+                }
+                candidates.keep(
+                    candidates.decoded,
+                    [cy, cx, h, w],
+                    score,
+                    class_idx as f32,
+                    (a, y, x),
+                    (class_channels.end..num_channels).map(|c| value(c, inner_x[c])),
+                );
+                candidates.decoded += 1;
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Clear the candidates, keeping at most `max_kept` of the following ones
+    fn reset(&mut self, max_kept: usize) {
+        self.pcy.clear();
+        self.pcx.clear();
+        self.ph.clear();
+        self.pw.clear();
+        self.scores.clear();
+        self.classes.clear();
+        self.mask_coefs.clear();
+        self.cells.clear();
+        self.decoded = 0;
+        self.top.reset(max_kept);
+    }
+
+    /// Keep the candidate decoded at position `seq` if it is among the highest scoring ones so
+    /// far, in place of the lowest scoring one if the cap is reached
+    fn keep(
+        &mut self,
+        seq: usize,
+        [cy, cx, h, w]: [f32; 4],
+        score: f32,
+        class: f32,
+        cell: (usize, usize, usize),
+        mask_coefs: impl Iterator<Item = f32>,
+    ) {
+        let Some(slot) = self.top.offer(seq, score, &self.scores) else { return };
+        if slot == self.scores.len() {
+            self.pcy.push(cy);
+            self.pcx.push(cx);
+            self.ph.push(h);
+            self.pw.push(w);
+            self.scores.push(score);
+            self.classes.push(class);
+            self.cells.push(cell);
+            self.mask_coefs.extend(mask_coefs);
+        } else {
+            self.pcy[slot] = cy;
+            self.pcx[slot] = cx;
+            self.ph[slot] = h;
+            self.pw[slot] = w;
+            self.scores[slot] = score;
+            self.classes[slot] = class;
+            self.cells[slot] = cell;
+            let num_masks = self.mask_coefs.len() / self.scores.len();
+            for (coef, value) in self.mask_coefs[slot * num_masks..].iter_mut().zip(mask_coefs) {
+                *coef = value;
+            }
+        }
+    }
+
+    /// Put the kept candidates back in decode order, which replacing the lowest scoring ones
+    /// shuffles
+    fn sort_by_decode_order(&mut self) {
+        let Some(order) = self.top.sort_by_seq() else { return };
+        let num_masks = self.mask_coefs.len() / self.scores.len().max(1);
+        for values in [&mut self.pcy, &mut self.pcx, &mut self.ph, &mut self.pw] {
+            *values = order.iter().map(|&slot| values[slot]).collect();
+        }
+        self.scores = order.iter().map(|&slot| self.scores[slot]).collect();
+        self.classes = order.iter().map(|&slot| self.classes[slot]).collect();
+        self.cells = order.iter().map(|&slot| self.cells[slot]).collect();
+        self.mask_coefs = order
+            .iter()
+            .flat_map(|&slot| &self.mask_coefs[slot * num_masks..(slot + 1) * num_masks])
+            .copied()
+            .collect();
+    }
+
+    /// Convert the centered boxes into LTRB boxes written into the given buffers, moving the
+    /// scores and classes out, so that no allocation is needed once the buffers are large enough
+    fn take_boxes(&mut self, buffers: [Vec<f32>; 4]) -> DetectionBoxes {
+        let [mut x1, mut y1, mut x2, mut y2] = buffers;
+        for buffer in [&mut x1, &mut y1, &mut x2, &mut y2] {
+            buffer.clear();
+        }
+        for (&cx, &cy, &w, &h) in izip!(&self.pcx, &self.pcy, &self.pw, &self.ph) {
+            x1.push(cx - w * 0.5);
+            y1.push(cy - h * 0.5);
+            x2.push(cx + w * 0.5);
+            y2.push(cy + h * 0.5);
+        }
+        let scores = mem::take(&mut self.scores);
+        let classes = mem::take(&mut self.classes);
+        DetectionBoxes::new(
+            x1.into(),
+            y1.into(),
+            x2.into(),
+            y2.into(),
+            scores.into(),
+            classes.into(),
+        )
+    }
+
+    /// (layer, anchor, y, x) of candidate `i`, given the number of candidates decoded up to the
+    /// end of each layer
+    fn origin(&self, layer_ends: &[usize], i: usize) -> [usize; 4] {
+        let (a, y, x) = self.cells[i];
+        let seq = self.top.seq(i);
+        [layer_ends.partition_point(|&end| end <= seq), a, y, x]
+    }
+
+    /// Take back the buffers of boxes made by [`Candidates::take_boxes`]
+    fn restore_boxes(&mut self, boxes: DetectionBoxes) -> [Vec<f32>; 4] {
+        let DetectionBoxes { x1, y1, x2, y2, scores, classes } = boxes;
+        self.scores = scores.into_raw_vec();
+        self.classes = classes.into_raw_vec();
+        [x1, y1, x2, y2].map(Array1::into_raw_vec)
+    }
+
+    /// Append the candidates decoded in `other`, in decode order, until `limit` candidates are
+    /// decoded
+    /// The candidates `other` did not keep are counted as decoded, as they are beaten by enough
+    /// of its kept ones not to be kept here either.
+    fn extend_limited(&mut self, other: &mut Self, limit: usize, num_masks: usize) {
+        other.sort_by_decode_order();
+        let n = usize::min(other.decoded, limit.saturating_sub(self.decoded));
+        for i in 0..other.len() {
+            let seq = other.top.seq(i);
+            if seq >= n {
+                break;
+            }
+            self.keep(
+                self.decoded + seq,
+                [other.pcy[i], other.pcx[i], other.ph[i], other.pw[i]],
+                other.scores[i],
+                other.classes[i],
+                other.cells[i],
+                other.mask_coefs[i * num_masks..(i + 1) * num_masks].iter().copied(),
+            );
+        }
+        self.decoded += n;
+    }
+}
+
+/// Classes of a cell whose calibrated `class_confidence * object_confidence` exceeds their
+/// `conf_threshold`, with their scores, given the activated objectness and class confidences
+fn score_classes(
+    class_mode: ClassMode,
+    object_confidence: f32,
+    class_confs: impl Iterator<Item = f32>,
+    conf_threshold: &ConfThreshold,
+    calibration: &Calibration,
+) -> Vec<(usize, f32)> {
+    let score = |(class_idx, class_conf): (usize, f32)| {
+        (class_idx, calibration.apply(class_idx, class_conf * object_confidence))
+    };
+    let survived = |&(class_idx, score): &(usize, f32)| score > conf_threshold.of(class_idx);
+    match class_mode {
+        ClassMode::MultiLabel => class_confs.enumerate().map(score).filter(survived).collect(),
+        ClassMode::Best => class_confs
+            .enumerate()
+            .map(score)
+            // The first of tied classes as argmax picks, among the calibrated scores
+            .reduce(|best, class| if class.1 > best.1 { class } else { best })
+            .filter(survived)
+            .into_iter()
+            .collect(),
+        ClassMode::ObjectnessOnly => Some(score((0, 1.0))).filter(survived).into_iter().collect(),
+    }
+}
+
+/// Buffers decoding an image, kept across calls so that they are only allocated once
+#[derive(Debug, Default)]
+struct Scratch {
+    cells: Vec<(usize, usize, usize)>,
+    candidates: Candidates,
+    /// Number of candidates decoded up to the end of each layer
+    layer_ends: Vec<usize>,
+    /// x1, y1, x2 and y2 of the candidate boxes
+    boxes: [Vec<f32>; 4],
+}
+
+/// Buffers free to be taken, which grow to the number of images and chunks decoded at once
+#[derive(Debug)]
+struct Pool<T>(Mutex<Vec<T>>);
+
+impl<T: Default> Pool<T> {
+    fn take(&self) -> T {
+        self.0.lock().ok().and_then(|mut free| free.pop()).unwrap_or_default()
+    }
+
+    fn put(&self, buffers: T) {
+        if let Ok(mut free) = self.0.lock() {
+            free.push(buffers);
+        }
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self(Mutex::new(Vec::new()))
+    }
+}
+
+/// Scratch buffers of a postprocessor, reused by its decodes instead of allocating the
+/// candidates of every image and chunk anew; a clone starts with buffers of its own
+#[derive(Debug, Default)]
+struct ScratchPool {
+    images: Pool<Scratch>,
+    chunks: Pool<Candidates>,
+}
+
+impl Clone for ScratchPool {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Decoded candidates of one image
+pub struct Decoded {
+    pub boxes: DetectionBoxes,
+    /// Mask coefficients of shape (num_candidates, num_masks)
+    pub mask_coefs: Array2<f32>,
+    /// (layer, anchor, y, x) of the cell each candidate was decoded from
+    pub origins: Vec<[usize; 4]>,
+}
+
+/// Detections of one image after NMS
+pub struct Suppressed {
+    /// Detections of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+    pub detections: Array2<f32>,
+    /// Indices of the detections among the decoded candidates, empty for Weighted Box Fusion
+    pub indices: Vec<usize>,
+    /// (layer, anchor, y, x) of each detection when decoded from a grid
+    pub origins: Vec<[usize; 4]>,
+    /// Whether NMS ran out of time
+    pub timed_out: bool,
+    /// Whether the decode reached `max_boxes` candidates, dropping any further ones
+    pub overflowed: bool,
+    /// Timings and counts of the stages the image went through
+    pub stats: Stats,
+}
+
+/// Time spent in each stage of the postprocess of an image and the number of boxes through them,
+/// summed over the images of a batch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    /// Decode of the grid cells into candidates
+    pub decode: Duration,
+    /// Trim of the candidates to the `max_nms` highest scoring ones, part of the decode when
+    /// it keeps them as it goes
+    pub trim: Duration,
+    /// Suppression, including the merge of suppressed boxes if enabled
+    pub nms: Duration,
+    /// Conversion of the kept candidates into detection rows
+    pub build: Duration,
+    /// Candidates decoded above the confidence threshold
+    pub candidates: usize,
+    /// Detections kept by NMS
+    pub detections: usize,
+}
+
+impl<'a> std::iter::Sum<&'a Stats> for Stats {
+    fn sum<I: Iterator<Item = &'a Stats>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, stats| Self {
+            decode: total.decode + stats.decode,
+            trim: total.trim + stats.trim,
+            nms: total.nms + stats.nms,
+            build: total.build + stats.build,
+            candidates: total.candidates + stats.candidates,
+            detections: total.detections + stats.detections,
+        })
+    }
+}
+
+impl Suppressed {
+    /// Remove the detections not exceeding `conf_threshold`, kept by a lower threshold during
+    /// decode, returning them
+    pub fn split_low(&mut self, conf_threshold: &ConfThreshold) -> Array2<f32> {
+        let high = self
+            .detections
+            .outer_iter()
+            .map(|row| row[4] > conf_threshold.of(row[5] as usize))
+            .collect_vec();
+        let select = |high_rows: bool| {
+            let rows = (0..high.len()).filter(|&i| high[i] == high_rows).collect_vec();
+            self.detections.select(Axis(0), &rows)
+        };
+        let low = select(false);
+        self.detections = select(true);
+        // Indices and origins are empty for Weighted Box Fusion
+        self.indices = izip!(&self.indices, &high).filter(|(_, &h)| h).map(|(&i, _)| i).collect();
+        self.origins = izip!(&self.origins, &high).filter(|(_, &h)| h).map(|(&o, _)| o).collect();
+        low
+    }
+}
+
+/// Memory layout of the output tensor of each detection layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// (batch, anchor, y, x, channels)
+    Nahwc,
+    /// Channels-last (batch, y, x, anchor * channels)
+    Nhwc,
+    /// Compiler-lowered channels-last (batch, y, x / tile, padded channels, tile), the
+    /// `channels` of each anchor laid out one after the other and padded up to the alignment of
+    /// the device
+    Lowered { channels: usize, tile: usize },
+}
+
+impl Layout {
+    /// Parse a layout name, with the number of channels per anchor and the tile width describing
+    /// the \"lowered\" layout
+    pub fn parse(
+        layout: Option<&str>,
+        channels: Option<usize>,
+        tile: Option<usize>,
+    ) -> Result<Self> {
+        match (layout.map(str::to_ascii_lowercase).as_deref(), channels) {
+            (None | Some("nahwc"), None) if tile.is_none() => Ok(Self::Nahwc),
+            (Some("nhwc"), None) if tile.is_none() => Ok(Self::Nhwc),
+            (Some("lowered"), Some(channels)) if channels > 5 && tile != Some(0) => {
+                Ok(Self::Lowered { channels, tile: tile.unwrap_or(1) })
+            }
+            (Some("lowered"), _) => Err(Error::invalid(
+                "the lowered layout needs more than 5 channels per anchor and a nonzero tile",
+            )),
+            (None | Some("nahwc" | "nhwc"), _) => {
+                Err(Error::invalid("channels and tile only describe the lowered layout"))
+            }
+            (Some(other), _) => Err(Error::invalid(format!(
+                "unknown layout {other:?}, expected \"nahwc\", \"nhwc\" or \"lowered\""
+            ))),
+        }
+    }
+
+    /// Name, channels per anchor and tile width of the layout, as given to [`Layout::parse`]
+    pub fn describe(self) -> (&'static str, Option<usize>, Option<usize>) {
+        match self {
+            Self::Nahwc => ("nahwc", None, None),
+            Self::Nhwc => ("nhwc", None, None),
+            Self::Lowered { channels, tile } => ("lowered", Some(channels), Some(tile)),
+        }
+    }
+}
+
+/// Activation applied during decode to the box, objectness and class channels
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Activation {
+    /// Outputs are already activated by the model
+    #[default]
+    Identity,
+    /// Outputs are raw logits of a model exported without its final sigmoid
+    Sigmoid,
+    /// Raw logits activated by an approximation of the sigmoid, see [`fast_sigmoid`]
+    FastSigmoid,
+}
+
+impl Activation {
+    /// Activation of models exported with `raw_logits`, approximated if `fast_sigmoid`
+    pub fn parse(raw_logits: Option<bool>, fast_sigmoid: Option<bool>) -> Result<Self> {
+        match (raw_logits.unwrap_or(false), fast_sigmoid.unwrap_or(false)) {
+            (false, false) => Ok(Self::Identity),
+            (false, true) => Err(Error::invalid("fast_sigmoid requires raw_logits")),
+            (true, false) => Ok(Self::Sigmoid),
+            (true, true) => Ok(Self::FastSigmoid),
+        }
+    }
+
+    /// raw_logits and fast_sigmoid of the activation, as given to [`Activation::parse`]
+    pub fn describe(self) -> (bool, bool) {
+        (self != Self::Identity, self == Self::FastSigmoid)
+    }
+
+    #[inline]
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Self::Identity => x,
+            Self::Sigmoid => sigmoid(x),
+            Self::FastSigmoid => fast_sigmoid(x),
+        }
+    }
+
+    /// Threshold on the outputs equivalent to `threshold` on the activated outputs, so that
+    /// cells are filtered without activating the whole tensor
+    fn threshold(self, threshold: f32) -> f32 {
+        match self {
+            Self::Identity => threshold,
+            Self::Sigmoid | Self::FastSigmoid => logit(threshold),
+        }
+    }
+}
+
+/// Classes a decoded cell yields candidates of
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClassMode {
+    /// A candidate per class whose confidence times the objectness exceeds the threshold
+    #[default]
+    MultiLabel,
+    /// A single candidate of the class of the highest calibrated score, kept if it exceeds the
+    /// threshold
+    Best,
+    /// A single candidate of class 0 scored by the objectness alone, for single-class models
+    /// whose class channels are absent or ignored
+    ObjectnessOnly,
+}
+
+impl ClassMode {
+    /// Class mode of models with `objectness_only`, or `multi_label` unless it is false
+    pub fn parse(objectness_only: Option<bool>, multi_label: Option<bool>) -> Self {
+        match (objectness_only, multi_label) {
+            (Some(true), _) => Self::ObjectnessOnly,
+            (_, Some(false)) => Self::Best,
+            _ => Self::MultiLabel,
+        }
+    }
+
+    /// objectness_only and multi_label of the class mode, as given to [`ClassMode::parse`]
+    pub fn describe(self) -> (bool, bool) {
+        (self == Self::ObjectnessOnly, self != Self::Best)
+    }
+
+    /// Number of classes of a layer of `channels` channels, `num_masks` of them being mask
+    /// coefficients
+    pub fn num_classes(self, channels: usize, num_masks: usize) -> usize {
+        match self {
+            Self::MultiLabel | Self::Best => channels.saturating_sub(5 + num_masks),
+            Self::ObjectnessOnly => 1,
+        }
+    }
+
+    /// Minimum number of channels of a layer, the class channels being required unless
+    /// objectness alone is the score
+    pub fn min_channels(self, num_masks: usize) -> usize {
+        match self {
+            Self::MultiLabel | Self::Best => 6 + num_masks,
+            Self::ObjectnessOnly => 5 + num_masks,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RustPostprocessor {
     pub anchors: Array3<f32>,
+    /// Anchors in pixels, scaled by the stride of their layer
+    anchor_grid: Array3<f32>,
     pub strides: Vec<f32>,
     pub agnostic: bool,
+    /// Whether to merge the boxes a kept box suppresses into it, averaging the coordinates of
+    /// those overlapping it by more than the IoU threshold weighted by their scores
+    pub merge: bool,
+    /// Whether to clip boxes to the image bounds after NMS
+    pub clip: bool,
+    pub max_det: usize,
+    pub max_boxes: usize,
+    pub max_nms: usize,
+    pub layout: Layout,
+    /// Activation of the outputs, the identity unless the model outputs raw logits
+    pub activation: Activation,
+    pub class_mode: ClassMode,
+    /// Size and shape bounds of the decoded boxes, the others being dropped before NMS
+    pub box_filter: BoxFilter,
+    /// Calibration of the scores, applied during decode before thresholding
+    pub calibration: Calibration,
+    /// Names and category ids of the classes, which label the detections
+    pub class_table: Arc<ClassTable>,
+    /// Dedicated thread pool, the global rayon pool is used if None
+    pub pool: Option<Arc<ThreadPool>>,
+    scratch: ScratchPool,
+    plans: PlanCache,
 }
 
 impl fmt::Display for RustPostprocessor {
@@ -20,264 +701,3439 @@ impl fmt::Display for RustPostprocessor {
         let shape = self.anchors.shape();
         write!(
             f,
-            "RustPostProcessor {{ num_detection_layers: {}, num_anchor: {}, strides: {:?}, agnostic: {} }}",
-            shape[0], shape[1], self.strides, self.agnostic
+            "RustPostProcessor {{ num_detection_layers: {}, num_anchor: {}, strides: {:?}, agnostic: {}, merge: {}, clip: {}, max_det: {}, max_boxes: {}, max_nms: {}, layout: {:?}, activation: {:?}, class_mode: {:?}, box_filter: {:?}, num_threads: {} }}",
+            shape[0],
+            shape[1],
+            self.strides,
+            self.agnostic,
+            self.merge,
+            self.clip,
+            self.max_det,
+            self.max_boxes,
+            self.max_nms,
+            self.layout,
+            self.activation,
+            self.class_mode,
+            self.box_filter,
+            self.num_threads()
         )
     }
 }
 
 impl RustPostprocessor {
-    fn new(anchors: Array3<f32>, strides: Vec<f32>, agnostic: Option<bool>) -> Self {
+    /// Postprocessor of `anchors` of shape (num_layers, num_anchors, 2) and the stride of each
+    /// layer, decoding and suppressing the images of a batch in a dedicated pool of
+    /// `num_threads` threads if given
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        anchors: Array3<f32>,
+        strides: Vec<f32>,
+        agnostic: Option<bool>,
+        num_threads: Option<usize>,
+        layout: Layout,
+        clip: Option<bool>,
+        max_det: Option<usize>,
+        max_boxes: Option<usize>,
+        max_nms: Option<usize>,
+    ) -> Result<Self> {
         pub const NUM_ANCHOR_LAST: usize = 2;
-        assert_eq!(
-            anchors.shape()[2],
-            NUM_ANCHOR_LAST,
-            "anchors' last dimension must be {NUM_ANCHOR_LAST}"
-        );
-        Self { anchors, strides, agnostic: agnostic.unwrap_or(false) }
+        let &[num_layers, num_anchors, last] = anchors.shape() else { unreachable!() };
+        if last != NUM_ANCHOR_LAST {
+            return Err(Error::invalid(format!(
+                "anchors must have shape (num_layers, num_anchors, {NUM_ANCHOR_LAST}) with (width, height) pairs but got {:?}",
+                anchors.shape()
+            )));
+        }
+        if num_layers == 0 || num_anchors == 0 {
+            return Err(Error::invalid("anchors must have at least one layer and one anchor"));
+        }
+        if strides.len() != num_layers {
+            return Err(Error::invalid(format!(
+                "anchors have {num_layers} layers but {} strides are given, one per detection layer is expected",
+                strides.len()
+            )));
+        }
+        if let Some(((layer, index, _), _)) =
+            anchors.indexed_iter().find(|(_, v)| !(v.is_finite() && **v > 0.0))
+        {
+            let anchor = anchors.slice(s![layer, index, ..]).to_vec();
+            return Err(Error::invalid(format!(
+                "anchor {index} of layer {layer} must have a positive width and height but got {anchor:?}"
+            )));
+        }
+        if !strides.iter().all(|s| s.is_finite() && *s > 0.0)
+            || strides.windows(2).any(|w| w[0] >= w[1])
+        {
+            return Err(Error::invalid(format!(
+                "strides must be positive and ascending, from the finest layer to the coarsest, but got {strides:?}"
+            )));
+        }
+        for (name, limit) in [("max_det", max_det), ("max_boxes", max_boxes), ("max_nms", max_nms)]
+        {
+            if limit == Some(0) {
+                return Err(Error::invalid(format!("{name} must be positive")));
+            }
+        }
+        let pool = num_threads
+            .map(|num_threads| threads::build_pool(num_threads, None).map(Arc::new))
+            .transpose()?;
+        let mut anchor_grid = anchors.clone();
+        for (mut layer, &stride) in anchor_grid.outer_iter_mut().zip(&strides) {
+            layer *= stride;
+        }
+        Ok(Self {
+            anchors,
+            anchor_grid,
+            strides,
+            agnostic: agnostic.unwrap_or(false),
+            merge: false,
+            clip: clip.unwrap_or(false),
+            max_det: max_det.unwrap_or(DEFAULT_MAX_DET),
+            max_boxes: max_boxes.unwrap_or(DEFAULT_MAX_BOXES),
+            max_nms: max_nms.unwrap_or(DEFAULT_MAX_NMS),
+            layout,
+            activation: Activation::default(),
+            class_mode: ClassMode::default(),
+            box_filter: BoxFilter::default(),
+            calibration: Calibration::default(),
+            class_table: Arc::default(),
+            pool,
+            scratch: ScratchPool::default(),
+            plans: PlanCache::default(),
+        })
     }
 
-    fn box_decode(
+    /// Arrange the input tensors as (batch, anchor, y, x, channels) according to the layout
+    /// Tensors are viewed without copying, lowered ones indexed in their padded and tiled layout.
+    pub fn layers<'a, T: 'a>(
         &self,
-        inputs: Vec<PyReadonlyArray5<'_, f32>>,
-        conf_threshold: f32,
-    ) -> Vec<DetectionBoxes> {
-        const MAX_BOXES: usize = 10_000;
-        let mut num_rows: usize = 0;
+        arrays: impl IntoIterator<Item = ArrayViewD<'a, T>>,
+    ) -> Result<Vec<Layer<'a, T>>> {
+        let num_anchors = self.anchors.shape()[1];
+        arrays
+            .into_iter()
+            .enumerate()
+            .map(|(i, view)| {
+                let shape = view.shape().to_vec();
+                match (self.layout, shape.as_slice()) {
+                    (Layout::Nahwc, &[_, _, _, _, _]) => {
+                        Ok(Layer::nahwc(view.into_dimensionality::<Ix5>().unwrap()))
+                    }
+                    (Layout::Nhwc, &[_, _, _, c]) if c % num_anchors == 0 => {
+                        let view = view.into_dimensionality::<Ix4>().unwrap().insert_axis(Axis(4));
+                        Layer::lowered(view, num_anchors, c / num_anchors)
+                    }
+                    (Layout::Lowered { channels, tile }, &[_, _, _, _, t]) if t == tile => {
+                        let view = view.into_dimensionality::<Ix5>().unwrap();
+                        Layer::lowered(view, num_anchors, channels)
+                            .map_err(|e| Error::invalid(format!("input {i}: {e}")))
+                    }
+                    (Layout::Nahwc, _) => Err(Error::invalid(format!(
+                        "input {i} must be 5D (batch, anchor, y, x, channels) but got shape {shape:?}"
+                    ))),
+                    (Layout::Nhwc, _) => Err(Error::invalid(format!(
+                        "input {i} must be 4D (batch, y, x, {num_anchors} * channels) but got shape {shape:?}"
+                    ))),
+                    (Layout::Lowered { tile, .. }, _) => Err(Error::invalid(format!(
+                        "input {i} must be 5D (batch, y, x / {tile}, padded channels, {tile}) but got shape {shape:?}"
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>>>()
+            .and_then(|layers| self.validate_layers(layers))
+    }
+
+    /// Check the layers against the anchors and strides, their grids having to tile the same
+    /// network input
+    fn validate_layers<'a, T>(&self, layers: Vec<Layer<'a, T>>) -> Result<Vec<Layer<'a, T>>> {
+        if layers.len() != self.strides.len() {
+            return Err(Error::invalid(format!(
+                "expected {} input tensors, one per detection layer, but got {}",
+                self.strides.len(),
+                layers.len()
+            )));
+        }
+        let num_anchors = self.anchors.shape()[1];
+        let first = &layers[0];
+        let input_shape = |layer: &Layer<'_, T>, stride: f32| {
+            (layer.height() as f32 * stride, layer.width() as f32 * stride)
+        };
+        let expected = input_shape(first, self.strides[0]);
+        for (i, (layer, &stride)) in izip!(&layers, &self.strides).enumerate() {
+            if layer.num_anchors() != num_anchors {
+                return Err(Error::invalid(format!(
+                    "input {i} has {} anchors but the postprocessor has {num_anchors}",
+                    layer.num_anchors()
+                )));
+            }
+            if layer.channels() < self.class_mode.min_channels(0) {
+                let classes = match self.class_mode {
+                    ClassMode::ObjectnessOnly => "",
+                    _ => " followed by the class confidences",
+                };
+                return Err(Error::invalid(format!(
+                    "input {i} must have at least {} channels, (x, y, w, h, objectness){classes}, but got {}",
+                    self.class_mode.min_channels(0),
+                    layer.channels()
+                )));
+            }
+            if layer.batch_size() != first.batch_size() {
+                return Err(Error::invalid(format!(
+                    "input {i} has a batch of {} but input 0 has {}",
+                    layer.batch_size(),
+                    first.batch_size()
+                )));
+            }
+            if input_shape(layer, stride) != expected {
+                let (height, width) = input_shape(layer, stride);
+                return Err(Error::invalid(format!(
+                    "input {i} has a {}x{} grid, a {height}x{width} input with stride {stride}, but input 0 implies a {}x{} input; check the order of the inputs and strides",
+                    layer.height(),
+                    layer.width(),
+                    expected.0,
+                    expected.1
+                )));
+            }
+        }
+        Ok(layers)
+    }
+
+    fn num_threads(&self) -> usize {
+        self.pool
+            .as_ref()
+            .map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads())
+    }
 
-        let batch_size = inputs[0].shape()[0];
-        let mut detection_boxes: Vec<DetectionBoxes> = vec![DetectionBoxes::empty(); batch_size];
+    /// Run `op` in the dedicated thread pool if any, so that rayon parallelism of the
+    /// postprocess is bounded by it
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
 
-        for (&stride, anchors_inner_stride, inner_stride) in
-            izip!(&self.strides, self.anchors.outer_iter(), inputs)
-        {
-            for (batch_index, inner_batch) in inner_stride.as_array().outer_iter().enumerate() {
-                // Perform box_decode for one batch
-                let mut pcy: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-                let mut pcx: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-                let mut ph: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-                let mut pw: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-
-                let mut scores: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-                let mut classes: Vec<f32> = Vec::with_capacity(MAX_BOXES);
-                'outer: for (anchors, inner_anchor) in
-                    izip!(anchors_inner_stride.outer_iter(), inner_batch.outer_iter())
-                {
-                    let &[ax, ay] = (anchors.to_owned() * stride).as_slice().unwrap() else {
+    pub fn box_decode(
+        &self,
+        inputs: &[Layer<'_, f32>],
+        conf_threshold: &ConfThreshold,
+    ) -> Vec<Decoded> {
+        self.box_decode_with_masks(inputs, conf_threshold, 0)
+    }
+
+    /// Box decode of half-precision outputs given as their bit patterns, converted on the fly
+    #[cfg(feature = "python")]
+    fn box_decode_half(
+        &self,
+        inputs: &[Layer<'_, u16>],
+        format: HalfFormat,
+        conf_threshold: &ConfThreshold,
+    ) -> Vec<Decoded> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.box_decode_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    0,
+                    &|_, _, bits| format.to_f32(bits),
+                    &|_, bits| format.to_f32(bits) > objectness_threshold,
+                )
+            })
+            .collect()
+    }
+
+    /// Box decode of quantized outputs, dequantized on the fly as
+    /// [`RustPostprocessor::postprocess_quantized`] does
+    #[cfg(feature = "python")]
+    fn box_decode_quantized<T: Copy + Sync + Into<i32>>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        quantization: &[Quantization],
+        conf_threshold: &ConfThreshold,
+    ) -> Vec<Decoded> {
+        let objectness_thresholds = quantization
+            .iter()
+            .map(|q| q.quantize_threshold(4, self.objectness_threshold(conf_threshold)))
+            .collect::<Vec<_>>();
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.box_decode_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    0,
+                    &|layer, c, q| quantization[layer].dequantize(c, q),
+                    &|layer, o: T| o.into() > objectness_thresholds[layer],
+                )
+            })
+            .collect()
+    }
+
+    /// Box decode of flattened outputs of shape (batch, num_boxes, 5 + num_classes) whose grid
+    /// decode is already done by the model, leaving only the scoring and the filters of the
+    /// other decodes
+    /// Returns the candidates of each image and whether rows were dropped at `max_boxes`.
+    fn box_decode_flat(
+        &self,
+        inputs: ArrayView3<'_, f32>,
+        conf_threshold: &ConfThreshold,
+    ) -> Vec<(DetectionBoxes, bool)> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        let images = inputs.outer_iter().collect::<Vec<_>>();
+        images
+            .into_par_iter()
+            .map(|image| {
+                let mut candidates = Candidates::default();
+                let mut overflowed = false;
+                'outer: for row in image.outer_iter() {
+                    let row = match row.as_slice() {
+                        Some(row) => Cow::Borrowed(row),
+                        None => Cow::Owned(row.to_vec()),
+                    };
+                    let &[cx, cy, w, h, objectness, ref class_confs @ ..] = row.as_ref() else {
                         unreachable!()
                     };
-                    for (y, inner_y) in inner_anchor.outer_iter().enumerate() {
-                        for (x, inner_x) in inner_y.outer_iter().enumerate() {
-                            // Destruct output array
-                            let &[bx, by, bw, bh, object_confidence, ref class_confs @ ..]: &[f32] =
-                                inner_x.as_slice().expect("inner_x must be contiguous")
-                            else {
-                                unreachable!()
-                            };
-
-                            // Find candidates where `class_confidence * object_confidence > conf_threshold`
-                            let candidates = class_confs
-                                .iter()
-                                .enumerate() // enumerate to store class index for later
-                                .filter(|(_, &class_conf)| {
-                                    class_conf * object_confidence > conf_threshold
-                                })
-                                .collect_vec();
-                            if candidates.is_empty() {
-                                continue;
-                            }
-
-                            // Decode box
-                            // (feat[..., 0:2] * 2. - 0.5 + self.grid[i]) * self.stride[i]  # xy
-                            // (feat[..., 2:4] * 2) ** 2 * self.anchor_grid[i]  # wh
-                            let cy = (by * 2.0 - 0.5 + y as f32) * stride;
-                            let cx = (bx * 2.0 - 0.5 + x as f32) * stride;
-                            let h = 4.0 * bh * bh * ay;
-                            let w = 4.0 * bw * bw * ax;
-
-                            for (class_idx, class_conf) in candidates {
-                                num_rows += 1;
-                                if num_rows >= MAX_BOXES {
-                                    break 'outer;
-                                }
-
-                                pcy.push(cy);
-                                pcx.push(cx);
-                                ph.push(h);
-                                pw.push(w);
-                                scores.push(class_conf * object_confidence);
-                                classes.push(class_idx as f32);
-                            }
+                    // The boxes are decoded by the model, only the confidences being activated
+                    if objectness <= objectness_threshold {
+                        continue;
+                    }
+                    let classes = score_classes(
+                        self.class_mode,
+                        self.activation.apply(objectness),
+                        class_confs.iter().map(|&c| self.activation.apply(c)),
+                        conf_threshold,
+                        &self.calibration,
+                    );
+                    if classes.is_empty() || !self.box_filter.keeps(w, h) {
+                        continue;
+                    }
+                    for (class_idx, score) in classes {
+                        if candidates.len() >= self.max_boxes {
+                            overflowed = true;
+                            break 'outer;
                         }
+                        candidates.pcy.push(cy);
+                        candidates.pcx.push(cx);
+                        candidates.ph.push(h);
+                        candidates.pw.push(w);
+                        candidates.scores.push(score);
+                        candidates.classes.push(class_idx as f32);
                     }
                 }
-                // Convert centered boxes to LTRB boxes at once
-                let (x1, y1, x2, y2): (Array1<f32>, Array1<f32>, Array1<f32>, Array1<f32>) =
+
+                let Candidates { pcy, pcx, ph, pw, scores, classes, .. } = candidates;
+                let (x1, y1, x2, y2) =
                     centered_box_to_ltrb_bulk(&pcy.into(), &pcx.into(), &pw.into(), &ph.into());
-                detection_boxes[batch_index].append(x1, y1, x2, y2, scores.into(), classes.into());
+                (DetectionBoxes::new(x1, y1, x2, y2, scores.into(), classes.into()), overflowed)
+            })
+            .collect()
+    }
+
+    /// Box decode which also gathers the trailing `num_masks` mask coefficients of each candidate
+    /// (YOLOv5-seg), returned as a `(num_candidates, num_masks)` array per batch
+    /// Images of the batch are decoded in parallel.
+    fn box_decode_with_masks(
+        &self,
+        inputs: &[Layer<'_, f32>],
+        conf_threshold: &ConfThreshold,
+        num_masks: usize,
+    ) -> Vec<Decoded> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.box_decode_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    num_masks,
+                    &|_, _, v| v,
+                    &|_, o| o > objectness_threshold,
+                )
+            })
+            .collect()
+    }
+
+    /// Box decode of a single image of the batch
+    /// `value` converts the element of a channel of a layer into f32.
+    fn box_decode_image<T: Copy + Sync>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        batch_index: usize,
+        conf_threshold: &ConfThreshold,
+        num_masks: usize,
+        value: &(impl Fn(usize, usize, T) -> f32 + Sync),
+        survives: &(impl Fn(usize, T) -> bool + Sync),
+    ) -> Decoded {
+        let mut scratch = self.scratch.images.take();
+        self.decode_into(
+            inputs,
+            batch_index,
+            conf_threshold,
+            num_masks,
+            value,
+            survives,
+            self.max_boxes,
+            None,
+            &mut scratch,
+        );
+        let Scratch { candidates, layer_ends, .. } = &mut scratch;
+        let origins = (0..candidates.len()).map(|i| candidates.origin(layer_ends, i)).collect();
+        let mask_coefs = Array2::from_shape_vec(
+            (candidates.len(), num_masks),
+            mem::take(&mut candidates.mask_coefs),
+        )
+        .unwrap();
+        let boxes = candidates.take_boxes(Default::default());
+        self.scratch.images.put(scratch);
+        Decoded { boxes, mask_coefs, origins }
+    }
+
+    /// Decode the candidates of an image into `scratch`, keeping the `max_kept` highest scoring
+    /// ones in decode order
+    /// Class confidences never exceed 1, so cells are first filtered by their objectness over
+    /// the whole layer at once with `survives`. The surviving cells are split into chunks
+    /// decoded in parallel, whose candidates are merged in grid order.
+    /// `survives` sees the objectness before activation, so its threshold goes through
+    /// [`Activation::threshold`].
+    #[allow(clippy::too_many_arguments)]
+    fn decode_into<T: Copy + Sync>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        batch_index: usize,
+        conf_threshold: &ConfThreshold,
+        num_masks: usize,
+        value: &(impl Fn(usize, usize, T) -> f32 + Sync),
+        survives: &(impl Fn(usize, T) -> bool + Sync),
+        max_kept: usize,
+        roi: Option<&Roi>,
+        scratch: &mut Scratch,
+    ) {
+        let Scratch { cells, candidates, layer_ends, .. } = scratch;
+        candidates.reset(max_kept);
+        layer_ends.clear();
+        let plan = self.plans.get(&self.strides, inputs);
+        for (layer_index, (grid, anchors, layer)) in
+            izip!(&plan.grids, self.anchor_grid.outer_iter(), inputs).enumerate()
+        {
+            let remaining = self.max_boxes - candidates.decoded;
+            if remaining == 0 {
+                break;
             }
-        }
+            cells.clear();
+            layer.select_cells(batch_index, 4, |&o| survives(layer_index, o), cells);
+            // Mask coefficients are never activated
+            let activated = layer.channels() - num_masks;
+            let value = |c: usize, v: T| {
+                let v = value(layer_index, c, v);
+                if c < activated {
+                    self.activation.apply(v)
+                } else {
+                    v
+                }
+            };
 
-        detection_boxes
+            let decode = |candidates: &mut Candidates, cells: &[(usize, usize, usize)]| {
+                candidates.decode(
+                    layer,
+                    batch_index,
+                    cells,
+                    anchors,
+                    grid,
+                    conf_threshold,
+                    self.class_mode,
+                    num_masks,
+                    candidates.decoded + remaining,
+                    roi,
+                    &self.box_filter,
+                    &self.calibration,
+                    value,
+                )
+            };
+            if cells.len() <= CELL_CHUNK_SIZE {
+                decode(candidates, cells);
+            } else {
+                let chunks = cells
+                    .par_chunks(CELL_CHUNK_SIZE)
+                    .map(|cells| {
+                        let mut chunk = self.scratch.chunks.take();
+                        chunk.reset(max_kept);
+                        decode(&mut chunk, cells);
+                        chunk
+                    })
+                    .collect::<Vec<_>>();
+                for mut chunk in chunks {
+                    candidates.extend_limited(&mut chunk, self.max_boxes, num_masks);
+                    self.scratch.chunks.put(chunk);
+                }
+            }
+            layer_ends.push(candidates.decoded);
+        }
+        candidates.sort_by_decode_order();
+        if candidates.decoded >= self.max_boxes {
+            tracing::debug!(
+                image = batch_index,
+                max_boxes = self.max_boxes,
+                "decode reached max_boxes candidates, dropping any further ones"
+            );
+        }
+        if candidates.decoded > max_kept {
+            tracing::debug!(
+                image = batch_index,
+                candidates = candidates.decoded,
+                kept = max_kept,
+                "dropped the candidates beyond the highest scoring ones"
+            );
+        }
     }
 
     /// Non-Maximum Suppression Algorithm
     /// Faster implementation by Malisiewicz et al.
-    fn nms(
+    pub fn nms(
         boxes: &DetectionBoxes,
         iou_threshold: f32,
         epsilon: Option<f32>,
+        max_det: usize,
         agnostic: bool,
     ) -> Vec<usize> {
-        const MAX_NMS: usize = 300;
-        const MAX_WH: f32 = 7680.;
-        let epsilon = epsilon.unwrap_or(1e-5);
+        let iou_threshold = IouThreshold::Scalar(iou_threshold);
+        Self::nms_until(boxes, &iou_threshold, epsilon, max_det, agnostic, None).0
+    }
 
-        let c =
-            if agnostic { Array1::zeros(boxes.len()) } else { boxes.classes.to_owned() * MAX_WH };
-        let x1 = &boxes.x1 + &c;
-        let y1 = &boxes.y1 + &c;
-        let x2 = &boxes.x2 + &c;
-        let y2 = &boxes.y2 + &c;
+    /// Non-Maximum Suppression which stops at `deadline`, returning the boxes kept so far and
+    /// whether it stopped early
+    /// From [`grid::MIN_GRID_BOXES`] boxes, each kept box is only compared with the boxes of the
+    /// cells of a spatial grid it covers, which keeps the same boxes.
+    /// Boxes are suppressed by the IoU threshold of the class of the box kept over them.
+    pub fn nms_until(
+        boxes: &DetectionBoxes,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        max_det: usize,
+        agnostic: bool,
+        deadline: Option<Instant>,
+    ) -> (Vec<usize>, bool) {
+        let epsilon = epsilon.unwrap_or(1e-5);
 
-        let mut indices: Vec<usize> = (0..boxes.len()).collect();
-        let mut results: Vec<usize> = Vec::new();
+        // Visit in descending order of score, then ascending index
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        order.sort_unstable_by(|&i, &j| {
+            let box_score_i = unsafe { *boxes.scores.uget(i) };
+            let box_score_j = unsafe { *boxes.scores.uget(j) };
+            descending(box_score_i, box_score_j).then(i.cmp(&j))
+        });
 
-        let dx = (&x2 - &x1).map(|&v| f32::max(0., v));
-        let dy = (&y2 - &y1).map(|&v| f32::max(0., v));
-        let areas: Array1<f32> = dx * dy;
+        // Boxes gathered in visiting order and offset by class, so that the boxes following the
+        // current one are scanned contiguously
+        let offset = |i: usize| if agnostic { 0.0 } else { boxes.classes[i] * MAX_WH };
+        let gather = |coords: &Array1<f32>| -> Vec<f32> {
+            order.iter().map(|&i| unsafe { *coords.uget(i) } + offset(i)).collect()
+        };
+        let mut sorted = SortedBoxes {
+            x1: gather(&boxes.x1),
+            y1: gather(&boxes.y1),
+            x2: gather(&boxes.x2),
+            y2: gather(&boxes.y2),
+            areas: Vec::new(),
+        };
+        sorted.areas = izip!(&sorted.x1, &sorted.y1, &sorted.x2, &sorted.y2)
+            .map(|(&x1, &y1, &x2, &y2)| f32::max(0., x2 - x1) * f32::max(0., y2 - y1))
+            .collect();
 
-        // Performs unstable argmax `indices = argmax(boxes.scores)`
-        indices.sort_unstable_by(|&i, &j| {
-            let box_score_i = unsafe { boxes.scores.uget(i) };
-            let box_score_j = unsafe { boxes.scores.uget(j) };
-            box_score_i.partial_cmp(box_score_j).unwrap()
-        });
+        // Boxes sharing no cell have an intersection of 0, which only keeps them if the threshold
+        // is not negative and the epsilon keeps their IoU from being NaN
+        let grid =
+            (order.len() >= grid::MIN_GRID_BOXES && iou_threshold.min() >= 0.0 && epsilon > 0.0)
+                .then(|| SpatialGrid::new(&sorted, deadline))
+                .flatten();
 
-        while let Some(cur_idx) = indices.pop() {
-            if results.len() >= MAX_NMS {
+        let mut suppressed = vec![0u64; (order.len() + 63) / 64];
+        let mut results: Vec<usize> = Vec::new();
+        for cur in 0..order.len() {
+            if suppressed[cur / 64] >> (cur % 64) & 1 == 1 {
+                continue;
+            }
+            if results.len() >= max_det {
                 break;
             }
-            results.push(cur_idx);
-
-            let xx1: Array1<f32> = unsafe {
-                indices.iter().map(|&i| f32::max(*x1.uget(cur_idx), *x1.uget(i))).collect()
-            };
-            let yy1: Array1<f32> = unsafe {
-                indices.iter().map(|&i| f32::max(*y1.uget(cur_idx), *y1.uget(i))).collect()
-            };
-            let xx2: Array1<f32> = unsafe {
-                indices.iter().map(|&i| f32::min(*x2.uget(cur_idx), *x2.uget(i))).collect()
-            };
-            let yy2: Array1<f32> = unsafe {
-                indices.iter().map(|&i| f32::min(*y2.uget(cur_idx), *y2.uget(i))).collect()
-            };
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return (results, true);
+            }
+            results.push(order[cur]);
+            let iou_threshold = iou_threshold.of(boxes.classes[order[cur]] as usize);
+            match &grid {
+                Some(grid) => {
+                    grid.suppress_after(&sorted, cur, iou_threshold, epsilon, &mut suppressed)
+                }
+                None => sorted.suppress_after(cur, iou_threshold, epsilon, &mut suppressed),
+            }
+        }
 
-            let widths = (xx2 - xx1).mapv(|v| f32::max(0.0, v));
-            let heights = (yy2 - yy1).mapv(|v| f32::max(0.0, v));
+        (results, false)
+    }
 
-            let ious = widths * heights;
-            let cut_areas: Array1<f32> =
-                indices.iter().map(|&i| unsafe { *areas.uget(i) }).collect();
-            let overlap = &ious / (unsafe { *areas.uget(cur_idx) } + cut_areas - &ious + epsilon);
+    /// Merge-NMS of YOLOv5, replacing the coordinates of each `kept` box with the average of
+    /// the boxes overlapping it by more than the `iou_threshold` of its class, itself included,
+    /// weighted by their scores
+    pub fn merge(
+        boxes: &mut DetectionBoxes,
+        kept: &[usize],
+        iou_threshold: &IouThreshold,
+        agnostic: bool,
+    ) {
+        let iou = |i: usize, j: usize| {
+            if !agnostic && boxes.classes[i] != boxes.classes[j] {
+                return 0.0;
+            }
+            let width = f32::max(
+                0.0,
+                f32::min(boxes.x2[i], boxes.x2[j]) - f32::max(boxes.x1[i], boxes.x1[j]),
+            );
+            let height = f32::max(
+                0.0,
+                f32::min(boxes.y2[i], boxes.y2[j]) - f32::max(boxes.y1[i], boxes.y1[j]),
+            );
+            let area = |k: usize| {
+                f32::max(0.0, boxes.x2[k] - boxes.x1[k]) * f32::max(0.0, boxes.y2[k] - boxes.y1[k])
+            };
+            let intersection = width * height;
+            intersection / (area(i) + area(j) - intersection)
+        };
 
-            indices = indices
-                .into_iter()
-                .enumerate()
-                .filter_map(|(i, j)| (unsafe { *overlap.uget(i) } <= iou_threshold).then_some(j))
-                .collect();
+        // Merged from the boxes before any is replaced, as a box may overlap several kept ones
+        let merged = kept
+            .iter()
+            .map(|&i| {
+                let mut sum = [0.0; 4];
+                let mut total = 0.0;
+                let iou_threshold = iou_threshold.of(boxes.classes[i] as usize);
+                for j in (0..boxes.len()).filter(|&j| j == i || iou(i, j) > iou_threshold) {
+                    let weight = boxes.scores[j];
+                    for (sum, coords) in
+                        sum.iter_mut().zip([&boxes.x1, &boxes.y1, &boxes.x2, &boxes.y2])
+                    {
+                        *sum += weight * coords[j];
+                    }
+                    total += weight;
+                }
+                (total > 0.0).then(|| sum.map(|sum| sum / total))
+            })
+            .collect_vec();
+        for (&i, merged) in kept.iter().zip(merged) {
+            if let Some([x1, y1, x2, y2]) = merged {
+                boxes.x1[i] = x1;
+                boxes.y1[i] = y1;
+                boxes.x2[i] = x2;
+                boxes.y2[i] = y2;
+            }
         }
-
-        results
     }
 
-    /// YOLOv5 postprocess function
-    /// The vector in function input/output is for batched input/output
-    fn postprocess(
-        &self,
-        inputs: Vec<PyReadonlyArray5<'_, f32>>,
-        conf_threshold: f32,
+    /// Soft Non-Maximum Suppression Algorithm by Bodla et al.
+    /// Instead of discarding overlapping boxes, their scores are decayed according to `method`
+    /// and boxes falling below `score_threshold` are dropped.
+    /// The decayed scores are written back into `boxes.scores`.
+    /// DIoU-NMS shares this path as its overlap measure is not the plain IoU.
+    pub fn soft_nms(
+        boxes: &mut DetectionBoxes,
+        method: NmsMethod,
         iou_threshold: f32,
-        epsilon: Option<f32>,
-        agnostic: Option<bool>,
-    ) -> Vec<Array2<f32>> {
-        const MAX_NMS_INPUT: usize = 30_000;
-        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
+        score_threshold: f32,
+        max_det: usize,
+        agnostic: bool,
+    ) -> Vec<usize> {
+        let iou_threshold = IouThreshold::Scalar(iou_threshold);
+        Self::soft_nms_until(
+            boxes,
+            method,
+            &iou_threshold,
+            score_threshold,
+            max_det,
+            agnostic,
+            None,
+        )
+        .0
+    }
+
+    /// Soft Non-Maximum Suppression which stops at `deadline`, returning the boxes kept so far
+    /// and whether it stopped early, with the IoU threshold of the class of each kept box
+    pub fn soft_nms_until(
+        boxes: &mut DetectionBoxes,
+        method: NmsMethod,
+        iou_threshold: &IouThreshold,
+        score_threshold: f32,
+        max_det: usize,
+        agnostic: bool,
+        deadline: Option<Instant>,
+    ) -> (Vec<usize>, bool) {
+        let c =
+            if agnostic { Array1::zeros(boxes.len()) } else { boxes.classes.to_owned() * MAX_WH };
+        let bboxes: Vec<BoundingBox> = izip!(&boxes.x1, &boxes.y1, &boxes.x2, &boxes.y2, &c)
+            .map(|(&x1, &y1, &x2, &y2, &c)| BoundingBox {
+                px1: x1 + c,
+                py1: y1 + c,
+                px2: x2 + c,
+                py2: y2 + c,
+            })
+            .collect();
+
+        let candidates = boxes.scores.iter().copied().zip(0..boxes.len()).collect();
+        let classes = &boxes.classes;
+        let (kept, timed_out) = soft_suppress_until(
+            candidates,
+            &bboxes,
+            method,
+            |i| iou_threshold.of(classes[i] as usize),
+            score_threshold,
+            max_det,
+            deadline,
+        );
+        let indices = kept
+            .into_iter()
+            .map(|(score, i)| {
+                boxes.scores[i] = score;
+                i
+            })
+            .collect();
+        (indices, timed_out)
+    }
+
+    /// Non-Maximum Suppression with the given method, taking the fast path for hard NMS
+    /// Weighted Box Fusion creates new boxes rather than selecting some, so callers handle it
+    /// Returns the kept boxes and whether the suppression stopped early at `deadline`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn suppress(
+        boxes: &mut DetectionBoxes,
+        method: NmsMethod,
+        iou_threshold: &IouThreshold,
+        score_threshold: f32,
+        epsilon: Option<f32>,
+        max_det: usize,
+        agnostic: bool,
+        deadline: Option<Instant>,
+    ) -> (Vec<usize>, bool) {
+        match method {
+            NmsMethod::Hard => {
+                Self::nms_until(boxes, iou_threshold, epsilon, max_det, agnostic, deadline)
+            }
+            _ => Self::soft_nms_until(
+                boxes,
+                method,
+                iou_threshold,
+                score_threshold,
+                max_det,
+                agnostic,
+                deadline,
+            ),
+        }
+    }
+
+    /// YOLOv5 postprocess function
+    /// The vector in function input/output is for batched input/output, along with the indices
+    /// and grid cells of the kept candidates and whether NMS of each image ran out of
+    /// `time_limit`
+    #[allow(clippy::too_many_arguments)]
+    pub fn postprocess(
+        &self,
+        inputs: &[Layer<'_, f32>],
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+        rois: Option<&[Roi]>,
+    ) -> Vec<Suppressed> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        let agnostic = agnostic.unwrap_or(self.agnostic);
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.postprocess_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    &|_, _, v| v,
+                    &|_, o| o > objectness_threshold,
+                    iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    time_limit,
+                    rois.map(|rois| &rois[batch_index]),
+                )
+            })
+            .collect()
+    }
+
+    /// YOLOv5 postprocess function of quantized outputs
+    #[allow(clippy::too_many_arguments)]
+    pub fn postprocess_quantized<T: Copy + Sync + Into<i32>>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        quantization: &[Quantization],
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+    ) -> Vec<Array2<f32>> {
+        let agnostic = agnostic.unwrap_or(self.agnostic);
+        let objectness_thresholds = quantization
+            .iter()
+            .map(|q| q.quantize_threshold(4, self.objectness_threshold(conf_threshold)))
+            .collect::<Vec<_>>();
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.postprocess_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    &|layer, c, q| quantization[layer].dequantize(c, q),
+                    &|layer, o: T| o.into() > objectness_thresholds[layer],
+                    iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    None,
+                    None,
+                )
+                .detections
+            })
+            .collect()
+    }
+
+    /// YOLOv5 postprocess function of flattened outputs, whose confidence threshold is checked
+    /// by [`RustPostprocessor::flat_conf_threshold`]
+    pub fn postprocess_flat(
+        &self,
+        inputs: ArrayView3<'_, f32>,
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+    ) -> Vec<Suppressed> {
+        let (boxes, overflowed): (Vec<_>, Vec<_>) =
+            self.box_decode_flat(inputs, conf_threshold).into_iter().unzip();
+        let mut results = self.suppress_batch(
+            boxes,
+            conf_threshold.min(),
+            iou_threshold,
+            epsilon,
+            agnostic,
+            nms_method,
+            None,
+        );
+        for (result, overflowed) in izip!(&mut results, overflowed) {
+            result.overflowed = overflowed;
+        }
+        results
+    }
+
+    /// YOLOv5 postprocess function of float16 or bfloat16 outputs
+    #[allow(clippy::too_many_arguments)]
+    pub fn postprocess_half(
+        &self,
+        inputs: &[Layer<'_, u16>],
+        format: HalfFormat,
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+        rois: Option<&[Roi]>,
+    ) -> Vec<Suppressed> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        let agnostic = agnostic.unwrap_or(self.agnostic);
+        (0..inputs[0].batch_size())
+            .into_par_iter()
+            .map(|batch_index| {
+                self.postprocess_image(
+                    inputs,
+                    batch_index,
+                    conf_threshold,
+                    &|_, _, bits| format.to_f32(bits),
+                    &|_, bits| format.to_f32(bits) > objectness_threshold,
+                    iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    time_limit,
+                    rois.map(|rois| &rois[batch_index]),
+                )
+            })
+            .collect()
+    }
+
+    /// YOLOv5 postprocess function of a single image, tuned for latency rather than throughput
+    /// The image is decoded and suppressed directly, without the batched vectors of candidates
+    /// of the other postprocess functions.
+    #[allow(clippy::too_many_arguments)]
+    pub fn postprocess_single(
+        &self,
+        inputs: &[Layer<'_, f32>],
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+    ) -> Result<Suppressed> {
+        let objectness_threshold = self.objectness_threshold(conf_threshold);
+        Self::check_single(inputs)?;
+        Ok(self.postprocess_image(
+            inputs,
+            0,
+            conf_threshold,
+            &|_, _, v| v,
+            &|_, o| o > objectness_threshold,
+            iou_threshold,
+            epsilon,
+            agnostic.unwrap_or(self.agnostic),
+            nms_method,
+            time_limit,
+            None,
+        ))
+    }
+
+    /// Check that `inputs` hold a batch of a single image
+    fn check_single<T>(inputs: &[Layer<'_, T>]) -> Result<()> {
+        match inputs.first().map(Layer::batch_size).filter(|&b| b != 1) {
+            Some(batch_size) => Err(Error::invalid(format!(
+                "expected a batch of a single image but got {batch_size}"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Decode and suppress an image of the batch in the scratch buffers of the postprocessor,
+    /// decoding as [`RustPostprocessor::box_decode_image`] does
+    #[allow(clippy::too_many_arguments)]
+    fn postprocess_image<T: Copy + Sync>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        batch_index: usize,
+        conf_threshold: &ConfThreshold,
+        value: &(impl Fn(usize, usize, T) -> f32 + Sync),
+        survives: &(impl Fn(usize, T) -> bool + Sync),
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: bool,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+        roi: Option<&Roi>,
+    ) -> Suppressed {
+        let _span = tracing::debug_span!("postprocess_image", image = batch_index).entered();
+        let start = Instant::now();
+        let mut scratch = self.scratch.images.take();
+        tracing::trace_span!("decode").in_scope(|| {
+            self.decode_into(
+                inputs,
+                batch_index,
+                conf_threshold,
+                0,
+                value,
+                survives,
+                self.max_nms,
+                roi,
+                &mut scratch,
+            )
+        });
+        let Scratch { candidates, layer_ends, boxes, .. } = &mut scratch;
+        let decode = start.elapsed();
+        let decoded = candidates.decoded;
+        let overflowed = decoded >= self.max_boxes;
+
+        let deadline = time_limit.map(|time_limit| Instant::now() + time_limit);
+        let mut dbox = candidates.take_boxes(mem::take(boxes));
+        let mut suppressed = self.suppress_image(
+            &mut dbox,
+            conf_threshold.min(),
+            iou_threshold,
+            epsilon,
+            agnostic,
+            nms_method,
+            deadline,
+        );
+        *boxes = candidates.restore_boxes(dbox);
+        let build = Instant::now();
+        let origins =
+            suppressed.indices.iter().map(|&i| candidates.origin(layer_ends, i)).collect();
+        self.scratch.images.put(scratch);
+        suppressed.stats.decode = decode;
+        suppressed.stats.candidates = decoded;
+        suppressed.stats.build += build.elapsed();
+        Suppressed { origins, overflowed, ..suppressed }
+    }
+
+    /// Non-Maximum Suppression of the decoded boxes of each image in parallel
+    /// NMS of an image stops once it has run for `time_limit`, keeping the boxes selected so far.
+    #[allow(clippy::too_many_arguments)]
+    fn suppress_batch(
+        &self,
+        boxes: Vec<DetectionBoxes>,
+        conf_threshold: f32,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+    ) -> Vec<Suppressed> {
+        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
 
-        self.box_decode(inputs, conf_threshold)
+        boxes
             .into_par_iter()
             .map(|mut dbox| {
-                if dbox.len() > MAX_NMS_INPUT {
-                    dbox.sort_by_score_and_trim(MAX_NMS_INPUT);
+                let deadline = time_limit.map(|time_limit| Instant::now() + time_limit);
+                self.suppress_image(
+                    &mut dbox,
+                    conf_threshold,
+                    iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    deadline,
+                )
+            })
+            .collect()
+    }
+
+    /// Rescore the decoded candidates of each image with `rescore`, the `set_rescore` callback,
+    /// and suppress them, the indices and origins of the detections referring to the decoded
+    /// candidates
+    ///
+    /// The candidates whose center is outside of the ROI of their image are dropped before the
+    /// callback sees them, as decode drops them without a callback.
+    #[cfg(feature = "python")]
+    #[allow(clippy::too_many_arguments)]
+    fn suppress_rescored(
+        &self,
+        py: Python<'_>,
+        rescore: &PyAny,
+        decoded: Vec<Decoded>,
+        rois: Option<&[Roi]>,
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        time_limit: Option<Duration>,
+    ) -> PyResult<Vec<Suppressed>> {
+        let mut candidates = Vec::with_capacity(decoded.len());
+        // Number of decoded candidates of each image and of those inside its ROI, the indices
+        // of the kept ones among the decoded ones, and the origins of the decoded ones
+        let mut kept = Vec::with_capacity(decoded.len());
+        for (image, Decoded { mut boxes, origins, .. }) in decoded.into_iter().enumerate() {
+            let count = boxes.len();
+            let mut inside = (0..count).collect::<Vec<_>>();
+            if let Some(roi) = rois.map(|rois| &rois[image]) {
+                let DetectionBoxes { x1, y1, x2, y2, .. } = &boxes;
+                inside.retain(|&i| roi.contains((x1[i] + x2[i]) / 2.0, (y1[i] + y2[i]) / 2.0));
+                if inside.len() < count {
+                    boxes.select(&inside);
+                }
+            }
+            let indices = rescore_py(rescore, &mut boxes, image, conf_threshold)?;
+            let indices = indices.into_iter().map(|i| inside[i]).collect::<Vec<_>>();
+            candidates.push(boxes);
+            kept.push((count, inside.len(), indices, origins));
+        }
+        let mut results = threads::allow_threads(py, || {
+            self.install(|| {
+                self.suppress_batch(
+                    candidates,
+                    conf_threshold.min(),
+                    iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    time_limit,
+                )
+            })
+        });
+        for (r, (count, inside, indices, origins)) in izip!(&mut results, kept) {
+            r.indices = r.indices.iter().map(|&i| indices[i]).collect();
+            // Flat outputs have no grid cells to trace the candidates back to
+            if !origins.is_empty() {
+                r.origins = r.indices.iter().map(|&i| origins[i]).collect();
+            }
+            // Decode keeps the `max_boxes` highest scoring candidates
+            r.overflowed = count >= self.max_boxes;
+            r.stats.candidates = inside;
+        }
+        Ok(results)
+    }
+
+    /// Non-Maximum Suppression of the decoded boxes of an image, which are trimmed in place to
+    /// the `max_nms` highest scoring ones
+    #[allow(clippy::too_many_arguments)]
+    fn suppress_image(
+        &self,
+        dbox: &mut DetectionBoxes,
+        conf_threshold: f32,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: bool,
+        nms_method: NmsMethod,
+        deadline: Option<Instant>,
+    ) -> Suppressed {
+        let mut stats = Stats { candidates: dbox.len(), ..Stats::default() };
+        let start = Instant::now();
+        let trimmed = (dbox.len() > self.max_nms).then(|| dbox.keep_top(self.max_nms));
+        stats.trim = start.elapsed();
+        if trimmed.is_some() {
+            tracing::debug!(
+                candidates = stats.candidates,
+                max_nms = self.max_nms,
+                "dropped the candidates beyond the max_nms highest scoring ones"
+            );
+        }
+        let _span = tracing::trace_span!("nms", candidates = dbox.len()).entered();
+        let start = Instant::now();
+        if nms_method == NmsMethod::Wbf {
+            let indices = (0..dbox.len()).collect::<Vec<_>>();
+            let detections = wbf::from_rows(dbox.select_and_convert(&indices).view());
+            let fused = wbf::fuse_single(detections, iou_threshold, self.max_det);
+            stats.nms = start.elapsed();
+            stats.detections = fused.len();
+            return Suppressed {
+                detections: wbf::to_rows(&fused),
+                indices: Vec::new(),
+                origins: Vec::new(),
+                timed_out: false,
+                overflowed: false,
+                stats,
+            };
+        }
+        let (indices, timed_out) = Self::suppress(
+            dbox,
+            nms_method,
+            iou_threshold,
+            conf_threshold,
+            epsilon,
+            self.max_det,
+            agnostic,
+            deadline,
+        );
+        if timed_out {
+            tracing::debug!(kept = indices.len(), "NMS ran out of time");
+        } else if indices.len() == self.max_det {
+            tracing::debug!(max_det = self.max_det, "NMS stopped at max_det detections");
+        }
+        if self.merge {
+            Self::merge(dbox, &indices, iou_threshold, agnostic);
+        }
+        stats.nms = start.elapsed();
+        let start = Instant::now();
+        let detections = dbox.select_and_convert(&indices);
+        let indices = match trimmed {
+            Some(trimmed) => indices.into_iter().map(|i| trimmed[i]).collect(),
+            None => indices,
+        };
+        stats.build = start.elapsed();
+        stats.detections = indices.len();
+        Suppressed { detections, indices, origins: Vec::new(), timed_out, overflowed: false, stats }
+    }
+
+    /// Letterboxes of the batch, fitted into the network input inferred from the first layer
+    pub fn letterboxes<T>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+    ) -> Result<Vec<Letterbox>> {
+        let Some(first) = inputs.first() else { return Ok(Vec::new()) };
+        let input_shape =
+            (first.height() as f32 * self.strides[0], first.width() as f32 * self.strides[0]);
+        Letterbox::batch(first.batch_size(), input_shape, original_shapes, letterbox_params)
+    }
+
+    /// Threshold on the objectness of the cells below which no class of the cell can exceed its
+    /// calibrated `conf_threshold`, compared to the outputs before activation
+    fn objectness_threshold(&self, conf_threshold: &ConfThreshold) -> f32 {
+        self.activation.threshold(self.calibration.min_threshold(conf_threshold))
+    }
+
+    /// Confidence threshold checked against the classes of every layer, with the classes not in
+    /// `classes` excluded from the decode when given
+    pub fn conf_threshold<T>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        conf_threshold: &ConfThreshold,
+        classes: Option<&[usize]>,
+        num_masks: usize,
+    ) -> Result<ConfThreshold> {
+        let channels = inputs.iter().map(|input| input.channels()).collect::<Vec<_>>();
+        self.channels_conf_threshold(&channels, conf_threshold, classes, num_masks)
+    }
+
+    /// Confidence threshold of a flattened output of shape (batch, num_boxes, channels), as
+    /// [`RustPostprocessor::conf_threshold`] gives for layers, after checking its channels
+    pub fn flat_conf_threshold(
+        &self,
+        inputs: ArrayView3<'_, f32>,
+        conf_threshold: &ConfThreshold,
+        classes: Option<&[usize]>,
+    ) -> Result<ConfThreshold> {
+        let channels = inputs.shape()[2];
+        if channels < self.class_mode.min_channels(0) {
+            return Err(Error::invalid(format!(
+                "input must have at least {} channels but got shape {:?}",
+                self.class_mode.min_channels(0),
+                inputs.shape()
+            )));
+        }
+        self.channels_conf_threshold(&[channels], conf_threshold, classes, 0)
+    }
+
+    /// Confidence threshold checked against the classes of outputs of the given numbers of
+    /// channels
+    fn channels_conf_threshold(
+        &self,
+        channels: &[usize],
+        conf_threshold: &ConfThreshold,
+        classes: Option<&[usize]>,
+        num_masks: usize,
+    ) -> Result<ConfThreshold> {
+        let num_classes = |channels: usize| self.class_mode.num_classes(channels, num_masks);
+        for &channels in channels {
+            conf_threshold.validate(num_classes(channels))?;
+            self.calibration.validate(num_classes(channels))?;
+            self.class_table.validate(num_classes(channels))?;
+        }
+        match (classes, channels.first()) {
+            (Some(classes), Some(&first)) => {
+                conf_threshold.keep_classes(classes, num_classes(first))
+            }
+            _ => Ok(conf_threshold.clone()),
+        }
+    }
+
+    /// Check the IoU threshold against the classes of every layer
+    pub fn validate_iou_threshold<T>(
+        &self,
+        inputs: &[Layer<'_, T>],
+        iou_threshold: &IouThreshold,
+        num_masks: usize,
+    ) -> Result<()> {
+        for input in inputs {
+            iou_threshold.validate(self.class_mode.num_classes(input.channels(), num_masks))?;
+        }
+        Ok(())
+    }
+
+    pub fn validate_quantized(
+        &self,
+        inputs: &[Layer<'_, impl Copy>],
+        quantization: &[Quantization],
+    ) -> Result<()> {
+        if quantization.len() != inputs.len() {
+            return Err(Error::invalid(format!(
+                "expected quantization parameters for each of the {} input tensors but got {}",
+                inputs.len(),
+                quantization.len()
+            )));
+        }
+        for (i, (input, q)) in izip!(inputs, quantization).enumerate() {
+            let channels = input.channels();
+            if q.num_channels() != 1 && q.num_channels() != channels {
+                return Err(Error::invalid(format!(
+                    "input {i} has {channels} channels but {} quantization parameters",
+                    q.num_channels()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// YOLOv5-seg postprocess function
+    /// Returns detections, their mask coefficients, and binary masks at prototype resolution
+    pub fn postprocess_seg(
+        &self,
+        inputs: &[Layer<'_, f32>],
+        protos: ArrayView4<'_, f32>,
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> Vec<(Array2<f32>, Array2<f32>, Array3<u8>)> {
+        const MASK_THRESHOLD: f32 = 0.5;
+        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
+        let mask_logit_threshold = logit(MASK_THRESHOLD);
+
+        let &[_, num_masks, mask_h, mask_w] = protos.shape() else { unreachable!() };
+        // Network input resolution, inferred from the first detection layer
+        let input_h = inputs[0].height() as f32 * self.strides[0];
+        let input_w = inputs[0].width() as f32 * self.strides[0];
+        let (scale_y, scale_x) = (mask_h as f32 / input_h, mask_w as f32 / input_w);
+
+        self.box_decode_with_masks(inputs, conf_threshold, num_masks)
+            .into_par_iter()
+            .enumerate()
+            .map(|(batch_index, Decoded { boxes: mut dbox, mask_coefs: mut coefs, .. })| {
+                if dbox.len() > self.max_nms {
+                    let kept = dbox.keep_top(self.max_nms);
+                    coefs = coefs.select(Axis(0), &kept);
                 };
-                let indices = Self::nms(&dbox, iou_threshold, epsilon, agnostic);
-                dbox.select_and_convert(&indices)
+                let (indices, _) =
+                    Self::nms_until(&dbox, iou_threshold, epsilon, self.max_det, agnostic, None);
+                let detections = dbox.select_and_convert(&indices);
+                let coefs = coefs.select(Axis(0), &indices);
+
+                // masks = sigmoid(coefs @ protos) cropped by the boxes downsampled to proto size
+                let proto = protos.index_axis(Axis(0), batch_index);
+                let proto = proto.to_shape((num_masks, mask_h * mask_w)).unwrap();
+                let mask_logits = coefs.dot(&proto);
+                let masks = Array3::from_shape_fn((indices.len(), mask_h, mask_w), |(i, y, x)| {
+                    let (px, py) = (x as f32, y as f32);
+                    let inside = px >= detections[[i, 0]] * scale_x
+                        && py >= detections[[i, 1]] * scale_y
+                        && px < detections[[i, 2]] * scale_x
+                        && py < detections[[i, 3]] * scale_y;
+                    (inside && mask_logits[[i, y * mask_w + x]] > mask_logit_threshold) as u8
+                });
+
+                (detections, coefs, masks)
             })
             .collect()
     }
 }
 
+impl Postprocessor for RustPostprocessor {
+    fn default_thresholds(&self) -> Thresholds {
+        Thresholds { conf: DEFAULT_CONF_THRESHOLD, iou: DEFAULT_IOU_THRESHOLD }
+    }
+
+    fn decode(
+        &self,
+        inputs: &[ArrayViewD<'_, f32>],
+        conf_threshold: f32,
+    ) -> Result<Vec<DetectionBoxes>> {
+        let layers = self.layers(inputs.iter().map(|input| input.view()))?;
+        let conf_threshold =
+            self.conf_threshold(&layers, &ConfThreshold::Scalar(conf_threshold), None, 0)?;
+        let decoded = self.install(|| self.box_decode(&layers, &conf_threshold));
+        Ok(decoded.into_iter().map(|decoded| decoded.boxes).collect())
+    }
+
+    /// Hard NMS of the `max_nms` highest scoring candidates, merging the suppressed boxes into
+    /// the kept ones if `merge` is set
+    fn suppress(&self, candidates: &mut DetectionBoxes, thresholds: Thresholds) -> Vec<usize> {
+        if candidates.len() > self.max_nms {
+            candidates.keep_top(self.max_nms);
+        }
+        let iou_threshold = IouThreshold::Scalar(thresholds.iou);
+        let (kept, _) = Self::suppress(
+            candidates,
+            NmsMethod::Hard,
+            &iou_threshold,
+            thresholds.conf,
+            None,
+            self.max_det,
+            self.agnostic,
+            None,
+        );
+        if self.merge {
+            Self::merge(candidates, &kept, &iou_threshold, self.agnostic);
+        }
+        kept
+    }
+}
+
 /// YOLOv5 PostProcessor
 ///
 /// It takes anchors, class_names, strides as input
 ///
 /// Args:
-///     anchors (numpy.ndarray): Anchors (3D Array)
-///     strides (numpy.ndarray): Strides (1D Array)
+///     anchors (numpy.ndarray): Anchors of shape (num_layers, num_anchors, 2) in units of the
+///         stride of each layer, any number of detection layers being supported, e.g. 4 for
+///         the P6 models at 1280
+///     strides (numpy.ndarray): Stride of each detection layer (1D Array)
 ///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
-#[pyclass]
-pub struct RustPostProcessor(RustPostprocessor);
+///     num_threads (Optional[int]): Number of threads decoding and suppressing the images of a
+///         batch in parallel, default is the number of logical CPUs
+///     layout (Optional[str]): Layout of the input tensors, "nahwc" for
+///         (batch, anchor, y, x, channels), "nhwc" for channels-last
+///         (batch, y, x, anchor * channels) or "lowered" for the device-native
+///         (batch, y, x / tile, padded channels, tile) of the compiler, default is "nahwc"
+///     clip (Optional[bool]): Whether to clip boxes to the image bounds after NMS, default is
+///         False
+///     max_det (Optional[int]): Maximum number of detections per image, default is 300
+///     max_boxes (Optional[int]): Maximum number of candidates decoded per image, default is
+///         10000
+///     max_nms (Optional[int]): Maximum number of highest scoring candidates per image entering
+///         NMS, default is 30000
+///     channels (Optional[int]): Number of channels per anchor of the "lowered" layout, the
+///         anchors being padded together up to the channel alignment of the device
+///     tile (Optional[int]): Width of the x tiles of the "lowered" layout, default is 1
+///     raw_logits (Optional[bool]): Whether the model outputs raw logits, exported without its
+///         final sigmoid, which is then applied during decode to the surviving cells only,
+///         default is False
+///     fast_sigmoid (Optional[bool]): Whether to approximate the sigmoid of `raw_logits`
+///         within 2e-4, default is False
+///     objectness_only (Optional[bool]): Whether objectness alone is the score of class 0, for
+///         single-class models with a single or no class channel, default is False
+///     multi_label (Optional[bool]): Whether every class above the threshold yields a candidate,
+///         rather than the class of the highest calibrated score alone, default is True
+///     merge (Optional[bool]): Whether to merge the boxes suppressed by a kept box into it,
+///         averaging the coordinates of those overlapping it weighted by their scores, default
+///         is False
+///     min_area (Optional[float]): Minimum area of the decoded boxes in pixels of the network
+///         input, the smaller ones being dropped before NMS, default is 0
+///     min_side (Optional[float]): Minimum width and height of the decoded boxes in pixels of
+///         the network input, default is 0
+///     max_aspect_ratio (Optional[float]): Maximum ratio of the longer side of the decoded
+///         boxes to their shorter side, default is unbounded
+///     calibration (Optional[Union[str, Dict[str, Any]]]): Calibration of the scores during
+///         decode, before thresholding, as `{"temperature": t}` dividing their logits by `t` or
+///         `{"a": [...], "b": [...]}` mapping the logit `x` of class `c` to `a[c] * x + b[c]`,
+///         or a JSON document or file of either, default is none
+///     class_names (Optional[Union[str, Sequence[str]]]): Name of each class, labeling the
+///         detections, or "coco80" for the 80 COCO classes, default is none
+///     category_ids (Optional[Union[str, Sequence[int]]]): Dataset category id of each class, or
+///         "coco91" to map the 80 contiguous COCO classes to the 91 COCO category ids, default is
+///         the class index itself
+#[cfg(feature = "python")]
+#[pyclass(module = "furiosa_native_postprocess.yolo")]
+pub struct RustPostProcessor(pub(crate) Arc<RustPostprocessor>, Rescore);
+
+/// Result of `RustPostProcessor.eval_detailed`, the detections of a batch with what each was
+/// kept from and the statistics of the postprocess
+#[cfg(feature = "python")]
+#[pyclass(module = "furiosa_native_postprocess.yolo")]
+pub struct EvalResult {
+    /// Detections of each image of shape (N, 6) with rows (x1, y1, x2, y2, score, class)
+    #[pyo3(get)]
+    pub detections: Vec<Py<PyArray2<f32>>>,
+    /// Detections of each image scoring between `low_conf_threshold` and `conf_threshold`,
+    /// empty unless `low_conf_threshold` is given
+    #[pyo3(get)]
+    pub low_conf: Vec<Py<PyArray2<f32>>>,
+    /// int64 arrays of shape (N, 5) with rows (candidate, layer, anchor, y, x) giving the index
+    /// of each detection among the candidates returned by `decode_only` and the grid cell it
+    /// was decoded from, None for "wbf" whose boxes are averages of candidates
+    #[pyo3(get)]
+    pub indices: Option<Vec<Py<PyArray2<i64>>>>,
+    /// Whether the NMS of each image ran out of `time_limit_ms`
+    #[pyo3(get)]
+    pub timed_out: Vec<bool>,
+    /// Time spent in each stage summed over the images, "decode_ms", "trim_ms", "nms_ms" and
+    /// "build_ms", the wall time of the call, "total_ms", the numbers of "candidates" decoded
+    /// and of "detections" kept, and the indices of the images whose decode reached
+    /// `max_boxes`, "overflowed", which also raise a RuntimeWarning
+    #[pyo3(get)]
+    pub stats: Py<PyDict>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl EvalResult {
+    fn __len__(&self) -> usize {
+        self.detections.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("EvalResult(batch_size: {})", self.detections.len())
+    }
+}
+
+/// Threshold of the decode, lowered to the low confidence threshold of the dual-threshold output
+/// if given
+#[cfg(feature = "python")]
+fn decode_threshold(conf_threshold: &ConfThreshold, low: Option<f32>) -> ConfThreshold {
+    match low {
+        Some(low) => conf_threshold.lowered(low),
+        None => conf_threshold.clone(),
+    }
+}
+
+/// ROIs of the images of the batch mapped into the network input, one per image
+#[cfg(feature = "python")]
+fn letterboxed_rois(
+    rois: Option<Vec<Roi>>,
+    letterboxes: &[Letterbox],
+) -> PyResult<Option<Vec<Roi>>> {
+    let Some(rois) = rois else { return Ok(None) };
+    if rois.len() != letterboxes.len() {
+        return Err(PyValueError::new_err(format!(
+            "expected a ROI for each of the {} images but got {}",
+            letterboxes.len(),
+            rois.len()
+        )));
+    }
+    Ok(Some(
+        rois.iter().zip(letterboxes).map(|(roi, letterbox)| roi.letterboxed(letterbox)).collect(),
+    ))
+}
+
+/// Calibration of the constructor option, a dict or a JSON document or file of one
+#[cfg(feature = "python")]
+fn calibration_from_py(calibration: Option<&PyAny>) -> PyResult<Calibration> {
+    let Some(calibration) = calibration.filter(|c| !c.is_none()) else {
+        return Ok(Calibration::Identity);
+    };
+    match calibration.extract::<&str>() {
+        Ok(path) if std::path::Path::new(path).is_file() => {
+            Ok(Calibration::from_json(&std::fs::read_to_string(path)?)?)
+        }
+        Ok(json) => Ok(Calibration::from_json(json)?),
+        Err(_) => from_py(calibration),
+    }
+}
+
+/// Class table of the constructor options, each a list or the name of a built-in table
+#[cfg(feature = "python")]
+fn class_table(
+    class_names: Option<&PyAny>,
+    category_ids: Option<CategoryIds>,
+) -> PyResult<ClassTable> {
+    let names = match class_names.filter(|names| !names.is_none()) {
+        Some(names) => Some(match names.extract::<&str>() {
+            Ok(builtin) => ClassTable::builtin_names(builtin)?,
+            Err(_) => names.extract()?,
+        }),
+        None => None,
+    };
+    Ok(ClassTable::new(names, category_ids.map(|CategoryIds(ids)| ids))?)
+}
+
+/// Postprocess shared by `eval` and `eval_detailed`, before the conversion of its results
+#[cfg(feature = "python")]
+struct Evaluation {
+    results: Vec<Suppressed>,
+    /// Detections of each image between the low and the regular confidence thresholds
+    low_conf: Vec<Array2<f32>>,
+    /// Images whose decode reached `max_boxes`
+    overflowed: Vec<usize>,
+    /// Start of the conversion of the detections, the last stage of the postprocess
+    build: Instant,
+}
+
+#[cfg(feature = "python")]
+impl RustPostProcessor {
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+        clip: Option<bool>,
+        classes: Option<Vec<usize>>,
+        time_limit_ms: Option<f32>,
+        low_conf_threshold: Option<f32>,
+        rois: Option<Vec<Roi>>,
+        normalized: Option<bool>,
+        box_format: Option<&str>,
+    ) -> PyResult<Evaluation> {
+        let box_format = BoxFormat::parse(box_format)?;
+        let time_limit = time_limit_ms
+            .map(|ms| {
+                Duration::try_from_secs_f32(ms / 1000.0).map_err(|_| {
+                    PyValueError::new_err(format!(
+                        "time_limit_ms must be a non-negative duration but got {ms}"
+                    ))
+                })
+            })
+            .transpose()?;
+        let rescore = self.1.get(py);
+        let half_format = match inputs.first() {
+            Some(input) => HalfFormat::of(input)?,
+            None => None,
+        };
+
+        let (letterboxes, checked_threshold);
+        let mut results = match half_format {
+            None => {
+                let arrays = extract_arrays::<f32>(&inputs)?;
+                let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+                checked_threshold =
+                    self.0.conf_threshold(&inputs, &conf_threshold, classes.as_deref(), 0)?;
+                self.0.validate_iou_threshold(&inputs, &iou_threshold, 0)?;
+                let decode_threshold = decode_threshold(&checked_threshold, low_conf_threshold);
+                letterboxes = self.0.letterboxes(&inputs, original_shapes, letterbox_params)?;
+                let rois = letterboxed_rois(rois, &letterboxes)?;
+                match &rescore {
+                    None => threads::allow_threads(py, || {
+                        self.0.install(|| {
+                            self.0.postprocess(
+                                &inputs,
+                                &decode_threshold,
+                                &iou_threshold,
+                                epsilon,
+                                agnostic,
+                                nms_method,
+                                time_limit,
+                                rois.as_deref(),
+                            )
+                        })
+                    }),
+                    Some(rescore) => {
+                        let decoded = threads::allow_threads(py, || {
+                            self.0.install(|| self.0.box_decode(&inputs, &decode_threshold))
+                        });
+                        self.0.suppress_rescored(
+                            py,
+                            rescore.as_ref(py),
+                            decoded,
+                            rois.as_deref(),
+                            &decode_threshold,
+                            &iou_threshold,
+                            epsilon,
+                            agnostic,
+                            nms_method,
+                            time_limit,
+                        )?
+                    }
+                }
+            }
+            Some(format) => {
+                let arrays = extract_half_arrays(&inputs, format)?;
+                let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+                checked_threshold =
+                    self.0.conf_threshold(&inputs, &conf_threshold, classes.as_deref(), 0)?;
+                self.0.validate_iou_threshold(&inputs, &iou_threshold, 0)?;
+                let decode_threshold = decode_threshold(&checked_threshold, low_conf_threshold);
+                letterboxes = self.0.letterboxes(&inputs, original_shapes, letterbox_params)?;
+                let rois = letterboxed_rois(rois, &letterboxes)?;
+                match &rescore {
+                    None => threads::allow_threads(py, || {
+                        self.0.install(|| {
+                            self.0.postprocess_half(
+                                &inputs,
+                                format,
+                                &decode_threshold,
+                                &iou_threshold,
+                                epsilon,
+                                agnostic,
+                                nms_method,
+                                time_limit,
+                                rois.as_deref(),
+                            )
+                        })
+                    }),
+                    Some(rescore) => {
+                        let decoded = threads::allow_threads(py, || {
+                            self.0.install(|| {
+                                self.0.box_decode_half(&inputs, format, &decode_threshold)
+                            })
+                        });
+                        self.0.suppress_rescored(
+                            py,
+                            rescore.as_ref(py),
+                            decoded,
+                            rois.as_deref(),
+                            &decode_threshold,
+                            &iou_threshold,
+                            epsilon,
+                            agnostic,
+                            nms_method,
+                            time_limit,
+                        )?
+                    }
+                }
+            }
+        };
+        let build = Instant::now();
+        let overflowed = results.iter().positions(|r| r.overflowed).collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, self.0.max_boxes)?;
+        let clip = clip.unwrap_or(self.0.clip);
+        let normalized = normalized.unwrap_or(false);
+        for (r, letterbox) in izip!(&mut results, &letterboxes) {
+            letterbox.invert(&mut r.detections);
+            if clip {
+                letterbox.clip(&mut r.detections);
+            }
+            if normalized {
+                letterbox.normalize(&mut r.detections);
+            }
+            box_format.convert(&mut r.detections);
+        }
+
+        let low_conf = results
+            .iter_mut()
+            .map(|r| match low_conf_threshold {
+                Some(_) => r.split_low(&checked_threshold),
+                None => Array2::zeros((0, 6)),
+            })
+            .collect();
+        Ok(Evaluation { results, low_conf, overflowed, build })
+    }
+}
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl RustPostProcessor {
     #[new]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         anchors: PyReadonlyArray3<'_, f32>,
         strides: Vec<f32>,
         agnostic: Option<bool>,
+        num_threads: Option<usize>,
+        layout: Option<&str>,
+        clip: Option<bool>,
+        max_det: Option<usize>,
+        max_boxes: Option<usize>,
+        max_nms: Option<usize>,
+        channels: Option<usize>,
+        tile: Option<usize>,
+        raw_logits: Option<bool>,
+        fast_sigmoid: Option<bool>,
+        objectness_only: Option<bool>,
+        multi_label: Option<bool>,
+        merge: Option<bool>,
+        min_area: Option<f32>,
+        min_side: Option<f32>,
+        max_aspect_ratio: Option<f32>,
+        calibration: Option<&PyAny>,
+        class_names: Option<&PyAny>,
+        category_ids: Option<CategoryIds>,
     ) -> PyResult<Self> {
-        Ok(Self(RustPostprocessor::new(anchors.to_owned_array(), strides, agnostic)))
+        let mut postprocessor = RustPostprocessor::new(
+            anchors.to_owned_array(),
+            strides,
+            agnostic,
+            num_threads,
+            Layout::parse(layout, channels, tile)?,
+            clip,
+            max_det,
+            max_boxes,
+            max_nms,
+        )?;
+        postprocessor.activation = Activation::parse(raw_logits, fast_sigmoid)?;
+        postprocessor.class_mode = ClassMode::parse(objectness_only, multi_label);
+        postprocessor.merge = merge.unwrap_or(false);
+        postprocessor.box_filter = BoxFilter::new(min_area, min_side, max_aspect_ratio)?;
+        postprocessor.calibration = calibration_from_py(calibration)?;
+        postprocessor.class_table = Arc::new(class_table(class_names, category_ids)?);
+        Ok(Self(Arc::new(postprocessor), Rescore::default()))
     }
 
     fn __repr__(&self) -> PyResult<String> {
         Ok(format!("{:?}", self.0))
     }
 
+    /// Anchors and strides the postprocessor is reconstructed from when unpickled
+    fn __getnewargs__(&self, py: Python<'_>) -> (Py<PyArray3<f32>>, Vec<f32>) {
+        let anchors = PyArray3::from_array(py, &self.0.anchors).to_owned();
+        (anchors, self.0.strides.clone())
+    }
+
+    /// Configuration of the postprocessor, restored by `__setstate__`
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.to_dict(py)
+    }
+
+    fn __setstate__(&mut self, state: &PyDict) -> PyResult<()> {
+        *self = Self::from_dict(state)?;
+        Ok(())
+    }
+
     fn __str__(&self) -> PyResult<String> {
         Ok(format!("{}", self.0))
     }
 
+    /// Configuration of the postprocessor, the arguments of its constructor
+    ///
+    /// Returns:
+    ///     Dict[str, Any]: Anchors as nested lists, strides and the other options
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        Ok(to_py(py, &Config::from(&*self.0))?.into_ref(py).downcast()?)
+    }
+
+    /// Postprocessor of a configuration as returned by `to_dict`
+    ///
+    /// Args:
+    ///     config (Dict[str, Any]): Constructor arguments, `anchors` and `strides` being required
+    ///
+    /// Returns:
+    ///     RustPostProcessor: Postprocessor of the configuration
+    #[staticmethod]
+    pub(crate) fn from_dict(config: &PyDict) -> PyResult<Self> {
+        Ok(Self(Arc::new(from_py::<Config>(config)?.build()?), Rescore::default()))
+    }
+
+    /// Serialize the configuration of the postprocessor as JSON
+    ///
+    /// Returns:
+    ///     str: JSON object of the configuration returned by `to_dict`
+    fn to_json(&self) -> String {
+        Config::from(&*self.0).to_json()
+    }
+
+    /// Postprocessor of a configuration serialized by `to_json`
+    ///
+    /// Args:
+    ///     config (str): JSON object of the configuration
+    ///
+    /// Returns:
+    ///     RustPostProcessor: Postprocessor of the configuration
+    #[staticmethod]
+    fn from_json(config: &str) -> PyResult<Self> {
+        Ok(Self(Arc::new(Config::from_json(config)?.build()?), Rescore::default()))
+    }
+
+    /// Serialize the configuration of the postprocessor as YAML
+    ///
+    /// Returns:
+    ///     str: YAML mapping of the configuration returned by `to_dict`
+    fn to_yaml(&self) -> String {
+        Config::from(&*self.0).to_yaml()
+    }
+
+    /// Postprocessor of a configuration serialized by `to_yaml`, or of the anchors and strides of
+    /// an Ultralytics YOLOv5 model definition
+    ///
+    /// The strides of a model definition are inferred by following the downsampling of its
+    /// backbone and head up to the inputs of its `Detect` layer, and its anchors in pixels are
+    /// divided by them as the model does when built.
+    ///
+    /// Args:
+    ///     config (str): Path of a YAML file or the YAML document itself
+    ///     strides (Optional[Sequence[float]]): Strides of the detection layers of a model
+    ///         definition, taking precedence over the inferred ones
+    ///
+    /// Returns:
+    ///     RustPostProcessor: Postprocessor of the configuration
+    #[staticmethod]
+    fn from_yaml(config: &str, strides: Option<Vec<f32>>) -> PyResult<Self> {
+        let config = match Path::new(config).is_file() {
+            true => Cow::Owned(fs::read_to_string(config)?),
+            false => Cow::Borrowed(config),
+        };
+        Ok(Self(Arc::new(Config::from_yaml(&config, strides)?.build()?), Rescore::default()))
+    }
+
+    /// Register a callback rescoring the candidates of each image between decode and NMS of
+    /// every eval method but `eval_seg`, which raises while one is registered, and of `submit`,
+    /// whose worker takes the GIL to call it
+    ///
+    /// It is called as `callback(boxes, scores, classes, image)` with the boxes of shape (N, 4)
+    /// with rows (x1, y1, x2, y2) in the network input, their scores and classes of shape (N,),
+    /// and the index of the image in the batch, and returns the N new scores. Candidates whose
+    /// new score does not exceed the confidence threshold of their class, or is NaN, are
+    /// dropped. Candidates outside of `rois` are dropped before the callback sees them.
+    ///
+    /// Args:
+    ///     callback (Optional[Callable[..., Any]]): Rescoring callback, None to remove it
+    fn set_rescore(&self, callback: Option<PyObject>) {
+        self.1.set(callback);
+    }
+
     /// Evaluate the postprocess
     ///
     /// Args:
-    ///     inputs (Sequence[numpy.ndarray]): Input tensors
-    ///     conf_threshold (float): Confidence threshold
-    ///     iou_threshold (float): IoU threshold
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16,
+    ///         half-precision ones being converted during decode
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
     ///     epsilon (Optional[float]): Epsilon for numerical stability
     ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///     original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+    ///         original image, to map boxes back to it assuming centered letterbox padding
+    ///     letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+    ///         pad_y) each original image was letterboxed with, takes precedence over
+    ///         `original_shapes`, which then only bound the clipping
+    ///     clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+    ///         input if it is not given, takes precedence constructor's
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+    ///         dropped during decode, default is all classes
+    ///     time_limit_ms (Optional[float]): Time budget of the NMS of each image in milliseconds,
+    ///         after which it stops and keeps the boxes selected so far, default is unlimited
+    ///     rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+    ///         rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+    ///         letterboxed or else the network input, the candidates centered outside of it being
+    ///         dropped during decode, default is the whole image
+    ///     normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+    ///         and height of the original image, or of the network input if it is not given,
+    ///         default is False
+    ///     box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+    ///         y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+    ///         width, height), default is "xyxy"
     ///
     /// Returns:
     ///     List[numpy.ndarray]: Batched detection results
+    #[allow(clippy::too_many_arguments)]
     fn eval(
         &self,
         py: Python<'_>,
-        inputs: Vec<PyReadonlyArray5<'_, f32>>,
-        conf_threshold: f32,
-        iou_threshold: f32,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
         epsilon: Option<f32>,
         agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+        clip: Option<bool>,
+        classes: Option<Vec<usize>>,
+        time_limit_ms: Option<f32>,
+        rois: Option<Vec<Roi>>,
+        normalized: Option<bool>,
+        box_format: Option<&str>,
     ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
-        Ok(self
-            .0
-            .postprocess(inputs, conf_threshold, iou_threshold, epsilon, agnostic)
+        let evaluation = self.evaluate(
+            py,
+            inputs,
+            conf_threshold,
+            iou_threshold,
+            epsilon,
+            agnostic,
+            NmsMethod::parse(nms_method, sigma)?,
+            original_shapes,
+            letterbox_params,
+            clip,
+            classes,
+            time_limit_ms,
+            None,
+            rois,
+            normalized,
+            box_format,
+        )?;
+        Ok(evaluation
+            .results
             .into_iter()
-            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .map(|r| PyArray2::from_owned_array(py, r.detections).to_owned())
             .collect())
     }
-}
 
-pub(crate) fn yolo(m: &PyModule) -> PyResult<()> {
-    m.add_class::<RustPostProcessor>()?;
-
-    Ok(())
+    /// Evaluate the postprocess as `eval` does, also returning what each detection was kept
+    /// from, whether NMS ran out of time and the statistics of the stages
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16,
+    ///         half-precision ones being converted during decode
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///     original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+    ///         original image, to map boxes back to it assuming centered letterbox padding
+    ///     letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+    ///         pad_y) each original image was letterboxed with, takes precedence over
+    ///         `original_shapes`, which then only bound the clipping
+    ///     clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+    ///         input if it is not given, takes precedence constructor's
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+    ///         dropped during decode, default is all classes
+    ///     time_limit_ms (Optional[float]): Time budget of the NMS of each image in milliseconds,
+    ///         after which it stops and keeps the boxes selected so far, default is unlimited
+    ///     low_conf_threshold (Optional[float]): Lower confidence threshold of the candidates
+    ///         also kept through NMS but returned apart, e.g. for the second association of
+    ///         ByteTrack, default is none
+    ///     rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+    ///         rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+    ///         letterboxed or else the network input, the candidates centered outside of it being
+    ///         dropped during decode, default is the whole image
+    ///     normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+    ///         and height of the original image, or of the network input if it is not given,
+    ///         default is False
+    ///     box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+    ///         y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+    ///         width, height), default is "xyxy"
+    ///
+    /// Returns:
+    ///     EvalResult: Batched detection results with their indices, timeouts and statistics
+    #[allow(clippy::too_many_arguments)]
+    fn eval_detailed(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+        clip: Option<bool>,
+        classes: Option<Vec<usize>>,
+        time_limit_ms: Option<f32>,
+        low_conf_threshold: Option<f32>,
+        rois: Option<Vec<Roi>>,
+        normalized: Option<bool>,
+        box_format: Option<&str>,
+    ) -> PyResult<EvalResult> {
+        let start = Instant::now();
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let Evaluation { results, low_conf, overflowed, build } = self.evaluate(
+            py,
+            inputs,
+            conf_threshold,
+            iou_threshold,
+            epsilon,
+            agnostic,
+            nms_method,
+            original_shapes,
+            letterbox_params,
+            clip,
+            classes,
+            time_limit_ms,
+            low_conf_threshold,
+            rois,
+            normalized,
+            box_format,
+        )?;
+        let to_py = |array: Array2<f32>| PyArray2::from_owned_array(py, array).to_owned();
+        // Weighted Box Fusion averages boxes rather than keeping candidates
+        let indices = (nms_method != NmsMethod::Wbf).then(|| {
+            results
+                .iter()
+                .map(|r| {
+                    let rows = izip!(&r.indices, &r.origins)
+                        .flat_map(|(&i, origin)| std::iter::once(i).chain(*origin))
+                        .map(|v| v as i64)
+                        .collect::<Vec<_>>();
+                    let rows = Array2::from_shape_vec((r.indices.len(), 5), rows).unwrap();
+                    PyArray2::from_owned_array(py, rows).to_owned()
+                })
+                .collect()
+        });
+        let timed_out = results.iter().map(|r| r.timed_out).collect();
+        let mut total = results.iter().map(|r| &r.stats).sum::<Stats>();
+        let detections = results.into_iter().map(|r| to_py(r.detections)).collect();
+        total.build += build.elapsed();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let stats = PyDict::new(py);
+        stats.set_item("decode_ms", ms(total.decode))?;
+        stats.set_item("trim_ms", ms(total.trim))?;
+        stats.set_item("nms_ms", ms(total.nms))?;
+        stats.set_item("build_ms", ms(total.build))?;
+        stats.set_item("total_ms", ms(start.elapsed()))?;
+        stats.set_item("candidates", total.candidates)?;
+        stats.set_item("detections", total.detections)?;
+        stats.set_item("overflowed", overflowed)?;
+        Ok(EvalResult {
+            detections,
+            low_conf: low_conf.into_iter().map(to_py).collect(),
+            indices,
+            timed_out,
+            stats: stats.into(),
+        })
+    }
+
+    /// Submit the postprocess of a batch to the background workers and return at once, e.g. to
+    /// postprocess a batch while the next one runs on the device
+    ///
+    /// The inputs are copied, so that their buffers can be reused as soon as this returns.
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     callback (Optional[Union[Callable[[List[numpy.ndarray]], None], concurrent.futures.Future]]):
+    ///         Callable called with the results from a worker thread, or a
+    ///         `concurrent.futures.Future` or `asyncio.Future` to set them to, default is a new
+    ///         `concurrent.futures.Future`
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///     original_shapes (Optional[Sequence[Tuple[float, float]]]): (height, width) of each
+    ///         original image, to map boxes back to it assuming centered letterbox padding
+    ///     letterbox_params (Optional[Sequence[Tuple[float, float, float]]]): (scale, pad_x,
+    ///         pad_y) each original image was letterboxed with, takes precedence over
+    ///         `original_shapes`
+    ///     clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+    ///         input if it is not given, takes precedence constructor's
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+    ///         classes
+    ///     rois (Optional[Sequence[Sequence[Any]]]): Region of interest of each image, a
+    ///         rectangle (x1, y1, x2, y2) or a polygon of (x, y) vertices in the original image if
+    ///         letterboxed or else the network input, the candidates centered outside of it being
+    ///         dropped during decode, default is the whole image
+    ///     normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+    ///         and height of the original image, or of the network input if it is not given,
+    ///         default is False
+    ///     box_format (Optional[str]): Layout of the returned boxes, "xyxy" for (x1, y1, x2,
+    ///         y2), "xywh" for (x1, y1, width, height) or "cxcywh" for (center x, center y,
+    ///         width, height), default is "xyxy"
+    ///
+    /// Returns:
+    ///     Optional[concurrent.futures.Future]: The future the batched detection results of
+    ///         `eval` are set to, None if `callback` is a callable, whose exceptions are reported
+    ///         as unraisable
+    #[allow(clippy::too_many_arguments)]
+    fn submit(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        callback: Option<&PyAny>,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+        clip: Option<bool>,
+        classes: Option<Vec<usize>>,
+        rois: Option<Vec<Roi>>,
+        normalized: Option<bool>,
+        box_format: Option<&str>,
+    ) -> PyResult<PyObject> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let box_format = BoxFormat::parse(box_format)?;
+        let half_format = match inputs.first() {
+            Some(input) => HalfFormat::of(input)?,
+            None => None,
+        };
+        // Checked before submitting, so that invalid inputs raise here rather than in the future
+        let inputs = match half_format {
+            None => OwnedInputs::Float(
+                extract_arrays::<f32>(&inputs)?.iter().map(|a| a.to_owned_array()).collect(),
+            ),
+            Some(format) => OwnedInputs::Half(
+                format,
+                extract_half_arrays(&inputs, format)?.iter().map(|a| a.to_owned_array()).collect(),
+            ),
+        };
+        let (conf_threshold, letterboxes) = match &inputs {
+            OwnedInputs::Float(arrays) => self.checked(
+                arrays,
+                &conf_threshold,
+                &iou_threshold,
+                classes,
+                original_shapes,
+                letterbox_params,
+            )?,
+            OwnedInputs::Half(_, arrays) => self.checked(
+                arrays,
+                &conf_threshold,
+                &iou_threshold,
+                classes,
+                original_shapes,
+                letterbox_params,
+            )?,
+        };
+        let rois = letterboxed_rois(rois, &letterboxes)?;
+        let clip = clip.unwrap_or(self.0.clip);
+        let normalized = normalized.unwrap_or(false);
+
+        let (completion, returned) = Completion::new(py, callback)?;
+        let postprocessor = Arc::clone(&self.0);
+        let rescore = self.1.get(py);
+        let compute = move || {
+            let p = &postprocessor;
+            // The GIL is only taken on the worker to call the rescore callback
+            let suppress_rescored = |rescore: &PyObject, decoded| {
+                Python::with_gil(|py| {
+                    p.suppress_rescored(
+                        py,
+                        rescore.as_ref(py),
+                        decoded,
+                        rois.as_deref(),
+                        &conf_threshold,
+                        &iou_threshold,
+                        epsilon,
+                        agnostic,
+                        nms_method,
+                        None,
+                    )
+                })
+            };
+            let mut results = match &inputs {
+                OwnedInputs::Float(arrays) => {
+                    let inputs = p.layers(arrays.iter().map(|a| a.view()))?;
+                    match &rescore {
+                        None => p.install(|| {
+                            p.postprocess(
+                                &inputs,
+                                &conf_threshold,
+                                &iou_threshold,
+                                epsilon,
+                                agnostic,
+                                nms_method,
+                                None,
+                                rois.as_deref(),
+                            )
+                        }),
+                        Some(rescore) => {
+                            let decoded = p.install(|| p.box_decode(&inputs, &conf_threshold));
+                            suppress_rescored(rescore, decoded)?
+                        }
+                    }
+                }
+                OwnedInputs::Half(format, arrays) => {
+                    let inputs = p.layers(arrays.iter().map(|a| a.view()))?;
+                    match &rescore {
+                        None => p.install(|| {
+                            p.postprocess_half(
+                                &inputs,
+                                *format,
+                                &conf_threshold,
+                                &iou_threshold,
+                                epsilon,
+                                agnostic,
+                                nms_method,
+                                None,
+                                rois.as_deref(),
+                            )
+                        }),
+                        Some(rescore) => {
+                            let decoded =
+                                p.install(|| p.box_decode_half(&inputs, *format, &conf_threshold));
+                            suppress_rescored(rescore, decoded)?
+                        }
+                    }
+                }
+            };
+            for (r, letterbox) in izip!(&mut results, &letterboxes) {
+                letterbox.invert(&mut r.detections);
+                if clip {
+                    letterbox.clip(&mut r.detections);
+                }
+                if normalized {
+                    letterbox.normalize(&mut r.detections);
+                }
+                box_format.convert(&mut r.detections);
+            }
+            Ok(results.into_iter().map(|r| r.detections).collect::<Vec<_>>())
+        };
+        worker::submit(completion, compute, |py, detections| {
+            let detections = detections
+                .into_iter()
+                .map(|d| PyArray2::from_owned_array(py, d).to_owned())
+                .collect::<Vec<_>>();
+            Ok(detections.into_py(py))
+        });
+        Ok(returned)
+    }
+
+    /// YOLOv5 postprocess of a single image, e.g. for the SingleStream scenario of MLPerf
+    ///
+    /// The layers are decoded sequentially into buffers the postprocessor reuses across calls,
+    /// avoiding the batched bookkeeping of `eval`.
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32 with a batch of 1
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///     original_shape (Optional[Tuple[float, float]]): (height, width) of the original
+    ///         image, to map boxes back to it assuming centered letterbox padding
+    ///     letterbox_params (Optional[Tuple[float, float, float]]): (scale, pad_x, pad_y) the
+    ///         original image was letterboxed with, takes precedence over `original_shape`
+    ///     clip (Optional[bool]): Whether to clip boxes to the original image, or to the network
+    ///         input if it is not given, takes precedence constructor's
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+    ///         classes
+    ///     normalized (Optional[bool]): Whether to return the boxes in fractions of the width
+    ///         and height of the original image, or of the network input if it is not given,
+    ///         default is False
+    ///
+    /// Returns:
+    ///     PyDetectionResults: Detections of the image
+    #[allow(clippy::too_many_arguments)]
+    fn eval_single(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+        original_shape: Option<(f32, f32)>,
+        letterbox_params: Option<(f32, f32, f32)>,
+        clip: Option<bool>,
+        classes: Option<Vec<usize>>,
+        normalized: Option<bool>,
+    ) -> PyResult<PyDetectionResults> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let arrays = extract_arrays::<f32>(&inputs)?;
+        let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+        let conf_threshold =
+            self.0.conf_threshold(&inputs, &conf_threshold, classes.as_deref(), 0)?;
+        self.0.validate_iou_threshold(&inputs, &iou_threshold, 0)?;
+        let letterboxes = self.0.letterboxes(
+            &inputs,
+            original_shape.map(|shape| vec![shape]),
+            letterbox_params.map(|params| vec![params]),
+        )?;
+        let mut result = match self.1.get(py) {
+            None => threads::allow_threads(py, || {
+                self.0.postprocess_single(
+                    &inputs,
+                    &conf_threshold,
+                    &iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    None,
+                )
+            })?,
+            Some(rescore) => {
+                RustPostprocessor::check_single(&inputs)?;
+                let decoded = threads::allow_threads(py, || {
+                    self.0.install(|| self.0.box_decode(&inputs, &conf_threshold))
+                });
+                let mut results = self.0.suppress_rescored(
+                    py,
+                    rescore.as_ref(py),
+                    decoded,
+                    None,
+                    &conf_threshold,
+                    &iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    None,
+                )?;
+                results.remove(0)
+            }
+        };
+        if result.overflowed {
+            warn_overflow(py, &[0], self.0.max_boxes)?;
+        }
+        let letterbox = &letterboxes[0];
+        letterbox.invert(&mut result.detections);
+        if clip.unwrap_or(self.0.clip) {
+            letterbox.clip(&mut result.detections);
+        }
+        if normalized.unwrap_or(false) {
+            letterbox.normalize(&mut result.detections);
+        }
+        Ok(PyDetectionResults(
+            wbf::from_rows(result.detections.view()),
+            Arc::clone(&self.0.class_table),
+        ))
+    }
+
+    /// Names of classes, e.g. of the class column of the detections of `eval`
+    ///
+    /// Args:
+    ///     classes (Sequence[int]): Class indices
+    ///
+    /// Returns:
+    ///     List[Optional[str]]: Name of each class, None without class names
+    fn labels(&self, classes: Vec<usize>) -> Vec<Option<String>> {
+        classes.into_iter().map(|c| self.0.class_table.label(c).map(str::to_string)).collect()
+    }
+
+    /// Dataset category ids of classes, e.g. of the class column of the detections of `eval`
+    ///
+    /// Args:
+    ///     classes (Sequence[int]): Class indices
+    ///
+    /// Returns:
+    ///     numpy.ndarray: int64 category id of each class, the class itself without category ids
+    fn category_ids(&self, py: Python<'_>, classes: Vec<usize>) -> Py<PyArray1<i64>> {
+        PyArray1::from_iter(py, classes.into_iter().map(|c| self.0.class_table.category_id(c)))
+            .to_owned()
+    }
+
+    /// Decode the candidate boxes without suppressing them, e.g. to apply a custom NMS
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype float32, float16 or bfloat16
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, default is all
+    ///         classes
+    ///
+    /// Returns:
+    ///     List[Tuple[numpy.ndarray, numpy.ndarray, numpy.ndarray]]: Batched candidates as boxes
+    ///         of shape (N, 4) with rows (x1, y1, x2, y2), scores of shape (N,) and classes of
+    ///         shape (N,)
+    fn decode_only(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        classes: Option<Vec<usize>>,
+    ) -> PyResult<Vec<DecodedCandidates>> {
+        let half_format = match inputs.first() {
+            Some(input) => HalfFormat::of(input)?,
+            None => None,
+        };
+
+        let boxes = match half_format {
+            None => {
+                let arrays = extract_arrays::<f32>(&inputs)?;
+                let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+                let conf_threshold =
+                    self.0.conf_threshold(&inputs, &conf_threshold, classes.as_deref(), 0)?;
+                threads::allow_threads(py, || {
+                    self.0.install(|| self.0.box_decode(&inputs, &conf_threshold))
+                })
+            }
+            Some(format) => {
+                let arrays = extract_half_arrays(&inputs, format)?;
+                let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+                let conf_threshold =
+                    self.0.conf_threshold(&inputs, &conf_threshold, classes.as_deref(), 0)?;
+                threads::allow_threads(py, || {
+                    self.0.install(|| self.0.box_decode_half(&inputs, format, &conf_threshold))
+                })
+            }
+        };
+        Ok(boxes
+            .into_iter()
+            .map(|decoded| {
+                let (boxes, scores, classes) = decoded.boxes.into_parts();
+                (
+                    PyArray2::from_owned_array(py, boxes).to_owned(),
+                    PyArray1::from_owned_array(py, scores).to_owned(),
+                    PyArray1::from_owned_array(py, classes).to_owned(),
+                )
+            })
+            .collect())
+    }
+
+    /// Evaluate the postprocess of a flattened output, as exported to ONNX with the grid decode
+    /// baked in, whose confidences are scored and filtered as in `eval` before NMS
+    ///
+    /// Args:
+    ///     inputs (numpy.ndarray): Input tensor of shape (N, num_boxes, 5 + num_classes) whose
+    ///         rows are (cx, cy, w, h, objectness, class confidences...) in pixels
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///     classes (Optional[Sequence[int]]): Indices of the classes to keep, the others being
+    ///         dropped during decode, default is all classes
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    #[allow(clippy::too_many_arguments)]
+    fn eval_flat(
+        &self,
+        py: Python<'_>,
+        inputs: PyReadonlyArray3<'_, f32>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+        classes: Option<Vec<usize>>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let inputs = inputs.as_array();
+        let conf_threshold =
+            self.0.flat_conf_threshold(inputs, &conf_threshold, classes.as_deref())?;
+        iou_threshold.validate(self.0.class_mode.num_classes(inputs.shape()[2], 0))?;
+
+        let results = match self.1.get(py) {
+            None => threads::allow_threads(py, || {
+                self.0.install(|| {
+                    self.0.postprocess_flat(
+                        inputs,
+                        &conf_threshold,
+                        &iou_threshold,
+                        epsilon,
+                        agnostic,
+                        nms_method,
+                    )
+                })
+            }),
+            Some(rescore) => {
+                let (boxes, overflowed): (Vec<_>, Vec<_>) = threads::allow_threads(py, || {
+                    self.0.install(|| self.0.box_decode_flat(inputs, &conf_threshold))
+                })
+                .into_iter()
+                .unzip();
+                let decoded = boxes
+                    .into_iter()
+                    .map(|boxes| Decoded {
+                        mask_coefs: Array2::zeros((boxes.len(), 0)),
+                        boxes,
+                        origins: Vec::new(),
+                    })
+                    .collect();
+                let mut results = self.0.suppress_rescored(
+                    py,
+                    rescore.as_ref(py),
+                    decoded,
+                    None,
+                    &conf_threshold,
+                    &iou_threshold,
+                    epsilon,
+                    agnostic,
+                    nms_method,
+                    None,
+                )?;
+                for (result, overflowed) in izip!(&mut results, overflowed) {
+                    result.overflowed = overflowed;
+                }
+                results
+            }
+        };
+        let overflowed = results.iter().positions(|r| r.overflowed).collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, self.0.max_boxes)?;
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results.detections).to_owned())
+            .collect())
+    }
+
+    /// Evaluate the postprocess of quantized outputs
+    ///
+    /// Dequantization is fused into the decode, so that only the channels of cells whose
+    /// quantized objectness exceeds the quantized confidence threshold are dequantized.
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors of dtype uint8 or int8
+    ///     scales (Sequence[Union[float, Sequence[float]]]): Scale of each input tensor, or of
+    ///         each of its channels (last axis)
+    ///     zero_points (Sequence[Union[int, Sequence[int]]]): Zero point of each input tensor, or
+    ///         of each of its channels (last axis)
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///     nms_method (Optional[str]): "hard", "diou", "linear"/"gaussian" for Soft-NMS, or "wbf"
+    ///         for class-wise Weighted Box Fusion, default is "hard"
+    ///     sigma (Optional[float]): Sigma of the Gaussian Soft-NMS, default is 0.5
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results
+    #[allow(clippy::too_many_arguments)]
+    fn eval_quantized(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<&PyAny>,
+        scales: Vec<&PyAny>,
+        zero_points: Vec<&PyAny>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: Option<&str>,
+        sigma: Option<f32>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let nms_method = NmsMethod::parse(nms_method, sigma)?;
+        let quantization = izip!(scales, zero_points)
+            .map(|(scale, zero_point)| Quantization::extract(scale, zero_point))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let results = if let Ok(arrays) = extract_arrays::<u8>(&inputs) {
+            self.postprocess_quantized(
+                py,
+                &arrays,
+                &quantization,
+                &conf_threshold,
+                &iou_threshold,
+                epsilon,
+                agnostic,
+                nms_method,
+            )?
+        } else if let Ok(arrays) = extract_arrays::<i8>(&inputs) {
+            self.postprocess_quantized(
+                py,
+                &arrays,
+                &quantization,
+                &conf_threshold,
+                &iou_threshold,
+                epsilon,
+                agnostic,
+                nms_method,
+            )?
+        } else {
+            return Err(PyTypeError::new_err("inputs must be all uint8 or all int8 arrays"));
+        };
+
+        Ok(results
+            .into_iter()
+            .map(|results| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+
+    /// Evaluate the postprocess of YOLOv5-seg models
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors, ending with `num_masks` mask coefficients
+    ///     protos (numpy.ndarray): Prototype masks of shape (N, num_masks, H, W)
+    ///     conf_threshold (Union[float, Sequence[float]]): Confidence threshold, or one per class
+    ///     iou_threshold (Union[float, Sequence[float]]): IoU threshold, or one per class
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///
+    /// Returns:
+    ///     List[PySegmentationResults]: Batched segmentation results
+    #[allow(clippy::too_many_arguments)]
+    fn eval_seg(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArrayDyn<'_, f32>>,
+        protos: PyReadonlyArray4<'_, f32>,
+        conf_threshold: ConfThreshold,
+        iou_threshold: IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Vec<PySegmentationResults>> {
+        if self.1.get(py).is_some() {
+            return Err(PyValueError::new_err(
+                "eval_seg does not support a rescore callback, remove it with set_rescore(None)",
+            ));
+        }
+        let num_masks = protos.shape()[1];
+        let inputs = self.0.layers(inputs.iter().map(|a| a.as_array()))?;
+        if protos.shape()[0] != inputs[0].batch_size() {
+            return Err(PyValueError::new_err(format!(
+                "protos must have shape (N, num_masks, H, W) with the batch of the inputs, {}, but got {:?}",
+                inputs[0].batch_size(),
+                protos.shape()
+            )));
+        }
+        for (i, input) in inputs.iter().enumerate() {
+            let channels = input.channels();
+            let min_channels = self.0.class_mode.min_channels(num_masks);
+            if channels < min_channels {
+                return Err(PyValueError::new_err(format!(
+                    "input {i} must have at least {min_channels} channels with {num_masks} masks but got {channels}"
+                )));
+            }
+        }
+        let conf_threshold = self.0.conf_threshold(&inputs, &conf_threshold, None, num_masks)?;
+        self.0.validate_iou_threshold(&inputs, &iou_threshold, num_masks)?;
+
+        let protos = protos.as_array();
+        let results = threads::allow_threads(py, || {
+            self.0.install(|| {
+                self.0.postprocess_seg(
+                    &inputs,
+                    protos,
+                    &conf_threshold,
+                    &iou_threshold,
+                    epsilon,
+                    agnostic,
+                )
+            })
+        });
+        Ok(results
+            .into_iter()
+            .map(|(detections, coefficients, masks)| PySegmentationResults {
+                detections: PyArray2::from_owned_array(py, detections).to_owned(),
+                coefficients: PyArray2::from_owned_array(py, coefficients).to_owned(),
+                masks: PyArray3::from_owned_array(py, masks).to_owned(),
+            })
+            .collect())
+    }
+}
+
+/// Inputs copied out of the Python arrays for a submission, as float32 or half-precision bits
+#[cfg(feature = "python")]
+enum OwnedInputs {
+    Float(Vec<ArrayD<f32>>),
+    Half(HalfFormat, Vec<ArrayD<u16>>),
+}
+
+#[cfg(feature = "python")]
+impl RustPostProcessor {
+    /// Confidence threshold and letterboxes of a submission, checked against its inputs along
+    /// with the IoU threshold
+    #[allow(clippy::too_many_arguments)]
+    fn checked<T>(
+        &self,
+        arrays: &[ArrayD<T>],
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        classes: Option<Vec<usize>>,
+        original_shapes: Option<Vec<(f32, f32)>>,
+        letterbox_params: Option<Vec<(f32, f32, f32)>>,
+    ) -> PyResult<(ConfThreshold, Vec<Letterbox>)> {
+        let inputs = self.0.layers(arrays.iter().map(|a| a.view()))?;
+        let conf_threshold =
+            self.0.conf_threshold(&inputs, conf_threshold, classes.as_deref(), 0)?;
+        self.0.validate_iou_threshold(&inputs, iou_threshold, 0)?;
+        let letterboxes = self.0.letterboxes(&inputs, original_shapes, letterbox_params)?;
+        Ok((conf_threshold, letterboxes))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn postprocess_quantized<T: numpy::Element + Copy + Sync + Into<i32>>(
+        &self,
+        py: Python<'_>,
+        arrays: &[PyReadonlyArrayDyn<'_, T>],
+        quantization: &[Quantization],
+        conf_threshold: &ConfThreshold,
+        iou_threshold: &IouThreshold,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+        nms_method: NmsMethod,
+    ) -> PyResult<Vec<Array2<f32>>> {
+        let inputs = self.0.layers(arrays.iter().map(|a| a.as_array()))?;
+        self.0.validate_quantized(&inputs, quantization)?;
+        let conf_threshold = &self.0.conf_threshold(&inputs, conf_threshold, None, 0)?;
+        self.0.validate_iou_threshold(&inputs, iou_threshold, 0)?;
+        let Some(rescore) = self.1.get(py) else {
+            return Ok(threads::allow_threads(py, || {
+                self.0.install(|| {
+                    self.0.postprocess_quantized(
+                        &inputs,
+                        quantization,
+                        conf_threshold,
+                        iou_threshold,
+                        epsilon,
+                        agnostic,
+                        nms_method,
+                    )
+                })
+            }));
+        };
+        let decoded = threads::allow_threads(py, || {
+            self.0.install(|| self.0.box_decode_quantized(&inputs, quantization, conf_threshold))
+        });
+        let results = self.0.suppress_rescored(
+            py,
+            rescore.as_ref(py),
+            decoded,
+            None,
+            conf_threshold,
+            iou_threshold,
+            epsilon,
+            agnostic,
+            nms_method,
+            None,
+        )?;
+        Ok(results.into_iter().map(|r| r.detections).collect())
+    }
+}
+
+#[cfg(feature = "python")]
+fn extract_arrays<'py, T: numpy::Element>(
+    inputs: &[&'py PyAny],
+) -> PyResult<Vec<PyReadonlyArrayDyn<'py, T>>> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            input.extract().map_err(|_| {
+                let dtype = input.getattr("dtype").map_or_else(
+                    |_| input.get_type().name().unwrap_or("?").to_string(),
+                    |dtype| dtype.to_string(),
+                );
+                PyTypeError::new_err(format!(
+                    "input {i} must be a numpy array of the dtype of input 0, {}, but got {dtype}",
+                    T::get_dtype(input.py())
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Bit patterns of half-precision inputs, which must all be of `format`
+#[cfg(feature = "python")]
+fn extract_half_arrays<'py>(
+    inputs: &[&'py PyAny],
+    format: HalfFormat,
+) -> PyResult<Vec<PyReadonlyArrayDyn<'py, u16>>> {
+    inputs
+        .iter()
+        .map(|&input| {
+            if HalfFormat::of(input)? != Some(format) {
+                return Err(PyTypeError::new_err("inputs must share the same dtype"));
+            }
+            HalfFormat::view_bits::<IxDyn>(input)
+        })
+        .collect()
+}
+
+#[cfg(feature = "python")]
+pub(crate) fn yolo(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+    m.add_class::<EvalResult>()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2, arr3, Array5};
+
+    use super::*;
+
+    /// Anchors in pixels and strides of the YOLOv5-P6 models
+    fn p6() -> (Array3<f32>, Vec<f32>) {
+        let strides = vec![8.0, 16.0, 32.0, 64.0];
+        let mut anchors = arr3(&[
+            [[19.0, 27.0], [44.0, 40.0], [38.0, 94.0]],
+            [[96.0, 68.0], [86.0, 152.0], [180.0, 137.0]],
+            [[140.0, 301.0], [303.0, 264.0], [238.0, 542.0]],
+            [[436.0, 615.0], [739.0, 380.0], [925.0, 792.0]],
+        ]);
+        for (mut layer, &stride) in anchors.outer_iter_mut().zip(&strides) {
+            layer /= stride;
+        }
+        (anchors, strides)
+    }
+
+    fn postprocessor(anchors: Array3<f32>, strides: Vec<f32>) -> RustPostprocessor {
+        RustPostprocessor::new(
+            anchors,
+            strides,
+            None,
+            Some(1),
+            Layout::Nahwc,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Empty (1, 3, input / stride, input / stride, 7) outputs of each layer
+    fn outputs(input: usize, strides: &[f32]) -> Vec<Array5<f32>> {
+        strides
+            .iter()
+            .map(|&stride| {
+                let grid = input / stride as usize;
+                Array5::zeros((1, 3, grid, grid, 7))
+            })
+            .collect()
+    }
+
+    /// Cell of a box of the anchor size centered in it, of class 1 at 0.72 and class 0 at 0.09
+    const CELL: [f32; 7] = [0.5, 0.5, 0.5, 0.5, 0.9, 0.1, 0.8];
+
+    /// P6 postprocessor and its outputs of a 1280 input with `cell` at the first anchor of the
+    /// cell (layer, layer) of each layer
+    fn one_cell_per_layer(cell: [f32; 7]) -> (RustPostprocessor, Vec<Array5<f32>>) {
+        let (anchors, strides) = p6();
+        let mut outputs = outputs(1280, &strides);
+        for (layer, output) in outputs.iter_mut().enumerate() {
+            output.slice_mut(s![0, 0, layer, layer, ..]).assign(&arr1(&cell));
+        }
+        (postprocessor(anchors, strides), outputs)
+    }
+
+    /// Hard NMS postprocess of the first image of `outputs` with an IoU threshold of 0.45
+    fn run(
+        yolo: &RustPostprocessor,
+        outputs: &[Array5<f32>],
+        conf_threshold: f32,
+        rois: Option<&[Roi]>,
+    ) -> Suppressed {
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+        yolo.postprocess(
+            &layers,
+            &ConfThreshold::Scalar(conf_threshold),
+            &IouThreshold::Scalar(0.45),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+            rois,
+        )
+        .remove(0)
+    }
+
+    #[test]
+    fn unittest_p6_box_decode() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(1280, &strides);
+        // A centered box of the anchor size of class 1 in the cell (1, 2) of each layer
+        for (layer, output) in outputs.iter_mut().enumerate() {
+            output.slice_mut(s![0, layer % 3, 1, 2, ..]).assign(&arr1(&CELL));
+        }
+
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+        let [decoded] = &yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25))[..] else {
+            panic!("expected a single image");
+        };
+        assert_eq!(decoded.origins, [[0, 0, 1, 2], [1, 1, 1, 2], [2, 2, 1, 2], [3, 0, 1, 2]]);
+        let (anchors, _) = p6();
+        for (i, (&stride, [layer, a, ..])) in izip!(&strides, &decoded.origins).enumerate() {
+            let (w, h) = (anchors[[*layer, *a, 0]] * stride, anchors[[*layer, *a, 1]] * stride);
+            let (cx, cy) = (2.5 * stride, 1.5 * stride);
+            let boxes = &decoded.boxes;
+            let actual = [boxes.x1[i], boxes.y1[i], boxes.x2[i], boxes.y2[i]];
+            let expected = [cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0];
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-3, "{actual:?} != {expected:?}");
+            }
+            assert!((boxes.scores[i] - 0.72).abs() < 1e-6);
+            assert_eq!(boxes.classes[i], 1.0);
+        }
+    }
+
+    #[test]
+    fn unittest_single_matches_batched() {
+        let (yolo, mut outputs) = one_cell_per_layer(CELL);
+        for (layer, output) in outputs.iter_mut().enumerate() {
+            output.slice_mut(s![0, 1, layer, layer, ..]).assign(&arr1(&CELL));
+        }
+        let batched = run(&yolo, &outputs, 0.25, None);
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+        let threshold = ConfThreshold::Scalar(0.25);
+
+        // The second call runs on the buffers left by the first one
+        for _ in 0..2 {
+            let single = yolo
+                .postprocess_single(
+                    &layers,
+                    &threshold,
+                    &IouThreshold::Scalar(0.45),
+                    None,
+                    None,
+                    NmsMethod::Hard,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(single.detections, batched.detections);
+            assert_eq!(single.indices, batched.indices);
+            assert_eq!(single.origins, batched.origins);
+        }
+    }
+
+    #[test]
+    fn unittest_roi() {
+        let (yolo, outputs) = one_cell_per_layer(CELL);
+        let all = run(&yolo, &outputs, 0.25, None);
+        assert_eq!(all.detections.nrows(), outputs.len());
+
+        // Only the centers of the first two layers' candidates fall inside
+        let center = |row: usize| {
+            let d = all.detections.row(row);
+            [(d[0] + d[2]) / 2.0, (d[1] + d[3]) / 2.0]
+        };
+        let inside =
+            (0..all.detections.nrows()).filter(|&row| all.origins[row][0] < 2).collect::<Vec<_>>();
+        let [x2, y2] = inside
+            .iter()
+            .map(|&row| center(row))
+            .fold([0.0f32; 2], |[x, y], [cx, cy]| [x.max(cx), y.max(cy)]);
+        let [x2, y2] = [x2 + 1.0, y2 + 1.0];
+        let roi = Roi::rect(0.0, 0.0, x2, y2).unwrap();
+        let polygon = Roi::polygon(vec![[0.0, 0.0], [x2, 0.0], [x2, y2], [0.0, y2]]).unwrap();
+        for roi in [roi, polygon] {
+            let result = run(&yolo, &outputs, 0.25, Some(&[roi]));
+            assert_eq!(result.detections, all.detections.select(Axis(0), &inside));
+        }
+    }
+
+    #[test]
+    fn unittest_box_filter() {
+        let (mut yolo, outputs) = one_cell_per_layer(CELL);
+        let all = run(&yolo, &outputs, 0.25, None);
+        let area = |row: usize| {
+            let d = all.detections.row(row);
+            (d[2] - d[0]) * (d[3] - d[1])
+        };
+        let areas = (0..all.detections.nrows()).map(area).sorted_by(f32::total_cmp).collect_vec();
+        let min_area = (areas[1] + areas[2]) / 2.0;
+
+        yolo.box_filter = BoxFilter::new(Some(min_area), None, None).unwrap();
+        let large = (0..all.detections.nrows()).filter(|&row| area(row) >= min_area).collect_vec();
+        assert_eq!(large.len(), areas.len() - 2);
+        let filtered = run(&yolo, &outputs, 0.25, None);
+        assert_eq!(filtered.detections, all.detections.select(Axis(0), &large));
+
+        // The anchors of the cells are not square
+        yolo.box_filter = BoxFilter::new(None, None, Some(1.0)).unwrap();
+        assert_eq!(run(&yolo, &outputs, 0.25, None).detections.nrows(), 0);
+    }
+
+    #[test]
+    fn unittest_calibration() {
+        let (mut yolo, outputs) = one_cell_per_layer(CELL);
+        let postprocess = |yolo: &RustPostprocessor, threshold: f32| {
+            run(yolo, &outputs, threshold, None).detections
+        };
+        let raw = postprocess(&yolo, 0.25);
+        assert_eq!(raw.nrows(), outputs.len());
+
+        // The 0.72 scores of class 1 are calibrated to sigmoid(logit(0.72) / 2) ~ 0.616
+        yolo.calibration = Calibration::temperature(2.0).unwrap();
+        let calibrated = postprocess(&yolo, 0.6);
+        let expected = sigmoid(logit(0.72) / 2.0);
+        assert_eq!(calibrated.slice(s![.., ..4]), raw.slice(s![.., ..4]));
+        assert!(calibrated.column(4).iter().all(|&score| (score - expected).abs() < 1e-6));
+        assert_eq!(postprocess(&yolo, 0.65).nrows(), 0);
+
+        // Class 0 at 0.09 is raised above the threshold, class 1 left as is
+        yolo.calibration = Calibration::platt(vec![1.0, 1.0], vec![3.0, 0.0]).unwrap();
+        let calibrated = postprocess(&yolo, 0.25);
+        assert_eq!(calibrated.nrows(), 2 * outputs.len());
+        let class_0 = calibrated.rows().into_iter().filter(|row| row[5] == 0.0).collect_vec();
+        assert_eq!(class_0.len(), outputs.len());
+        let expected = sigmoid(logit(0.09) + 3.0);
+        assert!(class_0.iter().all(|row| (row[4] - expected).abs() < 1e-5));
+    }
+
+    #[test]
+    fn unittest_shared_across_threads() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(1280, &strides);
+        outputs[0].slice_mut(s![0, .., ..4, .., ..]).assign(&arr1(&CELL));
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+        let threshold = ConfThreshold::Scalar(0.25);
+        let expected = run(&yolo, &outputs, 0.25, None);
+
+        // Concurrent calls contend for the scratch buffers and the plan cache
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..8 {
+                        let batched = run(&yolo, &outputs, 0.25, None);
+                        assert_eq!(batched.detections, expected.detections);
+                        let single = yolo
+                            .postprocess_single(
+                                &layers,
+                                &threshold,
+                                &IouThreshold::Scalar(0.45),
+                                None,
+                                None,
+                                NmsMethod::Hard,
+                                None,
+                            )
+                            .unwrap();
+                        assert_eq!(single.detections, expected.detections);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn unittest_stats() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(1280, &strides);
+        // Two overlapping candidates of the same class, one of which is suppressed
+        let cell = arr1(&CELL);
+        outputs[0].slice_mut(s![0, 0, 1, 2, ..]).assign(&cell);
+        outputs[0].slice_mut(s![0, 1, 1, 2, ..]).assign(&cell);
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+
+        let [result] = &yolo.postprocess(
+            &layers,
+            &ConfThreshold::Scalar(0.25),
+            &IouThreshold::Scalar(0.1),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+            None,
+        )[..] else {
+            panic!("expected a single image");
+        };
+        assert_eq!((result.stats.candidates, result.stats.detections), (2, 1));
+        let total = [result.stats, result.stats].iter().sum::<Stats>();
+        assert_eq!(total.candidates, 4);
+        assert_eq!(total.decode, result.stats.decode * 2);
+    }
+
+    #[test]
+    fn unittest_chunked_decode_limit() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(1280, &strides);
+        // Every cell of the first layer is a candidate, far more than max_boxes
+        let cell = arr1(&CELL);
+        outputs[0].slice_mut(s![0, .., .., .., ..]).assign(&cell);
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+
+        // The second decode runs on the buffers left by the first one
+        for _ in 0..2 {
+            let [decoded] = &yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25))[..] else {
+                panic!("expected a single image");
+            };
+            assert_eq!(decoded.boxes.len(), DEFAULT_MAX_BOXES);
+            let grid_order = (0..DEFAULT_MAX_BOXES).map(|i| [0, 0, i / 160, i % 160]);
+            assert!(decoded.origins.iter().copied().eq(grid_order));
+        }
+    }
+
+    #[test]
+    fn unittest_top_k_decode() {
+        let (anchors, strides) = p6();
+        let mut yolo = postprocessor(anchors, strides.clone());
+        yolo.max_nms = 100;
+        let mut outputs = outputs(1280, &strides);
+        // Scores scattered over the grid of the first layer, with ties
+        for ((_, _, y, x, c), v) in outputs[0].indexed_iter_mut() {
+            *v = match c {
+                4 => 0.9,
+                6 => ((y * 160 + x) * 7919 % 1000) as f32 / 1000.0,
+                _ => 0.5,
+            };
+        }
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+        let conf_threshold = ConfThreshold::Scalar(0.25);
+
+        // Kept while decoding, as trimmed after a full decode
+        let [fused] = &yolo.postprocess(
+            &layers,
+            &conf_threshold,
+            &IouThreshold::Scalar(0.45),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+            None,
+        )[..] else {
+            panic!("expected a single image");
+        };
+        let [decoded] = &yolo.box_decode(&layers, &conf_threshold)[..] else { unreachable!() };
+        let [trimmed] = &yolo.suppress_batch(
+            vec![decoded.boxes.clone()],
+            0.25,
+            &IouThreshold::Scalar(0.45),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+        )[..] else {
+            unreachable!()
+        };
+        assert!(fused.stats.candidates > yolo.max_nms);
+        assert_eq!(fused.detections, trimmed.detections);
+        let origins = trimmed.indices.iter().map(|&i| decoded.origins[i]).collect_vec();
+        assert_eq!(fused.origins, origins);
+    }
+
+    #[test]
+    fn unittest_overflow_per_image() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = strides
+            .iter()
+            .map(|&stride| {
+                let grid = 1280 / stride as usize;
+                Array5::zeros((2, 3, grid, grid, 7))
+            })
+            .collect::<Vec<_>>();
+        // The first image has far more candidates than max_boxes, the second a single one
+        let cell = arr1(&CELL);
+        outputs[0].slice_mut(s![0, .., .., .., ..]).assign(&cell);
+        outputs[1].slice_mut(s![1, 0, 3, 3, ..]).assign(&cell);
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+
+        let results = yolo.postprocess(
+            &layers,
+            &ConfThreshold::Scalar(0.25),
+            &IouThreshold::Scalar(0.45),
+            None,
+            None,
+            NmsMethod::Hard,
+            None,
+            None,
+        );
+        assert_eq!(results.iter().map(|r| r.overflowed).collect_vec(), [true, false]);
+        assert_eq!(results[0].stats.candidates, DEFAULT_MAX_BOXES);
+        assert_eq!(results[1].detections.nrows(), 1);
+    }
+
+    #[test]
+    fn unittest_raw_logits() {
+        let (anchors, strides) = p6();
+        let decode = |activation: Activation, cell: &[f32], background: f32| {
+            let mut yolo = postprocessor(anchors.clone(), strides.clone());
+            yolo.activation = activation;
+            let mut outputs = outputs(640, &strides);
+            for output in &mut outputs {
+                output.fill(background);
+            }
+            outputs[1].slice_mut(s![0, 2, 3, 4, ..]).assign(&Array1::from(cell.to_vec()));
+            let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+            yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25)).remove(0)
+        };
+
+        let expected = decode(Activation::Identity, &CELL, 0.0);
+        let logits = CELL.map(logit);
+        for activation in [Activation::Sigmoid, Activation::FastSigmoid] {
+            let decoded = decode(activation, &logits, -10.0);
+            assert_eq!(decoded.origins, expected.origins);
+            for (a, b) in izip!(&decoded.boxes.x1, &expected.boxes.x1) {
+                assert!((a - b).abs() < 1e-2, "{activation:?}: {a} != {b}");
+            }
+            for (a, b) in izip!(&decoded.boxes.scores, &expected.boxes.scores) {
+                assert!((a - b).abs() < 1e-3, "{activation:?}: {a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn unittest_objectness_only() {
+        let (anchors, strides) = p6();
+        let mut yolo = postprocessor(anchors, strides.clone());
+        yolo.class_mode = ClassMode::ObjectnessOnly;
+        // Without class channels and with a single one, whose confidence is ignored
+        for cell in [&[0.5, 0.5, 0.5, 0.5, 0.9][..], &[0.5, 0.5, 0.5, 0.5, 0.9, 0.1]] {
+            let mut outputs = strides
+                .iter()
+                .map(|&stride| {
+                    let grid = 640 / stride as usize;
+                    Array5::<f32>::zeros((1, 3, grid, grid, cell.len()))
+                })
+                .collect_vec();
+            outputs[1].slice_mut(s![0, 2, 3, 4, ..]).assign(&Array1::from(cell.to_vec()));
+            let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+            let [decoded] = &yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25))[..] else {
+                panic!("expected a single image");
+            };
+            assert_eq!(decoded.boxes.scores.to_vec(), [0.9]);
+            assert_eq!(decoded.boxes.classes.to_vec(), [0.0]);
+        }
+
+        // Class channels remain required otherwise
+        yolo.class_mode = ClassMode::MultiLabel;
+        let outputs = strides
+            .iter()
+            .map(|&stride| {
+                Array5::<f32>::zeros((1, 3, 640 / stride as usize, 640 / stride as usize, 5))
+            })
+            .collect_vec();
+        assert!(yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).is_err());
+    }
+
+    #[test]
+    fn unittest_best_class() {
+        let (anchors, strides) = p6();
+        let mut yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(640, &strides);
+        let cell = Array1::from(vec![0.5, 0.5, 0.5, 0.5, 0.9, 0.6, 0.8]);
+        outputs[1].slice_mut(s![0, 2, 3, 4, ..]).assign(&cell);
+        let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+
+        let decoded = yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25)).remove(0);
+        assert_eq!(decoded.boxes.classes.to_vec(), [0.0, 1.0]);
+
+        yolo.class_mode = ClassMode::Best;
+        let decoded = yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25)).remove(0);
+        assert_eq!(decoded.boxes.classes.to_vec(), [1.0]);
+        assert!((decoded.boxes.scores[0] - 0.72).abs() < 1e-6);
+        // The best class alone is held against its threshold
+        let thresholds = ConfThreshold::PerClass(vec![0.25, 0.75]);
+        assert!(yolo.box_decode(&layers, &thresholds).remove(0).boxes.scores.is_empty());
+
+        // The best class is the one of the highest calibrated score, 0.54 of class 0 being
+        // raised above the 0.72 of class 1
+        yolo.calibration = Calibration::Platt { a: vec![1.0, 1.0], b: vec![2.0, 0.0] };
+        let decoded = yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25)).remove(0);
+        assert_eq!(decoded.boxes.classes.to_vec(), [0.0]);
+        assert!((decoded.boxes.scores[0] - sigmoid(logit(0.54) + 2.0)).abs() < 1e-6);
+        // and a cell survives if that score exceeds the threshold of its class
+        let thresholds = ConfThreshold::PerClass(vec![0.8, 0.99]);
+        let decoded = yolo.box_decode(&layers, &thresholds).remove(0);
+        assert_eq!(decoded.boxes.classes.to_vec(), [0.0]);
+    }
+
+    #[test]
+    fn unittest_nms() {
+        let boxes = DetectionBoxes::new(
+            Array1::from(vec![0.0, 1.0, 0.0, 20.0, 0.5, 40.0]),
+            Array1::from(vec![0.0, 1.0, 0.0, 20.0, 0.5, 40.0]),
+            Array1::from(vec![10.0, 11.0, 10.0, 30.0, 10.5, 50.0]),
+            Array1::from(vec![10.0, 11.0, 10.0, 30.0, 10.5, 50.0]),
+            Array1::from(vec![0.9, 0.8, 0.7, 0.6, 0.9, 0.6]),
+            Array1::from(vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0]),
+        );
+        // Ties in score are visited in index order, and boxes of other classes never overlap
+        assert_eq!(RustPostprocessor::nms(&boxes, 0.45, None, 300, false), [0, 2, 3, 5]);
+        assert_eq!(RustPostprocessor::nms(&boxes, 0.45, None, 300, true), [0, 3, 5]);
+        assert_eq!(RustPostprocessor::nms(&boxes, 0.45, None, 2, false), [0, 2]);
+        assert_eq!(RustPostprocessor::nms(&boxes, 0.99, None, 300, true), [0, 4, 1, 3, 5]);
+
+        // Boxes are suppressed by the threshold of the class of the box kept over them
+        let nms = |thresholds: Vec<f32>, agnostic: bool| {
+            let iou_threshold = IouThreshold::PerClass(thresholds);
+            RustPostprocessor::nms_until(&boxes, &iou_threshold, None, 300, agnostic, None).0
+        };
+        assert_eq!(nms(vec![0.99, 0.45], false), [0, 4, 1, 2, 3, 5]);
+        assert_eq!(nms(vec![0.45, 0.99], true), [0, 3, 5]);
+        assert_eq!(nms(vec![0.99, 0.45], true), [0, 4, 1, 3, 5]);
+    }
+
+    #[test]
+    fn unittest_split_low() {
+        let mut suppressed = Suppressed {
+            detections: arr2(&[
+                [0.0, 0.0, 1.0, 1.0, 0.9, 0.0],
+                [0.0, 0.0, 1.0, 1.0, 0.3, 0.0],
+                [0.0, 0.0, 1.0, 1.0, 0.3, 1.0],
+            ]),
+            indices: vec![4, 7, 9],
+            origins: vec![[0; 4], [1; 4], [2; 4]],
+            timed_out: false,
+            overflowed: false,
+            stats: Stats::default(),
+        };
+        let conf_threshold = ConfThreshold::PerClass(vec![0.5, 0.25]);
+        let low = suppressed.split_low(&conf_threshold);
+        assert_eq!(low.column(4).to_vec(), [0.3]);
+        assert_eq!(suppressed.detections.column(5).to_vec(), [0.0, 1.0]);
+        assert_eq!(suppressed.indices, [4, 9]);
+        assert_eq!(suppressed.origins, [[0; 4], [2; 4]]);
+        // Excluded classes stay excluded when lowered
+        let excluded = ConfThreshold::PerClass(vec![0.5, f32::INFINITY]);
+        assert_eq!(excluded.lowered(0.1), ConfThreshold::PerClass(vec![0.1, f32::INFINITY]));
+    }
+
+    #[test]
+    fn unittest_merge_nms() {
+        let mut boxes = DetectionBoxes::new(
+            Array1::from(vec![0.0, 2.0, 0.0, 40.0]),
+            Array1::from(vec![0.0, 0.0, 0.0, 40.0]),
+            Array1::from(vec![10.0, 12.0, 10.0, 50.0]),
+            Array1::from(vec![10.0, 10.0, 10.0, 50.0]),
+            Array1::from(vec![0.6, 0.2, 0.9, 0.5]),
+            Array1::from(vec![0.0, 0.0, 1.0, 0.0]),
+        );
+        let kept = RustPostprocessor::nms(&boxes, 0.45, None, 300, false);
+        assert_eq!(kept, [2, 0, 3]);
+        RustPostprocessor::merge(&mut boxes, &kept, &IouThreshold::Scalar(0.45), false);
+        // Box 1 is merged into box 0 by a quarter, boxes of other classes are left alone
+        for (coords, expected) in [
+            (&boxes.x1, [0.5, 2.0, 0.0, 40.0]),
+            (&boxes.x2, [10.5, 12.0, 10.0, 50.0]),
+            (&boxes.y2, [10.0, 10.0, 10.0, 50.0]),
+        ] {
+            for (a, b) in izip!(coords, expected) {
+                assert!((a - b).abs() < 1e-5, "{coords} != {expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn unittest_dynamic_input_shapes() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        for input in [640, 1280, 640, 960] {
+            let mut outputs = outputs(input, &strides);
+            let cell = arr1(&CELL);
+            let last = input / 8 - 1;
+            outputs[0].slice_mut(s![0, 0, last, last, ..]).assign(&cell);
+            let layers = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap();
+            let [decoded] = &yolo.box_decode(&layers, &ConfThreshold::Scalar(0.25))[..] else {
+                panic!("expected a single image");
+            };
+            let center = (decoded.boxes.x1[0] + decoded.boxes.x2[0]) / 2.0;
+            assert!((center - (last as f32 + 0.5) * 8.0).abs() < 1e-3, "{center} at {input}");
+        }
+        assert_eq!(yolo.plans.0.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn unittest_p6_input_count_mismatch() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        // The 3 outputs of a P5 model given to a P6 postprocessor
+        let outputs = outputs(1280, &strides[..3]);
+        let err = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap_err();
+        assert!(err.to_string().contains("expected 4 input tensors"), "{err}");
+
+        let (anchors, _) = p6();
+        let err = RustPostprocessor::new(
+            anchors,
+            strides[..3].to_vec(),
+            None,
+            Some(1),
+            Layout::Nahwc,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("4 layers but 3 strides"), "{err}");
+    }
+
+    #[test]
+    fn unittest_p6_grid_mismatch() {
+        let (anchors, strides) = p6();
+        let yolo = postprocessor(anchors, strides.clone());
+        let mut outputs = outputs(1280, &strides);
+        // The P6 output of a 640 input
+        outputs[3] = Array5::zeros((1, 3, 10, 10, 7));
+        let err = yolo.layers(outputs.iter().map(|o| o.view().into_dyn())).unwrap_err();
+        assert!(err.to_string().contains("input 3 has a 10x10 grid"), "{err}");
+    }
+
+    #[test]
+    fn unittest_flat() {
+        let (anchors, strides) = p6();
+        // A large box of class 1, a small one of class 0 and one below the threshold
+        let rows = arr3(&[[
+            [100.0, 100.0, 20.0, 20.0, 0.9, 0.1, 0.8],
+            [300.0, 300.0, 2.0, 2.0, 0.9, 0.8, 0.1],
+            [500.0, 500.0, 20.0, 20.0, 0.2, 0.5, 0.5],
+        ]]);
+        let run = |yolo: &RustPostprocessor, inputs: &Array3<f32>, classes: Option<&[usize]>| {
+            let threshold =
+                yolo.flat_conf_threshold(inputs.view(), &ConfThreshold::Scalar(0.25), classes)?;
+            let iou_threshold = IouThreshold::Scalar(0.45);
+            let mut results = yolo.postprocess_flat(
+                inputs.view(),
+                &threshold,
+                &iou_threshold,
+                None,
+                None,
+                NmsMethod::Hard,
+            );
+            Ok::<_, Error>(results.remove(0))
+        };
+        let classes = |result: &Suppressed| result.detections.column(5).to_vec();
+
+        let mut yolo = postprocessor(anchors, strides);
+        let expected = run(&yolo, &rows, None).unwrap();
+        assert_eq!(classes(&expected), [1.0, 0.0]);
+        assert!(!expected.overflowed);
+        assert_eq!(classes(&run(&yolo, &rows, Some(&[0])).unwrap()), [0.0]);
+
+        // The confidences of raw logits are activated, but not the decoded boxes
+        let mut logits = rows.clone();
+        logits.slice_mut(s![.., .., 4..]).mapv_inplace(logit);
+        yolo.activation = Activation::Sigmoid;
+        let activated = run(&yolo, &logits, None).unwrap();
+        for (a, b) in izip!(&activated.detections, &expected.detections) {
+            assert!((a - b).abs() < 1e-4, "{a} != {b}");
+        }
+        yolo.activation = Activation::Identity;
+
+        yolo.box_filter = BoxFilter::new(None, Some(5.0), None).unwrap();
+        assert_eq!(classes(&run(&yolo, &rows, None).unwrap()), [1.0]);
+        yolo.box_filter = BoxFilter::default();
+
+        yolo.max_boxes = 1;
+        let overflowed = run(&yolo, &rows, None).unwrap();
+        assert!(overflowed.overflowed);
+        assert_eq!(classes(&overflowed), [1.0]);
+        yolo.max_boxes = DEFAULT_MAX_BOXES;
+
+        // Objectness alone scores rows without class channels, which are otherwise required
+        let objectness = rows.slice(s![.., .., ..5]).to_owned();
+        assert!(run(&yolo, &objectness, None).is_err());
+        yolo.class_mode = ClassMode::ObjectnessOnly;
+        assert_eq!(
+            run(&yolo, &objectness, None).unwrap().detections.column(4).to_vec(),
+            [0.9, 0.9]
+        );
+    }
 }
@@ -0,0 +1,275 @@
+use std::ops::Range;
+
+/// Boxes offset by class and laid out as structure of arrays in the order NMS visits them
+#[derive(Debug, Default)]
+pub(crate) struct SortedBoxes {
+    pub x1: Vec<f32>,
+    pub y1: Vec<f32>,
+    pub x2: Vec<f32>,
+    pub y2: Vec<f32>,
+    pub areas: Vec<f32>,
+}
+
+impl SortedBoxes {
+    pub fn len(&self) -> usize {
+        self.x1.len()
+    }
+
+    /// Mark the boxes after `cur` whose IoU with it exceeds `iou_threshold` in the `suppressed`
+    /// bitmask, with the widest SIMD kernel the CPU supports
+    ///
+    /// Bits of boxes before `cur` are left untouched. A NaN IoU suppresses the box.
+    pub fn suppress_after(
+        &self,
+        cur: usize,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        debug_assert!(suppressed.len() * 64 >= self.len());
+        #[cfg(target_arch = "x86_64")]
+        if !self.has_nan(cur) {
+            if is_x86_feature_detected!("avx512f") {
+                // SAFETY: the CPU supports AVX-512F
+                return unsafe { self.suppress_avx512(cur, iou_threshold, epsilon, suppressed) };
+            }
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: the CPU supports AVX2
+                return unsafe { self.suppress_avx2(cur, iou_threshold, epsilon, suppressed) };
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                // SAFETY: the CPU supports NEON
+                return unsafe { self.suppress_neon(cur, iou_threshold, epsilon, suppressed) };
+            }
+        }
+        self.suppress_scalar(cur, cur + 1..self.len(), iou_threshold, epsilon, suppressed);
+    }
+
+    /// Whether a coordinate of box `i` is NaN, which only the scalar kernel handles like
+    /// f32::min and f32::max
+    fn has_nan(&self, i: usize) -> bool {
+        [self.x1[i], self.y1[i], self.x2[i], self.y2[i], self.areas[i]].iter().any(|v| v.is_nan())
+    }
+
+    /// Lanes of `LANES` boxes to process with SIMD, aligned so that the bits of a lane never
+    /// straddle two words of the bitmask, and the remaining boxes
+    fn lanes<const LANES: usize>(&self, cur: usize) -> (Range<usize>, Range<usize>) {
+        let start = (cur + 1) / LANES * LANES;
+        let end = start + (self.len() - start) / LANES * LANES;
+        (start..end, usize::max(end, cur + 1)..self.len())
+    }
+
+    /// Bits of the lane starting at `i` which come after `cur`
+    fn after(cur: usize, i: usize) -> u64 {
+        if i <= cur {
+            !0 << (cur + 1 - i)
+        } else {
+            !0
+        }
+    }
+
+    fn suppress_scalar(
+        &self,
+        cur: usize,
+        range: Range<usize>,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        for i in range {
+            if self.suppresses(cur, i, iou_threshold, epsilon) {
+                suppressed[i / 64] |= 1 << (i % 64);
+            }
+        }
+    }
+
+    /// Whether the IoU of boxes `cur` and `i` exceeds `iou_threshold`, or is NaN
+    #[inline]
+    pub fn suppresses(&self, cur: usize, i: usize, iou_threshold: f32, epsilon: f32) -> bool {
+        let width =
+            f32::max(0.0, f32::min(self.x2[cur], self.x2[i]) - f32::max(self.x1[cur], self.x1[i]));
+        let height =
+            f32::max(0.0, f32::min(self.y2[cur], self.y2[i]) - f32::max(self.y1[cur], self.y1[i]));
+        let iou = width * height;
+        let kept = iou / (self.areas[cur] + self.areas[i] - iou + epsilon) <= iou_threshold;
+        !kept
+    }
+
+    // The min and max instructions of x86 return their second operand if either is NaN, so
+    // the current box goes second to be picked over a NaN coordinate as f32::min and f32::max
+    // do, which holds as long as the current box itself has no NaN
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn suppress_avx2(
+        &self,
+        cur: usize,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        use std::arch::x86_64::*;
+
+        let (lanes, rest) = self.lanes::<8>(cur);
+        let [cx1, cy1, cx2, cy2, area] =
+            [self.x1[cur], self.y1[cur], self.x2[cur], self.y2[cur], self.areas[cur]]
+                .map(|v| _mm256_set1_ps(v));
+        let (threshold, epsilon_v) = (_mm256_set1_ps(iou_threshold), _mm256_set1_ps(epsilon));
+        let zero = _mm256_setzero_ps();
+        for i in lanes.step_by(8) {
+            let load = |v: &[f32]| _mm256_loadu_ps(v.as_ptr().add(i));
+            let width = _mm256_sub_ps(
+                _mm256_min_ps(load(&self.x2), cx2),
+                _mm256_max_ps(load(&self.x1), cx1),
+            );
+            let height = _mm256_sub_ps(
+                _mm256_min_ps(load(&self.y2), cy2),
+                _mm256_max_ps(load(&self.y1), cy1),
+            );
+            let iou = _mm256_mul_ps(_mm256_max_ps(width, zero), _mm256_max_ps(height, zero));
+            let union = _mm256_add_ps(
+                _mm256_sub_ps(_mm256_add_ps(area, load(&self.areas)), iou),
+                epsilon_v,
+            );
+            let over = _mm256_cmp_ps::<_CMP_NLE_UQ>(_mm256_div_ps(iou, union), threshold);
+            let bits = _mm256_movemask_ps(over) as u32 as u64 & Self::after(cur, i);
+            suppressed[i / 64] |= bits << (i % 64);
+        }
+        self.suppress_scalar(cur, rest, iou_threshold, epsilon, suppressed);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn suppress_avx512(
+        &self,
+        cur: usize,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        use std::arch::x86_64::*;
+
+        let (lanes, rest) = self.lanes::<16>(cur);
+        let [cx1, cy1, cx2, cy2, area] =
+            [self.x1[cur], self.y1[cur], self.x2[cur], self.y2[cur], self.areas[cur]]
+                .map(|v| _mm512_set1_ps(v));
+        let (threshold, epsilon_v) = (_mm512_set1_ps(iou_threshold), _mm512_set1_ps(epsilon));
+        let zero = _mm512_setzero_ps();
+        for i in lanes.step_by(16) {
+            let load = |v: &[f32]| _mm512_loadu_ps(v.as_ptr().add(i));
+            let width = _mm512_sub_ps(
+                _mm512_min_ps(load(&self.x2), cx2),
+                _mm512_max_ps(load(&self.x1), cx1),
+            );
+            let height = _mm512_sub_ps(
+                _mm512_min_ps(load(&self.y2), cy2),
+                _mm512_max_ps(load(&self.y1), cy1),
+            );
+            let iou = _mm512_mul_ps(_mm512_max_ps(width, zero), _mm512_max_ps(height, zero));
+            let union = _mm512_add_ps(
+                _mm512_sub_ps(_mm512_add_ps(area, load(&self.areas)), iou),
+                epsilon_v,
+            );
+            let over = _mm512_cmp_ps_mask::<_CMP_NLE_UQ>(_mm512_div_ps(iou, union), threshold);
+            let bits = over as u64 & Self::after(cur, i);
+            suppressed[i / 64] |= bits << (i % 64);
+        }
+        self.suppress_scalar(cur, rest, iou_threshold, epsilon, suppressed);
+    }
+
+    // The minnm and maxnm instructions return the number when either operand is NaN, as
+    // f32::min and f32::max do
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn suppress_neon(
+        &self,
+        cur: usize,
+        iou_threshold: f32,
+        epsilon: f32,
+        suppressed: &mut [u64],
+    ) {
+        use std::arch::aarch64::*;
+
+        let (lanes, rest) = self.lanes::<4>(cur);
+        let [cx1, cy1, cx2, cy2, area] =
+            [self.x1[cur], self.y1[cur], self.x2[cur], self.y2[cur], self.areas[cur]]
+                .map(|v| vdupq_n_f32(v));
+        let (threshold, epsilon_v) = (vdupq_n_f32(iou_threshold), vdupq_n_f32(epsilon));
+        let zero = vdupq_n_f32(0.0);
+        let lane_bits = vld1q_u32([1, 2, 4, 8].as_ptr());
+        for i in lanes.step_by(4) {
+            let load = |v: &[f32]| vld1q_f32(v.as_ptr().add(i));
+            let width =
+                vsubq_f32(vminnmq_f32(cx2, load(&self.x2)), vmaxnmq_f32(cx1, load(&self.x1)));
+            let height =
+                vsubq_f32(vminnmq_f32(cy2, load(&self.y2)), vmaxnmq_f32(cy1, load(&self.y1)));
+            let iou = vmulq_f32(vmaxnmq_f32(zero, width), vmaxnmq_f32(zero, height));
+            let union = vaddq_f32(vsubq_f32(vaddq_f32(area, load(&self.areas)), iou), epsilon_v);
+            let kept = vcleq_f32(vdivq_f32(iou, union), threshold);
+            let over = vaddvq_u32(vandq_u32(vmvnq_u32(kept), lane_bits));
+            let bits = over as u64 & Self::after(cur, i);
+            suppressed[i / 64] |= bits << (i % 64);
+        }
+        self.suppress_scalar(cur, rest, iou_threshold, epsilon, suppressed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Overlapping boxes from a linear congruential generator, some of them empty or NaN
+    fn boxes(n: usize) -> SortedBoxes {
+        let mut state = 0x2545_f491_u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        let mut boxes = SortedBoxes::default();
+        for i in 0..n {
+            let (x, y) = (next() * 100.0, next() * 100.0);
+            let (w, h) = (next() * 40.0, if i % 17 == 0 { 0.0 } else { next() * 40.0 });
+            boxes.x1.push(if i % 23 == 0 { f32::NAN } else { x });
+            boxes.y1.push(y);
+            boxes.x2.push(x + w);
+            boxes.y2.push(y + h);
+            boxes.areas.push(w * h);
+        }
+        boxes
+    }
+
+    #[test]
+    fn unittest_kernels_match_scalar() {
+        let boxes = boxes(203);
+        let words = (boxes.len() + 63) / 64;
+        for cur in 0..boxes.len() {
+            let mut expected = vec![0; words];
+            boxes.suppress_scalar(cur, cur + 1..boxes.len(), 0.3, 1e-5, &mut expected);
+            let mut kernels: Vec<Vec<u64>> = Vec::new();
+            #[cfg(target_arch = "x86_64")]
+            if !boxes.has_nan(cur) {
+                if is_x86_feature_detected!("avx2") {
+                    let mut suppressed = vec![0; words];
+                    unsafe { boxes.suppress_avx2(cur, 0.3, 1e-5, &mut suppressed) };
+                    kernels.push(suppressed);
+                }
+                if is_x86_feature_detected!("avx512f") {
+                    let mut suppressed = vec![0; words];
+                    unsafe { boxes.suppress_avx512(cur, 0.3, 1e-5, &mut suppressed) };
+                    kernels.push(suppressed);
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                let mut suppressed = vec![0; words];
+                unsafe { boxes.suppress_neon(cur, 0.3, 1e-5, &mut suppressed) };
+                kernels.push(suppressed);
+            }
+            for suppressed in kernels {
+                assert_eq!(suppressed, expected, "suppressed after box {cur}");
+            }
+        }
+    }
+}
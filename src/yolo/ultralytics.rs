@@ -0,0 +1,100 @@
+use ndarray::Array3;
+use serde_yaml::Value;
+
+use crate::common::{Error, Result};
+
+/// Modules of the detection head, whose inputs are the detection layers
+const HEADS: [&str; 2] = ["Detect", "Segment"];
+
+/// Whether the mapping is an Ultralytics model definition rather than a postprocessor config
+pub(crate) fn is_model(config: &Value) -> bool {
+    config.get("head").is_some() && config.get("backbone").is_some()
+}
+
+/// Anchors of shape (num_layers, num_anchors, 2) of a YOLOv5 model definition and the strides
+/// of its detection layers, inferred by following the downsampling of the backbone and head
+pub(crate) fn anchors_and_strides(model: &Value) -> Result<(Array3<f32>, Vec<f32>)> {
+    let listed = model.get("anchors").filter(|anchors| anchors.is_sequence());
+    let Some(anchors) = listed else {
+        return Err(Error::invalid(
+            "the model must list the (width, height) anchor pairs of each detection layer",
+        ));
+    };
+    let anchors = serde_yaml::from_value::<Vec<Vec<f32>>>(anchors.clone())
+        .map_err(|e| Error::invalid(format!("invalid anchors: {e}")))?;
+    let num_anchors = anchors.first().map_or(0, |layer| layer.len() / 2);
+    if anchors.iter().any(|layer| layer.len() != 2 * num_anchors) {
+        return Err(Error::invalid(
+            "every detection layer must have the same number of (width, height) anchor pairs",
+        ));
+    }
+    let anchors = Array3::from_shape_vec(
+        (anchors.len(), num_anchors, 2),
+        anchors.into_iter().flatten().collect(),
+    )
+    .unwrap();
+
+    let mut layers = Vec::new();
+    for section in ["backbone", "head"] {
+        match model.get(section).and_then(Value::as_sequence) {
+            Some(section) => layers.extend(section),
+            None => return Err(Error::invalid(format!("the model {section} must be a list"))),
+        }
+    }
+    let mut strides: Vec<f32> = Vec::with_capacity(layers.len());
+    for (i, layer) in layers.iter().enumerate() {
+        let fields = layer.as_sequence().map_or(&[][..], Vec::as_slice);
+        let module = fields.get(2).and_then(Value::as_str);
+        let (Some(module), Some(from)) = (module, fields.first()) else {
+            return Err(Error::invalid(format!(
+                "layer {i} must be [from, number, module, args] but got {layer:?}"
+            )));
+        };
+        let from = match from {
+            Value::Sequence(from) => from.iter().map(Value::as_i64).collect::<Option<Vec<_>>>(),
+            from => from.as_i64().map(|from| vec![from]),
+        }
+        .ok_or_else(|| Error::invalid(format!("layer {i} takes invalid layers {from:?}")))?;
+        // Strides of the input layers, the first layer taking the image itself
+        let inputs = from
+            .iter()
+            .map(|&f| match if f < 0 { i as i64 + f } else { f } {
+                -1 if i == 0 => Ok(1.0),
+                index if (0..i as i64).contains(&index) => Ok(strides[index as usize]),
+                _ => Err(Error::invalid(format!("layer {i} takes the unknown layer {f}"))),
+            })
+            .collect::<Result<Vec<f32>>>()?;
+        if inputs.is_empty() {
+            return Err(Error::invalid(format!("layer {i} takes no input")));
+        }
+        let module = module.rsplit('.').next().unwrap_or(module);
+        let args = fields.get(3).and_then(Value::as_sequence).map_or(&[][..], Vec::as_slice);
+        let arg = |index: usize, default: f32| {
+            args.get(index).and_then(Value::as_f64).map_or(default, |a| a as f32)
+        };
+
+        if HEADS.contains(&module) {
+            if inputs.len() != anchors.shape()[0] {
+                return Err(Error::invalid(format!(
+                    "the model has {} anchor layers but its {module} head takes {} layers",
+                    anchors.shape()[0],
+                    inputs.len()
+                )));
+            }
+            return Ok((anchors, inputs));
+        }
+
+        let input = inputs[0];
+        let stride = match module {
+            "Conv" | "DWConv" | "GhostConv" | "CrossConv" | "MixConv2d" => input * arg(2, 1.0),
+            "MaxPool2d" => input * arg(1, arg(0, 1.0)),
+            "Focus" => input * 2.0,
+            "Contract" => input * arg(0, 2.0),
+            "Expand" => input / arg(0, 2.0),
+            "Upsample" => input / arg(1, 1.0),
+            _ => input,
+        };
+        strides.push(stride);
+    }
+    Err(Error::invalid(format!("the model head has no {} layer", HEADS.join(" or "))))
+}
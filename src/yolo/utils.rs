@@ -1,5 +1,7 @@
 use ndarray::{Array1, Array2};
 
+use crate::common::descending;
+
 pub fn centered_box_to_ltrb_bulk(
     pcy: &Array1<f32>,
     pcx: &Array1<f32>,
@@ -60,25 +62,32 @@ impl DetectionBoxes {
         self.classes.append(ndarray::Axis(0), classes.view()).unwrap();
     }
 
-    pub fn sort_by_score_and_trim(&mut self, len: usize) {
+    /// Keep the `len` highest scoring boxes in their order, the earliest winning ties, returning
+    /// their indices before trimming
+    /// A partial select rather than a sort of every box, so that trimming is linear in the
+    /// number of boxes.
+    pub fn keep_top(&mut self, len: usize) -> Vec<usize> {
         let mut indices: Vec<usize> = (0..self.len()).collect();
-        // Sort by score in descending order
-        unsafe {
-            indices.sort_unstable_by(|&a, &b| {
-                let a = self.scores.uget(a);
-                let b = self.scores.uget(b);
-                b.partial_cmp(a).unwrap()
-            });
+        if len < indices.len() {
+            let cmp = |&a: &usize, &b: &usize| unsafe {
+                descending(*self.scores.uget(a), *self.scores.uget(b)).then(a.cmp(&b))
+            };
+            indices.select_nth_unstable_by(len, cmp);
+            indices.truncate(len);
+            indices.sort_unstable();
         }
-        indices.truncate(len);
-        indices.reverse();
+        self.select(&indices);
+        indices
+    }
 
-        self.x1 = self.x1.select(ndarray::Axis(0), &indices).to_owned();
-        self.y1 = self.y1.select(ndarray::Axis(0), &indices).to_owned();
-        self.x2 = self.x2.select(ndarray::Axis(0), &indices).to_owned();
-        self.y2 = self.y2.select(ndarray::Axis(0), &indices).to_owned();
-        self.scores = self.scores.select(ndarray::Axis(0), &indices).to_owned();
-        self.classes = self.classes.select(ndarray::Axis(0), &indices).to_owned();
+    /// Keep the boxes at `indices`, in their order
+    pub fn select(&mut self, indices: &[usize]) {
+        self.x1 = self.x1.select(ndarray::Axis(0), indices);
+        self.y1 = self.y1.select(ndarray::Axis(0), indices);
+        self.x2 = self.x2.select(ndarray::Axis(0), indices);
+        self.y2 = self.y2.select(ndarray::Axis(0), indices);
+        self.scores = self.scores.select(ndarray::Axis(0), indices);
+        self.classes = self.classes.select(ndarray::Axis(0), indices);
     }
 
     pub fn len(&self) -> usize {
@@ -89,6 +98,12 @@ impl DetectionBoxes {
         self.x1.is_empty()
     }
 
+    /// Split into boxes of shape (N, 4) with rows (x1, y1, x2, y2), scores, and classes
+    pub fn into_parts(self) -> (Array2<f32>, Array1<f32>, Array1<f32>) {
+        let boxes = ndarray::stack![ndarray::Axis(1), self.x1, self.y1, self.x2, self.y2];
+        (boxes, self.scores, self.classes)
+    }
+
     pub fn select_and_convert(&mut self, indicies: &[usize]) -> Array2<f32> {
         let mut results = unsafe { Array2::uninit((indicies.len(), 6)).assume_init() };
         for (i, &j) in indicies.iter().enumerate() {
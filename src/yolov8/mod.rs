@@ -0,0 +1,271 @@
+use std::fmt;
+
+use itertools::{izip, Itertools};
+use ndarray::{Array2, ArrayView3, ArrayView4, Axis};
+use numpy::{PyArray2, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::{logit, threads, validate_batch, warn_overflow};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor as AnchorPostprocessor, DEFAULT_MAX_DET};
+
+/// Maximum number of candidates decoded per image
+const MAX_BOXES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub strides: Vec<f32>,
+    pub reg_max: usize,
+    pub agnostic: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ num_detection_layers: {}, reg_max: {}, strides: {:?}, agnostic: {} }}",
+            self.strides.len(),
+            self.reg_max,
+            self.strides,
+            self.agnostic
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(strides: Vec<f32>, reg_max: Option<usize>, agnostic: Option<bool>) -> PyResult<Self> {
+        const DEFAULT_REG_MAX: usize = 16;
+        let reg_max = reg_max.unwrap_or(DEFAULT_REG_MAX);
+        if strides.is_empty() {
+            return Err(PyValueError::new_err("strides must not be empty"));
+        }
+        if reg_max == 0 {
+            return Err(PyValueError::new_err("reg_max must be positive"));
+        }
+        Ok(Self { strides, reg_max, agnostic: agnostic.unwrap_or(false) })
+    }
+
+    /// Distribution Focal Loss decoding: the expectation of the softmax over `reg_max` bins
+    #[inline]
+    fn dfl(&self, feature: &ArrayView3<'_, f32>, side: usize, y: usize, x: usize) -> f32 {
+        let base = side * self.reg_max;
+        let mut max = f32::NEG_INFINITY;
+        for i in 0..self.reg_max {
+            max = f32::max(max, unsafe { *feature.uget((base + i, y, x)) });
+        }
+
+        let (mut sum, mut weighted) = (0f32, 0f32);
+        for i in 0..self.reg_max {
+            let e = f32::exp(unsafe { *feature.uget((base + i, y, x)) } - max);
+            sum += e;
+            weighted += e * i as f32;
+        }
+        weighted / sum
+    }
+
+    /// Box decode of each image of the batch in parallel
+    fn box_decode(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+    ) -> Vec<DetectionBoxes> {
+        (0..inputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| self.box_decode_image(inputs, batch_index, conf_threshold))
+            .collect()
+    }
+
+    /// Box decode of an image of the batch, stopping at `MAX_BOXES` candidates so that the
+    /// limit never depends on the other images
+    fn box_decode_image(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+    ) -> DetectionBoxes {
+        let box_channels = 4 * self.reg_max;
+        let logit_threshold = logit(conf_threshold);
+
+        let mut x1: Vec<f32> = Vec::new();
+        let mut y1: Vec<f32> = Vec::new();
+        let mut x2: Vec<f32> = Vec::new();
+        let mut y2: Vec<f32> = Vec::new();
+        let mut scores: Vec<f32> = Vec::new();
+        let mut classes: Vec<f32> = Vec::new();
+
+        'image: for (&stride, input) in izip!(&self.strides, inputs) {
+            let inner_batch = input.index_axis(Axis(0), batch_index);
+            let &[channels, height, width] = inner_batch.shape() else { unreachable!() };
+            for y in 0..height {
+                for x in 0..width {
+                    // Class scores are raw logits, so compare before applying sigmoid
+                    let candidates = (box_channels..channels)
+                        .map(|c| (c - box_channels, unsafe { *inner_batch.uget((c, y, x)) }))
+                        .filter(|&(_, class_logit)| class_logit > logit_threshold);
+
+                    let mut decoded = None;
+                    for (class_idx, class_logit) in candidates {
+                        if scores.len() >= MAX_BOXES {
+                            break 'image;
+                        }
+
+                        // Decode box only once per grid cell
+                        // dist2bbox(dfl(box), anchor_points) * stride
+                        let &mut (bx1, by1, bx2, by2) = decoded.get_or_insert_with(|| {
+                            let (ax, ay) = (x as f32 + 0.5, y as f32 + 0.5);
+                            (
+                                (ax - self.dfl(&inner_batch, 0, y, x)) * stride,
+                                (ay - self.dfl(&inner_batch, 1, y, x)) * stride,
+                                (ax + self.dfl(&inner_batch, 2, y, x)) * stride,
+                                (ay + self.dfl(&inner_batch, 3, y, x)) * stride,
+                            )
+                        });
+
+                        x1.push(bx1);
+                        y1.push(by1);
+                        x2.push(bx2);
+                        y2.push(by2);
+                        scores.push(1.0 / (1.0 + f32::exp(-class_logit)));
+                        classes.push(class_idx as f32);
+                    }
+                }
+            }
+        }
+        DetectionBoxes::new(
+            x1.into(),
+            y1.into(),
+            x2.into(),
+            y2.into(),
+            scores.into(),
+            classes.into(),
+        )
+    }
+
+    /// YOLOv8 postprocess function
+    /// The vector in function input/output is for batched input/output, along with whether the
+    /// decode of each image reached `MAX_BOXES`
+    fn postprocess(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> Vec<(Array2<f32>, bool)> {
+        const MAX_NMS_INPUT: usize = 30_000;
+        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
+
+        self.box_decode(inputs, conf_threshold)
+            .into_par_iter()
+            .map(|mut dbox| {
+                let overflowed = dbox.len() >= MAX_BOXES;
+                if dbox.len() > MAX_NMS_INPUT {
+                    dbox.keep_top(MAX_NMS_INPUT);
+                };
+                let indices = AnchorPostprocessor::nms(
+                    &dbox,
+                    iou_threshold,
+                    epsilon,
+                    DEFAULT_MAX_DET,
+                    agnostic,
+                );
+                (dbox.select_and_convert(&indices), overflowed)
+            })
+            .collect()
+    }
+
+    fn validate(&self, inputs: &[ArrayView4<'_, f32>]) -> PyResult<()> {
+        if inputs.len() != self.strides.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected {} input tensors but got {}",
+                self.strides.len(),
+                inputs.len()
+            )));
+        }
+        validate_batch(inputs)?;
+        let box_channels = 4 * self.reg_max;
+        for (i, input) in inputs.iter().enumerate() {
+            if input.shape()[1] <= box_channels {
+                return Err(PyValueError::new_err(format!(
+                    "input {i} must have more than {box_channels} channels (4 * reg_max + nc) \
+                     but got shape {:?}",
+                    input.shape()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// YOLOv8 PostProcessor
+///
+/// It decodes anchor-free detection heads whose box branch is regressed with
+/// Distribution Focal Loss. Each input tensor has shape `(N, 4 * reg_max + num_classes, H, W)`
+/// where class channels hold raw logits.
+///
+/// Args:
+///     strides (numpy.ndarray): Strides (1D Array)
+///     reg_max (Optional[int]): Number of DFL bins per box side, default is 16
+///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(strides: Vec<f32>, reg_max: Option<usize>, agnostic: Option<bool>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(strides, reg_max, agnostic)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): IoU threshold
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results, a RuntimeWarning being raised if the
+    ///         decode of an image reached 10000 candidates and dropped the remaining ones
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let inputs = inputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&inputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&inputs, conf_threshold, iou_threshold, epsilon, agnostic)
+        });
+        let overflowed =
+            results.iter().positions(|&(_, overflowed)| overflowed).collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, MAX_BOXES)?;
+        Ok(results
+            .into_iter()
+            .map(|(results, _)| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn yolov8(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use itertools::{izip, Itertools};
+use ndarray::{Array2, ArrayView4, Axis};
+use numpy::{PyArray2, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::common::{threads, validate_batch, warn_overflow};
+use crate::yolo::utils::DetectionBoxes;
+use crate::yolo::{RustPostprocessor as AnchorPostprocessor, DEFAULT_MAX_DET};
+
+/// Maximum number of candidates decoded per image
+const MAX_BOXES: usize = 10_000;
+/// Channels of the box regression (4) and objectness (1) preceding the class confidences
+const NUM_BOX_CHANNELS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct RustPostprocessor {
+    pub strides: Vec<f32>,
+    pub agnostic: bool,
+}
+
+impl fmt::Display for RustPostprocessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RustPostProcessor {{ num_detection_layers: {}, strides: {:?}, agnostic: {} }}",
+            self.strides.len(),
+            self.strides,
+            self.agnostic
+        )
+    }
+}
+
+impl RustPostprocessor {
+    fn new(strides: Vec<f32>, agnostic: Option<bool>) -> PyResult<Self> {
+        if strides.is_empty() {
+            return Err(PyValueError::new_err("strides must not be empty"));
+        }
+        Ok(Self { strides, agnostic: agnostic.unwrap_or(false) })
+    }
+
+    /// Box decode of each image of the batch in parallel, along with whether the decode of each
+    /// image dropped candidates beyond `MAX_BOXES`
+    fn box_decode(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+    ) -> Vec<(DetectionBoxes, bool)> {
+        (0..inputs[0].shape()[0])
+            .into_par_iter()
+            .map(|batch_index| self.box_decode_image(inputs, batch_index, conf_threshold))
+            .collect()
+    }
+
+    /// Box decode of an image of the batch, stopping at `MAX_BOXES` candidates so that the
+    /// limit never depends on the other images, along with whether further ones were dropped
+    fn box_decode_image(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        batch_index: usize,
+        conf_threshold: f32,
+    ) -> (DetectionBoxes, bool) {
+        let mut x1: Vec<f32> = Vec::new();
+        let mut y1: Vec<f32> = Vec::new();
+        let mut x2: Vec<f32> = Vec::new();
+        let mut y2: Vec<f32> = Vec::new();
+        let mut scores: Vec<f32> = Vec::new();
+        let mut classes: Vec<f32> = Vec::new();
+        let mut overflowed = false;
+
+        'image: for (&stride, input) in izip!(&self.strides, inputs) {
+            let inner_batch = input.index_axis(Axis(0), batch_index);
+            let &[channels, height, width] = inner_batch.shape() else { unreachable!() };
+            for y in 0..height {
+                for x in 0..width {
+                    let object_confidence = unsafe { *inner_batch.uget((4, y, x)) };
+                    // Class confidences never exceed 1, so the whole cell can be skipped early
+                    if object_confidence <= conf_threshold {
+                        continue;
+                    }
+
+                    let candidates = (NUM_BOX_CHANNELS..channels)
+                        .map(|c| (c - NUM_BOX_CHANNELS, unsafe { *inner_batch.uget((c, y, x)) }))
+                        .filter(|&(_, class_conf)| class_conf * object_confidence > conf_threshold);
+
+                    let mut decoded = None;
+                    for (class_idx, class_conf) in candidates {
+                        if scores.len() >= MAX_BOXES {
+                            overflowed = true;
+                            break 'image;
+                        }
+
+                        // Decode box, without any anchor multiplication
+                        // xy = (reg[..., 0:2] + grid) * stride
+                        // wh = exp(reg[..., 2:4]) * stride
+                        let &mut (bx1, by1, bx2, by2) = decoded.get_or_insert_with(|| {
+                            let (bx, by, bw, bh) = unsafe {
+                                (
+                                    *inner_batch.uget((0, y, x)),
+                                    *inner_batch.uget((1, y, x)),
+                                    *inner_batch.uget((2, y, x)),
+                                    *inner_batch.uget((3, y, x)),
+                                )
+                            };
+                            let cx = (bx + x as f32) * stride;
+                            let cy = (by + y as f32) * stride;
+                            let w = f32::exp(bw) * stride;
+                            let h = f32::exp(bh) * stride;
+                            (cx - w * 0.5, cy - h * 0.5, cx + w * 0.5, cy + h * 0.5)
+                        });
+
+                        x1.push(bx1);
+                        y1.push(by1);
+                        x2.push(bx2);
+                        y2.push(by2);
+                        scores.push(class_conf * object_confidence);
+                        classes.push(class_idx as f32);
+                    }
+                }
+            }
+        }
+        let boxes = DetectionBoxes::new(
+            x1.into(),
+            y1.into(),
+            x2.into(),
+            y2.into(),
+            scores.into(),
+            classes.into(),
+        );
+        (boxes, overflowed)
+    }
+
+    /// YOLOX postprocess function
+    /// The vector in function input/output is for batched input/output, along with whether the
+    /// decode of each image dropped candidates beyond `MAX_BOXES`
+    fn postprocess(
+        &self,
+        inputs: &[ArrayView4<'_, f32>],
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> Vec<(Array2<f32>, bool)> {
+        const MAX_NMS_INPUT: usize = 30_000;
+        let agnostic: bool = agnostic.unwrap_or(self.agnostic);
+
+        self.box_decode(inputs, conf_threshold)
+            .into_par_iter()
+            .map(|(mut dbox, overflowed)| {
+                if dbox.len() > MAX_NMS_INPUT {
+                    dbox.keep_top(MAX_NMS_INPUT);
+                };
+                let indices = AnchorPostprocessor::nms(
+                    &dbox,
+                    iou_threshold,
+                    epsilon,
+                    DEFAULT_MAX_DET,
+                    agnostic,
+                );
+                (dbox.select_and_convert(&indices), overflowed)
+            })
+            .collect()
+    }
+
+    fn validate(&self, inputs: &[ArrayView4<'_, f32>]) -> PyResult<()> {
+        if inputs.len() != self.strides.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected {} input tensors but got {}",
+                self.strides.len(),
+                inputs.len()
+            )));
+        }
+        validate_batch(inputs)?;
+        for (i, input) in inputs.iter().enumerate() {
+            if input.shape()[1] <= NUM_BOX_CHANNELS {
+                return Err(PyValueError::new_err(format!(
+                    "input {i} must have more than {NUM_BOX_CHANNELS} channels (5 + nc) but got \
+                     shape {:?}",
+                    input.shape()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// YOLOX PostProcessor
+///
+/// It decodes anchor-free decoupled heads. Each input tensor has shape
+/// `(N, 5 + num_classes, H, W)` holding raw box regressions followed by
+/// sigmoid-activated objectness and class confidences.
+///
+/// Args:
+///     strides (numpy.ndarray): Strides (1D Array)
+///     agnostic (Optional[bool]): Whether to use agnostic NMS, default is False
+#[pyclass]
+pub struct RustPostProcessor(RustPostprocessor);
+
+#[pymethods]
+impl RustPostProcessor {
+    #[new]
+    fn new(strides: Vec<f32>, agnostic: Option<bool>) -> PyResult<Self> {
+        Ok(Self(RustPostprocessor::new(strides, agnostic)?))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.0))
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!("{}", self.0))
+    }
+
+    /// Evaluate the postprocess
+    ///
+    /// Args:
+    ///     inputs (Sequence[numpy.ndarray]): Input tensors
+    ///     conf_threshold (float): Confidence threshold
+    ///     iou_threshold (float): IoU threshold
+    ///     epsilon (Optional[float]): Epsilon for numerical stability
+    ///     agnostic (Optional[bool]): Whether to use agnostic NMS, takes precedence constructor's
+    ///
+    /// Returns:
+    ///     List[numpy.ndarray]: Batched detection results, a RuntimeWarning being raised if the
+    ///         decode of an image reached 10000 candidates and dropped the remaining ones
+    fn eval(
+        &self,
+        py: Python<'_>,
+        inputs: Vec<PyReadonlyArray4<'_, f32>>,
+        conf_threshold: f32,
+        iou_threshold: f32,
+        epsilon: Option<f32>,
+        agnostic: Option<bool>,
+    ) -> PyResult<Vec<Py<PyArray2<f32>>>> {
+        let inputs = inputs.iter().map(|a| a.as_array()).collect::<Vec<_>>();
+        self.0.validate(&inputs)?;
+
+        let results = threads::allow_threads(py, || {
+            self.0.postprocess(&inputs, conf_threshold, iou_threshold, epsilon, agnostic)
+        });
+        let overflowed =
+            results.iter().positions(|&(_, overflowed)| overflowed).collect::<Vec<_>>();
+        warn_overflow(py, &overflowed, MAX_BOXES)?;
+        Ok(results
+            .into_iter()
+            .map(|(results, _)| PyArray2::from_owned_array(py, results).to_owned())
+            .collect())
+    }
+}
+
+pub(crate) fn yolox(m: &PyModule) -> PyResult<()> {
+    m.add_class::<RustPostProcessor>()?;
+
+    Ok(())
+}